@@ -1,13 +1,82 @@
 use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use tracing::{warn, debug};
+use tauri::{command, State};
+use tracing::{info, warn, error, debug};
 use regex::Regex;
 
+use crate::ai::{
+    csrf::validate_request_security,
+    error_sanitization::sanitize_log_error,
+    secure_commands::SecureSession,
+    storage::StorageManager,
+};
+
+/// Settings key holding user-defined whitelist rules
+const WHITELIST_SETTING_KEY: &str = "command_whitelist.rules";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WhitelistEffect {
+    Allow,
+    Deny,
+}
+
+/// User-defined whitelist rule persisted in the settings store.
+///
+/// Patterns are globs (`*`, `?`). A pattern without spaces matches the command
+/// name; one with spaces matches the full command line, e.g. `npm run *`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WhitelistRule {
+    pub pattern: String,
+    /// Rule applies only to this agent when set
+    pub agent_id: Option<String>,
+    pub effect: WhitelistEffect,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhitelistListing {
+    pub default_commands: Vec<String>,
+    pub rules: Vec<WhitelistRule>,
+}
+
+struct CompiledRule {
+    rule: WhitelistRule,
+    regex: Regex,
+    matches_full_command: bool,
+}
+
+/// Convert a glob pattern into an anchored regex
+pub fn glob_to_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            other => regex.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    regex.push('$');
+
+    Regex::new(&regex).context("Invalid glob pattern")
+}
+
+fn validate_rule_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.trim().is_empty() || pattern.len() > 256 {
+        return Err("Pattern must be between 1 and 256 characters".to_string());
+    }
+    if pattern.chars().any(|c| [';', '|', '&', '<', '>', '`', '$', '\n', '\0'].contains(&c)) {
+        return Err("Pattern contains shell metacharacters".to_string());
+    }
+    Ok(())
+}
+
 /// Command whitelist manager for securing command execution
 pub struct CommandWhitelist {
     allowed_commands: HashSet<String>,
     allowed_patterns: Vec<Regex>,
     blocked_patterns: Vec<Regex>,
+    custom_rules: Vec<CompiledRule>,
 }
 
 impl CommandWhitelist {
@@ -16,6 +85,7 @@ impl CommandWhitelist {
             allowed_commands: HashSet::new(),
             allowed_patterns: Vec::new(),
             blocked_patterns: Vec::new(),
+            custom_rules: Vec::new(),
         };
         
         whitelist.init_default_whitelist();
@@ -129,6 +199,11 @@ impl CommandWhitelist {
 
     /// Validate if a command is allowed
     pub fn is_command_allowed(&self, command: &str, args: &[String]) -> Result<bool> {
+        self.is_command_allowed_for_agent(command, args, None)
+    }
+
+    /// Validate a command, applying the agent's overrides before global rules and defaults
+    pub fn is_command_allowed_for_agent(&self, command: &str, args: &[String], agent_id: Option<&str>) -> Result<bool> {
         let full_command = if args.is_empty() {
             command.to_string()
         } else {
@@ -145,6 +220,27 @@ impl CommandWhitelist {
             }
         }
 
+        // User rules: agent overrides first, then global rules
+        let agent_rules = self.custom_rules.iter()
+            .filter(|r| r.rule.agent_id.is_some() && r.rule.agent_id.as_deref() == agent_id);
+        let global_rules = self.custom_rules.iter().filter(|r| r.rule.agent_id.is_none());
+
+        for rules in [agent_rules.collect::<Vec<_>>(), global_rules.collect::<Vec<_>>()] {
+            // Deny wins over allow within the same scope
+            let matching: Vec<&&CompiledRule> = rules.iter()
+                .filter(|r| r.regex.is_match(if r.matches_full_command { &full_command } else { command }))
+                .collect();
+
+            if matching.iter().any(|r| r.rule.effect == WhitelistEffect::Deny) {
+                warn!("Command denied by whitelist rule: {}", full_command);
+                return Ok(false);
+            }
+            if !matching.is_empty() {
+                debug!("Command allowed by whitelist rule: {}", full_command);
+                return Ok(true);
+            }
+        }
+
         // Check if base command is in whitelist
         if self.allowed_commands.contains(command) {
             debug!("Command allowed by whitelist: {}", command);
@@ -186,6 +282,26 @@ impl CommandWhitelist {
         self.allowed_commands.iter().cloned().collect()
     }
 
+    /// Replace the user-defined rules, compiling their glob patterns
+    pub fn set_custom_rules(&mut self, rules: Vec<WhitelistRule>) -> Result<()> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        for rule in rules {
+            compiled.push(CompiledRule {
+                regex: glob_to_regex(&rule.pattern)?,
+                matches_full_command: rule.pattern.contains(' '),
+                rule,
+            });
+        }
+
+        self.custom_rules = compiled;
+        Ok(())
+    }
+
+    /// Get the user-defined rules
+    pub fn get_custom_rules(&self) -> Vec<WhitelistRule> {
+        self.custom_rules.iter().map(|c| c.rule.clone()).collect()
+    }
+
     /// Validate file path for safety
     pub fn is_safe_file_path(&self, path: &str) -> bool {
         // Block dangerous paths
@@ -233,12 +349,170 @@ pub fn validate_command_execution(command: &str, args: &[String]) -> Result<bool
     whitelist.is_command_allowed(command, args)
 }
 
+/// Validate command execution with the agent's whitelist overrides
+pub fn validate_command_execution_for_agent(command: &str, args: &[String], agent_id: Option<&str>) -> Result<bool> {
+    let whitelist = COMMAND_WHITELIST.lock()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire whitelist lock"))?;
+
+    whitelist.is_command_allowed_for_agent(command, args, agent_id)
+}
+
 /// Sanitize command arguments using whitelist
 pub fn sanitize_command_args(args: &[String]) -> Vec<String> {
     let whitelist = COMMAND_WHITELIST.lock().unwrap();
     whitelist.sanitize_args(args)
 }
 
+fn load_whitelist_rules(storage: &StorageManager) -> Result<Vec<WhitelistRule>> {
+    match storage.get_setting(WHITELIST_SETTING_KEY)? {
+        Some(value) => serde_json::from_value(value).context("Stored whitelist rules are malformed"),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn save_whitelist_rules(storage: &StorageManager, rules: &[WhitelistRule]) -> Result<()> {
+    storage.set_setting(WHITELIST_SETTING_KEY, serde_json::to_value(rules)?)
+}
+
+/// Reload persisted rules into the global whitelist; takes effect for the next command
+pub fn reload_command_whitelist(storage: &StorageManager) -> Result<usize> {
+    let rules = load_whitelist_rules(storage)?;
+    let count = rules.len();
+
+    let mut whitelist = COMMAND_WHITELIST.lock()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire whitelist lock"))?;
+    whitelist.set_custom_rules(rules)?;
+
+    info!("Command whitelist reloaded with {} custom rules", count);
+    Ok(count)
+}
+
+/// Add a persisted whitelist rule
+#[command]
+pub async fn add_whitelisted_command(
+    session_id: String,
+    csrf_token: String,
+    pattern: String,
+    agent_id: Option<String>,
+    effect: Option<WhitelistEffect>,
+    secure_session: State<'_, SecureSession>,
+) -> Result<WhitelistRule, String> {
+    // Validate security
+    match validate_request_security(&session_id, &csrf_token) {
+        Ok(true) => {}
+        Ok(false) => return Err("Security validation failed".to_string()),
+        Err(_) => return Err("Security validation failed".to_string()),
+    }
+
+    validate_rule_pattern(&pattern)?;
+
+    let rule = WhitelistRule {
+        pattern: pattern.trim().to_string(),
+        agent_id,
+        effect: effect.unwrap_or(WhitelistEffect::Allow),
+        created_at: chrono::Utc::now(),
+    };
+
+    let storage_manager = secure_session.storage_manager.lock()
+        .map_err(|_| "Failed to acquire storage lock".to_string())?;
+
+    let result = (|| -> Result<()> {
+        let mut rules = load_whitelist_rules(&storage_manager)?;
+        rules.retain(|r| !(r.pattern == rule.pattern && r.agent_id == rule.agent_id));
+        rules.push(rule.clone());
+        save_whitelist_rules(&storage_manager, &rules)?;
+        reload_command_whitelist(&storage_manager)?;
+        Ok(())
+    })();
+
+    match result {
+        Ok(_) => {
+            info!("Whitelist rule added: {} ({:?})", rule.pattern, rule.effect);
+            Ok(rule)
+        }
+        Err(e) => {
+            error!("Failed to add whitelist rule: {}", sanitize_log_error(&e));
+            Err("Failed to add whitelist rule".to_string())
+        }
+    }
+}
+
+/// Remove a persisted whitelist rule
+#[command]
+pub async fn remove_whitelisted_command(
+    session_id: String,
+    csrf_token: String,
+    pattern: String,
+    agent_id: Option<String>,
+    secure_session: State<'_, SecureSession>,
+) -> Result<bool, String> {
+    // Validate security
+    match validate_request_security(&session_id, &csrf_token) {
+        Ok(true) => {}
+        Ok(false) => return Err("Security validation failed".to_string()),
+        Err(_) => return Err("Security validation failed".to_string()),
+    }
+
+    let storage_manager = secure_session.storage_manager.lock()
+        .map_err(|_| "Failed to acquire storage lock".to_string())?;
+
+    let result = (|| -> Result<bool> {
+        let mut rules = load_whitelist_rules(&storage_manager)?;
+        let before = rules.len();
+        rules.retain(|r| !(r.pattern == pattern.trim() && r.agent_id == agent_id));
+        let removed = rules.len() != before;
+
+        if removed {
+            save_whitelist_rules(&storage_manager, &rules)?;
+            reload_command_whitelist(&storage_manager)?;
+        }
+        Ok(removed)
+    })();
+
+    result.map_err(|e| {
+        error!("Failed to remove whitelist rule: {}", sanitize_log_error(&e));
+        "Failed to remove whitelist rule".to_string()
+    })
+}
+
+/// List default commands and persisted rules, optionally scoped to one agent
+#[command]
+pub async fn list_whitelisted_commands(
+    session_id: String,
+    csrf_token: String,
+    agent_id: Option<String>,
+    secure_session: State<'_, SecureSession>,
+) -> Result<WhitelistListing, String> {
+    // Validate security
+    match validate_request_security(&session_id, &csrf_token) {
+        Ok(true) => {}
+        Ok(false) => return Err("Security validation failed".to_string()),
+        Err(_) => return Err("Security validation failed".to_string()),
+    }
+
+    // Pick up edits made outside the app before listing
+    {
+        let storage_manager = secure_session.storage_manager.lock()
+            .map_err(|_| "Failed to acquire storage lock".to_string())?;
+        reload_command_whitelist(&storage_manager).map_err(|e| {
+            error!("Failed to reload whitelist: {}", sanitize_log_error(&e));
+            "Failed to load whitelist".to_string()
+        })?;
+    }
+
+    let whitelist = COMMAND_WHITELIST.lock()
+        .map_err(|_| "Failed to acquire whitelist lock".to_string())?;
+
+    let mut default_commands = whitelist.get_allowed_commands();
+    default_commands.sort();
+
+    let rules = whitelist.get_custom_rules().into_iter()
+        .filter(|r| agent_id.is_none() || r.agent_id.is_none() || r.agent_id == agent_id)
+        .collect();
+
+    Ok(WhitelistListing { default_commands, rules })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -280,6 +554,43 @@ mod tests {
         assert!(!whitelist.is_safe_file_path("~/../../root/.ssh/"));
     }
 
+    fn rule(pattern: &str, agent_id: Option<&str>, effect: WhitelistEffect) -> WhitelistRule {
+        WhitelistRule {
+            pattern: pattern.to_string(),
+            agent_id: agent_id.map(|a| a.to_string()),
+            effect,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_custom_glob_rules() {
+        let mut whitelist = CommandWhitelist::new();
+        whitelist.set_custom_rules(vec![
+            rule("deno", None, WhitelistEffect::Allow),
+            rule("go *", None, WhitelistEffect::Allow),
+            rule("git", Some("reviewer"), WhitelistEffect::Deny),
+            rule("docker", Some("ops"), WhitelistEffect::Allow),
+        ]).unwrap();
+
+        assert!(whitelist.is_command_allowed("deno", &["fmt".to_string()]).unwrap());
+        assert!(whitelist.is_command_allowed("go", &["vet".to_string()]).unwrap());
+        assert!(!whitelist.is_command_allowed("docker", &["ps".to_string()]).unwrap());
+        assert!(whitelist.is_command_allowed_for_agent("docker", &["ps".to_string()], Some("ops")).unwrap());
+        assert!(whitelist.is_command_allowed("git", &["status".to_string()]).unwrap());
+        assert!(!whitelist.is_command_allowed_for_agent("git", &["status".to_string()], Some("reviewer")).unwrap());
+    }
+
+    #[test]
+    fn test_custom_rules_do_not_bypass_blocked_patterns() {
+        let mut whitelist = CommandWhitelist::new();
+        whitelist.set_custom_rules(vec![rule("*", None, WhitelistEffect::Allow)]).unwrap();
+
+        assert!(!whitelist.is_command_allowed("sudo", &["ls".to_string()]).unwrap());
+        assert!(!whitelist.is_command_allowed("ls", &["|".to_string(), "sh".to_string()]).unwrap());
+        assert!(validate_rule_pattern("ls; rm").is_err());
+    }
+
     #[test]
     fn test_argument_sanitization() {
         let whitelist = CommandWhitelist::new();
@@ -1,11 +1,19 @@
 use super::{SecurityManager, SecurityMiddleware, StorageManager, HttpClientManager, HttpRequest};
+use super::{load_rate_limit_config, save_rate_limit_config, RateLimitBucketStats, RateLimitConfig, RateLimitScope};
+use super::{load_http_client_config, save_http_client_config, HttpClientConfig};
+use super::security::{enforce_path_policy, session_scoped_path, active_workspace, load_path_policy, save_path_policy, PathAccess, PathPolicy};
+use super::sandbox::{run_sandboxed, SandboxError, SandboxLimits, SandboxOutput};
+use super::encryption::{EncryptionKeyStatus, KeyRotationRecord};
+use super::error_sanitization::redact_error;
+use super::csrf::guard_secure_command;
+use super::secure_commands::SecureResponse;
+use crate::database::notification_center::{notify, NewNotification, NotificationSeverity};
+use crate::mcp::{notify_roots_changed, MCPProcessMap};
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-use std::process::Command;
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{State, Webview};
 use tokio::sync::Mutex as AsyncMutex;
 use tracing::{info, warn, error};
 use anyhow::Result;
@@ -19,13 +27,25 @@ pub struct AIState {
 
 impl AIState {
     pub fn new() -> Result<Self> {
-        let security_manager = Arc::new(AsyncMutex::new(SecurityManager::new()));
+        let storage = StorageManager::new()?;
+        let security_manager = match load_rate_limit_config(&storage) {
+            Ok(config) => SecurityManager::with_rate_limit_config(config),
+            Err(e) => {
+                warn!("Using default rate limits: {}", e);
+                SecurityManager::new()
+            }
+        };
+        let security_manager = Arc::new(AsyncMutex::new(security_manager));
         let security_middleware = Arc::new(SecurityMiddleware::new(security_manager));
+        let http_config = load_http_client_config(&storage).unwrap_or_else(|e| {
+            warn!("Using default HTTP retry policies: {}", e);
+            HttpClientConfig::default()
+        });
         
         Ok(Self {
             security_middleware,
-            storage: StorageManager::new()?,
-            http_client: HttpClientManager::new()?,
+            storage,
+            http_client: HttpClientManager::new()?.with_config(http_config),
         })
     }
     
@@ -167,6 +187,8 @@ pub async fn list_providers_command(
 #[tauri::command]
 pub async fn read_file_command(
     path: String,
+    workspace: Option<String>,
+    session_id: Option<String>,
     state: State<'_, AIState>,
 ) -> Result<String, String> {
     info!("Reading file: {}", path);
@@ -184,8 +206,10 @@ pub async fn read_file_command(
     
     // Use sanitized inputs
     let sanitized_path = &validation_result.sanitized_inputs[0];
+    let scoped_path = session_scoped_path(session_id.as_deref(), sanitized_path)?;
+    let resolved_path = enforce_path_policy(&state.storage, workspace.as_deref(), &scoped_path, PathAccess::Read)?;
 
-    fs::read_to_string(sanitized_path)
+    fs::read_to_string(&resolved_path)
         .map_err(|e| {
             error!("Failed to read file {}: {}", sanitized_path, e);
             format!("Failed to read file: {}", e)
@@ -196,6 +220,8 @@ pub async fn read_file_command(
 pub async fn write_file_command(
     path: String,
     content: String,
+    workspace: Option<String>,
+    session_id: Option<String>,
     state: State<'_, AIState>,
 ) -> Result<(), String> {
     info!("Writing file: {}", path);
@@ -214,14 +240,21 @@ pub async fn write_file_command(
     // Use sanitized inputs
     let sanitized_path = &validation_result.sanitized_inputs[0];
     let sanitized_content = &validation_result.sanitized_inputs[1];
+    let scoped_path = session_scoped_path(session_id.as_deref(), sanitized_path)?;
+    let resolved_path = enforce_path_policy(
+        &state.storage,
+        workspace.as_deref(),
+        &scoped_path,
+        PathAccess::Write { size: sanitized_content.len() as u64 },
+    )?;
 
     // Create parent directories if they don't exist
-    if let Some(parent) = Path::new(sanitized_path).parent() {
+    if let Some(parent) = resolved_path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create directories: {}", e))?;
     }
 
-    fs::write(sanitized_path, sanitized_content)
+    fs::write(&resolved_path, sanitized_content)
         .map_err(|e| {
             error!("Failed to write file {}: {}", sanitized_path, e);
             format!("Failed to write file: {}", e)
@@ -231,6 +264,8 @@ pub async fn write_file_command(
 #[tauri::command]
 pub async fn list_files_command(
     path: String,
+    workspace: Option<String>,
+    session_id: Option<String>,
     state: State<'_, AIState>,
 ) -> Result<Vec<String>, String> {
     info!("Listing files in: {}", path);
@@ -248,8 +283,10 @@ pub async fn list_files_command(
     
     // Use sanitized inputs
     let sanitized_path = &validation_result.sanitized_inputs[0];
+    let scoped_path = session_scoped_path(session_id.as_deref(), sanitized_path)?;
+    let resolved_path = enforce_path_policy(&state.storage, workspace.as_deref(), &scoped_path, PathAccess::List)?;
 
-    let entries = fs::read_dir(sanitized_path)
+    let entries = fs::read_dir(&resolved_path)
         .map_err(|e| {
             error!("Failed to read directory {}: {}", sanitized_path, e);
             format!("Failed to read directory: {}", e)
@@ -273,7 +310,62 @@ pub async fn list_files_command(
     Ok(files)
 }
 
+#[tauri::command]
+pub async fn get_path_policy(
+    workspace: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<PathPolicy, String> {
+    let workspace = workspace.unwrap_or_else(|| active_workspace(&state.storage));
+
+    load_path_policy(&state.storage, &workspace)
+        .map_err(|e| {
+            error!("Failed to load path policy for {}: {}", workspace, e);
+            format!("Failed to load path policy: {}", e)
+        })
+}
+
+/// Store a workspace's path policy; MCP servers scoped to the workspace are told their roots changed
+#[tauri::command]
+pub async fn set_path_policy(
+    policy: PathPolicy,
+    state: State<'_, AIState>,
+    mcp_processes: State<'_, MCPProcessMap>,
+) -> Result<(), String> {
+    info!("Updating path policy for workspace: {}", policy.workspace);
+
+    policy.validate()?;
+
+    save_path_policy(&state.storage, &policy)
+        .map_err(|e| {
+            error!("Failed to store path policy: {}", e);
+            format!("Failed to store path policy: {}", e)
+        })?;
+
+    notify_roots_changed(&mcp_processes, &policy.workspace);
+    Ok(())
+}
+
 // System Commands
+
+/// Basic command whitelist for security
+const ALLOWED_COMMANDS: [&str; 19] = [
+    "ls", "dir", "echo", "cat", "grep", "find", "wc", "sort", "head", "tail",
+    "git", "npm", "yarn", "pnpm", "bun", "cargo", "rustc", "node", "python3",
+];
+
+/// A command may run only when it is in `ALLOWED_COMMANDS` and the full command line passes the
+/// command whitelist, whose blocked patterns and deny rules catch dangerous arguments
+fn ensure_command_permitted(command: &str, args: &[String]) -> std::result::Result<(), String> {
+    if !ALLOWED_COMMANDS.contains(&command) {
+        return Err(format!("Command '{}' is not allowed", command));
+    }
+    match super::command_whitelist::validate_command_execution(command, args) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("Command '{}' with these arguments is not allowed", command)),
+        Err(e) => Err(format!("Failed to check command whitelist: {}", e)),
+    }
+}
+
 #[tauri::command]
 pub async fn execute_command(
     command: String,
@@ -300,31 +392,51 @@ pub async fn execute_command(
     let sanitized_command = &validation_result.sanitized_inputs[0];
     let sanitized_args: Vec<String> = validation_result.sanitized_inputs[1..].to_vec();
 
-    // Basic command whitelist for security
-    let allowed_commands = [
-        "ls", "dir", "echo", "cat", "grep", "find", "wc", "sort", "head", "tail",
-        "git", "npm", "yarn", "pnpm", "bun", "cargo", "rustc", "node", "python3",
-    ];
-
-    if !allowed_commands.contains(&sanitized_command.as_str()) {
-        return Err(format!("Command '{}' is not allowed", sanitized_command));
-    }
+    ensure_command_permitted(sanitized_command, &sanitized_args)?;
 
-    let output = Command::new(sanitized_command)
-        .args(&sanitized_args)
-        .output()
+    let output = run_sandboxed(sanitized_command, &sanitized_args, &SandboxLimits::default())
+        .await
         .map_err(|e| {
             error!("Failed to execute command: {}", e);
             format!("Failed to execute command: {}", e)
         })?;
 
     Ok(CommandResult {
-        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-        status: output.status.code().unwrap_or(-1),
+        stdout: output.stdout,
+        stderr: output.stderr,
+        status: output.status,
     })
 }
 
+/// Run a whitelisted command under explicit resource limits, reporting limit breaches as structured errors
+#[tauri::command]
+pub async fn execute_command_sandboxed(
+    command: String,
+    args: Vec<String>,
+    limits: Option<SandboxLimits>,
+    state: State<'_, AIState>,
+) -> Result<SandboxOutput, SandboxError> {
+    info!("Executing sandboxed command: {} {:?}", command, args);
+
+    // Security validation
+    let security_middleware = state.get_security_middleware();
+    let mut all_inputs = vec![command.clone()];
+    all_inputs.extend(args.clone());
+
+    let validation_result = security_middleware.validate_request(
+        "system_operations",
+        &all_inputs,
+        &[]
+    ).await.map_err(SandboxError::NotPermitted)?;
+
+    let sanitized_command = &validation_result.sanitized_inputs[0];
+    let sanitized_args: Vec<String> = validation_result.sanitized_inputs[1..].to_vec();
+
+    ensure_command_permitted(sanitized_command, &sanitized_args).map_err(SandboxError::NotPermitted)?;
+
+    run_sandboxed(sanitized_command, &sanitized_args, &limits.unwrap_or_default()).await
+}
+
 // HTTP Commands
 #[tauri::command]
 pub async fn http_request_command(
@@ -332,6 +444,8 @@ pub async fn http_request_command(
     method: String,
     headers: Option<HashMap<String, String>>,
     body: Option<String>,
+    retry_policy: Option<super::RetryPolicy>,
+    use_cache: Option<bool>,
     state: State<'_, AIState>,
 ) -> Result<super::HttpResponse, String> {
     info!("Making HTTP request: {} {}", method, url);
@@ -366,25 +480,65 @@ pub async fn http_request_command(
         method: sanitized_method.clone(),
         headers,
         body: sanitized_body,
+        retry_policy,
+        use_cache: use_cache.unwrap_or(false),
     };
 
     state.http_client
         .make_request(request)
         .await
         .map_err(|e| {
+            let e = redact_error(e);
             error!("HTTP request failed: {}", e);
             format!("HTTP request failed: {}", e)
         })
 }
 
+#[tauri::command]
+pub async fn get_http_cache_stats(
+    state: State<'_, AIState>,
+) -> Result<super::HttpCacheStats, String> {
+    state.http_client
+        .cache()
+        .map(|cache| cache.stats())
+        .ok_or_else(|| "HTTP response cache is not available".to_string())
+}
+
+#[tauri::command]
+pub async fn list_http_cache_entries(
+    state: State<'_, AIState>,
+) -> Result<Vec<super::HttpCacheEntry>, String> {
+    state.http_client
+        .cache()
+        .map(|cache| cache.entries())
+        .ok_or_else(|| "HTTP response cache is not available".to_string())
+}
+
+#[tauri::command]
+pub async fn clear_http_cache(
+    url_prefix: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<usize, String> {
+    info!("Clearing HTTP cache{}", url_prefix.as_ref().map(|p| format!(" for {}", p)).unwrap_or_default());
+
+    let cache = state.http_client
+        .cache()
+        .ok_or_else(|| "HTTP response cache is not available".to_string())?;
+    cache.clear(url_prefix.as_deref())
+        .map_err(|e| format!("Failed to clear HTTP cache: {}", e))
+}
+
 // UI Commands
 #[tauri::command]
 pub async fn show_notification_command(
     title: String,
     message: String,
     r#type: String,
+    actions: Option<Vec<super::NotificationAction>>,
+    session: Option<super::NotificationSession>,
+    app: tauri::AppHandle,
     state: State<'_, AIState>,
-) -> Result<(), String> {
+) -> Result<String, String> {
     info!("Showing notification: {} - {}", title, message);
     
     // Security validation
@@ -403,15 +557,23 @@ pub async fn show_notification_command(
     let sanitized_message = &validation_result.sanitized_inputs[1];
     let sanitized_type = &validation_result.sanitized_inputs[2];
     
-    // In a real implementation, you would use the system notification API
-    // For now, we'll just log it
     match sanitized_type.as_str() {
         "error" => error!("NOTIFICATION [{}]: {}", sanitized_title, sanitized_message),
         "warning" => warn!("NOTIFICATION [{}]: {}", sanitized_title, sanitized_message),
         _ => info!("NOTIFICATION [{}]: {}", sanitized_title, sanitized_message),
     }
     
-    Ok(())
+    let mut notification = NewNotification::new(
+        NotificationSeverity::from_kind(sanitized_type),
+        "app",
+        sanitized_title.clone(),
+        sanitized_message.clone(),
+    );
+    notification.actions = actions.unwrap_or_default();
+    notification.session = session;
+    let stored = notify(&app, notification).map_err(|e| format!("Failed to store notification: {}", e))?;
+    
+    Ok(stored.id)
 }
 
 // Settings Commands
@@ -481,9 +643,135 @@ pub async fn get_setting_command(
 // Security and diagnostics
 #[tauri::command]
 pub async fn get_rate_limit_stats(
-    provider: String,
+    filter: Option<String>,
     state: State<'_, AIState>,
-) -> Result<Option<(usize, usize)>, String> {
+) -> Result<Vec<RateLimitBucketStats>, String> {
     let security_middleware = state.get_security_middleware();
-    Ok(security_middleware.get_stats(&provider).await)
+    Ok(security_middleware.get_stats(filter.as_deref()).await)
+}
+
+#[tauri::command]
+pub async fn get_rate_limit_config(
+    state: State<'_, AIState>,
+) -> Result<RateLimitConfig, String> {
+    Ok(state.get_security_middleware().rate_limit_config().await)
+}
+
+#[tauri::command]
+pub async fn set_rate_limit_config(
+    config: RateLimitConfig,
+    state: State<'_, AIState>,
+) -> Result<(), String> {
+    config.validate()?;
+    save_rate_limit_config(&state.storage, &config).map_err(|e| {
+        error!("Failed to save rate limit config: {}", e);
+        format!("Failed to save rate limit config: {}", e)
+    })?;
+    state.get_security_middleware().set_rate_limit_config(config).await;
+    info!("Rate limit config updated");
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_http_client_config(
+    state: State<'_, AIState>,
+) -> Result<HttpClientConfig, String> {
+    Ok(state.http_client.config())
+}
+
+#[tauri::command]
+pub async fn set_http_client_config(
+    config: HttpClientConfig,
+    state: State<'_, AIState>,
+) -> Result<(), String> {
+    config.validate()?;
+    save_http_client_config(&state.storage, &config).map_err(|e| {
+        error!("Failed to save HTTP client config: {}", e);
+        format!("Failed to save HTTP client config: {}", e)
+    })?;
+    state.http_client.set_config(config);
+    info!("HTTP client config updated");
+    Ok(())
+}
+
+/// Re-encrypt stored API keys, OAuth tokens, memory database keys and MCP server secrets under
+/// fresh data keys, optionally replacing the master key
+#[tauri::command]
+pub async fn rotate_encryption_keys(
+    session_id: String,
+    csrf_token: String,
+    csrf_double_submit: String,
+    rotate_master: Option<bool>,
+    webview: Webview,
+    state: State<'_, AIState>,
+    app_state: State<'_, crate::AppState>,
+) -> Result<SecureResponse<KeyRotationRecord>, String> {
+    let csrf = guard_secure_command("rotate_encryption_keys", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
+
+    let envelope = state.storage.envelope();
+    let data_key_versions = envelope.rotate_data_keys().map_err(|e| {
+        error!("Failed to rotate data keys: {}", e);
+        format!("Failed to rotate data keys: {}", e)
+    })?;
+
+    let (api_keys, api_failed) = state.storage.reencrypt_api_keys()
+        .map_err(|e| format!("Failed to re-encrypt API keys: {}", e))?;
+    let (oauth_tokens, oauth_failed) = app_state.oauth_storage.reencrypt_tokens().await
+        .map_err(|e| format!("Failed to re-encrypt OAuth tokens: {}", e))?;
+    let (database_keys, database_keys_failed) = crate::database::memory_encryption::reseal_database_keys(envelope)
+        .map_err(|e| format!("Failed to re-encrypt memory database keys: {}", e))?;
+    let (mcp_servers, mcp_failed) = crate::mcp::registry::reencrypt_registry(&state.storage).await
+        .map_err(|e| format!("Failed to re-encrypt MCP server secrets: {}", e))?;
+    let failed_secrets = api_failed + oauth_failed + database_keys_failed + mcp_failed;
+
+    // Old key material is only destroyed once nothing is encrypted with it
+    if failed_secrets == 0 {
+        envelope.retire_inactive_data_keys()
+            .map_err(|e| format!("Failed to retire old data keys: {}", e))?;
+    }
+
+    let rotate_master = rotate_master.unwrap_or(false) && failed_secrets == 0;
+    let master_version = if rotate_master {
+        envelope.rotate_master_key()
+    } else {
+        envelope.status().map(|status| status.master_version)
+    }.map_err(|e| format!("Failed to rotate master key: {}", e))?;
+
+    let record = KeyRotationRecord {
+        rotated_at: chrono::Utc::now().to_rfc3339(),
+        master_version,
+        data_key_versions,
+        reencrypted_secrets: api_keys + oauth_tokens + database_keys + mcp_servers,
+        failed_secrets,
+    };
+    envelope.record_rotation(record.clone())
+        .map_err(|e| format!("Failed to record key rotation: {}", e))?;
+
+    if failed_secrets > 0 {
+        warn!("{} secrets could not be re-encrypted; old keys were kept", failed_secrets);
+    }
+    info!("Encryption keys rotated: {} secrets re-encrypted, master v{}", record.reencrypted_secrets, master_version);
+    Ok(SecureResponse { data: record, csrf })
+}
+
+#[tauri::command]
+pub async fn get_encryption_key_status(
+    state: State<'_, AIState>,
+) -> Result<EncryptionKeyStatus, String> {
+    state.storage.envelope().status()
+        .map_err(|e| format!("Failed to read encryption key status: {}", e))
+}
+
+/// Take a token for a provider/agent-scoped operation, e.g. before a model call
+#[tauri::command]
+pub async fn acquire_rate_limit(
+    scope: RateLimitScope,
+    state: State<'_, AIState>,
+) -> Result<(), String> {
+    state.get_security_middleware()
+        .check_scoped_rate_limit(&scope)
+        .await
+        .map_err(|rejection| format!(
+            "Rate limit exceeded ({}). Retry in {}ms.", rejection.bucket, rejection.retry_after_ms
+        ))
 }
\ No newline at end of file
@@ -1,10 +1,13 @@
 use anyhow::{Result, Context};
+use ring::hmac;
 use ring::rand::{SecureRandom, SystemRandom};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
-use tracing::{info, warn, debug};
+use tracing::{info, warn, debug, error};
 
 const TOKEN_LENGTH: usize = 32;
 const TOKEN_EXPIRY_SECONDS: u64 = 3600; // 1 hour
@@ -14,46 +17,89 @@ pub struct CSRFToken {
     pub token: String,
     pub expires_at: u64,
     pub session_id: String,
+    /// Label of the webview the token was issued to; Tauri supplies it with each IPC call,
+    /// so page scripts can neither read nor forge it
+    pub origin: String,
+}
+
+/// Token pair handed to the client; both values must be submitted with each privileged command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsrfGrant {
+    pub token: String,
+    /// Second copy of the token, bound to the session and the issuing webview by the server's key
+    pub double_submit: String,
+    pub expires_at: u64,
 }
 
 pub struct CSRFManager {
     tokens: Arc<Mutex<HashMap<String, CSRFToken>>>,
     rng: SystemRandom,
+    double_submit_key: hmac::Key,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
 }
 
 impl CSRFManager {
     pub fn new() -> Self {
+        let rng = SystemRandom::new();
+        let double_submit_key = hmac::Key::generate(hmac::HMAC_SHA256, &rng)
+            .expect("Failed to generate CSRF signing key");
         Self {
             tokens: Arc::new(Mutex::new(HashMap::new())),
-            rng: SystemRandom::new(),
+            rng,
+            double_submit_key,
         }
     }
 
-    /// Generate a new CSRF token for a session
-    pub fn generate_token(&self, session_id: &str) -> Result<String> {
+    fn new_token(&self, session_id: &str, origin: &str) -> Result<CSRFToken> {
         let mut token_bytes = [0u8; TOKEN_LENGTH];
         self.rng.fill(&mut token_bytes)
             .map_err(|_| anyhow::anyhow!("Failed to generate random token"))?;
-        
-        let token = BASE64.encode(&token_bytes);
-        let expires_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() + TOKEN_EXPIRY_SECONDS;
 
-        let csrf_token = CSRFToken {
-            token: token.clone(),
-            expires_at,
+        Ok(CSRFToken {
+            token: BASE64.encode(token_bytes),
+            expires_at: now_secs() + TOKEN_EXPIRY_SECONDS,
             session_id: session_id.to_string(),
-        };
+            origin: origin.to_string(),
+        })
+    }
+
+    fn double_submit_message(session_id: &str, origin: &str, token: &str) -> String {
+        format!("{}\n{}\n{}", session_id, origin, token)
+    }
+
+    fn grant(&self, csrf_token: &CSRFToken) -> CsrfGrant {
+        let message = Self::double_submit_message(&csrf_token.session_id, &csrf_token.origin, &csrf_token.token);
+        let signature = hmac::sign(&self.double_submit_key, message.as_bytes());
+        CsrfGrant {
+            token: csrf_token.token.clone(),
+            double_submit: BASE64.encode(signature.as_ref()),
+            expires_at: csrf_token.expires_at,
+        }
+    }
+
+    /// Issue a new token pair for a session, bound to the webview asking for it
+    pub fn issue_token(&self, session_id: &str, origin: &str) -> Result<CsrfGrant> {
+        let csrf_token = self.new_token(session_id, origin)?;
+        let grant = self.grant(&csrf_token);
 
         {
             let mut tokens = self.tokens.lock().unwrap();
-            tokens.insert(token.clone(), csrf_token);
+            tokens.insert(csrf_token.token.clone(), csrf_token);
         }
 
         debug!("Generated CSRF token for session: {}", session_id);
-        Ok(token)
+        Ok(grant)
+    }
+
+    /// Generate a new CSRF token for a session
+    pub fn generate_token(&self, session_id: &str) -> Result<String> {
+        Ok(self.issue_token(session_id, "")?.token)
     }
 
     /// Validate a CSRF token
@@ -88,6 +134,44 @@ impl CSRFManager {
         }
     }
 
+    /// Validate a token and its double-submit value from the webview `origin`, then spend it
+    /// and return its replacement. A token is good for exactly one command.
+    pub fn rotate_token(&self, token: &str, session_id: &str, double_submit: &str, origin: &str) -> Result<Option<CsrfGrant>> {
+        let signature_matches = BASE64.decode(double_submit).is_ok_and(|signature| {
+            let message = Self::double_submit_message(session_id, origin, token);
+            hmac::verify(&self.double_submit_key, message.as_bytes(), &signature).is_ok()
+        });
+        if !signature_matches {
+            warn!("CSRF double-submit value does not match token for session: {}", session_id);
+            return Ok(None);
+        }
+
+        let current_time = now_secs();
+        let mut tokens = self.tokens.lock().unwrap();
+
+        // Spent on first sight: a replayed or concurrent second use finds nothing
+        let Some(csrf_token) = tokens.remove(token) else {
+            warn!("Invalid or already used CSRF token for session: {}", session_id);
+            return Ok(None);
+        };
+        if current_time > csrf_token.expires_at {
+            warn!("CSRF token expired for session: {}", session_id);
+            return Ok(None);
+        }
+        if csrf_token.session_id != session_id || csrf_token.origin != origin {
+            warn!("CSRF token session mismatch: expected {}, got {}",
+                 csrf_token.session_id, session_id);
+            return Ok(None);
+        }
+
+        let next = self.new_token(session_id, origin)?;
+        let grant = self.grant(&next);
+        tokens.insert(next.token.clone(), next);
+
+        debug!("CSRF token rotated for session: {}", session_id);
+        Ok(Some(grant))
+    }
+
     /// Remove a token (after use)
     pub fn consume_token(&self, token: &str) -> Result<()> {
         let mut tokens = self.tokens.lock().unwrap();
@@ -137,6 +221,12 @@ pub struct SessionInfo {
     pub created_at: u64,
     pub last_activity: u64,
     pub csrf_tokens: Vec<String>,
+    /// Canonical root that file tools in this session are confined to
+    pub workspace_root: Option<PathBuf>,
+    /// Agent the session acts as; memory reads for other agents are limited to its grants
+    pub agent_id: Option<String>,
+    /// Label of the webview that created the session; only it may use the session
+    pub origin: String,
 }
 
 impl SessionManager {
@@ -147,8 +237,8 @@ impl SessionManager {
         }
     }
 
-    /// Create a new session
-    pub fn create_session(&self) -> Result<String> {
+    /// Create a new session for the webview `origin`
+    pub fn create_session(&self, origin: &str) -> Result<String> {
         let mut session_bytes = [0u8; TOKEN_LENGTH];
         self.rng.fill(&mut session_bytes)
             .map_err(|_| anyhow::anyhow!("Failed to generate session ID"))?;
@@ -164,6 +254,9 @@ impl SessionManager {
             created_at: current_time,
             last_activity: current_time,
             csrf_tokens: Vec::new(),
+            workspace_root: None,
+            agent_id: None,
+            origin: origin.to_string(),
         };
 
         {
@@ -195,6 +288,49 @@ impl SessionManager {
         }
     }
 
+    /// Bind a session to a workspace root, or unbind it with `None`; false for unknown sessions
+    pub fn set_workspace_root(&self, session_id: &str, root: Option<PathBuf>) -> Result<bool> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get_mut(session_id) {
+            Some(session) => {
+                session.workspace_root = root;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Bind a session to the agent it acts as, or unbind it with `None`; false for unknown sessions
+    pub fn set_agent(&self, session_id: &str, agent_id: Option<String>) -> Result<bool> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get_mut(session_id) {
+            Some(session) => {
+                session.agent_id = agent_id;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn agent_id(&self, session_id: &str) -> Option<String> {
+        self.sessions.lock().unwrap()
+            .get(session_id)
+            .and_then(|session| session.agent_id.clone())
+    }
+
+    /// Whether the session exists and was created by the webview `origin`
+    pub fn belongs_to(&self, session_id: &str, origin: &str) -> bool {
+        self.sessions.lock().unwrap()
+            .get(session_id)
+            .is_some_and(|session| session.origin == origin)
+    }
+
+    pub fn workspace_root(&self, session_id: &str) -> Option<PathBuf> {
+        self.sessions.lock().unwrap()
+            .get(session_id)
+            .and_then(|session| session.workspace_root.clone())
+    }
+
     /// Remove a session
     pub fn remove_session(&self, session_id: &str) -> Result<()> {
         let mut sessions = self.sessions.lock().unwrap();
@@ -256,6 +392,48 @@ pub fn validate_request_security(session_id: &str, csrf_token: &str) -> Result<b
     Ok(true)
 }
 
+/// Uniform check for every `*_secure` command: a valid session owned by the calling webview
+/// plus a matching token pair, which is spent and replaced by the returned grant
+pub fn guard_secure_command(
+    command: &str,
+    session_id: &str,
+    csrf_token: &str,
+    double_submit: &str,
+    origin: &str,
+) -> std::result::Result<CsrfGrant, String> {
+    let outcome = SESSION_MANAGER.validate_session(session_id).and_then(|valid| {
+        if valid && SESSION_MANAGER.belongs_to(session_id, origin) {
+            CSRF_MANAGER.rotate_token(csrf_token, session_id, double_submit, origin)
+        } else {
+            Ok(None)
+        }
+    });
+
+    match outcome {
+        Ok(Some(grant)) => Ok(grant),
+        Ok(None) => {
+            warn!("Security validation failed for command: {}", command);
+            Err("Security validation failed".to_string())
+        }
+        Err(e) => {
+            error!("Security validation error for {}: {}", command, e);
+            Err("Security validation failed".to_string())
+        }
+    }
+}
+
+/// Agent a read-only command acts as: the one bound to the session, which must be valid and
+/// owned by the calling webview. `None` means the session acts as the user, not an agent.
+pub fn session_agent(command: &str, session_id: &str, origin: &str) -> std::result::Result<Option<String>, String> {
+    match SESSION_MANAGER.validate_session(session_id) {
+        Ok(true) if SESSION_MANAGER.belongs_to(session_id, origin) => Ok(SESSION_MANAGER.agent_id(session_id)),
+        _ => {
+            warn!("Security validation failed for command: {}", command);
+            Err("Security validation failed".to_string())
+        }
+    }
+}
+
 /// Initialize security managers
 pub fn init_security_managers() {
     // Note: Cleanup tasks should be started within Tauri's async context
@@ -286,13 +464,49 @@ mod tests {
         assert!(!manager.validate_token(&token, wrong_session).unwrap());
     }
 
+    #[test]
+    fn test_rotation_spends_token_and_requires_double_submit() {
+        let manager = CSRFManager::new();
+        let grant = manager.issue_token("s1", "main").unwrap();
+
+        // Double-submit value must match this token, session and webview
+        let other = manager.issue_token("s1", "main").unwrap();
+        assert!(manager.rotate_token(&grant.token, "s1", &other.double_submit, "main").unwrap().is_none());
+        assert!(manager.rotate_token(&grant.token, "s2", &grant.double_submit, "main").unwrap().is_none());
+        assert!(manager.rotate_token(&grant.token, "s1", &grant.double_submit, "popup").unwrap().is_none());
+
+        let next = manager.rotate_token(&grant.token, "s1", &grant.double_submit, "main").unwrap().unwrap();
+        assert_ne!(next.token, grant.token);
+
+        // The spent token is gone at once; a second use is a replay
+        assert!(manager.rotate_token(&grant.token, "s1", &grant.double_submit, "main").unwrap().is_none());
+        assert_eq!(manager.token_count(), 2);
+        assert!(manager.rotate_token(&next.token, "s1", &next.double_submit, "main").unwrap().is_some());
+    }
+
     #[test]
     fn test_session_creation() {
         let manager = SessionManager::new();
-        let session_id = manager.create_session().unwrap();
+        let session_id = manager.create_session("main").unwrap();
         
         assert!(!session_id.is_empty());
         assert!(manager.validate_session(&session_id).unwrap());
+        assert!(manager.belongs_to(&session_id, "main"));
+        assert!(!manager.belongs_to(&session_id, "popup"));
+    }
+
+    #[test]
+    fn test_session_agent_comes_from_the_binding() {
+        let session_id = SESSION_MANAGER.create_session("main").unwrap();
+        assert_eq!(session_agent("test", &session_id, "main").unwrap(), None);
+
+        assert!(SESSION_MANAGER.set_agent(&session_id, Some("reader".to_string())).unwrap());
+        assert_eq!(session_agent("test", &session_id, "main").unwrap().as_deref(), Some("reader"));
+
+        // Another webview cannot borrow the session's identity, and unknown sessions have none
+        assert!(session_agent("test", &session_id, "popup").is_err());
+        assert!(session_agent("test", "missing", "main").is_err());
+        assert!(!SESSION_MANAGER.set_agent("missing", Some("reader".to_string())).unwrap());
     }
 
     #[test]
@@ -300,7 +514,7 @@ mod tests {
         let session_manager = SessionManager::new();
         let csrf_manager = CSRFManager::new();
         
-        let session_id = session_manager.create_session().unwrap();
+        let session_id = session_manager.create_session("main").unwrap();
         let csrf_token = csrf_manager.generate_token(&session_id).unwrap();
         
         // This would require the global managers to be the same instances
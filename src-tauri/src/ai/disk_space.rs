@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use thiserror::Error;
+use tracing::{debug, error, warn};
+
+use super::notifications::{dispatch_notification, NotificationPayload, NotificationState};
+
+/// Free space always left untouched so the OS, logs and SQLite journals keep working
+pub const RESERVED_FREE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Disk statistics are re-read at most this often; checks in between use the cached figures
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// At most one low-disk notification per volume in this window
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Event emitted with the `DiskSpaceError` whenever a write is refused
+pub const LOW_DISK_SPACE_EVENT: &str = "low_disk_space";
+
+#[derive(Error, Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "detail")]
+pub enum DiskSpaceError {
+    #[error("Not enough disk space for {operation} on {mount_point}: {required_bytes} bytes needed but only {available_bytes} available ({reserved_bytes} kept free)")]
+    InsufficientSpace {
+        operation: String,
+        mount_point: String,
+        required_bytes: u64,
+        available_bytes: u64,
+        reserved_bytes: u64,
+    },
+}
+
+struct DiskSnapshot {
+    probed_at: Instant,
+    /// Mount point and available bytes, less anything reserved since the probe
+    volumes: Vec<(PathBuf, u64)>,
+}
+
+/// Refuses writes that would run a volume out of space, probing disk statistics at a throttled rate
+pub struct DiskSpaceGuard {
+    snapshot: Mutex<Option<DiskSnapshot>>,
+    last_notified: Mutex<HashMap<PathBuf, Instant>>,
+    notifier: Mutex<Option<AppHandle>>,
+}
+
+fn probe_volumes() -> Vec<(PathBuf, u64)> {
+    sysinfo::Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .map(|disk| (disk.mount_point().to_path_buf(), disk.available_space()))
+        .collect()
+}
+
+/// The path itself if it exists, otherwise its nearest existing ancestor, made absolute
+fn existing_ancestor(path: &Path) -> PathBuf {
+    let mut current = Some(path);
+    while let Some(candidate) = current {
+        if let Ok(resolved) = candidate.canonicalize() {
+            // Windows canonical paths carry a `\\?\` prefix that mount points lack
+            #[cfg(windows)]
+            if let Some(plain) = resolved.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+                return PathBuf::from(plain);
+            }
+            return resolved;
+        }
+        current = candidate.parent();
+    }
+    std::env::current_dir().unwrap_or_default()
+}
+
+impl DiskSpaceGuard {
+    pub fn new() -> Self {
+        Self {
+            snapshot: Mutex::new(None),
+            last_notified: Mutex::new(HashMap::new()),
+            notifier: Mutex::new(None),
+        }
+    }
+
+    /// Low-disk notifications are only shown once an app handle is attached
+    pub fn attach(&self, app: AppHandle) {
+        *self.notifier.lock().unwrap() = Some(app);
+    }
+
+    fn check_with(
+        &self,
+        path: &Path,
+        required_bytes: u64,
+        operation: &str,
+        now: Instant,
+        probe: impl FnOnce() -> Vec<(PathBuf, u64)>,
+    ) -> Result<(), DiskSpaceError> {
+        let target = existing_ancestor(path);
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if snapshot.as_ref().map_or(true, |s| now.duration_since(s.probed_at) >= PROBE_INTERVAL) {
+            *snapshot = Some(DiskSnapshot { probed_at: now, volumes: probe() });
+        }
+
+        let volumes = &mut snapshot.as_mut().unwrap().volumes;
+        let Some((mount_point, available)) = volumes.iter_mut()
+            .filter(|(mount, _)| target.starts_with(mount))
+            .max_by_key(|(mount, _)| mount.components().count())
+        else {
+            // Unknown volume (e.g. some network mounts): let the write fail on its own if it must
+            debug!("No volume statistics for {}", target.display());
+            return Ok(());
+        };
+
+        if required_bytes.saturating_add(RESERVED_FREE_BYTES) > *available {
+            return Err(DiskSpaceError::InsufficientSpace {
+                operation: operation.to_string(),
+                mount_point: mount_point.display().to_string(),
+                required_bytes,
+                available_bytes: *available,
+                reserved_bytes: RESERVED_FREE_BYTES,
+            });
+        }
+
+        // Count this write against the cached figure so a burst of writes can't all pass
+        *available -= required_bytes;
+        Ok(())
+    }
+
+    /// Check that `required_bytes` can be written under `path`, notifying the user when it cannot
+    pub fn ensure(&self, path: &Path, required_bytes: u64, operation: &str) -> Result<(), DiskSpaceError> {
+        let result = self.check_with(path, required_bytes, operation, Instant::now(), probe_volumes);
+        if let Err(ref e) = result {
+            warn!("{}", e);
+            self.notify(e);
+        }
+        result
+    }
+
+    fn notify(&self, err: &DiskSpaceError) {
+        let DiskSpaceError::InsufficientSpace { mount_point, .. } = err;
+        {
+            let mut last_notified = self.last_notified.lock().unwrap();
+            let now = Instant::now();
+            let key = PathBuf::from(mount_point);
+            if last_notified.get(&key).is_some_and(|at| now.duration_since(*at) < NOTIFY_INTERVAL) {
+                return;
+            }
+            last_notified.insert(key, now);
+        }
+
+        let Some(app) = self.notifier.lock().unwrap().clone() else {
+            return;
+        };
+        if let Err(e) = app.emit(LOW_DISK_SPACE_EVENT, err) {
+            error!("Failed to emit low disk space event: {}", e);
+        }
+        if let Some(notification_state) = app.try_state::<NotificationState>() {
+            dispatch_notification(&app, &notification_state, NotificationPayload {
+                notification_id: uuid::Uuid::new_v4().to_string(),
+                title: "Low disk space".to_string(),
+                message: err.to_string(),
+                kind: "warning".to_string(),
+                actions: Vec::new(),
+                session: None,
+            });
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref DISK_SPACE_GUARD: DiskSpaceGuard = DiskSpaceGuard::new();
+}
+
+/// Refuse a write of `required_bytes` under `path` when its volume is nearly full
+pub fn ensure_disk_space(path: &Path, required_bytes: u64, operation: &str) -> Result<(), DiskSpaceError> {
+    DISK_SPACE_GUARD.ensure(path, required_bytes, operation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_most_specific_volume_and_reservations() {
+        let guard = DiskSpaceGuard::new();
+        let dir = std::env::temp_dir();
+        let now = Instant::now();
+        let volumes = || vec![(PathBuf::from("/"), 100 * GB), (existing_ancestor(&dir), GB)];
+
+        // The temp dir's own volume (1 GB) applies, not the root volume
+        assert!(guard.check_with(&dir.join("new/file.db"), 512 * 1024 * 1024, "backup", now, volumes).is_ok());
+        let err = guard.check_with(&dir, 512 * 1024 * 1024, "backup", now, || unreachable!()).unwrap_err();
+        let DiskSpaceError::InsufficientSpace { available_bytes, operation, .. } = err;
+        assert_eq!(available_bytes, 512 * 1024 * 1024);
+        assert_eq!(operation, "backup");
+    }
+
+    #[test]
+    fn test_probe_is_throttled() {
+        let guard = DiskSpaceGuard::new();
+        let dir = std::env::temp_dir();
+        let start = Instant::now();
+        let full = || vec![(PathBuf::from("/"), RESERVED_FREE_BYTES)];
+        let roomy = || vec![(PathBuf::from("/"), 10 * GB)];
+
+        assert!(guard.check_with(&dir, 1, "write", start, full).is_err());
+        // Cached figures are reused inside the probe interval
+        assert!(guard.check_with(&dir, 1, "write", start + Duration::from_secs(1), roomy).is_err());
+        assert!(guard.check_with(&dir, 1, "write", start + PROBE_INTERVAL, roomy).is_ok());
+    }
+}
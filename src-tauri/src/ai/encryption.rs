@@ -1,7 +1,11 @@
 use anyhow::{Result, Context};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use ring::{aead, pbkdf2, rand::{SecureRandom, SystemRandom}};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 
 const CREDENTIAL_LEN: usize = 32; // ChaCha20Poly1305 key length
 const NONCE_LEN: usize = 12;      // ChaCha20Poly1305 nonce length
@@ -112,48 +116,460 @@ impl SecureStorage {
     }
 }
 
-/// Get or create master password for encryption
-pub fn get_master_password() -> Result<String> {
-    use std::fs;
-    use dirs;
+/// Prefix marking ciphertext produced by `EnvelopeEncryption`
+const ENVELOPE_PREFIX: &str = "env1";
+const KEYRING_FILE: &str = "keys.json";
+
+/// What a data key protects; each purpose has its own key and version history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyPurpose {
+    ApiKeys,
+    OauthTokens,
+    /// Keys of encrypted agent memory databases
+    MemoryDatabases,
+    /// Tokens, secret env vars and headers of registered MCP servers
+    McpSecrets,
+}
 
-    let config_dir = dirs::config_dir()
-        .context("Failed to get config directory")?
-        .join("banshee");
+impl KeyPurpose {
+    pub const ALL: [KeyPurpose; 4] = [
+        KeyPurpose::ApiKeys, KeyPurpose::OauthTokens, KeyPurpose::MemoryDatabases, KeyPurpose::McpSecrets,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            KeyPurpose::ApiKeys => "api_keys",
+            KeyPurpose::OauthTokens => "oauth_tokens",
+            KeyPurpose::MemoryDatabases => "memory_databases",
+            KeyPurpose::McpSecrets => "mcp_secrets",
+        }
+    }
+}
 
-    fs::create_dir_all(&config_dir)
-        .context("Failed to create config directory")?;
+/// Where the master key lives. Slot `next` holds a master key mid-rotation.
+#[derive(Debug, Clone)]
+pub enum MasterKeyStore {
+    /// `.master_key` in the given directory, shared with the legacy password scheme
+    File(PathBuf),
+    #[cfg(feature = "os-keyring")]
+    OsKeyring,
+}
+
+impl MasterKeyStore {
+    fn backend(&self) -> &'static str {
+        match self {
+            MasterKeyStore::File(_) => "file",
+            #[cfg(feature = "os-keyring")]
+            MasterKeyStore::OsKeyring => "os_keyring",
+        }
+    }
 
-    let password_file = config_dir.join(".master_key");
+    fn load(&self, slot: &str) -> Result<Option<String>> {
+        match self {
+            MasterKeyStore::File(dir) => {
+                let path = dir.join(Self::file_name(slot));
+                if path.exists() {
+                    Ok(Some(std::fs::read_to_string(&path).context("Failed to read master key file")?))
+                } else {
+                    Ok(None)
+                }
+            }
+            #[cfg(feature = "os-keyring")]
+            MasterKeyStore::OsKeyring => match Self::keyring_entry(slot)?.get_password() {
+                Ok(key) => Ok(Some(key)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(anyhow::anyhow!("Failed to read master key from OS keyring: {}", e)),
+            },
+        }
+    }
 
-    if password_file.exists() {
-        // Read existing password
-        fs::read_to_string(&password_file)
-            .context("Failed to read master password file")
-    } else {
-        // Generate new password
-        let storage = SecureStorage::new();
-        let password = storage.generate_master_password()?;
-        
-        // Save password to file with restricted permissions
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            fs::write(&password_file, &password)
-                .context("Failed to write master password file")?;
-            let mut perms = fs::metadata(&password_file)?.permissions();
-            perms.set_mode(0o600); // Only owner can read/write
-            fs::set_permissions(&password_file, perms)
-                .context("Failed to set password file permissions")?;
+    fn store(&self, slot: &str, key: &str) -> Result<()> {
+        match self {
+            MasterKeyStore::File(dir) => write_private_file(&dir.join(Self::file_name(slot)), key),
+            #[cfg(feature = "os-keyring")]
+            MasterKeyStore::OsKeyring => Self::keyring_entry(slot)?.set_password(key)
+                .map_err(|e| anyhow::anyhow!("Failed to store master key in OS keyring: {}", e)),
         }
-        
-        #[cfg(not(unix))]
-        {
-            fs::write(&password_file, &password)
-                .context("Failed to write master password file")?;
+    }
+
+    fn delete(&self, slot: &str) -> Result<()> {
+        match self {
+            MasterKeyStore::File(dir) => {
+                let path = dir.join(Self::file_name(slot));
+                if path.exists() {
+                    std::fs::remove_file(path).context("Failed to remove master key file")?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "os-keyring")]
+            MasterKeyStore::OsKeyring => match Self::keyring_entry(slot)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(e) => Err(anyhow::anyhow!("Failed to remove master key from OS keyring: {}", e)),
+            },
         }
+    }
+
+    fn file_name(slot: &str) -> String {
+        match slot {
+            "current" => ".master_key".to_string(),
+            other => format!(".master_key.{}", other),
+        }
+    }
+
+    #[cfg(feature = "os-keyring")]
+    fn keyring_entry(slot: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new("banshee", &format!("master-key-{}", slot))
+            .map_err(|e| anyhow::anyhow!("OS keyring unavailable: {}", e))
+    }
+}
+
+fn write_private_file(path: &std::path::Path, contents: &str) -> Result<()> {
+    std::fs::write(path, contents).context("Failed to write key file")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .context("Failed to set key file permissions")?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DataKeyRecord {
+    version: u32,
+    /// Data key sealed with the master key; dropped once the version is retired
+    wrapped_key: Option<String>,
+    created_at: String,
+    retired_at: Option<String>,
+}
+
+/// Outcome of one `rotate_encryption_keys` run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRotationRecord {
+    pub rotated_at: String,
+    pub master_version: u32,
+    pub data_key_versions: HashMap<KeyPurpose, u32>,
+    pub reencrypted_secrets: usize,
+    pub failed_secrets: usize,
+}
 
-        Ok(password)
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeyRingFile {
+    master_version: u32,
+    /// SHA-256 prefix identifying the master key that wraps the data keys
+    master_fingerprint: String,
+    data_keys: HashMap<KeyPurpose, Vec<DataKeyRecord>>,
+    #[serde(default)]
+    rotations: Vec<KeyRotationRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataKeyInfo {
+    pub purpose: KeyPurpose,
+    pub version: u32,
+    pub active: bool,
+    pub created_at: String,
+    pub retired_at: Option<String>,
+}
+
+/// Key versions and rotation history; never contains key material
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionKeyStatus {
+    pub master_backend: String,
+    pub master_version: u32,
+    pub data_keys: Vec<DataKeyInfo>,
+    pub rotations: Vec<KeyRotationRecord>,
+}
+
+fn seal(key_bytes: &[u8], aad: &str, plaintext: &[u8], rng: &SystemRandom) -> Result<Vec<u8>> {
+    let key = aead::LessSafeKey::new(
+        aead::UnboundKey::new(&aead::CHACHA20_POLY1305, key_bytes)
+            .map_err(|_| anyhow::anyhow!("Failed to create encryption key"))?,
+    );
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    rng.fill(&mut nonce_bytes).map_err(|_| anyhow::anyhow!("Failed to generate nonce"))?;
+
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(nonce_bytes), aead::Aad::from(aad.as_bytes()), &mut in_out)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt data"))?;
+
+    let mut sealed = nonce_bytes.to_vec();
+    sealed.extend_from_slice(&in_out);
+    Ok(sealed)
+}
+
+fn open(key_bytes: &[u8], aad: &str, sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow::anyhow!("Ciphertext too short"));
+    }
+    let key = aead::LessSafeKey::new(
+        aead::UnboundKey::new(&aead::CHACHA20_POLY1305, key_bytes)
+            .map_err(|_| anyhow::anyhow!("Failed to create decryption key"))?,
+    );
+    let (nonce_bytes, ciphertext) = sealed.split_at(NONCE_LEN);
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(nonce_bytes);
+
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key.open_in_place(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::from(aad.as_bytes()), &mut in_out)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt data - wrong key or corrupted data"))?;
+    Ok(plaintext.to_vec())
+}
+
+fn fingerprint(master: &[u8]) -> String {
+    hex::encode(&Sha256::digest(master)[..8])
+}
+
+/// Envelope encryption: a master key wraps one versioned data key per purpose.
+///
+/// Secrets are encrypted with the active data key of their purpose and tagged
+/// with its version, so rotating a data key only requires re-encrypting the
+/// secrets, and rotating the master key only requires re-wrapping data keys.
+pub struct EnvelopeEncryption {
+    dir: PathBuf,
+    store: MasterKeyStore,
+    rng: SystemRandom,
+}
+
+impl EnvelopeEncryption {
+    pub fn new(dir: PathBuf, store: MasterKeyStore) -> Self {
+        Self { dir, store, rng: SystemRandom::new() }
+    }
+
+    /// Key ring in the app config directory; the master key uses the OS keyring when built with `os-keyring`
+    pub fn open_default() -> Result<Self> {
+        let dir = dirs::config_dir()
+            .context("Failed to get config directory")?
+            .join("banshee");
+        std::fs::create_dir_all(&dir).context("Failed to create config directory")?;
+
+        #[cfg(feature = "os-keyring")]
+        let store = MasterKeyStore::OsKeyring;
+        #[cfg(not(feature = "os-keyring"))]
+        let store = MasterKeyStore::File(dir.clone());
+
+        Ok(Self::new(dir, store))
+    }
+
+    /// Whether a stored value was produced by envelope encryption
+    pub fn is_envelope(ciphertext: &str) -> bool {
+        ciphertext.starts_with(&format!("{}:", ENVELOPE_PREFIX))
+    }
+
+    fn keyring_path(&self) -> PathBuf {
+        self.dir.join(KEYRING_FILE)
+    }
+
+    fn load_keyring(&self) -> Result<KeyRingFile> {
+        let path = self.keyring_path();
+        if !path.exists() {
+            return Ok(KeyRingFile::default());
+        }
+        serde_json::from_str(&std::fs::read_to_string(&path).context("Failed to read key ring")?)
+            .context("Key ring is malformed")
+    }
+
+    fn save_keyring(&self, keyring: &KeyRingFile) -> Result<()> {
+        write_private_file(&self.keyring_path(), &serde_json::to_string_pretty(keyring)?)
+    }
+
+    /// Master key bytes, creating one on first use and finishing an interrupted rotation
+    fn master_key(&self, keyring: &KeyRingFile) -> Result<Vec<u8>> {
+        let decode = |encoded: String| BASE64.decode(encoded.trim()).context("Master key is malformed");
+
+        if let Some(current) = self.store.load("current")? {
+            let key = decode(current)?;
+            if keyring.master_fingerprint.is_empty() || fingerprint(&key) == keyring.master_fingerprint {
+                return Ok(key);
+            }
+        }
+
+        // The key ring was re-wrapped but the new master key was not promoted yet
+        if let Some(next) = self.store.load("next")? {
+            let key = decode(next.clone())?;
+            if fingerprint(&key) == keyring.master_fingerprint {
+                self.store.store("current", &next)?;
+                self.store.delete("next")?;
+                return Ok(key);
+            }
+        }
+
+        if !keyring.master_fingerprint.is_empty() {
+            return Err(anyhow::anyhow!("Master key does not match the key ring"));
+        }
+
+        #[cfg(feature = "os-keyring")]
+        if let Some(legacy) = MasterKeyStore::File(self.dir.clone()).load("current")? {
+            // Adopt the file-based key so secrets encrypted before the keyring existed stay readable
+            self.store.store("current", legacy.trim())?;
+            return decode(legacy);
+        }
+
+        let password = SecureStorage::new().generate_master_password()?;
+        self.store.store("current", &password)?;
+        decode(password)
+    }
+
+    /// The password legacy `SecureStorage` ciphertext was encrypted with
+    pub fn legacy_password(&self) -> Result<String> {
+        let keyring = self.load_keyring()?;
+        Ok(BASE64.encode(self.master_key(&keyring)?))
+    }
+
+    fn new_data_key(&self, master: &[u8], purpose: KeyPurpose, version: u32) -> Result<DataKeyRecord> {
+        let mut data_key = [0u8; CREDENTIAL_LEN];
+        self.rng.fill(&mut data_key).map_err(|_| anyhow::anyhow!("Failed to generate data key"))?;
+        let wrapped = seal(master, &format!("{}:{}", purpose.as_str(), version), &data_key, &self.rng)?;
+        Ok(DataKeyRecord {
+            version,
+            wrapped_key: Some(BASE64.encode(wrapped)),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            retired_at: None,
+        })
+    }
+
+    fn unwrap_data_key(master: &[u8], purpose: KeyPurpose, record: &DataKeyRecord) -> Result<Vec<u8>> {
+        let wrapped = record.wrapped_key.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Data key {} v{} has been retired", purpose.as_str(), record.version))?;
+        open(master, &format!("{}:{}", purpose.as_str(), record.version), &BASE64.decode(wrapped)?)
+    }
+
+    /// Load the key ring, creating the master key and first data keys when missing
+    fn initialized_keyring(&self) -> Result<(KeyRingFile, Vec<u8>)> {
+        let mut keyring = self.load_keyring()?;
+        let master = self.master_key(&keyring)?;
+
+        let mut changed = keyring.master_fingerprint.is_empty();
+        if changed {
+            keyring.master_version = 1;
+            keyring.master_fingerprint = fingerprint(&master);
+        }
+        for purpose in KeyPurpose::ALL {
+            if !keyring.data_keys.get(&purpose).is_some_and(|keys| !keys.is_empty()) {
+                keyring.data_keys.insert(purpose, vec![self.new_data_key(&master, purpose, 1)?]);
+                changed = true;
+            }
+        }
+        if changed {
+            self.save_keyring(&keyring)?;
+        }
+        Ok((keyring, master))
+    }
+
+    pub fn encrypt(&self, purpose: KeyPurpose, plaintext: &str) -> Result<String> {
+        let (keyring, master) = self.initialized_keyring()?;
+        let active = keyring.data_keys[&purpose].iter()
+            .max_by_key(|k| k.version)
+            .ok_or_else(|| anyhow::anyhow!("No data key for {}", purpose.as_str()))?;
+        let data_key = Self::unwrap_data_key(&master, purpose, active)?;
+        let sealed = seal(&data_key, purpose.as_str(), plaintext.as_bytes(), &self.rng)?;
+        Ok(format!("{}:{}:{}:{}", ENVELOPE_PREFIX, purpose.as_str(), active.version, BASE64.encode(sealed)))
+    }
+
+    pub fn decrypt(&self, purpose: KeyPurpose, ciphertext: &str) -> Result<String> {
+        let mut parts = ciphertext.splitn(4, ':');
+        let (Some(ENVELOPE_PREFIX), Some(tag), Some(version), Some(data)) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(anyhow::anyhow!("Not an envelope-encrypted value"));
+        };
+        if tag != purpose.as_str() {
+            return Err(anyhow::anyhow!("Value was encrypted for {}, not {}", tag, purpose.as_str()));
+        }
+        let version: u32 = version.parse().context("Invalid data key version")?;
+
+        let (keyring, master) = self.initialized_keyring()?;
+        let record = keyring.data_keys.get(&purpose)
+            .and_then(|keys| keys.iter().find(|k| k.version == version))
+            .ok_or_else(|| anyhow::anyhow!("Unknown data key {} v{}", purpose.as_str(), version))?;
+        let data_key = Self::unwrap_data_key(&master, purpose, record)?;
+
+        String::from_utf8(open(&data_key, purpose.as_str(), &BASE64.decode(data)?)?)
+            .context("Decrypted data is not valid UTF-8")
+    }
+
+    /// Add a new active data key for every purpose; older versions stay usable until retired
+    pub fn rotate_data_keys(&self) -> Result<HashMap<KeyPurpose, u32>> {
+        let (mut keyring, master) = self.initialized_keyring()?;
+        let mut versions = HashMap::new();
+        for purpose in KeyPurpose::ALL {
+            let keys = keyring.data_keys.entry(purpose).or_default();
+            let version = keys.iter().map(|k| k.version).max().unwrap_or(0) + 1;
+            keys.push(self.new_data_key(&master, purpose, version)?);
+            versions.insert(purpose, version);
+        }
+        self.save_keyring(&keyring)?;
+        Ok(versions)
+    }
+
+    /// Destroy the material of every inactive data key; call only once nothing is encrypted with them
+    pub fn retire_inactive_data_keys(&self) -> Result<usize> {
+        let (mut keyring, _) = self.initialized_keyring()?;
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut retired = 0;
+        for keys in keyring.data_keys.values_mut() {
+            let active = keys.iter().map(|k| k.version).max().unwrap_or(0);
+            for key in keys.iter_mut().filter(|k| k.version != active && k.wrapped_key.is_some()) {
+                key.wrapped_key = None;
+                key.retired_at = Some(now.clone());
+                retired += 1;
+            }
+        }
+        self.save_keyring(&keyring)?;
+        Ok(retired)
+    }
+
+    /// Replace the master key and re-wrap every live data key under it
+    pub fn rotate_master_key(&self) -> Result<u32> {
+        let (mut keyring, old_master) = self.initialized_keyring()?;
+        let new_password = SecureStorage::new().generate_master_password()?;
+        let new_master = BASE64.decode(&new_password)?;
+
+        for (purpose, keys) in keyring.data_keys.iter_mut() {
+            for key in keys.iter_mut().filter(|k| k.wrapped_key.is_some()) {
+                let data_key = Self::unwrap_data_key(&old_master, *purpose, key)?;
+                let wrapped = seal(&new_master, &format!("{}:{}", purpose.as_str(), key.version), &data_key, &self.rng)?;
+                key.wrapped_key = Some(BASE64.encode(wrapped));
+            }
+        }
+        keyring.master_version += 1;
+        keyring.master_fingerprint = fingerprint(&new_master);
+
+        // Stage the new key first so a crash between the two writes can be recovered
+        self.store.store("next", &new_password)?;
+        self.save_keyring(&keyring)?;
+        self.store.store("current", &new_password)?;
+        self.store.delete("next")?;
+        Ok(keyring.master_version)
+    }
+
+    pub fn record_rotation(&self, record: KeyRotationRecord) -> Result<()> {
+        let mut keyring = self.load_keyring()?;
+        keyring.rotations.push(record);
+        self.save_keyring(&keyring)
+    }
+
+    pub fn status(&self) -> Result<EncryptionKeyStatus> {
+        let (keyring, _) = self.initialized_keyring()?;
+        let mut data_keys: Vec<DataKeyInfo> = keyring.data_keys.iter()
+            .flat_map(|(purpose, keys)| {
+                let active = keys.iter().map(|k| k.version).max().unwrap_or(0);
+                keys.iter().map(move |k| DataKeyInfo {
+                    purpose: *purpose,
+                    version: k.version,
+                    active: k.version == active,
+                    created_at: k.created_at.clone(),
+                    retired_at: k.retired_at.clone(),
+                })
+            })
+            .collect();
+        data_keys.sort_by_key(|k| (k.purpose.as_str(), k.version));
+
+        Ok(EncryptionKeyStatus {
+            master_backend: self.store.backend().to_string(),
+            master_version: keyring.master_version,
+            data_keys,
+            rotations: keyring.rotations,
+        })
     }
 }
 
@@ -202,4 +618,51 @@ mod tests {
         assert_eq!(storage.decrypt(&encrypted1, password).unwrap(), plaintext);
         assert_eq!(storage.decrypt(&encrypted2, password).unwrap(), plaintext);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_envelope_rotation_keeps_old_ciphertext_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let envelope = EnvelopeEncryption::new(dir.path().to_path_buf(), MasterKeyStore::File(dir.path().to_path_buf()));
+
+        let v1 = envelope.encrypt(KeyPurpose::ApiKeys, "sk-test-123").unwrap();
+        assert!(EnvelopeEncryption::is_envelope(&v1));
+        assert!(envelope.decrypt(KeyPurpose::OauthTokens, &v1).is_err());
+
+        let versions = envelope.rotate_data_keys().unwrap();
+        assert_eq!(versions[&KeyPurpose::ApiKeys], 2);
+        assert_eq!(envelope.rotate_master_key().unwrap(), 2);
+
+        // Old data key survives the master rotation until it is retired
+        assert_eq!(envelope.decrypt(KeyPurpose::ApiKeys, &v1).unwrap(), "sk-test-123");
+        let v2 = envelope.encrypt(KeyPurpose::ApiKeys, "sk-test-123").unwrap();
+        assert!(v2.starts_with("env1:api_keys:2:"));
+
+        assert_eq!(envelope.retire_inactive_data_keys().unwrap(), KeyPurpose::ALL.len());
+        assert!(envelope.decrypt(KeyPurpose::ApiKeys, &v1).is_err());
+        assert_eq!(envelope.decrypt(KeyPurpose::ApiKeys, &v2).unwrap(), "sk-test-123");
+
+        let status = envelope.status().unwrap();
+        assert_eq!(status.master_version, 2);
+        assert_eq!(status.data_keys.iter().filter(|k| k.active).count(), KeyPurpose::ALL.len());
+        assert!(!std::fs::read_to_string(dir.path().join(KEYRING_FILE)).unwrap().contains("sk-test"));
+    }
+
+    #[test]
+    fn test_interrupted_master_rotation_recovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MasterKeyStore::File(dir.path().to_path_buf());
+        let envelope = EnvelopeEncryption::new(dir.path().to_path_buf(), store.clone());
+        let ciphertext = envelope.encrypt(KeyPurpose::OauthTokens, "token").unwrap();
+
+        let old_master = store.load("current").unwrap().unwrap();
+        envelope.rotate_master_key().unwrap();
+        // Simulate a crash after the key ring was re-wrapped but before promotion
+        let new_master = store.load("current").unwrap().unwrap();
+        store.store("current", &old_master).unwrap();
+        store.store("next", &new_master).unwrap();
+
+        assert_eq!(envelope.decrypt(KeyPurpose::OauthTokens, &ciphertext).unwrap(), "token");
+        assert_eq!(store.load("current").unwrap().unwrap(), new_master);
+        assert!(store.load("next").unwrap().is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::State;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use super::commands::AIState;
+use super::security::{active_workspace, load_path_policy};
+
+/// How long a single `--version` probe may run
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Runtimes probed for the environment snapshot: (name, executable candidates)
+const RUNTIME_PROBES: [(&str, &[&str]); 6] = [
+    ("node", &["node"]),
+    ("npm", &["npm"]),
+    ("python", &["python3", "python"]),
+    ("rust", &["rustc"]),
+    ("cargo", &["cargo"]),
+    ("git", &["git"]),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeInfo {
+    pub name: String,
+    pub executable: Option<String>,
+    pub version: Option<String>,
+    pub available: bool,
+}
+
+/// Snapshot of the machine an agent is operating on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentContext {
+    pub os: String,
+    pub os_version: Option<String>,
+    pub arch: String,
+    pub shell: Option<String>,
+    pub runtimes: Vec<RuntimeInfo>,
+    pub working_directory: Option<String>,
+    pub workspace_roots: Vec<String>,
+    pub time_zone: Option<String>,
+    pub utc_offset: String,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Run `<executable> --version` with a cleared environment and a short timeout
+async fn probe_version(executable: &str) -> Option<String> {
+    let mut cmd = Command::new(executable);
+    cmd.arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .env_clear();
+    if let Ok(path) = std::env::var("PATH") {
+        cmd.env("PATH", path);
+    }
+
+    let output = tokio::time::timeout(PROBE_TIMEOUT, cmd.output()).await.ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Python 2 prints its version to stderr
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    String::from_utf8_lossy(&text).lines().next().map(|l| l.trim().to_string())
+}
+
+async fn probe_runtime(name: &str, candidates: &[&str]) -> RuntimeInfo {
+    for executable in candidates {
+        if let Some(version) = probe_version(executable).await {
+            return RuntimeInfo {
+                name: name.to_string(),
+                executable: Some(executable.to_string()),
+                version: Some(version),
+                available: true,
+            };
+        }
+    }
+
+    RuntimeInfo { name: name.to_string(), executable: None, version: None, available: false }
+}
+
+/// Human-readable OS release, e.g. "Ubuntu 22.04.3 LTS"
+fn os_version() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let release = std::fs::read_to_string("/etc/os-release").ok()?;
+        release.lines()
+            .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+            .map(|v| v.trim_matches('"').to_string())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+fn detect_shell() -> Option<String> {
+    std::env::var("SHELL").ok()
+        .or_else(|| std::env::var("ComSpec").ok())
+        .filter(|s| !s.is_empty())
+}
+
+/// IANA time zone name when the platform exposes one
+fn detect_time_zone() -> Option<String> {
+    if let Ok(tz) = std::env::var("TZ") {
+        if !tz.is_empty() {
+            return Some(tz.trim_start_matches(':').to_string());
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        if let Ok(target) = std::fs::read_link("/etc/localtime") {
+            let target = target.to_string_lossy();
+            if let Some((_, zone)) = target.split_once("zoneinfo/") {
+                return Some(zone.to_string());
+            }
+        }
+        if let Ok(zone) = std::fs::read_to_string("/etc/timezone") {
+            return Some(zone.trim().to_string()).filter(|z| !z.is_empty());
+        }
+    }
+
+    None
+}
+
+/// Report OS, shell, runtime versions, workspace roots and time zone for agent context
+#[tauri::command]
+pub async fn get_environment_context(
+    workspace: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<EnvironmentContext, String> {
+    info!("Collecting environment context");
+
+    let runtimes = futures::future::join_all(
+        RUNTIME_PROBES.iter().map(|(name, candidates)| probe_runtime(name, candidates))
+    ).await;
+
+    let workspace = workspace.unwrap_or_else(|| active_workspace(&state.storage));
+    let workspace_roots = match load_path_policy(&state.storage, &workspace) {
+        Ok(policy) => policy.resolved_roots().iter().map(|p| p.to_string_lossy().to_string()).collect(),
+        Err(e) => {
+            warn!("Failed to load path policy for {}: {}", workspace, e);
+            Vec::new()
+        }
+    };
+
+    Ok(EnvironmentContext {
+        os: std::env::consts::OS.to_string(),
+        os_version: os_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        shell: detect_shell(),
+        runtimes,
+        working_directory: std::env::current_dir().ok().map(|p| p.to_string_lossy().to_string()),
+        workspace_roots,
+        time_zone: detect_time_zone(),
+        utc_offset: chrono::Local::now().format("%:z").to_string(),
+        generated_at: chrono::Utc::now(),
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runtime_probe() {
+        let missing = probe_runtime("missing", &["definitely-not-a-real-binary"]).await;
+        assert!(!missing.available);
+        assert!(missing.version.is_none());
+        assert!(missing.executable.is_none());
+    }
+}
@@ -1,7 +1,12 @@
 use anyhow::{Result, Context};
 use std::collections::HashSet;
 use tracing::{warn, debug};
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use sha2::{Digest, Sha256};
+use std::sync::RwLock;
+use tauri::State;
+
+use super::{AIState, StorageManager};
 
 /// Error sanitization utilities to prevent information disclosure
 pub struct ErrorSanitizer {
@@ -124,6 +129,7 @@ impl ErrorSanitizer {
         for pattern in &self.sensitive_patterns {
             sanitized = pattern.replace_all(&sanitized, "[REDACTED]").to_string();
         }
+        sanitized = redact_secrets(&sanitized).0;
         
         // If the sanitized message is too different or empty, use a generic message
         if sanitized.len() < error_message.len() / 2 || sanitized.trim().is_empty() {
@@ -198,7 +204,7 @@ impl ErrorSanitizer {
             }
         }
         
-        sanitized
+        redact_secrets(&sanitized).0
     }
 }
 
@@ -223,6 +229,136 @@ pub fn has_sensitive_info(message: &str) -> bool {
     ERROR_SANITIZER.contains_sensitive_info(message)
 }
 
+/// Settings key holding user-defined secret patterns
+const SECRET_PATTERNS_SETTING: &str = "secret_redaction_patterns";
+
+/// Compiled size limit for user-defined patterns
+const MAX_SECRET_PATTERN_BYTES: usize = 1 << 20;
+
+lazy_static::lazy_static! {
+    /// Secret-looking text; the `secret` group is replaced and the rest kept for context
+    static ref SECRET_PATTERNS: Vec<Regex> = [
+        r#"(?i)\b(?:api[_-]?key|access[_-]?token|auth[_-]?token|client[_-]?secret|secret|password|passwd|token)["']?\s*[:=]\s*["']?(?P<secret>[^\s"',;]{8,})"#,
+        r"(?i)\bBearer\s+(?P<secret>[A-Za-z0-9._~+/=-]{16,})",
+        r"\b(?P<secret>sk-(?:ant-)?[A-Za-z0-9_-]{16,}|gh[pousr]_[A-Za-z0-9]{20,}|xox[abpr]-[A-Za-z0-9-]{10,}|AKIA[0-9A-Z]{16}|AIza[0-9A-Za-z_-]{35})\b",
+        r"(?P<secret>-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----)",
+        r"(?i)\b(?:https?|ftp|postgres(?:ql)?|mysql|mongodb(?:\+srv)?|redis)://[^:/\s@]+:(?P<secret>[^@\s]+)@",
+        r#"(?i)[?&](?:key|api[_-]?key|access[_-]?token|token|sig|signature)=(?P<secret>[^&\s#"']{8,})"#,
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .collect();
+
+    /// User-defined patterns, applied alongside the built-in ones
+    static ref CUSTOM_SECRET_PATTERNS: RwLock<Vec<Regex>> = RwLock::new(Vec::new());
+}
+
+/// Placeholder for a secret. The fingerprint is stable, so the same secret can be recognized
+/// across log lines and errors without being shown.
+pub fn secret_placeholder(secret: &str) -> String {
+    format!("[REDACTED:{}]", &hex::encode(Sha256::digest(secret.as_bytes()))[..8])
+}
+
+/// `text` with secret-looking values replaced by fingerprinted placeholders, and how many were
+pub fn redact_secrets(text: &str) -> (String, usize) {
+    let custom = CUSTOM_SECRET_PATTERNS.read().unwrap_or_else(|e| e.into_inner());
+    let mut spans: Vec<(usize, usize)> = SECRET_PATTERNS.iter()
+        .chain(custom.iter())
+        .flat_map(|pattern| pattern.captures_iter(text))
+        .filter_map(|caps| caps.name("secret").or_else(|| caps.get(0)))
+        .filter(|m| !m.is_empty())
+        .map(|m| (m.start(), m.end()))
+        .collect();
+    if spans.is_empty() {
+        return (text.to_string(), 0);
+    }
+
+    // Patterns can overlap (a key in a URL query is also a `key=value`); redact each span once
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start < last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    let mut redacted = String::with_capacity(text.len());
+    let mut copied = 0;
+    for &(start, end) in &merged {
+        redacted.push_str(&text[copied..start]);
+        redacted.push_str(&secret_placeholder(&text[start..end]));
+        copied = end;
+    }
+    redacted.push_str(&text[copied..]);
+    (redacted, merged.len())
+}
+
+/// An error message safe to return to the UI or store
+pub fn redact_error(error: impl std::fmt::Display) -> String {
+    redact_secrets(&format!("{:#}", error)).0
+}
+
+/// Command results with secrets redacted from their errors. Commands return errors as
+/// strings the UI shows and logs, so any error that can quote a request, URL, config or
+/// server output should go through here rather than `to_string` or `format!`.
+pub trait RedactErr<T> {
+    /// `context: error`, redacted
+    fn redact_err(self, context: &str) -> std::result::Result<T, String>;
+    /// The error alone, redacted
+    fn redacted(self) -> std::result::Result<T, String>;
+}
+
+impl<T, E: std::fmt::Display> RedactErr<T> for std::result::Result<T, E> {
+    fn redact_err(self, context: &str) -> std::result::Result<T, String> {
+        self.map_err(|e| format!("{}: {}", context, redact_error(e)))
+    }
+
+    fn redacted(self) -> std::result::Result<T, String> {
+        self.map_err(redact_error)
+    }
+}
+
+fn compile_secret_patterns(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns.iter()
+        .map(|pattern| RegexBuilder::new(pattern)
+            .size_limit(MAX_SECRET_PATTERN_BYTES)
+            .build()
+            .with_context(|| format!("Invalid secret pattern {}", pattern)))
+        .collect()
+}
+
+fn load_secret_patterns(storage: &StorageManager) -> Result<Vec<String>> {
+    match storage.get_setting(SECRET_PATTERNS_SETTING)? {
+        Some(value) => serde_json::from_value(value).context("Stored secret patterns are malformed"),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Install the user-defined secret patterns from settings
+pub fn reload_secret_patterns(storage: &StorageManager) -> Result<usize> {
+    let compiled = compile_secret_patterns(&load_secret_patterns(storage)?)?;
+    let count = compiled.len();
+    *CUSTOM_SECRET_PATTERNS.write().unwrap_or_else(|e| e.into_inner()) = compiled;
+    Ok(count)
+}
+
+/// Replace the user-defined secret patterns. A pattern's `secret` group, if it has one, is
+/// what gets redacted; otherwise the whole match is.
+#[tauri::command]
+pub async fn set_secret_patterns(patterns: Vec<String>, state: State<'_, AIState>) -> Result<(), String> {
+    let compiled = compile_secret_patterns(&patterns).map_err(|e| format!("{:#}", e))?;
+    let value = serde_json::to_value(&patterns).map_err(|e| e.to_string())?;
+    state.storage.set_setting(SECRET_PATTERNS_SETTING, value)
+        .map_err(|e| format!("Failed to store secret patterns: {}", e))?;
+    *CUSTOM_SECRET_PATTERNS.write().unwrap_or_else(|e| e.into_inner()) = compiled;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_secret_patterns(state: State<'_, AIState>) -> Result<Vec<String>, String> {
+    load_secret_patterns(&state.storage).map_err(|e| format!("Failed to load secret patterns: {}", e))
+}
+
 /// Macro for safe error logging
 #[macro_export]
 macro_rules! log_safe_error {
@@ -296,4 +432,33 @@ mod tests {
         assert!(log_safe.contains("[REDACTED]"));
         assert!(!log_safe.contains("user:pass"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_redact_secrets_with_fingerprints() {
+        let key = "sk-ant-REDACTED";
+        let text = format!(
+            "request to https://api.example.com/v1?key=AIzaSyA1234567890abcdefghijklmnopqrstu failed; \
+             header Authorization: Bearer {} and again {}",
+            key, key
+        );
+        let (redacted, count) = redact_secrets(&text);
+        assert_eq!(count, 3);
+        assert!(!redacted.contains("AIzaSy") && !redacted.contains(key));
+        assert_eq!(redacted.matches(&secret_placeholder(key)).count(), 2);
+        assert!(redacted.starts_with("request to https://api.example.com/v1?key=[REDACTED:"));
+        assert!(redacted.contains("header Authorization: Bearer [REDACTED:"));
+
+        assert_eq!(redact_secrets("No secrets here, token count 12"), ("No secrets here, token count 12".to_string(), 0));
+        assert_eq!(
+            redact_error(anyhow!("connect failed").context("postgres://admin:hunter2@db:5432")),
+            format!("postgres://admin:{}@db:5432: connect failed", secret_placeholder("hunter2"))
+        );
+        let failed: std::result::Result<(), _> = Err(anyhow!("GET https://api.example.com/?api_key=abcdef123456 failed"));
+        assert_eq!(
+            failed.redact_err("Failed to reach provider").unwrap_err(),
+            format!("Failed to reach provider: GET https://api.example.com/?api_key={} failed", secret_placeholder("abcdef123456"))
+        );
+        assert!(compile_secret_patterns(&["internal-(?P<secret>[a-z]+)".to_string()]).is_ok());
+        assert!(compile_secret_patterns(&["(unclosed".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,414 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::State;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::{info, warn};
+
+use super::commands::AIState;
+use super::disk_space::ensure_disk_space;
+use super::security::{enforce_path_policy, session_scoped_path, PathAccess};
+
+/// Largest chunk a single read or append may carry
+pub const MAX_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+/// Upper bound on concurrently open write streams
+const MAX_OPEN_STREAMS: usize = 16;
+
+/// Most bytes a single write stream may carry in total
+pub const MAX_STREAM_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Streams without an append for this long are discarded with their partial files
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChunk {
+    pub offset: u64,
+    /// Base64-encoded bytes
+    pub data: String,
+    pub length: usize,
+    pub file_size: u64,
+    pub eof: bool,
+    /// Hex SHA-256 of the decoded chunk bytes
+    pub checksum: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileStreamSummary {
+    pub path: String,
+    pub bytes_written: u64,
+    /// Hex SHA-256 of the complete file
+    pub checksum: String,
+}
+
+struct WriteStream {
+    target: PathBuf,
+    partial: PathBuf,
+    written: u64,
+    /// Size checked against free disk space at start; appends beyond it are checked individually
+    expected_size: u64,
+    hasher: Sha256,
+    last_activity: Instant,
+}
+
+/// Open chunked write streams keyed by stream id
+pub struct FileStreamState {
+    streams: Mutex<HashMap<String, WriteStream>>,
+}
+
+impl FileStreamState {
+    pub fn new() -> Self {
+        Self {
+            streams: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn take_stream(state: &FileStreamState, stream_id: &str) -> Result<WriteStream, String> {
+    state.streams.lock()
+        .map_err(|_| "Failed to acquire stream lock".to_string())?
+        .remove(stream_id)
+        .ok_or_else(|| format!("Unknown stream id: {}", stream_id))
+}
+
+fn put_stream(state: &FileStreamState, stream_id: String, stream: WriteStream) -> Result<(), String> {
+    state.streams.lock()
+        .map_err(|_| "Failed to acquire stream lock".to_string())?
+        .insert(stream_id, stream);
+    Ok(())
+}
+
+/// Drop streams idle past `STREAM_IDLE_TIMEOUT` and delete their partial files, so abandoned
+/// uploads neither hold a stream slot nor disk space
+async fn reap_idle_streams(state: &FileStreamState) -> Result<(), String> {
+    let idle: Vec<(String, PathBuf)> = {
+        let mut streams = state.streams.lock()
+            .map_err(|_| "Failed to acquire stream lock".to_string())?;
+        let expired: Vec<String> = streams.iter()
+            .filter(|(_, stream)| stream.last_activity.elapsed() >= STREAM_IDLE_TIMEOUT)
+            .map(|(id, _)| id.clone())
+            .collect();
+        expired.into_iter()
+            .filter_map(|id| streams.remove(&id).map(|stream| (id, stream.partial)))
+            .collect()
+    };
+
+    for (stream_id, partial) in idle {
+        warn!("Discarding idle write stream {}", stream_id);
+        let _ = tokio::fs::remove_file(&partial).await;
+    }
+    Ok(())
+}
+
+/// Partial file name of a stream: `.<file name>.<first 8 chars of the stream id>.partial`
+fn partial_file_name(file_name: &str, stream_id: &str) -> String {
+    format!(".{}.{}.partial", file_name, &stream_id[..8])
+}
+
+/// Whether `name` is a partial file left by a stream writing `file_name`
+fn is_partial_of(name: &str, file_name: &str) -> bool {
+    name.strip_prefix('.')
+        .and_then(|rest| rest.strip_prefix(file_name))
+        .and_then(|rest| rest.strip_prefix('.'))
+        .and_then(|rest| rest.strip_suffix(".partial"))
+        .is_some_and(|id| id.len() == 8 && id.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Delete partial files for `target` that no open stream owns and that haven't been written to
+/// within `STREAM_IDLE_TIMEOUT`, such as those left when the app exited mid-upload
+async fn remove_stale_partials(state: &FileStreamState, target: &std::path::Path) -> Result<usize, String> {
+    let (Some(dir), Some(file_name)) = (target.parent(), target.file_name().and_then(|n| n.to_str())) else {
+        return Ok(0);
+    };
+    let open: Vec<PathBuf> = state.streams.lock()
+        .map_err(|_| "Failed to acquire stream lock".to_string())?
+        .values()
+        .map(|stream| stream.partial.clone())
+        .collect();
+
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else { return Ok(0) };
+    let mut removed = 0;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let is_stale_partial = entry.file_name().to_str().is_some_and(|name| is_partial_of(name, file_name))
+            && !open.contains(&path)
+            && entry.metadata().await.ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age >= STREAM_IDLE_TIMEOUT);
+        if is_stale_partial && tokio::fs::remove_file(&path).await.is_ok() {
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        info!("Removed {} stale partial files for {}", removed, target.display());
+    }
+    Ok(removed)
+}
+
+/// Read up to `length` bytes starting at `offset`
+#[tauri::command]
+pub async fn read_file_chunk(
+    path: String,
+    offset: u64,
+    length: usize,
+    workspace: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<FileChunk, String> {
+    if length == 0 || length > MAX_CHUNK_BYTES {
+        return Err(format!("Chunk length must be between 1 and {} bytes", MAX_CHUNK_BYTES));
+    }
+
+    let scoped = session_scoped_path(session_id.as_deref(), &path)?;
+    let resolved = enforce_path_policy(&state.storage, workspace.as_deref(), &scoped, PathAccess::Chunked)?;
+    let mut file = tokio::fs::File::open(&resolved).await
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+    let file_size = file.metadata().await
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .len();
+
+    if offset > file_size {
+        return Err(format!("Offset {} is past the end of the file ({} bytes)", offset, file_size));
+    }
+
+    file.seek(std::io::SeekFrom::Start(offset)).await
+        .map_err(|e| format!("Failed to seek: {}", e))?;
+
+    let to_read = length.min((file_size - offset) as usize);
+    let mut buffer = vec![0u8; to_read];
+    file.read_exact(&mut buffer).await
+        .map_err(|e| format!("Failed to read chunk: {}", e))?;
+
+    Ok(FileChunk {
+        offset,
+        length: buffer.len(),
+        file_size,
+        eof: offset + buffer.len() as u64 >= file_size,
+        checksum: sha256_hex(&buffer),
+        data: BASE64.encode(&buffer),
+    })
+}
+
+/// Begin a chunked write; data goes to a partial file until the stream is finished
+#[tauri::command]
+pub async fn write_file_stream_start(
+    path: String,
+    overwrite: bool,
+    expected_size: Option<u64>,
+    workspace: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AIState>,
+    stream_state: State<'_, FileStreamState>,
+) -> Result<String, String> {
+    info!("Starting chunked write: {}", path);
+
+    if expected_size.is_some_and(|size| size > MAX_STREAM_BYTES) {
+        return Err(format!("File exceeds the {} byte stream limit", MAX_STREAM_BYTES));
+    }
+
+    let scoped = session_scoped_path(session_id.as_deref(), &path)?;
+    let target = enforce_path_policy(&state.storage, workspace.as_deref(), &scoped, PathAccess::Chunked)?;
+    if target.exists() && !overwrite {
+        return Err(format!("File already exists: {}", path));
+    }
+    if let Some(size) = expected_size {
+        ensure_disk_space(&target, size, "file write").map_err(|e| e.to_string())?;
+    }
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await
+            .map_err(|e| format!("Failed to create directories: {}", e))?;
+    }
+
+    reap_idle_streams(&stream_state).await?;
+    remove_stale_partials(&stream_state, &target).await?;
+
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let file_name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let partial = target.with_file_name(partial_file_name(&file_name, &stream_id));
+
+    tokio::fs::File::create(&partial).await
+        .map_err(|e| format!("Failed to create partial file: {}", e))?;
+
+    let open_streams = stream_state.streams.lock()
+        .map_err(|_| "Failed to acquire stream lock".to_string())?
+        .len();
+    if open_streams >= MAX_OPEN_STREAMS {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(format!("Too many open write streams (limit: {})", MAX_OPEN_STREAMS));
+    }
+
+    put_stream(&stream_state, stream_id.clone(), WriteStream {
+        target,
+        partial,
+        written: 0,
+        expected_size: expected_size.unwrap_or(0),
+        hasher: Sha256::new(),
+        last_activity: Instant::now(),
+    })?;
+    Ok(stream_id)
+}
+
+/// Append a chunk; `offset` must equal the bytes written so far
+#[tauri::command]
+pub async fn write_file_stream_append(
+    stream_id: String,
+    offset: u64,
+    data: String,
+    checksum: Option<String>,
+    stream_state: State<'_, FileStreamState>,
+) -> Result<u64, String> {
+    let bytes = BASE64.decode(data.as_bytes())
+        .map_err(|_| "Chunk data is not valid base64".to_string())?;
+    if bytes.len() > MAX_CHUNK_BYTES {
+        return Err(format!("Chunk exceeds {} bytes", MAX_CHUNK_BYTES));
+    }
+    if let Some(expected) = checksum {
+        if !expected.eq_ignore_ascii_case(&sha256_hex(&bytes)) {
+            return Err("Chunk checksum mismatch".to_string());
+        }
+    }
+
+    reap_idle_streams(&stream_state).await?;
+
+    // The stream is taken out of the map while writing so concurrent appends cannot interleave
+    let mut stream = take_stream(&stream_state, &stream_id)?;
+    stream.last_activity = Instant::now();
+    if offset != stream.written {
+        let expected = stream.written;
+        put_stream(&stream_state, stream_id, stream)?;
+        return Err(format!("Out-of-order chunk: expected offset {}, got {}", expected, offset));
+    }
+
+    let end = stream.written + bytes.len() as u64;
+    if end > MAX_STREAM_BYTES {
+        put_stream(&stream_state, stream_id, stream)?;
+        return Err(format!("File exceeds the {} byte stream limit", MAX_STREAM_BYTES));
+    }
+    if end > stream.expected_size {
+        let unchecked = end - stream.written.max(stream.expected_size);
+        if let Err(e) = ensure_disk_space(&stream.partial, unchecked, "file write") {
+            put_stream(&stream_state, stream_id, stream)?;
+            return Err(e.to_string());
+        }
+    }
+
+    let result = async {
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(&stream.partial).await?;
+        file.write_all(&bytes).await?;
+        file.flush().await
+    }.await;
+
+    if let Err(e) = result {
+        put_stream(&stream_state, stream_id, stream)?;
+        return Err(format!("Failed to write chunk: {}", e));
+    }
+
+    stream.hasher.update(&bytes);
+    stream.written += bytes.len() as u64;
+    let written = stream.written;
+    put_stream(&stream_state, stream_id, stream)?;
+    Ok(written)
+}
+
+/// Verify the whole-file checksum (when given) and move the partial file into place
+#[tauri::command]
+pub async fn write_file_stream_finish(
+    stream_id: String,
+    expected_checksum: Option<String>,
+    stream_state: State<'_, FileStreamState>,
+) -> Result<FileStreamSummary, String> {
+    let stream = take_stream(&stream_state, &stream_id)?;
+    let checksum = hex::encode(stream.hasher.finalize());
+
+    if let Some(expected) = expected_checksum {
+        if !expected.eq_ignore_ascii_case(&checksum) {
+            warn!("Checksum mismatch finishing stream {}", stream_id);
+            let _ = tokio::fs::remove_file(&stream.partial).await;
+            return Err("File checksum mismatch; stream discarded".to_string());
+        }
+    }
+
+    tokio::fs::rename(&stream.partial, &stream.target).await
+        .map_err(|e| format!("Failed to finalize file: {}", e))?;
+
+    info!("Chunked write finished: {} ({} bytes)", stream.target.display(), stream.written);
+    Ok(FileStreamSummary {
+        path: stream.target.to_string_lossy().to_string(),
+        bytes_written: stream.written,
+        checksum,
+    })
+}
+
+/// Discard an unfinished write stream
+#[tauri::command]
+pub async fn write_file_stream_abort(
+    stream_id: String,
+    stream_state: State<'_, FileStreamState>,
+) -> Result<(), String> {
+    let stream = take_stream(&stream_state, &stream_id)?;
+    let _ = tokio::fs::remove_file(&stream.partial).await;
+    info!("Chunked write aborted: {}", stream.target.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incremental_checksum_matches_whole_file() {
+        let chunks: [&[u8]; 3] = [b"hello ", b"chunked ", b"world"];
+        let mut hasher = Sha256::new();
+        for chunk in chunks {
+            hasher.update(chunk);
+        }
+        assert_eq!(hex::encode(hasher.finalize()), sha256_hex(b"hello chunked world"));
+        assert_eq!(BASE64.decode(BASE64.encode(b"\x00\xff")).unwrap(), b"\x00\xff");
+    }
+
+    #[tokio::test]
+    async fn test_idle_streams_and_stale_partials_are_collected() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("upload.bin");
+        let state = FileStreamState::new();
+
+        let idle_partial = dir.path().join(partial_file_name("upload.bin", "aaaaaaaa-idle"));
+        std::fs::write(&idle_partial, b"abandoned").unwrap();
+        put_stream(&state, "aaaaaaaa-idle".to_string(), WriteStream {
+            target: target.clone(),
+            partial: idle_partial.clone(),
+            written: 9,
+            expected_size: 0,
+            hasher: Sha256::new(),
+            last_activity: Instant::now() - STREAM_IDLE_TIMEOUT,
+        }).unwrap();
+        reap_idle_streams(&state).await.unwrap();
+        assert!(state.streams.lock().unwrap().is_empty());
+        assert!(!idle_partial.exists());
+
+        // Left by an earlier run: stale ones go, recent ones may belong to a writer still running
+        let stale = dir.path().join(partial_file_name("upload.bin", "bbbbbbbb"));
+        let recent = dir.path().join(partial_file_name("upload.bin", "cccccccc"));
+        let unrelated = dir.path().join(".other.bin.dddddddd.partial");
+        for path in [&stale, &recent, &unrelated] {
+            std::fs::write(path, b"x").unwrap();
+        }
+        let old = SystemTime::now() - STREAM_IDLE_TIMEOUT - Duration::from_secs(1);
+        for path in [&stale, &unrelated] {
+            std::fs::File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+        }
+
+        assert_eq!(remove_stale_partials(&state, &target).await.unwrap(), 1);
+        assert!(!stale.exists());
+        assert!(recent.exists() && unrelated.exists());
+    }
+}
@@ -0,0 +1,268 @@
+use notify::{event::ModifyKind, EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tracing::{info, warn, error};
+
+use super::commands::AIState;
+use super::security::{active_workspace, enforce_path_policy, load_path_policy, session_scoped_path, PathAccess, PathPolicy};
+
+/// Upper bound on concurrent watches so a misbehaving agent cannot exhaust inotify handles
+const MAX_ACTIVE_WATCHES: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchOptions {
+    #[serde(default = "default_recursive")]
+    pub recursive: bool,
+    /// Deepest directory level (relative to the root) that reports events
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+    /// Workspace whose path policy the root is checked against
+    #[serde(default)]
+    pub workspace: Option<String>,
+    /// Secure session whose workspace root relative paths resolve against
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+fn default_recursive() -> bool {
+    true
+}
+
+fn default_debounce_ms() -> u64 {
+    250
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            recursive: default_recursive(),
+            max_depth: None,
+            debounce_ms: default_debounce_ms(),
+            workspace: None,
+            session_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Renamed,
+    Deleted,
+}
+
+/// Payload emitted on `file_watch_<watch_id>` for each debounced change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChangeEvent {
+    pub watch_id: String,
+    pub kind: FileChangeKind,
+    pub paths: Vec<String>,
+}
+
+struct ActiveWatch {
+    root: PathBuf,
+    // Dropping the debouncer stops the watcher thread
+    _debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+}
+
+/// Active file watches keyed by watch id
+pub struct FileWatcherState {
+    watches: Mutex<HashMap<String, ActiveWatch>>,
+}
+
+impl FileWatcherState {
+    pub fn new() -> Self {
+        Self {
+            watches: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn classify(kind: &EventKind) -> Option<FileChangeKind> {
+    match kind {
+        EventKind::Create(_) => Some(FileChangeKind::Created),
+        EventKind::Modify(ModifyKind::Name(_)) => Some(FileChangeKind::Renamed),
+        EventKind::Modify(_) => Some(FileChangeKind::Modified),
+        EventKind::Remove(_) => Some(FileChangeKind::Deleted),
+        _ => None,
+    }
+}
+
+/// Number of directory levels between the watch root and a changed path
+fn depth_below(root: &Path, path: &Path) -> Option<usize> {
+    path.strip_prefix(root).ok().map(|rel| rel.components().count())
+}
+
+/// Paths under the watch root can still match a deny pattern (`.env`, `*.pem`),
+/// so each changed path is checked against the policy before it is reported
+fn to_change_events(
+    watch_id: &str,
+    root: &Path,
+    max_depth: Option<usize>,
+    policy: &PathPolicy,
+    result: DebounceEventResult,
+) -> Vec<FileChangeEvent> {
+    let events = match result {
+        Ok(events) => events,
+        Err(errors) => {
+            for e in errors {
+                warn!("File watch {} error: {}", watch_id, e);
+            }
+            return Vec::new();
+        }
+    };
+
+    events.into_iter()
+        .filter_map(|event| {
+            let kind = classify(&event.kind)?;
+            let paths: Vec<String> = event.paths.iter()
+                .filter(|p| match (max_depth, depth_below(root, p)) {
+                    (Some(max), Some(depth)) => depth <= max,
+                    _ => true,
+                })
+                .map(|p| p.to_string_lossy().to_string())
+                .filter(|p| policy.check(p, PathAccess::List).is_ok())
+                .collect();
+
+            (!paths.is_empty()).then(|| FileChangeEvent {
+                watch_id: watch_id.to_string(),
+                kind,
+                paths,
+            })
+        })
+        .collect()
+}
+
+/// Start watching a path; changes are emitted as `file_watch_<watch_id>` events
+#[tauri::command]
+pub async fn watch_path_command(
+    path: String,
+    options: Option<WatchOptions>,
+    app: AppHandle,
+    state: State<'_, AIState>,
+    watcher_state: State<'_, FileWatcherState>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    info!("Watching path: {} (recursive: {})", path, options.recursive);
+
+    let scoped = session_scoped_path(options.session_id.as_deref(), &path)?;
+    let root = enforce_path_policy(&state.storage, options.workspace.as_deref(), &scoped, PathAccess::List)?;
+    if !root.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+
+    let mut watches = watcher_state.watches.lock()
+        .map_err(|_| "Failed to acquire watcher lock".to_string())?;
+    if watches.len() >= MAX_ACTIVE_WATCHES {
+        return Err(format!("Too many active watches (limit: {})", MAX_ACTIVE_WATCHES));
+    }
+
+    let watch_id = uuid::Uuid::new_v4().to_string();
+    let event_name = format!("file_watch_{}", watch_id);
+    let handler_id = watch_id.clone();
+    let handler_root = root.clone();
+    let max_depth = options.max_depth;
+    let workspace = options.workspace.clone().unwrap_or_else(|| active_workspace(&state.storage));
+    let policy = load_path_policy(&state.storage, &workspace).map_err(|e| {
+        error!("Failed to load path policy for {}: {}", workspace, e);
+        "Failed to load path policy".to_string()
+    })?;
+
+    let mut debouncer = new_debouncer(
+        Duration::from_millis(options.debounce_ms.max(10)),
+        None,
+        move |result: DebounceEventResult| {
+            for change in to_change_events(&handler_id, &handler_root, max_depth, &policy, result) {
+                if let Err(e) = app.emit(&event_name, &change) {
+                    error!("Failed to emit file watch event: {}", e);
+                }
+            }
+        },
+    ).map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    let mode = if options.recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    debouncer.watch(&root, mode)
+        .map_err(|e| format!("Failed to watch {}: {}", path, e))?;
+
+    watches.insert(watch_id.clone(), ActiveWatch { root, _debouncer: debouncer });
+    info!("File watch {} started", watch_id);
+    Ok(watch_id)
+}
+
+#[tauri::command]
+pub async fn unwatch_path_command(
+    watch_id: String,
+    watcher_state: State<'_, FileWatcherState>,
+) -> Result<(), String> {
+    let mut watches = watcher_state.watches.lock()
+        .map_err(|_| "Failed to acquire watcher lock".to_string())?;
+
+    match watches.remove(&watch_id) {
+        Some(watch) => {
+            info!("File watch {} on {} stopped", watch_id, watch.root.display());
+            Ok(())
+        }
+        None => Err(format!("Unknown watch id: {}", watch_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, RemoveKind, RenameMode};
+    use notify_debouncer_full::DebouncedEvent;
+    use std::time::Instant;
+
+    fn debounced(kind: EventKind, path: &str) -> DebouncedEvent {
+        DebouncedEvent::new(notify::Event::new(kind).add_path(PathBuf::from(path)), Instant::now())
+    }
+
+    fn open_policy() -> PathPolicy {
+        PathPolicy {
+            workspace: "test".to_string(),
+            allowed_roots: vec!["/".to_string()],
+            denied_patterns: vec![".env".to_string(), "*.pem".to_string()],
+            max_file_size: 1024,
+        }
+    }
+
+    #[test]
+    fn test_event_classification_and_depth() {
+        let root = Path::new("/workspace");
+        let events = vec![
+            debounced(EventKind::Create(CreateKind::File), "/workspace/a.txt"),
+            debounced(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), "/workspace/b.txt"),
+            debounced(EventKind::Remove(RemoveKind::File), "/workspace/deep/nested/c.txt"),
+            debounced(EventKind::Access(notify::event::AccessKind::Read), "/workspace/a.txt"),
+        ];
+
+        let changes = to_change_events("w1", root, Some(2), &open_policy(), Ok(events));
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, FileChangeKind::Created);
+        assert_eq!(changes[1].kind, FileChangeKind::Renamed);
+        assert!(changes.iter().all(|c| c.watch_id == "w1"));
+    }
+
+    #[test]
+    fn test_denied_paths_are_not_reported() {
+        let root = Path::new("/workspace");
+        let events = vec![
+            debounced(EventKind::Create(CreateKind::File), "/workspace/.env"),
+            debounced(EventKind::Modify(ModifyKind::Any), "/workspace/certs/server.pem"),
+            debounced(EventKind::Create(CreateKind::File), "/workspace/notes.txt"),
+        ];
+
+        let changes = to_change_events("w1", root, None, &open_policy(), Ok(events));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].paths, vec!["/workspace/notes.txt".to_string()]);
+    }
+}
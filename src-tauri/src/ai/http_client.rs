@@ -1,9 +1,16 @@
 use reqwest::{Client, Method, Response};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::Duration;
-use tracing::{info, error};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn, error};
 use anyhow::{Result, Context};
+use super::StorageManager;
+
+pub const HTTP_CLIENT_SETTING_KEY: &str = "http_client.config";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HttpRequest {
@@ -11,6 +18,13 @@ pub struct HttpRequest {
     pub method: String,
     pub headers: Option<HashMap<String, String>>,
     pub body: Option<String>,
+    /// Overrides the client's default retry policy for this request
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    /// Serve GET requests from the response cache while fresh, revalidating with ETag/Last-Modified.
+    /// Off unless asked for; requests carrying `Authorization` are never cached.
+    #[serde(default)]
+    pub use_cache: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,10 +32,520 @@ pub struct HttpResponse {
     pub status: u16,
     pub headers: HashMap<String, String>,
     pub body: String,
+    #[serde(default)]
+    pub retry: RetryMetadata,
+    #[serde(default)]
+    pub from_cache: bool,
+}
+
+/// How failed requests are retried
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub backoff_multiplier: f64,
+    /// Response statuses that trigger a retry
+    pub retry_on_status: Vec<u16>,
+    /// Also retry POST and PATCH after timeouts and server errors, which may have been
+    /// processed. Off by default: those methods are only retried when the request provably
+    /// wasn't handled (connection refused, 429).
+    #[serde(default)]
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 250,
+            max_backoff_ms: 8_000,
+            backoff_multiplier: 2.0,
+            retry_on_status: vec![408, 429, 500, 502, 503, 504],
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether a failed attempt may be retried: a response with `status`, or a transport error
+    /// that did (`connected`) or didn't reach the server
+    fn allows_retry(&self, method: &Method, status: Option<u16>, connected: bool) -> bool {
+        let idempotent = !matches!(*method, Method::POST | Method::PATCH);
+        match status {
+            Some(status) => self.retry_on_status.contains(&status)
+                && (idempotent || self.retry_non_idempotent || status == 429),
+            None => !connected || idempotent || self.retry_non_idempotent,
+        }
+    }
+
+    /// Delay before retry number `retry` (1-based), with up to 20% jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let base = self.initial_backoff_ms as f64 * self.backoff_multiplier.max(1.0).powi(retry.saturating_sub(1) as i32);
+        let capped = base.min(self.max_backoff_ms as f64);
+        let jitter = capped * 0.2 * rand::random::<f64>();
+        Duration::from_millis((capped - jitter) as u64)
+    }
+}
+
+/// What happened on the way to a response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetryMetadata {
+    pub attempts: u32,
+    /// Status or transport error for each failed attempt
+    pub failures: Vec<String>,
+    pub total_backoff_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open a host's circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit rejects requests before a trial request is allowed
+    pub open_duration_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration_ms: 30_000,
+        }
+    }
+}
+
+/// Retry and circuit breaker settings for the shared HTTP client, kept in app settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HttpClientConfig {
+    /// Policy for requests that don't set their own
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Policies for model provider requests by provider name, e.g. `openai`
+    #[serde(default)]
+    pub provider_retry_policies: HashMap<String, RetryPolicy>,
+}
+
+impl HttpClientConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        for policy in std::iter::once(&self.retry_policy).chain(self.provider_retry_policies.values()) {
+            if !(1..=10).contains(&policy.max_attempts) {
+                return Err("Retry attempts must be between 1 and 10".to_string());
+            }
+            if policy.backoff_multiplier < 1.0 || policy.initial_backoff_ms > policy.max_backoff_ms {
+                return Err("Backoff must not shrink and must start at or below its maximum".to_string());
+            }
+        }
+        if self.circuit_breaker.failure_threshold == 0 {
+            return Err("Circuit breaker failure threshold must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn load_http_client_config(storage: &StorageManager) -> Result<HttpClientConfig> {
+    match storage.get_setting(HTTP_CLIENT_SETTING_KEY)? {
+        Some(value) => serde_json::from_value(value).context("Stored HTTP client config is malformed"),
+        None => Ok(HttpClientConfig::default()),
+    }
+}
+
+pub fn save_http_client_config(storage: &StorageManager, config: &HttpClientConfig) -> Result<()> {
+    storage.set_setting(HTTP_CLIENT_SETTING_KEY, serde_json::to_value(config)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    /// One trial request is in flight since `since`; others are rejected until it reports back
+    HalfOpen { since: Instant },
+}
+
+/// Per-host circuit breaker
+#[derive(Debug)]
+struct CircuitBreaker {
+    config: RwLock<CircuitBreakerConfig>,
+    hosts: Mutex<HashMap<String, CircuitState>>,
+}
+
+impl CircuitBreaker {
+    fn new(config: CircuitBreakerConfig) -> Self {
+        Self { config: RwLock::new(config), hosts: Mutex::new(HashMap::new()) }
+    }
+
+    fn config(&self) -> CircuitBreakerConfig {
+        self.config.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Applies to the next check or failure; circuits already open keep their deadline
+    fn set_config(&self, config: CircuitBreakerConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config;
+    }
+
+    /// Err with the remaining open time when the host's circuit rejects requests
+    fn check(&self, host: &str, now: Instant) -> std::result::Result<(), Duration> {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let open_duration = Duration::from_millis(self.config().open_duration_ms);
+        match hosts.get(host).copied() {
+            Some(CircuitState::Open { until }) if until > now => Err(until - now),
+            // A trial that never reported back (e.g. a dropped request) doesn't block the host forever
+            Some(CircuitState::HalfOpen { since }) if now.duration_since(since) < open_duration => {
+                Err(open_duration - now.duration_since(since))
+            }
+            Some(CircuitState::Open { .. } | CircuitState::HalfOpen { .. }) => {
+                hosts.insert(host.to_string(), CircuitState::HalfOpen { since: now });
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record_success(&self, host: &str) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        hosts.remove(host);
+    }
+
+    fn record_failure(&self, host: &str, now: Instant) {
+        let config = self.config();
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let open = CircuitState::Open { until: now + Duration::from_millis(config.open_duration_ms) };
+        let next = match hosts.get(host).copied().unwrap_or(CircuitState::Closed { failures: 0 }) {
+            CircuitState::Closed { failures } if failures + 1 < config.failure_threshold => {
+                CircuitState::Closed { failures: failures + 1 }
+            }
+            CircuitState::Open { until } => CircuitState::Open { until },
+            _ => {
+                warn!("Circuit opened for host {}", host);
+                open
+            }
+        };
+        hosts.insert(host.to_string(), next);
+    }
+}
+
+/// Largest response body kept in the cache
+const MAX_CACHE_ENTRY_BYTES: usize = 5 * 1024 * 1024;
+
+/// Oldest entries are evicted beyond this many cached responses
+const MAX_CACHE_ENTRIES: usize = 1000;
+
+#[derive(Debug, Default, PartialEq)]
+struct CacheDirectives {
+    no_store: bool,
+    no_cache: bool,
+    /// The response is meant for one user only
+    private: bool,
+    max_age: Option<i64>,
+}
+
+/// Header `name` of a request, matched case-insensitively
+fn request_header<'a>(headers: Option<&'a HashMap<String, String>>, name: &str) -> Option<&'a str> {
+    headers?.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Headers that identify who is asking, so responses to different credentials never share an entry
+fn is_credential_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    matches!(name.as_str(), "authorization" | "proxy-authorization" | "cookie")
+        || ["api-key", "apikey", "token", "secret", "auth"].iter().any(|marker| name.contains(marker))
+}
+
+/// Responses to requests with `Authorization` are specific to the caller and never cached
+fn is_authorized_request(headers: Option<&HashMap<String, String>>) -> bool {
+    request_header(headers, "authorization").is_some() || request_header(headers, "proxy-authorization").is_some()
+}
+
+fn parse_cache_control(headers: &HashMap<String, String>) -> CacheDirectives {
+    let mut directives = CacheDirectives::default();
+    let Some(value) = headers.get("cache-control") else { return directives };
+
+    for directive in value.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+        match directive.as_str() {
+            "no-store" => directives.no_store = true,
+            "no-cache" | "must-revalidate" => directives.no_cache = true,
+            d if d == "private" || d.starts_with("private=") => directives.private = true,
+            _ => {
+                if let Some(age) = directive.strip_prefix("max-age=") {
+                    directives.max_age = age.trim_matches('"').parse().ok();
+                }
+            }
+        }
+    }
+    directives
+}
+
+/// Expiry from `Cache-Control: max-age`, falling back to the `Expires` header
+fn expiry_from_headers(headers: &HashMap<String, String>, directives: &CacheDirectives, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some(max_age) = directives.max_age {
+        return Some(now + chrono::Duration::seconds(max_age.max(0)));
+    }
+    headers.get("expires")
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// A stored response plus what is needed to decide freshness and revalidate it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub key: String,
+    pub url: String,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub stored_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Set by `no-cache`: always revalidate before use
+    pub revalidate: bool,
+    /// Request values of the headers named by the response's `Vary`; the entry only serves
+    /// requests with the same values
+    #[serde(default)]
+    pub vary: BTreeMap<String, Option<String>>,
+}
+
+impl CachedResponse {
+    /// Build a cache entry when the response allows storing and can be reused or revalidated
+    fn from_response(
+        key: &str,
+        url: &str,
+        request_headers: Option<&HashMap<String, String>>,
+        response: &HttpResponse,
+        now: DateTime<Utc>,
+    ) -> Option<Self> {
+        let directives = parse_cache_control(&response.headers);
+        if response.status != 200 || directives.no_store || directives.private || response.body.len() > MAX_CACHE_ENTRY_BYTES {
+            return None;
+        }
+        if is_authorized_request(request_headers) {
+            return None;
+        }
+        let vary_names: Vec<String> = response.headers.get("vary")
+            .map(|v| v.split(',').map(|name| name.trim().to_ascii_lowercase()).filter(|name| !name.is_empty()).collect())
+            .unwrap_or_default();
+        if vary_names.iter().any(|name| name == "*") {
+            return None;
+        }
+        let vary = vary_names.into_iter()
+            .map(|name| {
+                let value = request_header(request_headers, &name).map(str::to_string);
+                (name, value)
+            })
+            .collect();
+
+        let expires_at = expiry_from_headers(&response.headers, &directives, now);
+        let etag = response.headers.get("etag").cloned();
+        let last_modified = response.headers.get("last-modified").cloned();
+        if expires_at.is_none() && etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            key: key.to_string(),
+            url: url.to_string(),
+            status: response.status,
+            headers: response.headers.clone(),
+            body: response.body.clone(),
+            etag,
+            last_modified,
+            stored_at: now,
+            expires_at,
+            revalidate: directives.no_cache,
+            vary,
+        })
+    }
+
+    /// Whether the request sends the same values for every header the response varies on
+    fn matches_request(&self, request_headers: Option<&HashMap<String, String>>) -> bool {
+        self.vary.iter().all(|(name, value)| request_header(request_headers, name) == value.as_deref())
+    }
+
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        !self.revalidate && self.expires_at.map_or(false, |expires| expires > now)
+    }
+
+    fn conditional_headers(&self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if let Some(ref etag) = self.etag {
+            headers.push(("If-None-Match".to_string(), etag.clone()));
+        }
+        if let Some(ref modified) = self.last_modified {
+            headers.push(("If-Modified-Since".to_string(), modified.clone()));
+        }
+        headers
+    }
+
+    /// Refresh freshness after a 304 Not Modified
+    fn revalidated(mut self, headers: &HashMap<String, String>, now: DateTime<Utc>) -> Self {
+        let directives = parse_cache_control(headers);
+        self.expires_at = expiry_from_headers(headers, &directives, now);
+        self.revalidate = directives.no_cache;
+        if let Some(etag) = headers.get("etag") {
+            self.etag = Some(etag.clone());
+        }
+        self.stored_at = now;
+        self
+    }
+
+    fn to_response(&self) -> HttpResponse {
+        HttpResponse {
+            status: self.status,
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+            retry: RetryMetadata::default(),
+            from_cache: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpCacheEntry {
+    pub key: String,
+    pub url: String,
+    pub status: u16,
+    pub size_bytes: usize,
+    pub stored_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub etag: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpCacheStats {
+    pub directory: String,
+    pub entries: usize,
+    pub total_bytes: u64,
+}
+
+/// On-disk response cache, one JSON file per entry
+pub struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(&dir).context("Failed to create HTTP cache directory")?;
+        Ok(Self { dir })
+    }
+
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(dirs::data_dir()
+            .context("Failed to get data directory")?
+            .join("banshee")
+            .join("http_cache"))
+    }
+
+    /// Cache key: SHA-256 over method, URL, body and the request's credential headers
+    pub fn key(method: &str, url: &str, body: Option<&str>, headers: Option<&HashMap<String, String>>) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(method.to_uppercase().as_bytes());
+        hasher.update(b"\n");
+        hasher.update(url.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(body.unwrap_or("").as_bytes());
+
+        let credentials: BTreeMap<String, &str> = headers.into_iter()
+            .flatten()
+            .filter(|(name, _)| is_credential_header(name))
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.as_str()))
+            .collect();
+        for (name, value) in credentials {
+            hasher.update(b"\n");
+            hasher.update(name.as_bytes());
+            hasher.update(b":");
+            hasher.update(value.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn entry_files(&self) -> Vec<(PathBuf, std::fs::Metadata)> {
+        std::fs::read_dir(&self.dir)
+            .map(|entries| entries.filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().map_or(false, |ext| ext == "json"))
+                .filter_map(|p| std::fs::metadata(&p).ok().map(|m| (p, m)))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let content = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn put(&self, entry: &CachedResponse) -> Result<()> {
+        let content = serde_json::to_string(entry).context("Failed to serialize cache entry")?;
+        std::fs::write(self.entry_path(&entry.key), content).context("Failed to write cache entry")?;
+
+        let mut files = self.entry_files();
+        if files.len() > MAX_CACHE_ENTRIES {
+            files.sort_by_key(|(_, m)| m.modified().ok());
+            for (path, _) in files.iter().take(files.len() - MAX_CACHE_ENTRIES) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Vec<HttpCacheEntry> {
+        let mut entries: Vec<HttpCacheEntry> = self.entry_files().into_iter()
+            .filter_map(|(path, _)| std::fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str::<CachedResponse>(&content).ok())
+            .map(|e| HttpCacheEntry {
+                size_bytes: e.body.len(),
+                key: e.key,
+                url: e.url,
+                status: e.status,
+                stored_at: e.stored_at,
+                expires_at: e.expires_at,
+                etag: e.etag,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.stored_at.cmp(&a.stored_at));
+        entries
+    }
+
+    /// Remove all entries, or only those whose URL starts with `url_prefix`
+    pub fn clear(&self, url_prefix: Option<&str>) -> Result<usize> {
+        let mut removed = 0;
+        for (path, _) in self.entry_files() {
+            if let Some(prefix) = url_prefix {
+                let matches = std::fs::read_to_string(&path).ok()
+                    .and_then(|c| serde_json::from_str::<CachedResponse>(&c).ok())
+                    .map_or(false, |e| e.url.starts_with(prefix));
+                if !matches {
+                    continue;
+                }
+            }
+            std::fs::remove_file(&path).context("Failed to remove cache entry")?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    pub fn stats(&self) -> HttpCacheStats {
+        let files = self.entry_files();
+        HttpCacheStats {
+            directory: self.dir.to_string_lossy().to_string(),
+            entries: files.len(),
+            total_bytes: files.iter().map(|(_, m)| m.len()).sum(),
+        }
+    }
 }
 
 pub struct HttpClientManager {
     client: Client,
+    retry_policies: RwLock<HttpClientConfig>,
+    breaker: CircuitBreaker,
+    cache: Option<HttpCache>,
 }
 
 impl HttpClientManager {
@@ -32,13 +556,102 @@ impl HttpClientManager {
             .build()
             .context("Failed to create HTTP client")?;
 
+        // The cache is optional; requests still work without it
+        let cache = HttpCache::default_dir()
+            .and_then(HttpCache::new)
+            .map_err(|e| warn!("HTTP response cache disabled: {}", e))
+            .ok();
+
         info!("HTTP client manager initialized");
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            retry_policies: RwLock::new(HttpClientConfig::default()),
+            breaker: CircuitBreaker::new(CircuitBreakerConfig::default()),
+            cache,
+        })
+    }
+
+    pub fn cache(&self) -> Option<&HttpCache> {
+        self.cache.as_ref()
+    }
+
+    pub fn with_config(self, config: HttpClientConfig) -> Self {
+        self.set_config(config);
+        self
+    }
+
+    pub fn config(&self) -> HttpClientConfig {
+        self.retry_policies.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the retry policies and breaker settings for requests made from now on
+    pub fn set_config(&self, config: HttpClientConfig) {
+        self.breaker.set_config(config.circuit_breaker.clone());
+        *self.retry_policies.write().unwrap_or_else(|e| e.into_inner()) = config;
+    }
+
+    /// Policy configured for a model provider's requests, if any
+    pub fn provider_retry_policy(&self, provider: &str) -> Option<RetryPolicy> {
+        self.retry_policies.read().unwrap_or_else(|e| e.into_inner())
+            .provider_retry_policies.get(provider).cloned()
     }
 
     pub async fn make_request(&self, request: HttpRequest) -> Result<HttpResponse> {
         info!("Making HTTP request to: {} {}", request.method, request.url);
 
+        let cache = self.cache.as_ref().filter(|_| {
+            request.use_cache
+                && request.method.eq_ignore_ascii_case("GET")
+                && !is_authorized_request(request.headers.as_ref())
+        });
+        let Some(cache) = cache else {
+            return self.send_with_retry(&request, &[]).await;
+        };
+
+        let key = HttpCache::key(&request.method, &request.url, request.body.as_deref(), request.headers.as_ref());
+        let cached = cache.get(&key).filter(|entry| entry.matches_request(request.headers.as_ref()));
+        if let Some(ref entry) = cached {
+            if entry.is_fresh(Utc::now()) {
+                info!("HTTP cache hit: {}", request.url);
+                return Ok(entry.to_response());
+            }
+        }
+
+        let conditional = cached.as_ref().map(|e| e.conditional_headers()).unwrap_or_default();
+        let response = self.send_with_retry(&request, &conditional).await?;
+
+        if response.status == 304 {
+            if let Some(entry) = cached {
+                info!("HTTP cache revalidated: {}", request.url);
+                let entry = entry.revalidated(&response.headers, Utc::now());
+                if let Err(e) = cache.put(&entry) {
+                    warn!("Failed to update HTTP cache entry: {}", e);
+                }
+                let mut revalidated = entry.to_response();
+                revalidated.retry = response.retry;
+                return Ok(revalidated);
+            }
+        }
+
+        if let Some(entry) = CachedResponse::from_response(&key, &request.url, request.headers.as_ref(), &response, Utc::now()) {
+            if let Err(e) = cache.put(&entry) {
+                warn!("Failed to store HTTP cache entry: {}", e);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Send a request under the retry policy and circuit breaker, adding `extra_headers`
+    async fn send_with_retry(&self, request: &HttpRequest, extra_headers: &[(String, String)]) -> Result<HttpResponse> {
+        let host = url::Url::parse(&request.url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .ok_or_else(|| anyhow::anyhow!("Invalid URL: {}", request.url))?;
+        let policy = request.retry_policy.clone()
+            .unwrap_or_else(|| self.retry_policies.read().unwrap_or_else(|e| e.into_inner()).retry_policy.clone());
+        let max_attempts = policy.max_attempts.max(1);
+        let mut metadata = RetryMetadata::default();
+
         let method = match request.method.to_uppercase().as_str() {
             "GET" => Method::GET,
             "POST" => Method::POST,
@@ -53,26 +666,78 @@ impl HttpClientManager {
             }
         };
 
-        let mut req_builder = self.client.request(method, &request.url);
+        loop {
+            if let Err(remaining) = self.breaker.check(&host, Instant::now()) {
+                return Err(anyhow::anyhow!(
+                    "Circuit open for host {}; retry in {}s", host, remaining.as_secs().max(1)
+                ));
+            }
+
+            metadata.attempts += 1;
+            let mut req_builder = self.client.request(method.clone(), &request.url);
 
-        // Add headers if provided
-        if let Some(headers) = request.headers {
-            for (key, value) in headers {
-                req_builder = req_builder.header(&key, &value);
+            // Add headers if provided
+            if let Some(ref headers) = request.headers {
+                for (key, value) in headers {
+                    req_builder = req_builder.header(key, value);
+                }
+            }
+            for (key, value) in extra_headers {
+                req_builder = req_builder.header(key, value);
             }
-        }
 
-        // Add body if provided
-        if let Some(body) = request.body {
-            req_builder = req_builder.body(body);
-        }
+            // Add body if provided
+            if let Some(ref body) = request.body {
+                req_builder = req_builder.body(body.clone());
+            }
 
-        let response = req_builder
-            .send()
-            .await
-            .context("Failed to send HTTP request")?;
+            let retry_after = match req_builder.send().await {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    if !policy.allows_retry(&method, Some(status), true) || metadata.attempts >= max_attempts {
+                        // Only server-side failures count against the host's circuit
+                        if response.status().is_server_error() {
+                            self.breaker.record_failure(&host, Instant::now());
+                        } else {
+                            self.breaker.record_success(&host);
+                        }
+                        let mut http_response = self.response_to_http_response(response).await?;
+                        http_response.retry = metadata;
+                        return Ok(http_response);
+                    }
 
-        self.response_to_http_response(response).await
+                    if response.status().is_server_error() {
+                        self.breaker.record_failure(&host, Instant::now());
+                    }
+                    metadata.failures.push(format!("HTTP {}", status));
+                    response.headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs)
+                }
+                Err(e) => {
+                    self.breaker.record_failure(&host, Instant::now());
+                    let retryable = (e.is_connect() || e.is_timeout())
+                        && policy.allows_retry(&method, None, !e.is_connect());
+                    if metadata.attempts >= max_attempts || !retryable {
+                        return Err(anyhow::Error::new(e).context(format!(
+                            "Failed to send HTTP request after {} attempt(s)", metadata.attempts
+                        )));
+                    }
+                    metadata.failures.push(e.to_string());
+                    None
+                }
+            };
+
+            let delay = retry_after
+                .map(|d| d.min(Duration::from_millis(policy.max_backoff_ms)))
+                .unwrap_or_else(|| policy.backoff(metadata.attempts));
+            warn!("Retrying {} {} in {}ms (attempt {}/{})",
+                request.method, request.url, delay.as_millis(), metadata.attempts + 1, max_attempts);
+            metadata.total_backoff_ms += delay.as_millis() as u64;
+            tokio::time::sleep(delay).await;
+        }
     }
 
     async fn response_to_http_response(&self, response: Response) -> Result<HttpResponse> {
@@ -100,6 +765,8 @@ impl HttpClientManager {
             status,
             headers,
             body,
+            retry: RetryMetadata::default(),
+            from_cache: false,
         })
     }
 
@@ -163,4 +830,171 @@ impl HttpClientManager {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = RetryPolicy { initial_backoff_ms: 100, max_backoff_ms: 1000, ..Default::default() };
+        let first = policy.backoff(1).as_millis();
+        let third = policy.backoff(3).as_millis();
+        assert!((80..=100).contains(&first));
+        assert!((320..=400).contains(&third));
+        assert!(policy.backoff(10).as_millis() <= 1000);
+    }
+
+    #[test]
+    fn test_cache_entry_freshness_and_revalidation() {
+        let now = Utc::now();
+        let response = |headers: &[(&str, &str)]| HttpResponse {
+            status: 200,
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: "{}".to_string(),
+            retry: RetryMetadata::default(),
+            from_cache: false,
+        };
+
+        let fresh = CachedResponse::from_response("k", "https://api.example.com/a", None, &response(&[("cache-control", "public, max-age=60")]), now).unwrap();
+        assert!(fresh.is_fresh(now));
+        assert!(!fresh.is_fresh(now + chrono::Duration::seconds(61)));
+
+        let tagged = CachedResponse::from_response("k", "u", None, &response(&[("cache-control", "no-cache"), ("etag", "\"v1\"")]), now).unwrap();
+        assert!(!tagged.is_fresh(now));
+        assert_eq!(tagged.conditional_headers(), vec![("If-None-Match".to_string(), "\"v1\"".to_string())]);
+        let refreshed = tagged.revalidated(&[("cache-control".to_string(), "max-age=10".to_string())].into_iter().collect(), now);
+        assert!(refreshed.is_fresh(now));
+
+        assert!(CachedResponse::from_response("k", "u", None, &response(&[("cache-control", "no-store, max-age=60")]), now).is_none());
+        assert!(CachedResponse::from_response("k", "u", None, &response(&[]), now).is_none());
+    }
+
+    #[test]
+    fn test_private_authorized_and_varying_responses() {
+        let now = Utc::now();
+        let response = |headers: &[(&str, &str)]| HttpResponse {
+            status: 200,
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: "{}".to_string(),
+            retry: RetryMetadata::default(),
+            from_cache: false,
+        };
+        let request = |headers: &[(&str, &str)]| -> HashMap<String, String> {
+            headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+
+        assert!(CachedResponse::from_response("k", "u", None, &response(&[("cache-control", "private, max-age=60")]), now).is_none());
+        let authorized = request(&[("Authorization", "Bearer sk-1")]);
+        assert!(CachedResponse::from_response("k", "u", Some(&authorized), &response(&[("cache-control", "max-age=60")]), now).is_none());
+
+        let english = request(&[("Accept-Language", "en")]);
+        let entry = CachedResponse::from_response("k", "u", Some(&english), &response(&[("cache-control", "max-age=60"), ("vary", "Accept-Language")]), now).unwrap();
+        assert!(entry.matches_request(Some(&request(&[("accept-language", "en")]))));
+        assert!(!entry.matches_request(Some(&request(&[("Accept-Language", "fr")]))));
+        assert!(!entry.matches_request(None));
+    }
+
+    #[test]
+    fn test_http_cache_store_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HttpCache::new(dir.path().to_path_buf()).unwrap();
+        let key = HttpCache::key("get", "https://api.example.com/a", None, None);
+        assert_eq!(key, HttpCache::key("GET", "https://api.example.com/a", None, None));
+        assert_ne!(key, HttpCache::key("GET", "https://api.example.com/a", Some("x"), None));
+        let api_key = |value: &str| HashMap::from([("X-Api-Key".to_string(), value.to_string())]);
+        assert_ne!(key, HttpCache::key("GET", "https://api.example.com/a", None, Some(&api_key("one"))));
+        assert_ne!(
+            HttpCache::key("GET", "https://api.example.com/a", None, Some(&api_key("one"))),
+            HttpCache::key("GET", "https://api.example.com/a", None, Some(&api_key("two")))
+        );
+        let accept = HashMap::from([("Accept".to_string(), "text/html".to_string())]);
+        assert_eq!(key, HttpCache::key("GET", "https://api.example.com/a", None, Some(&accept)));
+
+        let response = HttpResponse {
+            status: 200,
+            headers: [("etag".to_string(), "abc".to_string())].into_iter().collect(),
+            body: "hello".to_string(),
+            retry: RetryMetadata::default(),
+            from_cache: false,
+        };
+        let entry = CachedResponse::from_response(&key, "https://api.example.com/a", None, &response, Utc::now()).unwrap();
+        cache.put(&entry).unwrap();
+
+        assert_eq!(cache.get(&key).unwrap().body, "hello");
+        assert_eq!(cache.entries()[0].size_bytes, 5);
+        assert_eq!(cache.stats().entries, 1);
+        assert_eq!(cache.clear(Some("https://other.example.com")).unwrap(), 0);
+        assert_eq!(cache.clear(Some("https://api.example.com")).unwrap(), 1);
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[test]
+    fn test_non_idempotent_requests_retry_only_when_unprocessed() {
+        let policy = RetryPolicy::default();
+        assert!(policy.allows_retry(&Method::GET, Some(503), true));
+        assert!(policy.allows_retry(&Method::GET, None, true));
+        assert!(!policy.allows_retry(&Method::POST, Some(503), true));
+        assert!(!policy.allows_retry(&Method::POST, None, true));
+        assert!(policy.allows_retry(&Method::POST, Some(429), true));
+        assert!(policy.allows_retry(&Method::POST, None, false));
+        assert!(!policy.allows_retry(&Method::GET, Some(404), true));
+
+        let opted_in = RetryPolicy { retry_non_idempotent: true, ..Default::default() };
+        assert!(opted_in.allows_retry(&Method::PATCH, Some(503), true));
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_and_recovers() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig { failure_threshold: 2, open_duration_ms: 1000 });
+        let now = Instant::now();
+
+        breaker.record_failure("api.example.com", now);
+        assert!(breaker.check("api.example.com", now).is_ok());
+        breaker.record_failure("api.example.com", now);
+        assert!(breaker.check("api.example.com", now).is_err());
+        assert!(breaker.check("other.example.com", now).is_ok());
+
+        // After the open window one trial request goes through; a failure reopens immediately
+        let later = now + Duration::from_millis(1500);
+        assert!(breaker.check("api.example.com", later).is_ok());
+        assert!(breaker.check("api.example.com", later).is_err(), "only one trial request while half-open");
+        breaker.record_failure("api.example.com", later);
+        assert!(breaker.check("api.example.com", later).is_err());
+
+        let recovered = later + Duration::from_millis(1500);
+        assert!(breaker.check("api.example.com", recovered).is_ok());
+        breaker.record_success("api.example.com");
+        breaker.record_failure("api.example.com", recovered);
+        assert!(breaker.check("api.example.com", recovered).is_ok());
+    }
+
+    #[test]
+    fn test_config_updates_policies_and_breaker() {
+        let manager = HttpClientManager::new().unwrap();
+        assert!(manager.provider_retry_policy("openai").is_none());
+
+        let config: HttpClientConfig = serde_json::from_value(serde_json::json!({
+            "circuit_breaker": { "failure_threshold": 1, "open_duration_ms": 1000 },
+            "provider_retry_policies": {
+                "openai": { "max_attempts": 5, "initial_backoff_ms": 500, "max_backoff_ms": 20000,
+                            "backoff_multiplier": 2.0, "retry_on_status": [429, 529] }
+            }
+        })).unwrap();
+        assert!(config.validate().is_ok());
+        let manager = manager.with_config(config);
+
+        assert_eq!(manager.config().retry_policy.max_attempts, RetryPolicy::default().max_attempts);
+        assert_eq!(manager.provider_retry_policy("openai").unwrap().retry_on_status, vec![429, 529]);
+        let now = Instant::now();
+        manager.breaker.record_failure("api.openai.com", now);
+        assert!(manager.breaker.check("api.openai.com", now).is_err());
+
+        let invalid = HttpClientConfig {
+            retry_policy: RetryPolicy { max_attempts: 0, ..Default::default() },
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
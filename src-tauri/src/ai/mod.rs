@@ -1,5 +1,6 @@
 pub mod commands;
 pub mod security;
+pub mod rate_limiter;
 pub mod security_middleware;
 pub mod storage;
 pub mod http_client;
@@ -9,9 +10,27 @@ pub mod csrf;
 pub mod command_whitelist;
 pub mod error_sanitization;
 pub mod secure_commands;
+pub mod permissions;
+pub mod sandbox;
+pub mod summarizer;
+pub mod file_watcher;
+pub mod workspace_search;
+pub mod patch_tools;
+pub mod environment;
+pub mod file_streams;
+pub mod run_planner;
+pub mod websocket_client;
+pub mod notifications;
+pub mod response_validation;
+pub mod disk_space;
+pub mod provider_completion;
+pub mod orchestration;
+pub mod structured_output;
+pub mod guardrails;
 
 pub use commands::*;
 pub use security::*;
+pub use rate_limiter::*;
 pub use security_middleware::*;
 pub use storage::*;
 pub use http_client::*;
@@ -21,6 +40,23 @@ pub use csrf::*;
 pub use command_whitelist::*;
 pub use error_sanitization::*;
 pub use secure_commands::*;
+pub use permissions::*;
+pub use sandbox::*;
+pub use summarizer::*;
+pub use file_watcher::*;
+pub use workspace_search::*;
+pub use patch_tools::*;
+pub use environment::*;
+pub use file_streams::*;
+pub use run_planner::*;
+pub use websocket_client::*;
+pub use notifications::*;
+pub use response_validation::*;
+pub use disk_space::*;
+pub use provider_completion::*;
+pub use orchestration::*;
+pub use structured_output::*;
+pub use guardrails::*;
 
 // #[cfg(test)]
 // mod tests; // Commented out due to import issues
\ No newline at end of file
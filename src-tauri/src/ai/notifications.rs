@@ -0,0 +1,377 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+use tracing::{info, error};
+
+use super::commands::AIState;
+
+/// Notifications awaiting an action are forgotten after this long
+const PENDING_TTL_HOURS: i64 = 24;
+
+/// Upper bound on tracked notifications; the oldest are dropped first
+const MAX_PENDING: usize = 200;
+
+/// Longest quick-reply accepted into an agent session
+const MAX_REPLY_CHARS: usize = 4000;
+
+/// Event carrying a routed quick-reply into the agent runtime
+pub const AGENT_SESSION_MESSAGE_EVENT: &str = "agent_session_message";
+
+/// Event for button actions and replies the OS could not collect inline
+pub const NOTIFICATION_ACTION_EVENT: &str = "notification_action";
+
+/// Event asking the frontend to render a notification the OS could not show
+pub const NOTIFICATION_FALLBACK_EVENT: &str = "notification_fallback";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationActionKind {
+    Button,
+    Reply,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationAction {
+    pub id: String,
+    pub label: String,
+    pub kind: NotificationActionKind,
+    pub placeholder: Option<String>,
+}
+
+/// Agent session a notification originated from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSession {
+    pub agent_id: String,
+    pub session_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationCapabilities {
+    pub platform: String,
+    pub native: bool,
+    pub actions: bool,
+    /// Whether replies can be typed directly into the OS notification
+    pub quick_reply: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPayload {
+    pub notification_id: String,
+    pub title: String,
+    pub message: String,
+    pub kind: String,
+    pub actions: Vec<NotificationAction>,
+    pub session: Option<NotificationSession>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationActionEvent {
+    pub notification_id: String,
+    pub action_id: String,
+    pub session: Option<NotificationSession>,
+    /// Set when a reply action was clicked but the OS could not collect the text
+    pub reply_requested: bool,
+}
+
+/// A user message routed into an agent session from outside the main window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSessionMessage {
+    pub agent_id: String,
+    pub session_id: String,
+    pub role: String,
+    pub content: String,
+    pub source: String,
+    pub notification_id: String,
+    pub received_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingNotification {
+    actions: Vec<NotificationAction>,
+    session: Option<NotificationSession>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+type PendingMap = Arc<Mutex<HashMap<String, PendingNotification>>>;
+
+/// Notifications with actions that have not been answered yet
+pub struct NotificationState {
+    pending: PendingMap,
+}
+
+impl NotificationState {
+    pub fn new() -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+pub fn notification_capabilities() -> NotificationCapabilities {
+    let (native, actions, quick_reply) = if cfg!(target_os = "macos") {
+        (true, true, true)
+    } else if cfg!(target_os = "linux") {
+        // XDG servers show action buttons, but notify-rust cannot read inline replies
+        (true, true, false)
+    } else {
+        (false, false, false)
+    };
+
+    NotificationCapabilities {
+        platform: std::env::consts::OS.to_string(),
+        native,
+        actions,
+        quick_reply,
+    }
+}
+
+fn track(pending: &PendingMap, notification_id: &str, entry: PendingNotification) {
+    let Ok(mut pending) = pending.lock() else { return };
+    let cutoff = chrono::Utc::now() - chrono::Duration::hours(PENDING_TTL_HOURS);
+    pending.retain(|_, p| p.created_at > cutoff);
+
+    while pending.len() >= MAX_PENDING {
+        let oldest = pending.iter().min_by_key(|(_, p)| p.created_at).map(|(id, _)| id.clone());
+        match oldest {
+            Some(id) => { pending.remove(&id); }
+            None => break,
+        }
+    }
+    pending.insert(notification_id.to_string(), entry);
+}
+
+/// Turn a reply into a user message for the notification's originating session
+fn build_session_message(
+    notification_id: &str,
+    pending: &PendingNotification,
+    action_id: &str,
+    reply: &str,
+) -> Result<AgentSessionMessage, String> {
+    let action = pending.actions.iter()
+        .find(|a| a.id == action_id)
+        .ok_or_else(|| format!("Unknown notification action: {}", action_id))?;
+    if action.kind != NotificationActionKind::Reply {
+        return Err(format!("Action '{}' does not accept a reply", action_id));
+    }
+    let session = pending.session.as_ref()
+        .ok_or_else(|| "Notification is not linked to an agent session".to_string())?;
+
+    let content: String = reply.replace('\0', "").trim().chars().take(MAX_REPLY_CHARS).collect();
+    if content.is_empty() {
+        return Err("Reply is empty".to_string());
+    }
+
+    Ok(AgentSessionMessage {
+        agent_id: session.agent_id.clone(),
+        session_id: session.session_id.clone(),
+        role: "user".to_string(),
+        content,
+        source: "notification".to_string(),
+        notification_id: notification_id.to_string(),
+        received_at: chrono::Utc::now(),
+    })
+}
+
+fn route_reply(
+    app: &AppHandle,
+    pending: &PendingMap,
+    notification_id: &str,
+    action_id: &str,
+    reply: &str,
+) -> Result<AgentSessionMessage, String> {
+    let mut map = pending.lock().map_err(|_| "Failed to acquire notification lock".to_string())?;
+    let entry = map.get(notification_id)
+        .ok_or_else(|| format!("Unknown or expired notification: {}", notification_id))?;
+    let message = build_session_message(notification_id, entry, action_id, reply)?;
+    map.remove(notification_id);
+    drop(map);
+
+    app.emit(AGENT_SESSION_MESSAGE_EVENT, &message)
+        .map_err(|e| format!("Failed to route reply: {}", e))?;
+    info!("Routed notification reply into session {} of agent {}", message.session_id, message.agent_id);
+    Ok(message)
+}
+
+/// Report a button click, or a reply action whose text the frontend must collect
+fn route_action(app: &AppHandle, pending: &PendingMap, notification_id: &str, action_id: &str) {
+    let entry = pending.lock().ok().and_then(|map| map.get(notification_id).cloned());
+    let Some(entry) = entry else { return };
+    let reply_requested = entry.actions.iter()
+        .any(|a| a.id == action_id && a.kind == NotificationActionKind::Reply);
+
+    if !reply_requested {
+        if let Ok(mut map) = pending.lock() {
+            map.remove(notification_id);
+        }
+    }
+
+    let event = NotificationActionEvent {
+        notification_id: notification_id.to_string(),
+        action_id: action_id.to_string(),
+        session: entry.session,
+        reply_requested,
+    };
+    if let Err(e) = app.emit(NOTIFICATION_ACTION_EVENT, &event) {
+        error!("Failed to emit notification action: {}", e);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn show_native(app: AppHandle, pending: PendingMap, payload: &NotificationPayload) -> Result<(), String> {
+    let mut notification = notify_rust::Notification::new();
+    notification.appname("Banshee").summary(&payload.title).body(&payload.message);
+    for action in &payload.actions {
+        notification.action(&action.id, &action.label);
+    }
+
+    let handle = notification.show().map_err(|e| e.to_string())?;
+    if payload.actions.is_empty() {
+        return Ok(());
+    }
+
+    // wait_for_action blocks until the notification is answered or dismissed
+    let notification_id = payload.notification_id.clone();
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if action == "__closed" {
+                if let Ok(mut map) = pending.lock() {
+                    map.remove(&notification_id);
+                }
+            } else {
+                route_action(&app, &pending, &notification_id, action);
+            }
+        });
+    });
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn show_native(app: AppHandle, pending: PendingMap, payload: &NotificationPayload) -> Result<(), String> {
+    use mac_notification_sys::{MainButton, Notification, NotificationResponse};
+
+    let payload = payload.clone();
+    // send() blocks until the user interacts with the notification
+    std::thread::spawn(move || {
+        let reply_action = payload.actions.iter().find(|a| a.kind == NotificationActionKind::Reply);
+        let buttons: Vec<&str> = payload.actions.iter()
+            .filter(|a| a.kind == NotificationActionKind::Button)
+            .map(|a| a.label.as_str())
+            .collect();
+
+        let mut notification = Notification::new();
+        notification.title(&payload.title).message(&payload.message);
+        if let Some(action) = reply_action {
+            notification.main_button(MainButton::Response(action.placeholder.as_deref().unwrap_or(&action.label)));
+        } else if buttons.len() == 1 {
+            notification.main_button(MainButton::SingleAction(buttons[0]));
+        } else if !buttons.is_empty() {
+            notification.main_button(MainButton::DropdownActions("Actions", &buttons));
+        }
+
+        match notification.send() {
+            Ok(NotificationResponse::Reply(text)) => {
+                if let Some(action) = reply_action {
+                    if let Err(e) = route_reply(&app, &pending, &payload.notification_id, &action.id, &text) {
+                        tracing::warn!("Dropped notification reply: {}", e);
+                    }
+                }
+            }
+            Ok(NotificationResponse::ActionButton(label)) => {
+                if let Some(action) = payload.actions.iter().find(|a| a.label == label) {
+                    route_action(&app, &pending, &payload.notification_id, &action.id);
+                }
+            }
+            Ok(_) => {
+                if let Ok(mut map) = pending.lock() {
+                    map.remove(&payload.notification_id);
+                }
+            }
+            Err(e) => tracing::warn!("macOS notification failed: {}", e),
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn show_native(_app: AppHandle, _pending: PendingMap, _payload: &NotificationPayload) -> Result<(), String> {
+    Err("Native notifications are not supported on this platform".to_string())
+}
+
+/// Show a notification natively, falling back to an in-app notification the frontend renders
+pub fn dispatch_notification(
+    app: &AppHandle,
+    notification_state: &NotificationState,
+    payload: NotificationPayload,
+) {
+    if !payload.actions.is_empty() {
+        track(&notification_state.pending, &payload.notification_id, PendingNotification {
+            actions: payload.actions.clone(),
+            session: payload.session.clone(),
+            created_at: chrono::Utc::now(),
+        });
+    }
+
+    if let Err(e) = show_native(app.clone(), notification_state.pending.clone(), &payload) {
+        info!("Using in-app notification fallback: {}", e);
+        if let Err(e) = app.emit(NOTIFICATION_FALLBACK_EVENT, &payload) {
+            error!("Failed to emit notification fallback: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_notification_capabilities() -> Result<NotificationCapabilities, String> {
+    Ok(notification_capabilities())
+}
+
+/// Submit a quick-reply collected by the frontend (in-app fallback or a reply the OS could not capture)
+#[tauri::command]
+pub async fn notification_reply_command(
+    notification_id: String,
+    action_id: String,
+    reply: String,
+    app: AppHandle,
+    state: State<'_, AIState>,
+    notification_state: State<'_, NotificationState>,
+) -> Result<AgentSessionMessage, String> {
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "notification_operations",
+        &[reply],
+        &[]
+    ).await?;
+
+    route_reply(&app, &notification_state.pending, &notification_id, &action_id, &validation_result.sanitized_inputs[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(session: Option<NotificationSession>) -> PendingNotification {
+        PendingNotification {
+            actions: vec![
+                NotificationAction { id: "reply".into(), label: "Reply".into(), kind: NotificationActionKind::Reply, placeholder: None },
+                NotificationAction { id: "dismiss".into(), label: "Dismiss".into(), kind: NotificationActionKind::Button, placeholder: None },
+            ],
+            session,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_reply_becomes_session_user_message() {
+        let session = NotificationSession { agent_id: "agent-1".into(), session_id: "s-9".into() };
+        let message = build_session_message("n1", &pending(Some(session)), "reply", "  yes, go ahead \0").unwrap();
+        assert_eq!(message.role, "user");
+        assert_eq!(message.content, "yes, go ahead");
+        assert_eq!(message.session_id, "s-9");
+        assert_eq!(message.source, "notification");
+
+        assert!(build_session_message("n1", &pending(None), "reply", "hi").is_err());
+        let linked = pending(Some(NotificationSession { agent_id: "a".into(), session_id: "s".into() }));
+        assert!(build_session_message("n1", &linked, "dismiss", "hi").is_err());
+        assert!(build_session_message("n1", &linked, "reply", "   ").is_err());
+    }
+}
@@ -0,0 +1,551 @@
+//! Multi-agent workflows: a DAG of agent steps whose outputs feed later steps' prompts.
+//! Steps whose dependencies are done run concurrently as task-queue jobs, so a failed completion
+//! is retried, each result is kept as a task memory of the step's agent, and progress is
+//! streamed to the UI as events. Runs are operations, so they report overall progress and can be
+//! cancelled.
+
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Instant;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tracing::{error, info, warn};
+
+use super::guardrails::GuardrailViolation;
+use super::provider_completion::select_completion_provider;
+use super::{AIState, Agent, StorageManager};
+use crate::database::agent_sessions::load_agent;
+use crate::database::memory::{AgentMemory, MemoryType};
+use crate::database::notification_center::{notify_quietly, NewNotification, NotificationSeverity};
+use crate::database::open_conversation_db;
+use crate::database::prompt_templates::{check_variables, find_template};
+use crate::database::simple_commands::{store_memory, MemoryState};
+use crate::database::task_queue::{submit_and_wait, LlmJobOutput, TaskJob};
+use crate::operations::{start_operation, OperationHandle};
+
+/// Event emitted with a `WorkflowStepEvent` as each step starts, completes or fails
+pub const WORKFLOW_STEP_EVENT: &str = "agent_workflow_step";
+
+/// Event emitted with the `WorkflowRunResult` once a run ends
+pub const WORKFLOW_FINISHED_EVENT: &str = "agent_workflow_finished";
+
+const WORKFLOWS_SETTING_KEY: &str = "agent_workflows";
+
+/// Step outputs longer than this are truncated before being stored as memories
+const MAX_MEMORY_CHARS: usize = 10_000;
+
+/// Queue priority of step completions, which a run is waiting on, above background jobs
+const STEP_PRIORITY: i64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkflowStep {
+    pub id: String,
+    pub agent_id: String,
+    /// Prompt template; `{{name}}` is replaced by the `inputs` entry of that name
+    #[serde(default)]
+    pub prompt: String,
+    /// Stored prompt template used instead of `prompt`; `inputs` must cover exactly its variables
+    #[serde(default)]
+    pub prompt_template: Option<String>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Template variable -> source: `input`, `input.<field>` or `steps.<step id>.output`
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkflowDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<WorkflowStep>,
+    /// Step whose output is the workflow's result; defaults to the last step to run
+    #[serde(default)]
+    pub output_step: Option<String>,
+}
+
+impl WorkflowDefinition {
+    /// Check the definition and group its steps into layers; every step's dependencies
+    /// are in earlier layers, so the steps of one layer can run concurrently
+    pub fn layers(&self) -> Result<Vec<Vec<&WorkflowStep>>> {
+        if self.id.trim().is_empty() {
+            bail!("Workflow id is required");
+        }
+        if self.steps.is_empty() {
+            bail!("Workflow {} has no steps", self.id);
+        }
+
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if step.id.trim().is_empty() || step.agent_id.trim().is_empty() {
+                bail!("Every workflow step needs an id and an agent_id");
+            }
+            if !ids.insert(step.id.as_str()) {
+                bail!("Duplicate workflow step: {}", step.id);
+            }
+            if step.prompt.trim().is_empty() && step.prompt_template.is_none() {
+                bail!("Step {} needs a prompt or a prompt_template", step.id);
+            }
+        }
+        for step in &self.steps {
+            if let Some(dep) = step.depends_on.iter().find(|dep| !ids.contains(dep.as_str())) {
+                bail!("Step {} depends on unknown step {}", step.id, dep);
+            }
+            for source in step.inputs.values() {
+                if let Some(source_step) = source_step(source)? {
+                    if !step.depends_on.iter().any(|dep| dep == source_step) {
+                        bail!("Step {} reads {} without depending on {}", step.id, source, source_step);
+                    }
+                }
+            }
+        }
+        if let Some(output) = self.output_step.as_deref().filter(|output| !ids.contains(output)) {
+            bail!("Output step {} is not part of the workflow", output);
+        }
+
+        let mut done: HashSet<&str> = HashSet::new();
+        let mut layers = Vec::new();
+        while done.len() < self.steps.len() {
+            let layer: Vec<&WorkflowStep> = self.steps.iter()
+                .filter(|step| !done.contains(step.id.as_str()))
+                .filter(|step| step.depends_on.iter().all(|dep| done.contains(dep.as_str())))
+                .collect();
+            if layer.is_empty() {
+                bail!("Workflow {} has a dependency cycle", self.id);
+            }
+            done.extend(layer.iter().map(|step| step.id.as_str()));
+            layers.push(layer);
+        }
+        Ok(layers)
+    }
+}
+
+/// The step an input source reads from, if it reads a step output
+fn source_step(source: &str) -> Result<Option<&str>> {
+    if source == "input" || source.starts_with("input.") {
+        return Ok(None);
+    }
+    source.strip_prefix("steps.")
+        .and_then(|rest| rest.strip_suffix(".output"))
+        .filter(|step| !step.is_empty())
+        .map(Some)
+        .ok_or_else(|| anyhow!("Invalid input source: {}", source))
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn resolve_source(source: &str, input: &Value, outputs: &HashMap<String, String>) -> Result<String> {
+    if let Some(step) = source_step(source)? {
+        return outputs.get(step).cloned().ok_or_else(|| anyhow!("Step {} has no output", step));
+    }
+    match source.strip_prefix("input.") {
+        Some(field) => input.pointer(&format!("/{}", field.replace('.', "/")))
+            .map(value_text)
+            .ok_or_else(|| anyhow!("Workflow input has no field {}", field)),
+        None => Ok(value_text(input)),
+    }
+}
+
+/// Fill the step's prompt template from the run input and earlier outputs
+pub fn render_prompt(step: &WorkflowStep, input: &Value, outputs: &HashMap<String, String>) -> Result<String> {
+    let mut prompt = step.prompt.clone();
+    for (name, source) in &step.inputs {
+        let value = resolve_source(source, input, outputs)
+            .with_context(|| format!("Step {} input {}", step.id, name))?;
+        prompt = prompt.replace(&format!("{{{{{}}}}}", name), &value);
+    }
+    Ok(prompt)
+}
+
+pub fn load_workflows(storage: &StorageManager) -> Result<Vec<WorkflowDefinition>> {
+    match storage.get_setting(WORKFLOWS_SETTING_KEY)? {
+        Some(value) => serde_json::from_value(value).context("Stored agent workflows are malformed"),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn save_workflows(storage: &StorageManager, workflows: &[WorkflowDefinition]) -> Result<()> {
+    storage.set_setting(WORKFLOWS_SETTING_KEY, serde_json::to_value(workflows)?)
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowStepStatus {
+    Started,
+    Completed,
+    Failed,
+    Skipped,
+}
+
+/// Progress of one step, streamed on `WORKFLOW_STEP_EVENT`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowStepEvent {
+    pub run_id: String,
+    pub workflow_id: String,
+    pub step_id: String,
+    pub agent_id: String,
+    pub status: WorkflowStepStatus,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    /// Memory holding the step's output
+    pub memory_id: Option<String>,
+    pub duration_ms: u64,
+    /// Guardrail findings on the step's prompt and output
+    #[serde(default)]
+    pub guardrail_violations: Vec<GuardrailViolation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRunResult {
+    pub run_id: String,
+    pub workflow_id: String,
+    pub succeeded: bool,
+    /// Final state of every step, in execution order
+    pub steps: Vec<WorkflowStepEvent>,
+    pub output: Option<String>,
+}
+
+/// An agent with the provider to complete its steps
+struct StepAgent {
+    agent: Agent,
+    provider: String,
+}
+
+/// Provider named in the agent's stored configuration
+pub(crate) fn configured_provider(conn: &Connection, agent_id: &str) -> Result<Option<String>> {
+    let configuration: Option<String> = conn.query_row(
+        "SELECT configuration FROM agent_settings WHERE agent_id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).optional()?;
+    Ok(configuration
+        .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+        .and_then(|config| config.get("provider").and_then(|p| p.as_str()).map(str::to_string)))
+}
+
+/// Swap each templated step's prompt for its stored template, checking the step's inputs
+/// against the template's variables
+fn apply_prompt_templates(app: &AppHandle, workflow: &mut WorkflowDefinition) -> Result<()> {
+    if workflow.steps.iter().all(|step| step.prompt_template.is_none()) {
+        return Ok(());
+    }
+    let conn = open_conversation_db(app)?;
+    for step in &mut workflow.steps {
+        if let Some(template_id) = &step.prompt_template {
+            let template = find_template(&conn, template_id, None)?;
+            check_variables(&template.variables, step.inputs.keys())
+                .with_context(|| format!("Step {} uses prompt template {}", step.id, template_id))?;
+            step.prompt = template.body;
+        }
+    }
+    Ok(())
+}
+
+fn load_step_agents(app: &AppHandle, state: &AIState, workflow: &WorkflowDefinition) -> Result<HashMap<String, StepAgent>> {
+    let conn = open_conversation_db(app)?;
+    let mut agents = HashMap::new();
+    for step in &workflow.steps {
+        if agents.contains_key(&step.agent_id) {
+            continue;
+        }
+        let agent = load_agent(&conn, &step.agent_id)?;
+        let preferred = configured_provider(&conn, &step.agent_id)?;
+        let provider = select_completion_provider(state, preferred.as_deref())
+            .with_context(|| format!("No provider for agent {}", step.agent_id))?;
+        agents.insert(step.agent_id.clone(), StepAgent { agent, provider });
+    }
+    Ok(agents)
+}
+
+/// Keep a step's output as a task memory of its agent, tagged with the workflow and run
+async fn remember_output(
+    memory_state: &MemoryState,
+    state: &AIState,
+    event: &WorkflowStepEvent,
+    output: &str,
+) -> Result<String, String> {
+    let manager = memory_state.get_or_create_manager(event.agent_id.clone())?;
+    let content: String = output.chars().take(MAX_MEMORY_CHARS).collect();
+    let metadata = HashMap::from([
+        ("workflow_id".to_string(), event.workflow_id.clone()),
+        ("workflow_run_id".to_string(), event.run_id.clone()),
+        ("workflow_step_id".to_string(), event.step_id.clone()),
+    ]);
+    let memory = AgentMemory::new(event.agent_id.clone(), MemoryType::Task, content)
+        .with_tags(vec!["workflow".to_string(), event.workflow_id.clone()])
+        .with_metadata(metadata);
+    store_memory(memory_state, state, &manager, memory).await
+}
+
+fn emit_step(app: &AppHandle, event: &WorkflowStepEvent) {
+    if let Err(e) = app.emit(WORKFLOW_STEP_EVENT, event) {
+        error!("Failed to emit workflow step event: {}", e);
+    }
+}
+
+async fn run_step(
+    app: &AppHandle,
+    step: &WorkflowStep,
+    agent: &StepAgent,
+    prompt: Result<String>,
+    mut event: WorkflowStepEvent,
+) -> (WorkflowStepEvent, Option<String>) {
+    let started = Instant::now();
+    emit_step(app, &event);
+
+    let result = async {
+        // The job applies the agent's guardrails to the prompt and the reply
+        let job = TaskJob::Llm {
+            agent_id: Some(step.agent_id.clone()),
+            provider: Some(agent.provider.clone()),
+            model: Some(agent.agent.model.clone()),
+            system: agent.agent.system_prompt.clone().unwrap_or_default(),
+            prompt: prompt?,
+        };
+        let output: LlmJobOutput = serde_json::from_value(submit_and_wait(app, job, STEP_PRIORITY).await?)?;
+        event.guardrail_violations = output.guardrail_violations;
+        Ok::<_, anyhow::Error>(output.text)
+    }.await;
+    event.duration_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(output) => {
+            event.status = WorkflowStepStatus::Completed;
+            event.output = Some(output.clone());
+            (event, Some(output))
+        }
+        Err(e) => {
+            warn!("Workflow step {} failed: {:#}", step.id, e);
+            event.status = WorkflowStepStatus::Failed;
+            event.error = Some(format!("{:#}", e));
+            (event, None)
+        }
+    }
+}
+
+// Tauri Commands
+
+#[command]
+pub async fn list_agent_workflows(state: State<'_, AIState>) -> Result<Vec<WorkflowDefinition>, String> {
+    load_workflows(&state.storage).map_err(|e| e.to_string())
+}
+
+/// Add a workflow, or replace the one with the same id
+#[command]
+pub async fn register_agent_workflow(
+    workflow: WorkflowDefinition,
+    state: State<'_, AIState>,
+) -> Result<WorkflowDefinition, String> {
+    workflow.layers().map_err(|e| e.to_string())?;
+    let mut workflows = load_workflows(&state.storage).map_err(|e| e.to_string())?;
+    match workflows.iter_mut().find(|w| w.id == workflow.id) {
+        Some(existing) => *existing = workflow.clone(),
+        None => workflows.push(workflow.clone()),
+    }
+    save_workflows(&state.storage, &workflows)
+        .map_err(|e| format!("Failed to store workflow: {}", e))?;
+    info!("Registered agent workflow {} with {} steps", workflow.id, workflow.steps.len());
+    Ok(workflow)
+}
+
+#[command]
+pub async fn delete_agent_workflow(workflow_id: String, state: State<'_, AIState>) -> Result<(), String> {
+    let mut workflows = load_workflows(&state.storage).map_err(|e| e.to_string())?;
+    let before = workflows.len();
+    workflows.retain(|w| w.id != workflow_id);
+    if workflows.len() == before {
+        return Err(format!("Unknown workflow: {}", workflow_id));
+    }
+    save_workflows(&state.storage, &workflows).map_err(|e| format!("Failed to store workflows: {}", e))
+}
+
+async fn execute_workflow(
+    app: &AppHandle,
+    workflow: &WorkflowDefinition,
+    agents: &HashMap<String, StepAgent>,
+    input: &Value,
+    operation: &OperationHandle,
+) -> Result<WorkflowRunResult> {
+    let state = app.state::<AIState>();
+    let memory_state = app.state::<MemoryState>();
+    let run_id = operation.id().to_string();
+    let mut outputs: HashMap<String, String> = HashMap::new();
+    let mut steps = Vec::new();
+    let mut failed = false;
+
+    for layer in workflow.layers()? {
+        let event_for = |step: &WorkflowStep, status| WorkflowStepEvent {
+            run_id: run_id.clone(),
+            workflow_id: workflow.id.clone(),
+            step_id: step.id.clone(),
+            agent_id: step.agent_id.clone(),
+            status,
+            output: None,
+            error: None,
+            memory_id: None,
+            duration_ms: 0,
+            guardrail_violations: Vec::new(),
+        };
+        if failed || operation.is_cancelled() {
+            for step in layer {
+                let skipped = event_for(step, WorkflowStepStatus::Skipped);
+                emit_step(app, &skipped);
+                steps.push(skipped);
+            }
+            continue;
+        }
+
+        let runs = layer.iter().map(|step| {
+            let prompt = render_prompt(step, input, &outputs);
+            run_step(app, step, &agents[&step.agent_id], prompt, event_for(step, WorkflowStepStatus::Started))
+        });
+        for (mut event, output) in futures::future::join_all(runs).await {
+            match output {
+                Some(output) => {
+                    match remember_output(&memory_state, &state, &event, &output).await {
+                        Ok(memory_id) => event.memory_id = Some(memory_id),
+                        Err(e) => warn!("Failed to store output of workflow step {}: {}", event.step_id, e),
+                    }
+                    outputs.insert(event.step_id.clone(), output);
+                }
+                None => failed = true,
+            }
+            emit_step(app, &event);
+            steps.push(event);
+        }
+        operation.report(
+            steps.len() as f32 / workflow.steps.len() as f32,
+            format!("{} of {} steps done", steps.len(), workflow.steps.len()),
+        );
+    }
+
+    let output_step = workflow.output_step.clone()
+        .or_else(|| steps.last().map(|step| step.step_id.clone()));
+    let result = WorkflowRunResult {
+        run_id,
+        workflow_id: workflow.id.clone(),
+        succeeded: !failed && !operation.is_cancelled(),
+        steps,
+        output: output_step.and_then(|step| outputs.remove(&step)),
+    };
+    if let Err(e) = app.emit(WORKFLOW_FINISHED_EVENT, &result) {
+        error!("Failed to emit workflow finished event: {}", e);
+    }
+    notify_quietly(app, finished_notification(workflow, &result, operation.is_cancelled()));
+    Ok(result)
+}
+
+fn finished_notification(workflow: &WorkflowDefinition, result: &WorkflowRunResult, cancelled: bool) -> NewNotification {
+    let failed = result.steps.iter().find(|step| step.status == WorkflowStepStatus::Failed);
+    let (severity, title, message) = match failed {
+        _ if cancelled => (NotificationSeverity::Info, "Workflow cancelled", format!("{} was cancelled", workflow.name)),
+        Some(step) => (
+            NotificationSeverity::Error,
+            "Workflow failed",
+            format!("{} failed at step {}: {}", workflow.name, step.step_id, step.error.as_deref().unwrap_or_default()),
+        ),
+        None => (NotificationSeverity::Success, "Workflow finished", format!("{} finished {} steps", workflow.name, result.steps.len())),
+    };
+    NewNotification::new(severity, "agent_workflow", title, message)
+        .with_payload(serde_json::json!({ "workflow_id": result.workflow_id, "run_id": result.run_id }))
+}
+
+/// Start a registered workflow with `input` available to its steps; returns the operation id,
+/// which is also the run id of its step events. A failed or cancelled run stops once the steps
+/// running at the time finish, and the steps after them are reported as skipped.
+#[command]
+pub async fn run_agent_workflow(
+    workflow_id: String,
+    input: Option<Value>,
+    app: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<String, String> {
+    let mut workflow = load_workflows(&state.storage).map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|w| w.id == workflow_id)
+        .ok_or_else(|| format!("Unknown workflow: {}", workflow_id))?;
+    workflow.layers().map_err(|e| e.to_string())?;
+    apply_prompt_templates(&app, &mut workflow).map_err(|e| format!("{:#}", e))?;
+    let agents = load_step_agents(&app, &state, &workflow).map_err(|e| format!("{:#}", e))?;
+    let input = input.unwrap_or(Value::Null);
+
+    let operation_id = start_operation(&app, "agent_workflow", {
+        let app = app.clone();
+        move |operation| async move {
+            let result = execute_workflow(&app, &workflow, &agents, &input, &operation)
+                .await
+                .map_err(|e| format!("{:#}", e))?;
+            operation.check_cancelled()?;
+            if let Some(failed) = result.steps.iter().find(|step| step.status == WorkflowStepStatus::Failed) {
+                return Err(format!("Step {} failed: {}", failed.step_id, failed.error.as_deref().unwrap_or_default()));
+            }
+            serde_json::to_value(&result).map_err(|e| e.to_string())
+        }
+    });
+    info!("Running agent workflow {} as {}", workflow_id, operation_id);
+    Ok(operation_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn step(id: &str, depends_on: &[&str], inputs: &[(&str, &str)]) -> WorkflowStep {
+        WorkflowStep {
+            id: id.to_string(),
+            agent_id: "writer".to_string(),
+            prompt: format!("{} step", id),
+            prompt_template: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            inputs: inputs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    fn workflow(steps: Vec<WorkflowStep>) -> WorkflowDefinition {
+        WorkflowDefinition { id: "wf".to_string(), name: "Workflow".to_string(), description: String::new(), steps, output_step: None }
+    }
+
+    #[test]
+    fn test_layers_follow_dependencies() {
+        let wf = workflow(vec![
+            step("review", &["draft", "research"], &[("draft", "steps.draft.output")]),
+            step("research", &[], &[("topic", "input.topic")]),
+            step("draft", &["research"], &[("notes", "steps.research.output")]),
+            step("outline", &[], &[]),
+        ]);
+        let layers: Vec<Vec<&str>> = wf.layers().unwrap().iter()
+            .map(|layer| layer.iter().map(|s| s.id.as_str()).collect())
+            .collect();
+        assert_eq!(layers, vec![vec!["research", "outline"], vec!["draft"], vec!["review"]]);
+
+        let cyclic = workflow(vec![step("a", &["b"], &[]), step("b", &["a"], &[])]);
+        assert!(cyclic.layers().unwrap_err().to_string().contains("cycle"));
+        let undeclared = workflow(vec![step("a", &[], &[]), step("b", &[], &[("x", "steps.a.output")])]);
+        assert!(undeclared.layers().is_err());
+        assert!(workflow(vec![step("a", &["missing"], &[])]).layers().is_err());
+        let promptless = WorkflowStep { prompt: String::new(), ..step("a", &[], &[]) };
+        assert!(workflow(vec![promptless.clone()]).layers().is_err());
+        let templated = WorkflowStep { prompt_template: Some("code-review".to_string()), ..promptless };
+        assert!(workflow(vec![templated]).layers().is_ok());
+    }
+
+    #[test]
+    fn test_render_prompt_maps_inputs() {
+        let mut review = step("review", &["draft"], &[("draft", "steps.draft.output"), ("topic", "input.meta.topic")]);
+        review.prompt = "Review this {{topic}} draft:\n{{draft}}".to_string();
+        let outputs = HashMap::from([("draft".to_string(), "Rust is fast.".to_string())]);
+        let input = json!({ "meta": { "topic": "Rust" } });
+
+        assert_eq!(render_prompt(&review, &input, &outputs).unwrap(), "Review this Rust draft:\nRust is fast.");
+        assert!(render_prompt(&review, &json!({}), &outputs).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use anyhow::{anyhow, Context, Result};
+use diffy::{DiffOptions, Patch};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use tracing::{info, warn, error};
+
+use super::commands::AIState;
+use super::disk_space::ensure_disk_space;
+use super::security::{enforce_path_policy, session_scoped_path, PathAccess, PathPolicy};
+
+const DEV_NULL: &str = "/dev/null";
+
+/// Largest patch accepted by `apply_patch_command`
+const MAX_PATCH_BYTES: usize = 5 * 1024 * 1024;
+
+/// One file's section of a (possibly multi-file) unified diff
+#[derive(Debug, Clone, PartialEq)]
+struct FilePatch {
+    /// Target path with any `a/` / `b/` prefix removed
+    path: String,
+    is_new: bool,
+    is_deleted: bool,
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchFileResult {
+    pub path: String,
+    /// "modified", "created", "deleted" or "conflict"
+    pub status: String,
+    pub hunks: usize,
+    /// Why the file could not be patched, when status is "conflict"
+    pub conflict: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchApplyReport {
+    pub dry_run: bool,
+    /// True when the patch was written to disk; a clean dry run has no conflicts
+    pub applied: bool,
+    pub files: Vec<PatchFileResult>,
+    /// Directory holding copies of the files as they were before patching
+    pub backup_dir: Option<String>,
+}
+
+fn strip_diff_prefix(path: &str) -> String {
+    // Headers may carry a tab-separated timestamp after the file name
+    let path = path.split('\t').next().unwrap_or(path).trim();
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// Split a unified diff into per-file patches, ignoring git metadata lines
+fn split_patch(patch: &str) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = patch.split_inclusive('\n').collect();
+    let mut files = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let is_header = lines[i].starts_with("--- ")
+            && lines.get(i + 1).map_or(false, |next| next.starts_with("+++ "));
+        if !is_header {
+            i += 1;
+            continue;
+        }
+
+        let original = lines[i][4..].trim_end();
+        let modified = lines[i + 1][4..].trim_end();
+        let start = i;
+        i += 2;
+        while i < lines.len() && !lines[i].starts_with("diff ") && !lines[i].starts_with("--- ") {
+            i += 1;
+        }
+
+        let is_new = original.starts_with(DEV_NULL);
+        let is_deleted = modified.starts_with(DEV_NULL);
+        let path = strip_diff_prefix(if is_deleted { original } else { modified });
+        if path.is_empty() {
+            return Err(anyhow!("Patch header is missing a file name"));
+        }
+        if path.starts_with('/') || Path::new(&path).components().any(|c| c == std::path::Component::ParentDir) {
+            return Err(anyhow!("Patch targets a path outside the root: {}", path));
+        }
+
+        files.push(FilePatch {
+            path,
+            is_new,
+            is_deleted,
+            text: lines[start..i].concat(),
+        });
+    }
+
+    if files.is_empty() {
+        return Err(anyhow!("No file headers found in patch"));
+    }
+    Ok(files)
+}
+
+/// Produce a unified diff between two versions of a file
+pub fn unified_diff(path: &str, original: &str, modified: &str, context_lines: usize) -> String {
+    DiffOptions::new()
+        .set_context_len(context_lines)
+        .set_original_filename(format!("a/{}", path))
+        .set_modified_filename(format!("b/{}", path))
+        .create_patch(original, modified)
+        .to_string()
+}
+
+/// Apply one file patch to its current contents, returning the new contents (None = delete)
+fn apply_file_patch(file: &FilePatch, current: Option<&str>) -> Result<(Option<String>, usize), String> {
+    let patch = Patch::from_str(&file.text).map_err(|e| format!("Malformed patch: {}", e))?;
+    let hunks = patch.hunks().len();
+
+    match (current, file.is_new) {
+        (Some(_), true) => return Err("File already exists".to_string()),
+        (None, false) => return Err("File does not exist".to_string()),
+        _ => {}
+    }
+
+    let patched = diffy::apply(current.unwrap_or(""), &patch)
+        .map_err(|e| format!("Conflict: {}", e))?;
+
+    if file.is_deleted {
+        if !patched.is_empty() {
+            return Err("Conflict: file contents differ from the deletion patch".to_string());
+        }
+        return Ok((None, hunks));
+    }
+    Ok((Some(patched), hunks))
+}
+
+/// Copy the files about to change into `backup_dir`, preserving their relative layout
+fn backup_files(root: &Path, backup_dir: &Path, files: &[(PathBuf, String)]) -> Result<()> {
+    let total: u64 = files.iter()
+        .filter_map(|(resolved, _)| std::fs::metadata(resolved).ok())
+        .map(|m| m.len())
+        .sum();
+    ensure_disk_space(backup_dir, total, "patch backup")?;
+
+    for (resolved, relative) in files {
+        if !resolved.exists() {
+            continue;
+        }
+        let target = backup_dir.join(relative.trim_start_matches('/'));
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create backup directory")?;
+        }
+        std::fs::copy(resolved, &target)
+            .with_context(|| format!("Failed to back up {}", resolved.strip_prefix(root).unwrap_or(resolved).display()))?;
+    }
+    Ok(())
+}
+
+fn backup_root() -> Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .context("Failed to get data directory")?
+        .join("banshee")
+        .join("patch_backups")
+        .join(chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ").to_string());
+    Ok(dir)
+}
+
+/// Validate and (unless `dry_run`) apply a unified diff relative to `root`.
+///
+/// Every file is patched in memory first; nothing is written unless all files
+/// apply cleanly, and originals are backed up before any write.
+pub fn apply_patch(
+    root: &Path,
+    patch: &str,
+    dry_run: bool,
+    policy: &PathPolicy,
+    backup_dir: Option<PathBuf>,
+) -> Result<PatchApplyReport> {
+    let file_patches = split_patch(patch)?;
+    let mut results = Vec::new();
+    let mut planned: Vec<(PathBuf, String, Option<String>)> = Vec::new();
+
+    for file in &file_patches {
+        let target = root.join(&file.path);
+        let outcome = policy.check(&target.to_string_lossy(), PathAccess::List).and_then(|resolved| {
+            let current = std::fs::read_to_string(&resolved).ok();
+            let (contents, hunks) = apply_file_patch(file, current.as_deref())?;
+            if contents.as_ref().map_or(false, |c| c.len() as u64 > policy.max_file_size) {
+                return Err(format!("Patched file exceeds the {} byte size limit", policy.max_file_size));
+            }
+            Ok((resolved, contents, hunks))
+        });
+
+        match outcome {
+            Ok((resolved, contents, hunks)) => {
+                let status = if file.is_deleted { "deleted" } else if file.is_new { "created" } else { "modified" };
+                results.push(PatchFileResult { path: file.path.clone(), status: status.to_string(), hunks, conflict: None });
+                planned.push((resolved, file.path.clone(), contents));
+            }
+            Err(reason) => {
+                warn!("Patch for {} does not apply: {}", file.path, reason);
+                results.push(PatchFileResult { path: file.path.clone(), status: "conflict".to_string(), hunks: 0, conflict: Some(reason) });
+            }
+        }
+    }
+
+    let clean = results.iter().all(|r| r.conflict.is_none());
+    if dry_run || !clean {
+        return Ok(PatchApplyReport { dry_run, applied: false, files: results, backup_dir: None });
+    }
+
+    let backup_dir = match backup_dir {
+        Some(dir) => dir,
+        None => backup_root()?,
+    };
+    let to_backup: Vec<(PathBuf, String)> = planned.iter().map(|(p, rel, _)| (p.clone(), rel.clone())).collect();
+    backup_files(root, &backup_dir, &to_backup)?;
+
+    for (resolved, relative, contents) in planned {
+        match contents {
+            Some(contents) => {
+                if let Some(parent) = resolved.parent() {
+                    std::fs::create_dir_all(parent).context("Failed to create directories")?;
+                }
+                std::fs::write(&resolved, contents).with_context(|| format!("Failed to write {}", relative))?;
+            }
+            None => std::fs::remove_file(&resolved).with_context(|| format!("Failed to delete {}", relative))?,
+        }
+    }
+
+    Ok(PatchApplyReport {
+        dry_run,
+        applied: true,
+        files: results,
+        backup_dir: Some(backup_dir.to_string_lossy().to_string()),
+    })
+}
+
+/// Unified diff between a file on disk (empty if missing) and proposed new content
+#[tauri::command]
+pub async fn compute_diff_command(
+    path: String,
+    new_content: String,
+    context_lines: Option<usize>,
+    workspace: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<String, String> {
+    info!("Computing diff for: {}", path);
+
+    let scoped = session_scoped_path(session_id.as_deref(), &path)?;
+    let resolved = enforce_path_policy(&state.storage, workspace.as_deref(), &scoped, PathAccess::Read)?;
+    let original = match std::fs::read_to_string(&resolved) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read file: {}", e)),
+    };
+
+    Ok(unified_diff(&path, &original, &new_content, context_lines.unwrap_or(3)))
+}
+
+/// Apply a unified diff to files under `root` (defaults to the working directory)
+#[tauri::command]
+pub async fn apply_patch_command(
+    patch: String,
+    root: Option<String>,
+    dry_run: bool,
+    workspace: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<PatchApplyReport, String> {
+    info!("Applying patch (dry run: {})", dry_run);
+
+    if patch.len() > MAX_PATCH_BYTES {
+        return Err("Patch is too large".to_string());
+    }
+
+    let workspace = workspace.unwrap_or_else(|| super::security::active_workspace(&state.storage));
+    let root = session_scoped_path(session_id.as_deref(), root.as_deref().unwrap_or("."))?;
+    let root = enforce_path_policy(&state.storage, Some(&workspace), &root, PathAccess::List)?;
+    let policy = super::security::load_path_policy(&state.storage, &workspace)
+        .map_err(|e| format!("Failed to load path policy: {}", e))?;
+
+    apply_patch(&root, &patch, dry_run, &policy, None).map_err(|e| {
+        error!("Failed to apply patch: {}", e);
+        format!("Failed to apply patch: {}", e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (tempfile::TempDir, PathPolicy) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {\n    println!(\"hi\");\n}\n").unwrap();
+        let policy = PathPolicy {
+            allowed_roots: vec![dir.path().to_string_lossy().to_string()],
+            ..PathPolicy::default_for("test")
+        };
+        (dir, policy)
+    }
+
+    #[test]
+    fn test_diff_round_trip_with_backup() {
+        let (dir, policy) = setup();
+        let original = std::fs::read_to_string(dir.path().join("main.rs")).unwrap();
+        let modified = original.replace("hi", "hello");
+        let diff = unified_diff("main.rs", &original, &modified, 3);
+        assert!(diff.starts_with("--- a/main.rs\n+++ b/main.rs\n"));
+
+        let dry = apply_patch(dir.path(), &diff, true, &policy, None).unwrap();
+        assert!(!dry.applied);
+        assert_eq!(dry.files[0].status, "modified");
+        assert_eq!(std::fs::read_to_string(dir.path().join("main.rs")).unwrap(), original);
+
+        let backup = dir.path().join("backup");
+        let report = apply_patch(dir.path(), &diff, false, &policy, Some(backup.clone())).unwrap();
+        assert!(report.applied);
+        assert_eq!(std::fs::read_to_string(dir.path().join("main.rs")).unwrap(), modified);
+        assert_eq!(std::fs::read_to_string(backup.join("main.rs")).unwrap(), original);
+    }
+
+    #[test]
+    fn test_conflicts_block_all_writes() {
+        let (dir, policy) = setup();
+        let created = unified_diff("new.txt", "", "fresh\n", 3).replace("--- a/new.txt", "--- /dev/null");
+        let stale = unified_diff("main.rs", "fn other() {}\n", "fn changed() {}\n", 3);
+        let combined = format!("{}{}", created, stale);
+
+        let report = apply_patch(dir.path(), &combined, false, &policy, Some(dir.path().join("backup"))).unwrap();
+        assert!(!report.applied);
+        assert_eq!(report.files[0].status, "created");
+        assert_eq!(report.files[1].status, "conflict");
+        assert!(!dir.path().join("new.txt").exists());
+    }
+
+    #[test]
+    fn test_split_patch_headers() {
+        let patch = "diff --git a/x b/x\nindex 1..2\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n--- a/y\t2024-01-01\n+++ /dev/null\n@@ -1 +0,0 @@\n-y\n";
+        let files = split_patch(patch).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "x");
+        assert!(files[1].is_deleted && files[1].path == "y");
+        assert!(split_patch("not a patch").is_err());
+        assert!(split_patch("--- a/../etc/passwd\n+++ b/../etc/passwd\n@@ -1 +1 @@\n-a\n+b\n").is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+use tracing::{info, warn, error};
+
+use crate::ai::{
+    csrf::validate_request_security,
+    error_sanitization::sanitize_log_error,
+    secure_commands::SecureSession,
+    storage::StorageManager,
+};
+
+/// Settings key prefix for stored agent manifests
+const PERMISSIONS_SETTING_PREFIX: &str = "agent_permissions.";
+
+/// Wildcard entry granting every value in a scope
+pub const WILDCARD: &str = "*";
+
+/// Manifest id applied to secure commands that do not name an agent
+pub const DEFAULT_AGENT_ID: &str = "default";
+
+/// Capability manifest describing what an agent is allowed to touch.
+///
+/// Each scope is a list of grants; `"*"` grants the whole scope. Agents without
+/// a stored manifest get the unrestricted default so existing flows keep working
+/// until a manifest is configured. Calls that name no agent are not given that
+/// default: they get the `default` manifest when one is stored, and nothing otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentPermissions {
+    pub agent_id: String,
+    /// Relative directory roots the agent may read, write and list
+    pub allowed_file_roots: Vec<String>,
+    /// Executable names the agent may run (still subject to the global whitelist)
+    pub allowed_commands: Vec<String>,
+    /// Hosts the agent may reach; `*.example.com` matches subdomains
+    pub allowed_network_hosts: Vec<String>,
+    /// MCP server ids the agent may call
+    pub allowed_mcp_servers: Vec<String>,
+}
+
+impl AgentPermissions {
+    pub fn unrestricted(agent_id: &str) -> Self {
+        Self {
+            agent_id: agent_id.to_string(),
+            allowed_file_roots: vec![WILDCARD.to_string()],
+            allowed_commands: vec![WILDCARD.to_string()],
+            allowed_network_hosts: vec![WILDCARD.to_string()],
+            allowed_mcp_servers: vec![WILDCARD.to_string()],
+        }
+    }
+
+    /// Manifest granting nothing in any scope
+    pub fn denied(agent_id: &str) -> Self {
+        Self {
+            agent_id: agent_id.to_string(),
+            allowed_file_roots: Vec::new(),
+            allowed_commands: Vec::new(),
+            allowed_network_hosts: Vec::new(),
+            allowed_mcp_servers: Vec::new(),
+        }
+    }
+
+    /// Check a relative path against the allowed file roots
+    pub fn allows_path(&self, path: &str) -> bool {
+        let path = normalize_relative(path);
+
+        self.allowed_file_roots.iter().any(|root| {
+            if root == WILDCARD {
+                return true;
+            }
+            let root = normalize_relative(root);
+            root.is_empty() || path == root || path.starts_with(&format!("{}/", root))
+        })
+    }
+
+    pub fn allows_command(&self, command: &str) -> bool {
+        // Compare on the executable name so `/usr/bin/git` and `git` are treated alike
+        let name = std::path::Path::new(command)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(command);
+
+        self.allowed_commands.iter().any(|c| c == WILDCARD || c == name)
+    }
+
+    pub fn allows_network_host(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+
+        self.allowed_network_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_lowercase();
+            if allowed == WILDCARD {
+                true
+            } else if let Some(suffix) = allowed.strip_prefix("*.") {
+                host.ends_with(&format!(".{}", suffix))
+            } else {
+                host == allowed
+            }
+        })
+    }
+
+    /// Check the host of a URL against the allowed network hosts
+    pub fn allows_url(&self, url: &str) -> bool {
+        match url::Url::parse(url).ok().and_then(|u| u.host_str().map(|h| h.to_string())) {
+            Some(host) => self.allows_network_host(&host),
+            None => false,
+        }
+    }
+
+    pub fn allows_mcp_server(&self, server_id: &str) -> bool {
+        self.allowed_mcp_servers.iter().any(|s| s == WILDCARD || s == server_id)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.agent_id.trim().is_empty() || self.agent_id.len() > 100 {
+            return Err("Invalid agent id".to_string());
+        }
+
+        for root in &self.allowed_file_roots {
+            if root.contains("..") || root.starts_with('/') || root.contains('\x00') {
+                return Err(format!("Invalid file root: {}", root));
+            }
+        }
+
+        let all_entries = self.allowed_commands.iter()
+            .chain(&self.allowed_network_hosts)
+            .chain(&self.allowed_mcp_servers);
+        for entry in all_entries {
+            if entry.trim().is_empty() || entry.contains(char::is_whitespace) {
+                return Err(format!("Invalid permission entry: '{}'", entry));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn normalize_relative(path: &str) -> String {
+    path.trim_start_matches("./").trim_end_matches('/').to_string()
+}
+
+/// Load the manifest stored for an agent, if any
+fn load_stored_permissions(storage: &StorageManager, agent_id: &str) -> Result<Option<AgentPermissions>> {
+    let key = format!("{}{}", PERMISSIONS_SETTING_PREFIX, agent_id);
+
+    storage.get_setting(&key)?
+        .map(|value| serde_json::from_value(value).context("Stored agent permissions are malformed"))
+        .transpose()
+}
+
+/// Load the manifest for an agent, falling back to the unrestricted default
+pub fn load_agent_permissions(storage: &StorageManager, agent_id: &str) -> Result<AgentPermissions> {
+    Ok(load_stored_permissions(storage, agent_id)?
+        .unwrap_or_else(|| AgentPermissions::unrestricted(agent_id)))
+}
+
+/// Load the manifest for calls that name no agent, denying everything until one is stored
+pub fn load_default_permissions(storage: &StorageManager) -> Result<AgentPermissions> {
+    Ok(load_stored_permissions(storage, DEFAULT_AGENT_ID)?
+        .unwrap_or_else(|| AgentPermissions::denied(DEFAULT_AGENT_ID)))
+}
+
+/// Validate and store the manifest for an agent
+pub fn save_agent_permissions(storage: &StorageManager, permissions: &AgentPermissions) -> Result<()> {
+    permissions.validate().map_err(anyhow::Error::msg)?;
+    let key = format!("{}{}", PERMISSIONS_SETTING_PREFIX, permissions.agent_id);
+    storage.set_setting(&key, serde_json::to_value(permissions)?)
+}
+
+/// Resolve the manifest that applies to a secure command invocation
+pub fn resolve_agent_permissions(
+    secure_session: &SecureSession,
+    agent_id: Option<&str>,
+) -> Result<AgentPermissions, String> {
+    let storage_manager = secure_session.storage_manager.lock()
+        .map_err(|_| "Failed to acquire storage lock".to_string())?;
+
+    let loaded = match agent_id {
+        Some(id) => load_agent_permissions(&storage_manager, id),
+        None => load_default_permissions(&storage_manager),
+    };
+    loaded.map_err(|e| {
+        error!(
+            "Failed to load permissions for {}: {}",
+            agent_id.unwrap_or(DEFAULT_AGENT_ID),
+            sanitize_log_error(&e)
+        );
+        "Failed to load agent permissions".to_string()
+    })
+}
+
+/// Store the capability manifest for an agent
+#[command]
+pub async fn set_agent_permissions(
+    session_id: String,
+    csrf_token: String,
+    permissions: AgentPermissions,
+    secure_session: State<'_, SecureSession>,
+) -> Result<String, String> {
+    // Validate security
+    match validate_request_security(&session_id, &csrf_token) {
+        Ok(true) => {}
+        Ok(false) => return Err("Security validation failed".to_string()),
+        Err(_) => return Err("Security validation failed".to_string()),
+    }
+
+    permissions.validate()?;
+
+    let value = serde_json::to_value(&permissions)
+        .map_err(|_| "Failed to serialize permissions".to_string())?;
+
+    let storage_manager = secure_session.storage_manager.lock()
+        .map_err(|_| "Failed to acquire storage lock".to_string())?;
+
+    match storage_manager.set_setting(&format!("{}{}", PERMISSIONS_SETTING_PREFIX, permissions.agent_id), value) {
+        Ok(_) => {
+            info!("Permissions updated for agent: {}", permissions.agent_id);
+            Ok("Agent permissions updated".to_string())
+        }
+        Err(e) => {
+            error!("Failed to store agent permissions: {}", sanitize_log_error(&e));
+            Err("Failed to store agent permissions".to_string())
+        }
+    }
+}
+
+/// Retrieve the capability manifest for an agent
+#[command]
+pub async fn get_agent_permissions(
+    session_id: String,
+    csrf_token: String,
+    agent_id: String,
+    secure_session: State<'_, SecureSession>,
+) -> Result<AgentPermissions, String> {
+    // Validate security
+    match validate_request_security(&session_id, &csrf_token) {
+        Ok(true) => {}
+        Ok(false) => return Err("Security validation failed".to_string()),
+        Err(_) => return Err("Security validation failed".to_string()),
+    }
+
+    if agent_id.trim().is_empty() {
+        warn!("Permission lookup with empty agent id");
+        return Err("Agent id is required".to_string());
+    }
+
+    resolve_agent_permissions(&secure_session, Some(&agent_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn restricted() -> AgentPermissions {
+        AgentPermissions {
+            agent_id: "writer".to_string(),
+            allowed_file_roots: vec!["docs/".to_string(), "./output".to_string()],
+            allowed_commands: vec!["git".to_string()],
+            allowed_network_hosts: vec!["api.github.com".to_string(), "*.example.com".to_string()],
+            allowed_mcp_servers: vec!["filesystem".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_file_roots() {
+        let perms = restricted();
+        assert!(perms.allows_path("docs/readme.md"));
+        assert!(perms.allows_path("./output/report.txt"));
+        assert!(perms.allows_path("output"));
+        assert!(!perms.allows_path("docsextra/file.md"));
+        assert!(!perms.allows_path("src/main.rs"));
+    }
+
+    #[test]
+    fn test_commands_and_servers() {
+        let perms = restricted();
+        assert!(perms.allows_command("git"));
+        assert!(perms.allows_command("/usr/bin/git"));
+        assert!(!perms.allows_command("rm"));
+        assert!(perms.allows_mcp_server("filesystem"));
+        assert!(!perms.allows_mcp_server("github"));
+    }
+
+    #[test]
+    fn test_network_hosts() {
+        let perms = restricted();
+        assert!(perms.allows_network_host("api.github.com"));
+        assert!(perms.allows_url("https://docs.example.com/page"));
+        assert!(!perms.allows_network_host("example.com"));
+        assert!(!perms.allows_url("https://evil.com"));
+        assert!(!perms.allows_url("not a url"));
+    }
+
+    #[test]
+    fn test_unrestricted_and_validation() {
+        let perms = AgentPermissions::unrestricted("assistant");
+        assert!(perms.allows_path("anything/at/all"));
+        assert!(perms.allows_command("ls"));
+        assert!(perms.allows_url("https://anywhere.dev"));
+
+        let denied = AgentPermissions::denied(DEFAULT_AGENT_ID);
+        assert!(!denied.allows_path("docs/readme.md"));
+        assert!(!denied.allows_command("ls"));
+        assert!(!denied.allows_url("https://anywhere.dev"));
+        assert!(!denied.allows_mcp_server("filesystem"));
+
+        let mut invalid = restricted();
+        invalid.allowed_file_roots.push("../etc".to_string());
+        assert!(invalid.validate().is_err());
+        assert!(restricted().validate().is_ok());
+    }
+}
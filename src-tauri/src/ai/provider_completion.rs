@@ -0,0 +1,451 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::info;
+
+use super::commands::AIState;
+use super::error_sanitization::{redact_error, redact_secrets};
+use super::guardrails::{GuardrailScope, GuardrailStage};
+use super::http_client::HttpRequest;
+use super::security::{enforce_path_policy, PathAccess};
+use super::types::ImagePart;
+use crate::validation::ImageValidator;
+
+/// Providers the backend can call directly, in order of preference
+pub const COMPLETION_PROVIDERS: [&str; 2] = ["anthropic", "openai"];
+
+const ANTHROPIC_MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const OPENAI_CHAT_URL: &str = "https://api.openai.com/v1/chat/completions";
+
+/// Output cap for backend completions, which are short plans and answers
+const MAX_COMPLETION_TOKENS: u32 = 1024;
+
+/// Small, inexpensive models used when the caller doesn't pick one
+fn default_model(provider: &str) -> Option<&'static str> {
+    match provider {
+        "anthropic" => Some("claude-3-5-haiku-20241022"),
+        "openai" => Some("gpt-4o-mini"),
+        _ => None,
+    }
+}
+
+/// A JSON schema the provider's reply must conform to
+pub struct ResponseSchema<'a> {
+    pub name: &'a str,
+    pub description: &'a str,
+    pub schema: &'a Value,
+}
+
+/// A prior user or assistant message sent with a multi-turn completion
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChatTurn {
+    pub role: String,
+    pub content: String,
+    /// Only sent on user turns
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImagePart>,
+}
+
+impl ChatTurn {
+    pub fn new(role: &str, content: &str) -> Self {
+        Self { role: role.to_string(), content: content.to_string(), images: Vec::new() }
+    }
+
+    pub fn user(content: &str) -> Self {
+        Self::new("user", content)
+    }
+}
+
+/// Shape turns the way both providers accept them: only user and assistant turns, starting with
+/// a user turn, with consecutive turns of the same role joined and images only on user turns
+pub fn normalize_turns(turns: &[ChatTurn]) -> Vec<ChatTurn> {
+    let mut normalized: Vec<ChatTurn> = Vec::new();
+    for turn in turns {
+        if turn.role != "user" && turn.role != "assistant" {
+            continue;
+        }
+        if normalized.is_empty() && turn.role != "user" {
+            continue;
+        }
+        match normalized.last_mut() {
+            Some(last) if last.role == turn.role => {
+                last.content.push_str("\n\n");
+                last.content.push_str(&turn.content);
+                last.images.extend(turn.images.iter().cloned());
+            }
+            _ => normalized.push(turn.clone()),
+        }
+    }
+    for turn in normalized.iter_mut().filter(|turn| turn.role != "user") {
+        turn.images.clear();
+    }
+    normalized
+}
+
+/// Turn every image into validated base64 with its detected media type; file images are read
+/// through the active workspace's path policy
+pub fn resolve_images(state: &AIState, turns: &[ChatTurn]) -> Result<Vec<ChatTurn>> {
+    turns.iter().map(|turn| {
+        ImageValidator::validate_image_count(turn.images.len())?;
+        let images = turn.images.iter().map(|image| {
+            let (bytes, declared) = match image {
+                ImagePart::Base64 { media_type, data } => {
+                    (BASE64.decode(data.trim()).context("Image data is not valid base64")?, media_type.as_deref())
+                }
+                ImagePart::Path { path } => {
+                    let path = enforce_path_policy(&state.storage, None, path, PathAccess::Read)
+                        .map_err(|e| anyhow!(e))?;
+                    let bytes = std::fs::read(&path)
+                        .with_context(|| format!("Failed to read image {}", path.display()))?;
+                    (bytes, None)
+                }
+            };
+            let media_type = ImageValidator::validate_image(&bytes, declared)?;
+            Ok(ImagePart::Base64 { media_type: Some(media_type.to_string()), data: BASE64.encode(bytes) })
+        }).collect::<Result<Vec<_>>>()?;
+        Ok(ChatTurn { images, ..turn.clone() })
+    }).collect()
+}
+
+/// Message content in the provider's format: plain text, or content blocks when images are attached
+fn turn_content(provider: &str, turn: &ChatTurn) -> Result<Value> {
+    if turn.images.is_empty() {
+        return Ok(json!(turn.content));
+    }
+    let mut images = Vec::new();
+    for image in &turn.images {
+        let (media_type, data) = match image {
+            ImagePart::Base64 { media_type: Some(media_type), data } => (media_type, data),
+            _ => bail!("Images must be resolved before they are sent"),
+        };
+        images.push(match provider {
+            "anthropic" => json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": media_type, "data": data },
+            }),
+            _ => json!({
+                "type": "image_url",
+                "image_url": { "url": format!("data:{};base64,{}", media_type, data) },
+            }),
+        });
+    }
+    let text = json!({ "type": "text", "text": turn.content });
+    // Anthropic recommends images ahead of the text that refers to them
+    Ok(Value::Array(match provider {
+        "anthropic" => images.into_iter().chain(std::iter::once(text)).collect(),
+        _ => std::iter::once(text).chain(images).collect(),
+    }))
+}
+
+/// Whether OpenAI's strict mode accepts the schema: every object closes its properties and
+/// requires all of them
+fn strict_compatible(schema: &Value) -> bool {
+    let Some(node) = schema.as_object() else { return true };
+    let properties = node.get("properties").and_then(Value::as_object);
+    if properties.is_some() || node.get("type") == Some(&json!("object")) {
+        let required: Vec<&str> = node.get("required").and_then(Value::as_array).into_iter().flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        if node.get("additionalProperties") != Some(&Value::Bool(false))
+            || properties.is_some_and(|properties| properties.keys().any(|key| !required.contains(&key.as_str())))
+        {
+            return false;
+        }
+    }
+    let children = properties.into_iter()
+        .chain(["$defs", "definitions"].iter().filter_map(|key| node.get(*key).and_then(Value::as_object)))
+        .flat_map(|map| map.values());
+    let branches = ["anyOf", "allOf", "oneOf"].iter()
+        .filter_map(|key| node.get(*key).and_then(Value::as_array))
+        .flatten();
+    node.get("items").into_iter().chain(children).chain(branches).all(strict_compatible)
+}
+
+/// Build the provider request; with a schema the reply is forced into that shape (a forced tool
+/// call for Anthropic, `json_schema` output for OpenAI, strict when the schema allows it)
+fn build_request(
+    provider: &str,
+    api_key: &str,
+    model: &str,
+    system: &str,
+    turns: &[ChatTurn],
+    schema: Option<&ResponseSchema>,
+) -> Result<HttpRequest> {
+    let messages: Vec<Value> = turns.iter()
+        .map(|turn| Ok(json!({ "role": turn.role, "content": turn_content(provider, turn)? })))
+        .collect::<Result<_>>()?;
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), "application/json".to_string());
+
+    let (url, body) = match provider {
+        "anthropic" => {
+            headers.insert("x-api-key".to_string(), api_key.to_string());
+            headers.insert("anthropic-version".to_string(), ANTHROPIC_VERSION.to_string());
+            let mut body = json!({
+                "model": model,
+                "max_tokens": MAX_COMPLETION_TOKENS,
+                "system": system,
+                "messages": messages,
+            });
+            if let Some(schema) = schema {
+                body["tools"] = json!([{
+                    "name": schema.name,
+                    "description": schema.description,
+                    "input_schema": schema.schema,
+                }]);
+                body["tool_choice"] = json!({ "type": "tool", "name": schema.name });
+            }
+            (ANTHROPIC_MESSAGES_URL, body)
+        }
+        "openai" => {
+            headers.insert("authorization".to_string(), format!("Bearer {}", api_key));
+            let mut body = json!({
+                "model": model,
+                "max_tokens": MAX_COMPLETION_TOKENS,
+                "messages": std::iter::once(json!({ "role": "system", "content": system }))
+                    .chain(messages)
+                    .collect::<Vec<_>>(),
+            });
+            if let Some(schema) = schema {
+                body["response_format"] = json!({
+                    "type": "json_schema",
+                    "json_schema": {
+                        "name": schema.name,
+                        "description": schema.description,
+                        "strict": strict_compatible(schema.schema),
+                        "schema": schema.schema,
+                    },
+                });
+            }
+            (OPENAI_CHAT_URL, body)
+        }
+        other => bail!("Provider {} is not supported for backend completions", other),
+    };
+
+    Ok(HttpRequest {
+        url: url.to_string(),
+        method: "POST".to_string(),
+        headers: Some(headers),
+        body: Some(body.to_string()),
+        retry_policy: None,
+        use_cache: false,
+    })
+}
+
+/// Pull the reply out of a provider response: the structured value when a schema was sent, else the text
+fn parse_response(provider: &str, body: &str, structured: bool) -> Result<Value> {
+    let response: Value = serde_json::from_str(body).context("Provider returned invalid JSON")?;
+
+    match provider {
+        "anthropic" => {
+            let content = response["content"].as_array()
+                .ok_or_else(|| anyhow!("Provider response has no content"))?;
+            if structured {
+                content.iter()
+                    .find(|block| block["type"] == "tool_use")
+                    .map(|block| block["input"].clone())
+                    .ok_or_else(|| anyhow!("Provider did not return structured output"))
+            } else {
+                let text: Vec<&str> = content.iter()
+                    .filter(|block| block["type"] == "text")
+                    .filter_map(|block| block["text"].as_str())
+                    .collect();
+                Ok(Value::String(text.join("")))
+            }
+        }
+        _ => {
+            let message = &response["choices"][0]["message"];
+            if let Some(refusal) = message["refusal"].as_str() {
+                bail!("Provider refused the request: {}", refusal);
+            }
+            let text = message["content"].as_str()
+                .ok_or_else(|| anyhow!("Provider response has no message content"))?;
+            if structured {
+                serde_json::from_str(text).context("Provider returned malformed structured output")
+            } else {
+                Ok(Value::String(text.to_string()))
+            }
+        }
+    }
+}
+
+/// The requested provider if it has a key, otherwise the first supported provider that does
+pub fn select_completion_provider(state: &AIState, preferred: Option<&str>) -> Result<String> {
+    let configured = state.storage.list_providers()?;
+    if let Some(preferred) = preferred {
+        if !COMPLETION_PROVIDERS.contains(&preferred) {
+            bail!("Provider {} is not supported for backend completions", preferred);
+        }
+        if !configured.iter().any(|p| p == preferred) {
+            bail!("No API key configured for {}", preferred);
+        }
+        return Ok(preferred.to_string());
+    }
+
+    COMPLETION_PROVIDERS.iter()
+        .find(|p| configured.iter().any(|c| c == *p))
+        .map(|p| p.to_string())
+        .ok_or_else(|| anyhow!("No API key configured for a supported provider ({})", COMPLETION_PROVIDERS.join(", ")))
+}
+
+/// Every completion passes `guard`: user turns are checked as input on the way out and the
+/// reply as output on the way back
+async fn complete(
+    state: &AIState,
+    guard: &mut GuardrailScope<'_>,
+    provider: &str,
+    model: Option<&str>,
+    system: &str,
+    turns: &[ChatTurn],
+    schema: Option<&ResponseSchema<'_>>,
+) -> Result<Value> {
+    let api_key = state.storage.get_api_key(provider)?
+        .ok_or_else(|| anyhow!("No API key configured for {}", provider))?;
+    let model = model.or_else(|| default_model(provider))
+        .ok_or_else(|| anyhow!("No model given for {}", provider))?;
+
+    let turns = turns.iter()
+        .map(|turn| match turn.role.as_str() {
+            "user" => Ok(ChatTurn { content: guard.check(GuardrailStage::Input, &turn.content)?, ..turn.clone() }),
+            _ => Ok(turn.clone()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let turns = resolve_images(state, &turns)?;
+    info!("Requesting {} completion from {} ({})", if schema.is_some() { "structured" } else { "text" }, provider, model);
+    let mut request = build_request(provider, &api_key, model, system, &turns, schema)?;
+    request.retry_policy = state.http_client.provider_retry_policy(provider);
+    // Some providers take the key in the URL, which request errors quote
+    let response = state.http_client.make_request(request).await.map_err(|e| anyhow!(redact_error(e)))?;
+    if !(200..300).contains(&response.status) {
+        let detail: String = response.body.chars().take(500).collect();
+        bail!("{} returned HTTP {}: {}", provider, response.status, redact_secrets(&detail).0);
+    }
+
+    let mut reply = parse_response(provider, &response.body, schema.is_some())?;
+    guard.check_value(GuardrailStage::Output, &mut reply)?;
+    Ok(reply)
+}
+
+/// Ask the provider for a reply matching `schema`
+pub async fn complete_structured(
+    state: &AIState,
+    guard: &mut GuardrailScope<'_>,
+    provider: &str,
+    model: Option<&str>,
+    system: &str,
+    prompt: &str,
+    schema: &ResponseSchema<'_>,
+) -> Result<Value> {
+    complete(state, guard, provider, model, system, &[ChatTurn::user(prompt)], Some(schema)).await
+}
+
+/// Ask the provider for a plain-text reply
+pub async fn complete_text(
+    state: &AIState,
+    guard: &mut GuardrailScope<'_>,
+    provider: &str,
+    model: Option<&str>,
+    system: &str,
+    prompt: &str,
+) -> Result<String> {
+    complete_conversation(state, guard, provider, model, system, &[ChatTurn::user(prompt)]).await
+}
+
+/// Ask the provider for a plain-text reply continuing `turns`, which must end with a user turn
+pub async fn complete_conversation(
+    state: &AIState,
+    guard: &mut GuardrailScope<'_>,
+    provider: &str,
+    model: Option<&str>,
+    system: &str,
+    turns: &[ChatTurn],
+) -> Result<String> {
+    let reply = complete(state, guard, provider, model, system, turns, None).await?;
+    Ok(reply.as_str().unwrap_or_default().trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_is_enforced_per_provider() {
+        let schema_value = json!({ "type": "object", "properties": {}, "required": [], "additionalProperties": false });
+        let schema = ResponseSchema { name: "plan", description: "A plan", schema: &schema_value };
+
+        let turns = [ChatTurn::user("hi")];
+        let anthropic = build_request("anthropic", "k", "m", "sys", &turns, Some(&schema)).unwrap();
+        let body: Value = serde_json::from_str(anthropic.body.as_deref().unwrap()).unwrap();
+        assert_eq!(body["tool_choice"]["name"], "plan");
+        assert_eq!(anthropic.headers.as_ref().unwrap()["x-api-key"], "k");
+
+        let openai = build_request("openai", "k", "m", "sys", &turns, Some(&schema)).unwrap();
+        let body: Value = serde_json::from_str(openai.body.as_deref().unwrap()).unwrap();
+        assert_eq!(body["response_format"]["json_schema"]["strict"], true);
+        let open = json!({
+            "type": "object",
+            "properties": { "tags": { "type": "array", "items": { "type": "object" } } },
+            "required": ["tags"],
+            "additionalProperties": false,
+        });
+        assert!(!strict_compatible(&open));
+        assert!(strict_compatible(&schema_value));
+        assert_eq!(body["messages"][0]["role"], "system");
+
+        assert!(build_request("mistral", "k", "m", "sys", &turns, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_structured_and_text_replies() {
+        let anthropic = r#"{"content":[{"type":"text","text":"ok"},{"type":"tool_use","name":"plan","input":{"steps":[]}}]}"#;
+        assert_eq!(parse_response("anthropic", anthropic, true).unwrap(), json!({ "steps": [] }));
+        assert_eq!(parse_response("anthropic", anthropic, false).unwrap(), json!("ok"));
+
+        let openai = r#"{"choices":[{"message":{"content":"{\"steps\":[1]}"}}]}"#;
+        assert_eq!(parse_response("openai", openai, true).unwrap(), json!({ "steps": [1] }));
+        let refused = r#"{"choices":[{"message":{"content":null,"refusal":"no"}}]}"#;
+        assert!(parse_response("openai", refused, true).is_err());
+    }
+
+    #[test]
+    fn test_normalize_turns() {
+        let turn = ChatTurn::new;
+        let turns = [
+            turn("assistant", "Hello!"),
+            turn("user", "a"),
+            turn("tool", "{}"),
+            turn("user", "b"),
+            turn("assistant", "c"),
+        ];
+        assert_eq!(normalize_turns(&turns), vec![turn("user", "a\n\nb"), turn("assistant", "c")]);
+    }
+
+    #[test]
+    fn test_images_are_encoded_per_provider() {
+        let image = ImagePart::Base64 { media_type: Some("image/png".to_string()), data: "iVBORw0K".to_string() };
+        let turns = normalize_turns(&[
+            ChatTurn { images: vec![image.clone()], ..ChatTurn::user("What is this?") },
+            ChatTurn { images: vec![image.clone()], ..ChatTurn::new("assistant", "A logo") },
+        ]);
+        assert!(turns[1].images.is_empty());
+
+        let anthropic = build_request("anthropic", "k", "m", "sys", &turns, None).unwrap();
+        let body: Value = serde_json::from_str(anthropic.body.as_deref().unwrap()).unwrap();
+        let content = &body["messages"][0]["content"];
+        assert_eq!(content[0]["source"], json!({ "type": "base64", "media_type": "image/png", "data": "iVBORw0K" }));
+        assert_eq!(content[1], json!({ "type": "text", "text": "What is this?" }));
+        assert_eq!(body["messages"][1]["content"], "A logo");
+
+        let openai = build_request("openai", "k", "m", "sys", &turns, None).unwrap();
+        let body: Value = serde_json::from_str(openai.body.as_deref().unwrap()).unwrap();
+        let content = &body["messages"][1]["content"];
+        assert_eq!(content[0]["text"], "What is this?");
+        assert_eq!(content[1]["image_url"]["url"], "data:image/png;base64,iVBORw0K");
+
+        let unresolved = [ChatTurn { images: vec![ImagePart::Path { path: "/tmp/a.png".to_string() }], ..ChatTurn::user("hi") }];
+        assert!(build_request("openai", "k", "m", "sys", &unresolved, None).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::storage::StorageManager;
+
+/// Settings key holding the persisted `RateLimitConfig`
+pub const RATE_LIMIT_SETTING_KEY: &str = "rate_limits.config";
+
+/// Idle buckets are dropped once the map grows past this size
+const MAX_TRACKED_BUCKETS: usize = 2048;
+
+/// Token bucket parameters: `capacity` is the burst size, refilled continuously
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BucketConfig {
+    pub capacity: f64,
+    pub refill_per_second: f64,
+}
+
+impl BucketConfig {
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            capacity: requests as f64,
+            refill_per_second: requests as f64 / 60.0,
+        }
+    }
+}
+
+/// Limits for each level of the global → provider → agent → operation hierarchy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub global: BucketConfig,
+    pub default_provider: BucketConfig,
+    #[serde(default)]
+    pub providers: HashMap<String, BucketConfig>,
+    pub default_agent: BucketConfig,
+    #[serde(default)]
+    pub agents: HashMap<String, BucketConfig>,
+    pub default_operation: BucketConfig,
+    #[serde(default)]
+    pub operations: HashMap<String, BucketConfig>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            global: BucketConfig::per_minute(600),
+            default_provider: BucketConfig::per_minute(30),
+            providers: HashMap::from([
+                ("openai".to_string(), BucketConfig::per_minute(60)),
+                ("anthropic".to_string(), BucketConfig::per_minute(50)),
+            ]),
+            default_agent: BucketConfig::per_minute(120),
+            agents: HashMap::new(),
+            default_operation: BucketConfig::per_minute(30),
+            operations: HashMap::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    fn bucket_config(&self, level: BucketLevel, name: &str) -> BucketConfig {
+        match level {
+            BucketLevel::Global => self.global,
+            BucketLevel::Provider => self.providers.get(name).copied().unwrap_or(self.default_provider),
+            BucketLevel::Agent => self.agents.get(name).copied().unwrap_or(self.default_agent),
+            BucketLevel::Operation => self.operations.get(name).copied().unwrap_or(self.default_operation),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let all = [self.global, self.default_provider, self.default_agent, self.default_operation].into_iter()
+            .chain(self.providers.values().copied())
+            .chain(self.agents.values().copied())
+            .chain(self.operations.values().copied());
+        for bucket in all {
+            if !(bucket.capacity >= 1.0 && bucket.refill_per_second > 0.0) {
+                return Err("Bucket capacity must be at least 1 and refill rate positive".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BucketLevel {
+    Global,
+    Provider,
+    Agent,
+    Operation,
+}
+
+/// Who is making a request; unset levels are skipped in the hierarchy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitScope {
+    pub provider: Option<String>,
+    pub agent_id: Option<String>,
+    pub operation: String,
+}
+
+impl RateLimitScope {
+    pub fn operation(operation: &str) -> Self {
+        Self { provider: None, agent_id: None, operation: operation.to_string() }
+    }
+
+    /// Bucket keys from the root down, e.g. `provider:openai/agent:a1/operation:chat`
+    fn path(&self) -> Vec<(BucketLevel, String, String)> {
+        let mut path = vec![(BucketLevel::Global, "global".to_string(), "global".to_string())];
+        let mut prefix = String::new();
+        let levels = [
+            (BucketLevel::Provider, "provider", self.provider.as_deref()),
+            (BucketLevel::Agent, "agent", self.agent_id.as_deref()),
+            (BucketLevel::Operation, "operation", Some(self.operation.as_str())),
+        ];
+        for (level, label, name) in levels {
+            if let Some(name) = name {
+                if !prefix.is_empty() {
+                    prefix.push('/');
+                }
+                prefix.push_str(&format!("{}:{}", label, name));
+                path.push((level, prefix.clone(), name.to_string()));
+            }
+        }
+        path
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitRejection {
+    pub bucket: String,
+    pub retry_after_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitBucketStats {
+    pub key: String,
+    pub level: BucketLevel,
+    pub capacity: f64,
+    pub refill_per_second: f64,
+    pub available: f64,
+    pub allowed: u64,
+    pub rejected: u64,
+    pub next_available_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    level: BucketLevel,
+    name: String,
+    tokens: f64,
+    last_refill: Instant,
+    allowed: u64,
+    rejected: u64,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, config: BucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.refill_per_second).min(config.capacity);
+        self.last_refill = now;
+    }
+
+    fn wait_for_token(&self, config: BucketConfig) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / config.refill_per_second)
+        }
+    }
+}
+
+/// Hierarchical token buckets; a request must take a token from every level of its scope
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: HashMap<String, TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self { config, buckets: HashMap::new() }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// New limits apply immediately; stored tokens are clamped to the new capacities
+    pub fn set_config(&mut self, config: RateLimitConfig) {
+        for bucket in self.buckets.values_mut() {
+            let capacity = config.bucket_config(bucket.level, &bucket.name).capacity;
+            bucket.tokens = bucket.tokens.min(capacity);
+        }
+        self.config = config;
+    }
+
+    pub fn acquire(&mut self, scope: &RateLimitScope, now: Instant) -> Result<(), RateLimitRejection> {
+        if self.buckets.len() > MAX_TRACKED_BUCKETS {
+            self.prune(now);
+        }
+
+        let path = scope.path();
+        let mut blocking: Option<(String, Duration)> = None;
+        for (level, key, name) in &path {
+            let config = self.config.bucket_config(*level, name);
+            let bucket = self.buckets.entry(key.clone()).or_insert_with(|| TokenBucket {
+                level: *level,
+                name: name.clone(),
+                tokens: config.capacity,
+                last_refill: now,
+                allowed: 0,
+                rejected: 0,
+            });
+            bucket.refill(config, now);
+
+            let wait = bucket.wait_for_token(config);
+            if !wait.is_zero() && blocking.as_ref().map_or(true, |(_, longest)| wait > *longest) {
+                blocking = Some((key.clone(), wait));
+            }
+        }
+
+        // Tokens are only taken when every level has one, so a rejection costs nothing
+        if let Some((bucket, wait)) = blocking {
+            if let Some(b) = self.buckets.get_mut(&bucket) {
+                b.rejected += 1;
+            }
+            return Err(RateLimitRejection { bucket, retry_after_ms: wait.as_millis() as u64 });
+        }
+        for (_, key, _) in &path {
+            if let Some(bucket) = self.buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+                bucket.allowed += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop buckets that have refilled completely; they behave exactly like new ones
+    fn prune(&mut self, now: Instant) {
+        let config = &self.config;
+        self.buckets.retain(|key, bucket| {
+            let bucket_config = config.bucket_config(bucket.level, &bucket.name);
+            bucket.refill(bucket_config, now);
+            key == "global" || bucket.tokens < bucket_config.capacity
+        });
+    }
+
+    /// Usage per bucket, optionally limited to keys containing `filter`
+    pub fn stats(&mut self, filter: Option<&str>) -> Vec<RateLimitBucketStats> {
+        let now = Instant::now();
+        let wall_now = chrono::Utc::now();
+        let mut stats: Vec<RateLimitBucketStats> = self.buckets.iter_mut()
+            .filter(|(key, _)| filter.map_or(true, |f| key.contains(f)))
+            .map(|(key, bucket)| {
+                let config = self.config.bucket_config(bucket.level, &bucket.name);
+                bucket.refill(config, now);
+                let wait = chrono::Duration::from_std(bucket.wait_for_token(config)).unwrap_or_default();
+                RateLimitBucketStats {
+                    key: key.clone(),
+                    level: bucket.level,
+                    capacity: config.capacity,
+                    refill_per_second: config.refill_per_second,
+                    available: bucket.tokens,
+                    allowed: bucket.allowed,
+                    rejected: bucket.rejected,
+                    next_available_at: wall_now + wait,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.key.cmp(&b.key));
+        stats
+    }
+}
+
+pub fn load_rate_limit_config(storage: &StorageManager) -> Result<RateLimitConfig> {
+    match storage.get_setting(RATE_LIMIT_SETTING_KEY)? {
+        Some(value) => serde_json::from_value(value).context("Stored rate limit config is malformed"),
+        None => Ok(RateLimitConfig::default()),
+    }
+}
+
+pub fn save_rate_limit_config(storage: &StorageManager, config: &RateLimitConfig) -> Result<()> {
+    storage.set_setting(RATE_LIMIT_SETTING_KEY, serde_json::to_value(config)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(agent: &str, operation: &str) -> RateLimitScope {
+        RateLimitScope {
+            provider: Some("openai".into()),
+            agent_id: Some(agent.into()),
+            operation: operation.into(),
+        }
+    }
+
+    #[test]
+    fn test_agent_bucket_limits_only_that_agent() {
+        let mut config = RateLimitConfig::default();
+        config.default_agent = BucketConfig { capacity: 2.0, refill_per_second: 1.0 };
+        let mut limiter = RateLimiter::new(config);
+        let now = Instant::now();
+
+        assert!(limiter.acquire(&scope("a1", "chat"), now).is_ok());
+        assert!(limiter.acquire(&scope("a1", "tools"), now).is_ok());
+        let rejection = limiter.acquire(&scope("a1", "chat"), now).unwrap_err();
+        assert_eq!(rejection.bucket, "provider:openai/agent:a1");
+        assert_eq!(rejection.retry_after_ms, 1000);
+
+        // Another agent under the same provider still has tokens
+        assert!(limiter.acquire(&scope("a2", "chat"), now).is_ok());
+        // Refill restores the agent's bucket
+        assert!(limiter.acquire(&scope("a1", "chat"), now + Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_rejection_does_not_consume_and_stats_report_usage() {
+        let mut config = RateLimitConfig::default();
+        config.global = BucketConfig { capacity: 1.0, refill_per_second: 0.5 };
+        let mut limiter = RateLimiter::new(config);
+        let now = Instant::now();
+
+        assert!(limiter.acquire(&RateLimitScope::operation("memory_operations"), now).is_ok());
+        assert_eq!(limiter.acquire(&RateLimitScope::operation("memory_operations"), now).unwrap_err().bucket, "global");
+
+        let stats = limiter.stats(Some("operation:memory"));
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].allowed, 1);
+        assert_eq!(stats[0].rejected, 0);
+        assert!(stats[0].available >= 29.0);
+
+        let global = &limiter.stats(Some("global"))[0];
+        assert_eq!(global.rejected, 1);
+        assert!(global.next_available_at > chrono::Utc::now());
+    }
+}
@@ -0,0 +1,309 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+use crate::database::{open_conversation_db, parse_db_timestamp};
+
+/// Repair prompts issued per message before validation gives up
+const DEFAULT_MAX_REPAIR_ATTEMPTS: u32 = 2;
+
+/// Longest excerpt of the failed response quoted back in a repair prompt
+const MAX_QUOTED_CHARS: usize = 4000;
+
+/// What an assistant response is expected to look like
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseExpectations {
+    /// Response must be a JSON document (a fenced ```json block is accepted)
+    #[serde(default)]
+    pub json: bool,
+    /// Top-level keys the JSON object must contain
+    #[serde(default)]
+    pub required_keys: Vec<String>,
+    /// Markdown headings (or `Name:` lines) that must appear
+    #[serde(default)]
+    pub required_sections: Vec<String>,
+    /// Maximum length in characters
+    pub max_length: Option<usize>,
+    /// Case-insensitive phrases that must not appear
+    #[serde(default)]
+    pub banned_content: Vec<String>,
+    pub max_repair_attempts: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseIssueKind {
+    InvalidJson,
+    MissingKey,
+    MissingSection,
+    TooLong,
+    BannedContent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseIssue {
+    pub kind: ResponseIssueKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseCheck {
+    pub valid: bool,
+    pub issues: Vec<ResponseIssue>,
+    pub attempt: u32,
+    /// Prompt to send back to the model; absent when valid or out of attempts
+    pub repair_prompt: Option<String>,
+    pub exhausted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageRepairRecord {
+    pub id: String,
+    pub message_id: String,
+    pub attempt: u32,
+    pub valid: bool,
+    pub issues: Vec<ResponseIssue>,
+    pub repair_prompt: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Body of a ```json fenced block, or the trimmed text when there is none
+fn json_body(content: &str) -> &str {
+    let trimmed = content.trim();
+    if let Some(start) = trimmed.find("```") {
+        let after = &trimmed[start + 3..];
+        let after = after.strip_prefix("json").unwrap_or(after);
+        if let Some(end) = after.find("```") {
+            return after[..end].trim();
+        }
+    }
+    trimmed
+}
+
+fn has_section(content: &str, section: &str) -> bool {
+    let wanted = section.trim().to_lowercase();
+    content.lines().any(|line| {
+        let line = line.trim();
+        let heading = line.trim_start_matches('#').trim().trim_matches('*').trim();
+        let label = line.trim_matches('*').trim().strip_suffix(':').map(|l| l.trim_matches('*').trim());
+        (line.starts_with('#') && heading.to_lowercase() == wanted)
+            || label.is_some_and(|l| l.to_lowercase() == wanted)
+    })
+}
+
+/// Check a response against its expectations
+pub fn validate_response(content: &str, expectations: &ResponseExpectations) -> Vec<ResponseIssue> {
+    let mut issues = Vec::new();
+
+    if expectations.json || !expectations.required_keys.is_empty() {
+        match serde_json::from_str::<serde_json::Value>(json_body(content)) {
+            Ok(value) => {
+                for key in &expectations.required_keys {
+                    if value.get(key).is_none() {
+                        issues.push(ResponseIssue {
+                            kind: ResponseIssueKind::MissingKey,
+                            detail: format!("JSON is missing required key \"{}\"", key),
+                        });
+                    }
+                }
+            }
+            Err(e) => issues.push(ResponseIssue {
+                kind: ResponseIssueKind::InvalidJson,
+                detail: format!("Response is not valid JSON: {}", e),
+            }),
+        }
+    }
+
+    for section in &expectations.required_sections {
+        if !has_section(content, section) {
+            issues.push(ResponseIssue {
+                kind: ResponseIssueKind::MissingSection,
+                detail: format!("Missing required section \"{}\"", section),
+            });
+        }
+    }
+
+    if let Some(max) = expectations.max_length {
+        let length = content.chars().count();
+        if length > max {
+            issues.push(ResponseIssue {
+                kind: ResponseIssueKind::TooLong,
+                detail: format!("Response is {} characters; the limit is {}", length, max),
+            });
+        }
+    }
+
+    let lowered = content.to_lowercase();
+    for phrase in &expectations.banned_content {
+        if !phrase.trim().is_empty() && lowered.contains(&phrase.to_lowercase()) {
+            issues.push(ResponseIssue {
+                kind: ResponseIssueKind::BannedContent,
+                detail: format!("Response contains disallowed content \"{}\"", phrase),
+            });
+        }
+    }
+
+    issues
+}
+
+/// Instruction asking the model to fix exactly the reported problems
+pub fn build_repair_prompt(content: &str, issues: &[ResponseIssue], expectations: &ResponseExpectations) -> String {
+    let mut prompt = String::from("Your previous response did not meet the required format. Fix these problems:\n");
+    for issue in issues {
+        prompt.push_str(&format!("- {}\n", issue.detail));
+    }
+
+    if expectations.json {
+        prompt.push_str("\nReply with only the corrected JSON document and no surrounding text.");
+    } else {
+        prompt.push_str("\nReply with the complete corrected response only.");
+    }
+    if let Some(max) = expectations.max_length {
+        prompt.push_str(&format!(" Keep it under {} characters.", max));
+    }
+
+    let quoted: String = content.chars().take(MAX_QUOTED_CHARS).collect();
+    prompt.push_str(&format!("\n\nPrevious response:\n{}", quoted));
+    prompt
+}
+
+/// Validate a response and, while attempts remain, produce a repair prompt
+pub fn check_response(content: &str, expectations: &ResponseExpectations, attempt: u32) -> ResponseCheck {
+    let issues = validate_response(content, expectations);
+    let max_attempts = expectations.max_repair_attempts.unwrap_or(DEFAULT_MAX_REPAIR_ATTEMPTS);
+    let valid = issues.is_empty();
+    let exhausted = !valid && attempt >= max_attempts;
+
+    ResponseCheck {
+        repair_prompt: (!valid && !exhausted).then(|| build_repair_prompt(content, &issues, expectations)),
+        valid,
+        issues,
+        attempt,
+        exhausted,
+    }
+}
+
+fn record_check(conn: &Connection, message_id: &str, check: &ResponseCheck) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO message_repairs (id, message_id, attempt, valid, issues, repair_prompt) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            message_id,
+            check.attempt,
+            check.valid,
+            serde_json::to_string(&check.issues)?,
+            check.repair_prompt,
+        ],
+    )?;
+    Ok(())
+}
+
+fn load_repairs(conn: &Connection, message_id: &str) -> anyhow::Result<Vec<MessageRepairRecord>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, message_id, attempt, valid, issues, repair_prompt, created_at
+         FROM message_repairs WHERE message_id = ?1 ORDER BY attempt, created_at"
+    )?;
+    let rows = stmt.query_map(params![message_id], |row| {
+        let issues: String = row.get(4)?;
+        let created_at: String = row.get(6)?;
+        Ok(MessageRepairRecord {
+            id: row.get(0)?,
+            message_id: row.get(1)?,
+            attempt: row.get(2)?,
+            valid: row.get(3)?,
+            issues: serde_json::from_str(&issues).unwrap_or_default(),
+            repair_prompt: row.get(5)?,
+            created_at: parse_db_timestamp(&created_at),
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Validate an assistant response; attempt 0 is the original, each repair increments it
+#[tauri::command]
+pub async fn validate_assistant_response(
+    content: String,
+    expectations: ResponseExpectations,
+    attempt: Option<u32>,
+    message_id: Option<String>,
+    app_handle: AppHandle,
+) -> Result<ResponseCheck, String> {
+    let check = check_response(&content, &expectations, attempt.unwrap_or(0));
+
+    if !check.valid {
+        info!("Response failed validation (attempt {}): {} issue(s)", check.attempt, check.issues.len());
+    }
+
+    // Only repairs are worth recording: a first-pass success leaves no trace on the message
+    if let Some(message_id) = message_id.filter(|_| !check.valid || check.attempt > 0) {
+        if let Err(e) = open_conversation_db(&app_handle).and_then(|conn| record_check(&conn, &message_id, &check)) {
+            warn!("Failed to record repair attempt for message {}: {}", message_id, e);
+        }
+    }
+
+    Ok(check)
+}
+
+#[tauri::command]
+pub async fn get_message_repairs(
+    message_id: String,
+    app_handle: AppHandle,
+) -> Result<Vec<MessageRepairRecord>, String> {
+    open_conversation_db(&app_handle)
+        .and_then(|conn| load_repairs(&conn, &message_id))
+        .map_err(|e| format!("Failed to load repair history: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_issues_and_bounded_repair() {
+        let expectations = ResponseExpectations {
+            json: true,
+            required_keys: vec!["answer".into(), "sources".into()],
+            banned_content: vec!["As an AI".into()],
+            max_repair_attempts: Some(1),
+            ..Default::default()
+        };
+
+        let fenced = "Here you go:\n```json\n{\"answer\": 42, \"sources\": []}\n```";
+        assert!(check_response(fenced, &expectations, 0).valid);
+
+        let check = check_response("{\"answer\": \"as an ai, 42\"}", &expectations, 0);
+        let kinds: Vec<_> = check.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(kinds, vec![ResponseIssueKind::MissingKey, ResponseIssueKind::BannedContent]);
+        assert!(check.repair_prompt.as_deref().unwrap().contains("\"sources\""));
+
+        let exhausted = check_response("not json", &expectations, 1);
+        assert!(exhausted.exhausted);
+        assert!(exhausted.repair_prompt.is_none());
+    }
+
+    #[test]
+    fn test_sections_length_and_repair_records() {
+        let expectations = ResponseExpectations {
+            required_sections: vec!["Summary".into(), "Next steps".into()],
+            max_length: Some(40),
+            ..Default::default()
+        };
+        let long = format!("## Summary\n{}\n**Next steps:**", "x".repeat(50));
+        let issues = validate_response(&long, &expectations);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, ResponseIssueKind::TooLong);
+        assert!(validate_response("# Summary\nNext Steps:", &expectations).is_empty());
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(crate::database::INIT_SQL).unwrap();
+        record_check(&conn, "m1", &check_response("# Summary", &expectations, 0)).unwrap();
+        record_check(&conn, "m1", &check_response("# Summary\nNext steps:", &expectations, 1)).unwrap();
+
+        let records = load_repairs(&conn, "m1").unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(!records[0].valid);
+        assert_eq!(records[0].issues[0].kind, ResponseIssueKind::MissingSection);
+        assert!(records[1].valid);
+    }
+}
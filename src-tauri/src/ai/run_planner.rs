@@ -0,0 +1,487 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::State;
+use tracing::{info, warn};
+
+/// Output tokens assumed for a step that gives no estimate
+const DEFAULT_OUTPUT_TOKENS: u64 = 800;
+
+/// Duration assumed for a step that gives no estimate
+const DEFAULT_STEP_DURATION_MS: u64 = 15_000;
+
+/// Observed/estimated cost ratio is clamped to this range when re-planning
+const CALIBRATION_RANGE: (f64, f64) = (0.25, 4.0);
+
+/// Limits for a single autonomous run; unset limits are unbounded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunBudget {
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+    pub max_duration_ms: Option<u64>,
+}
+
+/// A model the planner may assign, priced like the frontend model configs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannerModel {
+    pub model_id: String,
+    pub input_tokens_per_1k: f64,
+    pub output_tokens_per_1k: f64,
+    /// Relative capability; higher is stronger
+    pub capability: u8,
+}
+
+impl PlannerModel {
+    fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 / 1000.0) * self.input_tokens_per_1k
+            + (output_tokens as f64 / 1000.0) * self.output_tokens_per_1k
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum StepRisk {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStep {
+    pub id: String,
+    pub description: String,
+    pub risk: StepRisk,
+    pub estimated_input_tokens: Option<u64>,
+    pub estimated_output_tokens: Option<u64>,
+    pub estimated_duration_ms: Option<u64>,
+}
+
+impl RunStep {
+    fn input_tokens(&self) -> u64 {
+        // ~4 characters per token, same heuristic as memory budgeting
+        self.estimated_input_tokens
+            .unwrap_or_else(|| (self.description.chars().count() as u64 + 3) / 4)
+    }
+
+    fn output_tokens(&self) -> u64 {
+        self.estimated_output_tokens.unwrap_or(DEFAULT_OUTPUT_TOKENS)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepPlan {
+    pub step_id: String,
+    pub model_id: String,
+    pub estimated_tokens: u64,
+    pub estimated_cost_usd: f64,
+    pub estimated_duration_ms: u64,
+    /// A cheaper model than the step's risk would normally get
+    pub downgraded: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunPlan {
+    pub run_id: String,
+    pub steps: Vec<StepPlan>,
+    pub estimated_tokens: u64,
+    pub estimated_cost_usd: f64,
+    pub estimated_duration_ms: u64,
+    pub fits_budget: bool,
+}
+
+/// Actual usage reported by the runtime after a step finishes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepOutcome {
+    pub step_id: String,
+    pub model_id: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub duration_ms: u64,
+    /// Short result text kept for the partial-results summary
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub tokens: u64,
+    pub cost_usd: f64,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialRunSummary {
+    pub reason: String,
+    pub completed_steps: Vec<String>,
+    pub skipped_steps: Vec<String>,
+    pub results: Vec<StepOutcome>,
+    pub usage: RunUsage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RunDecision {
+    Continue { next: StepPlan, remaining: RunUsage },
+    Halt { summary: PartialRunSummary },
+    Complete { usage: RunUsage },
+}
+
+struct RunTracker {
+    steps: Vec<RunStep>,
+    budget: RunBudget,
+    models: Vec<PlannerModel>,
+    outcomes: Vec<StepOutcome>,
+    usage: RunUsage,
+    /// Sum of planned cost for completed steps, used to calibrate estimates
+    planned_cost: f64,
+    plan: Vec<StepPlan>,
+    started_at: DateTime<Utc>,
+}
+
+/// Runs being tracked by the planner, keyed by run id
+pub struct RunPlannerState {
+    runs: Mutex<HashMap<String, RunTracker>>,
+}
+
+impl RunPlannerState {
+    pub fn new() -> Self {
+        Self {
+            runs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn by_capability(models: &[PlannerModel]) -> Vec<&PlannerModel> {
+    let mut sorted: Vec<&PlannerModel> = models.iter().collect();
+    sorted.sort_by(|a, b| b.capability.cmp(&a.capability)
+        .then(a.cost(1000, 1000).partial_cmp(&b.cost(1000, 1000)).unwrap_or(std::cmp::Ordering::Equal)));
+    sorted
+}
+
+/// Model a step gets when the budget is not a concern
+fn preferred_model<'a>(ranked: &[&'a PlannerModel], risk: StepRisk) -> &'a PlannerModel {
+    match risk {
+        StepRisk::High => ranked[0],
+        StepRisk::Medium => ranked[ranked.len() / 2],
+        StepRisk::Low => ranked[ranked.len() - 1],
+    }
+}
+
+fn step_plan(step: &RunStep, model: &PlannerModel, calibration: f64, downgraded: bool) -> StepPlan {
+    let (input, output) = (step.input_tokens(), step.output_tokens());
+    StepPlan {
+        step_id: step.id.clone(),
+        model_id: model.model_id.clone(),
+        estimated_tokens: ((input + output) as f64 * calibration).round() as u64,
+        estimated_cost_usd: model.cost(input, output) * calibration,
+        estimated_duration_ms: step.estimated_duration_ms.unwrap_or(DEFAULT_STEP_DURATION_MS),
+        downgraded,
+    }
+}
+
+fn fits(plans: &[StepPlan], budget: &RunBudget, spent: &RunUsage) -> bool {
+    let tokens: u64 = plans.iter().map(|p| p.estimated_tokens).sum();
+    let cost: f64 = plans.iter().map(|p| p.estimated_cost_usd).sum();
+    let duration: u64 = plans.iter().map(|p| p.estimated_duration_ms).sum();
+
+    budget.max_tokens.map_or(true, |max| spent.tokens + tokens <= max)
+        && budget.max_cost_usd.map_or(true, |max| spent.cost_usd + cost <= max)
+        && budget.max_duration_ms.map_or(true, |max| spent.duration_ms + duration <= max)
+}
+
+/// Assign models to the remaining steps, downgrading lowest-risk steps first until the plan fits
+pub fn plan_steps(
+    steps: &[RunStep],
+    models: &[PlannerModel],
+    budget: &RunBudget,
+    spent: &RunUsage,
+    calibration: f64,
+) -> (Vec<StepPlan>, bool) {
+    let ranked = by_capability(models);
+    let mut plans: Vec<StepPlan> = steps.iter()
+        .map(|s| step_plan(s, preferred_model(&ranked, s.risk), calibration, false))
+        .collect();
+
+    let mut order: Vec<usize> = (0..steps.len()).collect();
+    order.sort_by_key(|&i| steps[i].risk);
+
+    // Each pass moves every step one tier cheaper, low-risk steps first; high-risk steps are never downgraded
+    let cheapest = ranked[ranked.len() - 1];
+    while !fits(&plans, budget, spent) {
+        let mut changed = false;
+        for &i in &order {
+            if steps[i].risk == StepRisk::High || fits(&plans, budget, spent) {
+                continue;
+            }
+            let current = ranked.iter().position(|m| m.model_id == plans[i].model_id).unwrap_or(0);
+            if ranked[current].model_id != cheapest.model_id {
+                plans[i] = step_plan(&steps[i], ranked[current + 1], calibration, true);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let fits_budget = fits(&plans, budget, spent);
+    (plans, fits_budget)
+}
+
+fn remaining(budget: &RunBudget, usage: &RunUsage) -> RunUsage {
+    RunUsage {
+        tokens: budget.max_tokens.map_or(u64::MAX, |max| max.saturating_sub(usage.tokens)),
+        cost_usd: budget.max_cost_usd.map_or(f64::INFINITY, |max| (max - usage.cost_usd).max(0.0)),
+        duration_ms: budget.max_duration_ms.map_or(u64::MAX, |max| max.saturating_sub(usage.duration_ms)),
+    }
+}
+
+impl RunTracker {
+    fn calibration(&self) -> f64 {
+        if self.planned_cost <= 0.0 || self.usage.cost_usd <= 0.0 {
+            return 1.0;
+        }
+        (self.usage.cost_usd / self.planned_cost).clamp(CALIBRATION_RANGE.0, CALIBRATION_RANGE.1)
+    }
+
+    fn summary(&self, reason: String) -> PartialRunSummary {
+        PartialRunSummary {
+            reason,
+            completed_steps: self.outcomes.iter().map(|o| o.step_id.clone()).collect(),
+            skipped_steps: self.steps[self.outcomes.len()..].iter().map(|s| s.id.clone()).collect(),
+            results: self.outcomes.clone(),
+            usage: self.usage.clone(),
+        }
+    }
+
+    fn halt(&self, reason: String) -> RunDecision {
+        RunDecision::Halt { summary: self.summary(reason) }
+    }
+
+    /// Cost of a finished step. A model outside the run's list is priced as the most
+    /// expensive listed model so unknown ids cannot slip past the budget for free.
+    fn step_cost(&self, outcome: &StepOutcome) -> f64 {
+        match self.models.iter().find(|m| m.model_id == outcome.model_id) {
+            Some(model) => model.cost(outcome.input_tokens, outcome.output_tokens),
+            None => {
+                warn!("Step {} used unplanned model {}; pricing it conservatively", outcome.step_id, outcome.model_id);
+                self.models.iter()
+                    .map(|m| m.cost(outcome.input_tokens, outcome.output_tokens))
+                    .fold(0.0, f64::max)
+            }
+        }
+    }
+
+    /// Re-plan what is left and decide whether the next step can still run
+    fn decide(&mut self) -> RunDecision {
+        let pending = &self.steps[self.outcomes.len()..];
+        if pending.is_empty() {
+            return RunDecision::Complete { usage: self.usage.clone() };
+        }
+
+        let (plans, _) = plan_steps(pending, &self.models, &self.budget, &self.usage, self.calibration());
+        let next = plans[0].clone();
+        self.plan.truncate(self.outcomes.len());
+        self.plan.extend(plans);
+
+        // The next step alone must fit; later steps may still be halted once real usage is known
+        if !fits(std::slice::from_ref(&next), &self.budget, &self.usage) {
+            return self.halt(format!(
+                "Budget exhausted before step '{}' (estimated ${:.4}, {} tokens)",
+                next.step_id, next.estimated_cost_usd, next.estimated_tokens
+            ));
+        }
+
+        RunDecision::Continue { next, remaining: remaining(&self.budget, &self.usage) }
+    }
+}
+
+// Tauri Commands
+
+/// Plan a run: estimate per-step cost and assign models within the budget
+#[tauri::command]
+pub async fn plan_agent_run(
+    steps: Vec<RunStep>,
+    budget: RunBudget,
+    models: Vec<PlannerModel>,
+    state: State<'_, RunPlannerState>,
+) -> Result<RunPlan, String> {
+    if steps.is_empty() {
+        return Err("A run needs at least one step".to_string());
+    }
+    if models.is_empty() {
+        return Err("At least one model is required".to_string());
+    }
+
+    let (plans, fits_budget) = plan_steps(&steps, &models, &budget, &RunUsage::default(), 1.0);
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let plan = RunPlan {
+        run_id: run_id.clone(),
+        estimated_tokens: plans.iter().map(|p| p.estimated_tokens).sum(),
+        estimated_cost_usd: plans.iter().map(|p| p.estimated_cost_usd).sum(),
+        estimated_duration_ms: plans.iter().map(|p| p.estimated_duration_ms).sum(),
+        steps: plans.clone(),
+        fits_budget,
+    };
+
+    if !fits_budget {
+        warn!("Run {} is estimated to exceed its budget; it will halt early", run_id);
+    }
+    info!("Planned run {} with {} steps (est. ${:.4})", run_id, steps.len(), plan.estimated_cost_usd);
+
+    state.runs.lock()
+        .map_err(|_| "Failed to acquire planner lock".to_string())?
+        .insert(run_id, RunTracker {
+            steps,
+            budget,
+            models,
+            outcomes: Vec::new(),
+            usage: RunUsage::default(),
+            planned_cost: 0.0,
+            plan: plans,
+            started_at: Utc::now(),
+        });
+    Ok(plan)
+}
+
+/// Record a finished step and get the next step, or a graceful halt with partial results
+#[tauri::command]
+pub async fn record_run_step(
+    run_id: String,
+    outcome: StepOutcome,
+    state: State<'_, RunPlannerState>,
+) -> Result<RunDecision, String> {
+    let mut runs = state.runs.lock()
+        .map_err(|_| "Failed to acquire planner lock".to_string())?;
+    let tracker = runs.get_mut(&run_id)
+        .ok_or_else(|| format!("Unknown run: {}", run_id))?;
+
+    let index = tracker.outcomes.len();
+    let expected = tracker.steps.get(index)
+        .ok_or_else(|| "All steps of this run are already recorded".to_string())?;
+    if expected.id != outcome.step_id {
+        return Err(format!("Expected outcome for step '{}', got '{}'", expected.id, outcome.step_id));
+    }
+
+    let cost = tracker.step_cost(&outcome);
+    tracker.planned_cost += tracker.plan.get(index).map_or(0.0, |p| p.estimated_cost_usd);
+    tracker.usage.tokens += outcome.input_tokens + outcome.output_tokens;
+    tracker.usage.cost_usd += cost;
+    // Wall-clock time includes gaps between steps, so prefer it over summed step durations
+    let elapsed = (Utc::now() - tracker.started_at).num_milliseconds().max(0) as u64;
+    tracker.usage.duration_ms = elapsed.max(tracker.usage.duration_ms + outcome.duration_ms);
+    tracker.outcomes.push(outcome);
+
+    let decision = tracker.decide();
+    if !matches!(decision, RunDecision::Continue { .. }) {
+        info!("Run {} finished: {:?}", run_id, tracker.usage);
+        runs.remove(&run_id);
+    }
+    Ok(decision)
+}
+
+/// Stop a run early and return what it produced so far
+#[tauri::command]
+pub async fn halt_agent_run(
+    run_id: String,
+    reason: Option<String>,
+    state: State<'_, RunPlannerState>,
+) -> Result<PartialRunSummary, String> {
+    let tracker = state.runs.lock()
+        .map_err(|_| "Failed to acquire planner lock".to_string())?
+        .remove(&run_id)
+        .ok_or_else(|| format!("Unknown run: {}", run_id))?;
+
+    Ok(tracker.summary(reason.unwrap_or_else(|| "Halted by request".to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn models() -> Vec<PlannerModel> {
+        vec![
+            PlannerModel { model_id: "large".into(), input_tokens_per_1k: 15.0, output_tokens_per_1k: 60.0, capability: 3 },
+            PlannerModel { model_id: "medium".into(), input_tokens_per_1k: 3.0, output_tokens_per_1k: 12.0, capability: 2 },
+            PlannerModel { model_id: "small".into(), input_tokens_per_1k: 0.15, output_tokens_per_1k: 0.6, capability: 1 },
+        ]
+    }
+
+    fn step(id: &str, risk: StepRisk) -> RunStep {
+        RunStep {
+            id: id.into(),
+            description: "x".repeat(4000),
+            risk,
+            estimated_input_tokens: None,
+            estimated_output_tokens: Some(1000),
+            estimated_duration_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_downgrades_low_risk_steps_first() {
+        let steps = vec![step("review", StepRisk::High), step("draft", StepRisk::Medium), step("format", StepRisk::Low)];
+
+        let (unbounded, fits_budget) = plan_steps(&steps, &models(), &RunBudget::default(), &RunUsage::default(), 1.0);
+        assert!(fits_budget);
+        let assigned: Vec<&str> = unbounded.iter().map(|p| p.model_id.as_str()).collect();
+        assert_eq!(assigned, vec!["large", "medium", "small"]);
+
+        // large step costs $75, medium $15: an $80 cap forces the medium step down
+        let budget = RunBudget { max_cost_usd: Some(80.0), ..Default::default() };
+        let (capped, fits_budget) = plan_steps(&steps, &models(), &budget, &RunUsage::default(), 1.0);
+        assert!(fits_budget);
+        assert_eq!(capped[0].model_id, "large");
+        assert_eq!(capped[1].model_id, "small");
+        assert!(capped[1].downgraded);
+
+        let tight = RunBudget { max_cost_usd: Some(10.0), ..Default::default() };
+        assert!(!plan_steps(&steps, &models(), &tight, &RunUsage::default(), 1.0).1);
+    }
+
+    #[test]
+    fn test_tracker_halts_with_partial_results() {
+        let steps = vec![step("a", StepRisk::Low), step("b", StepRisk::High)];
+        let budget = RunBudget { max_tokens: Some(3000), ..Default::default() };
+        let (plan, _) = plan_steps(&steps, &models(), &budget, &RunUsage::default(), 1.0);
+        let mut tracker = RunTracker {
+            steps,
+            budget,
+            models: models(),
+            outcomes: Vec::new(),
+            usage: RunUsage::default(),
+            planned_cost: 0.0,
+            plan,
+            started_at: Utc::now(),
+        };
+
+        tracker.outcomes.push(StepOutcome {
+            step_id: "a".into(),
+            model_id: "small".into(),
+            input_tokens: 1000,
+            output_tokens: 1500,
+            duration_ms: 10,
+            summary: Some("done".into()),
+        });
+        tracker.usage.tokens = 2500;
+
+        match tracker.decide() {
+            RunDecision::Halt { summary } => {
+                assert_eq!(summary.completed_steps, vec!["a"]);
+                assert_eq!(summary.skipped_steps, vec!["b"]);
+                assert_eq!(summary.results[0].summary.as_deref(), Some("done"));
+            }
+            other => panic!("expected halt, got {:?}", other),
+        }
+
+        let unknown = StepOutcome {
+            step_id: "b".into(),
+            model_id: "unlisted".into(),
+            input_tokens: 1000,
+            output_tokens: 1000,
+            duration_ms: 10,
+            summary: None,
+        };
+        assert_eq!(tracker.step_cost(&unknown), 75.0);
+    }
+}
@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use super::error_sanitization::redact_error;
+
+/// Resource limits applied to a sandboxed process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxLimits {
+    /// CPU seconds before the process receives SIGXCPU (unix only)
+    pub cpu_time_secs: u64,
+    /// Memory ceiling in bytes: an `RLIMIT_DATA` on each process (unix) plus a poll of the
+    /// process group's resident memory (Linux)
+    pub max_memory_bytes: u64,
+    /// Wall-clock timeout in milliseconds
+    pub timeout_ms: u64,
+    /// Cap applied to each of the captured stdout and stderr streams
+    pub max_output_bytes: usize,
+    /// Directory the process is confined to; defaults to the current directory
+    pub jail_root: Option<String>,
+    /// Working directory relative to the jail root
+    pub working_dir: Option<String>,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            cpu_time_secs: 30,
+            max_memory_bytes: 512 * 1024 * 1024,
+            timeout_ms: 60_000,
+            max_output_bytes: 1024 * 1024,
+            jail_root: None,
+            working_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+    pub status: i32,
+    pub duration_ms: u64,
+}
+
+/// Structured failures returned to the frontend. Details that could reveal local paths or
+/// system errors are logged, not carried here.
+#[derive(Error, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "detail")]
+pub enum SandboxError {
+    #[error("Command exceeded wall-clock timeout of {0} ms")]
+    Timeout(u64),
+
+    #[error("Command exceeded CPU time limit of {0} s")]
+    CpuLimitExceeded(u64),
+
+    #[error("Command exceeded memory limit of {0} bytes")]
+    MemoryLimitExceeded(u64),
+
+    #[error("Command exceeded output limit of {0} bytes")]
+    OutputLimitExceeded(usize),
+
+    #[error("Command not permitted: {0}")]
+    NotPermitted(String),
+
+    #[error("Path escapes sandbox: {0}")]
+    PathOutsideJail(String),
+
+    #[error("Command terminated by signal {0}")]
+    Terminated(i32),
+
+    #[error("Failed to run command: {0}")]
+    SpawnFailed(String),
+}
+
+impl SandboxError {
+    /// Log an I/O failure and keep only its kind for the caller
+    fn io(context: &str, error: &std::io::Error) -> Self {
+        warn!("Sandbox {} failed: {}", context, redact_error(error));
+        let reason = match error.kind() {
+            std::io::ErrorKind::NotFound => "command or directory not found",
+            std::io::ErrorKind::PermissionDenied => "permission denied",
+            _ => "system error",
+        };
+        SandboxError::SpawnFailed(reason.to_string())
+    }
+
+    /// The argument as the caller wrote it, with any secrets it carries redacted
+    fn outside_jail(arg: &str) -> Self {
+        SandboxError::PathOutsideJail(redact_error(arg))
+    }
+}
+
+/// Resolve the jail root and working directory, rejecting anything outside the jail
+fn resolve_directories(limits: &SandboxLimits) -> Result<(PathBuf, PathBuf), SandboxError> {
+    let root = match &limits.jail_root {
+        Some(root) => PathBuf::from(root).canonicalize()
+            .map_err(|_| SandboxError::outside_jail(root))?,
+        None => std::env::current_dir()
+            .and_then(|dir| dir.canonicalize())
+            .map_err(|e| SandboxError::io("working directory lookup", &e))?,
+    };
+
+    let working_dir = match &limits.working_dir {
+        Some(dir) => {
+            let candidate = root.join(dir).canonicalize()
+                .map_err(|_| SandboxError::outside_jail(dir))?;
+            if !candidate.starts_with(&root) {
+                return Err(SandboxError::outside_jail(dir));
+            }
+            candidate
+        }
+        None => root.clone(),
+    };
+
+    Ok((root, working_dir))
+}
+
+/// Reject arguments that reference paths outside the jail
+fn check_argument_paths(args: &[String], root: &Path) -> Result<(), SandboxError> {
+    for arg in args {
+        // Flags such as --out=/tmp/x carry their path after the '='
+        let value = arg.split_once('=').map(|(_, v)| v).unwrap_or(arg);
+        let path = Path::new(value);
+
+        if path.components().any(|c| c == Component::ParentDir) {
+            return Err(SandboxError::outside_jail(arg));
+        }
+        if path.is_absolute() && !path.starts_with(root) {
+            return Err(SandboxError::outside_jail(arg));
+        }
+    }
+    Ok(())
+}
+
+async fn read_capped<R: AsyncRead + Unpin>(
+    mut reader: R,
+    cap: usize,
+    exceeded: Arc<Notify>,
+) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut chunk = [0u8; 8192];
+
+    loop {
+        match reader.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if output.len() + n > cap {
+                    output.extend_from_slice(&chunk[..cap - output.len()]);
+                    exceeded.notify_one();
+                    break;
+                }
+                output.extend_from_slice(&chunk[..n]);
+            }
+        }
+    }
+
+    output
+}
+
+/// Resident set size of a process in bytes
+#[cfg(target_os = "linux")]
+fn resident_memory(pid: &str) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status.lines()
+        .find(|line| line.starts_with("VmRSS:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+/// Combined resident memory of every process in a process group, if the platform exposes it.
+/// Children a command forks stay in its group, so they count against the limit too.
+#[cfg(target_os = "linux")]
+fn group_resident_memory(pgid: u32) -> Option<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        let name = entry.file_name();
+        let Some(pid) = name.to_str().filter(|n| n.bytes().all(|b| b.is_ascii_digit())) else { continue };
+        // `comm` may contain spaces, so fields are counted from the closing parenthesis
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else { continue };
+        let pgrp = stat.rsplit_once(')')
+            .and_then(|(_, rest)| rest.split_whitespace().nth(2))
+            .and_then(|pgrp| pgrp.parse::<u32>().ok());
+        if pgrp == Some(pgid) {
+            total += resident_memory(pid).unwrap_or(0);
+        }
+    }
+    Some(total)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn group_resident_memory(_pgid: u32) -> Option<u64> {
+    None
+}
+
+/// Kill the command and everything it started. Each command leads its own process group.
+async fn kill_group(child: &mut tokio::process::Child, pgid: Option<u32>) {
+    #[cfg(unix)]
+    if let Some(pgid) = pgid {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+        // ESRCH just means the group has already exited
+        let _ = killpg(Pid::from_raw(pgid as i32), Signal::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = pgid;
+    let _ = child.kill().await;
+}
+
+/// Run a command with CPU, memory, wall-clock, output and working-directory limits
+pub async fn run_sandboxed(
+    command: &str,
+    args: &[String],
+    limits: &SandboxLimits,
+) -> Result<SandboxOutput, SandboxError> {
+    let (root, working_dir) = resolve_directories(limits)?;
+    check_argument_paths(args, &root)?;
+
+    let mut cmd = Command::new(command);
+    cmd.args(args)
+        .current_dir(&working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .env_clear();
+
+    // Only pass through the environment needed to locate tools
+    for key in ["PATH", "HOME", "LANG", "SYSTEMROOT", "TEMP", "TMP"] {
+        if let Ok(value) = std::env::var(key) {
+            cmd.env(key, value);
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        // A group of its own, so limits and kills reach the processes the command forks
+        cmd.process_group(0);
+
+        let cpu_secs = limits.cpu_time_secs;
+        let memory_bytes = limits.max_memory_bytes;
+        // SAFETY: the closure only calls async-signal-safe setrlimit between fork and exec
+        unsafe {
+            cmd.pre_exec(move || {
+                use nix::sys::resource::{setrlimit, Resource};
+                setrlimit(Resource::RLIMIT_CPU, cpu_secs, cpu_secs + 1)
+                    .map_err(std::io::Error::from)?;
+                // RLIMIT_DATA rather than RLIMIT_AS: it counts memory the process writes to, not
+                // address space reserved up front, which JS runtimes reserve by the gigabyte
+                setrlimit(Resource::RLIMIT_DATA, memory_bytes, memory_bytes)
+                    .map_err(std::io::Error::from)?;
+                setrlimit(Resource::RLIMIT_CORE, 0, 0)
+                    .map_err(std::io::Error::from)?;
+                Ok(())
+            });
+        }
+    }
+
+    let started = Instant::now();
+    let mut child = cmd.spawn().map_err(|e| SandboxError::io("spawn", &e))?;
+    // The child leads its process group, so its pid is the group id
+    let pgid = child.id();
+
+    let output_exceeded = Arc::new(Notify::new());
+    let stdout_task = tokio::spawn(read_capped(
+        child.stdout.take().expect("stdout is piped"),
+        limits.max_output_bytes,
+        output_exceeded.clone(),
+    ));
+    let stderr_task = tokio::spawn(read_capped(
+        child.stderr.take().expect("stderr is piped"),
+        limits.max_output_bytes,
+        output_exceeded.clone(),
+    ));
+
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(limits.timeout_ms);
+    let mut memory_check = tokio::time::interval(Duration::from_millis(100));
+
+    let breach = loop {
+        tokio::select! {
+            status = child.wait() => {
+                break status.map_err(|e| SandboxError::io("wait", &e));
+            }
+            _ = tokio::time::sleep_until(deadline) => {
+                break Err(SandboxError::Timeout(limits.timeout_ms));
+            }
+            _ = output_exceeded.notified() => {
+                break Err(SandboxError::OutputLimitExceeded(limits.max_output_bytes));
+            }
+            _ = memory_check.tick() => {
+                let over_limit = pgid.and_then(group_resident_memory)
+                    .map_or(false, |rss| rss > limits.max_memory_bytes);
+                if over_limit {
+                    break Err(SandboxError::MemoryLimitExceeded(limits.max_memory_bytes));
+                }
+            }
+        }
+    };
+
+    // Background processes the command left behind are stopped with it
+    kill_group(&mut child, pgid).await;
+    let status = match breach {
+        Ok(status) => status,
+        Err(e) => {
+            warn!("Sandboxed command '{}' stopped: {}", command, e);
+            return Err(e);
+        }
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            if signal == nix::sys::signal::Signal::SIGXCPU as i32 {
+                return Err(SandboxError::CpuLimitExceeded(limits.cpu_time_secs));
+            }
+            return Err(SandboxError::Terminated(signal));
+        }
+    }
+
+    // Pipes close once the group is gone; bound the wait in case a process escaped it
+    let drain = Duration::from_millis(500);
+    let stdout = tokio::time::timeout(drain, stdout_task).await.ok().and_then(|r| r.ok()).unwrap_or_default();
+    let stderr = tokio::time::timeout(drain, stderr_task).await.ok().and_then(|r| r.ok()).unwrap_or_default();
+
+    let duration_ms = started.elapsed().as_millis() as u64;
+    info!("Sandboxed command '{}' finished in {} ms", command, duration_ms);
+
+    Ok(SandboxOutput {
+        success: status.success(),
+        stdout: String::from_utf8_lossy(&stdout).to_string(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        status: status.code().unwrap_or(-1),
+        duration_ms,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_successful_command() {
+        let output = run_sandboxed("echo", &["hello".to_string()], &SandboxLimits::default())
+            .await
+            .unwrap();
+        assert!(output.success);
+        assert_eq!(output.stdout.trim(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let limits = SandboxLimits { timeout_ms: 200, ..Default::default() };
+        let result = run_sandboxed("sleep", &["5".to_string()], &limits).await;
+        assert!(matches!(result, Err(SandboxError::Timeout(200))));
+    }
+
+    #[tokio::test]
+    async fn test_output_cap() {
+        let limits = SandboxLimits { max_output_bytes: 1024, ..Default::default() };
+        let result = run_sandboxed("yes", &[], &limits).await;
+        assert!(matches!(result, Err(SandboxError::OutputLimitExceeded(1024))));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_kills_background_processes() {
+        let jail = tempfile::tempdir().unwrap();
+        let marker = jail.path().join("marker");
+        let limits = SandboxLimits {
+            timeout_ms: 200,
+            jail_root: Some(jail.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        // The grandchild would create the marker after the sandbox gave up on the command
+        let script = "(sleep 1; touch marker) & sleep 5".to_string();
+        let result = run_sandboxed("sh", &["-c".to_string(), script], &limits).await;
+        assert!(matches!(result, Err(SandboxError::Timeout(200))));
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit_is_an_rlimit() {
+        let limits = SandboxLimits { max_memory_bytes: 64 * 1024 * 1024, ..Default::default() };
+        let output = run_sandboxed("sh", &["-c".to_string(), "ulimit -d".to_string()], &limits).await.unwrap();
+        assert_eq!(output.stdout.trim(), (64 * 1024).to_string());
+    }
+
+    #[tokio::test]
+    async fn test_errors_do_not_reveal_system_details() {
+        let result = run_sandboxed("definitely-not-a-command", &[], &SandboxLimits::default()).await;
+        match result {
+            Err(SandboxError::SpawnFailed(reason)) => assert_eq!(reason, "command or directory not found"),
+            other => panic!("unexpected result: {:?}", other.map(|o| o.status)),
+        }
+
+        let jail = tempfile::tempdir().unwrap();
+        let limits = SandboxLimits {
+            jail_root: Some(jail.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let result = run_sandboxed("cat", &["--password=/etc/hunter2hunter2".to_string()], &limits).await;
+        match result {
+            Err(SandboxError::PathOutsideJail(arg)) => assert!(!arg.contains("hunter2hunter2")),
+            other => panic!("unexpected result: {:?}", other.map(|o| o.status)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_working_directory_jail() {
+        let jail = tempfile::tempdir().unwrap();
+        let limits = SandboxLimits {
+            jail_root: Some(jail.path().to_string_lossy().to_string()),
+            working_dir: Some("..".to_string()),
+            ..Default::default()
+        };
+        let result = run_sandboxed("ls", &[], &limits).await;
+        assert!(matches!(result, Err(SandboxError::PathOutsideJail(_))));
+
+        let limits = SandboxLimits {
+            jail_root: Some(jail.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let result = run_sandboxed("cat", &["/etc/passwd".to_string()], &limits).await;
+        assert!(matches!(result, Err(SandboxError::PathOutsideJail(_))));
+    }
+}
@@ -1,12 +1,17 @@
 use anyhow::{Result, Context};
-use tauri::{command, AppHandle, State};
+use tauri::{command, AppHandle, State, Webview};
 use tracing::{info, warn, error};
+use serde::Serialize;
+use std::path::Path;
 use std::sync::Mutex;
 
 use crate::ai::{
-    csrf::{validate_request_security, SESSION_MANAGER, CSRF_MANAGER},
-    command_whitelist::validate_command_execution,
+    csrf::{guard_secure_command, CsrfGrant, SESSION_MANAGER, CSRF_MANAGER},
+    command_whitelist::{validate_command_execution_for_agent, reload_command_whitelist},
     error_sanitization::{sanitize_user_error, sanitize_log_error},
+    permissions::resolve_agent_permissions,
+    sandbox::{run_sandboxed, SandboxLimits},
+    security::{active_workspace, enforce_path_policy, load_path_policy, session_scoped_path, PathAccess},
     storage::StorageManager,
 };
 
@@ -15,10 +20,61 @@ pub struct SecureSession {
     pub storage_manager: Mutex<StorageManager>,
 }
 
+/// Result of a privileged command with the rotated token pair for the next call.
+/// The submitted pair is spent even when the command fails; request a new one
+/// with `generate_csrf_token` in that case.
+#[derive(Debug, Serialize)]
+pub struct SecureResponse<T> {
+    pub data: T,
+    pub csrf: CsrfGrant,
+}
+
+/// Check a file tool access against the session's workspace root and the workspace path policy
+fn check_path_policy(
+    secure_session: &SecureSession,
+    session_id: &str,
+    workspace: Option<&str>,
+    path: &str,
+    access: PathAccess,
+) -> Result<std::path::PathBuf, String> {
+    let path = session_scoped_path(Some(session_id), path)?;
+    let storage_manager = secure_session.storage_manager.lock()
+        .map_err(|_| "Failed to acquire storage lock".to_string())?;
+
+    enforce_path_policy(&storage_manager, workspace, &path, access)
+}
+
+/// Sandbox limits for a session's commands: a session bound to a workspace runs jailed to
+/// its root, and path-looking arguments are resolved against that root first
+fn session_sandbox(session_id: &str, args: &[String]) -> Result<(SandboxLimits, Vec<String>), String> {
+    let limits = SandboxLimits {
+        jail_root: SESSION_MANAGER.workspace_root(session_id).map(|root| root.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+
+    let args = args.iter().map(|arg| {
+        let (prefix, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => (Some(flag), value),
+            _ => (None, arg.as_str()),
+        };
+        let path_like = value.contains('/') || value.starts_with('.') || value.starts_with('~');
+        if !path_like || (prefix.is_none() && value.starts_with('-')) {
+            return Ok(arg.clone());
+        }
+        let scoped = session_scoped_path(Some(session_id), value)?;
+        Ok(match prefix {
+            Some(flag) => format!("{}={}", flag, scoped),
+            None => scoped,
+        })
+    }).collect::<Result<Vec<_>, String>>()?;
+
+    Ok((limits, args))
+}
+
 /// Create a new secure session
 #[command]
-pub async fn create_session() -> Result<String, String> {
-    match SESSION_MANAGER.create_session() {
+pub async fn create_session(webview: Webview) -> Result<String, String> {
+    match SESSION_MANAGER.create_session(webview.label()) {
         Ok(session_id) => {
             info!("New session created: {}", &session_id[..8]);
             Ok(session_id)
@@ -32,14 +88,14 @@ pub async fn create_session() -> Result<String, String> {
 
 /// Generate CSRF token for session
 #[command]
-pub async fn generate_csrf_token(session_id: String) -> Result<String, String> {
-    // Validate session first
+pub async fn generate_csrf_token(session_id: String, webview: Webview) -> Result<CsrfGrant, String> {
+    // Validate session first; only the webview that created it may get tokens
     match SESSION_MANAGER.validate_session(&session_id) {
-        Ok(true) => {
-            match CSRF_MANAGER.generate_token(&session_id) {
-                Ok(token) => {
+        Ok(true) if SESSION_MANAGER.belongs_to(&session_id, webview.label()) => {
+            match CSRF_MANAGER.issue_token(&session_id, webview.label()) {
+                Ok(grant) => {
                     info!("CSRF token generated for session: {}", &session_id[..8]);
-                    Ok(token)
+                    Ok(grant)
                 }
                 Err(e) => {
                     error!("Failed to generate CSRF token: {}", sanitize_log_error(&e));
@@ -47,7 +103,7 @@ pub async fn generate_csrf_token(session_id: String) -> Result<String, String> {
                 }
             }
         }
-        Ok(false) => {
+        Ok(_) => {
             warn!("Invalid session for CSRF token generation: {}", &session_id[..8]);
             Err("Invalid session".to_string())
         }
@@ -63,24 +119,25 @@ pub async fn generate_csrf_token(session_id: String) -> Result<String, String> {
 pub async fn execute_command_secure(
     session_id: String,
     csrf_token: String,
+    csrf_double_submit: String,
     command: String,
     args: Vec<String>,
-) -> Result<serde_json::Value, String> {
-    // Validate security
-    match validate_request_security(&session_id, &csrf_token) {
-        Ok(true) => {}
-        Ok(false) => {
-            warn!("Security validation failed for command: {}", command);
-            return Err("Security validation failed".to_string());
-        }
-        Err(e) => {
-            error!("Security validation error: {}", sanitize_log_error(&e));
-            return Err("Security validation failed".to_string());
-        }
+    agent_id: Option<String>,
+    webview: Webview,
+    secure_session: State<'_, SecureSession>,
+) -> Result<SecureResponse<serde_json::Value>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("execute_command_secure", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
+
+    // Validate command against the agent's manifest
+    let permissions = resolve_agent_permissions(&secure_session, agent_id.as_deref())?;
+    if !permissions.allows_command(&command) {
+        warn!("Command outside agent permissions: {} (agent: {})", command, permissions.agent_id);
+        return Err("Command not permitted for agent".to_string());
     }
 
     // Validate command against whitelist
-    match validate_command_execution(&command, &args) {
+    match validate_command_execution_for_agent(&command, &args, agent_id.as_deref()) {
         Ok(true) => {
             info!("Executing whitelisted command: {} with {} args", command, args.len());
         }
@@ -94,24 +151,21 @@ pub async fn execute_command_secure(
         }
     }
 
-    // Execute the command safely
-    match tokio::process::Command::new(&command)
-        .args(&args)
-        .output()
-        .await
-    {
+    // Execute the command inside the sandbox, jailed to the session's workspace
+    let (limits, args) = session_sandbox(&session_id, &args)?;
+    match run_sandboxed(&command, &args, &limits).await {
         Ok(output) => {
             let result = serde_json::json!({
-                "success": output.status.success(),
-                "stdout": String::from_utf8_lossy(&output.stdout),
-                "stderr": String::from_utf8_lossy(&output.stderr),
-                "status": output.status.code().unwrap_or(-1)
+                "success": output.success,
+                "stdout": output.stdout,
+                "stderr": output.stderr,
+                "status": output.status
             });
-            Ok(result)
+            Ok(SecureResponse { data: result, csrf })
         }
         Err(e) => {
-            error!("Command execution error: {}", sanitize_log_error(&anyhow::Error::from(e)));
-            Err("Command execution failed".to_string())
+            error!("Command execution error: {}", e);
+            Err(format!("Command execution failed: {}", e))
         }
     }
 }
@@ -121,14 +175,15 @@ pub async fn execute_command_secure(
 pub async fn read_file_tool_secure(
     session_id: String,
     csrf_token: String,
+    csrf_double_submit: String,
     path: String,
-) -> Result<String, String> {
-    // Validate security
-    match validate_request_security(&session_id, &csrf_token) {
-        Ok(true) => {}
-        Ok(false) => return Err("Security validation failed".to_string()),
-        Err(_) => return Err("Security validation failed".to_string()),
-    }
+    agent_id: Option<String>,
+    workspace: Option<String>,
+    webview: Webview,
+    secure_session: State<'_, SecureSession>,
+) -> Result<SecureResponse<String>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("read_file_tool_secure", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
 
     // Validate file path
     if path.contains("..") || path.starts_with("/") || path.contains('\x00') {
@@ -145,10 +200,19 @@ pub async fn read_file_tool_secure(
         }
     }
 
-    match tokio::fs::read_to_string(&path).await {
+    // Validate path against the agent's manifest
+    let permissions = resolve_agent_permissions(&secure_session, agent_id.as_deref())?;
+    if !permissions.allows_path(&path) {
+        warn!("File read outside agent permissions: {} (agent: {})", path, permissions.agent_id);
+        return Err("File access not permitted for agent".to_string());
+    }
+
+    let resolved_path = check_path_policy(&secure_session, &session_id, workspace.as_deref(), &path, PathAccess::Read)?;
+
+    match tokio::fs::read_to_string(&resolved_path).await {
         Ok(content) => {
             info!("File read successfully: {}", path);
-            Ok(content)
+            Ok(SecureResponse { data: content, csrf })
         }
         Err(e) => {
             error!("File read error for {}: {}", path, sanitize_log_error(&anyhow::Error::from(e)));
@@ -162,15 +226,16 @@ pub async fn read_file_tool_secure(
 pub async fn write_file_tool_secure(
     session_id: String,
     csrf_token: String,
+    csrf_double_submit: String,
     path: String,
     contents: String,
-) -> Result<String, String> {
-    // Validate security
-    match validate_request_security(&session_id, &csrf_token) {
-        Ok(true) => {}
-        Ok(false) => return Err("Security validation failed".to_string()),
-        Err(_) => return Err("Security validation failed".to_string()),
-    }
+    agent_id: Option<String>,
+    workspace: Option<String>,
+    webview: Webview,
+    secure_session: State<'_, SecureSession>,
+) -> Result<SecureResponse<String>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("write_file_tool_secure", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
 
     // Validate file path
     if path.contains("..") || path.starts_with("/") || path.contains('\x00') {
@@ -185,15 +250,30 @@ pub async fn write_file_tool_secure(
         return Err("File write location not permitted".to_string());
     }
 
+    // Validate path against the agent's manifest
+    let permissions = resolve_agent_permissions(&secure_session, agent_id.as_deref())?;
+    if !permissions.allows_path(&path) {
+        warn!("File write outside agent permissions: {} (agent: {})", path, permissions.agent_id);
+        return Err("File write not permitted for agent".to_string());
+    }
+
     // Content validation
     if contents.len() > 10_000_000 { // 10MB limit
         return Err("File content too large".to_string());
     }
 
-    match tokio::fs::write(&path, &contents).await {
+    let resolved_path = check_path_policy(
+        &secure_session,
+        &session_id,
+        workspace.as_deref(),
+        &path,
+        PathAccess::Write { size: contents.len() as u64 },
+    )?;
+
+    match tokio::fs::write(&resolved_path, &contents).await {
         Ok(_) => {
             info!("File written successfully: {}", path);
-            Ok("File written successfully".to_string())
+            Ok(SecureResponse { data: "File written successfully".to_string(), csrf })
         }
         Err(e) => {
             error!("File write error for {}: {}", path, sanitize_log_error(&anyhow::Error::from(e)));
@@ -207,15 +287,16 @@ pub async fn write_file_tool_secure(
 pub async fn list_files_tool_secure(
     session_id: String,
     csrf_token: String,
+    csrf_double_submit: String,
     path: String,
     recursive: bool,
-) -> Result<Vec<String>, String> {
-    // Validate security
-    match validate_request_security(&session_id, &csrf_token) {
-        Ok(true) => {}
-        Ok(false) => return Err("Security validation failed".to_string()),
-        Err(_) => return Err("Security validation failed".to_string()),
-    }
+    agent_id: Option<String>,
+    workspace: Option<String>,
+    webview: Webview,
+    secure_session: State<'_, SecureSession>,
+) -> Result<SecureResponse<Vec<String>>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("list_files_tool_secure", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
 
     // Validate directory path
     if path.contains("..") || path.starts_with("/") || path.contains('\x00') {
@@ -223,14 +304,30 @@ pub async fn list_files_tool_secure(
         return Err("Invalid directory path".to_string());
     }
 
+    // Validate directory against the agent's manifest
+    let permissions = resolve_agent_permissions(&secure_session, agent_id.as_deref())?;
+    if !permissions.allows_path(&path) {
+        warn!("Directory listing outside agent permissions: {} (agent: {})", path, permissions.agent_id);
+        return Err("Directory access not permitted for agent".to_string());
+    }
+
+    // Listings keep the caller's relative paths; the policy check only gates access
+    let list_path = session_scoped_path(Some(&session_id), &path)?;
+    check_path_policy(&secure_session, &session_id, workspace.as_deref(), &path, PathAccess::List)?;
+
+    let allowed = listing_filter(&secure_session, &session_id, workspace.as_deref())?;
+
     match if recursive {
-        list_files_recursive(&path).await
+        list_files_recursive(&list_path, &allowed).await
     } else {
-        list_files_single(&path).await
+        list_files_single(&list_path, &allowed).await
     } {
         Ok(files) => {
+            let files: Vec<String> = files.into_iter()
+                .map(|file| file.replacen(&list_path, &path, 1))
+                .collect();
             info!("Directory listed successfully: {} ({} files)", path, files.len());
-            Ok(files)
+            Ok(SecureResponse { data: files, csrf })
         }
         Err(e) => {
             error!("Directory listing error for {}: {}", path, sanitize_log_error(&e));
@@ -239,13 +336,41 @@ pub async fn list_files_tool_secure(
     }
 }
 
+/// Decide which listed entries may be returned: each must pass the workspace's path
+/// policy and, for a session bound to a workspace root, resolve inside that root
+fn listing_filter(
+    secure_session: &SecureSession,
+    session_id: &str,
+    workspace: Option<&str>,
+) -> Result<impl Fn(&Path) -> bool, String> {
+    let storage_manager = secure_session.storage_manager.lock()
+        .map_err(|_| "Failed to acquire storage lock".to_string())?;
+    let workspace = workspace.map_or_else(|| active_workspace(&storage_manager), str::to_string);
+    let policy = load_path_policy(&storage_manager, &workspace).map_err(|e| {
+        error!("Failed to load path policy for {}: {}", workspace, sanitize_log_error(&e));
+        "Failed to load path policy".to_string()
+    })?;
+    let root = SESSION_MANAGER.workspace_root(session_id);
+
+    Ok(move |path: &Path| match policy.check(&path.to_string_lossy(), PathAccess::List) {
+        Ok(resolved) => match &root {
+            Some(root) => resolved.starts_with(root),
+            None => true,
+        },
+        Err(_) => false,
+    })
+}
+
 /// Helper function for single directory listing
-async fn list_files_single(path: &str) -> Result<Vec<String>> {
+async fn list_files_single(path: &str, allowed: &impl Fn(&Path) -> bool) -> Result<Vec<String>> {
     let mut files = Vec::new();
     let mut dir = tokio::fs::read_dir(path).await
         .context("Failed to read directory")?;
 
     while let Some(entry) = dir.next_entry().await.context("Failed to read directory entry")? {
+        if !allowed(&entry.path()) {
+            continue;
+        }
         if let Some(name) = entry.file_name().to_str() {
             files.push(name.to_string());
         }
@@ -255,14 +380,20 @@ async fn list_files_single(path: &str) -> Result<Vec<String>> {
 }
 
 /// Helper function for recursive directory listing
-async fn list_files_recursive(path: &str) -> Result<Vec<String>> {
+async fn list_files_recursive(path: &str, allowed: &impl Fn(&Path) -> bool) -> Result<Vec<String>> {
     let mut files = Vec::new();
-    collect_files_recursive(path, &mut files, 0)?;
+    collect_files_recursive(path, &mut files, 0, allowed)?;
     Ok(files)
 }
 
-/// Recursive file collection with depth limit
-fn collect_files_recursive(path: &str, files: &mut Vec<String>, depth: usize) -> Result<()> {
+/// Recursive file collection with depth limit. Symlinked directories are not descended
+/// into, so a link inside the workspace cannot pull an outside tree into the listing.
+fn collect_files_recursive(
+    path: &str,
+    files: &mut Vec<String>,
+    depth: usize,
+    allowed: &impl Fn(&Path) -> bool,
+) -> Result<()> {
     // Prevent infinite recursion
     if depth > 10 {
         return Ok(());
@@ -274,10 +405,17 @@ fn collect_files_recursive(path: &str, files: &mut Vec<String>, depth: usize) ->
     for entry in entries {
         let entry = entry.context("Failed to read directory entry")?;
         let entry_path = entry.path();
-        
+        if !allowed(&entry_path) {
+            continue;
+        }
+
+        // `file_type` does not follow symlinks
+        let file_type = entry.file_type().context("Failed to read directory entry")?;
         if let Some(name) = entry_path.to_str() {
-            if entry_path.is_dir() {
-                collect_files_recursive(name, files, depth + 1)?;
+            if file_type.is_dir() {
+                collect_files_recursive(name, files, depth + 1, allowed)?;
+            } else if file_type.is_symlink() && entry_path.is_dir() {
+                continue;
             } else if let Some(file_name) = entry.file_name().to_str() {
                 files.push(format!("{}/{}", path, file_name));
             }
@@ -287,21 +425,90 @@ fn collect_files_recursive(path: &str, files: &mut Vec<String>, depth: usize) ->
     Ok(())
 }
 
+/// Bind a session to a workspace root so file tools resolve relative paths against it
+/// and refuse paths outside it; `None` removes the binding. The root must itself pass
+/// the path policy, so binding can only narrow what the session may touch.
+#[command]
+pub async fn set_session_workspace(
+    session_id: String,
+    csrf_token: String,
+    csrf_double_submit: String,
+    path: Option<String>,
+    workspace: Option<String>,
+    webview: Webview,
+    secure_session: State<'_, SecureSession>,
+) -> Result<SecureResponse<Option<String>>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("set_session_workspace", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
+
+    let root = match path {
+        Some(path) => {
+            let storage_manager = secure_session.storage_manager.lock()
+                .map_err(|_| "Failed to acquire storage lock".to_string())?;
+            let resolved = enforce_path_policy(&storage_manager, workspace.as_deref(), &path, PathAccess::List)?;
+            let root = resolved.canonicalize()
+                .map_err(|_| "Workspace root does not exist".to_string())?;
+            if !root.is_dir() {
+                return Err("Workspace root must be a directory".to_string());
+            }
+            Some(root)
+        }
+        None => None,
+    };
+
+    SESSION_MANAGER.set_workspace_root(&session_id, root.clone())
+        .map_err(|_| "Failed to update session".to_string())?;
+
+    let root = root.map(|r| r.to_string_lossy().to_string());
+    match &root {
+        Some(root) => info!("Session {} bound to workspace {}", &session_id[..8], root),
+        None => info!("Session {} workspace binding removed", &session_id[..8]),
+    }
+    Ok(SecureResponse { data: root, csrf })
+}
+
+/// Bind a session to the agent it acts as; `None` makes it act as the user again. Memory
+/// reads from a session bound to one agent only see other agents' memories through grants.
+#[command]
+pub async fn set_session_agent(
+    session_id: String,
+    csrf_token: String,
+    csrf_double_submit: String,
+    agent_id: Option<String>,
+    webview: Webview,
+) -> Result<SecureResponse<Option<String>>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("set_session_agent", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
+
+    if let Some(ref agent_id) = agent_id {
+        crate::validation::MemoryValidator::validate_agent_id(agent_id)
+            .map_err(|e| e.to_string())?;
+    }
+
+    SESSION_MANAGER.set_agent(&session_id, agent_id.clone())
+        .map_err(|_| "Failed to update session".to_string())?;
+
+    match &agent_id {
+        Some(agent_id) => info!("Session {} acting as agent {}", &session_id[..8], agent_id),
+        None => info!("Session {} agent binding removed", &session_id[..8]),
+    }
+    Ok(SecureResponse { data: agent_id, csrf })
+}
+
 /// Secure agent execution
 #[command]
 pub async fn execute_agent_tool_secure(
     session_id: String,
     csrf_token: String,
+    csrf_double_submit: String,
     agent_type: String,
     prompt: String,
     context: serde_json::Value,
-) -> Result<serde_json::Value, String> {
-    // Validate security
-    match validate_request_security(&session_id, &csrf_token) {
-        Ok(true) => {}
-        Ok(false) => return Err("Security validation failed".to_string()),
-        Err(_) => return Err("Security validation failed".to_string()),
-    }
+    webview: Webview,
+    secure_session: State<'_, SecureSession>,
+) -> Result<SecureResponse<serde_json::Value>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("execute_agent_tool_secure", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
 
     // Validate agent type
     let allowed_agents = ["assistant", "fileManager", "webAgent", "developer", "systemAdmin"];
@@ -315,6 +522,21 @@ pub async fn execute_agent_tool_secure(
         return Err("Prompt too long".to_string());
     }
 
+    // Validate requested resources against the agent's manifest
+    let permissions = resolve_agent_permissions(&secure_session, Some(&agent_type))?;
+    if let Some(server) = context.get("mcp_server").and_then(|v| v.as_str()) {
+        if !permissions.allows_mcp_server(server) {
+            warn!("MCP server outside agent permissions: {} (agent: {})", server, agent_type);
+            return Err("MCP server not permitted for agent".to_string());
+        }
+    }
+    if let Some(url) = context.get("url").and_then(|v| v.as_str()) {
+        if !permissions.allows_url(url) {
+            warn!("Network host outside agent permissions (agent: {})", agent_type);
+            return Err("Network host not permitted for agent".to_string());
+        }
+    }
+
     // For now, return a placeholder response
     // In a real implementation, this would execute the actual agent
     let result = serde_json::json!({
@@ -325,7 +547,7 @@ pub async fn execute_agent_tool_secure(
     });
 
     info!("Agent executed: {} for session: {}", agent_type, &session_id[..8]);
-    Ok(result)
+    Ok(SecureResponse { data: result, csrf })
 }
 
 /// Secure API key storage
@@ -333,17 +555,15 @@ pub async fn execute_agent_tool_secure(
 pub async fn store_api_key_secure(
     session_id: String,
     csrf_token: String,
+    csrf_double_submit: String,
     provider: String,
     key: String,
     app_handle: AppHandle,
+    webview: Webview,
     secure_session: State<'_, SecureSession>,
-) -> Result<String, String> {
-    // Validate security
-    match validate_request_security(&session_id, &csrf_token) {
-        Ok(true) => {}
-        Ok(false) => return Err("Security validation failed".to_string()),
-        Err(_) => return Err("Security validation failed".to_string()),
-    }
+) -> Result<SecureResponse<String>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("store_api_key_secure", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
 
     // Validate inputs
     if provider.is_empty() || key.is_empty() {
@@ -360,7 +580,7 @@ pub async fn store_api_key_secure(
     match storage_manager.store_api_key(&provider, &key) {
         Ok(_) => {
             info!("API key stored securely for provider: {}", provider);
-            Ok("API key stored securely".to_string())
+            Ok(SecureResponse { data: "API key stored securely".to_string(), csrf })
         }
         Err(e) => {
             error!("Failed to store API key: {}", sanitize_log_error(&e));
@@ -374,16 +594,14 @@ pub async fn store_api_key_secure(
 pub async fn get_api_key_secure(
     session_id: String,
     csrf_token: String,
+    csrf_double_submit: String,
     provider: String,
     app_handle: AppHandle,
+    webview: Webview,
     secure_session: State<'_, SecureSession>,
-) -> Result<Option<String>, String> {
-    // Validate security
-    match validate_request_security(&session_id, &csrf_token) {
-        Ok(true) => {}
-        Ok(false) => return Err("Security validation failed".to_string()),
-        Err(_) => return Err("Security validation failed".to_string()),
-    }
+) -> Result<SecureResponse<Option<String>>, String> {
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("get_api_key_secure", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
 
     if provider.is_empty() {
         return Err("Provider is required".to_string());
@@ -397,7 +615,7 @@ pub async fn get_api_key_secure(
             if key.is_some() {
                 info!("API key retrieved for provider: {}", provider);
             }
-            Ok(key)
+            Ok(SecureResponse { data: key, csrf })
         }
         Err(e) => {
             error!("Failed to retrieve API key: {}", sanitize_log_error(&e));
@@ -409,7 +627,72 @@ pub async fn get_api_key_secure(
 /// Initialize secure session state
 pub fn init_secure_session() -> SecureSession {
     let storage_manager = StorageManager::new().expect("Failed to initialize storage manager");
+
+    // Apply persisted whitelist rules before any command runs
+    if let Err(e) = reload_command_whitelist(&storage_manager) {
+        warn!("Failed to load persisted command whitelist: {}", sanitize_log_error(&e));
+    }
+
     SecureSession {
         storage_manager: Mutex::new(storage_manager),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::security::PathPolicy;
+
+    #[test]
+    fn test_recursive_listing_applies_policy_and_skips_symlinked_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join(".env"), "KEY=1").unwrap();
+        std::fs::write(root.join("src/cert.pem"), "pem").unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(outside.path(), root.join("linked")).unwrap();
+
+        let policy = PathPolicy {
+            workspace: "test".to_string(),
+            allowed_roots: vec![root.to_string_lossy().to_string()],
+            denied_patterns: vec![".env".to_string(), "*.pem".to_string()],
+            max_file_size: 1024,
+        };
+        let allowed = |path: &Path| policy.check(&path.to_string_lossy(), PathAccess::List).is_ok();
+
+        let mut files = Vec::new();
+        collect_files_recursive(&root.to_string_lossy(), &mut files, 0, &allowed).unwrap();
+
+        assert_eq!(files, vec![format!("{}/main.rs", root.join("src").display())]);
+    }
+
+    #[tokio::test]
+    async fn test_bound_session_commands_cannot_read_outside_the_workspace() {
+        let workspace = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let root = workspace.path().canonicalize().unwrap();
+        std::fs::write(root.join("notes.txt"), "inside").unwrap();
+        let secret = outside.path().join("secret.txt");
+        std::fs::write(&secret, "outside").unwrap();
+
+        let session_id = SESSION_MANAGER.create_session("main").unwrap();
+        SESSION_MANAGER.set_workspace_root(&session_id, Some(root.clone())).unwrap();
+
+        // Relative paths run from the workspace root
+        let (limits, args) = session_sandbox(&session_id, &["notes.txt".to_string()]).unwrap();
+        assert_eq!(limits.jail_root, Some(root.to_string_lossy().to_string()));
+        let output = run_sandboxed("cat", &args, &limits).await.unwrap();
+        assert_eq!(output.stdout, "inside");
+
+        let secret = secret.to_string_lossy().to_string();
+        assert!(session_sandbox(&session_id, std::slice::from_ref(&secret)).is_err());
+        assert!(session_sandbox(&session_id, &[format!("--file={}", secret)]).is_err());
+        assert!(session_sandbox(&session_id, &["../secret.txt".to_string()]).is_err());
+
+        // The jail still refuses the path if it reaches the sandbox
+        assert!(run_sandboxed("cat", &[secret], &limits).await.is_err());
+    }
+}
@@ -1,106 +1,28 @@
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use tracing::{info, warn, error};
 
-#[derive(Debug, Clone)]
-pub struct RateLimit {
-    pub requests_per_minute: u32,
-    pub requests_per_hour: u32,
-}
-
-#[derive(Debug)]
-struct RequestTracker {
-    requests: Vec<Instant>,
-    last_cleanup: Instant,
-}
-
-impl RequestTracker {
-    fn new() -> Self {
-        Self {
-            requests: Vec::new(),
-            last_cleanup: Instant::now(),
-        }
-    }
-
-    fn cleanup_old_requests(&mut self) {
-        let now = Instant::now();
-        let one_hour_ago = now - Duration::from_secs(3600);
-        
-        self.requests.retain(|&time| time > one_hour_ago);
-        self.last_cleanup = now;
-    }
-
-    fn add_request(&mut self) {
-        self.requests.push(Instant::now());
-        
-        // Cleanup every 100 requests to prevent memory bloat
-        if self.requests.len() % 100 == 0 {
-            self.cleanup_old_requests();
-        }
-    }
-
-    fn check_rate_limit(&mut self, limit: &RateLimit) -> bool {
-        self.cleanup_old_requests();
-        
-        let now = Instant::now();
-        let one_minute_ago = now - Duration::from_secs(60);
-        let one_hour_ago = now - Duration::from_secs(3600);
-        
-        let recent_requests = self.requests.iter()
-            .filter(|&&time| time > one_minute_ago)
-            .count() as u32;
-            
-        let hourly_requests = self.requests.iter()
-            .filter(|&&time| time > one_hour_ago)
-            .count() as u32;
-        
-        if recent_requests >= limit.requests_per_minute {
-            warn!("Rate limit exceeded: {} requests in last minute (limit: {})", 
-                  recent_requests, limit.requests_per_minute);
-            return false;
-        }
-        
-        if hourly_requests >= limit.requests_per_hour {
-            warn!("Rate limit exceeded: {} requests in last hour (limit: {})", 
-                  hourly_requests, limit.requests_per_hour);
-            return false;
-        }
-        
-        true
-    }
-}
+use crate::ai::command_whitelist::glob_to_regex;
+use crate::ai::csrf::SESSION_MANAGER;
+use crate::ai::rate_limiter::{RateLimitBucketStats, RateLimitConfig, RateLimitRejection, RateLimitScope, RateLimiter};
+use crate::ai::storage::StorageManager;
 
 pub struct SecurityManager {
-    rate_limits: HashMap<String, RateLimit>,
-    request_trackers: HashMap<String, RequestTracker>,
+    rate_limiter: RateLimiter,
     blocked_domains: Vec<String>,
     allowed_domains: Option<Vec<String>>,
 }
 
 impl SecurityManager {
     pub fn new() -> Self {
-        let mut rate_limits = HashMap::new();
-        
-        // Default rate limits for different providers
-        rate_limits.insert("openai".to_string(), RateLimit {
-            requests_per_minute: 60,
-            requests_per_hour: 1000,
-        });
-        
-        rate_limits.insert("anthropic".to_string(), RateLimit {
-            requests_per_minute: 50,
-            requests_per_hour: 800,
-        });
-        
-        // Default rate limit for unknown providers
-        rate_limits.insert("default".to_string(), RateLimit {
-            requests_per_minute: 30,
-            requests_per_hour: 500,
-        });
+        Self::with_rate_limit_config(RateLimitConfig::default())
+    }
 
+    pub fn with_rate_limit_config(config: RateLimitConfig) -> Self {
         Self {
-            rate_limits,
-            request_trackers: HashMap::new(),
+            rate_limiter: RateLimiter::new(config),
             blocked_domains: vec![
                 "malicious-site.com".to_string(),
                 "spam-domain.net".to_string(),
@@ -109,25 +31,27 @@ impl SecurityManager {
         }
     }
 
+    /// Rate limit an operation that has no provider or agent context
     pub fn check_rate_limit(&mut self, provider: &str) -> bool {
-        let limit = self.rate_limits.get(provider)
-            .unwrap_or_else(|| self.rate_limits.get("default").unwrap())
-            .clone();
-
-        let tracker = self.request_trackers
-            .entry(provider.to_string())
-            .or_insert_with(RequestTracker::new);
-
-        if tracker.check_rate_limit(&limit) {
-            tracker.add_request();
-            info!("Rate limit check passed for provider: {}", provider);
-            true
-        } else {
-            error!("Rate limit exceeded for provider: {}", provider);
-            false
+        match self.check_scoped_rate_limit(&RateLimitScope::operation(provider)) {
+            Ok(()) => {
+                info!("Rate limit check passed for provider: {}", provider);
+                true
+            }
+            Err(_) => {
+                error!("Rate limit exceeded for provider: {}", provider);
+                false
+            }
         }
     }
 
+    pub fn check_scoped_rate_limit(&mut self, scope: &RateLimitScope) -> std::result::Result<(), RateLimitRejection> {
+        self.rate_limiter.acquire(scope, Instant::now()).map_err(|rejection| {
+            warn!("Rate limit exceeded in bucket {} (retry in {}ms)", rejection.bucket, rejection.retry_after_ms);
+            rejection
+        })
+    }
+
     pub fn validate_url(&self, url: &str) -> bool {
         // Parse URL to extract domain
         let domain = match url::Url::parse(url) {
@@ -219,25 +143,319 @@ impl SecurityManager {
         self.allowed_domains = Some(domains);
     }
 
-    pub fn update_rate_limit(&mut self, provider: String, limit: RateLimit) {
-        self.rate_limits.insert(provider, limit);
-    }
-
-    pub fn get_request_stats(&self, provider: &str) -> Option<(usize, usize)> {
-        self.request_trackers.get(provider).map(|tracker| {
-            let now = Instant::now();
-            let one_minute_ago = now - Duration::from_secs(60);
-            let one_hour_ago = now - Duration::from_secs(3600);
-            
-            let recent = tracker.requests.iter()
-                .filter(|&&time| time > one_minute_ago)
-                .count();
-                
-            let hourly = tracker.requests.iter()
-                .filter(|&&time| time > one_hour_ago)
-                .count();
-                
-            (recent, hourly)
+    pub fn rate_limit_config(&self) -> &RateLimitConfig {
+        self.rate_limiter.config()
+    }
+
+    pub fn set_rate_limit_config(&mut self, config: RateLimitConfig) {
+        self.rate_limiter.set_config(config);
+    }
+
+    pub fn rate_limit_stats(&mut self, filter: Option<&str>) -> Vec<RateLimitBucketStats> {
+        self.rate_limiter.stats(filter)
+    }
+}
+/// Settings key prefix for stored path policies
+const PATH_POLICY_SETTING_PREFIX: &str = "path_policy.";
+
+/// Workspace used before any other is created or switched to
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// Settings key holding the id of the active workspace
+const ACTIVE_WORKSPACE_SETTING: &str = "active_workspace";
+
+/// Kind of access a file tool is about to perform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathAccess {
+    Read,
+    /// Write of `size` bytes
+    Write { size: u64 },
+    List,
+    /// Chunked read or write; callers bound each chunk instead of the whole file
+    Chunked,
+}
+
+/// Filesystem policy consulted by every file tool before touching disk.
+///
+/// Paths are resolved through symlinks before the roots are checked, so a link
+/// inside an allowed root cannot be used to reach a file outside it. Deny
+/// patterns are checked against both the requested and the resolved path.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PathPolicy {
+    pub workspace: String,
+    /// Directories file tools may access; `~` expands to the home directory and
+    /// relative roots resolve against the current working directory
+    pub allowed_roots: Vec<String>,
+    /// Patterns that are always refused. Patterns containing `/` match a path
+    /// and everything below it; others match any single path component
+    pub denied_patterns: Vec<String>,
+    /// Largest file that may be read or written, in bytes
+    pub max_file_size: u64,
+}
+
+impl PathPolicy {
+    pub fn default_for(workspace: &str) -> Self {
+        let mut allowed_roots = vec![".".to_string(), "~".to_string()];
+        allowed_roots.push(std::env::temp_dir().to_string_lossy().to_string());
+
+        Self {
+            workspace: workspace.to_string(),
+            allowed_roots,
+            denied_patterns: [
+                "~/.ssh", "~/.gnupg", "~/.aws", "~/.config/banshee",
+                ".env", ".env.*", "*.pem", "*.key", "id_rsa*", "id_ed25519*",
+            ].iter().map(|p| p.to_string()).collect(),
+            max_file_size: 10 * 1024 * 1024,
+        }
+    }
+
+    /// Resolve `path` and check it against the policy, returning the resolved path
+    pub fn check(&self, path: &str, access: PathAccess) -> Result<PathBuf, String> {
+        if path.trim().is_empty() || path.contains('\x00') {
+            return Err("Invalid file path".to_string());
+        }
+
+        let requested = absolutize(&expand_home(path))?;
+        let resolved = resolve_symlinks(&requested)?;
+
+        for candidate in [&requested, &resolved] {
+            if let Some(pattern) = self.matching_deny_pattern(candidate) {
+                warn!("Path {} blocked by deny pattern '{}'", path, pattern);
+                return Err("File access denied by path policy".to_string());
+            }
+        }
+
+        let inside_root = self.resolved_roots().iter().any(|root| resolved.starts_with(root));
+        if !inside_root {
+            warn!("Path {} resolves outside the allowed roots", path);
+            return Err("File path is outside the allowed roots".to_string());
+        }
+
+        let size = match access {
+            PathAccess::Write { size } => Some(size),
+            PathAccess::Read => std::fs::metadata(&resolved).ok().map(|m| m.len()),
+            PathAccess::List | PathAccess::Chunked => None,
+        };
+        if size.map_or(false, |size| size > self.max_file_size) {
+            warn!("File {} exceeds the {} byte limit", path, self.max_file_size);
+            return Err(format!("File exceeds the {} byte size limit", self.max_file_size));
+        }
+
+        Ok(resolved)
+    }
+
+    /// Allowed roots as canonical absolute paths; roots that do not exist are skipped
+    pub fn resolved_roots(&self) -> Vec<PathBuf> {
+        self.allowed_roots.iter()
+            .filter_map(|root| absolutize(&expand_home(root)).ok())
+            .filter_map(|root| root.canonicalize().ok())
+            .collect()
+    }
+
+    fn matching_deny_pattern(&self, path: &Path) -> Option<&str> {
+        self.denied_patterns.iter().find_map(|pattern| {
+            let matched = if pattern.contains('/') {
+                let expanded = expand_home(pattern).to_string_lossy().to_string();
+                glob_to_regex(&expanded).ok().map_or(false, |re| {
+                    path.ancestors().any(|p| re.is_match(&p.to_string_lossy()))
+                })
+            } else {
+                glob_to_regex(pattern).ok().map_or(false, |re| {
+                    path.components().any(|c| re.is_match(&c.as_os_str().to_string_lossy()))
+                })
+            };
+            matched.then_some(pattern.as_str())
         })
     }
-}
\ No newline at end of file
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.workspace.trim().is_empty() || self.workspace.len() > 100 {
+            return Err("Invalid workspace name".to_string());
+        }
+        if self.allowed_roots.is_empty() {
+            return Err("At least one allowed root is required".to_string());
+        }
+        for entry in self.allowed_roots.iter().chain(&self.denied_patterns) {
+            if entry.trim().is_empty() || entry.contains('\x00') {
+                return Err(format!("Invalid path policy entry: '{}'", entry));
+            }
+        }
+        if self.max_file_size == 0 {
+            return Err("Maximum file size must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+fn absolutize(path: &Path) -> Result<PathBuf, String> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .map_err(|_| "Failed to resolve working directory".to_string())
+    }
+}
+
+/// Canonicalize the longest existing prefix so paths that do not exist yet
+/// (e.g. a file about to be written) still have their symlinks resolved
+fn resolve_symlinks(path: &Path) -> Result<PathBuf, String> {
+    let mut existing = path.to_path_buf();
+    let mut remainder = Vec::new();
+
+    while !existing.exists() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                remainder.push(name.to_os_string());
+                existing = parent.to_path_buf();
+            }
+            _ => return Err("File path cannot be resolved".to_string()),
+        }
+    }
+
+    let mut resolved = existing.canonicalize()
+        .map_err(|_| "File path cannot be resolved".to_string())?;
+
+    // `file_name` is None for `..`, so the remainder never walks back up
+    for part in remainder.into_iter().rev() {
+        resolved.push(part);
+    }
+
+    Ok(resolved)
+}
+
+/// Load the path policy for a workspace, falling back to the default policy
+pub fn load_path_policy(storage: &StorageManager, workspace: &str) -> Result<PathPolicy> {
+    let key = format!("{}{}", PATH_POLICY_SETTING_PREFIX, workspace);
+
+    match storage.get_setting(&key)? {
+        Some(value) => serde_json::from_value(value)
+            .context("Stored path policy is malformed"),
+        None => Ok(PathPolicy::default_for(workspace)),
+    }
+}
+
+pub fn save_path_policy(storage: &StorageManager, policy: &PathPolicy) -> Result<()> {
+    let key = format!("{}{}", PATH_POLICY_SETTING_PREFIX, policy.workspace);
+    storage.set_setting(&key, serde_json::to_value(policy)?)
+}
+
+/// Workspace used when a caller does not name one
+pub fn active_workspace(storage: &StorageManager) -> String {
+    match storage.get_setting(ACTIVE_WORKSPACE_SETTING) {
+        Ok(value) => value.as_ref().and_then(|v| v.as_str()).unwrap_or(DEFAULT_WORKSPACE).to_string(),
+        Err(e) => {
+            warn!("Failed to load the active workspace: {}", e);
+            DEFAULT_WORKSPACE.to_string()
+        }
+    }
+}
+
+pub fn set_active_workspace(storage: &StorageManager, workspace: &str) -> Result<()> {
+    storage.set_setting(ACTIVE_WORKSPACE_SETTING, serde_json::Value::String(workspace.to_string()))
+}
+
+/// Resolve a tool path against a workspace root, refusing anything that lands outside it
+pub fn resolve_in_workspace(root: &Path, path: &str) -> Result<PathBuf, String> {
+    if path.trim().is_empty() || path.contains('\x00') {
+        return Err("Invalid file path".to_string());
+    }
+
+    // Absolute paths replace the root in `join`, so they are checked like any other
+    let resolved = resolve_symlinks(&root.join(expand_home(path)))?;
+    if !resolved.starts_with(root) {
+        warn!("Path {} resolves outside the session workspace {}", path, root.display());
+        return Err("File path is outside the session workspace".to_string());
+    }
+    Ok(resolved)
+}
+
+/// Rewrite a tool path for a session bound to a workspace; unbound sessions pass through
+pub fn session_scoped_path(session_id: Option<&str>, path: &str) -> Result<String, String> {
+    match session_id.and_then(|id| SESSION_MANAGER.workspace_root(id)) {
+        Some(root) => Ok(resolve_in_workspace(&root, path)?.to_string_lossy().to_string()),
+        None => Ok(path.to_string()),
+    }
+}
+
+/// Check a file tool access against the workspace's stored policy
+pub fn enforce_path_policy(
+    storage: &StorageManager,
+    workspace: Option<&str>,
+    path: &str,
+    access: PathAccess,
+) -> Result<PathBuf, String> {
+    let workspace = workspace.map_or_else(|| active_workspace(storage), str::to_string);
+    let policy = load_path_policy(storage, &workspace).map_err(|e| {
+        error!("Failed to load path policy for {}: {}", workspace, e);
+        "Failed to load path policy".to_string()
+    })?;
+
+    policy.check(path, access)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy_for(root: &Path) -> PathPolicy {
+        PathPolicy {
+            workspace: "test".to_string(),
+            allowed_roots: vec![root.to_string_lossy().to_string()],
+            denied_patterns: vec![".env".to_string(), "*.pem".to_string()],
+            max_file_size: 16,
+        }
+    }
+
+    #[test]
+    fn test_path_policy_roots_and_denials() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("notes.txt"), "hello").unwrap();
+        std::fs::write(root.join(".env"), "KEY=1").unwrap();
+        let policy = policy_for(root);
+
+        let notes = root.join("notes.txt").to_string_lossy().to_string();
+        assert!(policy.check(&notes, PathAccess::Read).is_ok());
+        assert!(policy.check(&root.join(".env").to_string_lossy(), PathAccess::Read).is_err());
+        assert!(policy.check(&root.join("cert.pem").to_string_lossy(), PathAccess::Write { size: 1 }).is_err());
+        assert!(policy.check("/etc/hostname", PathAccess::Read).is_err());
+        assert!(policy.check(&root.join("new/file.txt").to_string_lossy(), PathAccess::Write { size: 4 }).is_ok());
+        assert!(policy.check(&notes, PathAccess::Write { size: 17 }).is_err());
+    }
+
+    #[test]
+    fn test_workspace_root_confines_relative_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+
+        assert_eq!(resolve_in_workspace(&root, "src/lib.rs").unwrap(), root.join("src/lib.rs"));
+        assert_eq!(resolve_in_workspace(&root, "src/../README.md").unwrap(), root.join("README.md"));
+        assert!(resolve_in_workspace(&root, "../outside.txt").is_err());
+        assert!(resolve_in_workspace(&root, "/etc/hostname").is_err());
+        assert!(resolve_in_workspace(&root, &root.join("src").to_string_lossy()).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_policy_resolves_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("target.txt"), "x").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("target.txt"), dir.path().join("link.txt")).unwrap();
+
+        let policy = policy_for(dir.path());
+        let link = dir.path().join("link.txt").to_string_lossy().to_string();
+        assert!(policy.check(&link, PathAccess::Read).is_err());
+    }
+}
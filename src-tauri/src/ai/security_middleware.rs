@@ -1,4 +1,6 @@
 use super::SecurityManager;
+use super::error_sanitization::redact_secrets;
+use super::rate_limiter::{RateLimitBucketStats, RateLimitConfig, RateLimitRejection, RateLimitScope};
 use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -58,10 +60,26 @@ impl SecurityMiddleware {
         Ok(security.validate_url(url))
     }
 
-    /// Get request statistics
-    pub async fn get_stats(&self, provider: &str) -> Option<(usize, usize)> {
+    /// Take a token from every bucket in the scope's hierarchy
+    pub async fn check_scoped_rate_limit(&self, scope: &RateLimitScope) -> Result<(), RateLimitRejection> {
+        let mut security = self.security_manager.lock().await;
+        security.check_scoped_rate_limit(scope)
+    }
+
+    /// Per-bucket usage, optionally filtered by bucket key
+    pub async fn get_stats(&self, filter: Option<&str>) -> Vec<RateLimitBucketStats> {
+        let mut security = self.security_manager.lock().await;
+        security.rate_limit_stats(filter)
+    }
+
+    pub async fn rate_limit_config(&self) -> RateLimitConfig {
         let security = self.security_manager.lock().await;
-        security.get_request_stats(provider)
+        security.rate_limit_config().clone()
+    }
+
+    pub async fn set_rate_limit_config(&self, config: RateLimitConfig) {
+        let mut security = self.security_manager.lock().await;
+        security.set_rate_limit_config(config);
     }
 
     /// Sanitize a single input
@@ -70,6 +88,16 @@ impl SecurityMiddleware {
         security.sanitize_input(input)
     }
 
+    /// Record an access-control decision in the security log
+    pub fn audit_access(&self, action: &str, actor: &str, resource: &str, allowed: bool, detail: &str) {
+        let (detail, _) = redact_secrets(detail);
+        if allowed {
+            info!(action, actor, resource, detail = detail.as_str(), "Access allowed");
+        } else {
+            warn!(action, actor, resource, detail = detail.as_str(), "Access denied");
+        }
+    }
+
     /// Validate a single file path
     pub async fn validate_file_path(&self, path: &str) -> bool {
         let security = self.security_manager.lock().await;
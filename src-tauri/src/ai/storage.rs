@@ -4,7 +4,7 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn, error};
 use anyhow::{Result, Context};
-use crate::ai::encryption::{SecureStorage, get_master_password};
+use crate::ai::encryption::{EnvelopeEncryption, KeyPurpose, SecureStorage};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApiKeyConfig {
@@ -23,6 +23,7 @@ pub struct SecureStorageData {
 pub struct StorageManager {
     storage_path: PathBuf,
     encryption: SecureStorage,
+    envelope: EnvelopeEncryption,
 }
 
 impl StorageManager {
@@ -36,10 +37,49 @@ impl StorageManager {
         
         let storage_path = app_dir.join("secure_storage.json");
         let encryption = SecureStorage::new();
+        let envelope = EnvelopeEncryption::open_default()?;
         
         info!("Storage manager initialized with path: {:?}", storage_path);
         
-        Ok(Self { storage_path, encryption })
+        Ok(Self { storage_path, encryption, envelope })
+    }
+
+    pub fn envelope(&self) -> &EnvelopeEncryption {
+        &self.envelope
+    }
+
+    /// Decrypt an API key written by either the envelope or the legacy password scheme
+    fn decrypt_api_key(&self, encrypted_key: &str) -> Result<String> {
+        if EnvelopeEncryption::is_envelope(encrypted_key) {
+            return self.envelope.decrypt(KeyPurpose::ApiKeys, encrypted_key);
+        }
+        let master_password = self.envelope.legacy_password()
+            .context("Failed to get master encryption password")?;
+        self.encryption.decrypt(encrypted_key, &master_password)
+    }
+
+    /// Re-encrypt every stored API key with the active data key; returns (re-encrypted, failed)
+    pub fn reencrypt_api_keys(&self) -> Result<(usize, usize)> {
+        let mut storage = self.load_storage()?;
+        let (mut reencrypted, mut failed) = (0, 0);
+
+        for config in storage.api_keys.values_mut() {
+            match self.decrypt_api_key(&config.encrypted_key)
+                .and_then(|key| self.envelope.encrypt(KeyPurpose::ApiKeys, &key))
+            {
+                Ok(encrypted_key) => {
+                    config.encrypted_key = encrypted_key;
+                    reencrypted += 1;
+                }
+                Err(e) => {
+                    error!("Failed to re-encrypt API key for {}: {}", config.provider, e);
+                    failed += 1;
+                }
+            }
+        }
+
+        self.save_storage(&storage)?;
+        Ok((reencrypted, failed))
     }
 
     pub fn load_storage(&self) -> Result<SecureStorageData> {
@@ -72,12 +112,8 @@ impl StorageManager {
     pub fn store_api_key(&self, provider: &str, key: &str) -> Result<()> {
         let mut storage = self.load_storage()?;
         
-        // Get master password for encryption
-        let master_password = get_master_password()
-            .context("Failed to get master encryption password")?;
-        
-        // Encrypt the API key
-        let encrypted_key = self.encryption.encrypt(key, &master_password)
+        // Encrypt the API key with the active data key
+        let encrypted_key = self.envelope.encrypt(KeyPurpose::ApiKeys, key)
             .context("Failed to encrypt API key")?;
         
         let config = ApiKeyConfig {
@@ -98,12 +134,8 @@ impl StorageManager {
         let mut storage = self.load_storage()?;
         
         if let Some(config) = storage.api_keys.get_mut(provider) {
-            // Get master password for decryption
-            let master_password = get_master_password()
-                .context("Failed to get master encryption password")?;
-            
             // Decrypt the API key
-            let decrypted_key = self.encryption.decrypt(&config.encrypted_key, &master_password)
+            let decrypted_key = self.decrypt_api_key(&config.encrypted_key)
                 .context("Failed to decrypt API key - may be corrupted or password changed")?;
             
             // Update last used timestamp
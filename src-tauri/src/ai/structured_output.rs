@@ -0,0 +1,200 @@
+//! Structured generation against a caller-supplied JSON schema. The provider's native mode does
+//! the shaping (a forced tool call for Anthropic, `json_schema` output for OpenAI); every reply is
+//! then validated with `validation::json_schema`, and a failing reply is sent back with its
+//! violations until it matches or the attempts run out.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, State};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use super::commands::AIState;
+use super::guardrails::{GuardrailScope, GuardrailViolation};
+use super::orchestration::configured_provider;
+use super::provider_completion::{complete_structured, select_completion_provider, ResponseSchema};
+use crate::database::agent_sessions::load_agent;
+use crate::database::open_conversation_db;
+use crate::validation::{check_schema, validate_json_schema, SchemaViolation};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Violations quoted back to the model in a retry
+const MAX_QUOTED_VIOLATIONS: usize = 20;
+const MAX_QUOTED_REPLY_CHARS: usize = 4000;
+
+/// Property a non-object schema's value is wrapped in, since providers need an object at the root
+const WRAPPED_VALUE_KEY: &str = "value";
+
+#[derive(Error, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StructuredOutputError {
+    #[error("Invalid schema at {path}: {message}")]
+    InvalidSchema { path: String, message: String },
+
+    #[error("{message}")]
+    InvalidRequest { message: String },
+
+    #[error("Provider request failed: {message}")]
+    Provider { message: String },
+
+    #[error("{message}")]
+    Blocked { message: String, violations: Vec<GuardrailViolation> },
+
+    #[error("Reply did not match the schema after {attempts} attempts; first violation {}", .violations.first().map(ToString::to_string).unwrap_or_default())]
+    SchemaMismatch {
+        attempts: u32,
+        violations: Vec<SchemaViolation>,
+        last_reply: Value,
+    },
+}
+
+impl From<SchemaViolation> for StructuredOutputError {
+    fn from(violation: SchemaViolation) -> Self {
+        let path = if violation.path.is_empty() { "/".to_string() } else { violation.path };
+        StructuredOutputError::InvalidSchema { path, message: violation.message }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredOutput {
+    pub value: Value,
+    pub provider: String,
+    pub model: String,
+    pub attempts: u32,
+    /// What the agent's guardrails found in the prompts and replies, redactions included
+    pub guardrail_violations: Vec<GuardrailViolation>,
+}
+
+/// The schema sent to the provider: the caller's own when it describes an object, otherwise
+/// wrapped in one (with its definitions lifted so references still resolve)
+fn provider_schema(schema: &Value) -> (Value, bool) {
+    if schema.get("type").and_then(Value::as_str) == Some("object") {
+        return (schema.clone(), false);
+    }
+    let mut wrapped = json!({
+        "type": "object",
+        "properties": { WRAPPED_VALUE_KEY: schema },
+        "required": [WRAPPED_VALUE_KEY],
+        "additionalProperties": false,
+    });
+    for keyword in ["$defs", "definitions"] {
+        if let Some(definitions) = schema.get(keyword) {
+            wrapped[keyword] = definitions.clone();
+        }
+    }
+    (wrapped, true)
+}
+
+fn retry_prompt(prompt: &str, violations: &[SchemaViolation], reply: &Value) -> String {
+    let listed: Vec<String> = violations.iter()
+        .take(MAX_QUOTED_VIOLATIONS)
+        .map(|violation| format!("- {}", violation))
+        .collect();
+    let reply: String = reply.to_string().chars().take(MAX_QUOTED_REPLY_CHARS).collect();
+    format!(
+        "{}\n\nYour previous reply did not match the required schema:\n{}\n\nPrevious reply:\n{}\n\nReply again with output that matches the schema exactly.",
+        prompt,
+        listed.join("\n"),
+        reply,
+    )
+}
+
+// Tauri Commands
+
+/// Have an agent produce output matching `json_schema`. Replies that don't validate are retried
+/// with their violations up to `max_attempts` times (3 by default, at most 5).
+#[tauri::command]
+pub async fn generate_structured(
+    agent_id: String,
+    prompt: String,
+    json_schema: Value,
+    max_attempts: Option<u32>,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<StructuredOutput, StructuredOutputError> {
+    if prompt.trim().is_empty() {
+        return Err(StructuredOutputError::InvalidRequest { message: "Prompt cannot be empty".to_string() });
+    }
+    check_schema(&json_schema)?;
+    let max_attempts = max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).clamp(1, MAX_ATTEMPTS);
+
+    let (agent, provider) = open_conversation_db(&app_handle)
+        .and_then(|conn| {
+            let agent = load_agent(&conn, &agent_id)?;
+            let provider = select_completion_provider(&state, configured_provider(&conn, &agent_id)?.as_deref())?;
+            Ok((agent, provider))
+        })
+        .map_err(|e| StructuredOutputError::InvalidRequest { message: e.to_string() })?;
+    let system = agent.system_prompt.as_deref().unwrap_or_default();
+
+    let (sent_schema, wrapped) = provider_schema(&json_schema);
+    let response_schema = ResponseSchema {
+        name: "structured_output",
+        description: "The requested output",
+        schema: &sent_schema,
+    };
+
+    let mut guard = GuardrailScope::new(&app_handle, &state.storage, Some(&agent_id));
+    let mut request = prompt.clone();
+    let mut violations = Vec::new();
+    let mut reply = Value::Null;
+    for attempt in 1..=max_attempts {
+        let raw = match complete_structured(&state, &mut guard, &provider, Some(&agent.model), system, &request, &response_schema).await {
+            Ok(raw) => raw,
+            Err(e) if guard.blocked() => {
+                return Err(StructuredOutputError::Blocked { message: e.to_string(), violations: guard.into_violations() });
+            }
+            Err(e) => return Err(StructuredOutputError::Provider { message: e.to_string() }),
+        };
+        reply = if wrapped { raw.get(WRAPPED_VALUE_KEY).cloned().unwrap_or(Value::Null) } else { raw };
+
+        violations = validate_json_schema(&reply, &json_schema);
+        if violations.is_empty() {
+            info!("Structured output for agent {} matched its schema on attempt {}", agent_id, attempt);
+            return Ok(StructuredOutput {
+                value: reply,
+                provider,
+                model: agent.model.clone(),
+                attempts: attempt,
+                guardrail_violations: guard.into_violations(),
+            });
+        }
+        warn!("Structured output attempt {} for agent {} had {} schema violations", attempt, agent_id, violations.len());
+        request = retry_prompt(&prompt, &violations, &reply);
+    }
+
+    Err(StructuredOutputError::SchemaMismatch { attempts: max_attempts, violations, last_reply: reply })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_schemas_and_wrapping() {
+        let invalid = |schema: Value| StructuredOutputError::from(check_schema(&schema).unwrap_err());
+        assert!(matches!(invalid(json!("object")), StructuredOutputError::InvalidSchema { path, .. } if path == "/"));
+        assert!(matches!(
+            invalid(json!({ "properties": { "id": { "pattern": "(" } } })),
+            StructuredOutputError::InvalidSchema { path, .. } if path == "/properties/id"
+        ));
+
+        let list = json!({ "type": "array", "items": { "$ref": "#/$defs/n" }, "$defs": { "n": { "type": "number" } } });
+        let (sent, wrapped) = provider_schema(&list);
+        assert!(wrapped);
+        assert_eq!(sent["properties"]["value"]["type"], "array");
+        assert_eq!(sent["$defs"]["n"]["type"], "number");
+        assert!(check_schema(&sent).is_ok());
+        assert!(!provider_schema(&json!({ "type": "object" })).1);
+
+        let error = StructuredOutputError::SchemaMismatch {
+            attempts: 3,
+            violations: vec![SchemaViolation { path: "/id".to_string(), message: "is required".to_string() }],
+            last_reply: json!({}),
+        };
+        assert_eq!(serde_json::to_value(&error).unwrap()["kind"], "schema_mismatch");
+        assert!(retry_prompt("Make it", &[], &json!({})).starts_with("Make it\n\n"));
+    }
+}
@@ -0,0 +1,584 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{command, State};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use super::commands::AIState;
+use super::storage::StorageManager;
+
+/// Hugging Face repository of the bundled abstractive model
+pub const LOCAL_MODEL_REPO: &str = "google-t5/t5-small";
+
+/// Commit of `LOCAL_MODEL_REPO` the model files are downloaded from
+pub const LOCAL_MODEL_REVISION: &str = "df1b051c49625cf57a3d0d8d3863ed4d13564fe4";
+
+/// Files required to run the local model
+const LOCAL_MODEL_FILES: [&str; 3] = ["config.json", "tokenizer.json", "model.safetensors"];
+
+/// Longest input accepted for local summarization
+const MAX_INPUT_CHARS: usize = 200_000;
+
+const STOPWORDS: [&str; 48] = [
+    "the", "and", "for", "that", "this", "with", "you", "are", "was", "have", "has", "but",
+    "not", "can", "will", "from", "they", "their", "there", "what", "when", "which", "would",
+    "could", "should", "about", "into", "than", "then", "them", "these", "those", "been",
+    "were", "also", "just", "some", "your", "our", "its", "it's", "i'm", "how", "all", "any",
+    "more", "very", "out",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryKind {
+    Summary,
+    Title,
+    Digest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalSummary {
+    pub kind: SummaryKind,
+    pub text: String,
+    /// `t5-small` when the local model ran, otherwise `extractive`
+    pub engine: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalSummarizerStatus {
+    pub providers_configured: bool,
+    pub local_model_supported: bool,
+    pub local_model_downloaded: bool,
+    pub active_engine: String,
+}
+
+/// Directory the local model files are stored in
+pub fn local_model_dir() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("Failed to get config directory")?
+        .join("banshee")
+        .join("models")
+        .join("t5-small"))
+}
+
+pub fn local_model_downloaded() -> bool {
+    local_model_dir()
+        .map(|dir| LOCAL_MODEL_FILES.iter().all(|f| dir.join(f).exists()))
+        .unwrap_or(false)
+}
+
+/// The local summarizer is only used when no provider key is configured
+pub fn should_use_local_summarizer(storage: &StorageManager) -> bool {
+    storage.list_providers().map(|p| p.is_empty()).unwrap_or(true)
+}
+
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+
+    for c in text.chars() {
+        current.push(c);
+        if matches!(c, '.' | '!' | '?' | '\n') {
+            let trimmed = current.trim();
+            if trimmed.split_whitespace().count() >= 3 {
+                sentences.push(trimmed.to_string());
+            }
+            current.clear();
+        }
+    }
+    let trimmed = current.trim();
+    if trimmed.split_whitespace().count() >= 3 {
+        sentences.push(trimmed.to_string());
+    }
+
+    sentences
+}
+
+fn content_words(sentence: &str) -> Vec<String> {
+    sentence.split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.len() > 2 && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// Rank sentences by normalized content-word frequency with a small lead bias
+fn rank_sentences(sentences: &[String]) -> Vec<(usize, f32)> {
+    let mut frequencies: HashMap<String, f32> = HashMap::new();
+    for sentence in sentences {
+        for word in content_words(sentence) {
+            *frequencies.entry(word).or_insert(0.0) += 1.0;
+        }
+    }
+    let max_frequency = frequencies.values().cloned().fold(1.0, f32::max);
+
+    let mut ranked: Vec<(usize, f32)> = sentences.iter()
+        .enumerate()
+        .map(|(i, sentence)| {
+            let words = content_words(sentence);
+            let score = if words.is_empty() {
+                0.0
+            } else {
+                words.iter().map(|w| frequencies[w] / max_frequency).sum::<f32>() / (words.len() as f32).sqrt()
+            };
+            let lead_bonus = if i == 0 { 0.15 } else { 0.0 };
+            (i, score + lead_bonus)
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked
+}
+
+/// Extractive summary: the highest-ranked sentences in their original order
+pub fn extractive_summary(text: &str, max_sentences: usize) -> String {
+    let sentences = split_sentences(text);
+    if sentences.is_empty() {
+        return text.trim().chars().take(280).collect();
+    }
+
+    let mut chosen: Vec<usize> = rank_sentences(&sentences).into_iter()
+        .take(max_sentences.max(1))
+        .map(|(i, _)| i)
+        .collect();
+    chosen.sort_unstable();
+
+    chosen.iter().map(|&i| sentences[i].as_str()).collect::<Vec<_>>().join(" ")
+}
+
+/// Short title drawn from the most representative sentence
+pub fn extractive_title(text: &str) -> String {
+    let sentences = split_sentences(text);
+    let source = rank_sentences(&sentences).first()
+        .map(|(i, _)| sentences[*i].clone())
+        .unwrap_or_else(|| text.trim().to_string());
+
+    let title = source.split_whitespace().take(8).collect::<Vec<_>>().join(" ");
+    let title = title.trim_end_matches(|c: char| !c.is_alphanumeric()).to_string();
+
+    if title.is_empty() {
+        "Untitled conversation".to_string()
+    } else {
+        let mut chars = title.chars();
+        chars.next().map(|c| c.to_uppercase().collect::<String>() + chars.as_str()).unwrap_or(title)
+    }
+}
+
+/// Bullet digest of the key sentences
+pub fn extractive_digest(text: &str, max_points: usize) -> String {
+    let sentences = split_sentences(text);
+    let mut chosen: Vec<usize> = rank_sentences(&sentences).into_iter()
+        .take(max_points.max(1))
+        .map(|(i, _)| i)
+        .collect();
+    chosen.sort_unstable();
+
+    chosen.iter().map(|&i| format!("- {}", sentences[i])).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(feature = "local-summarizer")]
+mod t5_model {
+    use anyhow::{anyhow, Result};
+    use candle_core::{DType, Device, Tensor};
+    use candle_nn::VarBuilder;
+    use candle_transformers::generation::LogitsProcessor;
+    use candle_transformers::models::t5;
+    use std::path::Path;
+    use tokenizers::Tokenizer;
+
+    /// Encoder input is truncated to the model's context window
+    const MAX_INPUT_TOKENS: usize = 512;
+
+    pub struct T5Summarizer {
+        model: t5::T5ForConditionalGeneration,
+        tokenizer: Tokenizer,
+        config: t5::Config,
+        device: Device,
+    }
+
+    impl T5Summarizer {
+        pub fn load(model_dir: &Path) -> Result<Self> {
+            let device = Device::Cpu;
+            let config: t5::Config = serde_json::from_str(&std::fs::read_to_string(model_dir.join("config.json"))?)?;
+            let tokenizer = Tokenizer::from_file(model_dir.join("tokenizer.json"))
+                .map_err(|e| anyhow!("Failed to load tokenizer: {}", e))?;
+            // SAFETY: the weights file is owned by the app and not modified while mapped
+            let vb = unsafe {
+                VarBuilder::from_mmaped_safetensors(&[model_dir.join("model.safetensors")], DType::F32, &device)?
+            };
+            let model = t5::T5ForConditionalGeneration::load(vb, &config)?;
+
+            Ok(Self { model, tokenizer, config, device })
+        }
+
+        pub fn generate(&mut self, prompt: &str, max_new_tokens: usize) -> Result<String> {
+            let mut input_ids = self.tokenizer.encode(prompt, true)
+                .map_err(|e| anyhow!("Tokenization failed: {}", e))?
+                .get_ids()
+                .to_vec();
+            input_ids.truncate(MAX_INPUT_TOKENS);
+
+            self.model.clear_kv_cache();
+            let input = Tensor::new(input_ids.as_slice(), &self.device)?.unsqueeze(0)?;
+            let encoder_output = self.model.encode(&input)?;
+
+            let start = self.config.decoder_start_token_id.unwrap_or(self.config.pad_token_id) as u32;
+            let mut output_ids = vec![start];
+            // Greedy decoding keeps output deterministic
+            let mut logits_processor = LogitsProcessor::new(0, None, None);
+
+            for step in 0..max_new_tokens {
+                let decoder_ids = if step == 0 || !self.config.use_cache {
+                    Tensor::new(output_ids.as_slice(), &self.device)?.unsqueeze(0)?
+                } else {
+                    Tensor::new(&output_ids[output_ids.len() - 1..], &self.device)?.unsqueeze(0)?
+                };
+                let logits = self.model.decode(&decoder_ids, &encoder_output)?.squeeze(0)?;
+                let next = logits_processor.sample(&logits)?;
+                if next as usize == self.config.eos_token_id {
+                    break;
+                }
+                output_ids.push(next);
+            }
+
+            self.tokenizer.decode(&output_ids[1..], true)
+                .map_err(|e| anyhow!("Detokenization failed: {}", e))
+        }
+    }
+
+    pub static MODEL: once_cell::sync::Lazy<std::sync::Mutex<Option<T5Summarizer>>> =
+        once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+}
+
+/// Run the local abstractive model if it is compiled in and downloaded
+#[cfg(feature = "local-summarizer")]
+fn abstractive_summary(text: &str, kind: SummaryKind) -> Option<String> {
+    if !local_model_downloaded() {
+        return None;
+    }
+
+    let mut model = t5_model::MODEL.lock().ok()?;
+    if model.is_none() {
+        match local_model_dir().and_then(|dir| t5_model::T5Summarizer::load(&dir)) {
+            Ok(loaded) => *model = Some(loaded),
+            Err(e) => {
+                tracing::warn!("Failed to load local summarization model: {}", e);
+                return None;
+            }
+        }
+    }
+
+    let max_tokens = match kind {
+        SummaryKind::Title => 16,
+        SummaryKind::Summary => 128,
+        SummaryKind::Digest => 192,
+    };
+    match model.as_mut()?.generate(&format!("summarize: {}", text), max_tokens) {
+        Ok(output) if !output.trim().is_empty() => Some(output.trim().to_string()),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::warn!("Local summarization failed: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "local-summarizer"))]
+fn abstractive_summary(_text: &str, _kind: SummaryKind) -> Option<String> {
+    None
+}
+
+/// Summarize without any provider, preferring the local model over extraction
+pub fn summarize_offline(text: &str, kind: SummaryKind, max_sentences: usize) -> LocalSummary {
+    if let Some(output) = abstractive_summary(text, kind) {
+        let text = match kind {
+            SummaryKind::Title => extractive_title(&output),
+            SummaryKind::Digest => extractive_digest(&output, max_sentences),
+            SummaryKind::Summary => output,
+        };
+        return LocalSummary { kind, text, engine: "t5-small".to_string() };
+    }
+
+    let text = match kind {
+        SummaryKind::Summary => extractive_summary(text, max_sentences),
+        SummaryKind::Title => extractive_title(text),
+        SummaryKind::Digest => extractive_digest(text, max_sentences),
+    };
+    LocalSummary { kind, text, engine: "extractive".to_string() }
+}
+
+// Tauri Commands
+
+#[command]
+pub async fn get_local_summarizer_status(
+    state: State<'_, AIState>,
+) -> Result<LocalSummarizerStatus, String> {
+    let local_model_supported = cfg!(feature = "local-summarizer");
+    let local_model_downloaded = local_model_downloaded();
+
+    Ok(LocalSummarizerStatus {
+        providers_configured: !should_use_local_summarizer(&state.storage),
+        local_model_supported,
+        local_model_downloaded,
+        active_engine: if local_model_supported && local_model_downloaded { "t5-small" } else { "extractive" }.to_string(),
+    })
+}
+
+/// Digest the Hub publishes for a file at the pinned revision
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExpectedDigest {
+    /// SHA-256 of the content, reported for files stored in Git LFS
+    Sha256(String),
+    /// Git blob id (SHA-1 over `blob <len>\0<content>`), reported for regular files
+    GitBlob(String),
+}
+
+/// Hashes a download incrementally so the weights never have to sit in memory
+struct DownloadDigest {
+    sha256: Sha256,
+    blob: Sha1,
+}
+
+impl DownloadDigest {
+    fn new(len: u64) -> Self {
+        let mut blob = Sha1::new();
+        blob.update(format!("blob {}\0", len).as_bytes());
+        Self { sha256: Sha256::new(), blob }
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        self.sha256.update(chunk);
+        self.blob.update(chunk);
+    }
+
+    fn matches(self, expected: &ExpectedDigest) -> bool {
+        match expected {
+            ExpectedDigest::Sha256(hex_digest) => hex::encode(self.sha256.finalize()) == *hex_digest,
+            ExpectedDigest::GitBlob(hex_digest) => hex::encode(self.blob.finalize()) == *hex_digest,
+        }
+    }
+}
+
+fn header<'a>(headers: &'a reqwest::header::HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Read the revision and digest the Hub reports for a file, as `huggingface_hub` does
+fn expected_digest(file: &str, headers: &reqwest::header::HeaderMap) -> Result<ExpectedDigest, String> {
+    let commit = header(headers, "x-repo-commit")
+        .ok_or_else(|| format!("Hub did not report the revision of {}", file))?;
+    if commit != LOCAL_MODEL_REVISION {
+        return Err(format!("Hub served {} from revision {} instead of {}", file, commit, LOCAL_MODEL_REVISION));
+    }
+
+    let normalize = |etag: &str| etag.trim_start_matches("W/").trim_matches('"').to_ascii_lowercase();
+    let is_hex = |digest: &str, len: usize| digest.len() == len && digest.bytes().all(|b| b.is_ascii_hexdigit());
+
+    if let Some(linked) = header(headers, "x-linked-etag").map(normalize) {
+        if is_hex(&linked, 64) {
+            return Ok(ExpectedDigest::Sha256(linked));
+        }
+    }
+    match header(headers, "etag").map(normalize) {
+        Some(etag) if is_hex(&etag, 64) => Ok(ExpectedDigest::Sha256(etag)),
+        Some(etag) if is_hex(&etag, 40) => Ok(ExpectedDigest::GitBlob(etag)),
+        _ => Err(format!("Hub did not report a digest for {}", file)),
+    }
+}
+
+/// Download one model file at the pinned revision and keep it only if its digest matches
+async fn download_model_file(
+    metadata_client: &reqwest::Client,
+    client: &reqwest::Client,
+    dir: &std::path::Path,
+    file: &str,
+) -> Result<(), String> {
+    let url = format!("https://huggingface.co/{}/resolve/{}/{}", LOCAL_MODEL_REPO, LOCAL_MODEL_REVISION, file);
+
+    // LFS files redirect to a CDN that drops the Hub headers, so read them without following redirects
+    let head = metadata_client.head(&url).send().await
+        .map_err(|e| format!("Failed to resolve {}: {}", file, e))?;
+    if !(head.status().is_success() || head.status().is_redirection()) {
+        return Err(format!("Failed to resolve {}: HTTP {}", file, head.status()));
+    }
+    let expected = expected_digest(file, head.headers())?;
+
+    info!("Downloading local model file: {}", url);
+    let mut response = client.get(&url).send().await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", file, e))?;
+    let len = response.content_length()
+        .ok_or_else(|| format!("Failed to download {}: missing content length", file))?;
+
+    // Write to a temporary name so a partial or unverified download is never mistaken for a complete model
+    let partial = dir.join(format!("{}.part", file));
+    let mut out = tokio::fs::File::create(&partial).await
+        .map_err(|e| format!("Failed to write {}: {}", file, e))?;
+    let mut digest = DownloadDigest::new(len);
+    let mut written = 0u64;
+    let result: Result<(), String> = async {
+        while let Some(chunk) = response.chunk().await
+            .map_err(|e| format!("Failed to download {}: {}", file, e))?
+        {
+            written += chunk.len() as u64;
+            if written > len {
+                return Err(format!("Failed to download {}: more data than announced", file));
+            }
+            digest.update(&chunk);
+            out.write_all(&chunk).await
+                .map_err(|e| format!("Failed to write {}: {}", file, e))?;
+        }
+        out.flush().await.map_err(|e| format!("Failed to write {}: {}", file, e))?;
+        if written != len {
+            return Err(format!("Failed to download {}: connection closed early", file));
+        }
+        Ok(())
+    }.await;
+    drop(out);
+
+    let result = result.and_then(|()| {
+        if digest.matches(&expected) {
+            Ok(())
+        } else {
+            Err(format!("Downloaded {} does not match the digest for revision {}", file, LOCAL_MODEL_REVISION))
+        }
+    });
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e);
+    }
+
+    tokio::fs::rename(&partial, dir.join(file)).await
+        .map_err(|e| format!("Failed to write {}: {}", file, e))
+}
+
+/// Download the local summarization model into the app config directory
+#[command]
+pub async fn download_summarization_model() -> Result<String, String> {
+    if !cfg!(feature = "local-summarizer") {
+        return Err("This build does not include the local summarization model".to_string());
+    }
+
+    let dir = local_model_dir().map_err(|e| e.to_string())?;
+    tokio::fs::create_dir_all(&dir).await
+        .map_err(|e| format!("Failed to create model directory: {}", e))?;
+
+    // The shared client's 30s timeout is too short for the weights file
+    let client = reqwest::Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let metadata_client = reqwest::Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(30))
+        .timeout(std::time::Duration::from_secs(60))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    for file in LOCAL_MODEL_FILES {
+        if dir.join(file).exists() {
+            continue;
+        }
+        download_model_file(&metadata_client, &client, &dir, file).await?;
+    }
+
+    info!("Local summarization model ready at {:?}", dir);
+    Ok(dir.to_string_lossy().to_string())
+}
+
+#[command]
+pub async fn summarize_locally(
+    text: String,
+    kind: SummaryKind,
+    max_sentences: Option<usize>,
+) -> Result<LocalSummary, String> {
+    if text.trim().is_empty() {
+        return Err("Text is required".to_string());
+    }
+    if text.len() > MAX_INPUT_CHARS {
+        return Err(format!("Text exceeds {} characters", MAX_INPUT_CHARS));
+    }
+
+    let max_sentences = max_sentences.unwrap_or(3).clamp(1, 20);
+    tokio::task::spawn_blocking(move || summarize_offline(&text, kind, max_sentences))
+        .await
+        .map_err(|e| format!("Summarization task failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "The deployment failed because the database migration timed out. \
+        We increased the migration timeout to five minutes. \
+        The weather was nice today. \
+        After raising the timeout the database migration completed and the deployment succeeded.";
+
+    #[test]
+    fn test_extractive_summary_prefers_central_sentences() {
+        let summary = extractive_summary(TEXT, 2);
+        assert!(summary.contains("database migration"));
+        assert!(!summary.contains("weather"));
+    }
+
+    #[test]
+    fn test_title_and_digest() {
+        let title = extractive_title(TEXT);
+        assert!(title.split_whitespace().count() <= 8);
+        assert!(title.chars().next().unwrap().is_uppercase());
+
+        let digest = extractive_digest(TEXT, 2);
+        assert_eq!(digest.lines().count(), 2);
+        assert!(digest.lines().all(|l| l.starts_with("- ")));
+    }
+
+    fn hub_headers(pairs: &[(&'static str, &str)]) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_expected_digest_requires_the_pinned_revision() {
+        let lfs = "a".repeat(64);
+        let headers = hub_headers(&[("x-repo-commit", LOCAL_MODEL_REVISION), ("x-linked-etag", &format!("\"{}\"", lfs))]);
+        assert_eq!(expected_digest("model.safetensors", &headers), Ok(ExpectedDigest::Sha256(lfs.clone())));
+
+        let blob = "b".repeat(40);
+        let headers = hub_headers(&[("x-repo-commit", LOCAL_MODEL_REVISION), ("etag", &format!("W/\"{}\"", blob))]);
+        assert_eq!(expected_digest("config.json", &headers), Ok(ExpectedDigest::GitBlob(blob)));
+
+        let headers = hub_headers(&[("x-repo-commit", "0000000"), ("x-linked-etag", &lfs)]);
+        assert!(expected_digest("model.safetensors", &headers).is_err());
+        assert!(expected_digest("config.json", &hub_headers(&[("x-repo-commit", LOCAL_MODEL_REVISION)])).is_err());
+    }
+
+    #[test]
+    fn test_download_digest_checks_content() {
+        let content = b"hello world\n";
+        let sha256 = hex::encode(Sha256::digest(content));
+        // `git hash-object` of the same content
+        let blob = "3b18e512dba79e4c8300dd08aeb37f8e728b8dad".to_string();
+
+        for expected in [ExpectedDigest::Sha256(sha256), ExpectedDigest::GitBlob(blob)] {
+            let mut digest = DownloadDigest::new(content.len() as u64);
+            digest.update(&content[..5]);
+            digest.update(&content[5..]);
+            assert!(digest.matches(&expected));
+
+            let mut tampered = DownloadDigest::new(content.len() as u64);
+            tampered.update(b"hello World\n");
+            assert!(!tampered.matches(&expected));
+        }
+    }
+
+    #[test]
+    fn test_offline_summary_without_model() {
+        let summary = summarize_offline("short note", SummaryKind::Summary, 3);
+        assert_eq!(summary.text, "short note");
+    }
+}
@@ -29,10 +29,26 @@ pub struct Message {
     pub conversation_id: String,
     pub role: MessageRole,
     pub content: String,
+    /// Images sent along with the text, for vision-capable models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImagePart>,
     pub created_at: DateTime<Utc>,
     pub tokens: Option<u32>,
 }
 
+/// An image attached to a message, either inline or as a file read when the request is sent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImagePart {
+    Base64 {
+        /// Detected from the content when not given
+        #[serde(default)]
+        media_type: Option<String>,
+        data: String,
+    },
+    Path { path: String },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MessageRole {
@@ -0,0 +1,306 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{info, warn, error};
+
+use super::commands::AIState;
+
+/// Upper bound on concurrently open sockets
+const MAX_CONNECTIONS: usize = 16;
+
+/// Largest outgoing message accepted from the frontend
+const MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebSocketEventKind {
+    Text,
+    /// `data` is base64-encoded
+    Binary,
+    Closed,
+    Error,
+}
+
+/// Payload emitted on `websocket_message_<connection_id>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketEvent {
+    pub connection_id: String,
+    pub kind: WebSocketEventKind,
+    pub data: Option<String>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketConnectionInfo {
+    pub connection_id: String,
+    pub url: String,
+    pub protocol: Option<String>,
+}
+
+struct WebSocketConnection {
+    url: String,
+    outgoing: mpsc::UnboundedSender<Message>,
+}
+
+type ConnectionMap = Arc<Mutex<HashMap<String, WebSocketConnection>>>;
+
+/// Open WebSocket connections keyed by connection id
+pub struct WebSocketState {
+    connections: ConnectionMap,
+}
+
+impl WebSocketState {
+    pub fn new() -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+fn lock(connections: &ConnectionMap) -> Result<std::sync::MutexGuard<'_, HashMap<String, WebSocketConnection>>, String> {
+    connections.lock().map_err(|_| "Failed to acquire websocket lock".to_string())
+}
+
+/// Only ws:// and wss:// URLs with a host are accepted
+fn validate_ws_url(url: &str) -> Result<url::Url, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid WebSocket URL: {}", e))?;
+    if !matches!(parsed.scheme(), "ws" | "wss") {
+        return Err(format!("Unsupported WebSocket scheme: {}", parsed.scheme()));
+    }
+    if parsed.host_str().is_none() {
+        return Err("WebSocket URL has no host".to_string());
+    }
+    Ok(parsed)
+}
+
+fn to_event(connection_id: &str, message: Message) -> Option<WebSocketEvent> {
+    let (kind, data) = match message {
+        Message::Text(text) => (WebSocketEventKind::Text, Some(text.to_string())),
+        Message::Binary(bytes) => (WebSocketEventKind::Binary, Some(BASE64.encode(&bytes))),
+        Message::Close(frame) => (WebSocketEventKind::Closed, frame.map(|f| f.reason.to_string())),
+        // Pings are answered by tungstenite; pongs and raw frames are not interesting to agents
+        Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => return None,
+    };
+
+    Some(WebSocketEvent {
+        connection_id: connection_id.to_string(),
+        kind,
+        data,
+        timestamp: chrono::Utc::now(),
+    })
+}
+
+/// Open a WebSocket; incoming messages are emitted as `websocket_message_<connection_id>` events
+#[tauri::command]
+pub async fn websocket_connect_command(
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    protocols: Option<Vec<String>>,
+    app: AppHandle,
+    state: State<'_, AIState>,
+    ws_state: State<'_, WebSocketState>,
+) -> Result<WebSocketConnectionInfo, String> {
+    info!("Opening WebSocket: {}", url);
+
+    // Same rate limiting, sanitization and domain allow-list as the HTTP tool
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request("http_requests", &[url], &[]).await?;
+    let url = validation_result.sanitized_inputs[0].clone();
+    validate_ws_url(&url)?;
+    if !security_middleware.validate_url(&url).await? {
+        return Err("Access to this host is not allowed".to_string());
+    }
+
+    if lock(&ws_state.connections)?.len() >= MAX_CONNECTIONS {
+        return Err(format!("Too many open WebSocket connections (limit: {})", MAX_CONNECTIONS));
+    }
+
+    let mut request = url.as_str().into_client_request()
+        .map_err(|e| format!("Invalid WebSocket request: {}", e))?;
+    for (name, value) in headers.unwrap_or_default() {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("Invalid header name: {}", name))?;
+        let value = HeaderValue::from_str(&value)
+            .map_err(|_| format!("Invalid value for header {}", name))?;
+        request.headers_mut().insert(name, value);
+    }
+    if let Some(protocols) = protocols.filter(|p| !p.is_empty()) {
+        let value = HeaderValue::from_str(&protocols.join(", "))
+            .map_err(|_| "Invalid WebSocket protocol list".to_string())?;
+        request.headers_mut().insert("Sec-WebSocket-Protocol", value);
+    }
+
+    let (stream, response) = tokio::time::timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(request))
+        .await
+        .map_err(|_| format!("WebSocket connection timed out after {}s", CONNECT_TIMEOUT.as_secs()))?
+        .map_err(|e| format!("WebSocket connection failed: {}", e))?;
+
+    let protocol = response.headers()
+        .get("Sec-WebSocket-Protocol")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<Message>();
+    lock(&ws_state.connections)?.insert(connection_id.clone(), WebSocketConnection {
+        url: url.clone(),
+        outgoing,
+    });
+
+    let connections = ws_state.connections.clone();
+    let task_id = connection_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let event_name = format!("websocket_message_{}", task_id);
+        let emit = |event: WebSocketEvent| {
+            if let Err(e) = app.emit(&event_name, &event) {
+                error!("Failed to emit WebSocket event: {}", e);
+            }
+        };
+        let (mut sink, mut source) = stream.split();
+        let mut closing = false;
+
+        loop {
+            tokio::select! {
+                outgoing = outgoing_rx.recv(), if !closing => {
+                    // A dropped sender means the connection was removed by websocket_close_command
+                    let message = outgoing.unwrap_or(Message::Close(None));
+                    closing = matches!(message, Message::Close(_));
+                    if let Err(e) = sink.send(message).await {
+                        warn!("WebSocket {} send failed: {}", task_id, e);
+                        emit(WebSocketEvent {
+                            connection_id: task_id.clone(),
+                            kind: WebSocketEventKind::Error,
+                            data: Some(e.to_string()),
+                            timestamp: chrono::Utc::now(),
+                        });
+                        break;
+                    }
+                }
+                incoming = source.next() => match incoming {
+                    Some(Ok(message)) => {
+                        let closed = matches!(message, Message::Close(_));
+                        if let Some(event) = to_event(&task_id, message) {
+                            emit(event);
+                        }
+                        if closed {
+                            break;
+                        }
+                    }
+                    Some(Err(e)) => {
+                        warn!("WebSocket {} error: {}", task_id, e);
+                        emit(WebSocketEvent {
+                            connection_id: task_id.clone(),
+                            kind: WebSocketEventKind::Error,
+                            data: Some(e.to_string()),
+                            timestamp: chrono::Utc::now(),
+                        });
+                        break;
+                    }
+                    None => {
+                        emit(WebSocketEvent {
+                            connection_id: task_id.clone(),
+                            kind: WebSocketEventKind::Closed,
+                            data: None,
+                            timestamp: chrono::Utc::now(),
+                        });
+                        break;
+                    }
+                },
+            }
+        }
+
+        if let Ok(mut connections) = connections.lock() {
+            connections.remove(&task_id);
+        }
+        info!("WebSocket {} closed", task_id);
+    });
+
+    info!("WebSocket {} connected to {}", connection_id, url);
+    Ok(WebSocketConnectionInfo { connection_id, url, protocol })
+}
+
+/// Send a text message, or base64-encoded bytes when `binary` is set
+#[tauri::command]
+pub async fn websocket_send_command(
+    connection_id: String,
+    message: String,
+    binary: Option<bool>,
+    ws_state: State<'_, WebSocketState>,
+) -> Result<(), String> {
+    let message = if binary.unwrap_or(false) {
+        let bytes = BASE64.decode(message.as_bytes())
+            .map_err(|_| "Binary message is not valid base64".to_string())?;
+        if bytes.len() > MAX_MESSAGE_BYTES {
+            return Err(format!("Message exceeds {} bytes", MAX_MESSAGE_BYTES));
+        }
+        Message::binary(bytes)
+    } else {
+        if message.len() > MAX_MESSAGE_BYTES {
+            return Err(format!("Message exceeds {} bytes", MAX_MESSAGE_BYTES));
+        }
+        Message::text(message)
+    };
+
+    let connections = lock(&ws_state.connections)?;
+    let connection = connections.get(&connection_id)
+        .ok_or_else(|| format!("Unknown WebSocket connection: {}", connection_id))?;
+    connection.outgoing.send(message)
+        .map_err(|_| "WebSocket connection is closed".to_string())
+}
+
+/// Close a WebSocket with an optional reason
+#[tauri::command]
+pub async fn websocket_close_command(
+    connection_id: String,
+    reason: Option<String>,
+    ws_state: State<'_, WebSocketState>,
+) -> Result<(), String> {
+    let connection = lock(&ws_state.connections)?
+        .remove(&connection_id)
+        .ok_or_else(|| format!("Unknown WebSocket connection: {}", connection_id))?;
+
+    let frame = CloseFrame {
+        code: CloseCode::Normal,
+        reason: reason.unwrap_or_default().into(),
+    };
+    // The connection task may already have exited; nothing left to close then
+    let _ = connection.outgoing.send(Message::Close(Some(frame)));
+    info!("WebSocket {} to {} closing", connection_id, connection.url);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ws_url_validation() {
+        assert!(validate_ws_url("wss://stream.example.com/feed").is_ok());
+        assert!(validate_ws_url("ws://localhost:8080").is_ok());
+        assert!(validate_ws_url("https://example.com").is_err());
+        assert!(validate_ws_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_message_events() {
+        let text = to_event("c1", Message::text("hello")).unwrap();
+        assert_eq!(text.kind, WebSocketEventKind::Text);
+        assert_eq!(text.data.as_deref(), Some("hello"));
+
+        let binary = to_event("c1", Message::binary(vec![0u8, 255])).unwrap();
+        assert_eq!(binary.data.as_deref(), Some("AP8="));
+
+        assert!(to_event("c1", Message::Ping(vec![1].into())).is_none());
+    }
+}
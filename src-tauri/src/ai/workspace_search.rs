@@ -0,0 +1,308 @@
+use anyhow::{Context, Result};
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use tauri::State;
+use tracing::{info, error};
+
+use super::commands::AIState;
+use super::security::{active_workspace, enforce_path_policy, load_path_policy, session_scoped_path, PathAccess, PathPolicy};
+
+/// Files larger than this are skipped rather than scanned
+const MAX_SEARCH_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Upper bound on results a single search may return
+const MAX_SEARCH_RESULTS: usize = 5000;
+
+/// Lines longer than this are truncated in results
+const MAX_LINE_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSearchOptions {
+    /// Treat the query as a regular expression instead of a literal
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Lines of context returned before and after each match
+    #[serde(default = "default_context_lines")]
+    pub context_lines: usize,
+    #[serde(default = "default_max_results")]
+    pub max_results: usize,
+    /// Only search files matching these globs (e.g. `*.rs`); `!` prefixes exclude
+    #[serde(default)]
+    pub include_globs: Vec<String>,
+    /// Search dotfiles and dot-directories
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Workspace whose path policy the search root is checked against
+    #[serde(default)]
+    pub workspace: Option<String>,
+}
+
+fn default_context_lines() -> usize {
+    2
+}
+
+fn default_max_results() -> usize {
+    200
+}
+
+impl Default for WorkspaceSearchOptions {
+    fn default() -> Self {
+        Self {
+            regex: false,
+            case_sensitive: false,
+            context_lines: default_context_lines(),
+            max_results: default_max_results(),
+            include_globs: Vec::new(),
+            include_hidden: false,
+            workspace: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    /// Path relative to the search root
+    pub file: String,
+    /// 1-based line number
+    pub line_number: usize,
+    /// 1-based character column of the first match on the line
+    pub column: usize,
+    pub line: String,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSearchResult {
+    pub matches: Vec<SearchMatch>,
+    pub files_searched: usize,
+    /// True when matches beyond `max_results` were left out
+    pub truncated: bool,
+}
+
+fn build_matcher(query: &str, options: &WorkspaceSearchOptions) -> Result<Regex> {
+    let pattern = if options.regex { query.to_string() } else { regex::escape(query) };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .size_limit(1 << 20)
+        .build()
+        .context("Invalid search pattern")
+}
+
+fn truncate_line(line: &str) -> String {
+    if line.chars().count() > MAX_LINE_CHARS {
+        format!("{}…", line.chars().take(MAX_LINE_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Read a file as text, returning None for oversized or binary files
+fn read_text_file(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if metadata.len() > MAX_SEARCH_FILE_BYTES {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(metadata.len() as usize);
+    std::fs::File::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+
+    // Same heuristic as git: a NUL byte near the start means binary
+    if bytes.iter().take(8192).any(|&b| b == 0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Collect matches from one file, returning true when a match was left out because `out`
+/// already holds `limit` results
+fn search_file(matcher: &Regex, file: &str, text: &str, context: usize, out: &mut Vec<SearchMatch>, limit: usize) -> bool {
+    let lines: Vec<&str> = text.lines().collect();
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some(found) = matcher.find(line) else { continue };
+        if out.len() >= limit {
+            return true;
+        }
+
+        out.push(SearchMatch {
+            file: file.to_string(),
+            line_number: index + 1,
+            column: line[..found.start()].chars().count() + 1,
+            line: truncate_line(line),
+            context_before: lines[index.saturating_sub(context)..index].iter().map(|l| truncate_line(l)).collect(),
+            context_after: lines[index + 1..(index + 1 + context).min(lines.len())].iter().map(|l| truncate_line(l)).collect(),
+        });
+    }
+    false
+}
+
+/// Search files under `root`, honouring .gitignore, .ignore and global git excludes.
+/// Files the path policy refuses (e.g. `.env`, `*.pem`) are skipped.
+pub fn search_workspace(
+    root: &Path,
+    query: &str,
+    options: &WorkspaceSearchOptions,
+    policy: &PathPolicy,
+) -> Result<WorkspaceSearchResult> {
+    let matcher = build_matcher(query, options)?;
+    let limit = options.max_results.min(MAX_SEARCH_RESULTS);
+
+    let mut walker = WalkBuilder::new(root);
+    walker.hidden(!options.include_hidden)
+        .git_ignore(true)
+        .git_exclude(true)
+        .require_git(false);
+
+    if !options.include_globs.is_empty() {
+        let mut overrides = OverrideBuilder::new(root);
+        for glob in &options.include_globs {
+            overrides.add(glob).context("Invalid include glob")?;
+        }
+        walker.overrides(overrides.build().context("Invalid include globs")?);
+    }
+
+    let mut matches = Vec::new();
+    let mut files_searched = 0;
+    let mut truncated = false;
+
+    for entry in walker.build().filter_map(|e| e.ok()) {
+        if !entry.file_type().map_or(false, |t| t.is_file()) {
+            continue;
+        }
+        if policy.check(&entry.path().to_string_lossy(), PathAccess::Read).is_err() {
+            continue;
+        }
+        let Some(text) = read_text_file(entry.path()) else { continue };
+
+        files_searched += 1;
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().to_string();
+        if search_file(&matcher, &relative, &text, options.context_lines, &mut matches, limit) {
+            truncated = true;
+            break;
+        }
+    }
+
+    Ok(WorkspaceSearchResult {
+        matches,
+        files_searched,
+        truncated,
+    })
+}
+
+/// Literal or regex content search across workspace files. A secure session bound to a
+/// workspace root only searches inside that root.
+#[tauri::command]
+pub async fn search_workspace_command(
+    query: String,
+    path: Option<String>,
+    options: Option<WorkspaceSearchOptions>,
+    session_id: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<WorkspaceSearchResult, String> {
+    let options = options.unwrap_or_default();
+    let path = path.unwrap_or_else(|| ".".to_string());
+    info!("Searching workspace {} for: {}", path, query);
+
+    if query.is_empty() || query.len() > 1000 {
+        return Err("Search query must be between 1 and 1000 characters".to_string());
+    }
+
+    let scoped = session_scoped_path(session_id.as_deref(), &path)?;
+    let root = enforce_path_policy(&state.storage, options.workspace.as_deref(), &scoped, PathAccess::List)?;
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+
+    let workspace = options.workspace.clone().unwrap_or_else(|| active_workspace(&state.storage));
+    let policy = load_path_policy(&state.storage, &workspace).map_err(|e| {
+        error!("Failed to load path policy for {}: {}", workspace, e);
+        "Failed to load path policy".to_string()
+    })?;
+
+    let result = tokio::task::spawn_blocking(move || search_workspace(&root, &query, &options, &policy))
+        .await
+        .map_err(|e| format!("Search task failed: {}", e))?;
+
+    result.map_err(|e| {
+        error!("Workspace search failed: {}", e);
+        format!("Workspace search failed: {}", e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {\n    let value = compute();\n    println!(\"{}\", value);\n}\n").unwrap();
+        std::fs::write(dir.path().join("src/notes.txt"), "Compute later\n").unwrap();
+        std::fs::write(dir.path().join("target/build.rs"), "compute()\n").unwrap();
+        std::fs::write(dir.path().join("src/blob.bin"), b"compute\0\0").unwrap();
+        dir
+    }
+
+    fn policy_for(dir: &tempfile::TempDir) -> PathPolicy {
+        PathPolicy {
+            allowed_roots: vec![dir.path().to_string_lossy().to_string()],
+            ..PathPolicy::default_for("test")
+        }
+    }
+
+    #[test]
+    fn test_literal_search_respects_gitignore() {
+        let dir = workspace();
+        let result = search_workspace(dir.path(), "compute", &WorkspaceSearchOptions::default(), &policy_for(&dir)).unwrap();
+
+        let mut files: Vec<&str> = result.matches.iter().map(|m| m.file.as_str()).collect();
+        files.sort();
+        assert_eq!(files, vec!["src/main.rs", "src/notes.txt"]);
+
+        let hit = result.matches.iter().find(|m| m.file == "src/main.rs").unwrap();
+        assert_eq!(hit.line_number, 2);
+        assert_eq!(hit.column, 17);
+        assert_eq!(hit.context_before, vec!["fn main() {"]);
+        assert_eq!(hit.context_after.len(), 2);
+    }
+
+    #[test]
+    fn test_regex_globs_and_limits() {
+        let dir = workspace();
+        let options = WorkspaceSearchOptions {
+            regex: true,
+            case_sensitive: true,
+            include_globs: vec!["*.rs".to_string()],
+            ..Default::default()
+        };
+        let result = search_workspace(dir.path(), r"let \w+ =", &options, &policy_for(&dir)).unwrap();
+        assert_eq!(result.matches.len(), 1);
+
+        let limited = WorkspaceSearchOptions { max_results: 1, ..Default::default() };
+        assert!(search_workspace(dir.path(), "compute", &limited, &policy_for(&dir)).unwrap().truncated);
+        let exact = WorkspaceSearchOptions { max_results: 2, ..Default::default() };
+        assert!(!search_workspace(dir.path(), "compute", &exact, &policy_for(&dir)).unwrap().truncated);
+        assert!(build_matcher("(", &options).is_err());
+    }
+
+    #[test]
+    fn test_search_skips_files_denied_by_policy() {
+        let dir = workspace();
+        std::fs::write(dir.path().join("src/.env"), "COMPUTE_KEY=1\n").unwrap();
+        std::fs::write(dir.path().join("src/cert.pem"), "compute\n").unwrap();
+        let options = WorkspaceSearchOptions { include_hidden: true, ..Default::default() };
+
+        let result = search_workspace(dir.path(), "compute", &options, &policy_for(&dir)).unwrap();
+        assert!(result.matches.iter().all(|m| !m.file.ends_with(".env") && !m.file.ends_with(".pem")));
+        assert_eq!(result.matches.len(), 2);
+    }
+}
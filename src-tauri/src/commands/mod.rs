@@ -1,7 +1,15 @@
 use serde::Serialize;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 use crate::ai::AIState;
-use crate::mcp::MCPServer;
+use crate::database::agent_sessions::{
+    AgentSession, AgentSessionData, AgentSessionManager, AGENT_SESSION_CLOSED_EVENT, AGENT_SESSION_CREATED_EVENT,
+};
+use crate::database::open_conversation_db;
+use crate::mcp::{
+    diagnose_server, find_server, load_registry, refresh_server_catalog, MCPConnectionDiagnostics, MCPServer,
+    MCPToolCatalog,
+};
+use tracing::{info, warn};
 
 #[derive(Debug, Serialize)]
 pub struct SystemStats {
@@ -22,14 +30,6 @@ pub struct ApiKey {
     pub last_used: Option<chrono::DateTime<chrono::Utc>>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct AgentSessionData {
-    pub agent_id: String,
-    pub agent: crate::ai::Agent,
-    pub conversation_id: Option<String>,
-    pub conversation: Option<crate::ai::Conversation>,
-}
-
 #[tauri::command]
 pub async fn get_system_stats_command() -> Result<SystemStats, String> {
     // Mock implementation for now
@@ -76,81 +76,135 @@ pub async fn delete_api_key_command(
         .map_err(|e| e.to_string())
 }
 
+/// Registered MCP servers with their connection status from the tool catalog
 #[tauri::command]
-pub async fn get_mcp_servers_command() -> Result<Vec<MCPServer>, String> {
-    // Mock implementation
-    Ok(vec![
-        MCPServer {
-            id: "server1".to_string(),
-            name: "File System Server".to_string(),
-            description: Some("Access to local file system".to_string()),
-            status: "connected".to_string(),
-            version: "1.0.0".to_string(),
-            features: vec!["tools".to_string(), "resources".to_string()],
-        },
+pub async fn get_mcp_servers_command(
+    state: State<'_, AIState>,
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<Vec<MCPServer>, String> {
+    let servers = load_registry(&state.storage)
+        .map_err(|e| format!("Failed to load MCP registry: {}", e))?;
+
+    Ok(servers.into_iter().map(|server| {
+        let cache = catalog.server(&server.id);
+        let status = match cache.as_ref() {
+            _ if !server.enabled => "disabled",
+            Some(cache) if cache.connected => "connected",
+            _ => "disconnected",
+        };
+        let features = match cache.as_ref() {
+            Some(cache) if !cache.tools.is_empty() => vec!["tools".to_string()],
+            _ => Vec::new(),
+        };
         MCPServer {
-            id: "server2".to_string(),
-            name: "Web Browser Server".to_string(),
-            description: Some("Web browsing capabilities".to_string()),
-            status: "disconnected".to_string(),
-            version: "1.0.0".to_string(),
-            features: vec!["tools".to_string()],
-        },
-    ])
+            id: server.id,
+            name: server.name,
+            description: server.description,
+            status: status.to_string(),
+            version: cache.as_ref()
+                .and_then(|cache| cache.server_version.clone())
+                .unwrap_or_else(|| "unknown".to_string()),
+            features,
+        }
+    }).collect())
 }
 
+/// Connect to a registered server by fetching its tool list into the catalog
 #[tauri::command]
-pub async fn connect_mcp_server_command(server_id: String) -> Result<(), String> {
-    // Mock implementation
-    println!("Connecting to MCP server: {}", server_id);
+pub async fn connect_mcp_server_command(
+    server_id: String,
+    app: AppHandle,
+    state: State<'_, AIState>,
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<(), String> {
+    refresh_server_catalog(&app, &state.storage, &catalog, &server_id).await
+        .map_err(|e| format!("Failed to connect to MCP server {}: {}", server_id, e))?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn disconnect_mcp_server_command(server_id: String) -> Result<(), String> {
-    // Mock implementation
-    println!("Disconnecting from MCP server: {}", server_id);
+pub async fn disconnect_mcp_server_command(
+    server_id: String,
+    app: AppHandle,
+    state: State<'_, AIState>,
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<(), String> {
+    find_server(&state.storage, &server_id)
+        .map_err(|e| format!("Failed to disconnect from MCP server: {}", e))?;
+
+    if catalog.server(&server_id).is_some() {
+        catalog.mark_disconnected(&server_id);
+        let _ = app.emit("mcp_tools_changed", &server_id);
+    }
     Ok(())
 }
 
+/// Probe a registered server with a handshake and tool listing, without touching the catalog,
+/// reporting timings and the failure if there was one
 #[tauri::command]
-pub async fn test_mcp_connection_command(server_id: String) -> Result<bool, String> {
-    // Mock implementation
-    println!("Testing connection to MCP server: {}", server_id);
-    Ok(true)
+pub async fn test_mcp_connection_command(
+    server_id: String,
+    app: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<MCPConnectionDiagnostics, String> {
+    let server = find_server(&state.storage, &server_id)
+        .map_err(|e| format!("Failed to test MCP server: {}", e))?;
+
+    let diagnostics = diagnose_server(&app, &server).await;
+    match &diagnostics.last_error {
+        None => info!(
+            "MCP server {} answered with {} tools in {} ms",
+            server_id, diagnostics.tool_count.unwrap_or_default(), diagnostics.total_ms
+        ),
+        Some(e) => warn!("MCP server {} failed its connection test: {}", server_id, e),
+    }
+    Ok(diagnostics)
 }
 
 #[tauri::command]
-pub async fn get_active_sessions_command() -> Result<Vec<AgentSessionData>, String> {
-    // Mock implementation
-    Ok(vec![])
+pub async fn get_active_sessions_command(
+    app_handle: AppHandle,
+    sessions: State<'_, AgentSessionManager>,
+) -> Result<Vec<AgentSessionData>, String> {
+    let conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    sessions.list(&conn)
+        .map_err(|e| format!("Failed to load agent sessions: {}", e))
 }
 
+/// Open a session for a configured agent, continuing `conversation_id` when given; an agent
+/// that already has a session gets it back
 #[tauri::command]
-pub async fn create_agent_session_command(agent_id: String) -> Result<AgentSessionData, String> {
-    // Mock implementation
-    Ok(AgentSessionData {
-        agent_id: agent_id.clone(),
-        agent: crate::ai::Agent {
-            id: agent_id,
-            name: "Assistant".to_string(),
-            description: "General purpose AI assistant".to_string(),
-            model: "gpt-4".to_string(),
-            tools: vec![],
-            system_prompt: None,
-            temperature: None,
-            max_tokens: None,
-        },
-        conversation_id: Some("conv-123".to_string()),
-        conversation: None,
-    })
+pub async fn create_agent_session_command(
+    agent_id: String,
+    conversation_id: Option<String>,
+    app_handle: AppHandle,
+    sessions: State<'_, AgentSessionManager>,
+) -> Result<AgentSessionData, String> {
+    let conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    let (session, created) = sessions.create(&conn, &agent_id, conversation_id.as_deref())
+        .map_err(|e| format!("Failed to create agent session: {}", e))?;
+
+    if created {
+        let _ = app_handle.emit(AGENT_SESSION_CREATED_EVENT, &session);
+    }
+    Ok(session)
 }
 
 #[tauri::command]
-pub async fn close_agent_session_command(agent_id: String) -> Result<(), String> {
-    // Mock implementation
-    println!("Closing agent session: {}", agent_id);
-    Ok(())
+pub async fn close_agent_session_command(
+    agent_id: String,
+    app_handle: AppHandle,
+    sessions: State<'_, AgentSessionManager>,
+) -> Result<AgentSession, String> {
+    let conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    let session = sessions.close(&conn, &agent_id)
+        .map_err(|e| format!("Failed to close agent session: {}", e))?;
+
+    let _ = app_handle.emit(AGENT_SESSION_CLOSED_EVENT, &session);
+    Ok(session)
 }
 
 #[tauri::command]
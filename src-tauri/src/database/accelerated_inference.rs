@@ -0,0 +1,286 @@
+//! Optional GPU backend for the embedding and graph networks.
+//!
+//! Builds with the `gpu-metal` or `gpu-cuda` feature look for a device the first time a
+//! large batch runs and, if one is found, run the batch through candle on it. Everything
+//! else, including builds without those features and batches while CPU is forced, stays on
+//! the ndarray path in `neural_network`.
+
+use super::neural_network::ActivationFunction;
+use super::simple_memory::SimpleMemoryManager;
+use anyhow::Result;
+use ndarray::{Array1, Array2};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+
+/// Smallest batch worth copying the network and inputs to the device
+pub const MIN_ACCELERATED_ROWS: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComputeBackend {
+    Cpu,
+    Metal,
+    Cuda,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceleratorSettings {
+    /// Keep inference on the CPU even when a GPU is available
+    #[serde(default)]
+    pub force_cpu: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceleratorStatus {
+    /// GPU backends this build was compiled with
+    pub compiled_backends: Vec<ComputeBackend>,
+    /// GPU found at runtime, if any
+    pub detected_backend: Option<ComputeBackend>,
+    /// Where large batches run now
+    pub active_backend: ComputeBackend,
+    pub force_cpu: bool,
+}
+
+static FORCE_CPU: Lazy<AtomicBool> = Lazy::new(|| {
+    let settings = load_settings().unwrap_or_else(|e| {
+        warn!("Failed to load accelerator settings: {}", e);
+        AcceleratorSettings::default()
+    });
+    AtomicBool::new(settings.force_cpu)
+});
+
+fn settings_path() -> Result<PathBuf> {
+    Ok(SimpleMemoryManager::default_model_directory()?.join("accelerator.json"))
+}
+
+fn load_settings() -> Result<AcceleratorSettings> {
+    let path = settings_path()?;
+    if !path.exists() {
+        return Ok(AcceleratorSettings::default());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+fn save_settings(settings: &AcceleratorSettings) -> Result<()> {
+    let path = settings_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(settings)?)?;
+    Ok(())
+}
+
+pub fn force_cpu() -> bool {
+    FORCE_CPU.load(Ordering::Relaxed)
+}
+
+/// Change the setting for this session only; `set_force_cpu_inference` also saves it
+pub fn set_force_cpu(force: bool) {
+    FORCE_CPU.store(force, Ordering::Relaxed);
+}
+
+pub fn compiled_backends() -> Vec<ComputeBackend> {
+    let mut backends = Vec::new();
+    if cfg!(feature = "gpu-metal") {
+        backends.push(ComputeBackend::Metal);
+    }
+    if cfg!(feature = "gpu-cuda") {
+        backends.push(ComputeBackend::Cuda);
+    }
+    backends
+}
+
+pub fn status() -> AcceleratorStatus {
+    let detected_backend = device::detected_backend();
+    let force_cpu = force_cpu();
+    AcceleratorStatus {
+        compiled_backends: compiled_backends(),
+        detected_backend,
+        active_backend: detected_backend.filter(|_| !force_cpu).unwrap_or(ComputeBackend::Cpu),
+        force_cpu,
+    }
+}
+
+/// Run `inputs` (one row each) through `layers` on the GPU. `None` means the caller should
+/// use the CPU: no device, CPU forced, a batch too small to gain anything, or a device error.
+pub fn forward(
+    inputs: &Array2<f32>,
+    layers: &[(&Array2<f32>, &Array1<f32>, ActivationFunction)],
+) -> Option<Array2<f32>> {
+    if inputs.nrows() < MIN_ACCELERATED_ROWS || force_cpu() {
+        return None;
+    }
+    device::forward(inputs, layers)
+}
+
+#[cfg(feature = "accelerated-inference")]
+mod device {
+    use super::*;
+    use candle_core::{Device, Tensor};
+
+    static DEVICE: Lazy<Option<(ComputeBackend, Device)>> = Lazy::new(|| {
+        let detected = detect();
+        match &detected {
+            Some((backend, _)) => info!("Accelerated inference available on {:?}", backend),
+            None => info!("No GPU found for accelerated inference; using the CPU"),
+        }
+        detected
+    });
+
+    fn detect() -> Option<(ComputeBackend, Device)> {
+        #[cfg(feature = "gpu-metal")]
+        if candle_core::utils::metal_is_available() {
+            match Device::new_metal(0) {
+                Ok(device) => return Some((ComputeBackend::Metal, device)),
+                Err(e) => warn!("Metal device unavailable: {}", e),
+            }
+        }
+        #[cfg(feature = "gpu-cuda")]
+        if candle_core::utils::cuda_is_available() {
+            match Device::new_cuda(0) {
+                Ok(device) => return Some((ComputeBackend::Cuda, device)),
+                Err(e) => warn!("CUDA device unavailable: {}", e),
+            }
+        }
+        None
+    }
+
+    pub fn detected_backend() -> Option<ComputeBackend> {
+        DEVICE.as_ref().map(|(backend, _)| *backend)
+    }
+
+    pub fn forward(
+        inputs: &Array2<f32>,
+        layers: &[(&Array2<f32>, &Array1<f32>, ActivationFunction)],
+    ) -> Option<Array2<f32>> {
+        let (backend, device) = DEVICE.as_ref()?;
+        match forward_on(device, inputs, layers) {
+            Ok(outputs) => Some(outputs),
+            Err(e) => {
+                warn!("{:?} inference failed, falling back to the CPU: {}", backend, e);
+                None
+            }
+        }
+    }
+
+    pub(super) fn forward_on(
+        device: &Device,
+        inputs: &Array2<f32>,
+        layers: &[(&Array2<f32>, &Array1<f32>, ActivationFunction)],
+    ) -> candle_core::Result<Array2<f32>> {
+        let mut activations = to_tensor(inputs, device)?;
+        for (weights, biases, activation) in layers {
+            let weights = to_tensor(weights, device)?;
+            let biases = Tensor::from_slice(&biases.to_vec(), biases.len(), device)?;
+            let linear = activations.matmul(&weights.t()?)?.broadcast_add(&biases)?;
+            activations = activate(&linear, *activation)?;
+        }
+
+        let (rows, cols) = activations.dims2()?;
+        let values = activations.flatten_all()?.to_vec1::<f32>()?;
+        Array2::from_shape_vec((rows, cols), values)
+            .map_err(|e| candle_core::Error::Msg(e.to_string()))
+    }
+
+    fn to_tensor(matrix: &Array2<f32>, device: &Device) -> candle_core::Result<Tensor> {
+        let values: Vec<f32> = matrix.iter().copied().collect();
+        Tensor::from_slice(&values, matrix.dim(), device)
+    }
+
+    /// Same formulas as `ActivationFunction::apply`
+    fn activate(x: &Tensor, activation: ActivationFunction) -> candle_core::Result<Tensor> {
+        match activation {
+            ActivationFunction::Linear => Ok(x.clone()),
+            ActivationFunction::Sigmoid => x.neg()?.exp()?.affine(1.0, 1.0)?.recip(),
+            ActivationFunction::Tanh => x.tanh(),
+            ActivationFunction::ReLU => x.relu(),
+            ActivationFunction::LeakyReLU => x.maximum(&x.affine(0.01, 0.0)?),
+            ActivationFunction::GELU => {
+                let cubic = (x.sqr()? * x)?.affine(0.044715, 0.0)?;
+                let scale = (2.0 / std::f64::consts::PI).sqrt();
+                let gate = (x + cubic)?.affine(scale, 1.0)?.tanh()?;
+                x.affine(0.5, 0.0)? * gate
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "accelerated-inference"))]
+mod device {
+    use super::*;
+
+    pub fn detected_backend() -> Option<ComputeBackend> {
+        None
+    }
+
+    pub fn forward(
+        _inputs: &Array2<f32>,
+        _layers: &[(&Array2<f32>, &Array1<f32>, ActivationFunction)],
+    ) -> Option<Array2<f32>> {
+        None
+    }
+}
+
+/// GPU backends compiled in, the one detected, and whether CPU is forced
+#[tauri::command]
+pub async fn get_inference_accelerator() -> Result<AcceleratorStatus, String> {
+    Ok(status())
+}
+
+/// Keep neural inference on the CPU, or let it use a detected GPU again
+#[tauri::command]
+pub async fn set_force_cpu_inference(force_cpu: bool) -> Result<AcceleratorStatus, String> {
+    save_settings(&AcceleratorSettings { force_cpu })
+        .map_err(|e| format!("Failed to save accelerator settings: {}", e))?;
+    set_force_cpu(force_cpu);
+    info!("Neural inference {}", if force_cpu { "forced onto the CPU" } else { "may use the GPU" });
+    Ok(status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_batches_stay_on_cpu() {
+        let weights = Array2::from_elem((2, 3), 0.5);
+        let biases = Array1::zeros(2);
+        let inputs = Array2::from_elem((MIN_ACCELERATED_ROWS - 1, 3), 1.0);
+        assert!(forward(&inputs, &[(&weights, &biases, ActivationFunction::ReLU)]).is_none());
+    }
+
+    #[cfg(feature = "accelerated-inference")]
+    #[test]
+    fn test_tensor_forward_matches_network() {
+        use crate::database::neural_network::NetworkBuilder;
+
+        let activations = [
+            ActivationFunction::Sigmoid,
+            ActivationFunction::Tanh,
+            ActivationFunction::LeakyReLU,
+            ActivationFunction::GELU,
+        ];
+        for activation in activations {
+            let network = NetworkBuilder::new()
+                .input_layer(4)
+                .hidden_layer_with_activation(5, activation, 0.0)
+                .output_layer(3)
+                .build()
+                .unwrap();
+            let inputs = Array2::from_shape_fn((6, 4), |(i, j)| (i as f32 - 2.5) * 0.3 + j as f32 * 0.1);
+            let params = network.layer_params();
+
+            let outputs = device::forward_on(&candle_core::Device::Cpu, &inputs, &params).unwrap();
+            for (row, input) in outputs.rows().into_iter().zip(inputs.rows()) {
+                for (gpu, cpu) in row.iter().zip(network.run(&input.to_vec())) {
+                    assert!((gpu - cpu).abs() < 1e-5, "{:?}: {} != {}", activation, gpu, cpu);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use super::{open_conversation_db, parse_db_timestamp};
+use crate::ai::unified_diff;
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tracing::info;
+
+/// Configuration keys that hold an agent's instructions
+const SYSTEM_PROMPT_KEYS: [&str; 2] = ["systemPrompt", "system_prompt"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentConfigVersion {
+    pub id: String,
+    pub agent_id: String,
+    pub version: i64,
+    /// Raw JSON configuration as stored in `agent_settings`
+    pub configuration: String,
+    pub system_prompt: Option<String>,
+    pub author: String,
+    pub note: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentConfigHistoryEntry {
+    #[serde(flatten)]
+    pub version: AgentConfigVersion,
+    /// Unified diff against the previous version; None for the first version
+    pub diff: Option<String>,
+}
+
+fn system_prompt(configuration: &serde_json::Value) -> Option<String> {
+    SYSTEM_PROMPT_KEYS.iter()
+        .find_map(|key| configuration.get(key).and_then(|v| v.as_str()))
+        .map(|s| s.to_string())
+}
+
+/// Text form used for diffs: the system prompt verbatim, then the remaining config as pretty JSON
+fn diffable_text(configuration: &str) -> String {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(configuration) else {
+        return configuration.to_string();
+    };
+    let prompt = system_prompt(&value);
+    if let Some(object) = value.as_object_mut() {
+        for key in SYSTEM_PROMPT_KEYS {
+            object.remove(key);
+        }
+    }
+
+    let mut text = String::from("# system prompt\n");
+    if let Some(prompt) = prompt {
+        text.push_str(&prompt);
+        text.push('\n');
+    }
+    text.push_str("\n# configuration\n");
+    text.push_str(&serde_json::to_string_pretty(&value).unwrap_or_default());
+    text.push('\n');
+    text
+}
+
+fn diff_configurations(agent_id: &str, from: &str, to: &str) -> String {
+    unified_diff(&format!("{}/config", agent_id), &diffable_text(from), &diffable_text(to), 3)
+}
+
+fn row_to_version(row: &rusqlite::Row) -> rusqlite::Result<AgentConfigVersion> {
+    let configuration: String = row.get(3)?;
+    let created_at: String = row.get(6)?;
+    Ok(AgentConfigVersion {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        version: row.get(2)?,
+        system_prompt: serde_json::from_str(&configuration).ok().as_ref().and_then(system_prompt),
+        configuration,
+        author: row.get(4)?,
+        note: row.get(5)?,
+        created_at: parse_db_timestamp(&created_at),
+    })
+}
+
+const VERSION_COLUMNS: &str = "id, agent_id, version, configuration, author, note, created_at";
+
+pub fn load_versions(conn: &Connection, agent_id: &str) -> Result<Vec<AgentConfigVersion>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM agent_config_versions WHERE agent_id = ?1 ORDER BY version", VERSION_COLUMNS
+    ))?;
+    let versions = stmt.query_map(params![agent_id], row_to_version)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(versions)
+}
+
+/// Record a new version with its author and make it the agent's current settings
+pub fn save_config_version(
+    conn: &mut Connection,
+    agent_id: &str,
+    configuration: &str,
+    author: &str,
+    note: Option<&str>,
+) -> Result<AgentConfigVersion> {
+    let tx = conn.transaction()?;
+    let latest = tx.query_row(
+        &format!("SELECT {} FROM agent_config_versions WHERE agent_id = ?1 ORDER BY version DESC LIMIT 1", VERSION_COLUMNS),
+        params![agent_id],
+        row_to_version,
+    ).optional()?;
+
+    if let Some(latest) = latest.filter(|v| v.configuration == configuration) {
+        // Restores the settings of a deleted agent saved again unchanged
+        tx.execute(
+            "INSERT INTO agent_settings (id, agent_id, configuration) VALUES (?1, ?2, ?3)
+             ON CONFLICT(agent_id) DO NOTHING",
+            params![uuid::Uuid::new_v4().to_string(), agent_id, configuration],
+        )?;
+        tx.commit()?;
+        return Ok(latest);
+    }
+
+    tx.execute(
+        "INSERT INTO agent_config_versions (id, agent_id, version, configuration, author, note)
+         VALUES (?1, ?2, COALESCE((SELECT MAX(version) FROM agent_config_versions WHERE agent_id = ?2), 0) + 1, ?3, ?4, ?5)",
+        params![uuid::Uuid::new_v4().to_string(), agent_id, configuration, author, note],
+    )?;
+    // The versioning trigger sees this configuration is already the latest version and skips it
+    tx.execute(
+        "INSERT INTO agent_settings (id, agent_id, configuration) VALUES (?1, ?2, ?3)
+         ON CONFLICT(agent_id) DO UPDATE SET configuration = excluded.configuration",
+        params![uuid::Uuid::new_v4().to_string(), agent_id, configuration],
+    )?;
+
+    let saved = tx.query_row(
+        &format!("SELECT {} FROM agent_config_versions WHERE agent_id = ?1 ORDER BY version DESC LIMIT 1", VERSION_COLUMNS),
+        params![agent_id],
+        row_to_version,
+    )?;
+    tx.commit()?;
+    Ok(saved)
+}
+
+/// Versions newest first, each with its diff, optionally filtered by a case-insensitive query
+pub fn config_history(
+    conn: &Connection,
+    agent_id: &str,
+    query: Option<&str>,
+    limit: usize,
+) -> Result<Vec<AgentConfigHistoryEntry>> {
+    let versions = load_versions(conn, agent_id)?;
+    let query = query.map(|q| q.trim().to_lowercase()).filter(|q| !q.is_empty());
+
+    let mut entries: Vec<AgentConfigHistoryEntry> = versions.iter().enumerate()
+        .map(|(i, version)| AgentConfigHistoryEntry {
+            diff: i.checked_sub(1).map(|prev| diff_configurations(agent_id, &versions[prev].configuration, &version.configuration)),
+            version: version.clone(),
+        })
+        .filter(|entry| query.as_ref().map_or(true, |q| {
+            entry.version.configuration.to_lowercase().contains(q)
+                || entry.version.author.to_lowercase().contains(q)
+                || entry.version.note.as_deref().is_some_and(|n| n.to_lowercase().contains(q))
+                || entry.diff.as_deref().is_some_and(|d| d.to_lowercase().contains(q))
+        }))
+        .collect();
+
+    entries.reverse();
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+pub fn diff_versions(conn: &Connection, agent_id: &str, from_version: i64, to_version: i64) -> Result<String> {
+    let versions = load_versions(conn, agent_id)?;
+    let find = |n: i64| versions.iter()
+        .find(|v| v.version == n)
+        .ok_or_else(|| anyhow!("Agent {} has no config version {}", agent_id, n));
+    Ok(diff_configurations(agent_id, &find(from_version)?.configuration, &find(to_version)?.configuration))
+}
+
+#[tauri::command]
+pub async fn save_agent_config(
+    agent_id: String,
+    configuration: serde_json::Value,
+    author: Option<String>,
+    note: Option<String>,
+    app_handle: AppHandle,
+) -> Result<AgentConfigVersion, String> {
+    if agent_id.trim().is_empty() {
+        return Err("Agent ID cannot be empty".to_string());
+    }
+
+    let configuration = serde_json::to_string(&configuration)
+        .map_err(|e| format!("Invalid configuration: {}", e))?;
+    let author = author.filter(|a| !a.trim().is_empty()).unwrap_or_else(|| "user".to_string());
+
+    let mut conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    let version = save_config_version(&mut conn, &agent_id, &configuration, &author, note.as_deref())
+        .map_err(|e| format!("Failed to save agent config: {}", e))?;
+
+    info!("Agent {} config is at version {} ({})", agent_id, version.version, version.author);
+    Ok(version)
+}
+
+#[tauri::command]
+pub async fn get_agent_config_history(
+    agent_id: String,
+    query: Option<String>,
+    limit: Option<usize>,
+    app_handle: AppHandle,
+) -> Result<Vec<AgentConfigHistoryEntry>, String> {
+    let conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    config_history(&conn, &agent_id, query.as_deref(), limit.unwrap_or(50).clamp(1, 500))
+        .map_err(|e| format!("Failed to load config history: {}", e))
+}
+
+#[tauri::command]
+pub async fn diff_agent_config_versions(
+    agent_id: String,
+    from_version: i64,
+    to_version: i64,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    let conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    diff_versions(&conn, &agent_id, from_version, to_version)
+        .map_err(|e| format!("Failed to diff config versions: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(super::super::INIT_SQL).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_frontend_writes_are_versioned_once() {
+        let mut conn = setup();
+        // Same statement the frontend SQL plugin uses
+        let write = |conn: &Connection, config: &str| conn.execute(
+            "INSERT OR REPLACE INTO agent_settings (id, agent_id, configuration) VALUES ('s1', 'agent-1', ?1)",
+            params![config],
+        ).unwrap();
+
+        write(&conn, r#"{"systemPrompt":"Be terse."}"#);
+        write(&conn, r#"{"systemPrompt":"Be terse."}"#);
+        let saved = save_config_version(&mut conn, "agent-1", r#"{"systemPrompt":"Be thorough."}"#, "alice", Some("longer answers")).unwrap();
+        save_config_version(&mut conn, "agent-1", r#"{"systemPrompt":"Be thorough."}"#, "alice", None).unwrap();
+
+        let versions = load_versions(&conn, "agent-1").unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].author, "app");
+        assert_eq!(saved.version, 2);
+        assert_eq!(saved.system_prompt.as_deref(), Some("Be thorough."));
+
+        let current: String = conn.query_row(
+            "SELECT configuration FROM agent_settings WHERE agent_id = 'agent-1'", [], |r| r.get(0),
+        ).unwrap();
+        assert_eq!(current, r#"{"systemPrompt":"Be thorough."}"#);
+    }
+
+    #[test]
+    fn test_history_diffs_and_search() {
+        let mut conn = setup();
+        save_config_version(&mut conn, "a", r#"{"systemPrompt":"Line one\nLine two","temperature":0.2}"#, "bob", None).unwrap();
+        save_config_version(&mut conn, "a", r#"{"systemPrompt":"Line one\nLine 2","temperature":0.7}"#, "carol", None).unwrap();
+
+        let history = config_history(&conn, "a", None, 10).unwrap();
+        assert_eq!(history[0].version.version, 2);
+        let diff = history[0].diff.as_deref().unwrap();
+        assert!(diff.contains("-Line two\n+Line 2"));
+        assert!(diff.contains("+  \"temperature\": 0.7"));
+        assert!(history[1].diff.is_none());
+
+        assert_eq!(config_history(&conn, "a", Some("LINE 2"), 10).unwrap().len(), 1);
+        assert_eq!(config_history(&conn, "a", Some("bob"), 10).unwrap()[0].version.version, 1);
+        assert!(diff_versions(&conn, "a", 2, 1).unwrap().contains("+Line two"));
+    }
+}
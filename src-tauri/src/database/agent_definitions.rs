@@ -0,0 +1,339 @@
+use super::agent_config_history::{save_config_version, AgentConfigVersion};
+use super::data_purge::{purge_shared_graph, remove_agent_databases};
+use super::simple_commands::MemoryState;
+use super::simple_memory::SimpleMemoryManager;
+use super::{open_db, parse_db_timestamp};
+use crate::ai::{enforce_path_policy, AIState, PathAccess, StorageManager};
+use crate::validation::{validate_json_schema, MemoryValidator};
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+use tracing::{info, warn};
+
+pub const AGENT_EXPORT_VERSION: u32 = 1;
+
+/// JSON Schema for the configuration blob stored in `agent_settings`. Unknown keys are allowed
+/// so the frontend can keep UI state alongside the agent definition.
+pub fn agent_config_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Agent configuration",
+        "type": "object",
+        "required": ["name", "model"],
+        "properties": {
+            "name": { "type": "string", "minLength": 1, "maxLength": 100 },
+            "description": { "type": "string", "maxLength": 2000 },
+            "provider": { "type": "string" },
+            "model": { "type": "string", "minLength": 1 },
+            "systemPrompt": { "type": ["string", "null"], "maxLength": 100000 },
+            "tools": { "type": "array", "items": { "type": "string", "minLength": 1 } },
+            "temperature": { "type": ["number", "null"], "minimum": 0, "maximum": 2 },
+            "maxTokens": { "type": ["integer", "null"], "minimum": 1, "maximum": 1000000 }
+        }
+    })
+}
+
+pub fn validate_agent_config(configuration: &Value) -> Result<()> {
+    let errors: Vec<String> = validate_json_schema(configuration, &agent_config_schema()).iter()
+        .map(ToString::to_string)
+        .collect();
+    if !errors.is_empty() {
+        return Err(anyhow!("Invalid agent configuration: {}", errors.join("; ")));
+    }
+    Ok(())
+}
+
+fn validate_agent_id(agent_id: &str) -> Result<()> {
+    MemoryValidator::validate_agent_id(agent_id).map_err(|e| anyhow!(e.to_string()))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentDefinition {
+    pub id: String,
+    pub configuration: Value,
+    #[serde(default)]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentDefinitionsExport {
+    pub format_version: u32,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub agents: Vec<AgentDefinition>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentImportReport {
+    pub imported: Vec<String>,
+    pub skipped: Vec<String>,
+    /// Definitions that failed validation, by agent id, with the reason
+    pub invalid: BTreeMap<String, String>,
+}
+
+pub fn list_definitions(conn: &Connection) -> Result<Vec<AgentDefinition>> {
+    let mut stmt = conn.prepare("SELECT agent_id, configuration, updated_at FROM agent_settings ORDER BY agent_id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+    })?;
+
+    let mut agents = Vec::new();
+    for row in rows {
+        let (id, configuration, updated_at) = row?;
+        match serde_json::from_str(&configuration) {
+            Ok(configuration) => agents.push(AgentDefinition {
+                id,
+                configuration,
+                updated_at: updated_at.as_deref().map(parse_db_timestamp),
+            }),
+            Err(e) => warn!("Skipping agent {} with malformed configuration: {}", id, e),
+        }
+    }
+    Ok(agents)
+}
+
+fn agent_exists(conn: &Connection, agent_id: &str) -> Result<bool> {
+    Ok(conn.query_row("SELECT 1 FROM agent_settings WHERE agent_id = ?1", params![agent_id], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
+
+/// Validate and store an agent's configuration as a new version
+pub fn save_definition(
+    conn: &mut Connection,
+    agent_id: &str,
+    configuration: &Value,
+    author: &str,
+    note: Option<&str>,
+) -> Result<AgentConfigVersion> {
+    validate_agent_id(agent_id)?;
+    validate_agent_config(configuration)?;
+    save_config_version(conn, agent_id, &serde_json::to_string(configuration)?, author, note)
+}
+
+/// Remove an agent's current settings; its version history is kept so it can be restored
+pub fn delete_definition(conn: &Connection, agent_id: &str) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM agent_settings WHERE agent_id = ?1", params![agent_id])? > 0)
+}
+
+pub fn apply_agent_import(
+    conn: &mut Connection,
+    export: AgentDefinitionsExport,
+    overwrite: bool,
+) -> Result<AgentImportReport> {
+    if export.format_version > AGENT_EXPORT_VERSION {
+        return Err(anyhow!("Unsupported agent export format version {}", export.format_version));
+    }
+
+    let mut report = AgentImportReport::default();
+    for agent in export.agents {
+        if !overwrite && agent_exists(conn, &agent.id)? {
+            report.skipped.push(agent.id);
+            continue;
+        }
+        match save_definition(conn, &agent.id, &agent.configuration, "import", Some("Imported")) {
+            Ok(_) => report.imported.push(agent.id),
+            Err(e) => {
+                report.invalid.insert(agent.id, e.to_string());
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// The export file at `path`, resolved and checked against the path policy like any other file
+fn validate_export_path(storage: &StorageManager, path: &str, access: PathAccess) -> Result<PathBuf, String> {
+    if !path.to_lowercase().ends_with(".json") {
+        return Err("Agent export files must have a .json extension".to_string());
+    }
+    enforce_path_policy(storage, None, path, access)
+}
+
+fn author_or(author: Option<String>, default: &str) -> String {
+    author.filter(|a| !a.trim().is_empty()).unwrap_or_else(|| default.to_string())
+}
+
+#[tauri::command]
+pub async fn get_agent_config_schema() -> Result<Value, String> {
+    Ok(agent_config_schema())
+}
+
+#[tauri::command]
+pub async fn list_agents(app_handle: AppHandle) -> Result<Vec<AgentDefinition>, String> {
+    list_definitions(&open_db(&app_handle)?)
+        .map_err(|e| format!("Failed to list agents: {}", e))
+}
+
+#[tauri::command]
+pub async fn create_agent(
+    agent_id: String,
+    configuration: Value,
+    author: Option<String>,
+    app_handle: AppHandle,
+) -> Result<AgentConfigVersion, String> {
+    let mut conn = open_db(&app_handle)?;
+    if agent_exists(&conn, &agent_id).map_err(|e| e.to_string())? {
+        return Err(format!("Agent {} already exists", agent_id));
+    }
+    let version = save_definition(&mut conn, &agent_id, &configuration, &author_or(author, "user"), Some("Created"))
+        .map_err(|e| format!("Failed to create agent: {}", e))?;
+
+    info!("Created agent {}", agent_id);
+    Ok(version)
+}
+
+#[tauri::command]
+pub async fn update_agent(
+    agent_id: String,
+    configuration: Value,
+    author: Option<String>,
+    note: Option<String>,
+    app_handle: AppHandle,
+) -> Result<AgentConfigVersion, String> {
+    let mut conn = open_db(&app_handle)?;
+    if !agent_exists(&conn, &agent_id).map_err(|e| e.to_string())? {
+        return Err(format!("Unknown agent: {}", agent_id));
+    }
+    let version = save_definition(&mut conn, &agent_id, &configuration, &author_or(author, "user"), note.as_deref())
+        .map_err(|e| format!("Failed to update agent: {}", e))?;
+
+    info!("Agent {} is at config version {}", agent_id, version.version);
+    Ok(version)
+}
+
+/// Delete an agent with its memory databases in every workspace and its knowledge graph entries
+#[tauri::command]
+pub async fn delete_agent(
+    agent_id: String,
+    app_handle: AppHandle,
+    state: State<'_, MemoryState>,
+) -> Result<(), String> {
+    validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+    if !agent_exists(&open_db(&app_handle)?, &agent_id).map_err(|e| format!("Failed to delete agent: {}", e))? {
+        return Err(format!("Unknown agent: {}", agent_id));
+    }
+
+    // Memories go first, so a failure leaves the agent in place to retry. A cached manager
+    // would recreate the database on its next use.
+    state.forget_manager(&agent_id);
+    let doomed = agent_id.clone();
+    let removed = tokio::task::spawn_blocking(move || -> Result<usize> {
+        let removed = remove_agent_databases(&doomed)?;
+        let mut shared = SimpleMemoryManager::new(doomed.clone())?.shared_connection()?;
+        purge_shared_graph(&mut shared, &doomed, &[], true)?;
+        Ok(removed.len())
+    }).await
+        .map_err(|e| format!("Failed to delete agent memories: {}", e))?
+        .map_err(|e| format!("Failed to delete agent memories: {}", e))?;
+    if let Some(graph) = state.neural_graph_handle().lock().await.as_mut() {
+        graph.remove_agent_nodes(&agent_id, None).await;
+    }
+
+    delete_definition(&open_db(&app_handle)?, &agent_id)
+        .map_err(|e| format!("Failed to delete agent: {}", e))?;
+
+    info!("Deleted agent {} and {} memory database(s)", agent_id, removed);
+    Ok(())
+}
+
+/// Write every agent definition to a JSON file
+#[tauri::command]
+pub async fn export_agents(
+    path: String,
+    app_handle: AppHandle,
+    ai_state: State<'_, AIState>,
+) -> Result<AgentDefinitionsExport, String> {
+    let export = AgentDefinitionsExport {
+        format_version: AGENT_EXPORT_VERSION,
+        exported_at: chrono::Utc::now(),
+        agents: list_definitions(&open_db(&app_handle)?)
+            .map_err(|e| format!("Failed to list agents: {}", e))?,
+    };
+    let json = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize agents: {}", e))?;
+    let target = validate_export_path(&ai_state.storage, &path, PathAccess::Write { size: json.len() as u64 })?;
+    tokio::fs::write(&target, json).await
+        .map_err(|e| format!("Failed to write agent export: {}", e))?;
+
+    info!("Exported {} agents to {}", export.agents.len(), path);
+    Ok(export)
+}
+
+/// Import agent definitions from a file written by `export_agents`; existing agents are kept
+/// unless `overwrite` is set
+#[tauri::command]
+pub async fn import_agents(
+    path: String,
+    overwrite: Option<bool>,
+    app_handle: AppHandle,
+    ai_state: State<'_, AIState>,
+) -> Result<AgentImportReport, String> {
+    let source = validate_export_path(&ai_state.storage, &path, PathAccess::Read)?;
+
+    let contents = tokio::fs::read_to_string(&source).await
+        .map_err(|e| format!("Failed to read agent export: {}", e))?;
+    let export: AgentDefinitionsExport = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid agent export: {}", e))?;
+
+    let report = apply_agent_import(&mut open_db(&app_handle)?, export, overwrite.unwrap_or(false))
+        .map_err(|e| format!("Failed to import agents: {}", e))?;
+
+    info!(
+        "Imported {} agents ({} skipped, {} invalid)",
+        report.imported.len(), report.skipped.len(), report.invalid.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    #[test]
+    fn test_schema_validation_reports_every_violation() {
+        assert!(validate_agent_config(&json!({
+            "name": "Coder", "model": "gpt-4o", "tools": ["read_file"], "temperature": 0.2, "maxTokens": 4096,
+            "systemPrompt": null, "uiColor": "blue"
+        })).is_ok());
+
+        let errors: Vec<String> = validate_json_schema(&json!({
+            "name": "", "tools": ["ok", 3], "temperature": 3.5, "maxTokens": 1.5
+        }), &agent_config_schema()).iter().map(ToString::to_string).collect();
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert!(errors.contains(&"/model: is required".to_string()));
+        assert!(errors.contains(&"/tools/1: expected string, got integer".to_string()));
+        assert!(errors.iter().any(|e| e.starts_with("/temperature: must be at most")));
+
+        assert!(validate_agent_config(&json!("not an object")).is_err());
+    }
+
+    #[test]
+    fn test_crud_and_import_roundtrip() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+
+        save_definition(&mut conn, "coder", &json!({ "name": "Coder", "model": "gpt-4o" }), "user", None).unwrap();
+        assert!(save_definition(&mut conn, "x", &json!({ "name": "X", "model": "gpt-4o" }), "user", None).is_err());
+        let exported = list_definitions(&conn).unwrap();
+        assert_eq!(exported.len(), 1);
+
+        assert!(delete_definition(&conn, "coder").unwrap());
+        assert!(list_definitions(&conn).unwrap().is_empty());
+
+        let mut agents = exported.clone();
+        agents.push(AgentDefinition { id: "broken".to_string(), configuration: json!({ "name": "Broken" }), updated_at: None });
+        let export = AgentDefinitionsExport { format_version: AGENT_EXPORT_VERSION, exported_at: chrono::Utc::now(), agents };
+
+        let report = apply_agent_import(&mut conn, export.clone(), false).unwrap();
+        assert_eq!(report.imported, vec!["coder"]);
+        assert!(report.invalid.contains_key("broken"));
+        assert_eq!(list_definitions(&conn).unwrap()[0].configuration, exported[0].configuration);
+
+        let report = apply_agent_import(&mut conn, export, false).unwrap();
+        assert_eq!(report.skipped, vec!["coder"]);
+    }
+}
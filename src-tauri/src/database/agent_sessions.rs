@@ -0,0 +1,313 @@
+use super::parse_db_timestamp;
+use crate::ai::{Agent, Conversation};
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::info;
+
+/// Emitted with the `AgentSessionData` of a new session
+pub const AGENT_SESSION_CREATED_EVENT: &str = "agent_session_created";
+
+/// Emitted with the closed `AgentSession`
+pub const AGENT_SESSION_CLOSED_EVENT: &str = "agent_session_closed";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSession {
+    pub id: String,
+    pub agent_id: String,
+    pub conversation_id: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// An active session with its agent and conversation resolved
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionData {
+    pub session_id: String,
+    pub agent_id: String,
+    pub agent: Agent,
+    pub conversation_id: Option<String>,
+    pub conversation: Option<Conversation>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Agent configuration as the frontend stores it in `agent_settings`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredAgentConfig {
+    name: Option<String>,
+    #[serde(default)]
+    description: String,
+    model: Option<String>,
+    /// Tool names, or tool objects with a `name`
+    #[serde(default)]
+    tools: Vec<serde_json::Value>,
+    #[serde(alias = "system_prompt")]
+    system_prompt: Option<String>,
+    temperature: Option<f32>,
+    #[serde(alias = "max_tokens")]
+    max_tokens: Option<u32>,
+}
+
+fn agent_from_config(agent_id: &str, configuration: &str) -> Result<Agent> {
+    let config: StoredAgentConfig = serde_json::from_str(configuration)
+        .with_context(|| format!("Agent {} has a malformed configuration", agent_id))?;
+    let model = config.model
+        .filter(|m| !m.trim().is_empty())
+        .ok_or_else(|| anyhow!("Agent {} has no model configured", agent_id))?;
+
+    Ok(Agent {
+        id: agent_id.to_string(),
+        name: config.name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| agent_id.to_string()),
+        description: config.description,
+        model,
+        tools: config.tools.iter()
+            .filter_map(|tool| tool.as_str().or_else(|| tool.get("name").and_then(|n| n.as_str())))
+            .map(|name| name.to_string())
+            .collect(),
+        system_prompt: config.system_prompt,
+        temperature: config.temperature,
+        max_tokens: config.max_tokens,
+    })
+}
+
+pub fn load_agent(conn: &Connection, agent_id: &str) -> Result<Agent> {
+    let configuration: String = conn.query_row(
+        "SELECT configuration FROM agent_settings WHERE agent_id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).optional()?
+        .ok_or_else(|| anyhow!("Unknown agent: {}", agent_id))?;
+    agent_from_config(agent_id, &configuration)
+}
+
+pub fn load_conversation(conn: &Connection, conversation_id: &str) -> Result<Option<Conversation>> {
+    let conversation = conn.query_row(
+        "SELECT c.id, c.agent_id, c.title, c.created_at, c.updated_at,
+                (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)
+         FROM conversations c WHERE c.id = ?1",
+        params![conversation_id],
+        |row| {
+            let created_at: String = row.get(3)?;
+            let updated_at: String = row.get(4)?;
+            Ok(Conversation {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                title: row.get(2)?,
+                created_at: parse_db_timestamp(&created_at),
+                updated_at: parse_db_timestamp(&updated_at),
+                message_count: row.get(5)?,
+            })
+        },
+    ).optional()?;
+    Ok(conversation)
+}
+
+fn load_active(conn: &Connection) -> Result<HashMap<String, AgentSession>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_id, conversation_id, created_at FROM agent_sessions WHERE status = 'active'"
+    )?;
+    let sessions = stmt.query_map([], |row| {
+        let created_at: String = row.get(3)?;
+        Ok(AgentSession {
+            id: row.get(0)?,
+            agent_id: row.get(1)?,
+            conversation_id: row.get(2)?,
+            created_at: parse_db_timestamp(&created_at),
+        })
+    })?
+        .map(|session| session.map(|s| (s.agent_id.clone(), s)))
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+    Ok(sessions)
+}
+
+/// Tracks the active session of each agent. Sessions live in `agent_sessions`, so they survive a
+/// crash; a normal exit closes them. The cache is loaded on first use and its lock serializes
+/// session changes.
+#[derive(Default)]
+pub struct AgentSessionManager {
+    active: Mutex<Option<HashMap<String, AgentSession>>>,
+}
+
+impl AgentSessionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_active<T>(
+        &self,
+        conn: &Connection,
+        f: impl FnOnce(&mut HashMap<String, AgentSession>) -> Result<T>,
+    ) -> Result<T> {
+        let mut active = self.active.lock().unwrap();
+        if active.is_none() {
+            *active = Some(load_active(conn)?);
+        }
+        f(active.as_mut().expect("loaded above"))
+    }
+
+    fn resolve(conn: &Connection, session: &AgentSession) -> Result<AgentSessionData> {
+        let conversation = match &session.conversation_id {
+            Some(id) => load_conversation(conn, id)?,
+            None => None,
+        };
+        Ok(AgentSessionData {
+            session_id: session.id.clone(),
+            agent_id: session.agent_id.clone(),
+            agent: load_agent(conn, &session.agent_id)?,
+            conversation_id: conversation.as_ref().map(|c| c.id.clone()),
+            conversation,
+            created_at: session.created_at,
+        })
+    }
+
+    /// Active sessions, oldest first; sessions whose agent was deleted are skipped
+    pub fn list(&self, conn: &Connection) -> Result<Vec<AgentSessionData>> {
+        let mut sessions: Vec<AgentSession> = self.with_active(conn, |active| Ok(active.values().cloned().collect()))?;
+        sessions.sort_by_key(|s| s.created_at);
+        Ok(sessions.iter().filter_map(|s| Self::resolve(conn, s).ok()).collect())
+    }
+
+    /// Open a session for the agent, continuing `conversation_id` or starting a new conversation.
+    /// Returns the agent's existing session, and false, if it already has one.
+    pub fn create(
+        &self,
+        conn: &Connection,
+        agent_id: &str,
+        conversation_id: Option<&str>,
+    ) -> Result<(AgentSessionData, bool)> {
+        self.with_active(conn, |active| {
+            if let Some(existing) = active.get(agent_id) {
+                return Ok((Self::resolve(conn, existing)?, false));
+            }
+            let agent = load_agent(conn, agent_id)?;
+
+            let conversation_id = match conversation_id {
+                Some(id) => {
+                    let conversation = load_conversation(conn, id)?
+                        .ok_or_else(|| anyhow!("Unknown conversation: {}", id))?;
+                    if conversation.agent_id != agent_id {
+                        return Err(anyhow!("Conversation {} belongs to agent {}", id, conversation.agent_id));
+                    }
+                    conversation.id
+                }
+                None => {
+                    let id = uuid::Uuid::new_v4().to_string();
+                    conn.execute(
+                        "INSERT INTO conversations (id, agent_id, title) VALUES (?1, ?2, ?3)",
+                        params![id, agent_id, format!("Session with {}", agent.name)],
+                    )?;
+                    id
+                }
+            };
+
+            let session = AgentSession {
+                id: uuid::Uuid::new_v4().to_string(),
+                agent_id: agent_id.to_string(),
+                conversation_id: Some(conversation_id),
+                created_at: chrono::Utc::now(),
+            };
+            conn.execute(
+                "INSERT INTO agent_sessions (id, agent_id, conversation_id, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![session.id, session.agent_id, session.conversation_id, session.created_at.to_rfc3339()],
+            )?;
+            active.insert(agent_id.to_string(), session.clone());
+
+            info!("Opened session {} for agent {}", session.id, agent_id);
+            Ok((Self::resolve(conn, &session)?, true))
+        })
+    }
+
+    /// Close the agent's active session; its conversation is kept
+    pub fn close(&self, conn: &Connection, agent_id: &str) -> Result<AgentSession> {
+        self.with_active(conn, |active| {
+            let session = active.get(agent_id)
+                .cloned()
+                .ok_or_else(|| anyhow!("Agent {} has no active session", agent_id))?;
+            conn.execute(
+                "UPDATE agent_sessions SET status = 'closed', closed_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                params![session.id],
+            )?;
+            active.remove(agent_id);
+
+            info!("Closed session {} for agent {}", session.id, agent_id);
+            Ok(session)
+        })
+    }
+
+    /// Close every active session, as on app exit; returns how many were closed
+    pub fn close_all(&self, conn: &Connection) -> Result<usize> {
+        self.with_active(conn, |active| {
+            let closed = conn.execute(
+                "UPDATE agent_sessions SET status = 'closed', closed_at = CURRENT_TIMESTAMP WHERE status = 'active'",
+                [],
+            )?;
+            active.clear();
+            Ok(closed)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        conn.execute(
+            "INSERT INTO agent_settings (id, agent_id, configuration) VALUES ('s1', 'coder', ?1)",
+            params![r#"{"name":"Coder","model":"claude-3-5-sonnet","tools":["read_file",{"name":"web_search"}],"systemPrompt":"Write code"}"#],
+        ).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_session_lifecycle_survives_reload() {
+        let conn = setup();
+        let manager = AgentSessionManager::new();
+
+        let (session, created) = manager.create(&conn, "coder", None).unwrap();
+        assert!(created);
+        assert_eq!(session.agent.name, "Coder");
+        assert_eq!(session.agent.tools, vec!["read_file", "web_search"]);
+        assert_eq!(session.conversation.as_ref().unwrap().agent_id, "coder");
+
+        let (again, created) = manager.create(&conn, "coder", None).unwrap();
+        assert!(!created);
+        assert_eq!(again.session_id, session.session_id);
+
+        // A fresh manager picks up the persisted session
+        let reloaded = AgentSessionManager::new();
+        assert_eq!(reloaded.list(&conn).unwrap().len(), 1);
+        reloaded.close(&conn, "coder").unwrap();
+        assert!(reloaded.list(&conn).unwrap().is_empty());
+        assert!(reloaded.close(&conn, "coder").is_err());
+
+        // A new session can continue the earlier conversation
+        let (resumed, _) = reloaded.create(&conn, "coder", session.conversation_id.as_deref()).unwrap();
+        assert_eq!(resumed.conversation_id, session.conversation_id);
+        assert_ne!(resumed.session_id, session.session_id);
+
+        assert_eq!(reloaded.close_all(&conn).unwrap(), 1);
+        assert!(AgentSessionManager::new().list(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_create_requires_configured_agent() {
+        let conn = setup();
+        let manager = AgentSessionManager::new();
+        assert!(manager.create(&conn, "missing", None).is_err());
+
+        conn.execute(
+            "INSERT INTO agent_settings (id, agent_id, configuration) VALUES ('s2', 'blank', '{\"name\":\"Blank\"}')",
+            [],
+        ).unwrap();
+        assert!(manager.create(&conn, "blank", None).unwrap_err().to_string().contains("no model"));
+        assert!(manager.list(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,394 @@
+use super::agent_config_history::AgentConfigVersion;
+use super::agent_definitions::{save_definition, validate_agent_config};
+use super::open_db;
+use crate::ai::{load_agent_permissions, save_agent_permissions, AIState, AgentPermissions};
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, State};
+use tracing::info;
+
+/// Capability grants a template gives every agent created from it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PermissionPreset {
+    pub allowed_file_roots: Vec<String>,
+    pub allowed_commands: Vec<String>,
+    pub allowed_network_hosts: Vec<String>,
+    pub allowed_mcp_servers: Vec<String>,
+}
+
+impl PermissionPreset {
+    pub fn for_agent(&self, agent_id: &str) -> AgentPermissions {
+        AgentPermissions {
+            agent_id: agent_id.to_string(),
+            allowed_file_roots: self.allowed_file_roots.clone(),
+            allowed_commands: self.allowed_commands.clone(),
+            allowed_network_hosts: self.allowed_network_hosts.clone(),
+            allowed_mcp_servers: self.allowed_mcp_servers.clone(),
+        }
+    }
+}
+
+impl From<AgentPermissions> for PermissionPreset {
+    fn from(permissions: AgentPermissions) -> Self {
+        Self {
+            allowed_file_roots: permissions.allowed_file_roots,
+            allowed_commands: permissions.allowed_commands,
+            allowed_network_hosts: permissions.allowed_network_hosts,
+            allowed_mcp_servers: permissions.allowed_mcp_servers,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Agent configuration, checked against the agent config schema
+    pub configuration: Value,
+    pub permissions: Option<PermissionPreset>,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+pub fn builtin_templates() -> Vec<AgentTemplate> {
+    vec![
+        AgentTemplate {
+            id: "coder".to_string(),
+            name: "Coder".to_string(),
+            description: "Reads, writes and searches workspace code and runs build tools".to_string(),
+            configuration: json!({
+                "name": "Coder",
+                "description": "Writes and reviews code in the workspace",
+                "provider": "anthropic",
+                "model": "claude-3-5-sonnet-20241022",
+                "systemPrompt": "You are a careful software engineer. Read the surrounding code before changing it, keep changes minimal, and explain what you changed.",
+                "tools": ["read_file", "write_file", "list_files", "search_workspace"],
+                "temperature": 0.2,
+                "maxTokens": 8192
+            }),
+            permissions: Some(PermissionPreset {
+                allowed_file_roots: strings(&["*"]),
+                allowed_commands: strings(&["git", "cargo", "npm", "node", "python3"]),
+                allowed_network_hosts: Vec::new(),
+                allowed_mcp_servers: strings(&["*"]),
+            }),
+            builtin: true,
+        },
+        AgentTemplate {
+            id: "researcher".to_string(),
+            name: "Researcher".to_string(),
+            description: "Gathers sources from the web and MCP servers and writes up findings".to_string(),
+            configuration: json!({
+                "name": "Researcher",
+                "description": "Finds and cites sources for a question",
+                "provider": "openai",
+                "model": "gpt-4o",
+                "systemPrompt": "You are a meticulous researcher. Gather evidence from several sources, cite every claim, and say when sources disagree.",
+                "tools": ["read_file", "search_workspace"],
+                "temperature": 0.4,
+                "maxTokens": 4096
+            }),
+            permissions: Some(PermissionPreset {
+                allowed_file_roots: strings(&["research"]),
+                allowed_commands: Vec::new(),
+                allowed_network_hosts: strings(&["*"]),
+                allowed_mcp_servers: strings(&["*"]),
+            }),
+            builtin: true,
+        },
+        AgentTemplate {
+            id: "summarizer".to_string(),
+            name: "Summarizer".to_string(),
+            description: "Condenses documents and conversations without side effects".to_string(),
+            configuration: json!({
+                "name": "Summarizer",
+                "description": "Summarizes documents and conversations",
+                "provider": "anthropic",
+                "model": "claude-3-5-haiku-20241022",
+                "systemPrompt": "Summarize faithfully and concisely. Keep names, numbers and decisions; drop repetition.",
+                "tools": ["read_file"],
+                "temperature": 0.1,
+                "maxTokens": 2048
+            }),
+            permissions: Some(PermissionPreset {
+                allowed_file_roots: strings(&["*"]),
+                allowed_commands: Vec::new(),
+                allowed_network_hosts: Vec::new(),
+                allowed_mcp_servers: Vec::new(),
+            }),
+            builtin: true,
+        },
+    ]
+}
+
+/// Apply a JSON merge patch (RFC 7386): objects merge recursively, `null` removes a key and
+/// anything else replaces the target
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = json!({});
+    }
+    let target = target.as_object_mut().expect("made an object above");
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+fn row_to_template(row: &rusqlite::Row) -> rusqlite::Result<(String, String, String, String, Option<String>)> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+}
+
+fn parse_template(
+    (id, name, description, configuration, permissions): (String, String, String, String, Option<String>),
+) -> Result<AgentTemplate> {
+    Ok(AgentTemplate {
+        configuration: serde_json::from_str(&configuration)?,
+        permissions: permissions.map(|p| serde_json::from_str(&p)).transpose()?,
+        id,
+        name,
+        description,
+        builtin: false,
+    })
+}
+
+/// Built-in templates followed by user templates
+pub fn list_templates(conn: &Connection) -> Result<Vec<AgentTemplate>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, configuration, permissions FROM agent_templates ORDER BY name"
+    )?;
+    let user = stmt.query_map([], row_to_template)?
+        .map(|row| parse_template(row?))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(builtin_templates().into_iter().chain(user).collect())
+}
+
+pub fn find_template(conn: &Connection, template_id: &str) -> Result<AgentTemplate> {
+    if let Some(template) = builtin_templates().into_iter().find(|t| t.id == template_id) {
+        return Ok(template);
+    }
+    let row = conn.query_row(
+        "SELECT id, name, description, configuration, permissions FROM agent_templates WHERE id = ?1",
+        params![template_id],
+        row_to_template,
+    ).optional()?
+        .ok_or_else(|| anyhow!("Unknown agent template: {}", template_id))?;
+    parse_template(row)
+}
+
+pub fn save_template(conn: &Connection, template: &AgentTemplate) -> Result<()> {
+    if template.id.trim().is_empty() || template.name.trim().is_empty() {
+        return Err(anyhow!("Templates need an id and a name"));
+    }
+    if builtin_templates().iter().any(|t| t.id == template.id) {
+        return Err(anyhow!("'{}' is a built-in template", template.id));
+    }
+    validate_agent_config(&template.configuration)?;
+    if let Some(preset) = &template.permissions {
+        // Checked as if applied, so a bad preset fails here rather than on first use
+        preset.for_agent(&template.id).validate().map_err(anyhow::Error::msg)?;
+    }
+
+    conn.execute(
+        "INSERT INTO agent_templates (id, name, description, configuration, permissions) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, description = excluded.description,
+             configuration = excluded.configuration, permissions = excluded.permissions,
+             updated_at = CURRENT_TIMESTAMP",
+        params![
+            template.id,
+            template.name,
+            template.description,
+            serde_json::to_string(&template.configuration)?,
+            template.permissions.as_ref().map(serde_json::to_string).transpose()?,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn delete_template(conn: &Connection, template_id: &str) -> Result<bool> {
+    if builtin_templates().iter().any(|t| t.id == template_id) {
+        return Err(anyhow!("Built-in templates cannot be deleted"));
+    }
+    Ok(conn.execute("DELETE FROM agent_templates WHERE id = ?1", params![template_id])? > 0)
+}
+
+/// Id for a new agent when the caller doesn't choose one
+fn generated_agent_id(base: &str) -> String {
+    let base: String = base.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '-' })
+        .take(40)
+        .collect();
+    format!("{}-{}", base, &uuid::Uuid::new_v4().simple().to_string()[..8])
+}
+
+/// Store a new agent whose configuration is `base` with `overrides` merged in
+fn create_agent_from(
+    conn: &mut Connection,
+    agent_id: &str,
+    mut configuration: Value,
+    overrides: Option<&Value>,
+    author: &str,
+    note: &str,
+) -> Result<AgentConfigVersion> {
+    let exists = conn.query_row("SELECT 1 FROM agent_settings WHERE agent_id = ?1", params![agent_id], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if exists {
+        return Err(anyhow!("Agent {} already exists", agent_id));
+    }
+    if let Some(overrides) = overrides {
+        merge_patch(&mut configuration, overrides);
+    }
+    save_definition(conn, agent_id, &configuration, author, Some(note))
+}
+
+#[tauri::command]
+pub async fn list_agent_templates(app_handle: AppHandle) -> Result<Vec<AgentTemplate>, String> {
+    list_templates(&open_db(&app_handle)?)
+        .map_err(|e| format!("Failed to list agent templates: {}", e))
+}
+
+/// Create or replace a user template
+#[tauri::command]
+pub async fn save_agent_template(template: AgentTemplate, app_handle: AppHandle) -> Result<(), String> {
+    save_template(&open_db(&app_handle)?, &template)
+        .map_err(|e| format!("Failed to save agent template: {}", e))?;
+    info!("Saved agent template {}", template.id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_agent_template(template_id: String, app_handle: AppHandle) -> Result<(), String> {
+    let deleted = delete_template(&open_db(&app_handle)?, &template_id)
+        .map_err(|e| format!("Failed to delete agent template: {}", e))?;
+    if !deleted {
+        return Err(format!("Unknown agent template: {}", template_id));
+    }
+    Ok(())
+}
+
+/// Create an agent from a template, merging `overrides` into its configuration and applying its
+/// permission preset. The agent id is generated from the template when not given.
+#[tauri::command]
+pub async fn create_agent_from_template(
+    template_id: String,
+    overrides: Option<Value>,
+    agent_id: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<AgentConfigVersion, String> {
+    let mut conn = open_db(&app_handle)?;
+    let template = find_template(&conn, &template_id).map_err(|e| e.to_string())?;
+    let agent_id = agent_id.unwrap_or_else(|| generated_agent_id(&template.id));
+
+    let version = create_agent_from(
+        &mut conn,
+        &agent_id,
+        template.configuration,
+        overrides.as_ref(),
+        &format!("template:{}", template.id),
+        &format!("Created from template {}", template.name),
+    ).map_err(|e| format!("Failed to create agent from template: {}", e))?;
+
+    if let Some(preset) = template.permissions {
+        save_agent_permissions(&state.storage, &preset.for_agent(&agent_id))
+            .map_err(|e| format!("Created agent {} but failed to apply its permissions: {}", agent_id, e))?;
+    }
+
+    info!("Created agent {} from template {}", agent_id, template_id);
+    Ok(version)
+}
+
+/// Copy an agent's configuration and permissions to a new agent, merging `overrides` in
+#[tauri::command]
+pub async fn clone_agent(
+    agent_id: String,
+    new_agent_id: Option<String>,
+    overrides: Option<Value>,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<AgentConfigVersion, String> {
+    let mut conn = open_db(&app_handle)?;
+    let configuration: String = conn.query_row(
+        "SELECT configuration FROM agent_settings WHERE agent_id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Unknown agent: {}", agent_id))?;
+    let configuration: Value = serde_json::from_str(&configuration)
+        .map_err(|e| format!("Agent {} has a malformed configuration: {}", agent_id, e))?;
+    let new_agent_id = new_agent_id.unwrap_or_else(|| generated_agent_id(&agent_id));
+
+    let version = create_agent_from(
+        &mut conn,
+        &new_agent_id,
+        configuration,
+        overrides.as_ref(),
+        &format!("clone:{}", agent_id),
+        &format!("Cloned from {}", agent_id),
+    ).map_err(|e| format!("Failed to clone agent: {}", e))?;
+
+    let permissions = load_agent_permissions(&state.storage, &agent_id)
+        .map_err(|e| format!("Failed to load permissions of {}: {}", agent_id, e))?;
+    save_agent_permissions(&state.storage, &PermissionPreset::from(permissions).for_agent(&new_agent_id))
+        .map_err(|e| format!("Cloned agent {} but failed to copy its permissions: {}", new_agent_id, e))?;
+
+    info!("Cloned agent {} to {}", agent_id, new_agent_id);
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    #[test]
+    fn test_builtin_templates_are_valid() {
+        for template in builtin_templates() {
+            validate_agent_config(&template.configuration).unwrap();
+        }
+        let mut config = json!({ "name": "Coder", "tools": ["read_file"], "nested": { "a": 1, "b": 2 } });
+        merge_patch(&mut config, &json!({ "name": "Rust coder", "tools": [], "nested": { "a": null, "c": 3 } }));
+        assert_eq!(config, json!({ "name": "Rust coder", "tools": [], "nested": { "b": 2, "c": 3 } }));
+    }
+
+    #[test]
+    fn test_user_templates_and_agent_creation() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+
+        let mut template = find_template(&conn, "summarizer").unwrap();
+        assert!(save_template(&conn, &template).is_err());
+        template.id = "brief".to_string();
+        template.name = "Brief".to_string();
+        save_template(&conn, &template).unwrap();
+        assert_eq!(list_templates(&conn).unwrap().len(), 4);
+        assert!(!find_template(&conn, "brief").unwrap().builtin);
+
+        let version = create_agent_from(
+            &mut conn, "brief-1", template.configuration.clone(), Some(&json!({ "temperature": 0.7 })), "template:brief", "test",
+        ).unwrap();
+        assert!(version.configuration.contains("0.7"));
+        assert!(create_agent_from(&mut conn, "brief-1", template.configuration.clone(), None, "t", "t").is_err());
+        assert!(create_agent_from(&mut conn, "brief-2", template.configuration, Some(&json!({ "model": null })), "t", "t").is_err());
+
+        assert!(delete_template(&conn, "brief").unwrap());
+        assert!(delete_template(&conn, "coder").is_err());
+        assert!(generated_agent_id("My Agent").starts_with("my-agent-"));
+    }
+}
@@ -0,0 +1,418 @@
+//! Files, images and code blobs attached to conversations. Content is stored once on disk under
+//! its SHA-256, sharded by the first two hex digits; each attachment is a row in `artifacts`
+//! pointing at a blob. Blobs no row refers to any more are removed by garbage collection.
+
+use super::{open_conversation_db, open_db};
+use crate::ai::ImagePart;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+/// Largest artifact accepted
+pub const MAX_ARTIFACT_BYTES: usize = 50 * 1024 * 1024;
+
+/// Unreferenced blobs younger than this are kept, so a save racing a collection isn't lost
+pub const ARTIFACT_GC_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// Held by saves from writing (or reusing) a blob until its row is inserted, and by garbage
+/// collection throughout, so a collection can never remove a blob a save has just deduplicated
+/// against; the mtime grace alone doesn't cover collections run with no grace, as a purge does
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+fn lock_store() -> MutexGuard<'static, ()> {
+    STORE_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    File,
+    Image,
+    Code,
+}
+
+impl ArtifactKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Image => "image",
+            Self::Code => "code",
+        }
+    }
+
+    fn parse(kind: &str) -> Self {
+        match kind {
+            "image" => Self::Image,
+            "code" => Self::Code,
+            _ => Self::File,
+        }
+    }
+
+    fn default_mime_type(self) -> &'static str {
+        match self {
+            Self::Code => "text/plain",
+            Self::File | Self::Image => "application/octet-stream",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Artifact {
+    pub id: String,
+    pub conversation_id: String,
+    /// Message that produced or consumed the artifact
+    pub message_id: Option<String>,
+    pub name: String,
+    pub kind: ArtifactKind,
+    pub mime_type: String,
+    /// Hex SHA-256 of the content
+    pub hash: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactContent {
+    pub artifact: Artifact,
+    /// Base64-encoded content
+    pub data: String,
+}
+
+/// Blobs removed by a garbage collection
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ArtifactGcReport {
+    pub blobs_removed: usize,
+    pub bytes_freed: u64,
+    /// Unreferenced blobs kept because they were written within the grace period
+    pub blobs_kept: usize,
+}
+
+/// New attachment to store
+#[derive(Debug, Clone)]
+pub struct NewArtifact<'a> {
+    pub conversation_id: &'a str,
+    pub message_id: Option<&'a str>,
+    pub name: &'a str,
+    pub kind: ArtifactKind,
+    pub mime_type: Option<&'a str>,
+}
+
+/// Directory holding artifact blobs, next to the conversation database
+pub fn artifact_store_dir(app_handle: &AppHandle) -> Result<PathBuf> {
+    let config_dir = app_handle.path().app_config_dir()
+        .map_err(|e| anyhow!("Could not resolve app config directory: {}", e))?;
+    Ok(config_dir.join("artifacts"))
+}
+
+fn is_blob_hash(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase())
+}
+
+fn blob_path(store: &Path, hash: &str) -> PathBuf {
+    store.join(&hash[..2]).join(hash)
+}
+
+/// Write `data` under its hash unless an identical blob is already stored
+fn write_blob(store: &Path, data: &[u8]) -> Result<String> {
+    let hash = hex::encode(Sha256::digest(data));
+    let path = blob_path(store, &hash);
+    if path.exists() {
+        // A fresh mtime keeps the blob out of a collection running before its row is inserted
+        std::fs::File::options().append(true).open(&path)?.set_modified(SystemTime::now())?;
+        return Ok(hash);
+    }
+    let shard = path.parent().expect("blob paths have a shard directory");
+    std::fs::create_dir_all(shard)?;
+    let partial = shard.join(format!("{}.{}.tmp", hash, uuid::Uuid::new_v4()));
+    std::fs::write(&partial, data)?;
+    std::fs::rename(&partial, &path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&partial);
+    })?;
+    Ok(hash)
+}
+
+fn artifact_from_row(row: &rusqlite::Row) -> rusqlite::Result<Artifact> {
+    Ok(Artifact {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        message_id: row.get(2)?,
+        name: row.get(3)?,
+        kind: ArtifactKind::parse(&row.get::<_, String>(4)?),
+        mime_type: row.get(5)?,
+        hash: row.get(6)?,
+        size: row.get::<_, i64>(7)? as u64,
+        created_at: super::parse_db_timestamp(&row.get::<_, String>(8)?),
+    })
+}
+
+const ARTIFACT_COLUMNS: &str = "id, conversation_id, message_id, name, kind, mime_type, hash, size, created_at";
+
+pub fn save_artifact_blob(conn: &Connection, store: &Path, artifact: &NewArtifact, data: &[u8]) -> Result<Artifact> {
+    let name = artifact.name.trim();
+    if name.is_empty() {
+        bail!("Artifacts need a name");
+    }
+    if data.len() > MAX_ARTIFACT_BYTES {
+        bail!("Artifact is {} bytes; the limit is {}", data.len(), MAX_ARTIFACT_BYTES);
+    }
+    let exists = conn.query_row("SELECT 1 FROM conversations WHERE id = ?1", params![artifact.conversation_id], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if !exists {
+        bail!("Conversation not found: {}", artifact.conversation_id);
+    }
+
+    let _store = lock_store();
+    let hash = write_blob(store, data).context("Failed to write artifact content")?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let mime_type = artifact.mime_type.map(str::trim).filter(|m| !m.is_empty())
+        .unwrap_or(artifact.kind.default_mime_type());
+    conn.execute(
+        "INSERT INTO artifacts (id, conversation_id, message_id, name, kind, mime_type, hash, size, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            id, artifact.conversation_id, artifact.message_id, name, artifact.kind.as_str(),
+            mime_type, hash, data.len() as i64, Utc::now().to_rfc3339(),
+        ],
+    )?;
+    find_artifact(conn, &id)
+}
+
+pub fn find_artifact(conn: &Connection, artifact_id: &str) -> Result<Artifact> {
+    conn.query_row(
+        &format!("SELECT {} FROM artifacts WHERE id = ?1", ARTIFACT_COLUMNS),
+        params![artifact_id],
+        artifact_from_row,
+    ).optional()?
+        .ok_or_else(|| anyhow!("Artifact not found: {}", artifact_id))
+}
+
+/// An artifact's content, checked against its hash
+pub fn read_artifact_blob(store: &Path, artifact: &Artifact) -> Result<Vec<u8>> {
+    if !is_blob_hash(&artifact.hash) {
+        bail!("Artifact {} has an invalid hash", artifact.id);
+    }
+    let data = std::fs::read(blob_path(store, &artifact.hash))
+        .with_context(|| format!("Content of artifact {} is missing", artifact.id))?;
+    if hex::encode(Sha256::digest(&data)) != artifact.hash {
+        bail!("Content of artifact {} is corrupted", artifact.id);
+    }
+    Ok(data)
+}
+
+pub fn list_artifacts(conn: &Connection, conversation_id: &str) -> Result<Vec<Artifact>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM artifacts WHERE conversation_id = ?1 ORDER BY created_at, rowid",
+        ARTIFACT_COLUMNS
+    ))?;
+    let artifacts = stmt.query_map(params![conversation_id], artifact_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(artifacts)
+}
+
+/// Image artifacts attached to a message, as parts to send with it
+pub fn message_images(conn: &Connection, store: &Path, message_id: &str) -> Result<Vec<ImagePart>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM artifacts WHERE message_id = ?1 AND kind = 'image' ORDER BY created_at, rowid",
+        ARTIFACT_COLUMNS
+    ))?;
+    let artifacts = stmt.query_map(params![message_id], artifact_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    artifacts.iter().map(|artifact| {
+        Ok(ImagePart::Base64 {
+            media_type: Some(artifact.mime_type.clone()).filter(|m| m.starts_with("image/")),
+            data: BASE64.encode(read_artifact_blob(store, artifact)?),
+        })
+    }).collect()
+}
+
+/// Remove blobs no artifact refers to, and leftovers of interrupted writes, once older than `grace`
+pub fn collect_garbage(conn: &Connection, store: &Path, grace: Duration) -> Result<ArtifactGcReport> {
+    let mut report = ArtifactGcReport::default();
+    let _store = lock_store();
+    if !store.exists() {
+        return Ok(report);
+    }
+    let referenced: HashSet<String> = conn.prepare("SELECT DISTINCT hash FROM artifacts")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let now = SystemTime::now();
+
+    for shard in std::fs::read_dir(store)? {
+        let shard = shard?.path();
+        if !shard.is_dir() {
+            continue;
+        }
+        for entry in std::fs::read_dir(&shard)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let partial = name.ends_with(".tmp");
+            if !partial && (!is_blob_hash(&name) || referenced.contains(&name)) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let age = metadata.modified().ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            if age < grace {
+                report.blobs_kept += 1;
+                continue;
+            }
+            match std::fs::remove_file(entry.path()) {
+                Ok(()) => {
+                    report.blobs_removed += 1;
+                    report.bytes_freed += metadata.len();
+                }
+                Err(e) => warn!("Failed to remove artifact blob {}: {}", entry.path().display(), e),
+            }
+        }
+        // Only succeeds once the shard is empty
+        let _ = std::fs::remove_dir(&shard);
+    }
+    Ok(report)
+}
+
+/// Collect unreferenced artifact blobs of the app's store
+pub fn collect_artifact_garbage_for(app_handle: &AppHandle, grace: Duration) -> Result<ArtifactGcReport> {
+    let conn = open_conversation_db(app_handle)?;
+    let report = collect_garbage(&conn, &artifact_store_dir(app_handle)?, grace)?;
+    if report.blobs_removed > 0 {
+        info!("Removed {} unreferenced artifact blobs ({} bytes)", report.blobs_removed, report.bytes_freed);
+    }
+    Ok(report)
+}
+
+/// Decode base64 artifact content, refusing input that would decode past `max_bytes`
+/// before allocating for it
+fn decode_artifact_data(data: &str, max_bytes: usize) -> Result<Vec<u8>> {
+    let max_encoded = max_bytes.div_ceil(3) * 4;
+    if data.len() > max_encoded {
+        bail!("Artifact data is {} base64 characters; the limit is {}", data.len(), max_encoded);
+    }
+    let bytes = BASE64.decode(data.as_bytes()).map_err(|e| anyhow!("Invalid artifact data: {}", e))?;
+    if bytes.len() > max_bytes {
+        bail!("Artifact is {} bytes; the limit is {}", bytes.len(), max_bytes);
+    }
+    Ok(bytes)
+}
+
+// Tauri Commands
+
+/// Store a file, image or code blob with a conversation; `data` is base64-encoded
+#[tauri::command]
+pub async fn save_artifact(
+    conversation_id: String,
+    message_id: Option<String>,
+    name: String,
+    kind: ArtifactKind,
+    mime_type: Option<String>,
+    data: String,
+    app_handle: AppHandle,
+) -> Result<Artifact, String> {
+    let bytes = decode_artifact_data(&data, MAX_ARTIFACT_BYTES).map_err(|e| e.to_string())?;
+    let store = artifact_store_dir(&app_handle).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&store).map_err(|e| format!("Failed to create artifact store: {}", e))?;
+    crate::ai::ensure_disk_space(&store, bytes.len() as u64, "artifact save")
+        .map_err(|e| e.to_string())?;
+
+    let artifact = NewArtifact {
+        conversation_id: &conversation_id,
+        message_id: message_id.as_deref(),
+        name: &name,
+        kind,
+        mime_type: mime_type.as_deref(),
+    };
+    let artifact = save_artifact_blob(&open_db(&app_handle)?, &store, &artifact, &bytes)
+        .map_err(|e| format!("Failed to save artifact: {}", e))?;
+    info!("Saved artifact {} ({} bytes) for conversation {}", artifact.id, artifact.size, conversation_id);
+    Ok(artifact)
+}
+
+#[tauri::command]
+pub async fn get_artifact(
+    artifact_id: String,
+    app_handle: AppHandle,
+) -> Result<ArtifactContent, String> {
+    let artifact = find_artifact(&open_db(&app_handle)?, &artifact_id).map_err(|e| e.to_string())?;
+    let store = artifact_store_dir(&app_handle).map_err(|e| e.to_string())?;
+    let data = read_artifact_blob(&store, &artifact).map_err(|e| e.to_string())?;
+    Ok(ArtifactContent { artifact, data: BASE64.encode(data) })
+}
+
+#[tauri::command]
+pub async fn list_conversation_artifacts(
+    conversation_id: String,
+    app_handle: AppHandle,
+) -> Result<Vec<Artifact>, String> {
+    list_artifacts(&open_db(&app_handle)?, &conversation_id)
+        .map_err(|e| format!("Failed to list artifacts: {}", e))
+}
+
+/// Remove artifact blobs that no conversation refers to any more
+#[tauri::command]
+pub async fn collect_artifact_garbage(app_handle: AppHandle) -> Result<ArtifactGcReport, String> {
+    collect_artifact_garbage_for(&app_handle, ARTIFACT_GC_GRACE)
+        .map_err(|e| format!("Failed to collect artifact garbage: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    #[test]
+    fn test_store_dedupe_and_collect_artifacts() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        conn.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             INSERT INTO conversations (id, agent_id, title) VALUES ('c1', 'coder', 'One'), ('c2', 'coder', 'Two');",
+        ).unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let new = |conversation_id, name, kind| NewArtifact { conversation_id, message_id: None, name, kind, mime_type: None };
+
+        let code = save_artifact_blob(&conn, store.path(), &new("c1", "main.rs", ArtifactKind::Code), b"fn main() {}").unwrap();
+        assert_eq!(code.mime_type, "text/plain");
+        assert_eq!(code.size, 12);
+        let copy = save_artifact_blob(&conn, store.path(), &new("c2", "copy.rs", ArtifactKind::Code), b"fn main() {}").unwrap();
+        assert_eq!(copy.hash, code.hash);
+        let image = save_artifact_blob(&conn, store.path(), &new("c1", "logo.png", ArtifactKind::Image), &[0x89, b'P', b'N', b'G']).unwrap();
+        assert!(save_artifact_blob(&conn, store.path(), &new("missing", "x", ArtifactKind::File), b"x").is_err());
+        assert!(save_artifact_blob(&conn, store.path(), &new("c1", " ", ArtifactKind::File), b"x").is_err());
+
+        let listed = list_artifacts(&conn, "c1").unwrap();
+        assert_eq!(listed.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), vec!["main.rs", "logo.png"]);
+        assert_eq!(listed[1].kind, ArtifactKind::Image);
+        assert_eq!(read_artifact_blob(store.path(), &find_artifact(&conn, &image.id).unwrap()).unwrap(), vec![0x89, b'P', b'N', b'G']);
+        conn.execute("UPDATE artifacts SET message_id = 'm1' WHERE id IN (?1, ?2)", params![image.id, code.id]).unwrap();
+        assert_eq!(message_images(&conn, store.path(), "m1").unwrap(), vec![ImagePart::Base64 { media_type: None, data: "iVBORw==".to_string() }]);
+
+        conn.execute("DELETE FROM conversations WHERE id = 'c1'", []).unwrap();
+        assert_eq!(collect_garbage(&conn, store.path(), ARTIFACT_GC_GRACE).unwrap().blobs_kept, 1);
+        let report = collect_garbage(&conn, store.path(), Duration::ZERO).unwrap();
+        assert_eq!((report.blobs_removed, report.bytes_freed), (1, 4));
+        // Still referenced by the copy in c2
+        assert_eq!(read_artifact_blob(store.path(), &copy).unwrap(), b"fn main() {}");
+        assert!(read_artifact_blob(store.path(), &image).is_err());
+
+        std::fs::write(blob_path(store.path(), &copy.hash), b"tampered").unwrap();
+        assert!(read_artifact_blob(store.path(), &copy).is_err());
+    }
+
+    #[test]
+    fn test_base64_artifact_data_is_capped_before_decoding() {
+        assert_eq!(decode_artifact_data(&BASE64.encode(b"abcdef"), 6).unwrap(), b"abcdef");
+        assert!(decode_artifact_data(&BASE64.encode(b"abcdefg"), 6).is_err());
+        assert!(decode_artifact_data(&"A".repeat(1024), 6).unwrap_err().to_string().contains("base64 characters"));
+        assert!(decode_artifact_data("not base64!", 64).is_err());
+    }
+}
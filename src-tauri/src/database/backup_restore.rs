@@ -0,0 +1,395 @@
+use super::memory_encryption::{apply_key, database_key};
+use super::schema::AGENT_MEMORY_SCHEMA_VERSION;
+use super::simple_commands::MemoryState;
+use crate::ai::{enforce_path_policy, AIState, PathAccess};
+use crate::validation::MemoryValidator;
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+use tracing::{info, warn};
+
+/// Columns a backup's `agent_memories` must have to be restored
+const MEMORY_COLUMNS: [&str; 11] = [
+    "id", "agent_id", "memory_type", "content", "metadata", "embedding",
+    "relevance_score", "created_at", "updated_at", "access_count", "tags",
+];
+
+/// Columns added since, restored when the backup has them and left at their defaults otherwise
+const LATER_MEMORY_COLUMNS: [&str; 2] = ["pinned", "tier"];
+
+/// Integrity problems listed back to the user
+const MAX_REPORTED_PROBLEMS: usize = 20;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    /// Discard current memories and restore the backup's
+    Replace,
+    /// Bring back memories missing from the current database, keeping current versions
+    Merge,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupVerification {
+    pub path: String,
+    pub valid: bool,
+    /// `PRAGMA integrity_check` output, "ok" when healthy
+    pub integrity: Vec<String>,
+    /// 0 for backups taken before schema versions were recorded
+    pub schema_version: i64,
+    pub memory_count: usize,
+    pub agent_ids: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    pub mode: RestoreMode,
+    pub restored: usize,
+    /// Memories discarded by a replace
+    pub removed: usize,
+    /// Backup memories a merge left alone because the current database has them
+    pub kept: usize,
+    /// Snapshot of the database taken before a replace
+    pub safety_backup: Option<String>,
+}
+
+fn readable(conn: &Connection) -> bool {
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)).is_ok()
+}
+
+/// Open a backup read-only. A backup of an encrypted database is encrypted with the same key,
+/// so a file that doesn't read as plain SQLite is tried with `candidate_key`. Returns the
+/// connection and the key that opened it.
+pub fn open_backup(path: &Path, candidate_key: Option<&str>) -> Result<(Connection, Option<String>)> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let conn = Connection::open_with_flags(path, flags)?;
+    if readable(&conn) {
+        return Ok((conn, None));
+    }
+    if let Some(key) = candidate_key {
+        let conn = Connection::open_with_flags(path, flags)?;
+        apply_key(&conn, key)?;
+        if readable(&conn) {
+            return Ok((conn, Some(key.to_string())));
+        }
+    }
+    bail!("not a SQLite database, or encrypted with a key this agent doesn't have")
+}
+
+/// Check that a backup opens, passes SQLite's integrity check and has a schema this
+/// version can restore. The file is opened read-only.
+pub fn verify_backup_file(path: &Path, candidate_key: Option<&str>) -> Result<BackupVerification> {
+    let mut verification = BackupVerification {
+        path: path.to_string_lossy().to_string(),
+        valid: false,
+        integrity: Vec::new(),
+        schema_version: 0,
+        memory_count: 0,
+        agent_ids: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let conn = match open_backup(path, candidate_key) {
+        Ok((conn, _)) => conn,
+        Err(e) => {
+            verification.errors.push(format!("Cannot open backup: {}", e));
+            return Ok(verification);
+        }
+    };
+    let integrity = conn.prepare("PRAGMA integrity_check").and_then(|mut stmt| {
+        stmt.query_map([], |row| row.get::<_, String>(0))?.take(MAX_REPORTED_PROBLEMS).collect::<rusqlite::Result<Vec<_>>>()
+    });
+    match integrity {
+        // The FTS5 index check needs write access; restores rebuild that index from the
+        // table anyway, so a read-only connection skips it
+        Ok(lines) => {
+            let lines: Vec<String> = lines.into_iter()
+                .filter(|line| !(line.contains("for FTS5 table") && line.contains("readonly database")))
+                .collect();
+            verification.integrity = if lines.is_empty() { vec!["ok".to_string()] } else { lines };
+        }
+        Err(e) => {
+            verification.errors.push(format!("Not a readable SQLite database: {}", e));
+            return Ok(verification);
+        }
+    }
+    if verification.integrity != ["ok"] {
+        verification.errors.push("Integrity check failed".to_string());
+    }
+
+    verification.schema_version = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if verification.schema_version > AGENT_MEMORY_SCHEMA_VERSION {
+        verification.errors.push(format!(
+            "Backup schema version {} is newer than the supported version {}",
+            verification.schema_version, AGENT_MEMORY_SCHEMA_VERSION
+        ));
+    }
+
+    let columns: Vec<String> = conn.prepare("SELECT name FROM pragma_table_info('agent_memories')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let missing: Vec<&str> = MEMORY_COLUMNS.iter().copied().filter(|c| !columns.iter().any(|col| col == c)).collect();
+    if columns.is_empty() {
+        verification.errors.push("Backup has no agent_memories table".to_string());
+    } else if !missing.is_empty() {
+        verification.errors.push(format!("Backup agent_memories is missing columns: {}", missing.join(", ")));
+    } else {
+        verification.memory_count = conn.query_row("SELECT COUNT(*) FROM agent_memories", [], |row| row.get::<_, i64>(0))? as usize;
+        verification.agent_ids = conn.prepare("SELECT DISTINCT agent_id FROM agent_memories ORDER BY agent_id")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+    }
+
+    verification.valid = verification.errors.is_empty();
+    Ok(verification)
+}
+
+/// Restore memories from a verified backup into `conn`, re-owned by `agent_id`. `backup_key`
+/// is the key from `open_backup`; `None` attaches the backup as plain SQLite even when `conn`
+/// is encrypted. The backup is checked again once attached, before a replace deletes
+/// anything. Returns (restored, removed, kept).
+pub fn restore_from_backup(
+    conn: &mut Connection,
+    backup: &Path,
+    backup_key: Option<&str>,
+    agent_id: &str,
+    mode: RestoreMode,
+) -> Result<(usize, usize, usize)> {
+    if backup_key.is_some_and(|key| !key.chars().all(|c| c.is_ascii_hexdigit())) {
+        bail!("Backup key must be hex");
+    }
+    // An attached database inherits the main database's key unless given its own
+    let key = backup_key.map(|key| format!("x'{}'", key)).unwrap_or_default();
+    conn.execute("ATTACH DATABASE ?1 AS backup KEY ?2", params![backup.to_string_lossy(), key])?;
+    let result = (|| {
+        let tx = conn.transaction()?;
+        let check: String = tx.query_row("PRAGMA backup.quick_check(1)", [], |row| row.get(0))?;
+        if check != "ok" {
+            bail!("Backup failed its integrity check: {}", check);
+        }
+        let mut columns = MEMORY_COLUMNS.to_vec();
+        let mut backup_has = tx.prepare("SELECT 1 FROM pragma_table_info('agent_memories', 'backup') WHERE name = ?1")?;
+        for column in MEMORY_COLUMNS {
+            if !backup_has.exists(params![column])? {
+                bail!("Backup agent_memories is missing column {}", column);
+            }
+        }
+        for column in LATER_MEMORY_COLUMNS {
+            if backup_has.exists(params![column])? {
+                columns.push(column);
+            }
+        }
+        drop(backup_has);
+        let available: usize = tx.query_row("SELECT COUNT(*) FROM backup.agent_memories", [], |row| row.get::<_, i64>(0))? as usize;
+
+        let removed = match mode {
+            RestoreMode::Replace => {
+                tx.execute("DELETE FROM memory_access_log", [])?;
+                tx.execute("DELETE FROM agent_memories", [])?
+            }
+            RestoreMode::Merge => 0,
+        };
+        let selected = columns.iter().map(|&c| if c == "agent_id" { "?1" } else { c }).collect::<Vec<_>>().join(", ");
+        let columns = columns.join(", ");
+        let restored = tx.execute(
+            &format!("INSERT OR IGNORE INTO agent_memories ({}) SELECT {} FROM backup.agent_memories", columns, selected),
+            params![agent_id],
+        )?;
+        // Bulk changes are where the search index drifts, so rebuild it from the table
+        tx.execute("INSERT INTO agent_memories_fts(agent_memories_fts) VALUES ('rebuild')", [])?;
+        tx.commit()?;
+        Ok((restored, removed, available - restored))
+    })();
+    conn.execute("DETACH DATABASE backup", [])?;
+    result
+}
+
+/// Check a memory backup's integrity and schema before relying on it
+#[tauri::command]
+pub async fn verify_backup(
+    path: String,
+    agent_id: Option<String>,
+    state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<BackupVerification, String> {
+    info!("Verifying memory backup: {}", path);
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "backup_operations",
+        &agent_id.into_iter().collect::<Vec<_>>(),
+        std::slice::from_ref(&path)
+    ).await?;
+    let backup = enforce_path_policy(&ai_state.storage, None, &path, PathAccess::Chunked)?;
+    if !backup.is_file() {
+        return Err(format!("Backup not found: {}", path));
+    }
+
+    // A backup of an encrypted agent can only be read with that agent's key
+    let candidate_key = match validation_result.sanitized_inputs.first() {
+        Some(agent_id) => {
+            MemoryValidator::validate_agent_id(agent_id).map_err(|e| e.to_string())?;
+            let manager = state.get_or_create_manager(agent_id.clone())?;
+            database_key(manager.get_agent_db_path())
+                .map_err(|e| format!("Failed to load database key: {}", e))?
+        }
+        None => None,
+    };
+    verify_backup_file(&backup, candidate_key.as_deref()).map_err(|e| format!("Failed to verify backup: {}", e))
+}
+
+/// Restore an agent's memories from a backup, replacing or merging with what it has now.
+/// A replace first snapshots the current database so it can be undone.
+#[tauri::command]
+pub async fn restore_agent_memories(
+    agent_id: String,
+    backup_path: String,
+    mode: RestoreMode,
+    state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<RestoreSummary, String> {
+    info!("Restoring memories of agent {} from {} ({:?})", agent_id, backup_path, mode);
+
+    MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "backup_operations",
+        &[agent_id],
+        std::slice::from_ref(&backup_path)
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    let backup = &enforce_path_policy(&ai_state.storage, None, &backup_path, PathAccess::Chunked)?;
+    if !backup.is_file() {
+        return Err(format!("Backup not found: {}", backup_path));
+    }
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let candidate_key = database_key(manager.get_agent_db_path())
+        .map_err(|e| format!("Failed to load database key: {}", e))?;
+    let verification = verify_backup_file(backup, candidate_key.as_deref())
+        .map_err(|e| format!("Failed to verify backup: {}", e))?;
+    if !verification.valid {
+        return Err(format!("Backup failed verification: {}", verification.errors.join("; ")));
+    }
+    let (_, backup_key) = open_backup(backup, candidate_key.as_deref())
+        .map_err(|e| format!("Failed to open backup: {}", e))?;
+
+    let backup_size = std::fs::metadata(backup).map(|m| m.len()).unwrap_or(0);
+    let safety_backup = match mode {
+        RestoreMode::Replace => {
+            let backup_dir = super::simple_commands::backup_directory()?;
+            let path = backup_dir.join(format!(
+                "agent_{}_pre_restore_{}.db",
+                sanitized_agent_id,
+                chrono::Utc::now().format("%Y%m%d_%H%M%S")
+            ));
+            let db_size = std::fs::metadata(manager.get_agent_db_path()).map(|m| m.len()).unwrap_or(0);
+            crate::ai::ensure_disk_space(&backup_dir, db_size, "pre-restore backup")
+                .map_err(|e| e.to_string())?;
+            manager.backup_agent_memory(&path)
+                .map_err(|e| format!("Failed to back up current memories: {}", e))?;
+            Some(path.to_string_lossy().to_string())
+        }
+        RestoreMode::Merge => None,
+    };
+    crate::ai::ensure_disk_space(manager.get_agent_db_path(), backup_size, "memory restore")
+        .map_err(|e| e.to_string())?;
+
+    let mut conn = manager.agent_connection()
+        .map_err(|e| format!("Failed to open memory database: {}", e))?;
+    let (restored, removed, kept) = restore_from_backup(&mut conn, backup, backup_key.as_deref(), sanitized_agent_id, mode)
+        .map_err(|e| format!("Failed to restore memories: {}", e))?;
+
+    if verification.agent_ids.iter().any(|id| id != sanitized_agent_id) {
+        warn!("Restored memories of {:?} into agent {}", verification.agent_ids, sanitized_agent_id);
+    }
+    info!("Restored {} memories into agent {} ({} removed, {} kept)", restored, sanitized_agent_id, removed, kept);
+    Ok(RestoreSummary { mode, restored, removed, kept, safety_backup })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory::{AgentMemory, MemoryType};
+    use crate::database::memory_transfer::{import_memories, MergeStrategy};
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+
+    fn database(path: &Path, contents: &[&str]) -> (Connection, Vec<AgentMemory>) {
+        let mut conn = Connection::open(path).unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        conn.pragma_update(None, "user_version", AGENT_MEMORY_SCHEMA_VERSION).unwrap();
+        let memories: Vec<AgentMemory> = contents.iter()
+            .map(|c| AgentMemory::new("old-agent".to_string(), MemoryType::Task, c.to_string()))
+            .collect();
+        import_memories(&mut conn, "old-agent", memories.clone(), MergeStrategy::Skip).unwrap();
+        (conn, memories)
+    }
+
+    fn contents(conn: &Connection) -> Vec<(String, String)> {
+        conn.prepare("SELECT agent_id, content FROM agent_memories ORDER BY content").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn test_verify_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let (conn, _) = database(&dir.path().join("live.db"), &["first", "second"]);
+        let backup = dir.path().join("backup.db");
+        conn.execute("VACUUM INTO ?1", params![backup.to_string_lossy()]).unwrap();
+
+        let verification = verify_backup_file(&backup, None).unwrap();
+        assert!(verification.valid, "{:?} {:?}", verification.errors, verification.integrity);
+        assert_eq!(verification.schema_version, AGENT_MEMORY_SCHEMA_VERSION);
+        assert_eq!(verification.memory_count, 2);
+        assert_eq!(verification.agent_ids, vec!["old-agent"]);
+
+        Connection::open(&backup).unwrap().pragma_update(None, "user_version", AGENT_MEMORY_SCHEMA_VERSION + 1).unwrap();
+        assert!(verify_backup_file(&backup, None).unwrap().errors[0].contains("newer"));
+
+        let garbage = dir.path().join("garbage.db");
+        std::fs::write(&garbage, b"definitely not sqlite, just some bytes padded out past the header").unwrap();
+        assert!(!verify_backup_file(&garbage, None).unwrap().valid);
+    }
+
+    #[test]
+    fn test_restore_replace_and_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, _) = database(&dir.path().join("source.db"), &["backed up"]);
+        source.execute("UPDATE agent_memories SET pinned = 1", []).unwrap();
+        let backup = dir.path().join("backup.db");
+        source.execute("VACUUM INTO ?1", params![backup.to_string_lossy()]).unwrap();
+
+        let (mut live, _) = database(&dir.path().join("live.db"), &["written later"]);
+        assert_eq!(restore_from_backup(&mut live, &backup, None, "agent", RestoreMode::Merge).unwrap(), (1, 0, 0));
+        assert_eq!(restore_from_backup(&mut live, &backup, None, "agent", RestoreMode::Merge).unwrap(), (0, 0, 1));
+        assert_eq!(contents(&live), vec![
+            ("agent".to_string(), "backed up".to_string()),
+            ("old-agent".to_string(), "written later".to_string()),
+        ]);
+
+        assert_eq!(restore_from_backup(&mut live, &backup, None, "agent", RestoreMode::Replace).unwrap(), (1, 2, 0));
+        assert_eq!(contents(&live), vec![("agent".to_string(), "backed up".to_string())]);
+        let matches: i64 = live.query_row(
+            "SELECT COUNT(*) FROM agent_memories_fts WHERE agent_memories_fts MATCH 'backed'", [], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(matches, 1);
+        let pinned: bool = live.query_row("SELECT pinned FROM agent_memories", [], |row| row.get(0)).unwrap();
+        assert!(pinned);
+    }
+
+    #[test]
+    fn test_replace_keeps_memories_when_the_backup_is_unusable() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = dir.path().join("backup.db");
+        Connection::open(&backup).unwrap().execute_batch("CREATE TABLE agent_memories (id TEXT PRIMARY KEY);").unwrap();
+
+        let (mut live, _) = database(&dir.path().join("live.db"), &["current"]);
+        assert!(restore_from_backup(&mut live, &backup, None, "agent", RestoreMode::Replace).is_err());
+        assert_eq!(contents(&live), vec![("old-agent".to_string(), "current".to_string())]);
+    }
+}
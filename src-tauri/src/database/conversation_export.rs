@@ -0,0 +1,351 @@
+//! Exporting a conversation with its tool calls and timestamps as Markdown, JSON or a standalone
+//! HTML page. Tool output can be redacted: secret-named fields in tool calls are blanked like the
+//! MCP audit log does, and secret-looking text in tool messages and calls is replaced.
+
+use super::conversation_list::{conversation_from_row, CONVERSATION_COLUMNS};
+use super::message_revisions::ordered_messages;
+use super::{open_conversation_db, DbConversation, DbMessage};
+use crate::ai::{enforce_path_policy, redact_secrets, AIState, PathAccess};
+use crate::mcp::redact_arguments;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, State};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExportedMessage {
+    pub id: String,
+    pub role: String,
+    pub content: String,
+    /// Tool calls as stored, parsed when they are valid JSON
+    pub tool_calls: Option<Value>,
+    pub timestamp: DateTime<Utc>,
+    pub tokens: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversationExport {
+    pub conversation: DbConversation,
+    pub messages: Vec<ExportedMessage>,
+    pub exported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationExportSummary {
+    pub path: String,
+    pub format: ExportFormat,
+    pub message_count: usize,
+    /// Secrets replaced in tool output
+    pub redactions: usize,
+    pub bytes: u64,
+}
+
+fn redact_value(value: &mut Value) -> usize {
+    match value {
+        Value::String(text) => {
+            let (redacted, count) = redact_secrets(text);
+            *text = redacted;
+            count
+        }
+        Value::Array(items) => items.iter_mut().map(redact_value).sum(),
+        Value::Object(map) => map.values_mut().map(redact_value).sum(),
+        _ => 0,
+    }
+}
+
+/// Messages ready to render; with `redact_tool_outputs`, tool messages and tool calls are
+/// redacted and the number of replaced secrets returned
+pub fn prepare_messages(messages: &[DbMessage], redact_tool_outputs: bool) -> (Vec<ExportedMessage>, usize) {
+    let mut redactions = 0;
+    let exported = messages.iter().map(|message| {
+        let mut content = message.content.clone();
+        let mut tool_calls = message.tool_calls.as_ref().map(|calls| {
+            serde_json::from_str(calls).unwrap_or_else(|_| Value::String(calls.clone()))
+        });
+        if redact_tool_outputs {
+            if message.role == "tool" {
+                let (redacted, count) = redact_secrets(&content);
+                content = redacted;
+                redactions += count;
+            }
+            if let Some(calls) = tool_calls.as_mut() {
+                let (mut blanked, fields) = redact_arguments(calls);
+                redactions += fields.len() + redact_value(&mut blanked);
+                *calls = blanked;
+            }
+        }
+        ExportedMessage {
+            id: message.id.clone(),
+            role: message.role.clone(),
+            content,
+            tool_calls,
+            timestamp: message.timestamp,
+            tokens: message.tokens,
+        }
+    }).collect();
+    (exported, redactions)
+}
+
+fn role_label(role: &str) -> &str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        "system" => "System",
+        "tool" => "Tool output",
+        other => other,
+    }
+}
+
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+fn pretty_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// A fenced code block whose fence is longer than any backtick run inside `text`
+fn code_block(language: &str, text: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, text.trim_end(), fence)
+}
+
+pub fn render_markdown(export: &ConversationExport) -> String {
+    let conversation = &export.conversation;
+    let mut out = format!("# {}\n\n", conversation.title);
+    out.push_str(&format!("- Agent: {}\n", conversation.agent_id));
+    out.push_str(&format!("- Created: {}\n", format_timestamp(&conversation.created_at)));
+    out.push_str(&format!("- Messages: {}\n", export.messages.len()));
+    out.push_str(&format!("- Tokens: {}\n", conversation.token_count));
+    if !conversation.tags.is_empty() {
+        out.push_str(&format!("- Tags: {}\n", conversation.tags.join(", ")));
+    }
+    if let Some(summary) = conversation.summary.as_deref().filter(|s| !s.trim().is_empty()) {
+        out.push_str(&format!("\n> {}\n", summary.trim().replace('\n', "\n> ")));
+    }
+
+    for message in &export.messages {
+        out.push_str(&format!("\n---\n\n### {} · {}\n\n", role_label(&message.role), format_timestamp(&message.timestamp)));
+        if message.role == "tool" {
+            out.push_str(&code_block("", &message.content));
+        } else {
+            out.push_str(message.content.trim_end());
+        }
+        out.push('\n');
+        if let Some(calls) = &message.tool_calls {
+            out.push_str("\n**Tool calls**\n\n");
+            out.push_str(&code_block("json", &pretty_json(calls)));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:860px;margin:2rem auto;padding:0 1rem;color:#1f2328}\
+header p{color:#59636e;margin:.2rem 0}\
+.message{border:1px solid #d1d9e0;border-radius:8px;padding:.75rem 1rem;margin:1rem 0}\
+.message.user{background:#f6f8fa}.message.tool{background:#fff8e6}\
+.meta{font-size:.85rem;color:#59636e;margin-bottom:.5rem}\
+pre{white-space:pre-wrap;word-wrap:break-word;margin:0;font-family:ui-monospace,monospace;font-size:.9rem}\
+.content{white-space:pre-wrap}details{margin-top:.5rem}";
+
+/// A self-contained page; all conversation text is escaped
+pub fn render_html(export: &ConversationExport) -> String {
+    let conversation = &export.conversation;
+    let mut body = format!(
+        "<header><h1>{}</h1><p>Agent: {} · Created: {} · {} messages · {} tokens</p>",
+        escape_html(&conversation.title),
+        escape_html(&conversation.agent_id),
+        format_timestamp(&conversation.created_at),
+        export.messages.len(),
+        conversation.token_count,
+    );
+    if !conversation.tags.is_empty() {
+        body.push_str(&format!("<p>Tags: {}</p>", escape_html(&conversation.tags.join(", "))));
+    }
+    if let Some(summary) = conversation.summary.as_deref().filter(|s| !s.trim().is_empty()) {
+        body.push_str(&format!("<blockquote>{}</blockquote>", escape_html(summary.trim())));
+    }
+    body.push_str("</header>\n");
+
+    for message in &export.messages {
+        let role = escape_html(&message.role);
+        body.push_str(&format!(
+            "<section class=\"message {}\"><div class=\"meta\">{} · <time datetime=\"{}\">{}</time></div>",
+            role,
+            escape_html(role_label(&message.role)),
+            message.timestamp.to_rfc3339(),
+            format_timestamp(&message.timestamp),
+        ));
+        if message.role == "tool" {
+            body.push_str(&format!("<pre>{}</pre>", escape_html(&message.content)));
+        } else {
+            body.push_str(&format!("<div class=\"content\">{}</div>", escape_html(&message.content)));
+        }
+        if let Some(calls) = &message.tool_calls {
+            body.push_str(&format!("<details><summary>Tool calls</summary><pre>{}</pre></details>", escape_html(&pretty_json(calls))));
+        }
+        body.push_str("</section>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(&conversation.title),
+        HTML_STYLE,
+        body,
+    )
+}
+
+pub fn render_export(export: &ConversationExport, format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Markdown => render_markdown(export),
+        ExportFormat::Json => serde_json::to_string_pretty(export)?,
+        ExportFormat::Html => render_html(export),
+    })
+}
+
+pub fn load_export(conn: &Connection, conversation_id: &str, redact_tool_outputs: bool) -> Result<(ConversationExport, usize)> {
+    let conversation = conn.query_row(
+        &format!("SELECT {} FROM conversations c WHERE c.id = ?1", CONVERSATION_COLUMNS),
+        params![conversation_id],
+        conversation_from_row,
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => anyhow::anyhow!("Conversation not found: {}", conversation_id),
+        other => other.into(),
+    })?;
+    let (messages, redactions) = prepare_messages(&ordered_messages(conn, conversation_id)?, redact_tool_outputs);
+    Ok((ConversationExport { conversation, messages, exported_at: Utc::now() }, redactions))
+}
+
+// Tauri Commands
+
+/// Write a conversation to `path` as Markdown, JSON or HTML. The path is checked against the
+/// active workspace's path policy.
+#[tauri::command]
+pub async fn export_conversation(
+    conversation_id: String,
+    format: ExportFormat,
+    path: String,
+    redact_tool_outputs: Option<bool>,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<ConversationExportSummary, String> {
+    info!("Exporting conversation {} as {:?} to {}", conversation_id, format, path);
+
+    let conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    let (export, redactions) = load_export(&conn, &conversation_id, redact_tool_outputs.unwrap_or(false))
+        .map_err(|e| format!("Failed to load conversation: {}", e))?;
+    let content = render_export(&export, format)
+        .map_err(|e| format!("Failed to render conversation: {}", e))?;
+
+    let bytes = content.len() as u64;
+    let target = enforce_path_policy(&state.storage, None, &path, PathAccess::Write { size: bytes })?;
+    if let Some(directory) = target.parent() {
+        crate::ai::ensure_disk_space(directory, bytes, "conversation export")
+            .map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&target, content).map_err(|e| format!("Failed to write export {}: {}", path, e))?;
+
+    info!("Exported {} messages of conversation {} ({} redactions)", export.messages.len(), conversation_id, redactions);
+    Ok(ConversationExportSummary {
+        path: target.to_string_lossy().to_string(),
+        format,
+        message_count: export.messages.len(),
+        redactions,
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::secret_placeholder;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+    use crate::mcp::REDACTED;
+
+    fn fixture() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        conn.execute_batch(
+            r#"INSERT INTO conversations (id, agent_id, title, created_at) VALUES ('c1', 'coder', 'Deploy <prod>', '2024-01-01 09:00:00');
+               INSERT INTO messages (id, conversation_id, role, content, tool_calls, timestamp, tokens) VALUES
+                  ('m1', 'c1', 'user', 'Check the ```config```', NULL, '2024-01-01T10:00:00.000Z', 5),
+                  ('m2', 'c1', 'assistant', 'Reading it', '[{"name":"read_file","arguments":{"path":".env","api_token":"abc123"}}]', '2024-01-01T10:00:01.000Z', 3),
+                  ('m3', 'c1', 'tool', 'OPENAI_API_KEY=sk-abcdefghijklmnop1234 and Bearer abcdefghijklmnopqrstuvwx', NULL, '2024-01-01T10:00:02.000Z', 20);"#,
+        ).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_redacts_tool_output() {
+        let conn = fixture();
+        let (export, redactions) = load_export(&conn, "c1", false).unwrap();
+        assert_eq!(redactions, 0);
+        assert!(export.messages[2].content.contains("sk-abcdefghijklmnop1234"));
+
+        let (export, redactions) = load_export(&conn, "c1", true).unwrap();
+        assert_eq!(redactions, 3);
+        assert_eq!(
+            export.messages[2].content,
+            format!(
+                "OPENAI_API_KEY={} and Bearer {}",
+                secret_placeholder("sk-abcdefghijklmnop1234"),
+                secret_placeholder("abcdefghijklmnopqrstuvwx")
+            )
+        );
+        assert_eq!(export.messages[1].tool_calls.as_ref().unwrap()[0]["arguments"]["api_token"], REDACTED);
+        assert_eq!(export.messages[1].tool_calls.as_ref().unwrap()[0]["arguments"]["path"], ".env");
+        assert_eq!(export.messages[0].content, "Check the ```config```");
+        assert!(load_export(&conn, "missing", true).is_err());
+    }
+
+    #[test]
+    fn test_renders_each_format() {
+        let (export, _) = load_export(&fixture(), "c1", true).unwrap();
+
+        let markdown = render_export(&export, ExportFormat::Markdown).unwrap();
+        assert!(markdown.starts_with("# Deploy <prod>\n"));
+        assert!(markdown.contains("### Tool output · 2024-01-01 10:00:02 UTC"));
+        assert!(markdown.contains("```\nOPENAI_API_KEY=[REDACTED:"));
+        assert!(markdown.contains("Check the ```config```"));
+        assert!(markdown.contains("```json\n[\n  {"));
+
+        let html = render_export(&export, ExportFormat::Html).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Deploy &lt;prod&gt;</title>"));
+        assert!(html.contains("<section class=\"message tool\">"));
+        assert!(!html.contains("sk-abcdefghijklmnop1234"));
+
+        let json: Value = serde_json::from_str(&render_export(&export, ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(json["conversation"]["title"], "Deploy <prod>");
+    }
+}
@@ -0,0 +1,282 @@
+//! Organizing the conversation list: tags, pinning and archiving, and the filtered, sorted query
+//! behind `get_conversations`.
+
+use super::{open_db, parse_db_timestamp, DbConversation};
+use anyhow::{bail, Result};
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tracing::info;
+
+const MAX_TAGS: usize = 20;
+const MAX_TAG_CHARS: usize = 50;
+
+/// Columns read by `conversation_from_row`, for a query over `conversations c`
+pub(super) const CONVERSATION_COLUMNS: &str =
+    "c.id, c.agent_id, c.title, c.summary, c.created_at, c.updated_at, c.token_count, c.pinned, c.archived_at,
+     (SELECT json_group_array(t.tag) FROM conversation_tags t WHERE t.conversation_id = c.id)";
+
+pub(super) fn conversation_from_row(row: &rusqlite::Row) -> rusqlite::Result<DbConversation> {
+    let tags: String = row.get(9)?;
+    Ok(DbConversation {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        title: row.get(2)?,
+        summary: row.get(3)?,
+        created_at: parse_db_timestamp(&row.get::<_, String>(4)?),
+        updated_at: parse_db_timestamp(&row.get::<_, String>(5)?),
+        token_count: row.get(6)?,
+        pinned: row.get(7)?,
+        archived_at: row.get::<_, Option<String>>(8)?.map(|at| parse_db_timestamp(&at)),
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchivedFilter {
+    /// Only conversations that aren't archived
+    #[default]
+    Exclude,
+    Include,
+    Only,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationSort {
+    #[default]
+    UpdatedAt,
+    CreatedAt,
+    Title,
+    TokenCount,
+}
+
+impl ConversationSort {
+    fn column(self) -> &'static str {
+        match self {
+            Self::UpdatedAt => "c.updated_at",
+            Self::CreatedAt => "c.created_at",
+            Self::Title => "c.title COLLATE NOCASE",
+            Self::TokenCount => "c.token_count",
+        }
+    }
+}
+
+/// Which conversations `get_conversations` returns and in what order. Pinned conversations
+/// always come first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationFilter {
+    /// Only conversations carrying every one of these tags
+    pub tags: Vec<String>,
+    pub pinned: Option<bool>,
+    pub archived: ArchivedFilter,
+    pub sort_by: ConversationSort,
+    /// Sort oldest, smallest or A-Z first instead
+    pub ascending: bool,
+}
+
+/// Trimmed, lowercased and deduplicated tags
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || normalized.contains(&tag) {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_CHARS {
+            bail!("Tags can be at most {} characters", MAX_TAG_CHARS);
+        }
+        normalized.push(tag);
+    }
+    if normalized.len() > MAX_TAGS {
+        bail!("A conversation can have at most {} tags", MAX_TAGS);
+    }
+    Ok(normalized)
+}
+
+pub fn list_conversations(
+    conn: &Connection,
+    agent_id: Option<&str>,
+    limit: Option<i32>,
+    filter: &ConversationFilter,
+) -> Result<Vec<DbConversation>> {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+    if let Some(agent_id) = agent_id {
+        params.push(Box::new(agent_id.to_string()));
+        conditions.push(format!("c.agent_id = ?{}", params.len()));
+    }
+    match filter.archived {
+        ArchivedFilter::Exclude => conditions.push("c.archived_at IS NULL".to_string()),
+        ArchivedFilter::Only => conditions.push("c.archived_at IS NOT NULL".to_string()),
+        ArchivedFilter::Include => {}
+    }
+    if let Some(pinned) = filter.pinned {
+        params.push(Box::new(pinned));
+        conditions.push(format!("c.pinned = ?{}", params.len()));
+    }
+    let tags = normalize_tags(&filter.tags)?;
+    if !tags.is_empty() {
+        let placeholders: Vec<String> = tags.iter()
+            .map(|tag| {
+                params.push(Box::new(tag.clone()));
+                format!("?{}", params.len())
+            })
+            .collect();
+        conditions.push(format!(
+            "c.id IN (SELECT conversation_id FROM conversation_tags WHERE tag IN ({}) GROUP BY conversation_id HAVING COUNT(*) = {})",
+            placeholders.join(", "),
+            tags.len()
+        ));
+    }
+
+    let where_clause = if conditions.is_empty() { String::new() } else { format!(" WHERE {}", conditions.join(" AND ")) };
+    let sql = format!(
+        "SELECT {} FROM conversations c{} ORDER BY c.pinned DESC, {} {} LIMIT {}",
+        CONVERSATION_COLUMNS,
+        where_clause,
+        filter.sort_by.column(),
+        if filter.ascending { "ASC" } else { "DESC" },
+        limit.map_or(-1, |limit| limit.max(0)),
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let conversations = stmt.query_map(rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())), conversation_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(conversations)
+}
+
+fn ensure_updated(changed: usize, conversation_id: &str) -> Result<()> {
+    if changed == 0 {
+        bail!("Conversation not found: {}", conversation_id);
+    }
+    Ok(())
+}
+
+/// Replace a conversation's tags, returning them normalized
+pub fn set_tags(conn: &mut Connection, conversation_id: &str, tags: &[String]) -> Result<Vec<String>> {
+    let tags = normalize_tags(tags)?;
+    let tx = conn.transaction()?;
+    let exists = tx.query_row("SELECT 1 FROM conversations WHERE id = ?1", params![conversation_id], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if !exists {
+        bail!("Conversation not found: {}", conversation_id);
+    }
+    tx.execute("DELETE FROM conversation_tags WHERE conversation_id = ?1", params![conversation_id])?;
+    for tag in &tags {
+        tx.execute(
+            "INSERT INTO conversation_tags (conversation_id, tag) VALUES (?1, ?2)",
+            params![conversation_id, tag],
+        )?;
+    }
+    tx.commit()?;
+    Ok(tags)
+}
+
+pub fn set_archived(conn: &Connection, conversation_id: &str, archived: bool) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE conversations SET archived_at = CASE WHEN ?2 THEN COALESCE(archived_at, CURRENT_TIMESTAMP) END WHERE id = ?1",
+        params![conversation_id, archived],
+    )?;
+    ensure_updated(changed, conversation_id)
+}
+
+pub fn set_pinned(conn: &Connection, conversation_id: &str, pinned: bool) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE conversations SET pinned = ?2 WHERE id = ?1",
+        params![conversation_id, pinned],
+    )?;
+    ensure_updated(changed, conversation_id)
+}
+
+// Tauri Commands
+
+/// Replace a conversation's tags; tags are stored lowercased
+#[tauri::command]
+pub async fn set_conversation_tags(
+    conversation_id: String,
+    tags: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<Vec<String>, String> {
+    let tags = set_tags(&mut open_db(&app_handle)?, &conversation_id, &tags)
+        .map_err(|e| format!("Failed to set conversation tags: {}", e))?;
+    info!("Tagged conversation {} with {:?}", conversation_id, tags);
+    Ok(tags)
+}
+
+/// Archive a conversation, or restore it with `archived: false`
+#[tauri::command]
+pub async fn archive_conversation(
+    conversation_id: String,
+    archived: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let archived = archived.unwrap_or(true);
+    set_archived(&open_db(&app_handle)?, &conversation_id, archived)
+        .map_err(|e| format!("Failed to archive conversation: {}", e))?;
+    info!("{} conversation {}", if archived { "Archived" } else { "Restored" }, conversation_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn pin_conversation(
+    conversation_id: String,
+    pinned: bool,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    set_pinned(&open_db(&app_handle)?, &conversation_id, pinned)
+        .map_err(|e| format!("Failed to pin conversation: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    fn ids(conversations: &[DbConversation]) -> Vec<&str> {
+        conversations.iter().map(|c| c.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_filter_and_sort_conversations() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        conn.execute_batch(
+            "INSERT INTO conversations (id, agent_id, title, updated_at, token_count) VALUES
+                ('a', 'coder', 'Alpha', '2024-01-01 10:00:00', 30),
+                ('b', 'coder', 'beta', '2024-01-02 10:00:00', 10),
+                ('c', 'coder', 'Gamma', '2024-01-03 10:00:00', 20),
+                ('d', 'writer', 'Delta', '2024-01-04 10:00:00', 40);",
+        ).unwrap();
+
+        assert_eq!(set_tags(&mut conn, "a", &[" Rust ".to_string(), "rust".to_string(), "work".to_string()]).unwrap(), vec!["rust", "work"]);
+        set_tags(&mut conn, "b", &["rust".to_string()]).unwrap();
+        assert!(set_tags(&mut conn, "missing", &[]).is_err());
+        set_pinned(&conn, "a", true).unwrap();
+        set_archived(&conn, "c", true).unwrap();
+
+        let all = ConversationFilter::default();
+        assert_eq!(ids(&list_conversations(&conn, None, None, &all).unwrap()), vec!["a", "d", "b"]);
+        assert_eq!(ids(&list_conversations(&conn, Some("coder"), Some(1), &all).unwrap()), vec!["a"]);
+
+        let tagged = ConversationFilter { tags: vec!["RUST".to_string(), "work".to_string()], ..Default::default() };
+        let conversations = list_conversations(&conn, None, None, &tagged).unwrap();
+        assert_eq!(ids(&conversations), vec!["a"]);
+        assert!(conversations[0].pinned);
+        assert_eq!(conversations[0].tags, vec!["rust", "work"]);
+
+        let archived = ConversationFilter { archived: ArchivedFilter::Only, ..Default::default() };
+        let conversations = list_conversations(&conn, None, None, &archived).unwrap();
+        assert_eq!(ids(&conversations), vec!["c"]);
+        assert!(conversations[0].archived_at.is_some());
+        set_archived(&conn, "c", false).unwrap();
+
+        let by_title = ConversationFilter { pinned: Some(false), sort_by: ConversationSort::Title, ascending: true, ..Default::default() };
+        assert_eq!(ids(&list_conversations(&conn, None, None, &by_title).unwrap()), vec!["b", "d", "c"]);
+    }
+}
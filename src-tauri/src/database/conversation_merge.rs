@@ -0,0 +1,522 @@
+use super::memory::{cosine_similarity, extract_keywords};
+use super::conversation_list::{conversation_from_row, CONVERSATION_COLUMNS};
+use super::neural_embeddings::NeuralEmbeddingService;
+use super::simple_commands::MemoryState;
+use super::{open_conversation_db, parse_db_timestamp, DbConversation, DbMessage};
+use anyhow::{anyhow, bail, Result};
+use chrono::Duration;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use tauri::{AppHandle, State};
+use tracing::{info, warn};
+
+/// Dimension of the hashed bag-of-words conversation fingerprint
+const FINGERPRINT_DIM: usize = 256;
+
+/// Messages per conversation embedded for duplicate detection; retries share their opening
+const MAX_EMBEDDED_MESSAGES: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateConversationGroup {
+    pub agent_id: String,
+    /// Suggested conversation to keep (the one with the most messages)
+    pub primary_id: String,
+    pub duplicate_ids: Vec<String>,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMergeResult {
+    pub primary_id: String,
+    pub merged_conversations: Vec<String>,
+    pub messages_moved: usize,
+    pub messages_dropped: usize,
+    pub memories_repointed: usize,
+}
+
+fn embedded_messages(messages: &[DbMessage]) -> impl Iterator<Item = &DbMessage> {
+    messages.iter()
+        .filter(|m| m.role != "system" && !m.content.trim().is_empty())
+        .take(MAX_EMBEDDED_MESSAGES)
+}
+
+/// Mean of the agent's embeddings of a conversation's messages, so paraphrased retries land
+/// close together. Expects messages in chronological order.
+pub async fn conversation_embedding(
+    service: &NeuralEmbeddingService,
+    agent_id: &str,
+    messages: &[DbMessage],
+) -> Result<Vec<f32>> {
+    let mut sum: Vec<f32> = Vec::new();
+    let mut count = 0;
+
+    for message in embedded_messages(messages) {
+        let embedding = service.embed_text_for_agent(agent_id, &message.content, None).await?;
+        if sum.is_empty() {
+            sum = vec![0.0; embedding.len()];
+        } else if embedding.len() != sum.len() {
+            bail!("Embedding dimension changed from {} to {}", sum.len(), embedding.len());
+        }
+        for (total, value) in sum.iter_mut().zip(&embedding) {
+            *total += value;
+        }
+        count += 1;
+    }
+
+    for total in &mut sum {
+        *total /= count.max(1) as f32;
+    }
+    Ok(sum)
+}
+
+/// Hashed keyword frequency vector, the fallback when no embedding service is available.
+/// It only matches retries that reuse the same words. Keywords are bucketed by SHA-256 so
+/// fingerprints are the same across builds.
+pub fn conversation_fingerprint(messages: &[DbMessage]) -> Vec<f32> {
+    let mut vector = vec![0.0f32; FINGERPRINT_DIM];
+
+    for message in embedded_messages(messages) {
+        for keyword in extract_keywords(&message.content) {
+            let digest = Sha256::digest(keyword.as_bytes());
+            let bucket = u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+            vector[(bucket % FINGERPRINT_DIM as u64) as usize] += 1.0;
+        }
+    }
+
+    vector
+}
+
+/// One vector per conversation: embeddings when the service can embed every conversation,
+/// otherwise keyword fingerprints for all of them, since the two are not comparable
+async fn conversation_vectors(
+    state: &MemoryState,
+    conversations: &[(DbConversation, Vec<DbMessage>)],
+) -> Result<Vec<Vec<f32>>, String> {
+    let service_lock = state.get_neural_embedding_service().await?;
+    let service = service_lock.lock().await;
+
+    if let Some(service) = service.as_ref() {
+        let mut vectors = Vec::with_capacity(conversations.len());
+        for (conversation, messages) in conversations {
+            match conversation_embedding(service, &conversation.agent_id, messages).await {
+                Ok(vector) => vectors.push(vector),
+                Err(e) => {
+                    warn!("Failed to embed conversation {}: {}", conversation.id, e);
+                    break;
+                }
+            }
+        }
+        if vectors.len() == conversations.len() {
+            return Ok(vectors);
+        }
+    }
+
+    warn!("Comparing conversations by keyword fingerprints instead of embeddings");
+    Ok(conversations.iter().map(|(_, messages)| conversation_fingerprint(messages)).collect())
+}
+
+/// Group conversations of the same agent that were created close together and whose
+/// vectors (one per conversation, in the same order) are similar
+pub fn detect_duplicates(
+    conversations: &[(DbConversation, Vec<DbMessage>)],
+    fingerprints: &[Vec<f32>],
+    similarity_threshold: f32,
+    window: Duration,
+) -> Vec<DuplicateConversationGroup> {
+    debug_assert_eq!(conversations.len(), fingerprints.len());
+
+    // Union-find over candidate pairs
+    let mut parent: Vec<usize> = (0..conversations.len()).collect();
+    fn find(parent: &mut Vec<usize>, i: usize) -> usize {
+        if parent[i] != i {
+            let root = find(parent, parent[i]);
+            parent[i] = root;
+        }
+        parent[i]
+    }
+
+    let mut pair_similarity: Vec<(usize, usize, f32)> = Vec::new();
+    for i in 0..conversations.len() {
+        for j in (i + 1)..conversations.len() {
+            let (a, _) = &conversations[i];
+            let (b, _) = &conversations[j];
+            if a.agent_id != b.agent_id || (a.created_at - b.created_at).abs() > window {
+                continue;
+            }
+
+            let similarity = cosine_similarity(&fingerprints[i], &fingerprints[j]);
+            if similarity >= similarity_threshold {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                parent[rj] = ri;
+                pair_similarity.push((i, j, similarity));
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for i in 0..conversations.len() {
+        let root = find(&mut parent, i);
+        match groups.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((root, vec![i])),
+        }
+    }
+
+    groups.into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(root, members)| {
+            let primary = *members.iter()
+                .max_by_key(|&&i| (conversations[i].1.len(), std::cmp::Reverse(conversations[i].0.created_at)))
+                .unwrap();
+            let similarity = pair_similarity.iter()
+                .filter(|(i, _, _)| find(&mut parent, *i) == root)
+                .map(|(_, _, s)| *s)
+                .fold(f32::MAX, f32::min);
+
+            DuplicateConversationGroup {
+                agent_id: conversations[primary].0.agent_id.clone(),
+                primary_id: conversations[primary].0.id.clone(),
+                duplicate_ids: members.iter()
+                    .filter(|&&i| i != primary)
+                    .map(|&i| conversations[i].0.id.clone())
+                    .collect(),
+                similarity,
+            }
+        })
+        .collect()
+}
+
+/// Order messages chronologically and drop copies of the same message.
+///
+/// A message is a copy when a kept message has the same role, exact content and timestamp;
+/// repeating the same words later is a new message. A row listed twice is kept once and
+/// never reported as dropped, since deleting its id would delete the kept row too.
+/// Returns the messages to keep and the ids of dropped duplicates.
+pub fn splice_messages(mut messages: Vec<DbMessage>) -> (Vec<DbMessage>, Vec<String>) {
+    messages.sort_by_key(|m| m.timestamp);
+
+    let mut kept: Vec<DbMessage> = Vec::new();
+    let mut dropped = Vec::new();
+    let mut seen_ids = HashSet::new();
+    let mut seen_messages = HashSet::new();
+
+    for message in messages {
+        if !seen_ids.insert(message.id.clone()) {
+            continue;
+        }
+        if !seen_messages.insert((message.role.clone(), message.content.clone(), message.timestamp)) {
+            dropped.push(message.id);
+            continue;
+        }
+        kept.push(message);
+    }
+
+    (kept, dropped)
+}
+
+fn load_conversation(conn: &Connection, conversation_id: &str) -> Result<DbConversation> {
+    conn.query_row(
+        &format!("SELECT {} FROM conversations c WHERE c.id = ?1", CONVERSATION_COLUMNS),
+        params![conversation_id],
+        conversation_from_row,
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => anyhow!("Conversation not found: {}", conversation_id),
+        other => other.into(),
+    })
+}
+
+fn load_messages(conn: &Connection, conversation_id: &str) -> Result<Vec<DbMessage>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, conversation_id, role, content, tool_calls, timestamp, tokens FROM messages WHERE conversation_id = ?1 ORDER BY timestamp",
+    )?;
+
+    let messages = stmt.query_map(params![conversation_id], |row| {
+        Ok(DbMessage {
+            id: row.get(0)?,
+            conversation_id: row.get(1)?,
+            role: row.get(2)?,
+            content: row.get(3)?,
+            tool_calls: row.get(4)?,
+            timestamp: parse_db_timestamp(&row.get::<_, String>(5)?),
+            tokens: row.get(6)?,
+        })
+    })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(messages)
+}
+
+fn load_agent_conversations(conn: &Connection, agent_id: Option<&str>) -> Result<Vec<(DbConversation, Vec<DbMessage>)>> {
+    let ids: Vec<String> = match agent_id {
+        Some(agent) => {
+            let mut stmt = conn.prepare("SELECT id FROM conversations WHERE agent_id = ?1")?;
+            let rows = stmt.query_map(params![agent], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        }
+        None => {
+            let mut stmt = conn.prepare("SELECT id FROM conversations")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        }
+    };
+
+    ids.iter()
+        .map(|id| Ok((load_conversation(conn, id)?, load_messages(conn, id)?)))
+        .collect()
+}
+
+fn merge_summaries(primary: Option<String>, others: impl Iterator<Item = Option<String>>) -> Option<String> {
+    let mut seen = HashSet::new();
+    let parts: Vec<String> = primary.into_iter()
+        .chain(others.flatten())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty() && seen.insert(s.clone()))
+        .collect();
+
+    if parts.is_empty() { None } else { Some(parts.join("\n\n")) }
+}
+
+/// Merge duplicate conversations into the primary inside a single transaction
+pub fn merge_conversation_records(
+    conn: &mut Connection,
+    primary_id: &str,
+    duplicate_ids: &[String],
+) -> Result<(DbConversation, usize, usize)> {
+    let primary = load_conversation(conn, primary_id)?;
+
+    let mut duplicates = Vec::new();
+    for id in duplicate_ids {
+        let duplicate = load_conversation(conn, id)?;
+        if duplicate.agent_id != primary.agent_id {
+            return Err(anyhow!("Conversation {} belongs to a different agent", id));
+        }
+        duplicates.push(duplicate);
+    }
+
+    let mut all_messages = load_messages(conn, primary_id)?;
+    let primary_message_ids: HashSet<String> = all_messages.iter().map(|m| m.id.clone()).collect();
+    for id in duplicate_ids {
+        all_messages.extend(load_messages(conn, id)?);
+    }
+
+    let (kept, dropped) = splice_messages(all_messages);
+    let moved = kept.iter().filter(|m| !primary_message_ids.contains(&m.id)).count();
+    let token_count: i32 = kept.iter().filter_map(|m| m.tokens).sum();
+    let summary = merge_summaries(primary.summary.clone(), duplicates.iter().map(|d| d.summary.clone()));
+
+    let tx = conn.transaction()?;
+    for id in &dropped {
+        tx.execute("DELETE FROM messages WHERE id = ?1", params![id])?;
+    }
+    for message in &kept {
+        tx.execute(
+            "UPDATE messages SET conversation_id = ?1 WHERE id = ?2",
+            params![primary_id, message.id],
+        )?;
+    }
+    tx.execute(
+        "UPDATE conversations SET summary = ?1, token_count = ?2 WHERE id = ?3",
+        params![summary, token_count, primary_id],
+    )?;
+    for id in duplicate_ids {
+        // The primary keeps the tags of every conversation merged into it
+        tx.execute(
+            "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag)
+             SELECT ?1, tag FROM conversation_tags WHERE conversation_id = ?2",
+            params![primary_id, id],
+        )?;
+        tx.execute("DELETE FROM conversations WHERE id = ?1", params![id])?;
+    }
+    tx.commit()?;
+
+    Ok((primary, moved, dropped.len()))
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn find_duplicate_conversations(
+    agent_id: Option<String>,
+    similarity_threshold: Option<f32>,
+    window_minutes: Option<i64>,
+    app_handle: AppHandle,
+    state: State<'_, MemoryState>,
+) -> Result<Vec<DuplicateConversationGroup>, String> {
+    let threshold = similarity_threshold.unwrap_or(0.85);
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err("Similarity threshold must be between 0.0 and 1.0".to_string());
+    }
+    let window = Duration::minutes(window_minutes.unwrap_or(10).clamp(1, 24 * 60));
+
+    let conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    let conversations = load_agent_conversations(&conn, agent_id.as_deref())
+        .map_err(|e| format!("Failed to load conversations: {}", e))?;
+
+    let vectors = conversation_vectors(&state, &conversations).await?;
+    let groups = detect_duplicates(&conversations, &vectors, threshold, window);
+    info!("Found {} duplicate conversation groups", groups.len());
+    Ok(groups)
+}
+
+#[tauri::command]
+pub async fn merge_conversations(
+    primary: String,
+    duplicates: Vec<String>,
+    app_handle: AppHandle,
+    state: State<'_, MemoryState>,
+) -> Result<ConversationMergeResult, String> {
+    info!("Merging {} conversations into: {}", duplicates.len(), primary);
+
+    if primary.trim().is_empty() || duplicates.is_empty() {
+        return Err("A primary conversation and at least one duplicate are required".to_string());
+    }
+    if duplicates.iter().any(|d| d == &primary) {
+        return Err("Primary conversation cannot also be a duplicate".to_string());
+    }
+    let unique: HashSet<&String> = duplicates.iter().collect();
+    if unique.len() != duplicates.len() {
+        return Err("Duplicate conversation ids must be unique".to_string());
+    }
+
+    let mut conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    let (conversation, messages_moved, messages_dropped) = merge_conversation_records(&mut conn, &primary, &duplicates)
+        .map_err(|e| format!("Failed to merge conversations: {}", e))?;
+
+    // Repoint memories that referenced the merged conversations
+    let manager = state.get_or_create_manager(conversation.agent_id.clone())?;
+    let memories_repointed = match manager.reassign_conversation_memories(&duplicates, &primary) {
+        Ok(count) => count,
+        Err(e) => {
+            warn!("Failed to repoint memories for {}: {}", conversation.agent_id, e);
+            0
+        }
+    };
+
+    info!(
+        "Merged conversations into {}: {} messages moved, {} retries dropped, {} memories repointed",
+        primary, messages_moved, messages_dropped, memories_repointed
+    );
+
+    Ok(ConversationMergeResult {
+        primary_id: primary,
+        merged_conversations: duplicates,
+        messages_moved,
+        messages_dropped,
+        memories_repointed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn message(id: &str, conversation: &str, role: &str, content: &str, offset_secs: i64) -> DbMessage {
+        DbMessage {
+            id: id.to_string(),
+            conversation_id: conversation.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            timestamp: Utc::now() + Duration::seconds(offset_secs),
+            tokens: Some(10),
+        }
+    }
+
+    fn conversation(id: &str, agent: &str, offset_secs: i64) -> DbConversation {
+        DbConversation {
+            id: id.to_string(),
+            agent_id: agent.to_string(),
+            title: "Chat".to_string(),
+            summary: None,
+            created_at: Utc::now() + Duration::seconds(offset_secs),
+            updated_at: Utc::now(),
+            token_count: 0,
+            tags: Vec::new(),
+            pinned: false,
+            archived_at: None,
+        }
+    }
+
+    #[test]
+    fn test_splice_drops_copies_only() {
+        let question = message("m1", "a", "user", "how do I configure the proxy settings", 0);
+        let copy = DbMessage { id: "m3".to_string(), conversation_id: "b".to_string(), ..question.clone() };
+        let messages = vec![
+            question.clone(),
+            copy,
+            message("m2", "a", "assistant", "open the settings panel", 10),
+            // Asking again later is kept
+            message("m4", "a", "user", "how do I configure the proxy settings", 20),
+            question,
+        ];
+
+        let (kept, dropped) = splice_messages(messages);
+        assert_eq!(kept.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m1", "m2", "m4"]);
+        assert_eq!(dropped, vec!["m3".to_string()]);
+    }
+
+    #[test]
+    fn test_detects_retried_conversation() {
+        let text = "please summarise the quarterly revenue report for finance";
+        let conversations = vec![
+            (conversation("a", "agent", 0), vec![message("1", "a", "user", text, 0)]),
+            (conversation("b", "agent", 30), vec![message("2", "b", "user", text, 30), message("3", "b", "assistant", "revenue summary attached", 40)]),
+            (conversation("c", "agent", 60), vec![message("4", "c", "user", "write a poem about autumn leaves falling", 60)]),
+            (conversation("d", "other", 0), vec![message("5", "d", "user", text, 0)]),
+        ];
+
+        let fingerprints: Vec<Vec<f32>> = conversations.iter().map(|(_, m)| conversation_fingerprint(m)).collect();
+        let groups = detect_duplicates(&conversations, &fingerprints, 0.7, Duration::minutes(10));
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].primary_id, "b");
+        assert_eq!(groups[0].duplicate_ids, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn test_fingerprint_buckets_are_stable() {
+        let fingerprint = conversation_fingerprint(&[message("1", "a", "user", "Revenue", 0)]);
+        // First 8 bytes of sha256("revenue"), big-endian, mod 256
+        assert_eq!(fingerprint.iter().position(|&v| v == 1.0), Some(139));
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_average_the_conversation() {
+        let service = NeuralEmbeddingService::new(None).await.unwrap();
+        let text = "please summarise the quarterly revenue report for finance";
+        let retry = vec![message("1", "a", "user", text, 0), message("2", "a", "system", "ignored", 1)];
+        let original = vec![message("3", "b", "user", text, 30)];
+
+        let a = conversation_embedding(&service, "agent", &retry).await.unwrap();
+        let b = conversation_embedding(&service, "agent", &original).await.unwrap();
+        assert!(!a.is_empty());
+        assert!((cosine_similarity(&a, &b) - 1.0).abs() < 1e-5);
+        assert!(conversation_embedding(&service, "agent", &[]).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_records() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::database::migrations::migrate(&conn, crate::database::migrations::CONVERSATION_MIGRATIONS).unwrap();
+        conn.execute_batch(
+            "INSERT INTO conversations (id, agent_id, title, summary) VALUES ('a', 'agent', 'A', 'first'), ('b', 'agent', 'B', 'second');
+             INSERT INTO messages (id, conversation_id, role, content, timestamp, tokens) VALUES
+                ('m1', 'a', 'user', 'hello there', '2024-01-01 10:00:00', 3),
+                ('m2', 'b', 'user', 'hello there', '2024-01-01 10:00:00', 3),
+                ('m3', 'b', 'assistant', 'hi!', '2024-01-01 10:00:09', 2);",
+        ).unwrap();
+
+        let (_, moved, dropped) = merge_conversation_records(&mut conn, "a", &["b".to_string()]).unwrap();
+        assert_eq!((moved, dropped), (1, 1));
+
+        let remaining = load_messages(&conn, "a").unwrap();
+        assert_eq!(remaining.len(), 2);
+        let merged = load_conversation(&conn, "a").unwrap();
+        assert_eq!(merged.summary.as_deref(), Some("first\n\nsecond"));
+        assert_eq!(merged.token_count, 5);
+        assert!(load_conversation(&conn, "b").is_err());
+    }
+}
@@ -0,0 +1,718 @@
+use super::memory::*;
+use super::memory_anomalies::MEMORY_ANOMALY_MONITOR;
+use super::memory_encryption::key_path;
+use super::pool::block_pool;
+use super::artifacts::collect_artifact_garbage_for;
+use super::open_conversation_db;
+use super::simple_commands::MemoryState;
+use super::simple_memory::SimpleMemoryManager;
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tracing::{info, warn};
+
+/// How long removing an agent's database waits for its connections to come back
+const POOL_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Matches memories of agent ?1, optionally restricted to those tagged ?2 (case-insensitive)
+const MEMORY_SCOPE_FILTER: &str = r#"
+    agent_id = ?1 AND (?2 IS NULL OR EXISTS (
+        SELECT 1 FROM json_each(agent_memories.tags) WHERE lower(json_each.value) = lower(?2)
+    ))
+"#;
+
+/// What to delete for an agent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum PurgeScope {
+    /// Everything stored for the agent
+    All,
+    /// Only memories carrying this tag, plus data derived from them
+    Tag { tag: String },
+}
+
+impl PurgeScope {
+    fn tag(&self) -> Option<&str> {
+        match self {
+            PurgeScope::All => None,
+            PurgeScope::Tag { tag } => Some(tag),
+        }
+    }
+}
+
+/// Rows removed from one memory database
+#[derive(Debug, Clone, Default)]
+pub struct PurgedMemoryRows {
+    pub memory_ids: Vec<String>,
+    pub embeddings: usize,
+    pub access_log_entries: usize,
+    pub cache_entries: usize,
+}
+
+/// Entries removed from the shared knowledge database
+#[derive(Debug, Clone, Default)]
+pub struct PurgedSharedRows {
+    pub nodes: usize,
+    pub edges: usize,
+    pub knowledge_deleted: usize,
+    pub knowledge_detached: usize,
+    pub interactions: usize,
+}
+
+/// Rows removed from the conversations database
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PurgedConversationRows {
+    pub conversations: usize,
+    pub messages: usize,
+    pub message_repairs: usize,
+    pub runs: usize,
+    pub config_versions: usize,
+    pub sessions: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DataPurgeReport {
+    pub agent_id: String,
+    pub scope: Option<PurgeScope>,
+    pub memories_deleted: usize,
+    pub embeddings_deleted: usize,
+    pub access_log_entries_deleted: usize,
+    pub embedding_cache_entries_deleted: usize,
+    pub graph_nodes_deleted: usize,
+    pub graph_edges_deleted: usize,
+    pub edge_suggestions_deleted: usize,
+    pub shared_knowledge_deleted: usize,
+    /// Shared knowledge kept because other agents also contributed it, with this agent removed as a source
+    pub shared_knowledge_detached: usize,
+    pub interactions_deleted: usize,
+    pub conversations_deleted: usize,
+    pub messages_deleted: usize,
+    #[serde(default)]
+    pub message_repairs_deleted: usize,
+    /// Run traces with their tool calls
+    #[serde(default)]
+    pub runs_deleted: usize,
+    /// Earlier configurations (system prompts included); the current definition is kept
+    #[serde(default)]
+    pub config_versions_deleted: usize,
+    #[serde(default)]
+    pub sessions_deleted: usize,
+    /// Attachment content left without any conversation
+    pub artifact_blobs_deleted: usize,
+    pub backups_deleted: Vec<String>,
+    /// Backups shared with other data that had the matching memories removed in place
+    pub backups_scrubbed: Vec<String>,
+    pub injection_report_cleared: bool,
+    /// True when a re-check after deletion found nothing left in scope
+    pub verified: bool,
+    pub verification_issues: Vec<String>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Overwrite freed pages and flush the WAL so deleted rows do not linger on disk
+fn compact(conn: &Connection) -> Result<()> {
+    conn.execute_batch("VACUUM;")?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(())
+}
+
+/// Memories in scope that are still present
+pub fn count_scoped_memories(conn: &Connection, agent_id: &str, tag: Option<&str>) -> Result<usize> {
+    let count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM agent_memories WHERE {}", MEMORY_SCOPE_FILTER),
+        params![agent_id, tag],
+        |row| row.get(0),
+    )?;
+    Ok(count as usize)
+}
+
+/// Delete an agent's memories (optionally by tag) with their access log and cached embeddings
+pub fn purge_memory_db(conn: &mut Connection, agent_id: &str, tag: Option<&str>) -> Result<PurgedMemoryRows> {
+    conn.pragma_update(None, "secure_delete", true)?;
+    let tx = conn.transaction()?;
+
+    let rows: Vec<(String, String, bool)> = {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT id, content, embedding IS NOT NULL FROM agent_memories WHERE {}",
+            MEMORY_SCOPE_FILTER
+        ))?;
+        let rows = stmt.query_map(params![agent_id, tag], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    let mut purged = PurgedMemoryRows::default();
+    for (id, content, has_embedding) in &rows {
+        purged.access_log_entries += tx.execute("DELETE FROM memory_access_log WHERE memory_id = ?1", params![id])?;
+        tx.execute("DELETE FROM memory_anomalies WHERE memory_id = ?1", params![id])?;
+        tx.execute("DELETE FROM memory_consolidations WHERE semantic_id = ?1 OR episodic_id = ?1", params![id])?;
+        if tag.is_some() {
+            purged.cache_entries += tx.execute("DELETE FROM embedding_cache WHERE content = ?1", params![content])?;
+        }
+        tx.execute("DELETE FROM agent_memories WHERE id = ?1", params![id])?;
+        if *has_embedding {
+            purged.embeddings += 1;
+        }
+        purged.memory_ids.push(id.clone());
+    }
+
+    if tag.is_none() {
+        purged.access_log_entries += tx.execute("DELETE FROM memory_access_log WHERE agent_id = ?1", params![agent_id])?;
+        tx.execute("DELETE FROM memory_anomalies WHERE agent_id = ?1", params![agent_id])?;
+        tx.execute("DELETE FROM embedding_evaluations WHERE agent_id = ?1", params![agent_id])?;
+        tx.execute("DELETE FROM memory_access_grants WHERE owner_agent = ?1", params![agent_id])?;
+        tx.execute("DELETE FROM memory_quota WHERE agent_id = ?1", params![agent_id])?;
+        // Agent databases are per-agent, so the whole embedding cache belongs to it
+        purged.cache_entries += tx.execute("DELETE FROM embedding_cache", [])?;
+    }
+
+    tx.commit()?;
+    compact(conn)?;
+    Ok(purged)
+}
+
+/// Remove graph nodes for the purged memories and, for a full purge, everything attributed to the agent
+pub fn purge_shared_graph(conn: &mut Connection, agent_id: &str, memory_ids: &[String], whole_agent: bool) -> Result<PurgedSharedRows> {
+    conn.pragma_update(None, "secure_delete", true)?;
+    let tx = conn.transaction()?;
+    let mut purged = PurgedSharedRows::default();
+
+    let mut node_ids: HashSet<String> = HashSet::new();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT id FROM knowledge_nodes WHERE id = ?1 OR id = 'memory_' || ?1 OR json_extract(properties, '$.memory_id') = ?1"
+        )?;
+        for memory_id in memory_ids {
+            let ids = stmt.query_map(params![memory_id], |row| row.get::<_, String>(0))?;
+            node_ids.extend(ids.collect::<rusqlite::Result<Vec<_>>>()?);
+        }
+
+        if whole_agent {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM knowledge_nodes WHERE json_extract(properties, '$.agent_id') = ?1 OR (node_type = 'Agent' AND (name = ?1 OR id = ?1))"
+            )?;
+            let ids = stmt.query_map(params![agent_id], |row| row.get::<_, String>(0))?;
+            node_ids.extend(ids.collect::<rusqlite::Result<Vec<_>>>()?);
+        }
+    }
+
+    for node_id in &node_ids {
+        purged.edges += tx.execute("DELETE FROM knowledge_edges WHERE from_node = ?1 OR to_node = ?1", params![node_id])?;
+        purged.nodes += tx.execute("DELETE FROM knowledge_nodes WHERE id = ?1", params![node_id])?;
+    }
+
+    if whole_agent {
+        purged.edges += tx.execute(
+            "DELETE FROM knowledge_edges WHERE json_extract(properties, '$.agent_id') = ?1",
+            params![agent_id],
+        )?;
+
+        let sourced: Vec<(String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, source_agents FROM shared_knowledge WHERE EXISTS (SELECT 1 FROM json_each(shared_knowledge.source_agents) WHERE json_each.value = ?1)"
+            )?;
+            let rows = stmt.query_map(params![agent_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for (id, sources_json) in sourced {
+            let sources: Vec<String> = serde_json::from_str(&sources_json).unwrap_or_default();
+            let remaining: Vec<&String> = sources.iter().filter(|s| s.as_str() != agent_id).collect();
+            if remaining.is_empty() {
+                tx.execute("DELETE FROM shared_knowledge_conflicts WHERE knowledge_id = ?1", params![id])?;
+                tx.execute(
+                    "DELETE FROM shared_knowledge_contradictions WHERE knowledge_id = ?1 OR contradicting_id = ?1",
+                    params![id],
+                )?;
+                purged.knowledge_deleted += tx.execute("DELETE FROM shared_knowledge WHERE id = ?1", params![id])?;
+            } else {
+                purged.knowledge_detached += tx.execute(
+                    "UPDATE shared_knowledge SET source_agents = ?1 WHERE id = ?2",
+                    params![serde_json::to_string(&remaining)?, id],
+                )?;
+            }
+        }
+
+        tx.execute("DELETE FROM shared_knowledge_conflicts WHERE agent_id = ?1", params![agent_id])?;
+
+        purged.interactions += tx.execute(
+            "DELETE FROM agent_interactions WHERE agent1_id = ?1 OR agent2_id = ?1",
+            params![agent_id],
+        )?;
+    }
+
+    // The graph change log and snapshots hold copies of purged rows, including the deletes just made
+    for node_id in &node_ids {
+        tx.execute(
+            "DELETE FROM knowledge_graph_history WHERE entity_id = ?1 OR json_extract(data, '$.from_node') = ?1 OR json_extract(data, '$.to_node') = ?1",
+            params![node_id],
+        )?;
+        tx.execute("DELETE FROM knowledge_graph_snapshots WHERE instr(data, '\"' || ?1 || '\"') > 0", params![node_id])?;
+    }
+    if whole_agent {
+        tx.execute(
+            "DELETE FROM knowledge_graph_history WHERE json_extract(data, '$.properties.agent_id') = ?1",
+            params![agent_id],
+        )?;
+        tx.execute("DELETE FROM knowledge_graph_snapshots WHERE agent_id = ?1", params![agent_id])?;
+    }
+
+    tx.commit()?;
+    compact(conn)?;
+    Ok(purged)
+}
+
+/// Delete everything the conversations database holds for an agent: its conversations with
+/// their messages, repairs, revisions, tags and attachment rows, its run traces and the MCP
+/// calls made during them, its sessions and its configuration history. The current definition
+/// in `agent_settings` is kept; deleting the agent removes it.
+///
+/// Child rows are deleted explicitly because foreign keys are not enforced on every connection.
+pub fn purge_conversations(conn: &mut Connection, agent_id: &str) -> Result<PurgedConversationRows> {
+    conn.pragma_update(None, "secure_delete", true)?;
+    let tx = conn.transaction()?;
+    let mut purged = PurgedConversationRows::default();
+
+    const AGENT_CONVERSATIONS: &str = "SELECT id FROM conversations WHERE agent_id = ?1";
+    purged.message_repairs = tx.execute(
+        &format!(
+            "DELETE FROM message_repairs WHERE message_id IN (SELECT id FROM messages WHERE conversation_id IN ({}))",
+            AGENT_CONVERSATIONS
+        ),
+        params![agent_id],
+    )?;
+    for table in ["message_revisions", "conversation_tags", "artifacts"] {
+        tx.execute(
+            &format!("DELETE FROM {} WHERE conversation_id IN ({})", table, AGENT_CONVERSATIONS),
+            params![agent_id],
+        )?;
+    }
+    purged.messages = tx.execute(
+        &format!("DELETE FROM messages WHERE conversation_id IN ({})", AGENT_CONVERSATIONS),
+        params![agent_id],
+    )?;
+    purged.sessions = tx.execute("DELETE FROM agent_sessions WHERE agent_id = ?1", params![agent_id])?;
+    purged.conversations = tx.execute("DELETE FROM conversations WHERE agent_id = ?1", params![agent_id])?;
+
+    const AGENT_RUNS: &str = "SELECT id FROM agent_runs WHERE agent_id = ?1";
+    tx.execute(&format!("DELETE FROM mcp_tool_calls WHERE run_id IN ({})", AGENT_RUNS), params![agent_id])?;
+    tx.execute(&format!("DELETE FROM agent_run_tool_calls WHERE run_id IN ({})", AGENT_RUNS), params![agent_id])?;
+    purged.runs = tx.execute("DELETE FROM agent_runs WHERE agent_id = ?1", params![agent_id])?;
+    purged.config_versions = tx.execute("DELETE FROM agent_config_versions WHERE agent_id = ?1", params![agent_id])?;
+
+    tx.commit()?;
+    Ok(purged)
+}
+
+fn remove_db_file(path: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(path)?;
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = path.with_file_name(format!("{}{}", path.file_name().unwrap_or_default().to_string_lossy(), suffix));
+        if sidecar.exists() {
+            std::fs::remove_file(sidecar)?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete every memory database of the agent, in all workspaces, with its WAL files and sealed
+/// key. Each database's pool is closed first so no write lands in a file being removed.
+pub fn remove_agent_databases(agent_id: &str) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for database in SimpleMemoryManager::list_agent_databases()?.into_iter().filter(|d| d.agent_id == agent_id) {
+        remove_memory_database(&database.path)?;
+        removed.push(database.path);
+    }
+    Ok(removed)
+}
+
+fn remove_memory_database(path: &Path) -> Result<()> {
+    let _block = block_pool(path, POOL_DRAIN_TIMEOUT)?;
+    remove_db_file(path)?;
+    let key = key_path(path);
+    if key.exists() {
+        std::fs::remove_file(key)?;
+    }
+    Ok(())
+}
+
+/// Delete or scrub memory backups holding the agent's data; returns (deleted, scrubbed) paths
+pub fn purge_backups(backup_dir: &Path, agent_id: &str, tag: Option<&str>) -> Result<(Vec<String>, Vec<String>)> {
+    let mut deleted = Vec::new();
+    let mut scrubbed = Vec::new();
+    if !backup_dir.is_dir() {
+        return Ok((deleted, scrubbed));
+    }
+
+    let named_prefix = format!("agent_{}_backup_", agent_id);
+    for entry in std::fs::read_dir(backup_dir)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().map_or(true, |ext| ext != "db") {
+            continue;
+        }
+
+        let mut conn = Connection::open(&path)?;
+        let Some(total) = conn.query_row("SELECT COUNT(*) FROM agent_memories", [], |row| row.get::<_, i64>(0))
+            .optional()
+            .ok()
+            .flatten() else { continue };
+        let owned = count_scoped_memories(&conn, agent_id, None)? as i64;
+        let named = path.file_name().map_or(false, |n| n.to_string_lossy().starts_with(&named_prefix));
+
+        if owned == 0 && !named {
+            continue;
+        }
+
+        if tag.is_none() && owned == total {
+            drop(conn);
+            remove_db_file(&path)?;
+            deleted.push(path.to_string_lossy().to_string());
+        } else if count_scoped_memories(&conn, agent_id, tag)? > 0 {
+            purge_memory_db(&mut conn, agent_id, tag)?;
+            scrubbed.push(path.to_string_lossy().to_string());
+        }
+    }
+
+    Ok((deleted, scrubbed))
+}
+
+fn backup_directory() -> Option<std::path::PathBuf> {
+    dirs::home_dir().map(|home| home.join(".agent-memory").join("backups"))
+}
+
+/// Permanently delete an agent's data (or only data tagged `tag`) and report what was removed
+#[tauri::command]
+pub async fn purge_agent_data(
+    agent_id: String,
+    scope: PurgeScope,
+    app_handle: AppHandle,
+    state: State<'_, MemoryState>,
+) -> Result<DataPurgeReport, String> {
+    info!("Purging data for agent {} ({:?})", agent_id, scope);
+
+    // Phase 1: Input Validation
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    if let Some(tag) = scope.tag() {
+        MemoryValidator::validate_tags(&[tag.to_string()])
+            .map_err(|e| e.to_string())?;
+    }
+
+    // Phase 2: Security Middleware
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "memory_operations",
+        &[agent_id.clone()],
+        &[]
+    ).await?;
+    let agent_id = validation_result.sanitized_inputs[0].clone();
+    let tag = scope.tag().map(|t| t.trim().to_string());
+
+    // Phase 3: Business Logic
+    // The agent has a memory database in every workspace it was used in
+    let manager = state.get_or_create_manager(agent_id.clone())?;
+    let mut managers = vec![manager.clone()];
+    let agent_databases = SimpleMemoryManager::list_agent_databases()
+        .map_err(|e| format!("Failed to list agent databases: {}", e))?;
+    for database in agent_databases {
+        if database.agent_id != agent_id || &database.path == manager.get_agent_db_path() {
+            continue;
+        }
+        let other = SimpleMemoryManager::for_workspace(agent_id.clone(), &database.workspace)
+            .and_then(|other| other.initialize().map(|_| other))
+            .map_err(|e| format!("Failed to open agent database in workspace {}: {}", database.workspace, e))?;
+        managers.push(other);
+    }
+    let mut report = DataPurgeReport {
+        agent_id: agent_id.clone(),
+        scope: Some(scope.clone()),
+        ..Default::default()
+    };
+
+    // Load the memories first so embeddings cached from their content can be evicted
+    let mut doomed: Vec<AgentMemory> = Vec::new();
+    for manager in &managers {
+        doomed.extend(manager.search_memories(&MemoryQuery {
+            agent_id: Some(agent_id.clone()),
+            memory_types: None,
+            content_search: None,
+            tags: None,
+            embedding: None,
+            similarity_threshold: None,
+            similarity_metric: SimilarityMetric::default(),
+            limit: None,
+            offset: None,
+            time_range: None,
+            tiers: None,
+            fusion: None,
+        }).map_err(|e| format!("Failed to load memories: {}", e))?
+            .into_iter()
+            .map(|result| result.memory)
+            .filter(|m| tag.as_ref().is_none_or(|t| m.tags.iter().any(|mt| mt.eq_ignore_ascii_case(t)))));
+    }
+
+    let mut rows = PurgedMemoryRows::default();
+    for manager in &managers {
+        let mut conn = manager.agent_connection()
+            .map_err(|e| format!("Failed to open agent database: {}", e))?;
+        let purged = purge_memory_db(&mut conn, &agent_id, tag.as_deref())
+            .map_err(|e| format!("Failed to delete memories: {}", e))?;
+        rows.memory_ids.extend(purged.memory_ids);
+        rows.embeddings += purged.embeddings;
+        rows.access_log_entries += purged.access_log_entries;
+        rows.cache_entries += purged.cache_entries;
+    }
+    report.memories_deleted = rows.memory_ids.len();
+    report.embeddings_deleted = rows.embeddings;
+    report.access_log_entries_deleted = rows.access_log_entries;
+    report.embedding_cache_entries_deleted = rows.cache_entries;
+
+    // In-memory neural state; services that were never started hold nothing
+    let purged_ids: HashSet<String> = rows.memory_ids.iter().cloned().collect();
+    {
+        let graph_handle = state.neural_graph_handle();
+        let mut graph = graph_handle.lock().await;
+        if let Some(graph) = graph.as_mut() {
+            let (nodes, edges, suggestions) = graph.remove_agent_nodes(
+                &agent_id,
+                if tag.is_some() { Some(&purged_ids) } else { None },
+            ).await;
+            report.graph_nodes_deleted += nodes;
+            report.graph_edges_deleted += edges;
+            report.edge_suggestions_deleted += suggestions;
+            report.embedding_cache_entries_deleted += graph.evict_memory_embeddings(&doomed).await;
+        }
+    }
+    {
+        let service_handle = state.neural_embedding_handle();
+        let service = service_handle.lock().await;
+        if let Some(service) = service.as_ref() {
+            report.embedding_cache_entries_deleted += service.evict_memories(&doomed).await;
+        }
+    }
+    // The anomaly detector has learned from the purged content
+    MEMORY_ANOMALY_MONITOR.forget(&agent_id);
+
+    let mut shared_conn = manager.shared_connection()
+        .map_err(|e| format!("Failed to open shared knowledge database: {}", e))?;
+    let shared = purge_shared_graph(&mut shared_conn, &agent_id, &rows.memory_ids, tag.is_none())
+        .map_err(|e| format!("Failed to delete graph entries: {}", e))?;
+    report.graph_nodes_deleted += shared.nodes;
+    report.graph_edges_deleted += shared.edges;
+    report.shared_knowledge_deleted = shared.knowledge_deleted;
+    report.shared_knowledge_detached = shared.knowledge_detached;
+    report.interactions_deleted = shared.interactions;
+
+    // Conversations carry no tags, so only a full purge removes them
+    if tag.is_none() {
+        match open_conversation_db(&app_handle).and_then(|mut c| purge_conversations(&mut c, &agent_id)) {
+            Ok(rows) => {
+                report.conversations_deleted = rows.conversations;
+                report.messages_deleted = rows.messages;
+                report.message_repairs_deleted = rows.message_repairs;
+                report.runs_deleted = rows.runs;
+                report.config_versions_deleted = rows.config_versions;
+                report.sessions_deleted = rows.sessions;
+            }
+            Err(e) => {
+                warn!("Failed to delete conversations for {}: {}", agent_id, e);
+                report.verification_issues.push(format!("Conversations not deleted: {}", e));
+            }
+        }
+        // The conversations' attachments went with them; their content goes right away
+        match collect_artifact_garbage_for(&app_handle, Duration::ZERO) {
+            Ok(gc) => report.artifact_blobs_deleted = gc.blobs_removed,
+            Err(e) => report.verification_issues.push(format!("Attachment content not deleted: {}", e)),
+        }
+    }
+
+    if let Some(dir) = backup_directory() {
+        match purge_backups(&dir, &agent_id, tag.as_deref()) {
+            Ok((deleted, scrubbed)) => {
+                report.backups_deleted = deleted;
+                report.backups_scrubbed = scrubbed;
+            }
+            Err(e) => {
+                warn!("Failed to purge backups for {}: {}", agent_id, e);
+                report.verification_issues.push(format!("Backups not purged: {}", e));
+            }
+        }
+    }
+
+    report.injection_report_cleared = state.clear_injection_report(&agent_id);
+
+    // Verification: re-read every store that can be checked from disk
+    for manager in &managers {
+        match manager.agent_connection().and_then(|conn| count_scoped_memories(&conn, &agent_id, tag.as_deref())) {
+            Ok(0) => {}
+            Ok(n) => report.verification_issues.push(format!("{} memories still present", n)),
+            Err(e) => report.verification_issues.push(format!("Could not verify memories: {}", e)),
+        }
+    }
+    if tag.is_none() {
+        if let Ok(conn) = open_conversation_db(&app_handle) {
+            for (table, label) in [
+                ("conversations", "conversations"),
+                ("agent_runs", "runs"),
+                ("agent_config_versions", "configuration versions"),
+                ("agent_sessions", "sessions"),
+            ] {
+                let remaining: i64 = conn.query_row(
+                    &format!("SELECT COUNT(*) FROM {} WHERE agent_id = ?1", table),
+                    params![agent_id],
+                    |row| row.get(0),
+                ).unwrap_or(-1);
+                if remaining != 0 {
+                    report.verification_issues.push(format!("{} {} still present", remaining, label));
+                }
+            }
+        }
+    }
+    for path in &report.backups_scrubbed {
+        let remaining = Connection::open(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|c| count_scoped_memories(&c, &agent_id, tag.as_deref()));
+        if !matches!(remaining, Ok(0)) {
+            report.verification_issues.push(format!("Backup {} still holds matching memories", path));
+        }
+    }
+
+    report.verified = report.verification_issues.is_empty();
+    report.completed_at = Some(chrono::Utc::now());
+
+    info!(
+        "Purged agent {}: {} memories, {} graph nodes, {} conversations, {} backups (verified: {})",
+        agent_id, report.memories_deleted, report.graph_nodes_deleted,
+        report.conversations_deleted, report.backups_deleted.len(), report.verified
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS, CONVERSATION_MIGRATIONS};
+
+    fn memory_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        conn
+    }
+
+    fn insert_memory(conn: &Connection, id: &str, agent_id: &str, content: &str, tags: &[&str]) {
+        conn.execute(
+            "INSERT INTO agent_memories (id, agent_id, memory_type, content, tags, embedding) VALUES (?1, ?2, 'Task', ?3, ?4, x'00')",
+            params![id, agent_id, content, serde_json::to_string(tags).unwrap()],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO memory_access_log (id, memory_id, agent_id, access_type) VALUES (?1, ?2, ?3, 'Write')",
+            params![format!("log-{}", id), id, agent_id],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO embedding_cache (content_hash, content, embedding) VALUES (?1, ?2, x'00')",
+            params![format!("hash-{}", id), content],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_purge_memories_by_tag_then_all() {
+        let mut conn = memory_db();
+        insert_memory(&conn, "m1", "agent_a", "home address", &["PII", "contact"]);
+        insert_memory(&conn, "m2", "agent_a", "likes rust", &["prefs"]);
+        insert_memory(&conn, "m3", "agent_b", "other agent", &["pii"]);
+
+        let tagged = purge_memory_db(&mut conn, "agent_a", Some("pii")).unwrap();
+        assert_eq!(tagged.memory_ids, vec!["m1"]);
+        assert_eq!(tagged.embeddings, 1);
+        assert_eq!(tagged.access_log_entries, 1);
+        assert_eq!(tagged.cache_entries, 1);
+        assert_eq!(count_scoped_memories(&conn, "agent_a", None).unwrap(), 1);
+        assert_eq!(count_scoped_memories(&conn, "agent_b", Some("PII")).unwrap(), 1);
+
+        let all = purge_memory_db(&mut conn, "agent_a", None).unwrap();
+        assert_eq!(all.memory_ids, vec!["m2"]);
+        assert_eq!(count_scoped_memories(&conn, "agent_a", None).unwrap(), 0);
+        assert_eq!(count_scoped_memories(&conn, "agent_b", None).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_removing_a_memory_database_takes_its_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent_a.db");
+        crate::database::pool::connection(&path).unwrap()
+            .execute_batch("CREATE TABLE items (id INTEGER PRIMARY KEY); INSERT INTO items VALUES (1);").unwrap();
+        std::fs::write(key_path(&path), "sealed").unwrap();
+
+        remove_memory_database(&path).unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        // The pool reopens, on a fresh database
+        let conn = crate::database::pool::connection(&path).unwrap();
+        let tables: i64 = conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get(0)).unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    #[test]
+    fn test_purge_shared_graph_and_conversations() {
+        let mut shared = memory_db();
+        shared.execute_batch(r#"
+            INSERT INTO knowledge_nodes (id, node_type, name, properties) VALUES
+                ('memory_m1', 'Memory', 'm1', '{}'),
+                ('n2', 'Concept', 'owned', '{"agent_id":"agent_a"}'),
+                ('n3', 'Concept', 'other', '{"agent_id":"agent_b"}');
+            INSERT INTO knowledge_edges (id, from_node, to_node, relationship_type) VALUES
+                ('e1', 'memory_m1', 'n3', 'Similar'),
+                ('e2', 'n3', 'n3', 'Similar');
+            INSERT INTO shared_knowledge (id, knowledge_type, title, content, source_agents) VALUES
+                ('k1', 'Fact', 'solo', 'x', '["agent_a"]'),
+                ('k2', 'Fact', 'joint', 'y', '["agent_a","agent_b"]');
+            INSERT INTO agent_interactions (id, agent1_id, agent2_id, interaction_type, context) VALUES
+                ('i1', 'agent_b', 'agent_a', 'Collaboration', 'ctx');
+        "#).unwrap();
+
+        let purged = purge_shared_graph(&mut shared, "agent_a", &["m1".to_string()], true).unwrap();
+        assert_eq!((purged.nodes, purged.edges), (2, 1));
+        assert_eq!((purged.knowledge_deleted, purged.knowledge_detached), (1, 1));
+        assert_eq!(purged.interactions, 1);
+        let sources: String = shared.query_row("SELECT source_agents FROM shared_knowledge WHERE id = 'k2'", [], |r| r.get(0)).unwrap();
+        assert_eq!(sources, r#"["agent_b"]"#);
+        let history: i64 = shared.query_row(
+            "SELECT COUNT(*) FROM knowledge_graph_history WHERE entity_id IN ('memory_m1', 'n2', 'e1')", [], |r| r.get(0),
+        ).unwrap();
+        assert_eq!(history, 0);
+
+        let mut conversations = Connection::open_in_memory().unwrap();
+        migrate(&conversations, CONVERSATION_MIGRATIONS).unwrap();
+        conversations.execute_batch(r#"
+            INSERT INTO conversations (id, agent_id, title) VALUES ('c1', 'agent_a', 't'), ('c2', 'agent_b', 't');
+            INSERT INTO messages (id, conversation_id, role, content) VALUES
+                ('x1', 'c1', 'user', 'hi'), ('x2', 'c1', 'assistant', 'hello'), ('x3', 'c2', 'user', 'hey');
+            INSERT INTO message_repairs (id, message_id, attempt, valid, issues) VALUES
+                ('r1', 'x2', 1, 0, '[]'), ('r2', 'x3', 1, 0, '[]');
+            INSERT INTO agent_runs (id, agent_id, input) VALUES ('run1', 'agent_a', 'go'), ('run2', 'agent_b', 'go');
+            INSERT INTO agent_run_tool_calls (id, run_id, sequence, tool_name, arguments) VALUES ('t1', 'run1', 1, 'read', '{}');
+            INSERT INTO mcp_tool_calls (id, server_id, tool_name, run_id, arguments, arguments_hash, success)
+                VALUES ('m1', 'fs', 'read', 'run1', '{}', 'h', 1);
+            INSERT INTO agent_config_versions (id, agent_id, version, configuration) VALUES ('v1', 'agent_a', 1, '{}');
+            INSERT INTO agent_sessions (id, agent_id, conversation_id) VALUES ('s1', 'agent_a', 'c1');
+        "#).unwrap();
+        let purged = purge_conversations(&mut conversations, "agent_a").unwrap();
+        assert_eq!(purged, PurgedConversationRows {
+            conversations: 1,
+            messages: 2,
+            message_repairs: 1,
+            runs: 1,
+            config_versions: 1,
+            sessions: 1,
+        });
+        for table in ["agent_run_tool_calls", "mcp_tool_calls"] {
+            let remaining: i64 = conversations.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0)).unwrap();
+            assert_eq!(remaining, 0, "{} not purged", table);
+        }
+        let other: i64 = conversations.query_row("SELECT COUNT(*) FROM message_repairs", [], |r| r.get(0)).unwrap();
+        assert_eq!(other, 1);
+    }
+}
@@ -0,0 +1,160 @@
+//! Bounded least-recently-used cache for computed embeddings, with hit, miss and
+//! eviction counters for `EmbeddingStats`.
+
+use std::collections::{BTreeMap, HashMap};
+
+pub struct EmbeddingCache {
+    capacity: usize,
+    /// Embedding and the tick it was last used at
+    entries: HashMap<String, (Vec<f32>, u64)>,
+    /// Keys by last-use tick, least recent first
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl EmbeddingCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Cached embedding for `key`, marking it most recently used
+    pub fn get(&mut self, key: &str) -> Option<Vec<f32>> {
+        self.tick += 1;
+        match self.entries.get_mut(key) {
+            Some((embedding, last_used)) => {
+                self.hits += 1;
+                self.recency.remove(last_used);
+                *last_used = self.tick;
+                self.recency.insert(self.tick, key.to_string());
+                Some(embedding.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Cache an embedding, evicting the least recently used entries to stay within capacity
+    pub fn insert(&mut self, key: String, embedding: Vec<f32>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, last_used)) = self.entries.get(&key) {
+            self.recency.remove(last_used);
+        } else {
+            while self.entries.len() >= self.capacity {
+                let Some((_, oldest)) = self.recency.pop_first() else {
+                    break;
+                };
+                self.entries.remove(&oldest);
+                self.evictions += 1;
+            }
+        }
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (embedding, self.tick));
+    }
+
+    /// Drop one entry because it is stale; not counted as an eviction
+    pub fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some((_, last_used)) => {
+                self.recency.remove(&last_used);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop every entry, keeping the counters
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    pub fn reset_counters(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+        self.evictions = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Share of lookups served from the cache, 0 before any lookup
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f32 / lookups as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = EmbeddingCache::new(2);
+        cache.insert("a".to_string(), vec![1.0]);
+        cache.insert("b".to_string(), vec![2.0]);
+        // Reading "a" makes "b" the least recently used
+        assert_eq!(cache.get("a"), Some(vec![1.0]));
+        cache.insert("c".to_string(), vec![3.0]);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(vec![1.0]));
+        assert_eq!(cache.get("c"), Some(vec![3.0]));
+        assert_eq!((cache.hits(), cache.misses(), cache.evictions()), (3, 1, 1));
+        assert_eq!(cache.hit_rate(), 0.75);
+    }
+
+    #[test]
+    fn test_reinsert_and_remove_do_not_evict() {
+        let mut cache = EmbeddingCache::new(2);
+        cache.insert("a".to_string(), vec![1.0]);
+        cache.insert("b".to_string(), vec![2.0]);
+        cache.insert("a".to_string(), vec![1.5]);
+        assert!(cache.remove("b"));
+        cache.insert("c".to_string(), vec![3.0]);
+
+        assert_eq!(cache.evictions(), 0);
+        assert_eq!(cache.get("a"), Some(vec![1.5]));
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.hits(), 1);
+    }
+}
@@ -0,0 +1,359 @@
+//! A/B comparison of two embedding providers on a sample of an agent's memories, to help
+//! choose one before running a migration. Memories sharing a tag count as relevant to each
+//! other; each provider is scored on how many of those it ranks among a memory's nearest
+//! neighbours.
+
+use super::embedding_providers::EmbeddingProviderConfig;
+use super::embeddings::SimilarityMetric;
+use super::memory::{AgentMemory, MemoryQuery, MemoryType};
+use super::neural_embeddings::{cosine_similarity, NeuralEmbeddingService};
+use super::parse_db_timestamp;
+use super::simple_commands::MemoryState;
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rand::seq::SliceRandom;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Instant;
+use tauri::State;
+use tracing::info;
+
+const DEFAULT_SAMPLE_SIZE: usize = 200;
+const MAX_SAMPLE_SIZE: usize = 2000;
+
+/// Tagged memories needed for the comparison to mean anything
+const MIN_SAMPLE_SIZE: usize = 10;
+
+/// Most memories considered before sampling, newest first
+const MAX_CANDIDATE_MEMORIES: usize = 10_000;
+
+/// Neighbours retrieved per query memory
+const RECALL_K: usize = 10;
+
+/// Recall difference below which neither provider is recommended
+const RECALL_TIE_MARGIN: f32 = 0.01;
+
+const DEFAULT_LIST_LIMIT: usize = 20;
+const MAX_LIST_LIMIT: usize = 100;
+
+/// Retrieval quality of one provider over the sample
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderEvaluation {
+    pub provider: EmbeddingProviderConfig,
+    pub provider_id: String,
+    /// Mean share of a memory's tag-sharing memories found in its top k, out of at most k
+    pub recall_at_k: f32,
+    /// Mean reciprocal rank of the first tag-sharing memory
+    pub mean_reciprocal_rank: f32,
+    pub dimensions: usize,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingEvaluationReport {
+    pub id: String,
+    pub agent_id: String,
+    pub k: usize,
+    pub sample_size: usize,
+    /// Sampled memories with at least one tag-sharing memory in the sample
+    pub query_count: usize,
+    pub provider_a: ProviderEvaluation,
+    pub provider_b: ProviderEvaluation,
+    /// Provider id with the better recall, unless they are within the tie margin
+    pub recommended: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Recall@k, mean reciprocal rank and the number of queries scored. Each memory is a query;
+/// the others sharing one of its tags are the relevant results.
+pub fn retrieval_metrics(embeddings: &[Vec<f32>], tags: &[HashSet<String>], k: usize) -> (f32, f32, usize) {
+    let mut recall_total = 0.0;
+    let mut reciprocal_rank_total = 0.0;
+    let mut queries = 0;
+
+    for (query, query_tags) in tags.iter().enumerate() {
+        let relevant: HashSet<usize> = tags.iter().enumerate()
+            .filter(|(other, other_tags)| *other != query && !query_tags.is_disjoint(other_tags))
+            .map(|(other, _)| other)
+            .collect();
+        if relevant.is_empty() {
+            continue;
+        }
+
+        let mut ranked: Vec<(usize, f32)> = embeddings.iter().enumerate()
+            .filter(|(other, _)| *other != query)
+            .map(|(other, embedding)| (other, cosine_similarity(&embeddings[query], embedding)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let found = ranked.iter().take(k).filter(|(other, _)| relevant.contains(other)).count();
+        recall_total += found as f32 / relevant.len().min(k) as f32;
+        if let Some(rank) = ranked.iter().position(|(other, _)| relevant.contains(other)) {
+            reciprocal_rank_total += 1.0 / (rank + 1) as f32;
+        }
+        queries += 1;
+    }
+
+    if queries == 0 {
+        return (0.0, 0.0, 0);
+    }
+    (recall_total / queries as f32, reciprocal_rank_total / queries as f32, queries)
+}
+
+/// The provider's scores and how many queries they cover
+async fn evaluate_provider(
+    service: &NeuralEmbeddingService,
+    provider: EmbeddingProviderConfig,
+    texts: &[(String, Option<MemoryType>)],
+    tags: &[HashSet<String>],
+) -> Result<(ProviderEvaluation, usize)> {
+    let started = Instant::now();
+    let embeddings = service.embed_batch_with(&provider, texts).await?;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let (recall_at_k, mean_reciprocal_rank, queries) = retrieval_metrics(&embeddings, tags, RECALL_K);
+
+    Ok((ProviderEvaluation {
+        provider_id: provider.cache_id(),
+        provider,
+        recall_at_k,
+        mean_reciprocal_rank,
+        dimensions: embeddings.first().map_or(0, Vec::len),
+        duration_ms,
+    }, queries))
+}
+
+pub fn record_evaluation(conn: &Connection, report: &EmbeddingEvaluationReport) -> Result<()> {
+    conn.execute(
+        "INSERT INTO embedding_evaluations (id, agent_id, provider_a, provider_b, report, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            report.id,
+            report.agent_id,
+            report.provider_a.provider_id,
+            report.provider_b.provider_id,
+            serde_json::to_string(report)?,
+            report.created_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Most recent first
+pub fn list_evaluations(conn: &Connection, agent_id: &str, limit: usize) -> Result<Vec<EmbeddingEvaluationReport>> {
+    let mut stmt = conn.prepare(
+        "SELECT report, created_at FROM embedding_evaluations WHERE agent_id = ?1
+         ORDER BY created_at DESC LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![agent_id, limit], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut reports = Vec::new();
+    for row in rows {
+        let (report, created_at) = row?;
+        let mut report: EmbeddingEvaluationReport = serde_json::from_str(&report)?;
+        report.created_at = parse_db_timestamp(&created_at);
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// Embed a random sample of the agent's tagged memories with both providers, score each on
+/// recall@k against tag overlap and store the comparison in the agent database
+#[tauri::command]
+pub async fn evaluate_embeddings(
+    agent_id: String,
+    provider_a: EmbeddingProviderConfig,
+    provider_b: EmbeddingProviderConfig,
+    sample_size: Option<usize>,
+    state: State<'_, MemoryState>,
+) -> Result<EmbeddingEvaluationReport, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    if provider_a == provider_b {
+        return Err("Choose two different embedding providers to compare".to_string());
+    }
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+    let sample_size = sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE).clamp(MIN_SAMPLE_SIZE, MAX_SAMPLE_SIZE);
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let tagged: Vec<AgentMemory> = manager.search_memories(&MemoryQuery {
+        agent_id: Some(sanitized_agent_id.clone()),
+        memory_types: None,
+        content_search: None,
+        tags: None,
+        embedding: None,
+        similarity_threshold: None,
+        similarity_metric: SimilarityMetric::default(),
+        limit: Some(MAX_CANDIDATE_MEMORIES),
+        offset: None,
+        time_range: None,
+        tiers: None,
+        fusion: None,
+    })
+    .map_err(|e| format!("Failed to retrieve memories: {}", e))?
+    .into_iter()
+    .map(|result| result.memory)
+    .filter(|memory| !memory.tags.is_empty())
+    .collect();
+    if tagged.len() < MIN_SAMPLE_SIZE {
+        return Err(format!(
+            "Need at least {} tagged memories to compare providers, found {}",
+            MIN_SAMPLE_SIZE, tagged.len()
+        ));
+    }
+
+    let sample: Vec<&AgentMemory> = tagged.choose_multiple(&mut rand::thread_rng(), sample_size).collect();
+    let texts: Vec<(String, Option<MemoryType>)> = sample.iter()
+        .map(|memory| (memory.content.clone(), Some(memory.memory_type.clone())))
+        .collect();
+    let tags: Vec<HashSet<String>> = sample.iter()
+        .map(|memory| memory.tags.iter().map(|tag| tag.to_lowercase()).collect())
+        .collect();
+
+    let ((provider_a, query_count), (provider_b, _)) = {
+        let service_lock = state.get_neural_embedding_service().await?;
+        let service = service_lock.lock().await;
+        let service = service.as_ref().ok_or("Neural embedding service not initialized")?;
+        let a = evaluate_provider(service, provider_a, &texts, &tags).await
+            .map_err(|e| format!("Failed to evaluate provider A: {}", e))?;
+        let b = evaluate_provider(service, provider_b, &texts, &tags).await
+            .map_err(|e| format!("Failed to evaluate provider B: {}", e))?;
+        (a, b)
+    };
+
+    let recommended = if (provider_a.recall_at_k - provider_b.recall_at_k).abs() < RECALL_TIE_MARGIN {
+        None
+    } else if provider_a.recall_at_k > provider_b.recall_at_k {
+        Some(provider_a.provider_id.clone())
+    } else {
+        Some(provider_b.provider_id.clone())
+    };
+    let report = EmbeddingEvaluationReport {
+        id: uuid::Uuid::new_v4().to_string(),
+        agent_id: sanitized_agent_id,
+        k: RECALL_K,
+        sample_size: sample.len(),
+        query_count,
+        provider_a,
+        provider_b,
+        recommended,
+        created_at: Utc::now(),
+    };
+
+    let stored = report.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let conn = manager.agent_connection()?;
+        record_evaluation(&conn, &stored)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to store embedding evaluation: {}", e))?;
+
+    info!(
+        "Compared embeddings for {} on {} memories: {} recall@{} {:.3}, {} recall@{} {:.3}",
+        report.agent_id, report.sample_size,
+        report.provider_a.provider_id, report.k, report.provider_a.recall_at_k,
+        report.provider_b.provider_id, report.k, report.provider_b.recall_at_k
+    );
+    Ok(report)
+}
+
+/// Stored provider comparisons for the agent, most recent first
+#[tauri::command]
+pub async fn list_embedding_evaluations(
+    agent_id: String,
+    limit: Option<usize>,
+    state: State<'_, MemoryState>,
+) -> Result<Vec<EmbeddingEvaluationReport>, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let limit = limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT);
+
+    tokio::task::spawn_blocking(move || -> Result<Vec<EmbeddingEvaluationReport>> {
+        let conn = manager.agent_connection()?;
+        list_evaluations(&conn, &sanitized_agent_id, limit)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to list embedding evaluations: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+
+    fn tag_set(tags: &[&str]) -> HashSet<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn test_retrieval_metrics_reward_tag_neighbours() {
+        let tags = vec![tag_set(&["deploy"]), tag_set(&["deploy"]), tag_set(&["billing"]), tag_set(&["billing"]), tag_set(&["misc"])];
+        // Memories sharing a tag point the same way
+        let aligned = vec![vec![1.0, 0.0], vec![0.9, 0.1], vec![0.0, 1.0], vec![0.1, 0.9], vec![-1.0, 0.0]];
+        let (recall, mrr, queries) = retrieval_metrics(&aligned, &tags, 1);
+        assert_eq!(queries, 4);
+        assert_eq!((recall, mrr), (1.0, 1.0));
+
+        // Each memory is closest to one from the other tag group
+        let crossed = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.9, 0.1], vec![0.1, 0.9], vec![-1.0, 0.0]];
+        let (recall, mrr, _) = retrieval_metrics(&crossed, &tags, 1);
+        assert_eq!(recall, 0.0);
+        assert!(mrr > 0.0 && mrr < 1.0);
+        assert_eq!(retrieval_metrics(&[], &[], 5), (0.0, 0.0, 0));
+    }
+
+    #[test]
+    fn test_reports_round_trip_newest_first() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        let evaluation = |provider: EmbeddingProviderConfig, recall_at_k| ProviderEvaluation {
+            provider_id: provider.cache_id(),
+            provider,
+            recall_at_k,
+            mean_reciprocal_rank: recall_at_k,
+            dimensions: 256,
+            duration_ms: 5,
+        };
+        for (i, recommended) in [None, Some("neural".to_string())].into_iter().enumerate() {
+            let report = EmbeddingEvaluationReport {
+                id: format!("eval_{}", i),
+                agent_id: "agent_1".to_string(),
+                k: RECALL_K,
+                sample_size: 50,
+                query_count: 40,
+                provider_a: evaluation(EmbeddingProviderConfig::Neural, 0.6),
+                provider_b: evaluation(EmbeddingProviderConfig::Cohere { model: "embed-english-v3.0".to_string() }, 0.4),
+                recommended,
+                created_at: Utc::now() + chrono::Duration::seconds(i as i64),
+            };
+            record_evaluation(&conn, &report).unwrap();
+        }
+
+        let reports = list_evaluations(&conn, "agent_1", 10).unwrap();
+        assert_eq!(reports.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["eval_1", "eval_0"]);
+        assert_eq!(reports[0].recommended.as_deref(), Some("neural"));
+        assert_eq!(reports[0].provider_b.provider_id, "cohere:embed-english-v3.0");
+        assert!(list_evaluations(&conn, "agent_2", 10).unwrap().is_empty());
+    }
+}
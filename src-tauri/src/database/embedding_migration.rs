@@ -1,15 +1,60 @@
 use anyhow::{Result, anyhow};
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::sync::RwLock;
 use chrono::{DateTime, Utc};
-use rusqlite::{Connection, params};
-use super::neural_embeddings::{NeuralEmbeddingService, EmbeddingConfig};
-use super::memory::MemoryType;
+use rusqlite::params;
+use tauri::{AppHandle, Emitter, State};
+use tracing::{error, info};
+use super::neural_embeddings::{cosine_similarity, NeuralEmbeddingService, EmbeddingConfig};
+use super::embedding_projection::EmbeddingProjection;
+use super::embedding_quantization::{decode_embedding, encode_embedding};
+use super::pool::PooledConnection;
+use super::simple_commands::MemoryState;
+use crate::ai::ensure_disk_space;
+use crate::operations::{start_operation, OPERATION_CANCELLED};
+use crate::validation::MemoryValidator;
+
+/// Emitted with the current `MigrationStatus` after each batch and phase change
+pub const EMBEDDING_MIGRATION_PROGRESS_EVENT: &str = "embedding_migration_progress";
+
+/// Time between operation progress updates while a migration runs
+const OPERATION_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Tables that store embeddings: (table, key column, column the embedding is computed from)
+const EMBEDDING_TABLES: [(&str, &str, &str); 4] = [
+    ("agent_memories", "id", "content"),
+    ("shared_knowledge", "id", "content"),
+    ("knowledge_nodes", "id", "name"),
+    ("embedding_cache", "content_hash", "content"),
+];
+
+/// How stored embeddings reach the target dimension
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DimensionStrategy {
+    /// Generate every embedding again from its text with the target model
+    #[default]
+    ReEmbed,
+    /// Re-embed a sample, learn a linear map from old to new embeddings and apply it to the rest
+    LinearProjection,
+    /// Keep the top principal components of the old embeddings; no target model calls
+    PcaTruncation,
+}
+
+fn default_projection_samples() -> usize {
+    512
+}
+
+fn default_projection_epochs() -> usize {
+    50
+}
 
 /// Migration configuration for embedding updates
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EmbeddingMigrationConfig {
     /// Source model name (e.g., "text-embedding-ada-002")
     pub source_model: String,
@@ -33,6 +78,14 @@ pub struct EmbeddingMigrationConfig {
     pub use_neural_embeddings: bool,
     /// Start time for migration tracking
     pub start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub dimension_strategy: DimensionStrategy,
+    /// Stored embeddings used to fit a projection
+    #[serde(default = "default_projection_samples")]
+    pub projection_samples: usize,
+    /// Training passes for the learned projection
+    #[serde(default = "default_projection_epochs")]
+    pub projection_epochs: usize,
 }
 
 impl Default for EmbeddingMigrationConfig {
@@ -49,12 +102,16 @@ impl Default for EmbeddingMigrationConfig {
             max_retries: 3,
             use_neural_embeddings: false,
             start_time: None,
+            dimension_strategy: DimensionStrategy::ReEmbed,
+            projection_samples: default_projection_samples(),
+            projection_epochs: default_projection_epochs(),
         }
     }
 }
 
 /// Migration status for tracking progress
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MigrationStatus {
     /// Total items to migrate
     pub total_items: usize,
@@ -76,12 +133,51 @@ pub struct MigrationStatus {
     pub status_message: String,
     /// Detailed error messages
     pub errors: Vec<String>,
+    /// Whether the migration is still running
+    #[serde(default)]
+    pub running: bool,
+    /// Mean `1 - cosine` between migrated embeddings and what they should be, see
+    /// `EmbeddingProjection::average_cosine_drift`; re-embedding compares against the old
+    /// embedding when the dimensions match
+    #[serde(default)]
+    pub average_cosine_drift: Option<f32>,
+    #[serde(default)]
+    pub drift_samples: usize,
+}
+
+impl MigrationStatus {
+    fn idle(message: &str) -> Self {
+        Self {
+            total_items: 0,
+            processed_items: 0,
+            successful_items: 0,
+            failed_items: 0,
+            current_batch: 0,
+            total_batches: 0,
+            start_time: Utc::now(),
+            estimated_completion: None,
+            status_message: message.to_string(),
+            errors: Vec::new(),
+            running: false,
+            average_cosine_drift: None,
+            drift_samples: 0,
+        }
+    }
+
+    /// Fold one embedding's drift into the running average
+    fn record_drift(&mut self, drift: f32) {
+        let total = self.average_cosine_drift.unwrap_or(0.0) * self.drift_samples as f32 + drift;
+        self.drift_samples += 1;
+        self.average_cosine_drift = Some(total / self.drift_samples as f32);
+    }
 }
 
 /// Embedding migration utility
 pub struct EmbeddingMigrationUtility {
-    /// Database connection (thread-safe with Mutex)
-    db: Arc<Mutex<Connection>>,
+    /// Memory database being migrated, opened through the connection pool
+    db_path: PathBuf,
+    /// Receives progress events when running inside the app
+    app_handle: Option<AppHandle>,
     /// Source embedding service (neural)
     source_service: NeuralEmbeddingService,
     /// Target embedding service (neural or traditional)
@@ -147,10 +243,10 @@ impl EmbeddingServiceTrait for NeuralEmbeddingWrapper {
 impl EmbeddingMigrationUtility {
     /// Create a new migration utility
     pub async fn new(
-        db_path: &str,
+        db_path: PathBuf,
         config: EmbeddingMigrationConfig,
+        app_handle: Option<AppHandle>,
     ) -> Result<Self> {
-        let db = Arc::new(Mutex::new(Connection::open(db_path)?));
         
         // Initialize source service with default config
         let source_config = EmbeddingConfig {
@@ -176,21 +272,11 @@ impl EmbeddingMigrationUtility {
             Arc::new(RwLock::new(Box::new(TraditionalEmbeddingWrapper { service: traditional_service })))
         };
 
-        let status = Arc::new(RwLock::new(MigrationStatus {
-            total_items: 0,
-            processed_items: 0,
-            successful_items: 0,
-            failed_items: 0,
-            current_batch: 0,
-            total_batches: 0,
-            start_time: Utc::now(),
-            estimated_completion: None,
-            status_message: "Initializing migration...".to_string(),
-            errors: Vec::new(),
-        }));
+        let status = Arc::new(RwLock::new(MigrationStatus::idle("Initializing migration...")));
 
         Ok(Self {
-            db,
+            db_path,
+            app_handle,
             source_service,
             target_service,
             config,
@@ -210,39 +296,37 @@ impl EmbeddingMigrationUtility {
         update(&mut status);
     }
 
+    /// Send the current status to the frontend's progress bar
+    async fn publish_status(&self) {
+        if let Some(ref app) = self.app_handle {
+            let status = self.status.read().await.clone();
+            if let Err(e) = app.emit(EMBEDDING_MIGRATION_PROGRESS_EVENT, &status) {
+                error!("Failed to emit embedding migration progress: {}", e);
+            }
+        }
+    }
+
+    /// Pooled connections are only held between awaits, never across them
+    fn connection(&self) -> Result<PooledConnection> {
+        super::pool::connection(&self.db_path)
+    }
+
     /// Get all tables that contain embeddings
     pub async fn get_embedding_tables(&self) -> Result<Vec<String>> {
         // Use a whitelist approach to prevent SQL injection
-        let allowed_tables = vec![
-            "agent_memories".to_string(),
-            "shared_knowledge".to_string(), 
-            "knowledge_nodes".to_string(),
-            "knowledge_items".to_string(),
-            "embedding_cache".to_string(),
-        ];
-        Ok(allowed_tables)
-    }
-
-    /// Count total items that need migration using a single query
-    pub async fn count_migration_items(&self) -> Result<usize> {
-        // Use a more efficient UNION ALL query to count all embeddings across tables
-        let query = r#"
-            SELECT SUM(count) FROM (
-                SELECT COUNT(*) as count FROM agent_memories WHERE embedding IS NOT NULL
-                UNION ALL
-                SELECT COUNT(*) FROM shared_knowledge WHERE embedding IS NOT NULL
-                UNION ALL
-                SELECT COUNT(*) FROM knowledge_nodes WHERE embedding IS NOT NULL
-                UNION ALL
-                SELECT COUNT(*) FROM knowledge_items WHERE embedding IS NOT NULL
-                UNION ALL
-                SELECT COUNT(*) FROM embedding_cache WHERE embedding IS NOT NULL
-            )
-        "#;
-        
-        let db = self.db.lock().unwrap();
-        let total: usize = db.query_row(query, [], |row| row.get(0))?;
-        Ok(total)
+        let conn = self.connection()?;
+        let mut tables = Vec::new();
+        for (table, _, _) in EMBEDDING_TABLES {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )?;
+            if exists {
+                tables.push(table.to_string());
+            }
+        }
+        Ok(tables)
     }
 
     /// Backup original embeddings before migration
@@ -255,68 +339,72 @@ impl EmbeddingMigrationUtility {
             s.status_message = "Creating backup of original embeddings...".to_string();
         }).await;
 
+        let tables = self.get_embedding_tables().await?;
+
         // Use transaction for backup operations
-        let db = self.db.lock().unwrap();
-        let tx = db.transaction()?;
-        
-        // Create backup tables
-        let backup_tables = vec![
-            ("agent_memories", "agent_memories_backup"),
-            ("shared_knowledge", "shared_knowledge_backup"),
-            ("knowledge_nodes", "knowledge_nodes_backup"),
-            ("knowledge_items", "knowledge_items_backup"),
-            ("embedding_cache", "embedding_cache_backup"),
-        ];
-
-        for (original, backup) in backup_tables {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        for original in tables {
             tx.execute(
-                &format!("CREATE TABLE IF NOT EXISTS {} AS SELECT * FROM {}", backup, original),
+                &format!("CREATE TABLE IF NOT EXISTS {0}_backup AS SELECT * FROM {0}", original),
                 [],
             )?;
         }
-
         tx.commit()?;
         Ok(())
     }
 
-    /// Migrate embeddings for a specific table using streaming approach
-    pub async fn migrate_table_embeddings(&self, table_name: &str) -> Result<()> {
+    /// Migrate embeddings for a specific table a batch at a time, re-embedding each row or
+    /// applying `projection` when given. Each batch is read and written in short
+    /// pooled-connection sections, so no connection or lock is held while embeddings are
+    /// generated and the database stays usable during the migration.
+    pub async fn migrate_table_embeddings(&self, table_name: &str, projection: Option<&EmbeddingProjection>) -> Result<()> {
+        let (table_name, key_column, text_column) = EMBEDDING_TABLES.iter()
+            .find(|(table, _, _)| *table == table_name)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown embedding table: {}", table_name))?;
+
         self.update_status(|s| {
             s.status_message = format!("Migrating embeddings for table: {}", table_name);
         }).await;
+        self.publish_status().await;
 
-        // Use cursor-based approach to avoid loading all items into memory
-        let mut offset = 0;
-        let batch_size = self.config.batch_size;
+        // Keyset pagination, so rows updated by earlier batches can't shift later ones
+        let batch_size = self.config.batch_size.max(1);
+        let mut last_key = String::new();
         let mut total_processed = 0;
 
         loop {
-            // Get batch of items
-            let db = self.db.lock().unwrap();
-            let mut stmt = db.prepare(&format!(
-                "SELECT id, content, embedding FROM {} WHERE embedding IS NOT NULL LIMIT ? OFFSET ?",
-                table_name
-            ))?;
-
-            let rows = stmt.query_map(params![batch_size, offset], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, Vec<u8>>(2)?,
-                ))
-            })?;
-
-            let mut batch_count = 0;
-            for row in rows {
-                let (id, content, _old_embedding) = row?;
-                
-                // Process each row individually to avoid memory issues
-                match self.migrate_single_embedding(table_name, &id, &content).await {
-                    Ok(_) => {
-                        self.update_status(|s| {
-                            s.successful_items += 1;
-                            s.processed_items += 1;
-                        }).await;
+            let batch: Vec<(String, String, Vec<u8>)> = {
+                let conn = self.connection()?;
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {0}, {1}, embedding FROM {2} WHERE embedding IS NOT NULL AND {0} > ?1 ORDER BY {0} LIMIT ?2",
+                    key_column, text_column, table_name
+                ))?;
+                let rows = stmt.query_map(params![last_key, batch_size], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()?
+            };
+            let Some((key, _, _)) = batch.last() else {
+                break;
+            };
+            last_key = key.clone();
+
+            let mut updates = Vec::with_capacity(batch.len());
+            for (id, content, old_embedding_bytes) in &batch {
+                let old_embedding = decode_embedding(old_embedding_bytes);
+                let migrated = match projection {
+                    Some(projection) => self.project_embedding(projection, old_embedding.as_deref()),
+                    None => self.generate_embedding(id, content).await,
+                };
+                match migrated.map(|embedding| (encode_embedding(&embedding), embedding)) {
+                    Ok((embedding_bytes, embedding)) => {
+                        if projection.is_none() {
+                            if let Some(old) = old_embedding.filter(|old| old.len() == embedding.len()) {
+                                let drift = 1.0 - cosine_similarity(&old, &embedding);
+                                self.update_status(|s| s.record_drift(drift)).await;
+                            }
+                        }
+                        updates.push((id, embedding_bytes));
                     }
                     Err(e) => {
                         self.update_status(|s| {
@@ -326,36 +414,46 @@ impl EmbeddingMigrationUtility {
                         }).await;
                     }
                 }
-                batch_count += 1;
             }
 
-            if batch_count == 0 {
-                break;
+            {
+                let mut conn = self.connection()?;
+                let tx = conn.transaction()?;
+                {
+                    let mut stmt = tx.prepare(&format!("UPDATE {} SET embedding = ?1 WHERE {} = ?2", table_name, key_column))?;
+                    for (id, embedding_bytes) in &updates {
+                        stmt.execute(params![embedding_bytes, id])?;
+                    }
+                }
+                tx.commit()?;
             }
 
-            total_processed += batch_count;
-            offset += batch_size;
+            total_processed += batch.len();
+            let migrated = updates.len();
 
             // Update batch progress
             self.update_status(|s| {
-                s.current_batch = (offset / batch_size) + 1;
+                s.successful_items += migrated;
+                s.processed_items += migrated;
+                s.current_batch += 1;
                 s.status_message = format!(
                     "Processed {} items from table {}",
                     total_processed, table_name
                 );
+                if s.processed_items > 0 {
+                    let elapsed = Utc::now() - s.start_time;
+                    let remaining = s.total_items.saturating_sub(s.processed_items) as i32;
+                    s.estimated_completion = Some(Utc::now() + elapsed / s.processed_items as i32 * remaining);
+                }
             }).await;
+            self.publish_status().await;
         }
 
         Ok(())
     }
 
-    /// Migrate a single embedding
-    async fn migrate_single_embedding(
-        &self,
-        table_name: &str,
-        id: &str,
-        content: &str,
-    ) -> Result<()> {
+    /// Generate the new embedding for one row from its text
+    async fn generate_embedding(&self, id: &str, content: &str) -> Result<Vec<f32>> {
         // Validate content before processing
         if content.trim().is_empty() {
             return Err(anyhow!("Content is empty for id: {}", id));
@@ -367,47 +465,160 @@ impl EmbeddingMigrationUtility {
         let new_embedding = target_service.embed_text(content).await?;
         
         // Validate embedding if required
-        if self.config.validate_embeddings {
-            if !target_service.validate_embedding(&new_embedding).await? {
-                return Err(anyhow!("Generated embedding failed validation"));
-            }
+        if self.config.validate_embeddings && !target_service.validate_embedding(&new_embedding).await? {
+            return Err(anyhow!("Generated embedding failed validation"));
         }
 
-        // Convert embedding to bytes for storage
-        let embedding_bytes = bincode::serialize(&new_embedding)?;
+        Ok(new_embedding)
+    }
 
-        // Update database using parameterized query
-        let db = self.db.lock().unwrap();
-        db.execute(
-            &format!(
-                "UPDATE {} SET embedding = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-                table_name
-            ),
-            params![embedding_bytes, id],
-        )?;
+    /// Map a stored embedding with the fitted projection
+    fn project_embedding(&self, projection: &EmbeddingProjection, old_embedding: Option<&[f32]>) -> Result<Vec<f32>> {
+        let old_embedding = old_embedding.ok_or_else(|| anyhow!("Stored embedding could not be read"))?;
+        if old_embedding.len() != self.config.source_dimensions {
+            return Err(anyhow!(
+                "Stored embedding has {} dimensions, expected {}",
+                old_embedding.len(), self.config.source_dimensions
+            ));
+        }
+        Ok(projection.project(old_embedding))
+    }
 
-        Ok(())
+    /// Fit the configured projection on a sample of stored embeddings and record its drift
+    async fn fit_projection(&self, tables: &[String]) -> Result<EmbeddingProjection> {
+        self.update_status(|s| {
+            s.status_message = "Fitting embedding projection...".to_string();
+        }).await;
+        self.publish_status().await;
+
+        let sample_limit = self.config.projection_samples.max(1);
+        let mut samples: Vec<(String, Vec<f32>)> = Vec::new();
+        {
+            let conn = self.connection()?;
+            for table in tables {
+                let Some((_, _, text_column)) = EMBEDDING_TABLES.iter().find(|(name, _, _)| name == table) else {
+                    continue;
+                };
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {}, embedding FROM {} WHERE embedding IS NOT NULL LIMIT ?1",
+                    text_column, table
+                ))?;
+                let rows = stmt.query_map(params![sample_limit - samples.len()], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+                })?;
+                for row in rows {
+                    let (text, bytes) = row?;
+                    if let Some(embedding) = decode_embedding(&bytes) {
+                        if embedding.len() == self.config.source_dimensions {
+                            samples.push((text, embedding));
+                        }
+                    }
+                }
+                if samples.len() >= sample_limit {
+                    break;
+                }
+            }
+        }
+        if samples.is_empty() {
+            return Err(anyhow!("No stored {}-dimensional embeddings to fit a projection on", self.config.source_dimensions));
+        }
+
+        let (projection, drift, measured) = match self.config.dimension_strategy {
+            DimensionStrategy::PcaTruncation => {
+                let inputs: Vec<Vec<f32>> = samples.into_iter().map(|(_, embedding)| embedding).collect();
+                let projection = EmbeddingProjection::fit_pca(&inputs, self.config.target_dimensions)?;
+                let drift = projection.average_cosine_drift(&inputs, &[]);
+                (projection, drift, inputs.len())
+            }
+            DimensionStrategy::LinearProjection | DimensionStrategy::ReEmbed => {
+                let target_service = self.target_service.read().await;
+                let mut inputs = Vec::with_capacity(samples.len());
+                let mut targets = Vec::with_capacity(samples.len());
+                for (text, embedding) in samples {
+                    inputs.push(embedding);
+                    targets.push(target_service.embed_text(&text).await?);
+                }
+                drop(target_service);
+
+                // Every fifth pair is held out to measure drift, unless there are too few to spare
+                let holdout = |i: &usize| inputs.len() >= 10 && i % 5 == 4;
+                let (train_inputs, train_targets): (Vec<_>, Vec<_>) = (0..inputs.len())
+                    .filter(|i| !holdout(i))
+                    .map(|i| (inputs[i].clone(), targets[i].clone()))
+                    .unzip();
+                let (test_inputs, test_targets): (Vec<_>, Vec<_>) = (0..inputs.len())
+                    .filter(|i| holdout(i) || inputs.len() < 10)
+                    .map(|i| (inputs[i].clone(), targets[i].clone()))
+                    .unzip();
+
+                let projection = EmbeddingProjection::fit_linear(&train_inputs, &train_targets, self.config.projection_epochs)?;
+                let drift = projection.average_cosine_drift(&test_inputs, &test_targets);
+                (projection, drift, test_inputs.len())
+            }
+        };
+
+        if projection.output_dim() != self.config.target_dimensions {
+            return Err(anyhow!(
+                "Projection produces {} dimensions, expected {}",
+                projection.output_dim(), self.config.target_dimensions
+            ));
+        }
+        self.update_status(|s| {
+            s.average_cosine_drift = Some(drift);
+            s.drift_samples = measured;
+            s.status_message = format!("Fitted projection with average cosine drift {:.4}", drift);
+        }).await;
+        Ok(projection)
     }
 
-    /// Run complete migration
+    /// Run complete migration, reporting the outcome through the status and progress event
     pub async fn run_migration(&self) -> Result<()> {
+        let result = self.run_migration_steps().await;
+
+        self.update_status(|s| {
+            s.running = false;
+            if let Err(ref e) = result {
+                s.status_message = format!("Migration failed: {}", e);
+                s.errors.push(e.to_string());
+            }
+        }).await;
+        self.publish_status().await;
+        result
+    }
+
+    async fn run_migration_steps(&self) -> Result<()> {
         // Initialize migration
-        let total_items = self.count_migration_items().await?;
+        let table_counts = self.table_counts().await?;
+        let batch_size = self.config.batch_size.max(1);
         
         self.update_status(|s| {
-            s.total_items = total_items;
+            s.total_items = table_counts.iter().map(|(_, count)| count).sum();
+            s.total_batches = table_counts.iter().map(|(_, count)| count.div_ceil(batch_size)).sum();
             s.start_time = Utc::now();
+            s.running = true;
             s.status_message = "Starting migration...".to_string();
         }).await;
+        self.publish_status().await;
 
         // Create backup if requested
         if self.config.backup_original {
+            // Backup tables copy every embedding, so roughly the database size is needed again
+            let db_size = std::fs::metadata(&self.db_path).map(|m| m.len()).unwrap_or(0);
+            ensure_disk_space(&self.db_path, db_size, "embedding migration")?;
             self.backup_embeddings().await?;
         }
 
+        let projection = match self.config.dimension_strategy {
+            DimensionStrategy::ReEmbed => None,
+            _ => {
+                let tables: Vec<String> = table_counts.iter().map(|(table, _)| table.clone()).collect();
+                Some(self.fit_projection(&tables).await?)
+            }
+        };
+
         // Migrate each table
-        for table in self.get_embedding_tables().await? {
-            self.migrate_table_embeddings(&table).await?;
+        for (table, _) in table_counts {
+            self.migrate_table_embeddings(&table, projection.as_ref()).await?;
         }
 
         // Final status update
@@ -419,6 +630,20 @@ impl EmbeddingMigrationUtility {
         Ok(())
     }
 
+    /// Embeddings stored per table
+    async fn table_counts(&self) -> Result<Vec<(String, usize)>> {
+        let tables = self.get_embedding_tables().await?;
+        let conn = self.connection()?;
+        tables.into_iter().map(|table| {
+            let count: usize = conn.query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE embedding IS NOT NULL", table),
+                [],
+                |row| row.get(0),
+            )?;
+            Ok((table, count))
+        }).collect()
+    }
+
     /// Validate migration results
     pub async fn validate_migration(&self) -> Result<MigrationValidationResult> {
         let mut results = MigrationValidationResult {
@@ -426,30 +651,31 @@ impl EmbeddingMigrationUtility {
             valid_embeddings: 0,
             invalid_embeddings: 0,
             errors: Vec::new(),
+            average_cosine_drift: self.get_status().await.average_cosine_drift,
         };
 
         let target_service = self.target_service.read().await;
 
         for table in self.get_embedding_tables().await? {
-            let db = self.db.lock().unwrap();
-            let mut stmt = db.prepare(&format!(
-                "SELECT id, embedding FROM {} WHERE embedding IS NOT NULL",
-                table
-            ))?;
-
-            let rows = stmt.query_map([], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, Vec<u8>>(1)?,
-                ))
-            })?;
-
-            for row in rows {
-                let (id, embedding_bytes) = row?;
+            let key_column = EMBEDDING_TABLES.iter()
+                .find(|(name, _, _)| *name == table)
+                .map(|(_, key, _)| *key)
+                .unwrap_or("id");
+            let rows: Vec<(String, Vec<u8>)> = {
+                let conn = self.connection()?;
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {}, embedding FROM {} WHERE embedding IS NOT NULL",
+                    key_column, table
+                ))?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()?
+            };
+
+            for (id, embedding_bytes) in rows {
                 results.total_validated += 1;
 
-                match bincode::deserialize::<Vec<f32>>(&embedding_bytes) {
-                    Ok(embedding) => {
+                match decode_embedding(&embedding_bytes) {
+                    Some(embedding) => {
                         if target_service.validate_embedding(&embedding).await? {
                             results.valid_embeddings += 1;
                         } else {
@@ -457,9 +683,9 @@ impl EmbeddingMigrationUtility {
                             results.errors.push(format!("Invalid embedding for {} in {}", id, table));
                         }
                     }
-                    Err(e) => {
+                    None => {
                         results.invalid_embeddings += 1;
-                        results.errors.push(format!("Failed to deserialize embedding for {} in {}: {}", id, table, e));
+                        results.errors.push(format!("Failed to deserialize embedding for {} in {}", id, table));
                     }
                 }
             }
@@ -468,7 +694,7 @@ impl EmbeddingMigrationUtility {
         Ok(results)
     }
 
-    /// Rollback migration to backup using transactions
+    /// Put the backed-up embeddings back, leaving any other changes to the rows in place
     pub async fn rollback_migration(&self) -> Result<()> {
         if !self.config.backup_original {
             return Err(anyhow!("Cannot rollback: no backup was created"));
@@ -477,57 +703,48 @@ impl EmbeddingMigrationUtility {
         self.update_status(|s| {
             s.status_message = "Rolling back migration...".to_string();
         }).await;
+        self.publish_status().await;
 
-        // Use transaction for rollback operations
-        let db = self.db.lock().unwrap();
-        let tx = db.transaction()?;
-
-        // Restore from backup tables
-        let backup_tables = vec![
-            ("agent_memories_backup", "agent_memories"),
-            ("shared_knowledge_backup", "shared_knowledge"),
-            ("knowledge_nodes_backup", "knowledge_nodes"),
-            ("knowledge_items_backup", "knowledge_items"),
-            ("embedding_cache_backup", "embedding_cache"),
-        ];
-
-        for (backup, original) in backup_tables {
-            // Check if backup table exists
-            let exists: bool = tx.query_row(
-                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
-                [backup],
-                |row| row.get(0),
-            )?;
+        let tables = self.get_embedding_tables().await?;
 
-            if exists {
-                // Drop original table and rename backup
-                tx.execute(&format!("DROP TABLE IF EXISTS {}", original), [])?;
-                tx.execute(&format!("ALTER TABLE {} RENAME TO {}", backup, original), [])?;
+        {
+            // Use transaction for rollback operations
+            let mut conn = self.connection()?;
+            let tx = conn.transaction()?;
+            for (table, key_column, _) in EMBEDDING_TABLES {
+                if !tables.iter().any(|t| t == table) {
+                    continue;
+                }
+                let exists: bool = tx.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                    [format!("{}_backup", table)],
+                    |row| row.get(0),
+                )?;
+                if exists {
+                    tx.execute(&format!(
+                        "UPDATE {0} SET embedding = (SELECT b.embedding FROM {0}_backup b WHERE b.{1} = {0}.{1})
+                         WHERE {1} IN (SELECT {1} FROM {0}_backup)",
+                        table, key_column
+                    ), [])?;
+                    tx.execute(&format!("DROP TABLE {}_backup", table), [])?;
+                }
             }
+            tx.commit()?;
         }
 
-        tx.commit()?;
-
         self.update_status(|s| {
             s.status_message = "Rollback completed successfully".to_string();
         }).await;
+        self.publish_status().await;
 
         Ok(())
     }
 
     /// Clean up backup tables
     pub async fn cleanup_backups(&self) -> Result<()> {
-        let backup_tables = vec![
-            "agent_memories_backup",
-            "shared_knowledge_backup",
-            "knowledge_nodes_backup",
-            "knowledge_items_backup",
-            "embedding_cache_backup",
-        ];
-
-        let db = self.db.lock().unwrap();
-        for table in backup_tables {
-            db.execute(&format!("DROP TABLE IF EXISTS {}", table), [])?;
+        let conn = self.connection()?;
+        for (table, _, _) in EMBEDDING_TABLES {
+            conn.execute(&format!("DROP TABLE IF EXISTS {}_backup", table), [])?;
         }
 
         Ok(())
@@ -535,59 +752,33 @@ impl EmbeddingMigrationUtility {
 
     /// Get migration statistics
     pub async fn get_migration_stats(&self) -> Result<MigrationStats> {
-        let mut stats = MigrationStats {
-            total_embeddings: 0,
-            migrated_embeddings: 0,
-            failed_embeddings: 0,
-            table_breakdown: HashMap::new(),
-        };
-
-        for table in self.get_embedding_tables().await? {
-            let db = self.db.lock().unwrap();
-            let count: usize = db.query_row(
-                &format!("SELECT COUNT(*) FROM {} WHERE embedding IS NOT NULL", table),
-                [],
-                |row| row.get(0),
-            )?;
-
-            stats.total_embeddings += count;
-            stats.table_breakdown.insert(table, count);
-        }
-
-        // Count migrated embeddings (with new model)
-        if let Some(start_time) = self.config.start_time {
-            for table in self.get_embedding_tables().await? {
-                let db = self.db.lock().unwrap();
-                let count: usize = db.query_row(
-                    &format!(
-                        "SELECT COUNT(*) FROM {} WHERE embedding IS NOT NULL AND updated_at > ?",
-                        table
-                    ),
-                    [start_time.timestamp()],
-                    |row| row.get(0),
-                )?;
-
-                stats.migrated_embeddings += count;
-            }
-        }
-
-        stats.failed_embeddings = stats.total_embeddings - stats.migrated_embeddings;
-
-        Ok(stats)
+        let table_counts = self.table_counts().await?;
+        let status = self.get_status().await;
+
+        Ok(MigrationStats {
+            total_embeddings: table_counts.iter().map(|(_, count)| count).sum(),
+            migrated_embeddings: status.successful_items,
+            failed_embeddings: status.failed_items,
+            table_breakdown: table_counts.into_iter().collect(),
+        })
     }
 }
 
 /// Migration validation result
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MigrationValidationResult {
     pub total_validated: usize,
     pub valid_embeddings: usize,
     pub invalid_embeddings: usize,
     pub errors: Vec<String>,
+    /// See `MigrationStatus::average_cosine_drift`
+    pub average_cosine_drift: Option<f32>,
 }
 
 /// Migration statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MigrationStats {
     pub total_embeddings: usize,
     pub migrated_embeddings: usize,
@@ -596,85 +787,193 @@ pub struct MigrationStats {
 }
 
 // Thread-safe global state for migration utility
-static MIGRATION_UTILITY: once_cell::sync::Lazy<Arc<RwLock<Option<EmbeddingMigrationUtility>>>> = 
-    once_cell::sync::Lazy::new(|| Arc::new(RwLock::new(None)));
+static MIGRATION_UTILITY: once_cell::sync::Lazy<RwLock<Option<Arc<EmbeddingMigrationUtility>>>> = 
+    once_cell::sync::Lazy::new(|| RwLock::new(None));
+
+async fn current_utility() -> Result<Arc<EmbeddingMigrationUtility>, String> {
+    MIGRATION_UTILITY.read().await.clone().ok_or_else(|| "No migration in progress".to_string())
+}
 
-/// Tauri commands for embedding migration
+/// Re-embed a memory database with the configured target model in the background.
+/// Migrates the shared knowledge database, or the agent's own database when `agent_id` is
+/// given; progress is reported through `embedding_migration_progress` events. Returns the id of
+/// the operation running it; a cancelled migration stops mid-run and should be rolled back.
 #[tauri::command]
 pub async fn start_embedding_migration(
     config: EmbeddingMigrationConfig,
+    agent_id: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, MemoryState>,
 ) -> Result<String, String> {
-    let migration_utility = EmbeddingMigrationUtility::new("banshee.db", config)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    // Store the migration utility in global state
-    {
-        let mut global_utility = MIGRATION_UTILITY.write().await;
-        *global_utility = Some(migration_utility);
-    }
-
-    // Run migration in background
-    let utility_clone = MIGRATION_UTILITY.clone();
-    tokio::spawn(async move {
-        if let Some(utility) = utility_clone.read().await.as_ref() {
-            if let Err(e) = utility.run_migration().await {
-                eprintln!("Migration failed: {}", e);
+    let db_path = match agent_id {
+        Some(agent_id) => {
+            MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+            let validation_result = state.get_security_middleware().validate_request(
+                "memory_operations",
+                &[agent_id],
+                &[]
+            ).await?;
+            let manager = state.get_or_create_manager(validation_result.sanitized_inputs[0].clone())?;
+            manager.get_agent_db_path().clone()
+        }
+        None => super::simple_memory::SimpleMemoryManager::default_shared_db_path().map_err(|e| e.to_string())?,
+    };
+
+    let mut global_utility = MIGRATION_UTILITY.write().await;
+    if let Some(ref running) = *global_utility {
+        if running.get_status().await.running {
+            return Err("An embedding migration is already running".to_string());
+        }
+    }
+
+    let migration_utility = Arc::new(
+        EmbeddingMigrationUtility::new(db_path, config, Some(app_handle.clone()))
+            .await
+            .map_err(|e| e.to_string())?
+    );
+    migration_utility.update_status(|s| s.running = true).await;
+    *global_utility = Some(migration_utility.clone());
+    drop(global_utility);
+
+    // Run migration in background, mirroring its status onto the operation
+    let operation_id = start_operation(&app_handle, "embedding_migration", move |operation| async move {
+        let report_progress = async {
+            loop {
+                tokio::time::sleep(OPERATION_PROGRESS_INTERVAL).await;
+                let status = migration_utility.get_status().await;
+                let done = status.processed_items as f32 / status.total_items.max(1) as f32;
+                operation.report(done, status.status_message);
             }
+        };
+        tokio::select! {
+            result = migration_utility.run_migration() => match result {
+                Ok(()) => {
+                    info!("Embedding migration completed");
+                    serde_json::to_value(migration_utility.get_status().await).map_err(|e| e.to_string())
+                }
+                Err(e) => {
+                    error!("Embedding migration failed: {}", e);
+                    Err(e.to_string())
+                }
+            },
+            _ = operation.cancelled() => {
+                migration_utility.update_status(|s| {
+                    s.running = false;
+                    s.status_message = "Migration cancelled; roll back to restore the original embeddings".to_string();
+                }).await;
+                migration_utility.publish_status().await;
+                Err(OPERATION_CANCELLED.to_string())
+            }
+            _ = report_progress => unreachable!("progress reporting never finishes"),
         }
     });
 
-    Ok("Migration started successfully".to_string())
+    Ok(operation_id)
 }
 
 #[tauri::command]
 pub async fn get_migration_status() -> Result<MigrationStatus, String> {
-    let global_utility = MIGRATION_UTILITY.read().await;
-    if let Some(utility) = global_utility.as_ref() {
-        Ok(utility.get_status().await)
-    } else {
-        Ok(MigrationStatus {
-            total_items: 0,
-            processed_items: 0,
-            successful_items: 0,
-            failed_items: 0,
-            current_batch: 0,
-            total_batches: 0,
-            start_time: Utc::now(),
-            estimated_completion: None,
-            status_message: "No migration in progress".to_string(),
-            errors: Vec::new(),
-        })
+    match MIGRATION_UTILITY.read().await.clone() {
+        Some(utility) => Ok(utility.get_status().await),
+        None => Ok(MigrationStatus::idle("No migration in progress")),
     }
 }
 
 #[tauri::command]
 pub async fn validate_migration_results() -> Result<MigrationValidationResult, String> {
-    let global_utility = MIGRATION_UTILITY.read().await;
-    if let Some(utility) = global_utility.as_ref() {
-        utility.validate_migration().await.map_err(|e| e.to_string())
-    } else {
-        Err("No migration in progress".to_string())
-    }
+    current_utility().await?.validate_migration().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn rollback_migration() -> Result<String, String> {
-    let global_utility = MIGRATION_UTILITY.read().await;
-    if let Some(utility) = global_utility.as_ref() {
-        utility.rollback_migration().await.map_err(|e| e.to_string())?;
-        Ok("Migration rolled back successfully".to_string())
-    } else {
-        Err("No migration in progress".to_string())
+    let utility = current_utility().await?;
+    if utility.get_status().await.running {
+        return Err("Cannot roll back while the migration is running".to_string());
     }
+    utility.rollback_migration().await.map_err(|e| e.to_string())?;
+    Ok("Migration rolled back successfully".to_string())
 }
 
 #[tauri::command]
 pub async fn get_migration_stats() -> Result<MigrationStats, String> {
-    let global_utility = MIGRATION_UTILITY.read().await;
-    if let Some(utility) = global_utility.as_ref() {
-        utility.get_migration_stats().await.map_err(|e| e.to_string())
-    } else {
-        Err("No migration in progress".to_string())
+    current_utility().await?.get_migration_stats().await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+
+    #[tokio::test]
+    async fn test_migration_reembeds_and_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("agent.db");
+        let original = bincode::serialize(&vec![1.0f32, 0.0]).unwrap();
+        {
+            let conn = super::super::pool::connection(&db_path).unwrap();
+            migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+            for (id, content) in [("m1", "first memory"), ("m2", "second memory"), ("m3", "third memory")] {
+                conn.execute(
+                    "INSERT INTO agent_memories (id, agent_id, memory_type, content, embedding) VALUES (?1, 'agent', 'Learning', ?2, ?3)",
+                    params![id, content, original],
+                ).unwrap();
+            }
+        }
+
+        let config = EmbeddingMigrationConfig {
+            target_dimensions: 32,
+            batch_size: 2,
+            use_neural_embeddings: true,
+            ..Default::default()
+        };
+        let utility = EmbeddingMigrationUtility::new(db_path.clone(), config, None).await.unwrap();
+        utility.run_migration().await.unwrap();
+
+        let status = utility.get_status().await;
+        assert_eq!((status.total_items, status.successful_items, status.failed_items), (3, 3, 0));
+        assert_eq!((status.current_batch, status.total_batches), (2, 2));
+        assert!(!status.running);
+        let validation = utility.validate_migration().await.unwrap();
+        assert_eq!(validation.valid_embeddings, 3);
+
+        utility.rollback_migration().await.unwrap();
+        let conn = super::super::pool::connection(&db_path).unwrap();
+        let restored: Vec<u8> = conn.query_row("SELECT embedding FROM agent_memories WHERE id = 'm2'", [], |row| row.get(0)).unwrap();
+        assert_eq!(restored, original);
+    }
+
+    #[tokio::test]
+    async fn test_pca_migration_truncates_dimensions() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("agent.db");
+        {
+            let conn = super::super::pool::connection(&db_path).unwrap();
+            migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+            for i in 0..6 {
+                let embedding = bincode::serialize(&vec![i as f32, 1.0 - i as f32, 0.5, 0.0]).unwrap();
+                conn.execute(
+                    "INSERT INTO agent_memories (id, agent_id, memory_type, content, embedding) VALUES (?1, 'agent', 'Learning', 'memory', ?2)",
+                    params![format!("m{}", i), embedding],
+                ).unwrap();
+            }
+        }
+
+        let config = EmbeddingMigrationConfig {
+            source_dimensions: 4,
+            target_dimensions: 2,
+            dimension_strategy: DimensionStrategy::PcaTruncation,
+            backup_original: false,
+            ..Default::default()
+        };
+        let utility = EmbeddingMigrationUtility::new(db_path.clone(), config, None).await.unwrap();
+        utility.run_migration().await.unwrap();
+
+        let status = utility.get_status().await;
+        assert_eq!((status.successful_items, status.failed_items), (6, 0));
+        // The samples span one direction plus an offset, so two components reconstruct them exactly
+        assert!(status.average_cosine_drift.unwrap() < 0.01);
+        let conn = super::super::pool::connection(&db_path).unwrap();
+        let migrated: Vec<u8> = conn.query_row("SELECT embedding FROM agent_memories WHERE id = 'm3'", [], |row| row.get(0)).unwrap();
+        assert_eq!(decode_embedding(&migrated).unwrap().len(), 2);
     }
-} 
\ No newline at end of file
+}
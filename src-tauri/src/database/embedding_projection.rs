@@ -0,0 +1,172 @@
+use anyhow::{anyhow, bail, Result};
+use ndarray::{Array1, Array2, Axis};
+use super::neural_embeddings::cosine_similarity;
+use super::neural_network::{NeuralNetwork, NetworkBuilder};
+
+/// Subspace iterations used to find the principal components
+const PCA_ITERATIONS: usize = 20;
+
+/// Fixed so the same samples always give the same components
+const PCA_SEED: u64 = 0x5eed;
+
+/// Step size for fitting the linear map; inputs are unit vectors, so this is well inside the stable range
+const PROJECTION_LEARNING_RATE: f32 = 0.05;
+
+/// Maps stored embeddings to a different dimension without re-embedding their text
+pub enum EmbeddingProjection {
+    /// Single-layer linear network trained on embeddings paired with the target model's output
+    Learned(NeuralNetwork),
+    /// Projection onto the top principal components of the stored embeddings
+    Pca {
+        mean: Array1<f32>,
+        /// One component per row
+        components: Array2<f32>,
+    },
+}
+
+impl EmbeddingProjection {
+    /// Fit a linear map from source to target embeddings
+    pub fn fit_linear(inputs: &[Vec<f32>], targets: &[Vec<f32>], epochs: usize) -> Result<Self> {
+        let input_dim = inputs.first().map(Vec::len).ok_or_else(|| anyhow!("No samples to fit a projection"))?;
+        let output_dim = targets.first().map(Vec::len).ok_or_else(|| anyhow!("No targets to fit a projection"))?;
+
+        let mut network = NetworkBuilder::new()
+            .input_layer(input_dim)
+            .output_layer(output_dim)
+            .learning_rate(PROJECTION_LEARNING_RATE)
+            .build()?;
+        network.train(inputs, targets, epochs)?;
+        Ok(Self::Learned(network))
+    }
+
+    /// Find the `target_dim` directions of greatest variance in the samples
+    pub fn fit_pca(samples: &[Vec<f32>], target_dim: usize) -> Result<Self> {
+        let dim = samples.first().map(Vec::len).ok_or_else(|| anyhow!("No samples to fit a projection"))?;
+        if target_dim == 0 || target_dim >= dim {
+            bail!("PCA truncation needs a target dimension between 1 and {}, got {}", dim - 1, target_dim);
+        }
+
+        let data = Array2::from_shape_vec((samples.len(), dim), samples.concat())
+            .map_err(|_| anyhow!("Samples have mixed dimensions"))?;
+        let mean = data.mean_axis(Axis(0)).ok_or_else(|| anyhow!("No samples to fit a projection"))?;
+        let centered = &data - &mean;
+
+        let mut rng = fastrand::Rng::with_seed(PCA_SEED);
+        let mut basis = Array2::from_shape_simple_fn((dim, target_dim), || rng.f32() - 0.5);
+        orthonormalize_columns(&mut basis);
+        for _ in 0..PCA_ITERATIONS {
+            basis = centered.t().dot(&centered.dot(&basis));
+            orthonormalize_columns(&mut basis);
+        }
+
+        Ok(Self::Pca { mean, components: basis.reversed_axes() })
+    }
+
+    pub fn output_dim(&self) -> usize {
+        match self {
+            Self::Learned(network) => network.num_outputs(),
+            Self::Pca { components, .. } => components.nrows(),
+        }
+    }
+
+    /// Project and normalize one embedding
+    pub fn project(&self, embedding: &[f32]) -> Vec<f32> {
+        let projected = match self {
+            Self::Learned(network) => network.run(embedding),
+            Self::Pca { mean, components } => {
+                components.dot(&(&Array1::from_vec(embedding.to_vec()) - mean)).to_vec()
+            }
+        };
+        normalize(projected)
+    }
+
+    /// Average `1 - cosine` between each projected sample and what it should have become:
+    /// the paired target for a learned map, or the original for PCA, measured on its
+    /// reconstruction from the kept components
+    pub fn average_cosine_drift(&self, inputs: &[Vec<f32>], targets: &[Vec<f32>]) -> f32 {
+        let drifts: Vec<f32> = match self {
+            Self::Learned(_) => inputs.iter().zip(targets)
+                .map(|(input, target)| 1.0 - cosine_similarity(&self.project(input), target))
+                .collect(),
+            Self::Pca { mean, components } => inputs.iter()
+                .map(|input| {
+                    let original = Array1::from_vec(input.clone());
+                    let reconstructed = components.t().dot(&components.dot(&(&original - mean))) + mean;
+                    1.0 - cosine_similarity(input, reconstructed.as_slice().unwrap_or_default())
+                })
+                .collect(),
+        };
+        if drifts.is_empty() {
+            0.0
+        } else {
+            drifts.iter().sum::<f32>() / drifts.len() as f32
+        }
+    }
+}
+
+/// Modified Gram-Schmidt; columns with no remaining variance are left at zero
+fn orthonormalize_columns(basis: &mut Array2<f32>) {
+    for j in 0..basis.ncols() {
+        for k in 0..j {
+            let previous = basis.column(k).to_owned();
+            let overlap = basis.column(j).dot(&previous);
+            basis.column_mut(j).scaled_add(-overlap, &previous);
+        }
+        let norm = basis.column(j).dot(&basis.column(j)).sqrt();
+        if norm > 1e-8 {
+            basis.column_mut(j).mapv_inplace(|x| x / norm);
+        } else {
+            basis.column_mut(j).fill(0.0);
+        }
+    }
+}
+
+fn normalize(mut embedding: Vec<f32>) -> Vec<f32> {
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+    embedding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_unit(rng: &mut fastrand::Rng, dim: usize) -> Vec<f32> {
+        normalize((0..dim).map(|_| rng.f32() - 0.5).collect())
+    }
+
+    #[test]
+    fn test_pca_keeps_low_rank_structure() {
+        // Samples spanned by two directions in 6 dimensions lose nothing when truncated to 2
+        let mut rng = fastrand::Rng::with_seed(7);
+        let (a, b) = (random_unit(&mut rng, 6), random_unit(&mut rng, 6));
+        let samples: Vec<Vec<f32>> = (0..40)
+            .map(|_| {
+                let (x, y) = (rng.f32() - 0.5, rng.f32() - 0.5);
+                a.iter().zip(&b).map(|(ai, bi)| x * ai + y * bi).collect()
+            })
+            .collect();
+
+        let pca = EmbeddingProjection::fit_pca(&samples, 2).unwrap();
+        assert_eq!(pca.output_dim(), 2);
+        assert_eq!(pca.project(&samples[0]).len(), 2);
+        assert!(pca.average_cosine_drift(&samples, &[]) < 0.01);
+        assert!(EmbeddingProjection::fit_pca(&samples, 6).is_err());
+    }
+
+    #[test]
+    fn test_learned_projection_recovers_linear_map() {
+        let mut rng = fastrand::Rng::with_seed(11);
+        let map: Vec<Vec<f32>> = (0..3).map(|_| random_unit(&mut rng, 6)).collect();
+        let inputs: Vec<Vec<f32>> = (0..100).map(|_| random_unit(&mut rng, 6)).collect();
+        let targets: Vec<Vec<f32>> = inputs.iter()
+            .map(|x| map.iter().map(|row| row.iter().zip(x).map(|(w, v)| w * v).sum()).collect())
+            .collect();
+
+        let projection = EmbeddingProjection::fit_linear(&inputs, &targets, 300).unwrap();
+        assert_eq!(projection.output_dim(), 3);
+        assert!(projection.average_cosine_drift(&inputs, &targets) < 0.02);
+    }
+}
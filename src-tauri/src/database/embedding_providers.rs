@@ -0,0 +1,306 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ai::{redact_error, redact_secrets};
+
+const OPENAI_EMBEDDINGS_URL: &str = "https://api.openai.com/v1/embeddings";
+const COHERE_EMBED_URL: &str = "https://api.cohere.com/v2/embed";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn default_openai_model() -> String {
+    "text-embedding-3-small".to_string()
+}
+
+fn default_cohere_model() -> String {
+    "embed-english-v3.0".to_string()
+}
+
+/// Where an agent's memory embeddings come from
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EmbeddingProviderConfig {
+    /// The built-in neural networks, trained on the agent's own memories
+    #[default]
+    Neural,
+    #[serde(rename = "openai")]
+    OpenAi {
+        #[serde(default = "default_openai_model")]
+        model: String,
+        /// Shortened output size, supported by the `text-embedding-3` models
+        #[serde(default)]
+        dimensions: Option<usize>,
+    },
+    Cohere {
+        #[serde(default = "default_cohere_model")]
+        model: String,
+    },
+    /// An encoder-only T5 model in GGUF format, with `config.json` and `tokenizer.json`
+    /// next to it. Requires the `local-embeddings` feature.
+    LocalGguf { model_path: PathBuf },
+}
+
+impl EmbeddingProviderConfig {
+    /// Identifies the embedding space, so cached vectors from different models never mix
+    pub fn cache_id(&self) -> String {
+        match self {
+            Self::Neural => "neural".to_string(),
+            Self::OpenAi { model, dimensions } => match dimensions {
+                Some(dimensions) => format!("openai:{}:{}", model, dimensions),
+                None => format!("openai:{}", model),
+            },
+            Self::Cohere { model } => format!("cohere:{}", model),
+            Self::LocalGguf { model_path } => format!("gguf:{}", model_path.display()),
+        }
+    }
+}
+
+/// An external embedding model
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    fn name(&self) -> String;
+
+    /// One vector per input text, in order
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Create the provider for a config; the built-in networks have none
+pub fn build_provider(config: &EmbeddingProviderConfig) -> Result<Option<Arc<dyn EmbeddingProvider>>> {
+    let provider: Arc<dyn EmbeddingProvider> = match config {
+        EmbeddingProviderConfig::Neural => return Ok(None),
+        EmbeddingProviderConfig::OpenAi { model, dimensions } => Arc::new(OpenAiEmbeddings {
+            client: http_client()?,
+            api_key: stored_api_key("openai")?,
+            model: model.clone(),
+            dimensions: *dimensions,
+        }),
+        EmbeddingProviderConfig::Cohere { model } => Arc::new(CohereEmbeddings {
+            client: http_client()?,
+            api_key: stored_api_key("cohere")?,
+            model: model.clone(),
+        }),
+        EmbeddingProviderConfig::LocalGguf { model_path } => local_gguf_provider(model_path)?,
+    };
+    Ok(Some(provider))
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?)
+}
+
+fn stored_api_key(provider: &str) -> Result<String> {
+    crate::ai::StorageManager::new()?
+        .get_api_key(provider)?
+        .ok_or_else(|| anyhow!("No {} API key is configured", provider))
+}
+
+async fn post_json(request: reqwest::RequestBuilder, body: &Value, provider: &str) -> Result<Value> {
+    // Provider errors can echo the request, key included, so both are redacted
+    let response = request.json(body).send().await
+        .map_err(|e| anyhow!("{} embedding request failed: {}", provider, redact_error(e)))?;
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        let detail: String = text.chars().take(500).collect();
+        bail!("{} embedding request failed with {}: {}", provider, status, redact_secrets(&detail).0);
+    }
+    serde_json::from_str(&text).with_context(|| format!("{} returned invalid JSON", provider))
+}
+
+fn parse_vector(value: &Value) -> Result<Vec<f32>> {
+    value.as_array()
+        .ok_or_else(|| anyhow!("Embedding is not an array"))?
+        .iter()
+        .map(|v| v.as_f64().map(|f| f as f32).ok_or_else(|| anyhow!("Embedding contains a non-number")))
+        .collect()
+}
+
+/// `data[].embedding`, ordered by `index` since OpenAI doesn't promise input order
+pub fn parse_openai_response(response: &Value, expected: usize) -> Result<Vec<Vec<f32>>> {
+    let mut data: Vec<&Value> = response["data"].as_array()
+        .ok_or_else(|| anyhow!("OpenAI response has no data"))?
+        .iter()
+        .collect();
+    data.sort_by_key(|item| item["index"].as_u64().unwrap_or(0));
+    let vectors = data.iter().map(|item| parse_vector(&item["embedding"])).collect::<Result<Vec<_>>>()?;
+    if vectors.len() != expected {
+        bail!("OpenAI returned {} embeddings for {} inputs", vectors.len(), expected);
+    }
+    Ok(vectors)
+}
+
+/// `embeddings.float`, in input order
+pub fn parse_cohere_response(response: &Value, expected: usize) -> Result<Vec<Vec<f32>>> {
+    let vectors = response["embeddings"]["float"].as_array()
+        .ok_or_else(|| anyhow!("Cohere response has no float embeddings"))?
+        .iter()
+        .map(parse_vector)
+        .collect::<Result<Vec<_>>>()?;
+    if vectors.len() != expected {
+        bail!("Cohere returned {} embeddings for {} inputs", vectors.len(), expected);
+    }
+    Ok(vectors)
+}
+
+pub struct OpenAiEmbeddings {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    dimensions: Option<usize>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiEmbeddings {
+    fn name(&self) -> String {
+        format!("openai:{}", self.model)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut body = json!({ "model": self.model, "input": texts });
+        if let Some(dimensions) = self.dimensions {
+            body["dimensions"] = json!(dimensions);
+        }
+        let request = self.client.post(OPENAI_EMBEDDINGS_URL).bearer_auth(&self.api_key);
+        parse_openai_response(&post_json(request, &body, "OpenAI").await?, texts.len())
+    }
+}
+
+pub struct CohereEmbeddings {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+}
+
+#[async_trait]
+impl EmbeddingProvider for CohereEmbeddings {
+    fn name(&self) -> String {
+        format!("cohere:{}", self.model)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        // Memories are both stored and searched, so they're embedded as documents
+        let body = json!({
+            "model": self.model,
+            "texts": texts,
+            "input_type": "search_document",
+            "embedding_types": ["float"],
+        });
+        let request = self.client.post(COHERE_EMBED_URL).bearer_auth(&self.api_key);
+        parse_cohere_response(&post_json(request, &body, "Cohere").await?, texts.len())
+    }
+}
+
+#[cfg(feature = "local-embeddings")]
+fn local_gguf_provider(model_path: &std::path::Path) -> Result<Arc<dyn EmbeddingProvider>> {
+    Ok(Arc::new(gguf::GgufEmbeddings::load(model_path)?))
+}
+
+#[cfg(not(feature = "local-embeddings"))]
+fn local_gguf_provider(_model_path: &std::path::Path) -> Result<Arc<dyn EmbeddingProvider>> {
+    bail!("This build does not include local embedding models")
+}
+
+#[cfg(feature = "local-embeddings")]
+mod gguf {
+    use super::EmbeddingProvider;
+    use anyhow::{anyhow, Context, Result};
+    use async_trait::async_trait;
+    use candle_core::{Device, Tensor};
+    use candle_transformers::models::quantized_t5;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use tokenizers::Tokenizer;
+
+    /// Encoder input is truncated to the model's context window
+    const MAX_INPUT_TOKENS: usize = 512;
+
+    struct Encoder {
+        model: quantized_t5::T5EncoderModel,
+        tokenizer: Tokenizer,
+        device: Device,
+    }
+
+    impl Encoder {
+        /// Mean of the encoder's final hidden states, as sentence-T5 models are trained for
+        fn embed(&mut self, text: &str) -> Result<Vec<f32>> {
+            let mut input_ids = self.tokenizer.encode(text, true)
+                .map_err(|e| anyhow!("Tokenization failed: {}", e))?
+                .get_ids()
+                .to_vec();
+            input_ids.truncate(MAX_INPUT_TOKENS);
+            let token_count = input_ids.len().max(1);
+
+            let input = Tensor::new(input_ids.as_slice(), &self.device)?.unsqueeze(0)?;
+            let hidden = self.model.forward(&input)?;
+            let pooled = (hidden.sum(1)? / token_count as f64)?;
+            Ok(pooled.squeeze(0)?.to_vec1::<f32>()?)
+        }
+    }
+
+    pub struct GgufEmbeddings {
+        path: PathBuf,
+        encoder: Arc<Mutex<Encoder>>,
+    }
+
+    impl GgufEmbeddings {
+        pub fn load(model_path: &Path) -> Result<Self> {
+            let dir = model_path.parent().context("Model path has no parent directory")?;
+            let device = Device::Cpu;
+            let config: quantized_t5::Config = serde_json::from_str(&std::fs::read_to_string(dir.join("config.json"))?)?;
+            let tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+                .map_err(|e| anyhow!("Failed to load tokenizer: {}", e))?;
+            let vb = quantized_t5::VarBuilder::from_gguf(model_path, &device)?;
+            let model = quantized_t5::T5EncoderModel::load(vb, &config)?;
+
+            Ok(Self {
+                path: model_path.to_path_buf(),
+                encoder: Arc::new(Mutex::new(Encoder { model, tokenizer, device })),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for GgufEmbeddings {
+        fn name(&self) -> String {
+            format!("gguf:{}", self.path.display())
+        }
+
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            let encoder = self.encoder.clone();
+            let texts = texts.to_vec();
+            tokio::task::spawn_blocking(move || {
+                let mut encoder = encoder.lock().map_err(|_| anyhow!("Local embedding model lock poisoned"))?;
+                texts.iter().map(|text| encoder.embed(text)).collect()
+            }).await?
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_provider_responses() {
+        let openai = json!({
+            "data": [
+                { "index": 1, "embedding": [0.5, 0.25] },
+                { "index": 0, "embedding": [1.0, 0.0] },
+            ]
+        });
+        assert_eq!(parse_openai_response(&openai, 2).unwrap(), vec![vec![1.0, 0.0], vec![0.5, 0.25]]);
+        assert!(parse_openai_response(&openai, 3).is_err());
+
+        let cohere = json!({ "embeddings": { "float": [[0.1, 0.2, 0.3]] } });
+        assert_eq!(parse_cohere_response(&cohere, 1).unwrap(), vec![vec![0.1, 0.2, 0.3]]);
+        assert!(parse_cohere_response(&json!({ "embeddings": [] }), 1).is_err());
+
+        let config: EmbeddingProviderConfig = serde_json::from_value(json!({ "kind": "openai" })).unwrap();
+        assert_eq!(config.cache_id(), "openai:text-embedding-3-small");
+        assert!(build_provider(&EmbeddingProviderConfig::Neural).unwrap().is_none());
+    }
+}
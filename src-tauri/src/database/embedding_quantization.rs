@@ -0,0 +1,241 @@
+//! Compact storage for embeddings: int8 values with one f32 scale per vector, a quarter of
+//! the size of the bincode `Vec<f32>` blobs written before. Both formats are read, so old
+//! databases keep working; `quantize_stored_embeddings` rewrites their blobs in place.
+
+use super::simple_commands::MemoryState;
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tracing::info;
+
+/// Marks a quantized blob. A bincode `Vec<f32>` starts with its length, and one starting
+/// with these bytes would have to hold over 20 million values.
+const MAGIC: [u8; 4] = *b"EQ8\x01";
+
+/// Magic plus the f32 scale
+const HEADER_LEN: usize = 8;
+
+/// Rows rewritten per transaction by `quantize_database`
+const QUANTIZE_BATCH_SIZE: usize = 500;
+
+/// Tables with an embedding blob, and the column each is keyed by
+const QUANTIZED_TABLES: [(&str, &str); 4] = [
+    ("agent_memories", "id"),
+    ("shared_knowledge", "id"),
+    ("knowledge_nodes", "id"),
+    ("embedding_cache", "content_hash"),
+];
+
+/// An embedding stored as `value = scale * q` with `q` in `-127..=127`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedEmbedding {
+    pub scale: f32,
+    pub values: Vec<i8>,
+}
+
+impl QuantizedEmbedding {
+    /// Scale so the largest magnitude maps to 127, keeping each value within half a step
+    pub fn quantize(embedding: &[f32]) -> Self {
+        let max_abs = embedding.iter().fold(0.0f32, |max, x| max.max(x.abs()));
+        let scale = if max_abs > 0.0 && max_abs.is_finite() { max_abs / 127.0 } else { 0.0 };
+        let values = embedding.iter()
+            .map(|x| if scale > 0.0 { (x / scale).round().clamp(-127.0, 127.0) as i8 } else { 0 })
+            .collect();
+        Self { scale, values }
+    }
+
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.values.iter().map(|&q| q as f32 * self.scale).collect()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.values.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&self.scale.to_le_bytes());
+        bytes.extend(self.values.iter().map(|&q| q as u8));
+        bytes
+    }
+
+    /// `None` unless `bytes` were written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if !is_quantized(bytes) {
+            return None;
+        }
+        let scale = f32::from_le_bytes(bytes[4..HEADER_LEN].try_into().ok()?);
+        let values = bytes[HEADER_LEN..].iter().map(|&b| b as i8).collect();
+        Some(Self { scale, values })
+    }
+}
+
+pub fn is_quantized(blob: &[u8]) -> bool {
+    blob.len() >= HEADER_LEN && blob[..4] == MAGIC
+}
+
+/// Blob to store for an embedding
+pub fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    QuantizedEmbedding::quantize(embedding).to_bytes()
+}
+
+/// Read a stored embedding in either format, dequantizing int8 blobs
+pub fn decode_embedding(blob: &[u8]) -> Option<Vec<f32>> {
+    match QuantizedEmbedding::from_bytes(blob) {
+        Some(quantized) => Some(quantized.dequantize()),
+        None => bincode::deserialize(blob).ok(),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuantizationReport {
+    pub rows_quantized: usize,
+    pub already_quantized: usize,
+    /// Blobs that are neither format; left untouched
+    pub unreadable: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Rewrite every f32 embedding blob in the database as int8, a batch per transaction
+pub fn quantize_database(conn: &mut Connection) -> Result<QuantizationReport> {
+    let mut report = QuantizationReport::default();
+    for (table, key_column) in QUANTIZED_TABLES {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            continue;
+        }
+
+        // Keyset pagination, so rewritten rows can't shift later batches
+        let mut last_key = String::new();
+        loop {
+            let batch: Vec<(String, Vec<u8>)> = {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {0}, embedding FROM {1} WHERE embedding IS NOT NULL AND {0} > ?1 ORDER BY {0} LIMIT ?2",
+                    key_column, table
+                ))?;
+                let rows = stmt.query_map(params![last_key, QUANTIZE_BATCH_SIZE], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()?
+            };
+            let Some((key, _)) = batch.last() else {
+                break;
+            };
+            last_key = key.clone();
+
+            let tx = conn.transaction()?;
+            {
+                let mut update = tx.prepare(&format!("UPDATE {} SET embedding = ?1 WHERE {} = ?2", table, key_column))?;
+                for (key, blob) in &batch {
+                    report.bytes_before += blob.len() as u64;
+                    if is_quantized(blob) {
+                        report.already_quantized += 1;
+                        report.bytes_after += blob.len() as u64;
+                        continue;
+                    }
+                    match bincode::deserialize::<Vec<f32>>(blob) {
+                        Ok(embedding) => {
+                            let quantized = encode_embedding(&embedding);
+                            report.bytes_after += quantized.len() as u64;
+                            update.execute(params![quantized, key])?;
+                            report.rows_quantized += 1;
+                        }
+                        Err(_) => {
+                            report.unreadable += 1;
+                            report.bytes_after += blob.len() as u64;
+                        }
+                    }
+                }
+            }
+            tx.commit()?;
+        }
+    }
+    Ok(report)
+}
+
+/// Convert an agent's stored embeddings, or the shared database's when no agent is given,
+/// to the int8 format
+#[tauri::command]
+pub async fn quantize_stored_embeddings(
+    agent_id: Option<String>,
+    state: State<'_, MemoryState>,
+) -> Result<QuantizationReport, String> {
+    let db_path = match agent_id {
+        Some(agent_id) => {
+            MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+            let validation_result = state.get_security_middleware().validate_request(
+                "memory_operations",
+                &[agent_id],
+                &[]
+            ).await?;
+            let manager = state.get_or_create_manager(validation_result.sanitized_inputs[0].clone())?;
+            manager.get_agent_db_path().clone()
+        }
+        None => super::simple_memory::SimpleMemoryManager::default_shared_db_path().map_err(|e| e.to_string())?,
+    };
+
+    let report = tokio::task::spawn_blocking(move || -> Result<QuantizationReport> {
+        let mut conn = super::pool::connection(&db_path)?;
+        quantize_database(&mut conn)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to quantize embeddings: {}", e))?;
+
+    info!(
+        "Quantized {} embeddings ({} -> {} bytes)",
+        report.rows_quantized, report.bytes_before, report.bytes_after
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+    use crate::database::neural_embeddings::cosine_similarity;
+
+    #[test]
+    fn test_quantized_round_trip_stays_close() {
+        let embedding: Vec<f32> = (0..256).map(|i| ((i as f32) * 0.37).sin() * 0.2).collect();
+        let blob = encode_embedding(&embedding);
+        assert_eq!(blob.len(), HEADER_LEN + embedding.len());
+
+        let decoded = decode_embedding(&blob).unwrap();
+        let max_error = embedding.iter().zip(&decoded).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        assert!(max_error <= 0.2 / 127.0);
+        assert!(cosine_similarity(&embedding, &decoded) > 0.9999);
+
+        // Older f32 blobs and all-zero vectors still read back
+        let legacy = bincode::serialize(&embedding).unwrap();
+        assert_eq!(decode_embedding(&legacy).unwrap(), embedding);
+        assert_eq!(decode_embedding(&encode_embedding(&[0.0; 4])).unwrap(), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_quantize_database_rewrites_legacy_blobs() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        for i in 0..3 {
+            let embedding = vec![i as f32, 1.0, -0.5, 0.25];
+            let blob = if i == 2 { encode_embedding(&embedding) } else { bincode::serialize(&embedding).unwrap() };
+            conn.execute(
+                "INSERT INTO agent_memories (id, agent_id, memory_type, content, metadata, embedding, created_at, updated_at, tags)
+                 VALUES (?1, 'agent', 'Context', 'text', '{}', ?2, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z', '[]')",
+                params![format!("m{}", i), blob],
+            ).unwrap();
+        }
+
+        let report = quantize_database(&mut conn).unwrap();
+        assert_eq!((report.rows_quantized, report.already_quantized, report.unreadable), (2, 1, 0));
+        assert!(report.bytes_after < report.bytes_before);
+
+        let blob: Vec<u8> = conn.query_row("SELECT embedding FROM agent_memories WHERE id = 'm1'", [], |row| row.get(0)).unwrap();
+        assert!(is_quantized(&blob));
+        let decoded = decode_embedding(&blob).unwrap();
+        assert!(decoded.iter().zip([1.0, 1.0, -0.5, 0.25]).all(|(a, b)| (a - b).abs() <= 0.5 / 127.0));
+    }
+}
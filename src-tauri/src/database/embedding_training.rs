@@ -0,0 +1,329 @@
+//! Background incremental training for the neural embedding networks.
+//!
+//! Each run trains a snapshot of the networks on memories created since the previous run,
+//! outside the service lock, then swaps the trained weights in and checkpoints them to disk.
+//! Interactive commands only ever wait for the snapshot and the swap.
+
+use super::memory::{AgentMemory, MemoryQuery};
+use super::neural_embeddings::NeuralEmbeddingService;
+use super::simple_commands::MemoryState;
+use super::simple_memory::SimpleMemoryManager;
+use crate::database::embeddings::SimilarityMetric;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::{error, info, warn};
+
+type ServiceHandle = Arc<AsyncMutex<Option<NeuralEmbeddingService>>>;
+
+fn default_interval_minutes() -> u64 {
+    60
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_epochs() -> usize {
+    10
+}
+
+fn default_initial_lookback_hours() -> i64 {
+    24
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingScheduleConfig {
+    pub enabled: bool,
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: u64,
+    /// Most memories trained on per run, newest first
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Epochs per network per run
+    #[serde(default = "default_epochs")]
+    pub epochs: usize,
+    /// How far back the first run looks, before there is a previous run to continue from
+    #[serde(default = "default_initial_lookback_hours")]
+    pub initial_lookback_hours: i64,
+}
+
+impl Default for TrainingScheduleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: default_interval_minutes(),
+            batch_size: default_batch_size(),
+            epochs: default_epochs(),
+            initial_lookback_hours: default_initial_lookback_hours(),
+        }
+    }
+}
+
+impl TrainingScheduleConfig {
+    fn validate(&self) -> Result<()> {
+        if !(1..=7 * 24 * 60).contains(&self.interval_minutes) {
+            return Err(anyhow!("Training interval must be between 1 minute and 7 days"));
+        }
+        if !(1..=10_000).contains(&self.batch_size) {
+            return Err(anyhow!("Training batch size must be between 1 and 10000"));
+        }
+        if !(1..=1_000).contains(&self.epochs) {
+            return Err(anyhow!("Training epochs must be between 1 and 1000"));
+        }
+        if self.initial_lookback_hours < 0 {
+            return Err(anyhow!("Initial lookback must not be negative"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingStatus {
+    pub config: TrainingScheduleConfig,
+    /// A run is in progress
+    pub training: bool,
+    pub completed_runs: u64,
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// Memories created before this have been trained on
+    pub trained_through: Option<DateTime<Utc>>,
+    pub last_memory_count: usize,
+    pub last_duration_ms: u64,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+struct TrainingScheduler {
+    status: Mutex<TrainingStatus>,
+    task: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+}
+
+static SCHEDULER: Lazy<TrainingScheduler> = Lazy::new(|| TrainingScheduler {
+    status: Mutex::new(TrainingStatus::default()),
+    task: Mutex::new(None),
+});
+
+fn update_status(update: impl FnOnce(&mut TrainingStatus)) {
+    if let Ok(mut status) = SCHEDULER.status.lock() {
+        update(&mut status);
+    }
+}
+
+fn current_status() -> TrainingStatus {
+    SCHEDULER.status.lock().map(|status| status.clone()).unwrap_or_default()
+}
+
+fn config_path() -> Result<PathBuf> {
+    Ok(SimpleMemoryManager::default_model_directory()?.join("training_schedule.json"))
+}
+
+fn load_config() -> Result<TrainingScheduleConfig> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(TrainingScheduleConfig::default());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+fn save_config(config: &TrainingScheduleConfig) -> Result<()> {
+    let path = config_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+/// Newest memories of every agent, in every workspace, created in `[since, until]`, at most `limit` in total
+fn recent_memories(since: DateTime<Utc>, until: DateTime<Utc>, limit: usize) -> Result<Vec<AgentMemory>> {
+    let mut memories = Vec::new();
+    for database in SimpleMemoryManager::list_agent_databases()? {
+        let manager = SimpleMemoryManager::for_workspace(database.agent_id.clone(), &database.workspace)?;
+        let results = manager.search_memories(&MemoryQuery {
+            agent_id: Some(database.agent_id),
+            memory_types: None,
+            content_search: None,
+            tags: None,
+            embedding: None,
+            similarity_threshold: None,
+            similarity_metric: SimilarityMetric::default(),
+            limit: Some(limit),
+            offset: None,
+            time_range: Some((since, until)),
+            tiers: None,
+            fusion: None,
+        })?;
+        memories.extend(results.into_iter().map(|result| result.memory));
+    }
+    memories.sort_by_key(|memory| std::cmp::Reverse(memory.created_at));
+    memories.truncate(limit);
+    Ok(memories)
+}
+
+/// Train a snapshot of the service's networks on `memories` and swap it in, unless the
+/// networks changed meanwhile (a reset, reload or manual training), in which case the run
+/// is discarded. Returns whether the trained weights were adopted.
+pub async fn train_in_background(service: &ServiceHandle, memories: Vec<AgentMemory>, epochs: usize) -> Result<bool> {
+    if memories.is_empty() {
+        return Ok(false);
+    }
+
+    let (mut snapshot, generation) = {
+        let service_lock = service.lock().await;
+        let current = service_lock.as_ref().ok_or_else(|| anyhow!("Neural embedding service not initialized"))?;
+        (current.training_snapshot(epochs), current.generation())
+    };
+
+    // Training is CPU-bound, so it runs on a blocking thread rather than an async worker
+    let trained = tokio::task::spawn_blocking(move || -> Result<NeuralEmbeddingService> {
+        futures::executor::block_on(snapshot.train_on_memories(&memories))?;
+        Ok(snapshot)
+    }).await??;
+
+    let mut service_lock = service.lock().await;
+    let current = service_lock.as_mut().ok_or_else(|| anyhow!("Neural embedding service not initialized"))?;
+    if current.generation() != generation {
+        warn!("Neural networks changed during background training; discarding this run");
+        return Ok(false);
+    }
+    current.adopt_networks(trained).await;
+    current.save_models()?;
+    Ok(true)
+}
+
+async fn run_once(service: &ServiceHandle, config: &TrainingScheduleConfig) -> Result<()> {
+    if service.lock().await.is_none() {
+        // Nothing has used embeddings yet this session
+        return Ok(());
+    }
+
+    let started = Utc::now();
+    let since = current_status().trained_through
+        .unwrap_or_else(|| started - Duration::hours(config.initial_lookback_hours));
+    let batch_size = config.batch_size;
+    let memories = tokio::task::spawn_blocking(move || recent_memories(since, started, batch_size)).await??;
+    let memory_count = memories.len();
+
+    update_status(|s| s.training = true);
+    let result = train_in_background(service, memories, config.epochs).await;
+    update_status(|s| {
+        s.training = false;
+        s.last_run_at = Some(started);
+        s.last_memory_count = memory_count;
+        s.last_duration_ms = (Utc::now() - started).num_milliseconds().max(0) as u64;
+        match &result {
+            Ok(_) => {
+                s.completed_runs += 1;
+                s.trained_through = Some(started);
+                s.last_error = None;
+            }
+            Err(e) => s.last_error = Some(e.to_string()),
+        }
+    });
+    if memory_count > 0 && result.is_ok() {
+        info!("Background training finished on {} memories", memory_count);
+    }
+    result.map(|_| ())
+}
+
+/// Replace any running schedule with one for `config`
+fn restart(service: ServiceHandle, config: TrainingScheduleConfig) {
+    if let Ok(mut task) = SCHEDULER.task.lock() {
+        if let Some(task) = task.take() {
+            task.abort();
+        }
+        let enabled = config.enabled;
+        update_status(|s| {
+            s.config = config.clone();
+            s.training = false;
+            s.next_run_at = None;
+        });
+        if !enabled {
+            return;
+        }
+
+        let interval = std::time::Duration::from_secs(config.interval_minutes * 60);
+        *task = Some(tauri::async_runtime::spawn(async move {
+            loop {
+                update_status(|s| {
+                    s.next_run_at = Some(Utc::now() + Duration::from_std(interval).unwrap_or_default());
+                });
+                tokio::time::sleep(interval).await;
+                if let Err(e) = run_once(&service, &config).await {
+                    error!("Background neural training failed: {}", e);
+                }
+            }
+        }));
+    }
+}
+
+/// Resume the schedule saved by an earlier session
+pub fn spawn_training_scheduler(service: ServiceHandle) {
+    match load_config() {
+        Ok(config) => restart(service, config),
+        Err(e) => error!("Failed to load neural training schedule: {}", e),
+    }
+}
+
+/// Enable, disable or change the background training schedule
+#[tauri::command]
+pub async fn configure_embedding_training(
+    config: TrainingScheduleConfig,
+    state: State<'_, MemoryState>,
+) -> Result<TrainingStatus, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    save_config(&config).map_err(|e| format!("Failed to save training schedule: {}", e))?;
+    info!("Neural training schedule {}", if config.enabled { "enabled" } else { "disabled" });
+    restart(state.neural_embedding_handle(), config);
+    Ok(current_status())
+}
+
+#[tauri::command]
+pub async fn get_training_status() -> Result<TrainingStatus, String> {
+    Ok(current_status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory::MemoryType;
+    use crate::database::neural_embeddings::EmbeddingConfig;
+
+    #[tokio::test]
+    async fn test_background_training_swaps_in_trained_networks() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EmbeddingConfig {
+            embedding_dim: 16,
+            model_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let service: ServiceHandle = Arc::new(AsyncMutex::new(Some(
+            NeuralEmbeddingService::new(Some(config)).await.unwrap()
+        )));
+        let memories = vec![
+            AgentMemory::new("agent".to_string(), MemoryType::Learning, "Rust ownership rules".to_string()),
+            AgentMemory::new("agent".to_string(), MemoryType::Task, "Write the release notes".to_string()),
+        ];
+
+        let before = service.lock().await.as_ref().unwrap().embed_text("Rust ownership rules", None).await.unwrap();
+        assert!(train_in_background(&service, memories.clone(), 2).await.unwrap());
+        let after = service.lock().await.as_ref().unwrap().embed_text("Rust ownership rules", None).await.unwrap();
+        assert_ne!(before, after);
+        assert!(dir.path().join("embedding_general.bin").exists());
+
+        // Runs snapshot the generation, so a reset meanwhile would make them stale
+        let generation = service.lock().await.as_ref().unwrap().generation();
+        service.lock().await.as_mut().unwrap().reset_models().await.unwrap();
+        assert_ne!(service.lock().await.as_ref().unwrap().generation(), generation);
+
+        assert!(!train_in_background(&service, Vec::new(), 2).await.unwrap());
+        TrainingScheduleConfig { batch_size: 0, ..Default::default() }.validate().unwrap_err();
+    }
+}
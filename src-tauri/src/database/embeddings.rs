@@ -3,6 +3,11 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use sha2::{Sha256, Digest};
+use serde::{Deserialize, Serialize};
+use rayon::prelude::*;
+
+/// Below this many candidates, scoring on one thread is faster than splitting the work
+const PARALLEL_SIMILARITY_THRESHOLD: usize = 256;
 
 // Note: Old EmbeddingService and TransformerEmbeddingService have been removed
 // and replaced with NeuralEmbeddingService in neural_embeddings.rs
@@ -24,6 +29,44 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     }
 }
 
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// How two embeddings are compared; must match the metric the embedding model was trained for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimilarityMetric {
+    #[default]
+    Cosine,
+    DotProduct,
+    Euclidean,
+}
+
+impl SimilarityMetric {
+    /// Similarity score where higher is more similar; Euclidean distance maps to `1 / (1 + d)`
+    pub fn similarity(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            SimilarityMetric::Cosine => cosine_similarity(a, b),
+            SimilarityMetric::DotProduct => dot_product(a, b),
+            SimilarityMetric::Euclidean => 1.0 / (1.0 + euclidean_distance(a, b)),
+        }
+    }
+
+    /// Score `query` against every candidate, in order, in parallel for large candidate sets
+    pub fn similarities(&self, query: &[f32], candidates: &[&[f32]]) -> Vec<f32> {
+        if candidates.len() < PARALLEL_SIMILARITY_THRESHOLD {
+            candidates.iter().map(|candidate| self.similarity(query, candidate)).collect()
+        } else {
+            candidates.par_iter().map(|candidate| self.similarity(query, candidate)).collect()
+        }
+    }
+}
+
 pub fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
         return f32::INFINITY;
@@ -147,6 +190,22 @@ mod tests {
         assert_eq!(cosine_similarity(&a, &d), 1.0);
     }
 
+    #[test]
+    fn test_similarity_metrics_rank_unnormalized_embeddings() {
+        let query = vec![1.0, 0.0];
+        let short = vec![1.0, 0.0];
+        let long = vec![3.0, 0.5];
+
+        // Cosine ignores magnitude, dot product rewards it, Euclidean penalizes distance
+        assert!(SimilarityMetric::Cosine.similarity(&query, &short) > SimilarityMetric::Cosine.similarity(&query, &long));
+        assert!(SimilarityMetric::DotProduct.similarity(&query, &long) > SimilarityMetric::DotProduct.similarity(&query, &short));
+        assert_eq!(SimilarityMetric::Euclidean.similarity(&query, &short), 1.0);
+        assert!(SimilarityMetric::Euclidean.similarity(&query, &long) < 0.5);
+
+        let parsed: SimilarityMetric = serde_json::from_str("\"dot_product\"").unwrap();
+        assert_eq!(parsed, SimilarityMetric::DotProduct);
+    }
+
     #[test]
     fn test_k_means_clustering() {
         let embeddings = vec![
@@ -0,0 +1,342 @@
+use super::graph_commands::{parse_node_type, parse_relationship_type};
+use super::memory::{KnowledgeEdge, KnowledgeNode};
+use crate::ai::{ensure_disk_space, SecurityMiddleware};
+use crate::validation::GraphValidator;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+use tracing::{info, warn};
+
+/// Most items accepted in one batch call
+pub const MAX_BATCH_ITEMS: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchNodeInput {
+    pub node_type: String,
+    pub name: String,
+    pub properties: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchEdgeInput {
+    pub from_node: String,
+    pub to_node: String,
+    pub relationship_type: String,
+    pub weight: Option<f32>,
+    pub properties: Option<HashMap<String, String>>,
+}
+
+/// Outcome for one item of a batch, in request order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchItemResult {
+    pub index: usize,
+    pub id: Option<String>,
+    pub error: Option<String>,
+}
+
+impl BatchItemResult {
+    fn created(index: usize, id: String) -> Self {
+        Self { index, id: Some(id), error: None }
+    }
+
+    fn failed(index: usize, error: String) -> Self {
+        Self { index, id: None, error: Some(error) }
+    }
+}
+
+/// Run every string of the batch through the security middleware in one request, so the
+/// whole batch costs one rate-limit token. Returns the sanitized agent id and per-item strings.
+async fn sanitize_batch(
+    middleware: &SecurityMiddleware,
+    agent_id: &str,
+    items: Vec<Vec<String>>,
+) -> Result<(String, Vec<Vec<String>>), String> {
+    let mut inputs = vec![agent_id.to_string()];
+    let lengths: Vec<usize> = items.iter().map(|item| item.len()).collect();
+    inputs.extend(items.into_iter().flatten());
+
+    let mut sanitized = middleware.validate_request("graph_operations", &inputs, &[]).await?
+        .sanitized_inputs
+        .into_iter();
+    let agent_id = sanitized.next().unwrap_or_default();
+    let items = lengths.into_iter().map(|len| sanitized.by_ref().take(len).collect()).collect();
+    Ok((agent_id, items))
+}
+
+/// Flatten properties into alternating keys and values
+fn flatten_properties(properties: &Option<HashMap<String, String>>, out: &mut Vec<String>) {
+    for (k, v) in properties.iter().flatten() {
+        out.push(k.clone());
+        out.push(v.clone());
+    }
+}
+
+fn collect_properties(rest: &[String], agent_id: &str) -> HashMap<String, String> {
+    let mut properties: HashMap<String, String> = rest.chunks_exact(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+    properties.insert("agent_id".to_string(), agent_id.to_string());
+    properties.insert("created_at".to_string(), chrono::Utc::now().to_rfc3339());
+    properties
+}
+
+fn validate_properties(properties: &Option<HashMap<String, String>>) -> Result<(), String> {
+    match properties {
+        Some(props) => GraphValidator::validate_properties(props).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Validate each node and build the valid ones; failures are recorded in `results`
+fn build_nodes(
+    agent_id: &str,
+    inputs: &[BatchNodeInput],
+    results: &mut [Option<BatchItemResult>],
+) -> Vec<(usize, KnowledgeNode)> {
+    let mut nodes = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let built = GraphValidator::validate_node_name(&input.name).map_err(|e| e.to_string())
+            .and_then(|_| GraphValidator::validate_node_type(&input.node_type).map_err(|e| e.to_string()))
+            .and_then(|_| validate_properties(&input.properties))
+            .and_then(|_| parse_node_type(&input.node_type))
+            .map(|node_type| {
+                let mut node = KnowledgeNode::new(node_type, input.name.clone());
+                let mut flat = Vec::new();
+                flatten_properties(&input.properties, &mut flat);
+                node.properties = collect_properties(&flat, agent_id);
+                node
+            });
+        match built {
+            Ok(node) => nodes.push((index, node)),
+            Err(e) => results[index] = Some(BatchItemResult::failed(index, e)),
+        }
+    }
+    nodes
+}
+
+/// Validate each edge against `known_nodes` and build the valid ones; failures are recorded in `results`
+fn build_edges(
+    agent_id: &str,
+    inputs: &[BatchEdgeInput],
+    known_nodes: &HashSet<String>,
+    results: &mut [Option<BatchItemResult>],
+) -> Vec<(usize, KnowledgeEdge)> {
+    let mut edges = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let built = GraphValidator::validate_node_id(&input.from_node).map_err(|e| e.to_string())
+            .and_then(|_| GraphValidator::validate_node_id(&input.to_node).map_err(|e| e.to_string()))
+            .and_then(|_| GraphValidator::validate_relationship_type(&input.relationship_type).map_err(|e| e.to_string()))
+            .and_then(|_| match input.weight {
+                Some(weight) => GraphValidator::validate_weight(weight).map_err(|e| e.to_string()),
+                None => Ok(()),
+            })
+            .and_then(|_| validate_properties(&input.properties))
+            .and_then(|_| if input.from_node == input.to_node {
+                Err("Self-loops are not allowed".to_string())
+            } else {
+                Ok(())
+            })
+            .and_then(|_| match [&input.from_node, &input.to_node].into_iter().find(|id| !known_nodes.contains(*id)) {
+                Some(missing) => Err(format!("Node not found: {}", missing)),
+                None => Ok(()),
+            })
+            .and_then(|_| parse_relationship_type(&input.relationship_type))
+            .map(|relationship| {
+                let mut edge = KnowledgeEdge::new(input.from_node.clone(), input.to_node.clone(), relationship);
+                if let Some(weight) = input.weight {
+                    edge = edge.with_weight(weight);
+                }
+                let mut flat = Vec::new();
+                flatten_properties(&input.properties, &mut flat);
+                edge.properties = collect_properties(&flat, agent_id);
+                edge
+            });
+        match built {
+            Ok(edge) => edges.push((index, edge)),
+            Err(e) => results[index] = Some(BatchItemResult::failed(index, e)),
+        }
+    }
+    edges
+}
+
+/// Fill in results for the items that were written, or that failed with the whole write
+fn finish_results(
+    mut results: Vec<Option<BatchItemResult>>,
+    written: Vec<(usize, String)>,
+    write_error: Option<String>,
+) -> Vec<BatchItemResult> {
+    for (index, id) in written {
+        results[index] = Some(match &write_error {
+            Some(e) => BatchItemResult::failed(index, e.clone()),
+            None => BatchItemResult::created(index, id),
+        });
+    }
+    results.into_iter().flatten().collect()
+}
+
+fn check_batch_size(len: usize) -> Result<(), String> {
+    if len == 0 || len > MAX_BATCH_ITEMS {
+        return Err(format!("Batch must contain between 1 and {} items", MAX_BATCH_ITEMS));
+    }
+    Ok(())
+}
+
+/// Create many graph nodes in one transaction; invalid items are reported and skipped
+#[tauri::command]
+pub async fn create_graph_nodes_batch(
+    agent_id: String,
+    nodes: Vec<BatchNodeInput>,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<Vec<BatchItemResult>, String> {
+    info!("Creating {} graph node(s) for agent: {}", nodes.len(), agent_id);
+
+    GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    check_batch_size(nodes.len())?;
+
+    let security_middleware = state.get_security_middleware();
+    let items = nodes.iter().map(|node| {
+        let mut item = vec![node.node_type.clone(), node.name.clone()];
+        flatten_properties(&node.properties, &mut item);
+        item
+    }).collect();
+    let (sanitized_agent_id, sanitized) = sanitize_batch(&security_middleware, &agent_id, items).await?;
+    let inputs: Vec<BatchNodeInput> = sanitized.into_iter().map(|item| BatchNodeInput {
+        node_type: item[0].clone(),
+        name: item[1].clone(),
+        properties: Some(item[2..].chunks_exact(2).map(|p| (p[0].clone(), p[1].clone())).collect()),
+    }).collect();
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let mut results = vec![None; inputs.len()];
+    let built = build_nodes(&sanitized_agent_id, &inputs, &mut results);
+
+    let estimate: usize = built.iter().map(|(_, n)| n.name.len() + n.properties.iter().map(|(k, v)| k.len() + v.len()).sum::<usize>()).sum();
+    ensure_disk_space(manager.get_shared_db_path(), (estimate * 4) as u64, "graph batch write")
+        .map_err(|e| e.to_string())?;
+
+    let (indices, nodes): (Vec<usize>, Vec<KnowledgeNode>) = built.into_iter().unzip();
+    let write_error = manager.add_knowledge_batch(&nodes, &[]).err()
+        .map(|e| format!("Failed to add knowledge nodes: {}", e));
+    if let Some(ref e) = write_error {
+        warn!("{}", e);
+    }
+
+    let written = indices.into_iter().zip(nodes.into_iter().map(|n| n.id)).collect();
+    Ok(finish_results(results, written, write_error))
+}
+
+/// Create many graph edges in one transaction; invalid items are reported and skipped
+#[tauri::command]
+pub async fn create_graph_edges_batch(
+    agent_id: String,
+    edges: Vec<BatchEdgeInput>,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<Vec<BatchItemResult>, String> {
+    info!("Creating {} graph edge(s) for agent: {}", edges.len(), agent_id);
+
+    GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    check_batch_size(edges.len())?;
+
+    let security_middleware = state.get_security_middleware();
+    let items = edges.iter().map(|edge| {
+        let mut item = vec![edge.from_node.clone(), edge.to_node.clone(), edge.relationship_type.clone()];
+        flatten_properties(&edge.properties, &mut item);
+        item
+    }).collect();
+    let (sanitized_agent_id, sanitized) = sanitize_batch(&security_middleware, &agent_id, items).await?;
+    let inputs: Vec<BatchEdgeInput> = sanitized.into_iter().zip(&edges).map(|(item, edge)| BatchEdgeInput {
+        from_node: item[0].clone(),
+        to_node: item[1].clone(),
+        relationship_type: item[2].clone(),
+        weight: edge.weight,
+        properties: Some(item[3..].chunks_exact(2).map(|p| (p[0].clone(), p[1].clone())).collect()),
+    }).collect();
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let endpoints: Vec<&str> = inputs.iter()
+        .flat_map(|e| [e.from_node.as_str(), e.to_node.as_str()])
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let known_nodes = manager.existing_knowledge_node_ids(&endpoints)
+        .map_err(|e| format!("Failed to look up nodes: {}", e))?;
+
+    let mut results = vec![None; inputs.len()];
+    let built = build_edges(&sanitized_agent_id, &inputs, &known_nodes, &mut results);
+
+    let estimate: usize = built.iter().map(|(_, e)| 128 + e.properties.iter().map(|(k, v)| k.len() + v.len()).sum::<usize>()).sum();
+    ensure_disk_space(manager.get_shared_db_path(), (estimate * 4) as u64, "graph batch write")
+        .map_err(|e| e.to_string())?;
+
+    let (indices, edges): (Vec<usize>, Vec<KnowledgeEdge>) = built.into_iter().unzip();
+    let write_error = manager.add_knowledge_batch(&[], &edges).err()
+        .map(|e| format!("Failed to add knowledge edges: {}", e));
+    if let Some(ref e) = write_error {
+        warn!("{}", e);
+    }
+
+    let written = indices.into_iter().zip(edges.into_iter().map(|e| e.id)).collect();
+    Ok(finish_results(results, written, write_error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_input(node_type: &str, name: &str) -> BatchNodeInput {
+        BatchNodeInput { node_type: node_type.to_string(), name: name.to_string(), properties: None }
+    }
+
+    fn edge_input(from: &str, to: &str, weight: Option<f32>) -> BatchEdgeInput {
+        BatchEdgeInput {
+            from_node: from.to_string(),
+            to_node: to.to_string(),
+            relationship_type: "DependsOn".to_string(),
+            weight,
+            properties: None,
+        }
+    }
+
+    #[test]
+    fn test_node_batch_reports_each_item() {
+        let inputs = vec![node_input("Concept", "Rust"), node_input("Planet", "Mars"), node_input("Tool", "Cargo")];
+        let mut results = vec![None; inputs.len()];
+        let built = build_nodes("agent-1", &inputs, &mut results);
+
+        assert_eq!(built.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(built[0].1.properties["agent_id"], "agent-1");
+
+        let written = built.iter().map(|(i, n)| (*i, n.id.clone())).collect();
+        let results = finish_results(results, written, None);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].id.as_deref(), Some(built[0].1.id.as_str()));
+        assert!(results[1].error.as_deref().unwrap().contains("node type"));
+        assert!(results[2].error.is_none());
+    }
+
+    #[test]
+    fn test_edge_batch_checks_endpoints_and_write_failure() {
+        let (a, b, ghost) = (uuid::Uuid::new_v4().to_string(), uuid::Uuid::new_v4().to_string(), uuid::Uuid::new_v4().to_string());
+        let known: HashSet<String> = [a.clone(), b.clone()].into_iter().collect();
+        let inputs = vec![
+            edge_input(&a, &b, Some(0.4)),
+            edge_input(&a, &ghost, None),
+            edge_input(&a, &a, None),
+            edge_input(&b, &a, Some(7.0)),
+        ];
+        let mut results = vec![None; inputs.len()];
+        let built = build_edges("agent-1", &inputs, &known, &mut results);
+        assert_eq!(built.len(), 1);
+        assert_eq!(built[0].1.weight, 0.4);
+
+        let written = built.iter().map(|(i, e)| (*i, e.id.clone())).collect();
+        let results = finish_results(results, written, Some("disk full".to_string()));
+        let errors: Vec<&str> = results.iter().map(|r| r.error.as_deref().unwrap()).collect();
+        assert_eq!(errors[0], "disk full");
+        assert_eq!(errors[1], format!("Node not found: {}", ghost));
+        assert_eq!(errors[2], "Self-loops are not allowed");
+        assert!(results.iter().all(|r| r.id.is_none()));
+    }
+}
@@ -21,6 +21,10 @@
 use crate::ai::{SecurityManager, SecurityMiddleware};
 use crate::validation::{GraphValidator, ValidationError};
 use super::memory::*;
+use super::graph_optimize::{
+    apply_plan, plan_optimization, storage_bytes, GraphOptimizationReport, GraphSizeSummary,
+    DEFAULT_MIN_EDGE_WEIGHT, DEFAULT_NODE_SIMILARITY_THRESHOLD,
+};
 use super::simple_memory::SimpleMemoryManager;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -72,6 +76,9 @@ pub struct GraphQuery {
     pub start_node: Option<String>,
     pub depth: Option<usize>,
     pub limit: Option<usize>,
+    /// Show the graph as it was at this time instead of now
+    #[serde(default)]
+    pub as_of: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -472,17 +479,23 @@ pub async fn get_graph_view(
         &[query.agent_id.clone()],
         &[]
     ).await?;
-    
-    // In a real implementation, build graph view from data
-    let graph_view = GraphView {
-        nodes: vec![],
-        edges: vec![],
-        selected_node: None,
-        selected_edge: None,
-        zoom: 1.0,
-        center: [0.0, 0.0],
+
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    // Business Logic
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let (nodes, edges) = match query.as_of {
+        Some(as_of) => {
+            let conn = manager.shared_connection()
+                .map_err(|e| format!("Failed to open graph storage: {}", e))?;
+            super::graph_history::graph_as_of(&conn, sanitized_agent_id, as_of)
+                .map_err(|e| format!("Failed to load knowledge graph as of {}: {}", as_of, e))?
+        }
+        None => manager.load_knowledge_graph()
+            .map_err(|e| format!("Failed to load knowledge graph: {}", e))?,
     };
-    
+
+    let graph_view = build_graph_view(nodes, edges, &query);
     Ok(graph_view)
 }
 
@@ -491,11 +504,13 @@ pub async fn find_graph_path(
     from_node: String,
     to_node: String,
     max_depth: Option<usize>,
+    max_paths: Option<usize>,
+    use_heuristic: Option<bool>,
     agent_id: String,
     state: State<'_, super::simple_commands::MemoryState>,
 ) -> Result<Vec<PathResult>, String> {
     info!("Finding path from {} to {} for agent: {}", from_node, to_node, agent_id);
-    
+
     // Validation
     GraphValidator::validate_agent_id(&agent_id)
         .map_err(|e| e.to_string())?;
@@ -503,15 +518,46 @@ pub async fn find_graph_path(
         .map_err(|e| e.to_string())?;
     GraphValidator::validate_node_id(&to_node)
         .map_err(|e| e.to_string())?;
-    
+
     let depth = max_depth.unwrap_or(6);
     if depth == 0 || depth > 10 {
         return Err("Max depth must be between 1 and 10".to_string());
     }
-    
-    // Security and pathfinding would happen here
-    warn!("Find graph path not fully implemented - would require graph traversal support");
-    Ok(vec![])
+
+    let path_limit = max_paths.unwrap_or(5);
+    if path_limit == 0 || path_limit > 50 {
+        return Err("Max paths must be between 1 and 50".to_string());
+    }
+
+    // Security
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "graph_operations",
+        &[from_node.clone(), to_node.clone(), agent_id.clone()],
+        &[]
+    ).await?;
+
+    let sanitized_from_node = &validation_result.sanitized_inputs[0];
+    let sanitized_to_node = &validation_result.sanitized_inputs[1];
+    let sanitized_agent_id = &validation_result.sanitized_inputs[2];
+
+    // Pathfinding over the stored graph
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let (nodes, edges) = manager.load_knowledge_graph()
+        .map_err(|e| format!("Failed to load knowledge graph: {}", e))?;
+
+    let paths = super::graph_paths::ranked_paths(
+        &nodes,
+        &edges,
+        sanitized_from_node,
+        sanitized_to_node,
+        depth,
+        path_limit,
+        use_heuristic.unwrap_or(false),
+    );
+
+    info!("Found {} path(s) from {} to {}", paths.len(), sanitized_from_node, sanitized_to_node);
+    Ok(paths)
 }
 
 #[tauri::command]
@@ -539,6 +585,60 @@ pub async fn get_graph_neighbors(
     Ok(vec![])
 }
 
+/// Weighted neighbourhood of `seed_node`, strongest connections first
+#[tauri::command]
+pub async fn get_subgraph(
+    seed_node: String,
+    depth: Option<usize>,
+    max_nodes: Option<usize>,
+    agent_id: String,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<super::simple_commands::KnowledgeGraphView, String> {
+    info!("Extracting subgraph around {} for agent: {}", seed_node, agent_id);
+
+    // Validation
+    GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    GraphValidator::validate_node_id(&seed_node)
+        .map_err(|e| e.to_string())?;
+
+    let search_depth = depth.unwrap_or(2);
+    if search_depth == 0 || search_depth > 10 {
+        return Err("Depth must be between 1 and 10".to_string());
+    }
+    let node_limit = max_nodes.unwrap_or(100);
+    if node_limit == 0 || node_limit > 500 {
+        return Err("Max nodes must be between 1 and 500".to_string());
+    }
+
+    // Security
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "graph_operations",
+        &[seed_node.clone(), agent_id.clone()],
+        &[]
+    ).await?;
+
+    let sanitized_seed_node = &validation_result.sanitized_inputs[0];
+    let sanitized_agent_id = &validation_result.sanitized_inputs[1];
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let (nodes, edges) = manager.load_knowledge_graph()
+        .map_err(|e| format!("Failed to load knowledge graph: {}", e))?;
+    if !nodes.iter().any(|n| &n.id == sanitized_seed_node) {
+        return Err(format!("Node not found: {}", sanitized_seed_node));
+    }
+
+    Ok(super::simple_commands::subgraph_view(
+        sanitized_agent_id,
+        &nodes,
+        &edges,
+        sanitized_seed_node,
+        search_depth,
+        node_limit,
+    ))
+}
+
 #[tauri::command]
 pub async fn get_graph_stats(
     agent_id: String,
@@ -593,14 +693,25 @@ pub async fn find_graph_clusters(
 #[tauri::command]
 pub async fn optimize_graph(
     agent_id: String,
+    min_edge_weight: Option<f32>,
+    node_similarity_threshold: Option<f32>,
     state: State<'_, super::simple_commands::MemoryState>,
-) -> Result<(), String> {
+) -> Result<GraphOptimizationReport, String> {
     info!("Optimizing graph for agent: {}", agent_id);
-    
+
     // Validation
     GraphValidator::validate_agent_id(&agent_id)
         .map_err(|e| e.to_string())?;
-    
+
+    let min_edge_weight = min_edge_weight.unwrap_or(DEFAULT_MIN_EDGE_WEIGHT);
+    GraphValidator::validate_weight(min_edge_weight)
+        .map_err(|e| e.to_string())?;
+
+    let similarity_threshold = node_similarity_threshold.unwrap_or(DEFAULT_NODE_SIMILARITY_THRESHOLD);
+    if !(similarity_threshold > 0.0 && similarity_threshold <= 1.0) {
+        return Err("Node similarity threshold must be greater than 0.0 and at most 1.0".to_string());
+    }
+
     // Security
     let security_middleware = state.get_security_middleware();
     let validation_result = security_middleware.validate_request(
@@ -608,14 +719,116 @@ pub async fn optimize_graph(
         &[agent_id.clone()],
         &[]
     ).await?;
-    
-    // Optimization would happen here
-    warn!("Optimize graph not fully implemented - would require graph optimization algorithms");
-    Ok(())
+
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    // Business Logic
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let (nodes, edges) = manager.load_knowledge_graph()
+        .map_err(|e| format!("Failed to load knowledge graph: {}", e))?;
+    let metric = state.similarity_metric().await;
+
+    let mut conn = manager.shared_connection()
+        .map_err(|e| format!("Failed to open graph storage: {}", e))?;
+
+    let mut report = GraphOptimizationReport {
+        before: GraphSizeSummary {
+            node_count: nodes.len(),
+            edge_count: edges.len(),
+            storage_bytes: storage_bytes(&conn).map_err(|e| e.to_string())?,
+        },
+        ..Default::default()
+    };
+
+    let plan = plan_optimization(&nodes, &edges, min_edge_weight, similarity_threshold, metric);
+    apply_plan(&mut conn, &plan)
+        .map_err(|e| format!("Failed to optimize graph: {}", e))?;
+    report.merged_edges = plan.merged_edges;
+    report.pruned_edges = plan.pruned_edges;
+    report.removed_self_loops = plan.removed_self_loops;
+    report.collapsed_nodes = plan.node_merges.len();
+
+    // VACUUM rewrites the whole database into a temporary copy first
+    match crate::ai::ensure_disk_space(manager.get_shared_db_path(), report.before.storage_bytes, "graph vacuum") {
+        Ok(()) => {
+            conn.execute_batch("VACUUM;")
+                .map_err(|e| format!("Failed to vacuum graph storage: {}", e))?;
+            report.vacuumed = true;
+        }
+        Err(e) => warn!("Skipping graph vacuum: {}", e),
+    }
+
+    let (nodes, edges) = manager.load_knowledge_graph()
+        .map_err(|e| format!("Failed to load knowledge graph: {}", e))?;
+    report.after = GraphSizeSummary {
+        node_count: nodes.len(),
+        edge_count: edges.len(),
+        storage_bytes: storage_bytes(&conn).map_err(|e| e.to_string())?,
+    };
+
+    info!(
+        "Optimized graph for agent {}: {} edge(s) merged, {} pruned, {} node(s) collapsed",
+        sanitized_agent_id, report.merged_edges, report.pruned_edges, report.collapsed_nodes
+    );
+    Ok(report)
 }
 
 // Helper functions
-fn parse_node_type(node_type: &str) -> Result<NodeType, String> {
+/// Filter the agent's graph down to the requested types, neighbourhood and size
+fn build_graph_view(nodes: Vec<KnowledgeNode>, edges: Vec<KnowledgeEdge>, query: &GraphQuery) -> GraphView {
+    let type_matches = |node: &KnowledgeNode| query.node_types.as_ref()
+        .map_or(true, |types| types.contains(&format!("{:?}", node.node_type)));
+    let relationship_matches = |edge: &KnowledgeEdge| query.relationship_types.as_ref()
+        .map_or(true, |types| types.contains(&format!("{:?}", edge.relationship_type)));
+
+    let mut nodes: Vec<KnowledgeNode> = nodes.into_iter()
+        .filter(type_matches)
+        .map(|node| KnowledgeNode { embedding: None, ..node })
+        .collect();
+    let mut edges: Vec<KnowledgeEdge> = edges.into_iter().filter(relationship_matches).collect();
+
+    // Breadth-first neighbourhood of the start node, in either edge direction
+    if let Some(ref start) = query.start_node {
+        let depth = query.depth.unwrap_or(2);
+        let present: std::collections::HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+        let mut reached: HashMap<String, usize> = HashMap::new();
+        if present.contains(start.as_str()) {
+            reached.insert(start.clone(), 0);
+        }
+        let mut frontier = vec![start.clone()];
+        for hop in 1..=depth {
+            let mut next = Vec::new();
+            for edge in &edges {
+                for (a, b) in [(&edge.from_node, &edge.to_node), (&edge.to_node, &edge.from_node)] {
+                    if frontier.contains(a) && present.contains(b.as_str()) && !reached.contains_key(b) {
+                        reached.insert(b.clone(), hop);
+                        next.push(b.clone());
+                    }
+                }
+            }
+            frontier = next;
+        }
+        nodes.retain(|n| reached.contains_key(&n.id));
+        nodes.sort_by_key(|n| reached[&n.id]);
+    }
+
+    if let Some(limit) = query.limit {
+        nodes.truncate(limit);
+    }
+    let kept: std::collections::HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+    edges.retain(|e| kept.contains(e.from_node.as_str()) && kept.contains(e.to_node.as_str()));
+
+    GraphView {
+        nodes,
+        edges,
+        selected_node: query.start_node.clone(),
+        selected_edge: None,
+        zoom: 1.0,
+        center: [0.0, 0.0],
+    }
+}
+
+pub(super) fn parse_node_type(node_type: &str) -> Result<NodeType, String> {
     match node_type {
         "Agent" => Ok(NodeType::Agent),
         "Memory" => Ok(NodeType::Memory),
@@ -628,7 +841,7 @@ fn parse_node_type(node_type: &str) -> Result<NodeType, String> {
     }
 }
 
-fn parse_relationship_type(rel_type: &str) -> Result<RelationshipType, String> {
+pub(super) fn parse_relationship_type(rel_type: &str) -> Result<RelationshipType, String> {
     match rel_type {
         "Knows" => Ok(RelationshipType::Knows),
         "Uses" => Ok(RelationshipType::Uses),
@@ -0,0 +1,655 @@
+use super::memory::{KnowledgeEdge, KnowledgeNode, NodeType, RelationshipType};
+use crate::validation::GraphValidator;
+use anyhow::{anyhow, bail, Result};
+use quick_xml::escape::{escape, resolve_predefined_entity};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use tauri::State;
+use tracing::info;
+
+/// Largest import file accepted
+const MAX_IMPORT_BYTES: usize = 32 * 1024 * 1024;
+
+/// Validation errors listed back to the user; the rest are counted
+const MAX_REPORTED_ERRORS: usize = 50;
+
+/// Node type and relationship assumed when a file written by another tool leaves them out
+const DEFAULT_IMPORT_NODE_TYPE: &str = "Concept";
+const DEFAULT_IMPORT_RELATIONSHIP: &str = "Similar";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphExportFormat {
+    /// GraphML, readable by Gephi, yEd and NetworkX
+    Graphml,
+    /// GEXF 1.3, Gephi's native format
+    Gexf,
+    /// Cypher CREATE statements for Neo4j
+    Cypher,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphImportSummary {
+    pub nodes_imported: usize,
+    pub edges_imported: usize,
+}
+
+/// A node or edge as read from a file, before validation
+#[derive(Debug, Default, Clone)]
+struct ImportedItem {
+    id: String,
+    name: Option<String>,
+    kind: Option<String>,
+    source: String,
+    target: String,
+    weight: Option<String>,
+    properties: HashMap<String, String>,
+}
+
+impl ImportedItem {
+    /// Route a named attribute to its field; anything unrecognised becomes a property
+    fn set(&mut self, key: &str, value: String) {
+        match key {
+            "name" | "label" if self.name.is_none() => self.name = Some(value),
+            "node_type" | "relationship_type" => self.kind = Some(value),
+            "weight" => self.weight = Some(value),
+            "properties" => {
+                if let Ok(map) = serde_json::from_str::<HashMap<String, String>>(&value) {
+                    self.properties.extend(map);
+                }
+            }
+            "id" => {}
+            _ => {
+                self.properties.insert(key.to_string(), value);
+            }
+        }
+    }
+}
+
+fn properties_json(properties: &HashMap<String, String>) -> String {
+    serde_json::to_string(properties).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn to_graphml(nodes: &[KnowledgeNode], edges: &[KnowledgeEdge]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>\n",
+        "  <key id=\"node_type\" for=\"node\" attr.name=\"node_type\" attr.type=\"string\"/>\n",
+        "  <key id=\"relationship_type\" for=\"edge\" attr.name=\"relationship_type\" attr.type=\"string\"/>\n",
+        "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n",
+        "  <key id=\"properties\" for=\"all\" attr.name=\"properties\" attr.type=\"string\"/>\n",
+        "  <graph id=\"knowledge\" edgedefault=\"directed\">\n",
+    ));
+    for node in nodes {
+        let _ = writeln!(
+            out,
+            "    <node id=\"{}\"><data key=\"name\">{}</data><data key=\"node_type\">{:?}</data><data key=\"properties\">{}</data></node>",
+            escape(node.id.as_str()), escape(node.name.as_str()), node.node_type, escape(properties_json(&node.properties)),
+        );
+    }
+    for edge in edges {
+        let _ = writeln!(
+            out,
+            "    <edge id=\"{}\" source=\"{}\" target=\"{}\"><data key=\"relationship_type\">{:?}</data><data key=\"weight\">{}</data><data key=\"properties\">{}</data></edge>",
+            escape(edge.id.as_str()), escape(edge.from_node.as_str()), escape(edge.to_node.as_str()),
+            edge.relationship_type, edge.weight, escape(properties_json(&edge.properties)),
+        );
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn to_gexf(nodes: &[KnowledgeNode], edges: &[KnowledgeEdge]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n",
+        "  <graph defaultedgetype=\"directed\" mode=\"static\">\n",
+        "    <attributes class=\"node\">\n",
+        "      <attribute id=\"node_type\" title=\"node_type\" type=\"string\"/>\n",
+        "      <attribute id=\"properties\" title=\"properties\" type=\"string\"/>\n",
+        "    </attributes>\n",
+        "    <attributes class=\"edge\">\n",
+        "      <attribute id=\"relationship_type\" title=\"relationship_type\" type=\"string\"/>\n",
+        "      <attribute id=\"properties\" title=\"properties\" type=\"string\"/>\n",
+        "    </attributes>\n",
+        "    <nodes>\n",
+    ));
+    for node in nodes {
+        let _ = writeln!(
+            out,
+            "      <node id=\"{}\" label=\"{}\"><attvalues><attvalue for=\"node_type\" value=\"{:?}\"/><attvalue for=\"properties\" value=\"{}\"/></attvalues></node>",
+            escape(node.id.as_str()), escape(node.name.as_str()), node.node_type, escape(properties_json(&node.properties)),
+        );
+    }
+    out.push_str("    </nodes>\n    <edges>\n");
+    for edge in edges {
+        let _ = writeln!(
+            out,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\" weight=\"{}\" label=\"{:?}\"><attvalues><attvalue for=\"relationship_type\" value=\"{:?}\"/><attvalue for=\"properties\" value=\"{}\"/></attvalues></edge>",
+            escape(edge.id.as_str()), escape(edge.from_node.as_str()), escape(edge.to_node.as_str()), edge.weight,
+            edge.relationship_type, edge.relationship_type, escape(properties_json(&edge.properties)),
+        );
+    }
+    out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    out
+}
+
+fn cypher_string(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('\'', "\\'").replace('\n', "\\n").replace('\r', "\\r");
+    format!("'{}'", escaped)
+}
+
+/// `{id: '…', key: '…'}`; property keys named like the reserved fields are left out
+fn cypher_map(fixed: &[(&str, String)], properties: &HashMap<String, String>) -> String {
+    let mut entries: Vec<String> = fixed.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+    let mut keys: Vec<&String> = properties.keys()
+        .filter(|k| !fixed.iter().any(|(reserved, _)| reserved == k))
+        .collect();
+    keys.sort();
+    entries.extend(keys.into_iter().map(|k| format!("`{}`: {}", k.replace('`', "``"), cypher_string(&properties[k]))));
+    format!("{{{}}}", entries.join(", "))
+}
+
+fn to_cypher(nodes: &[KnowledgeNode], edges: &[KnowledgeEdge]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        let map = cypher_map(&[("id", cypher_string(&node.id)), ("name", cypher_string(&node.name))], &node.properties);
+        let _ = writeln!(out, "CREATE (:{:?} {});", node.node_type, map);
+    }
+    for edge in edges {
+        let map = cypher_map(&[("id", cypher_string(&edge.id)), ("weight", edge.weight.to_string())], &edge.properties);
+        let _ = writeln!(
+            out,
+            "MATCH (a {{id: {}}}), (b {{id: {}}}) CREATE (a)-[:{:?} {}]->(b);",
+            cypher_string(&edge.from_node), cypher_string(&edge.to_node), edge.relationship_type, map,
+        );
+    }
+    out
+}
+
+pub fn export_graph(nodes: &[KnowledgeNode], edges: &[KnowledgeEdge], format: GraphExportFormat) -> String {
+    match format {
+        GraphExportFormat::Graphml => to_graphml(nodes, edges),
+        GraphExportFormat::Gexf => to_gexf(nodes, edges),
+        GraphExportFormat::Cypher => to_cypher(nodes, edges),
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Result<Option<String>> {
+    for attr in element.attributes() {
+        let attr = attr?;
+        if attr.key.local_name().as_ref() == name {
+            return Ok(Some(attr.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn element_item(element: &BytesStart) -> Result<ImportedItem> {
+    let mut item = ImportedItem {
+        id: attribute(element, b"id")?.unwrap_or_default(),
+        source: attribute(element, b"source")?.unwrap_or_default(),
+        target: attribute(element, b"target")?.unwrap_or_default(),
+        weight: attribute(element, b"weight")?,
+        ..Default::default()
+    };
+    if let Some(label) = attribute(element, b"label")? {
+        item.set("label", label);
+    }
+    Ok(item)
+}
+
+/// Shared reader for GraphML (`<data key>` text) and GEXF (`<attvalue for value>`)
+fn parse_xml(content: &str) -> Result<(Vec<ImportedItem>, Vec<ImportedItem>)> {
+    // Text is only collected inside <data>, and trimming would eat the spaces around entity refs
+    let mut reader = Reader::from_str(content);
+
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    // (element class, key or attribute id) -> attribute name
+    let mut keys: HashMap<(String, String), String> = HashMap::new();
+    let mut attributes_class = String::new();
+    let mut current: Option<(bool, ImportedItem)> = None;
+    let mut data_key: Option<String> = None;
+    let mut text = String::new();
+
+    let key_name = |keys: &HashMap<(String, String), String>, is_edge: bool, id: &str| {
+        let class = if is_edge { "edge" } else { "node" };
+        keys.get(&(class.to_string(), id.to_string()))
+            .or_else(|| keys.get(&("all".to_string(), id.to_string())))
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    loop {
+        let event = reader.read_event()?;
+        let (element, empty) = match &event {
+            Event::Start(e) => (Some(e), false),
+            Event::Empty(e) => (Some(e), true),
+            _ => (None, false),
+        };
+
+        if let Some(e) = element {
+            match e.local_name().as_ref() {
+                b"key" => {
+                    let id = attribute(e, b"id")?.unwrap_or_default();
+                    let name = attribute(e, b"attr.name")?.unwrap_or_else(|| id.clone());
+                    let class = attribute(e, b"for")?.unwrap_or_else(|| "all".to_string());
+                    keys.insert((class, id), name);
+                }
+                b"attributes" => attributes_class = attribute(e, b"class")?.unwrap_or_default(),
+                b"attribute" => {
+                    let id = attribute(e, b"id")?.unwrap_or_default();
+                    let title = attribute(e, b"title")?.unwrap_or_else(|| id.clone());
+                    keys.insert((attributes_class.clone(), id), title);
+                }
+                tag @ (b"node" | b"edge") => {
+                    let item = (tag == b"edge", element_item(e)?);
+                    if empty {
+                        if item.0 { edges.push(item.1) } else { nodes.push(item.1) }
+                    } else {
+                        current = Some(item);
+                    }
+                }
+                b"data" => {
+                    data_key = attribute(e, b"key")?;
+                    text.clear();
+                }
+                b"attvalue" => {
+                    if let Some((is_edge, item)) = current.as_mut() {
+                        let id = attribute(e, b"for")?.or(attribute(e, b"id")?).unwrap_or_default();
+                        let value = attribute(e, b"value")?.unwrap_or_default();
+                        item.set(&key_name(&keys, *is_edge, &id), value);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Text(t) if data_key.is_some() => text.push_str(&t.decode()?),
+            Event::CData(t) if data_key.is_some() => text.push_str(&t.decode()?),
+            Event::GeneralRef(r) if data_key.is_some() => {
+                if let Some(c) = r.resolve_char_ref()? {
+                    text.push(c);
+                } else {
+                    let name = r.decode()?;
+                    text.push_str(resolve_predefined_entity(&name).ok_or_else(|| anyhow!("Unknown entity &{};", name))?);
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"data" => {
+                    if let (Some(key), Some((is_edge, item))) = (data_key.take(), current.as_mut()) {
+                        item.set(&key_name(&keys, *is_edge, &key), std::mem::take(&mut text));
+                    }
+                }
+                b"node" | b"edge" => {
+                    if let Some((is_edge, item)) = current.take() {
+                        if is_edge { edges.push(item) } else { nodes.push(item) }
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok((nodes, edges))
+}
+
+/// Cursor over the Cypher this module writes
+struct CypherCursor<'a> {
+    rest: &'a str,
+}
+
+impl<'a> CypherCursor<'a> {
+    fn expect(&mut self, token: &str) -> Result<()> {
+        self.rest = self.rest.trim_start();
+        self.rest = self.rest.strip_prefix(token)
+            .ok_or_else(|| anyhow!("Expected '{}' near: {}", token, self.rest.chars().take(40).collect::<String>()))?;
+        Ok(())
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        self.rest = self.rest.trim_start();
+        if let Some(quoted) = self.rest.strip_prefix('`') {
+            let end = quoted.find('`').ok_or_else(|| anyhow!("Unterminated identifier"))?;
+            self.rest = &quoted[end + 1..];
+            return Ok(quoted[..end].to_string());
+        }
+        let end = self.rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(self.rest.len());
+        if end == 0 {
+            bail!("Expected an identifier near: {}", self.rest.chars().take(40).collect::<String>());
+        }
+        let ident = self.rest[..end].to_string();
+        self.rest = &self.rest[end..];
+        Ok(ident)
+    }
+
+    fn value(&mut self) -> Result<String> {
+        self.rest = self.rest.trim_start();
+        let mut chars = self.rest.char_indices();
+        match chars.next() {
+            Some((_, quote @ ('\'' | '"'))) => {
+                let mut value = String::new();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 'r')) => value.push('\r'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, other)) => value.push(other),
+                            None => break,
+                        },
+                        c if c == quote => {
+                            self.rest = &self.rest[i + 1..];
+                            return Ok(value);
+                        }
+                        c => value.push(c),
+                    }
+                }
+                bail!("Unterminated string")
+            }
+            _ => {
+                let end = self.rest.find([',', '}']).unwrap_or(self.rest.len());
+                let value = self.rest[..end].trim().to_string();
+                self.rest = &self.rest[end..];
+                Ok(value)
+            }
+        }
+    }
+
+    fn map(&mut self) -> Result<Vec<(String, String)>> {
+        self.expect("{")?;
+        let mut entries = Vec::new();
+        loop {
+            self.rest = self.rest.trim_start();
+            if let Some(rest) = self.rest.strip_prefix('}') {
+                self.rest = rest;
+                return Ok(entries);
+            }
+            if !entries.is_empty() {
+                self.expect(",")?;
+            }
+            let key = self.identifier()?;
+            self.expect(":")?;
+            entries.push((key, self.value()?));
+        }
+    }
+}
+
+fn parse_cypher(content: &str) -> Result<(Vec<ImportedItem>, Vec<ImportedItem>)> {
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let mut cursor = CypherCursor { rest: line };
+        let parsed: Result<()> = (|| {
+            if line.starts_with("CREATE") {
+                cursor.expect("CREATE")?;
+                cursor.expect("(")?;
+                cursor.expect(":")?;
+                let mut item = ImportedItem { kind: Some(cursor.identifier()?), ..Default::default() };
+                for (key, value) in cursor.map()? {
+                    if key == "id" { item.id = value } else { item.set(&key, value) }
+                }
+                cursor.expect(")")?;
+                nodes.push(item);
+            } else {
+                cursor.expect("MATCH")?;
+                cursor.expect("(a")?;
+                let source = cursor.map()?;
+                cursor.expect("),")?;
+                cursor.expect("(b")?;
+                let target = cursor.map()?;
+                cursor.expect(")")?;
+                cursor.expect("CREATE")?;
+                cursor.expect("(a)-[:")?;
+                let id_of = |map: &[(String, String)]| map.iter().find(|(k, _)| k == "id").map(|(_, v)| v.clone()).unwrap_or_default();
+                let mut item = ImportedItem {
+                    source: id_of(&source),
+                    target: id_of(&target),
+                    kind: Some(cursor.identifier()?),
+                    ..Default::default()
+                };
+                if cursor.rest.trim_start().starts_with('{') {
+                    for (key, value) in cursor.map()? {
+                        if key == "id" { item.id = value } else { item.set(&key, value) }
+                    }
+                }
+                cursor.expect("]->(b)")?;
+                edges.push(item);
+            }
+            cursor.expect(";")
+        })();
+        parsed.map_err(|e| anyhow!("Line {}: {}", line_number + 1, e))?;
+    }
+
+    Ok((nodes, edges))
+}
+
+fn parse_import(content: &str, format: GraphExportFormat) -> Result<(Vec<ImportedItem>, Vec<ImportedItem>)> {
+    match format {
+        GraphExportFormat::Graphml | GraphExportFormat::Gexf => parse_xml(content),
+        GraphExportFormat::Cypher => parse_cypher(content),
+    }
+}
+
+fn parse_node_type(value: &str) -> NodeType {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).unwrap_or(NodeType::Concept)
+}
+
+fn parse_relationship_type(value: &str) -> RelationshipType {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).unwrap_or(RelationshipType::Similar)
+}
+
+/// Validate every item with `GraphValidator` and give each a fresh id owned by `agent_id`.
+/// Any invalid item rejects the whole import.
+fn prepare_import(
+    agent_id: &str,
+    nodes: Vec<ImportedItem>,
+    edges: Vec<ImportedItem>,
+) -> Result<(Vec<KnowledgeNode>, Vec<KnowledgeEdge>), Vec<String>> {
+    let mut errors = Vec::new();
+    let mut id_map: HashMap<String, String> = HashMap::new();
+    let mut prepared_nodes = Vec::new();
+    let mut prepared_edges = Vec::new();
+
+    for (i, item) in nodes.into_iter().enumerate() {
+        let label = if item.id.is_empty() { format!("node #{}", i + 1) } else { format!("node {}", item.id) };
+        let name = item.name.clone().unwrap_or_else(|| item.id.clone());
+        let node_type = item.kind.clone().unwrap_or_else(|| DEFAULT_IMPORT_NODE_TYPE.to_string());
+        let mut properties = item.properties;
+        properties.insert("agent_id".to_string(), agent_id.to_string());
+
+        let checks = [
+            GraphValidator::validate_node_name(&name).map_err(|e| e.to_string()),
+            GraphValidator::validate_node_type(&node_type).map_err(|e| e.to_string()),
+            GraphValidator::validate_properties(&properties).map_err(|e| e.to_string()),
+        ];
+        let failures: Vec<String> = checks.into_iter().filter_map(|c| c.err()).collect();
+        if item.id.is_empty() || id_map.contains_key(&item.id) {
+            errors.push(format!("{}: missing or duplicate id", label));
+        }
+        if !failures.is_empty() {
+            errors.extend(failures.into_iter().map(|f| format!("{}: {}", label, f)));
+            continue;
+        }
+
+        let mut node = KnowledgeNode::new(parse_node_type(&node_type), name);
+        node.properties = properties;
+        id_map.insert(item.id, node.id.clone());
+        prepared_nodes.push(node);
+    }
+
+    for (i, item) in edges.into_iter().enumerate() {
+        let label = if item.id.is_empty() { format!("edge #{}", i + 1) } else { format!("edge {}", item.id) };
+        let relationship = item.kind.clone().unwrap_or_else(|| DEFAULT_IMPORT_RELATIONSHIP.to_string());
+        let weight = match item.weight.as_deref().map(|w| w.trim().parse::<f32>()) {
+            None => Ok(1.0),
+            Some(Ok(w)) => GraphValidator::validate_weight(w).map(|_| w).map_err(|e| e.to_string()),
+            Some(Err(_)) => Err(format!("Invalid weight: {}", item.weight.as_deref().unwrap_or_default())),
+        };
+        let mut properties = item.properties;
+        properties.insert("agent_id".to_string(), agent_id.to_string());
+
+        let mut failures: Vec<String> = [
+            GraphValidator::validate_relationship_type(&relationship).map_err(|e| e.to_string()),
+            GraphValidator::validate_properties(&properties).map_err(|e| e.to_string()),
+            weight.as_ref().map(|_| ()).map_err(|e| e.clone()),
+        ].into_iter().filter_map(|c| c.err()).collect();
+        let (from, to) = (id_map.get(&item.source), id_map.get(&item.target));
+        if from.is_none() || to.is_none() {
+            failures.push(format!("endpoints {} -> {} are not nodes in the file", item.source, item.target));
+        } else if from == to {
+            failures.push("self-loops are not allowed".to_string());
+        }
+        if !failures.is_empty() {
+            errors.extend(failures.into_iter().map(|f| format!("{}: {}", label, f)));
+            continue;
+        }
+
+        let mut edge = KnowledgeEdge::new(from.unwrap().clone(), to.unwrap().clone(), parse_relationship_type(&relationship))
+            .with_weight(weight.unwrap_or(1.0));
+        edge.properties = properties;
+        prepared_edges.push(edge);
+    }
+
+    if errors.is_empty() {
+        Ok((prepared_nodes, prepared_edges))
+    } else {
+        Err(errors)
+    }
+}
+
+#[tauri::command]
+pub async fn export_knowledge_graph(
+    agent_id: String,
+    format: GraphExportFormat,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<String, String> {
+    info!("Exporting knowledge graph for agent {} as {:?}", agent_id, format);
+
+    GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "graph_operations",
+        &[agent_id.clone()],
+        &[]
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let (nodes, edges) = manager.load_knowledge_graph()
+        .map_err(|e| format!("Failed to load knowledge graph: {}", e))?;
+    Ok(export_graph(&nodes, &edges, format))
+}
+
+#[tauri::command]
+pub async fn import_knowledge_graph(
+    agent_id: String,
+    format: GraphExportFormat,
+    content: String,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<GraphImportSummary, String> {
+    info!("Importing {:?} knowledge graph for agent {}", format, agent_id);
+
+    GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    if content.len() > MAX_IMPORT_BYTES {
+        return Err(format!("Import exceeds {} bytes", MAX_IMPORT_BYTES));
+    }
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "graph_operations",
+        &[agent_id.clone()],
+        &[]
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    let (nodes, edges) = parse_import(&content, format)
+        .map_err(|e| format!("Failed to parse {:?} import: {}", format, e))?;
+    let (nodes, edges) = prepare_import(sanitized_agent_id, nodes, edges).map_err(|errors| {
+        let shown: Vec<&String> = errors.iter().take(MAX_REPORTED_ERRORS).collect();
+        let more = errors.len().saturating_sub(MAX_REPORTED_ERRORS);
+        let mut message = format!("Import rejected with {} validation error(s):\n{}", errors.len(),
+            shown.iter().map(|e| e.as_str()).collect::<Vec<_>>().join("\n"));
+        if more > 0 {
+            message.push_str(&format!("\n…and {} more", more));
+        }
+        message
+    })?;
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    crate::ai::ensure_disk_space(manager.get_shared_db_path(), content.len() as u64 * 2, "knowledge graph import")
+        .map_err(|e| e.to_string())?;
+    manager.add_knowledge_batch(&nodes, &edges)
+        .map_err(|e| format!("Failed to import knowledge graph: {}", e))?;
+
+    info!("Imported {} node(s) and {} edge(s) for agent {}", nodes.len(), edges.len(), sanitized_agent_id);
+    Ok(GraphImportSummary { nodes_imported: nodes.len(), edges_imported: edges.len() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (Vec<KnowledgeNode>, Vec<KnowledgeEdge>) {
+        let mut rust = KnowledgeNode::new(NodeType::Concept, "Rust <systems> & \"safe\"".to_string());
+        rust.properties.insert("source".to_string(), "it's docs\nline 2".to_string());
+        let cargo = KnowledgeNode::new(NodeType::Tool, "Cargo".to_string());
+        let mut edge = KnowledgeEdge::new(cargo.id.clone(), rust.id.clone(), RelationshipType::DependsOn).with_weight(0.75);
+        edge.properties.insert("note".to_string(), "build tool".to_string());
+        (vec![rust, cargo], vec![edge])
+    }
+
+    #[test]
+    fn test_formats_round_trip() {
+        let (nodes, edges) = fixture();
+        for format in [GraphExportFormat::Graphml, GraphExportFormat::Gexf, GraphExportFormat::Cypher] {
+            let exported = export_graph(&nodes, &edges, format);
+            let (parsed_nodes, parsed_edges) = parse_import(&exported, format).unwrap();
+            let (imported_nodes, imported_edges) = prepare_import("agent-2", parsed_nodes, parsed_edges).unwrap();
+
+            assert_eq!(imported_nodes.len(), 2, "{:?}", format);
+            assert_eq!(imported_nodes[0].name, nodes[0].name, "{:?}", format);
+            assert_eq!(imported_nodes[0].properties["source"], "it's docs\nline 2", "{:?}", format);
+            assert!(matches!(imported_nodes[1].node_type, NodeType::Tool), "{:?}", format);
+
+            let edge = &imported_edges[0];
+            assert_eq!((edge.weight, edge.properties["note"].as_str()), (0.75, "build tool"), "{:?}", format);
+            assert!(matches!(edge.relationship_type, RelationshipType::DependsOn), "{:?}", format);
+            // Imports get fresh ids owned by the importing agent
+            assert_eq!(edge.from_node, imported_nodes[1].id);
+            assert_ne!(edge.from_node, nodes[1].id);
+            assert_eq!(edge.properties["agent_id"], "agent-2");
+        }
+    }
+
+    #[test]
+    fn test_import_validation_rejects_whole_file() {
+        let graphml = r#"<graphml><key id="d0" for="node" attr.name="node_type"/>
+            <graph><node id="a"><data key="d0">Concept</data></node><node id="b"><data key="d0">Planet</data></node>
+            <edge source="a" target="missing" /></graph></graphml>"#;
+        let (nodes, edges) = parse_import(graphml, GraphExportFormat::Graphml).unwrap();
+        let errors = prepare_import("agent-1", nodes, edges).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("node b: Invalid node type"));
+        assert!(errors[1].contains("not nodes in the file"));
+
+        assert!(parse_import("CREATE (:Concept {id: 'x', name: 'unterminated);", GraphExportFormat::Cypher).is_err());
+    }
+}
@@ -0,0 +1,493 @@
+use super::memory::{KnowledgeEdge, KnowledgeNode, NodeType, RelationshipType};
+use super::simple_memory::SimpleMemoryManager;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tauri::State;
+use tracing::{error, info, warn};
+
+/// An agent gets a new snapshot once its latest one is this old
+const SNAPSHOT_INTERVAL_HOURS: i64 = 24;
+
+/// How often the scheduler looks for agents due a snapshot
+const SNAPSHOT_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Every snapshot younger than this is kept
+const DAILY_SNAPSHOT_RETENTION_DAYS: i64 = 30;
+
+/// Past the daily window one snapshot per week is kept, up to this age
+const WEEKLY_SNAPSHOT_RETENTION_DAYS: i64 = 365;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphSnapshotInfo {
+    pub id: String,
+    pub agent_id: String,
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub taken_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphHistoryPruned {
+    pub snapshots: usize,
+    pub history_rows: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotData {
+    nodes: Vec<KnowledgeNode>,
+    edges: Vec<KnowledgeEdge>,
+}
+
+/// Row image written by the history triggers
+#[derive(Debug, Deserialize)]
+struct NodeRecord {
+    id: String,
+    node_type: NodeType,
+    name: String,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EdgeRecord {
+    id: String,
+    from_node: String,
+    to_node: String,
+    relationship_type: RelationshipType,
+    weight: Option<f32>,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+fn timestamp(value: Option<&str>) -> DateTime<Utc> {
+    value.map(super::parse_db_timestamp).unwrap_or_else(Utc::now)
+}
+
+impl From<NodeRecord> for KnowledgeNode {
+    fn from(record: NodeRecord) -> Self {
+        KnowledgeNode {
+            created_at: timestamp(record.created_at.as_deref()),
+            updated_at: timestamp(record.updated_at.as_deref()),
+            id: record.id,
+            node_type: record.node_type,
+            name: record.name,
+            properties: record.properties,
+            embedding: None,
+        }
+    }
+}
+
+impl From<EdgeRecord> for KnowledgeEdge {
+    fn from(record: EdgeRecord) -> Self {
+        KnowledgeEdge {
+            created_at: timestamp(record.created_at.as_deref()),
+            updated_at: timestamp(record.updated_at.as_deref()),
+            id: record.id,
+            from_node: record.from_node,
+            to_node: record.to_node,
+            relationship_type: record.relationship_type,
+            weight: record.weight.unwrap_or(1.0),
+            properties: record.properties,
+        }
+    }
+}
+
+/// Same format the history triggers write, so timestamps compare as text
+fn history_timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Matches the scope of `SimpleMemoryManager::load_knowledge_graph`
+fn scope_to_agent(
+    agent_id: &str,
+    nodes: HashMap<String, KnowledgeNode>,
+    edges: HashMap<String, KnowledgeEdge>,
+) -> (Vec<KnowledgeNode>, Vec<KnowledgeEdge>) {
+    let nodes: Vec<KnowledgeNode> = nodes.into_values()
+        .filter(|n| {
+            n.properties.get("agent_id").is_some_and(|a| a == agent_id)
+                || (matches!(n.node_type, NodeType::Agent) && (n.name == agent_id || n.id == agent_id))
+        })
+        .collect();
+    let owned: HashSet<&str> = nodes.iter()
+        .filter(|n| n.properties.get("agent_id").is_some_and(|a| a == agent_id))
+        .map(|n| n.id.as_str())
+        .collect();
+    let edges = edges.into_values()
+        .filter(|e| e.properties.get("agent_id").is_some_and(|a| a == agent_id) || owned.contains(e.from_node.as_str()))
+        .collect();
+    (nodes, edges)
+}
+
+/// The agent's graph as it stood at `as_of`: the newest snapshot taken by then, with later history replayed on top
+pub fn graph_as_of(conn: &Connection, agent_id: &str, as_of: DateTime<Utc>) -> Result<(Vec<KnowledgeNode>, Vec<KnowledgeEdge>)> {
+    let cutoff = history_timestamp(as_of);
+
+    let base: Option<(i64, String)> = conn.query_row(
+        "SELECT history_seq, data FROM knowledge_graph_snapshots WHERE agent_id = ?1 AND taken_at <= ?2 ORDER BY taken_at DESC LIMIT 1",
+        params![agent_id, cutoff],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?;
+
+    let mut nodes: HashMap<String, KnowledgeNode> = HashMap::new();
+    let mut edges: HashMap<String, KnowledgeEdge> = HashMap::new();
+    let mut from_seq = 0;
+    if let Some((seq, data)) = base {
+        let snapshot: SnapshotData = serde_json::from_str(&data)?;
+        nodes.extend(snapshot.nodes.into_iter().map(|n| (n.id.clone(), n)));
+        edges.extend(snapshot.edges.into_iter().map(|e| (e.id.clone(), e)));
+        from_seq = seq;
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT entity_kind, entity_id, operation, data FROM knowledge_graph_history WHERE seq > ?1 AND changed_at <= ?2 ORDER BY seq"
+    )?;
+    let rows = stmt.query_map(params![from_seq, cutoff], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+    })?;
+
+    for row in rows {
+        let (kind, entity_id, operation, data) = row?;
+        match (kind.as_str(), operation.as_str()) {
+            ("node", "delete") => { nodes.remove(&entity_id); }
+            ("edge", "delete") => { edges.remove(&entity_id); }
+            ("node", _) => match serde_json::from_str::<NodeRecord>(&data) {
+                Ok(record) => { nodes.insert(entity_id, record.into()); }
+                Err(e) => warn!("Skipping unreadable node history for {}: {}", entity_id, e),
+            },
+            _ => match serde_json::from_str::<EdgeRecord>(&data) {
+                Ok(record) => { edges.insert(entity_id, record.into()); }
+                Err(e) => warn!("Skipping unreadable edge history for {}: {}", entity_id, e),
+            },
+        }
+    }
+
+    Ok(scope_to_agent(agent_id, nodes, edges))
+}
+
+/// Latest history row, recorded with a snapshot so replay can resume after it
+pub fn latest_history_seq(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM knowledge_graph_history", [], |row| row.get(0))?)
+}
+
+pub fn record_snapshot(
+    conn: &Connection,
+    agent_id: &str,
+    history_seq: i64,
+    nodes: Vec<KnowledgeNode>,
+    edges: Vec<KnowledgeEdge>,
+    taken_at: DateTime<Utc>,
+) -> Result<GraphSnapshotInfo> {
+    let info = GraphSnapshotInfo {
+        id: uuid::Uuid::new_v4().to_string(),
+        agent_id: agent_id.to_string(),
+        node_count: nodes.len(),
+        edge_count: edges.len(),
+        taken_at,
+    };
+    let nodes = nodes.into_iter().map(|n| KnowledgeNode { embedding: None, ..n }).collect();
+    let data = serde_json::to_string(&SnapshotData { nodes, edges })?;
+
+    conn.execute(
+        "INSERT INTO knowledge_graph_snapshots (id, agent_id, history_seq, node_count, edge_count, data, taken_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![info.id, agent_id, history_seq, info.node_count as i64, info.edge_count as i64, data, history_timestamp(taken_at)],
+    )?;
+    Ok(info)
+}
+
+/// Snapshot the agent's current graph
+pub fn take_snapshot(manager: &SimpleMemoryManager) -> Result<GraphSnapshotInfo> {
+    let conn = manager.shared_connection()?;
+    // Read the sequence first: rows written while loading are replayed again, which is harmless
+    let history_seq = latest_history_seq(&conn)?;
+    let (nodes, edges) = manager.load_knowledge_graph()?;
+    record_snapshot(&conn, &manager.agent_id, history_seq, nodes, edges, Utc::now())
+}
+
+pub fn list_snapshots(conn: &Connection, agent_id: &str) -> Result<Vec<GraphSnapshotInfo>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_id, node_count, edge_count, taken_at FROM knowledge_graph_snapshots WHERE agent_id = ?1 ORDER BY taken_at DESC"
+    )?;
+    let snapshots = stmt.query_map(params![agent_id], |row| {
+        Ok(GraphSnapshotInfo {
+            id: row.get(0)?,
+            agent_id: row.get(1)?,
+            node_count: row.get::<_, i64>(2)? as usize,
+            edge_count: row.get::<_, i64>(3)? as usize,
+            taken_at: super::parse_db_timestamp(&row.get::<_, String>(4)?),
+        })
+    })?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(snapshots)
+}
+
+pub fn snapshot_due(conn: &Connection, agent_id: &str, now: DateTime<Utc>) -> Result<bool> {
+    let since = history_timestamp(now - chrono::Duration::hours(SNAPSHOT_INTERVAL_HOURS));
+    let recent: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM knowledge_graph_snapshots WHERE agent_id = ?1 AND taken_at > ?2",
+        params![agent_id, since],
+        |row| row.get(0),
+    )?;
+    Ok(recent == 0)
+}
+
+/// Thin snapshots to daily then weekly, drop those past the retention window, and drop history
+/// that only views older than every agent's oldest remaining snapshot would replay
+pub fn prune_graph_history(conn: &Connection, now: DateTime<Utc>) -> Result<GraphHistoryPruned> {
+    let daily_cutoff = history_timestamp(now - chrono::Duration::days(DAILY_SNAPSHOT_RETENTION_DAYS));
+    let weekly_cutoff = history_timestamp(now - chrono::Duration::days(WEEKLY_SNAPSHOT_RETENTION_DAYS));
+
+    let tx = conn.unchecked_transaction()?;
+    let expired = tx.execute("DELETE FROM knowledge_graph_snapshots WHERE taken_at < ?1", params![weekly_cutoff])?;
+    let thinned = tx.execute(
+        "DELETE FROM knowledge_graph_snapshots WHERE taken_at < ?1 AND id NOT IN (
+            SELECT id FROM (
+                SELECT id, ROW_NUMBER() OVER (
+                    PARTITION BY agent_id, strftime('%Y-%W', taken_at) ORDER BY taken_at DESC
+                ) AS rank
+                FROM knowledge_graph_snapshots WHERE taken_at < ?1
+            ) WHERE rank = 1
+        )",
+        params![daily_cutoff],
+    )?;
+
+    // An agent with nodes but no snapshot yet still replays from the first row, so nothing goes
+    let replay_floor: i64 = tx.query_row(
+        "SELECT COALESCE(MIN(COALESCE(
+            (SELECT MIN(s.history_seq) FROM knowledge_graph_snapshots s WHERE s.agent_id = a.agent_id), 0
+        )), 0)
+        FROM (
+            SELECT json_extract(properties, '$.agent_id') AS agent_id FROM knowledge_nodes
+            WHERE json_extract(properties, '$.agent_id') IS NOT NULL
+            UNION
+            SELECT agent_id FROM knowledge_graph_snapshots
+        ) a",
+        [],
+        |row| row.get(0),
+    )?;
+    let history_rows = tx.execute("DELETE FROM knowledge_graph_history WHERE seq <= ?1", params![replay_floor])?;
+    tx.commit()?;
+
+    Ok(GraphHistoryPruned { snapshots: expired + thinned, history_rows })
+}
+
+fn prune_shared_graph_history() -> Result<GraphHistoryPruned> {
+    let shared_db_path = SimpleMemoryManager::default_shared_db_path()?;
+    if !shared_db_path.exists() {
+        return Ok(GraphHistoryPruned::default());
+    }
+    let conn = super::pool::connection(&shared_db_path)?;
+    prune_graph_history(&conn, Utc::now())
+}
+
+fn snapshot_due_agents() -> Result<usize> {
+    let shared_db_path = SimpleMemoryManager::default_shared_db_path()?;
+    if !shared_db_path.exists() {
+        return Ok(0);
+    }
+    let conn = super::pool::connection(&shared_db_path)?;
+    let agents: Vec<String> = {
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT json_extract(properties, '$.agent_id') FROM knowledge_nodes WHERE json_extract(properties, '$.agent_id') IS NOT NULL"
+        )?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+
+    let mut taken = 0;
+    for agent_id in agents {
+        if !snapshot_due(&conn, &agent_id, Utc::now())? {
+            continue;
+        }
+        match SimpleMemoryManager::new(agent_id.clone()).and_then(|manager| take_snapshot(&manager)) {
+            Ok(_) => taken += 1,
+            Err(e) => error!("Failed to snapshot knowledge graph for agent {}: {}", agent_id, e),
+        }
+    }
+    Ok(taken)
+}
+
+/// Take a daily snapshot of every agent's graph and prune old history for as long as the app runs
+pub fn spawn_graph_snapshot_scheduler() {
+    tauri::async_runtime::spawn(async {
+        let mut interval = tokio::time::interval(SNAPSHOT_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match snapshot_due_agents() {
+                Ok(0) => {}
+                Ok(taken) => info!("Took {} daily knowledge graph snapshot(s)", taken),
+                Err(e) => error!("Knowledge graph snapshot check failed: {}", e),
+            }
+            match prune_shared_graph_history() {
+                Ok(pruned) if pruned == GraphHistoryPruned::default() => {}
+                Ok(pruned) => info!(
+                    "Pruned {} knowledge graph snapshot(s) and {} history row(s)",
+                    pruned.snapshots, pruned.history_rows
+                ),
+                Err(e) => error!("Knowledge graph history pruning failed: {}", e),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn list_graph_snapshots(
+    agent_id: String,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<Vec<GraphSnapshotInfo>, String> {
+    crate::validation::GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "graph_operations",
+        &[agent_id.clone()],
+        &[]
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let conn = manager.shared_connection()
+        .map_err(|e| format!("Failed to open graph storage: {}", e))?;
+    list_snapshots(&conn, sanitized_agent_id)
+        .map_err(|e| format!("Failed to list graph snapshots: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::AGENT_MEMORY_SCHEMA;
+
+    fn shared_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(AGENT_MEMORY_SCHEMA).unwrap();
+        conn
+    }
+
+    fn add_node(conn: &Connection, id: &str, name: &str) {
+        conn.execute(
+            "INSERT OR REPLACE INTO knowledge_nodes (id, node_type, name, properties) VALUES (?1, 'Concept', ?2, '{\"agent_id\":\"a1\"}')",
+            params![id, name],
+        ).unwrap();
+    }
+
+    /// Backdate history rows written so far, keeping their order
+    fn age_history(conn: &Connection, at: &str) {
+        conn.execute("UPDATE knowledge_graph_history SET changed_at = ?1 WHERE changed_at > ?1", params![at]).unwrap();
+    }
+
+    #[test]
+    fn test_time_travel_replays_history() {
+        let conn = shared_db();
+        add_node(&conn, "n1", "Rust");
+        add_node(&conn, "n2", "Cargo");
+        conn.execute(
+            "INSERT INTO knowledge_edges (id, from_node, to_node, relationship_type, weight, properties) VALUES ('e1', 'n2', 'n1', 'DependsOn', 0.8, '{\"agent_id\":\"a1\"}')",
+            [],
+        ).unwrap();
+        age_history(&conn, "2000-01-01T00:00:00.000Z");
+
+        add_node(&conn, "n1", "Rust 2024");
+        conn.execute("DELETE FROM knowledge_edges WHERE id = 'e1'", []).unwrap();
+        conn.execute("DELETE FROM knowledge_nodes WHERE id = 'n2'", []).unwrap();
+
+        let jan = "2000-01-02T00:00:00Z".parse().unwrap();
+        let (nodes, edges) = graph_as_of(&conn, "a1", jan).unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes.iter().any(|n| n.name == "Rust"));
+        assert_eq!(edges.len(), 1);
+
+        let (nodes, edges) = graph_as_of(&conn, "a1", Utc::now()).unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].name, "Rust 2024");
+        assert!(edges.is_empty());
+        assert!(graph_as_of(&conn, "a1", "1999-12-31T00:00:00Z".parse().unwrap()).unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_seed_replay_and_schedule() {
+        let conn = shared_db();
+        add_node(&conn, "n1", "Rust");
+        let taken_at: DateTime<Utc> = "2000-03-01T00:00:00Z".parse().unwrap();
+        age_history(&conn, "2000-02-01T00:00:00.000Z");
+
+        let seq = latest_history_seq(&conn).unwrap();
+        let (nodes, edges) = graph_as_of(&conn, "a1", taken_at).unwrap();
+        record_snapshot(&conn, "a1", seq, nodes, edges, taken_at).unwrap();
+        // History older than the snapshot is no longer needed to answer later queries
+        conn.execute("DELETE FROM knowledge_graph_history WHERE seq <= ?1", params![seq]).unwrap();
+        add_node(&conn, "n2", "Cargo");
+
+        let (nodes, _) = graph_as_of(&conn, "a1", Utc::now()).unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(list_snapshots(&conn, "a1").unwrap()[0].node_count, 1);
+        assert!(!snapshot_due(&conn, "a1", taken_at + chrono::Duration::hours(23)).unwrap());
+        assert!(snapshot_due(&conn, "a1", taken_at + chrono::Duration::hours(25)).unwrap());
+    }
+
+    #[test]
+    fn test_pruning_thins_snapshots_and_keeps_views_answerable() {
+        let conn = shared_db();
+        let now: DateTime<Utc> = "2001-01-31T12:00:00Z".parse().unwrap();
+        add_node(&conn, "n1", "Rust");
+        age_history(&conn, "1999-06-01T00:00:00.000Z");
+
+        // Daily snapshots for the past 400 days
+        for days_ago in (1..=400).rev() {
+            let taken_at = now - chrono::Duration::days(days_ago);
+            add_node(&conn, "n1", &format!("Rust {}", days_ago));
+            age_history(&conn, &history_timestamp(taken_at - chrono::Duration::hours(1)));
+            let seq = latest_history_seq(&conn).unwrap();
+            let (nodes, edges) = graph_as_of(&conn, "a1", taken_at).unwrap();
+            record_snapshot(&conn, "a1", seq, nodes, edges, taken_at).unwrap();
+        }
+        let before = graph_as_of(&conn, "a1", now - chrono::Duration::days(100)).unwrap().0;
+
+        let pruned = prune_graph_history(&conn, now).unwrap();
+        let snapshots = list_snapshots(&conn, "a1").unwrap();
+        assert_eq!(pruned.snapshots, 400 - snapshots.len());
+        assert!(snapshots.len() < 90, "kept {} snapshots", snapshots.len());
+        let oldest = snapshots.last().unwrap().taken_at;
+        assert!(oldest >= now - chrono::Duration::days(WEEKLY_SNAPSHOT_RETENTION_DAYS));
+        let recent = snapshots.iter().filter(|s| s.taken_at >= now - chrono::Duration::days(DAILY_SNAPSHOT_RETENTION_DAYS)).count();
+        assert_eq!(recent, DAILY_SNAPSHOT_RETENTION_DAYS as usize);
+
+        assert!(pruned.history_rows > 0);
+        let remaining: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM knowledge_graph_history WHERE changed_at < ?1", params![history_timestamp(oldest)], |r| r.get(0),
+        ).unwrap();
+        assert_eq!(remaining, 0);
+
+        // Views inside the retention window come out the same
+        let after = graph_as_of(&conn, "a1", now - chrono::Duration::days(100)).unwrap().0;
+        assert_eq!(after[0].name, before[0].name);
+        assert_eq!(graph_as_of(&conn, "a1", now).unwrap().0[0].name, "Rust 1");
+    }
+
+    #[test]
+    fn test_pruning_keeps_history_for_agents_without_snapshots() {
+        let conn = shared_db();
+        add_node(&conn, "n1", "Rust");
+        conn.execute(
+            "INSERT INTO knowledge_nodes (id, node_type, name, properties) VALUES ('n2', 'Concept', 'Go', '{\"agent_id\":\"a2\"}')",
+            [],
+        ).unwrap();
+        age_history(&conn, "2000-01-01T00:00:00.000Z");
+        let seq = latest_history_seq(&conn).unwrap();
+        let taken_at = "2000-01-02T00:00:00Z".parse().unwrap();
+        let (nodes, edges) = graph_as_of(&conn, "a1", taken_at).unwrap();
+        record_snapshot(&conn, "a1", seq, nodes, edges, taken_at).unwrap();
+
+        let pruned = prune_graph_history(&conn, "2000-01-03T00:00:00Z".parse().unwrap()).unwrap();
+        assert_eq!(pruned, GraphHistoryPruned::default());
+        assert_eq!(graph_as_of(&conn, "a2", Utc::now()).unwrap().0.len(), 1);
+    }
+}
@@ -0,0 +1,285 @@
+use super::embeddings::SimilarityMetric;
+use super::memory::{KnowledgeEdge, KnowledgeNode};
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Edges weaker than this are pruned unless the caller sets a threshold
+pub const DEFAULT_MIN_EDGE_WEIGHT: f32 = 0.05;
+
+/// Nodes of the same type whose embeddings are at least this similar are collapsed
+pub const DEFAULT_NODE_SIMILARITY_THRESHOLD: f32 = 0.95;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphSizeSummary {
+    pub node_count: usize,
+    pub edge_count: usize,
+    /// Size of the shared graph database
+    pub storage_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphOptimizationReport {
+    pub before: GraphSizeSummary,
+    pub after: GraphSizeSummary,
+    pub merged_edges: usize,
+    pub pruned_edges: usize,
+    pub removed_self_loops: usize,
+    pub collapsed_nodes: usize,
+    /// False when the vacuum was skipped, e.g. for lack of disk space
+    pub vacuumed: bool,
+}
+
+/// Changes that tidy the graph, computed without touching storage
+#[derive(Debug, Default)]
+pub struct OptimizationPlan {
+    /// Duplicate node id and the node it is folded into
+    pub node_merges: Vec<(String, String)>,
+    /// Surviving nodes whose properties absorbed their duplicates'
+    pub node_updates: Vec<KnowledgeNode>,
+    pub edge_deletes: Vec<String>,
+    /// Surviving edges whose weight or properties absorbed their duplicates
+    pub edge_updates: Vec<KnowledgeEdge>,
+    pub merged_edges: usize,
+    pub pruned_edges: usize,
+    pub removed_self_loops: usize,
+}
+
+/// Plan the optimization: collapse near-duplicate nodes, then merge edges that now share
+/// endpoints and relationship, then prune what is still below `min_edge_weight`. Node
+/// embeddings are compared with `metric`, the one the embedding model is configured for.
+pub fn plan_optimization(
+    nodes: &[KnowledgeNode],
+    edges: &[KnowledgeEdge],
+    min_edge_weight: f32,
+    similarity_threshold: f32,
+    metric: SimilarityMetric,
+) -> OptimizationPlan {
+    let mut plan = OptimizationPlan::default();
+
+    // Better-connected, older nodes survive a collapse
+    let mut degree: HashMap<&str, usize> = HashMap::new();
+    for edge in edges {
+        *degree.entry(edge.from_node.as_str()).or_insert(0) += 1;
+        *degree.entry(edge.to_node.as_str()).or_insert(0) += 1;
+    }
+    let mut candidates: Vec<&KnowledgeNode> = nodes.iter().filter(|n| n.embedding.is_some()).collect();
+    candidates.sort_by(|a, b| {
+        degree.get(b.id.as_str()).cmp(&degree.get(a.id.as_str()))
+            .then(a.created_at.cmp(&b.created_at))
+    });
+
+    let mut canonical: HashMap<&str, &str> = HashMap::new();
+    for (i, keep) in candidates.iter().enumerate() {
+        if canonical.contains_key(keep.id.as_str()) {
+            continue;
+        }
+        let keep_embedding = keep.embedding.as_ref().unwrap();
+        let mut merged = (*keep).clone();
+        let mut changed = false;
+        for duplicate in &candidates[i + 1..] {
+            if canonical.contains_key(duplicate.id.as_str())
+                || std::mem::discriminant(&duplicate.node_type) != std::mem::discriminant(&keep.node_type)
+            {
+                continue;
+            }
+            if metric.similarity(keep_embedding, duplicate.embedding.as_ref().unwrap()) >= similarity_threshold {
+                canonical.insert(duplicate.id.as_str(), keep.id.as_str());
+                plan.node_merges.push((duplicate.id.clone(), keep.id.clone()));
+                // The survivor's own values win; what only the duplicate knew is kept
+                for (k, v) in &duplicate.properties {
+                    if !merged.properties.contains_key(k) {
+                        merged.properties.insert(k.clone(), v.clone());
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if changed {
+            plan.node_updates.push(merged);
+        }
+    }
+    let resolve = |id: &str| canonical.get(id).copied().unwrap_or(id).to_string();
+
+    // Oldest edge of each (from, to, relationship) group survives and keeps the strongest weight
+    let mut ordered: Vec<&KnowledgeEdge> = edges.iter().collect();
+    ordered.sort_by_key(|e| e.created_at);
+    let mut survivors: Vec<(KnowledgeEdge, bool)> = Vec::new();
+    let mut by_key: HashMap<(String, String, String), usize> = HashMap::new();
+    for edge in ordered {
+        let (from, to) = (resolve(&edge.from_node), resolve(&edge.to_node));
+        if from == to {
+            plan.edge_deletes.push(edge.id.clone());
+            plan.removed_self_loops += 1;
+            continue;
+        }
+
+        let key = (from.clone(), to.clone(), format!("{:?}", edge.relationship_type));
+        match by_key.get(&key) {
+            Some(&index) => {
+                let (kept, changed) = &mut survivors[index];
+                if edge.weight > kept.weight {
+                    kept.weight = edge.weight;
+                    *changed = true;
+                }
+                for (k, v) in &edge.properties {
+                    if !kept.properties.contains_key(k) {
+                        kept.properties.insert(k.clone(), v.clone());
+                        *changed = true;
+                    }
+                }
+                plan.edge_deletes.push(edge.id.clone());
+                plan.merged_edges += 1;
+            }
+            None => {
+                by_key.insert(key, survivors.len());
+                let rewired = from != edge.from_node || to != edge.to_node;
+                survivors.push((KnowledgeEdge { from_node: from, to_node: to, ..edge.clone() }, rewired));
+            }
+        }
+    }
+
+    for (edge, changed) in survivors {
+        if edge.weight < min_edge_weight {
+            plan.edge_deletes.push(edge.id);
+            plan.pruned_edges += 1;
+        } else if changed {
+            plan.edge_updates.push(edge);
+        }
+    }
+
+    plan
+}
+
+/// Apply a plan to the shared graph database in one transaction
+pub fn apply_plan(conn: &mut Connection, plan: &OptimizationPlan) -> Result<()> {
+    let tx = conn.transaction()?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    // Repoint every edge, including ones outside the agent's slice, before dropping the duplicate
+    for (duplicate, keep) in &plan.node_merges {
+        tx.execute("UPDATE knowledge_edges SET from_node = ?1 WHERE from_node = ?2", params![keep, duplicate])?;
+        tx.execute("UPDATE knowledge_edges SET to_node = ?1 WHERE to_node = ?2", params![keep, duplicate])?;
+        tx.execute("DELETE FROM knowledge_edges WHERE from_node = ?1 AND to_node = ?1", params![keep])?;
+        tx.execute("DELETE FROM knowledge_nodes WHERE id = ?1", params![duplicate])?;
+    }
+    for node in &plan.node_updates {
+        tx.execute(
+            "UPDATE knowledge_nodes SET properties = ?1, updated_at = ?2 WHERE id = ?3",
+            params![serde_json::to_string(&node.properties)?, now, node.id],
+        )?;
+    }
+    for edge_id in &plan.edge_deletes {
+        tx.execute("DELETE FROM knowledge_edges WHERE id = ?1", params![edge_id])?;
+    }
+    for edge in &plan.edge_updates {
+        tx.execute(
+            "UPDATE knowledge_edges SET weight = ?1, properties = ?2, updated_at = ?3 WHERE id = ?4",
+            params![edge.weight, serde_json::to_string(&edge.properties)?, now, edge.id],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// Database size from its page count, which unlike the file size accounts for WAL contents
+pub fn storage_bytes(conn: &Connection) -> Result<u64> {
+    let pages: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok((pages * page_size).max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::memory::{NodeType, RelationshipType};
+    use crate::database::schema::AGENT_MEMORY_SCHEMA;
+
+    fn node(id: &str, embedding: Vec<f32>) -> KnowledgeNode {
+        let mut node = KnowledgeNode::new(NodeType::Concept, id.to_string());
+        node.id = id.to_string();
+        node.embedding = Some(embedding);
+        node
+    }
+
+    fn edge(id: &str, from: &str, to: &str, weight: f32) -> KnowledgeEdge {
+        let mut edge = KnowledgeEdge::new(from.to_string(), to.to_string(), RelationshipType::DependsOn).with_weight(weight);
+        edge.id = id.to_string();
+        edge
+    }
+
+    fn fixture() -> (Vec<KnowledgeNode>, Vec<KnowledgeEdge>) {
+        let mut rust = node("rust", vec![1.0, 0.0, 0.0]);
+        rust.properties.insert("edition".to_string(), "2021".to_string());
+        let mut rust_lang = node("rust-lang", vec![0.99, 0.05, 0.0]);
+        rust_lang.properties.insert("edition".to_string(), "2018".to_string());
+        rust_lang.properties.insert("homepage".to_string(), "rust-lang.org".to_string());
+        let nodes = vec![
+            rust,
+            rust_lang,
+            node("cargo", vec![0.0, 1.0, 0.0]),
+            node("tokio", vec![0.0, 0.0, 1.0]),
+        ];
+        let edges = vec![
+            edge("e1", "cargo", "rust", 0.4),
+            edge("e2", "cargo", "rust-lang", 0.7),
+            edge("e3", "rust", "rust-lang", 0.9),
+            edge("e4", "tokio", "rust", 0.01),
+            edge("e5", "tokio", "cargo", 0.5),
+        ];
+        (nodes, edges)
+    }
+
+    #[test]
+    fn test_plan_collapses_merges_and_prunes() {
+        let (nodes, edges) = fixture();
+        let plan = plan_optimization(&nodes, &edges, DEFAULT_MIN_EDGE_WEIGHT, DEFAULT_NODE_SIMILARITY_THRESHOLD, SimilarityMetric::Cosine);
+
+        assert_eq!(plan.node_merges, vec![("rust-lang".to_string(), "rust".to_string())]);
+        // The survivor keeps its own edition and gains the duplicate's homepage
+        assert_eq!(plan.node_updates.len(), 1);
+        assert_eq!(plan.node_updates[0].properties["edition"], "2021");
+        assert_eq!(plan.node_updates[0].properties["homepage"], "rust-lang.org");
+        assert_eq!((plan.merged_edges, plan.pruned_edges, plan.removed_self_loops), (1, 1, 1));
+        let mut deleted = plan.edge_deletes.clone();
+        deleted.sort();
+        assert_eq!(deleted, vec!["e2", "e3", "e4"]);
+        // e1 survives with its duplicate's stronger weight
+        assert_eq!(plan.edge_updates.len(), 1);
+        assert_eq!(plan.edge_updates[0].id, "e1");
+        assert_eq!(plan.edge_updates[0].weight, 0.7);
+    }
+
+    #[test]
+    fn test_apply_plan_updates_storage() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(AGENT_MEMORY_SCHEMA).unwrap();
+        let (nodes, edges) = fixture();
+        for n in &nodes {
+            conn.execute(
+                "INSERT INTO knowledge_nodes (id, node_type, name) VALUES (?1, 'Concept', ?1)",
+                params![n.id],
+            ).unwrap();
+        }
+        for e in &edges {
+            conn.execute(
+                "INSERT INTO knowledge_edges (id, from_node, to_node, relationship_type, weight) VALUES (?1, ?2, ?3, 'DependsOn', ?4)",
+                params![e.id, e.from_node, e.to_node, e.weight],
+            ).unwrap();
+        }
+
+        let plan = plan_optimization(&nodes, &edges, DEFAULT_MIN_EDGE_WEIGHT, DEFAULT_NODE_SIMILARITY_THRESHOLD, SimilarityMetric::Cosine);
+        apply_plan(&mut conn, &plan).unwrap();
+
+        let count = |sql: &str| conn.query_row(sql, [], |r| r.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM knowledge_nodes"), 3);
+        assert_eq!(count("SELECT COUNT(*) FROM knowledge_edges"), 2);
+        let weight: f32 = conn.query_row("SELECT weight FROM knowledge_edges WHERE id = 'e1'", [], |r| r.get(0)).unwrap();
+        assert_eq!(weight, 0.7);
+        let properties: String = conn.query_row("SELECT properties FROM knowledge_nodes WHERE id = 'rust'", [], |r| r.get(0)).unwrap();
+        assert!(properties.contains("rust-lang.org"));
+        assert!(storage_bytes(&conn).unwrap() > 0);
+    }
+}
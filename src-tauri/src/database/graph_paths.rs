@@ -0,0 +1,316 @@
+use super::graph_commands::PathResult;
+use super::memory::{cosine_similarity, KnowledgeEdge, KnowledgeNode, RelationshipType};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Upper bound on partial paths popped from the queue, so dense graphs can't stall a request
+const MAX_EXPANSIONS: usize = 50_000;
+
+/// Weights at or below this are treated as missing edges
+const MIN_EDGE_WEIGHT: f32 = 1e-6;
+
+/// Relationships that hold in both directions and can be walked either way
+fn is_symmetric(relationship: &RelationshipType) -> bool {
+    matches!(
+        relationship,
+        RelationshipType::Similar
+            | RelationshipType::SimilarTo
+            | RelationshipType::CollaboratesWith
+            | RelationshipType::Knows
+            | RelationshipType::Opposite
+    )
+}
+
+/// Edge weights are strengths in (0, 1]; costs are their negative logs so a path's
+/// cost sums to the negative log of the product of its weights
+fn edge_cost(weight: f32) -> Option<f32> {
+    (weight > MIN_EDGE_WEIGHT).then(|| -weight.min(1.0).ln())
+}
+
+struct Candidate {
+    /// Cost so far plus the heuristic estimate to the target
+    priority: f32,
+    cost: f32,
+    path: Vec<usize>,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed so the max-heap pops the cheapest candidate, fewer hops first on ties
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+            .then_with(|| other.path.len().cmp(&self.path.len()))
+    }
+}
+
+/// Up to `max_paths` simple paths from `from` to `to` of at most `max_depth` hops, strongest first.
+///
+/// Runs Dijkstra over partial paths, so paths come out in order of total cost. With
+/// `use_heuristic` the queue is ordered A*-style by embedding distance to the target, scaled
+/// by the cheapest edge so the estimate never overshoots and the ranking stays exact.
+pub fn ranked_paths(
+    nodes: &[KnowledgeNode],
+    edges: &[KnowledgeEdge],
+    from: &str,
+    to: &str,
+    max_depth: usize,
+    max_paths: usize,
+    use_heuristic: bool,
+) -> Vec<PathResult> {
+    let mut ids: Vec<&str> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    let endpoints = edges.iter().flat_map(|e| [e.from_node.as_str(), e.to_node.as_str()]);
+    for id in nodes.iter().map(|n| n.id.as_str()).chain(endpoints) {
+        if !index.contains_key(id) {
+            index.insert(id, ids.len());
+            ids.push(id);
+        }
+    }
+
+    let (Some(&start), Some(&target)) = (index.get(from), index.get(to)) else {
+        return Vec::new();
+    };
+
+    // Parallel edges collapse to the strongest one
+    let mut best: HashMap<(usize, usize), f32> = HashMap::new();
+    for edge in edges {
+        let Some(cost) = edge_cost(edge.weight) else { continue };
+        let (a, b) = (index[edge.from_node.as_str()], index[edge.to_node.as_str()]);
+        let mut add = |key: (usize, usize)| {
+            best.entry(key).and_modify(|c| *c = c.min(cost)).or_insert(cost);
+        };
+        add((a, b));
+        if is_symmetric(&edge.relationship_type) {
+            add((b, a));
+        }
+    }
+
+    let mut adjacency: Vec<Vec<(usize, f32)>> = vec![Vec::new(); ids.len()];
+    for (&(a, b), &cost) in &best {
+        if a != b {
+            adjacency[a].push((b, cost));
+        }
+    }
+
+    let heuristic: Vec<f32> = if use_heuristic {
+        let min_cost = best.values().copied().fold(f32::INFINITY, f32::min);
+        let embeddings: HashMap<&str, &Vec<f32>> = nodes.iter()
+            .filter_map(|n| n.embedding.as_ref().map(|e| (n.id.as_str(), e)))
+            .collect();
+        let target_embedding = embeddings.get(to).copied();
+        ids.iter().enumerate().map(|(i, id)| {
+            match (target_embedding, embeddings.get(id)) {
+                (Some(t), Some(e)) if i != target && min_cost.is_finite() => {
+                    min_cost * (1.0 - cosine_similarity(e, t)).clamp(0.0, 1.0)
+                }
+                _ => 0.0,
+            }
+        }).collect()
+    } else {
+        vec![0.0; ids.len()]
+    };
+
+    let mut queue = BinaryHeap::new();
+    queue.push(Candidate { priority: heuristic[start], cost: 0.0, path: vec![start] });
+
+    let mut results = Vec::new();
+    let mut expansions = 0;
+    while let Some(Candidate { cost, path, .. }) = queue.pop() {
+        expansions += 1;
+        if expansions > MAX_EXPANSIONS {
+            break;
+        }
+
+        let last = *path.last().unwrap();
+        if last == target {
+            results.push(PathResult {
+                path: path.iter().map(|&i| ids[i].to_string()).collect(),
+                distance: path.len() - 1,
+                weight: (-cost).exp(),
+            });
+            if results.len() >= max_paths {
+                break;
+            }
+            continue;
+        }
+        if path.len() > max_depth {
+            continue;
+        }
+
+        for &(next, edge_cost) in &adjacency[last] {
+            if path.contains(&next) {
+                continue;
+            }
+            let mut extended = path.clone();
+            extended.push(next);
+            queue.push(Candidate {
+                priority: cost + edge_cost + heuristic[next],
+                cost: cost + edge_cost,
+                path: extended,
+            });
+        }
+    }
+
+    results
+}
+
+/// Neighbourhood of `seed` within `depth` hops in either edge direction, capped at `max_nodes`.
+///
+/// Expands one hop at a time; within a hop, nodes reached through stronger edges are taken
+/// first, so the cap drops the weakest connections. Returns indices of the selected nodes in
+/// visiting order and of the edges between them, strongest first.
+pub fn bounded_subgraph(
+    nodes: &[KnowledgeNode],
+    edges: &[KnowledgeEdge],
+    seed: &str,
+    depth: usize,
+    max_nodes: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    let index: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+    let Some(&start) = index.get(seed) else {
+        return (Vec::new(), Vec::new());
+    };
+    if max_nodes == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let mut adjacency: Vec<Vec<(usize, f32)>> = vec![Vec::new(); nodes.len()];
+    for edge in edges {
+        if let (Some(&a), Some(&b)) = (index.get(edge.from_node.as_str()), index.get(edge.to_node.as_str())) {
+            adjacency[a].push((b, edge.weight));
+            adjacency[b].push((a, edge.weight));
+        }
+    }
+
+    let mut selected = vec![start];
+    let mut seen: HashSet<usize> = HashSet::from([start]);
+    let mut frontier = vec![start];
+    for _ in 0..depth {
+        let mut reached: HashMap<usize, f32> = HashMap::new();
+        for &node in &frontier {
+            for &(neighbor, weight) in &adjacency[node] {
+                if !seen.contains(&neighbor) {
+                    let best = reached.entry(neighbor).or_insert(weight);
+                    *best = best.max(weight);
+                }
+            }
+        }
+        let mut next: Vec<(usize, f32)> = reached.into_iter().collect();
+        next.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| nodes[a.0].id.cmp(&nodes[b.0].id)));
+        next.truncate(max_nodes - selected.len());
+
+        frontier = next.into_iter().map(|(node, _)| node).collect();
+        seen.extend(&frontier);
+        selected.extend(&frontier);
+        if frontier.is_empty() || selected.len() >= max_nodes {
+            break;
+        }
+    }
+
+    let mut edge_indices: Vec<usize> = edges.iter().enumerate()
+        .filter(|(_, e)| {
+            let inside = |id: &str| index.get(id).is_some_and(|i| seen.contains(i));
+            inside(&e.from_node) && inside(&e.to_node)
+        })
+        .map(|(i, _)| i)
+        .collect();
+    edge_indices.sort_by(|&a, &b| edges[b].weight.total_cmp(&edges[a].weight));
+
+    (selected, edge_indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::memory::NodeType;
+
+    fn node(id: &str, embedding: Option<Vec<f32>>) -> KnowledgeNode {
+        let mut node = KnowledgeNode::new(NodeType::Concept, id.to_string());
+        node.id = id.to_string();
+        node.embedding = embedding;
+        node
+    }
+
+    fn edge(from: &str, to: &str, weight: f32) -> KnowledgeEdge {
+        KnowledgeEdge::new(from.to_string(), to.to_string(), RelationshipType::LeadsTo).with_weight(weight)
+    }
+
+    #[test]
+    fn test_paths_ranked_by_weight_within_depth() {
+        let nodes: Vec<_> = ["a", "b", "c", "d"].iter().map(|id| node(id, None)).collect();
+        let edges = vec![
+            edge("a", "d", 0.2),
+            edge("a", "b", 0.9),
+            edge("b", "d", 0.9),
+            edge("a", "c", 0.8),
+            edge("c", "b", 0.9),
+            // Directed: no way back from d
+            edge("d", "a", 0.0),
+        ];
+
+        let paths = ranked_paths(&nodes, &edges, "a", "d", 3, 5, false);
+        let routes: Vec<Vec<&str>> = paths.iter().map(|p| p.path.iter().map(|s| s.as_str()).collect()).collect();
+        assert_eq!(routes, vec![vec!["a", "b", "d"], vec!["a", "c", "b", "d"], vec!["a", "d"]]);
+        assert!((paths[0].weight - 0.81).abs() < 1e-5);
+        assert_eq!(paths[1].distance, 3);
+
+        // Depth limit drops the three-hop route
+        assert_eq!(ranked_paths(&nodes, &edges, "a", "d", 2, 5, false).len(), 2);
+        assert!(ranked_paths(&nodes, &edges, "d", "a", 3, 5, false).is_empty());
+    }
+
+    #[test]
+    fn test_heuristic_keeps_ranking_exact() {
+        let nodes = vec![
+            node("start", Some(vec![1.0, 0.0])),
+            node("near", Some(vec![0.1, 1.0])),
+            node("far", Some(vec![1.0, 0.0])),
+            node("goal", Some(vec![0.0, 1.0])),
+        ];
+        let edges = vec![
+            edge("start", "near", 0.5),
+            edge("near", "goal", 0.5),
+            edge("start", "far", 0.9),
+            edge("far", "goal", 0.9),
+        ];
+
+        let plain = ranked_paths(&nodes, &edges, "start", "goal", 4, 2, false);
+        let guided = ranked_paths(&nodes, &edges, "start", "goal", 4, 2, true);
+        let route = |paths: &[PathResult]| paths.iter().map(|p| p.path.join(">")).collect::<Vec<_>>();
+        assert_eq!(route(&guided), route(&plain));
+        assert_eq!(route(&guided)[0], "start>far>goal");
+    }
+
+    #[test]
+    fn test_bounded_subgraph_prefers_strong_edges() {
+        let nodes: Vec<_> = ["seed", "strong", "weak", "beyond", "far"].iter().map(|id| node(id, None)).collect();
+        let edges = vec![
+            edge("seed", "weak", 0.2),
+            // Incoming edges count too
+            edge("strong", "seed", 0.9),
+            edge("strong", "beyond", 0.7),
+            edge("beyond", "far", 0.9),
+        ];
+
+        let ids = |(n, _): (Vec<usize>, Vec<usize>)| n.into_iter().map(|i| nodes[i].id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(bounded_subgraph(&nodes, &edges, "seed", 2, 10)), vec!["seed", "strong", "weak", "beyond"]);
+        assert_eq!(ids(bounded_subgraph(&nodes, &edges, "seed", 3, 3)), vec!["seed", "strong", "weak"]);
+
+        let (_, edge_indices) = bounded_subgraph(&nodes, &edges, "seed", 1, 2);
+        assert_eq!(edge_indices, vec![1]);
+        assert!(bounded_subgraph(&nodes, &edges, "missing", 2, 10).0.is_empty());
+    }
+}
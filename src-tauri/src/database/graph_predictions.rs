@@ -0,0 +1,178 @@
+//! Relationship predictions from the neural graph's edge and attention networks, and the
+//! path that turns an accepted prediction into a persistent knowledge graph edge.
+
+use super::graph_commands::{create_graph_edge, CreateEdgeRequest};
+use super::memory::{KnowledgeNode, NodeType};
+use super::neural_knowledge_graph::{NeuralGraphNode, RelationshipPrediction};
+use super::simple_commands::MemoryState;
+use super::simple_memory::SimpleMemoryManager;
+use crate::validation::GraphValidator;
+use chrono::Utc;
+use std::collections::HashMap;
+use tauri::State;
+use tracing::info;
+
+const DEFAULT_TOP_K: usize = 10;
+const MAX_TOP_K: usize = 50;
+
+/// Provenance `source` recorded on edges created from predictions
+pub const PREDICTION_SOURCE: &str = "gnn_prediction";
+
+/// Memory nodes in the neural graph are `memory_<uuid>`; the persistent graph keys them by the uuid
+fn persistent_node_id(node_id: &str) -> Result<&str, String> {
+    let id = node_id.strip_prefix("memory_")
+        .ok_or_else(|| format!("Not a memory node: {}", node_id))?;
+    GraphValidator::validate_node_id(id).map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+fn check_owner(node: Option<NeuralGraphNode>, node_id: &str, agent_id: &str) -> Result<NeuralGraphNode, String> {
+    let node = node.ok_or_else(|| format!("Node not found: {}", node_id))?;
+    if node.agent_id.as_deref() != Some(agent_id) {
+        return Err(format!("Node {} does not belong to agent {}", node_id, agent_id));
+    }
+    Ok(node)
+}
+
+/// Persistent nodes for the edge's endpoints, created from the neural nodes where missing
+fn ensure_persistent_nodes(manager: &SimpleMemoryManager, agent_id: &str, nodes: &[&NeuralGraphNode]) -> Result<(), String> {
+    let ids: Vec<&str> = nodes.iter()
+        .map(|node| persistent_node_id(&node.id))
+        .collect::<Result<_, _>>()?;
+    let existing = manager.existing_knowledge_node_ids(&ids)
+        .map_err(|e| format!("Failed to look up graph nodes: {}", e))?;
+
+    for (node, id) in nodes.iter().zip(ids) {
+        if existing.contains(id) {
+            continue;
+        }
+        let mut knowledge_node = KnowledgeNode::new(NodeType::Memory, node.name.clone());
+        knowledge_node.id = id.to_string();
+        knowledge_node.embedding = node.embedding.clone();
+        knowledge_node.properties = HashMap::from([
+            ("agent_id".to_string(), agent_id.to_string()),
+            ("memory_id".to_string(), id.to_string()),
+        ]);
+        manager.add_knowledge_node(&knowledge_node)
+            .map_err(|e| format!("Failed to add graph node: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Relationships the graph network predicts from one of the agent's memory nodes to others
+/// it isn't connected to yet, strongest first
+#[tauri::command]
+pub async fn predict_graph_relationships(
+    agent_id: String,
+    node_id: String,
+    top_k: Option<usize>,
+    state: State<'_, MemoryState>,
+) -> Result<Vec<RelationshipPrediction>, String> {
+    GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    persistent_node_id(&node_id)?;
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "graph_operations",
+        &[agent_id, node_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+    let sanitized_node_id = &validation_result.sanitized_inputs[1];
+    let top_k = top_k.unwrap_or(DEFAULT_TOP_K).clamp(1, MAX_TOP_K);
+
+    let graph_lock = state.get_neural_graph().await?;
+    let graph = graph_lock.lock().await;
+    let graph = graph.as_ref().ok_or("Neural knowledge graph not initialized")?;
+    check_owner(graph.get_node(sanitized_node_id).await, sanitized_node_id, sanitized_agent_id)?;
+
+    graph.predict_node_relationships(sanitized_node_id, top_k).await
+        .map_err(|e| format!("Failed to predict relationships: {}", e))
+}
+
+/// Accept the network's prediction for a pair of the agent's nodes: the edge is created in
+/// the persistent graph through `create_graph_edge`, with where it came from recorded in its
+/// properties, and in the neural graph. Returns the persistent edge id.
+#[tauri::command]
+pub async fn accept_predicted_relationship(
+    agent_id: String,
+    from_node: String,
+    to_node: String,
+    state: State<'_, MemoryState>,
+) -> Result<String, String> {
+    GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    persistent_node_id(&from_node)?;
+    persistent_node_id(&to_node)?;
+    if from_node == to_node {
+        return Err("Self-loops are not allowed".to_string());
+    }
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "graph_operations",
+        &[agent_id, from_node, to_node],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+    let sanitized_from = &validation_result.sanitized_inputs[1];
+    let sanitized_to = &validation_result.sanitized_inputs[2];
+
+    // Re-score rather than trust a prediction the client may have held onto
+    let graph_handle = state.get_neural_graph().await?;
+    let (prediction, from, to) = {
+        let graph = graph_handle.lock().await;
+        let graph = graph.as_ref().ok_or("Neural knowledge graph not initialized")?;
+        let from = check_owner(graph.get_node(sanitized_from).await, sanitized_from, &sanitized_agent_id)?;
+        let to = check_owner(graph.get_node(sanitized_to).await, sanitized_to, &sanitized_agent_id)?;
+        if graph.is_connected(sanitized_from, sanitized_to).await {
+            return Err(format!("Nodes are already connected: {} -> {}", sanitized_from, sanitized_to));
+        }
+        let prediction = graph.predict_relationship_between(sanitized_from, sanitized_to).await
+            .map_err(|e| format!("Failed to predict relationship: {}", e))?;
+        (prediction, from, to)
+    };
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    ensure_persistent_nodes(&manager, &sanitized_agent_id, &[&from, &to])?;
+
+    let properties = HashMap::from([
+        ("source".to_string(), PREDICTION_SOURCE.to_string()),
+        ("neural_relationship_type".to_string(), format!("{:?}", prediction.relationship_type)),
+        ("predicted_strength".to_string(), format!("{:.4}", prediction.strength)),
+        ("predicted_similarity".to_string(), format!("{:.4}", prediction.similarity)),
+        ("accepted_at".to_string(), Utc::now().to_rfc3339()),
+    ]);
+    let edge_id = create_graph_edge(CreateEdgeRequest {
+        from_node: persistent_node_id(&prediction.from_node)?.to_string(),
+        to_node: persistent_node_id(&prediction.to_node)?.to_string(),
+        relationship_type: format!("{:?}", prediction.relationship_type.knowledge_relationship()),
+        weight: Some(prediction.strength),
+        properties: Some(properties),
+        agent_id: sanitized_agent_id.clone(),
+    }, state).await?;
+
+    let mut graph = graph_handle.lock().await;
+    if let Some(graph) = graph.as_mut() {
+        graph.accept_predicted_relationship(&prediction).await
+            .map_err(|e| format!("Edge {} saved but not added to the neural graph: {}", edge_id, e))?;
+    }
+
+    info!(
+        "Accepted predicted {:?} relationship {} -> {} as edge {}",
+        prediction.relationship_type, prediction.from_node, prediction.to_node, edge_id
+    );
+    Ok(edge_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persistent_node_id_requires_memory_uuid() {
+        let uuid = "123e4567-e89b-12d3-a456-426614174000";
+        assert_eq!(persistent_node_id(&format!("memory_{}", uuid)).unwrap(), uuid);
+        assert!(persistent_node_id(uuid).is_err());
+        assert!(persistent_node_id("memory_not-a-uuid").is_err());
+    }
+}
@@ -0,0 +1,402 @@
+use super::graph_paths::ranked_paths;
+use super::memory::{KnowledgeEdge, KnowledgeNode};
+use crate::ai::{complete_structured, complete_text, select_completion_provider, AIState, GuardrailScope, ResponseSchema};
+use crate::validation::GraphValidator;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet, VecDeque};
+use tauri::{AppHandle, State};
+use tracing::{info, warn};
+
+/// Longest question accepted
+const MAX_QUESTION_CHARS: usize = 2_000;
+
+/// Queries executed per question; extra steps from the provider are dropped
+const MAX_QUERY_STEPS: usize = 5;
+
+/// Nodes a single `find_nodes` step can return
+const MAX_FOUND_NODES: usize = 25;
+
+/// Facts passed to the provider when composing the answer
+const MAX_ANSWER_FACTS: usize = 150;
+
+const NODE_TYPES: [&str; 7] = ["Agent", "Memory", "Concept", "Task", "Tool", "Context", "Pattern"];
+const RELATIONSHIP_TYPES: [&str; 9] = [
+    "Knows", "Uses", "LearnedFrom", "CollaboratesWith", "DependsOn", "Similar", "Opposite", "CausedBy", "LeadsTo",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphQueryOp {
+    /// Nodes whose name contains `node`, optionally restricted to `node_types`
+    FindNodes,
+    /// Nodes within `depth` hops of `node`, following `relationship_types`
+    Neighbors,
+    /// Strongest paths from `node` to `target`
+    Path,
+}
+
+/// One query step as planned by the provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphQueryStep {
+    pub op: GraphQueryOp,
+    pub node: Option<String>,
+    pub target: Option<String>,
+    #[serde(default)]
+    pub node_types: Vec<String>,
+    #[serde(default)]
+    pub relationship_types: Vec<String>,
+    pub depth: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GraphQueryPlan {
+    steps: Vec<GraphQueryStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeGraphAnswer {
+    pub answer: String,
+    pub queries: Vec<GraphQueryStep>,
+    /// Nodes the answer was drawn from, without embeddings
+    pub nodes: Vec<KnowledgeNode>,
+    pub edges: Vec<KnowledgeEdge>,
+    pub provider: Option<String>,
+}
+
+/// Schema the provider's plan must follow; strict-mode compatible (every field required, nulls allowed)
+fn plan_schema() -> serde_json::Value {
+    let nullable_string = json!({ "type": ["string", "null"] });
+    json!({
+        "type": "object",
+        "properties": {
+            "steps": {
+                "type": "array",
+                "description": format!("At most {} graph queries that together answer the question", MAX_QUERY_STEPS),
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "op": { "type": "string", "enum": ["find_nodes", "neighbors", "path"] },
+                        "node": nullable_string,
+                        "target": nullable_string,
+                        "node_types": { "type": "array", "items": { "type": "string", "enum": NODE_TYPES } },
+                        "relationship_types": { "type": "array", "items": { "type": "string", "enum": RELATIONSHIP_TYPES } },
+                        "depth": { "type": ["integer", "null"], "minimum": 1, "maximum": 3 },
+                    },
+                    "required": ["op", "node", "target", "node_types", "relationship_types", "depth"],
+                    "additionalProperties": false,
+                },
+            },
+        },
+        "required": ["steps"],
+        "additionalProperties": false,
+    })
+}
+
+const PLANNER_SYSTEM_PROMPT: &str = "You translate questions about a knowledge graph into graph queries. \
+Nodes have a name and a type; edges have a relationship type and a weight between 0 and 1. \
+Operations: find_nodes looks up nodes whose name contains `node` (null for any), filtered by node_types; \
+neighbors returns nodes within `depth` hops of the node named `node`, following relationship_types (empty for all); \
+path finds the strongest connections from the node named `node` to the node named `target`. \
+Use the names that appear in the question. Leave unused fields null or empty.";
+
+const ANSWER_SYSTEM_PROMPT: &str = "Answer the question using only the knowledge graph facts provided. \
+If the facts don't answer it, say so. Be concise and refer to nodes by name.";
+
+/// Collects query results without duplicates, in the order they were found
+#[derive(Default)]
+struct Subgraph {
+    nodes: Vec<usize>,
+    edges: Vec<usize>,
+    seen_nodes: HashSet<usize>,
+    seen_edges: HashSet<usize>,
+}
+
+impl Subgraph {
+    fn add_node(&mut self, i: usize) {
+        if self.seen_nodes.insert(i) {
+            self.nodes.push(i);
+        }
+    }
+
+    fn add_edge(&mut self, i: usize) {
+        if self.seen_edges.insert(i) {
+            self.edges.push(i);
+        }
+    }
+}
+
+fn type_name<T: std::fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value)
+}
+
+/// Nodes a name from the question refers to: exact id, then exact name, then name substring
+fn resolve_nodes(nodes: &[KnowledgeNode], reference: &str) -> Vec<usize> {
+    let needle = reference.trim().to_lowercase();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let matching = |pred: &dyn Fn(&KnowledgeNode) -> bool| -> Vec<usize> {
+        nodes.iter().enumerate().filter(|(_, n)| pred(n)).map(|(i, _)| i).collect()
+    };
+
+    let exact = matching(&|n| n.id == reference || n.name.to_lowercase() == needle);
+    if !exact.is_empty() {
+        return exact;
+    }
+    matching(&|n| n.name.to_lowercase().contains(&needle))
+}
+
+/// Run the planned steps against the agent's graph, returning the node and edge indices they touched
+fn execute_plan(nodes: &[KnowledgeNode], edges: &[KnowledgeEdge], steps: &[GraphQueryStep]) -> (Vec<usize>, Vec<usize>) {
+    let by_id: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+    let mut found = Subgraph::default();
+
+    for step in steps.iter().take(MAX_QUERY_STEPS) {
+        let type_allowed = |node: &KnowledgeNode| {
+            step.node_types.is_empty() || step.node_types.contains(&type_name(&node.node_type))
+        };
+        let relationship_allowed = |edge: &KnowledgeEdge| {
+            step.relationship_types.is_empty() || step.relationship_types.contains(&type_name(&edge.relationship_type))
+        };
+
+        match step.op {
+            GraphQueryOp::FindNodes => {
+                let needle = step.node.as_deref().unwrap_or("").trim().to_lowercase();
+                nodes.iter().enumerate()
+                    .filter(|(_, n)| type_allowed(n) && n.name.to_lowercase().contains(&needle))
+                    .take(MAX_FOUND_NODES)
+                    .for_each(|(i, _)| found.add_node(i));
+            }
+            GraphQueryOp::Neighbors => {
+                let depth = step.depth.unwrap_or(1).clamp(1, 3);
+                let anchors = step.node.as_deref().map(|r| resolve_nodes(nodes, r)).unwrap_or_default();
+                let mut visited: HashSet<usize> = anchors.iter().copied().collect();
+                let mut frontier: VecDeque<(usize, usize)> = anchors.iter().map(|&i| (i, 0)).collect();
+                anchors.iter().for_each(|&i| found.add_node(i));
+
+                while let Some((current, hops)) = frontier.pop_front() {
+                    if hops >= depth {
+                        continue;
+                    }
+                    let current_id = nodes[current].id.as_str();
+                    for (e, edge) in edges.iter().enumerate().filter(|(_, e)| relationship_allowed(e)) {
+                        let other = if edge.from_node == current_id {
+                            &edge.to_node
+                        } else if edge.to_node == current_id {
+                            &edge.from_node
+                        } else {
+                            continue;
+                        };
+                        let Some(&next) = by_id.get(other.as_str()) else { continue };
+                        if !type_allowed(&nodes[next]) {
+                            continue;
+                        }
+                        found.add_edge(e);
+                        found.add_node(next);
+                        if visited.insert(next) {
+                            frontier.push_back((next, hops + 1));
+                        }
+                    }
+                }
+            }
+            GraphQueryOp::Path => {
+                let (Some(from), Some(to)) = (step.node.as_deref(), step.target.as_deref()) else { continue };
+                let (Some(&from), Some(&to)) = (resolve_nodes(nodes, from).first(), resolve_nodes(nodes, to).first()) else {
+                    continue;
+                };
+                let depth = step.depth.unwrap_or(6).clamp(1, 10);
+                for path in ranked_paths(nodes, edges, &nodes[from].id, &nodes[to].id, depth, 3, false) {
+                    for pair in path.path.windows(2) {
+                        let strongest = edges.iter().enumerate()
+                            .filter(|(_, e)| (e.from_node == pair[0] && e.to_node == pair[1]) || (e.from_node == pair[1] && e.to_node == pair[0]))
+                            .max_by(|a, b| a.1.weight.total_cmp(&b.1.weight));
+                        if let Some((e, _)) = strongest {
+                            found.add_edge(e);
+                        }
+                    }
+                    path.path.iter().filter_map(|id| by_id.get(id.as_str())).for_each(|&i| found.add_node(i));
+                }
+            }
+        }
+    }
+
+    (found.nodes, found.edges)
+}
+
+/// Facts as plain lines for the answering prompt
+fn describe_facts(nodes: &[KnowledgeNode], edges: &[KnowledgeEdge]) -> String {
+    let names: HashMap<&str, &str> = nodes.iter().map(|n| (n.id.as_str(), n.name.as_str())).collect();
+    let name = |id: &str| names.get(id).copied().unwrap_or("(unknown)").to_string();
+
+    let node_lines = nodes.iter().map(|n| format!("Node: {} ({})", n.name, type_name(&n.node_type)));
+    let edge_lines = edges.iter().map(|e| format!(
+        "Edge: {} -[{} {:.2}]-> {}", name(&e.from_node), type_name(&e.relationship_type), e.weight, name(&e.to_node)
+    ));
+    node_lines.chain(edge_lines).take(MAX_ANSWER_FACTS).collect::<Vec<_>>().join("\n")
+}
+
+#[tauri::command]
+pub async fn ask_knowledge_graph(
+    agent_id: String,
+    question: String,
+    provider: Option<String>,
+    model: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, super::simple_commands::MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<KnowledgeGraphAnswer, String> {
+    info!("Answering knowledge graph question for agent: {}", agent_id);
+
+    // Validation
+    GraphValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    if question.trim().is_empty() {
+        return Err("Question cannot be empty".to_string());
+    }
+    if question.chars().count() > MAX_QUESTION_CHARS {
+        return Err(format!("Question exceeds {} characters", MAX_QUESTION_CHARS));
+    }
+
+    // Security
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "graph_operations",
+        &[agent_id.clone(), question.clone()],
+        &[]
+    ).await?;
+
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+    let sanitized_question = &validation_result.sanitized_inputs[1];
+
+    // Plan the queries
+    let provider = select_completion_provider(&ai_state, provider.as_deref())
+        .map_err(|e| e.to_string())?;
+    let schema = plan_schema();
+    let mut guard = GuardrailScope::new(&app_handle, &ai_state.storage, Some(sanitized_agent_id));
+    let plan = complete_structured(
+        &ai_state,
+        &mut guard,
+        &provider,
+        model.as_deref(),
+        PLANNER_SYSTEM_PROMPT,
+        sanitized_question,
+        &ResponseSchema { name: "graph_query_plan", description: "Graph queries that answer the question", schema: &schema },
+    ).await.map_err(|e| format!("Failed to plan graph queries: {}", e))?;
+    let mut plan: GraphQueryPlan = serde_json::from_value(plan)
+        .map_err(|e| format!("Provider returned an invalid query plan: {}", e))?;
+    plan.steps.truncate(MAX_QUERY_STEPS);
+
+    // Run them against the stored graph
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let (nodes, edges) = manager.load_knowledge_graph()
+        .map_err(|e| format!("Failed to load knowledge graph: {}", e))?;
+    let (node_indices, edge_indices) = execute_plan(&nodes, &edges, &plan.steps);
+
+    let used_nodes: Vec<KnowledgeNode> = node_indices.iter()
+        .map(|&i| KnowledgeNode { embedding: None, ..nodes[i].clone() })
+        .collect();
+    let used_edges: Vec<KnowledgeEdge> = edge_indices.iter().map(|&i| edges[i].clone()).collect();
+
+    if used_nodes.is_empty() {
+        warn!("No graph entries matched the planned queries for agent {}", sanitized_agent_id);
+        return Ok(KnowledgeGraphAnswer {
+            answer: "The knowledge graph has no entries matching this question.".to_string(),
+            queries: plan.steps,
+            nodes: used_nodes,
+            edges: used_edges,
+            provider: Some(provider),
+        });
+    }
+
+    // Compose the answer from the retrieved facts only
+    let prompt = format!(
+        "Knowledge graph facts:\n{}\n\nQuestion: {}",
+        describe_facts(&used_nodes, &used_edges),
+        sanitized_question
+    );
+    let answer = complete_text(&ai_state, &mut guard, &provider, model.as_deref(), ANSWER_SYSTEM_PROMPT, &prompt)
+        .await
+        .map_err(|e| format!("Failed to answer from the knowledge graph: {}", e))?;
+
+    info!("Answered from {} node(s) and {} edge(s)", used_nodes.len(), used_edges.len());
+    Ok(KnowledgeGraphAnswer {
+        answer,
+        queries: plan.steps,
+        nodes: used_nodes,
+        edges: used_edges,
+        provider: Some(provider),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::memory::{NodeType, RelationshipType};
+
+    fn graph() -> (Vec<KnowledgeNode>, Vec<KnowledgeEdge>) {
+        let node = |id: &str, name: &str, node_type: NodeType| {
+            let mut n = KnowledgeNode::new(node_type, name.to_string());
+            n.id = id.to_string();
+            n
+        };
+        let edge = |from: &str, to: &str, rel: RelationshipType, weight: f32| {
+            KnowledgeEdge::new(from.to_string(), to.to_string(), rel).with_weight(weight)
+        };
+        (
+            vec![
+                node("n1", "Researcher", NodeType::Agent),
+                node("n2", "Web Search", NodeType::Tool),
+                node("n3", "Rust Borrow Checker", NodeType::Concept),
+                node("n4", "Writer", NodeType::Agent),
+            ],
+            vec![
+                edge("n1", "n2", RelationshipType::Uses, 0.9),
+                edge("n1", "n3", RelationshipType::LearnedFrom, 0.6),
+                edge("n4", "n1", RelationshipType::CollaboratesWith, 0.8),
+            ],
+        )
+    }
+
+    fn step(op: GraphQueryOp, node: Option<&str>, target: Option<&str>) -> GraphQueryStep {
+        GraphQueryStep {
+            op,
+            node: node.map(String::from),
+            target: target.map(String::from),
+            node_types: Vec::new(),
+            relationship_types: Vec::new(),
+            depth: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_steps_collect_subgraph() {
+        let (nodes, edges) = graph();
+
+        let mut tools = step(GraphQueryOp::Neighbors, Some("researcher"), None);
+        tools.relationship_types = vec!["Uses".to_string()];
+        let (found_nodes, found_edges) = execute_plan(&nodes, &edges, &[tools]);
+        assert_eq!(found_nodes, vec![0, 1]);
+        assert_eq!(found_edges, vec![0]);
+
+        // Path from Writer to the concept goes through the researcher
+        let (found_nodes, found_edges) = execute_plan(&nodes, &edges, &[step(GraphQueryOp::Path, Some("Writer"), Some("borrow"))]);
+        assert_eq!(found_nodes, vec![3, 0, 2]);
+        assert_eq!(found_edges, vec![2, 1]);
+
+        let mut agents = step(GraphQueryOp::FindNodes, None, None);
+        agents.node_types = vec!["Agent".to_string()];
+        assert_eq!(execute_plan(&nodes, &edges, &[agents]).0, vec![0, 3]);
+    }
+
+    #[test]
+    fn test_plan_parses_from_strict_schema_output() {
+        let output = json!({ "steps": [
+            { "op": "neighbors", "node": "Writer", "target": null, "node_types": [], "relationship_types": ["CollaboratesWith"], "depth": 2 }
+        ]});
+        let plan: GraphQueryPlan = serde_json::from_value(output).unwrap();
+        assert_eq!(plan.steps[0].op, GraphQueryOp::Neighbors);
+        assert_eq!(plan.steps[0].depth, Some(2));
+        assert_eq!(plan_schema()["properties"]["steps"]["items"]["required"].as_array().unwrap().len(), 6);
+    }
+}
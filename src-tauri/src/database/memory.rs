@@ -4,6 +4,9 @@ use anyhow::Result;
 use uuid::Uuid;
 use std::collections::HashMap;
 
+pub use super::embeddings::SimilarityMetric;
+use super::neural_knowledge_graph::{ContradictionClassifier, ContradictionKind};
+
 // Agent Memory Types
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AgentMemory {
@@ -18,6 +21,28 @@ pub struct AgentMemory {
     pub updated_at: DateTime<Utc>,
     pub access_count: i32,
     pub tags: Vec<String>,
+    /// Exempt from decay, consolidation and eviction, and ranked first within its type
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub tier: MemoryTier,
+}
+
+/// Episodic memories are recorded as things happen; semantic ones are consolidated from them
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MemoryTier {
+    #[default]
+    Episodic,
+    Semantic,
+}
+
+impl MemoryTier {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Semantic" => MemoryTier::Semantic,
+            _ => MemoryTier::Episodic,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
@@ -48,6 +73,13 @@ impl std::fmt::Display for MemoryType {
 }
 
 // Shared Knowledge Types
+
+/// Confidence is scaled by this each time an entry is found to be contradicted
+pub const CONTRADICTION_CONFIDENCE_FACTOR: f32 = 0.7;
+
+/// Floor for confidence lowered by contradictions
+pub const MIN_CONFIDENCE_SCORE: f32 = 0.1;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SharedKnowledge {
     pub id: String,
@@ -127,7 +159,7 @@ pub enum RelationshipType {
 }
 
 // Memory Search and Retrieval
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryQuery {
     pub agent_id: Option<String>,
     pub memory_types: Option<Vec<MemoryType>>,
@@ -135,9 +167,55 @@ pub struct MemoryQuery {
     pub tags: Option<Vec<String>>,
     pub embedding: Option<Vec<f32>>,
     pub similarity_threshold: Option<f32>,
+    /// Metric used to score `embedding` against stored memory embeddings
+    #[serde(default)]
+    pub similarity_metric: SimilarityMetric,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
     pub time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// Only memories in these tiers; `None` searches both
+    #[serde(default)]
+    pub tiers: Option<Vec<MemoryTier>>,
+    /// Rank by fusing vector, full-text, tag and recency rankings instead of filtering on
+    /// `content_search` and sorting by relevance
+    #[serde(default)]
+    pub fusion: Option<FusionWeights>,
+}
+
+/// Weight of each signal in hybrid search's reciprocal rank fusion; 0 leaves a signal out
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FusionWeights {
+    pub vector: f32,
+    pub text: f32,
+    pub tags: f32,
+    pub recency: f32,
+}
+
+impl Default for FusionWeights {
+    fn default() -> Self {
+        Self { vector: 1.0, text: 1.0, tags: 0.5, recency: 0.25 }
+    }
+}
+
+/// Where a result stood in one signal's ranking and what that added to its fused score
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SignalScore {
+    /// 0-based
+    pub rank: usize,
+    /// Similarity, negated BM25, matching tag count or age in hours
+    pub raw: f32,
+    pub contribution: f32,
+}
+
+/// Per-signal breakdown of a hybrid search result; signals that didn't rank it are `None`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FusionScores {
+    pub vector: Option<SignalScore>,
+    pub text: Option<SignalScore>,
+    pub tags: Option<SignalScore>,
+    pub recency: Option<SignalScore>,
+    pub fused: f32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -145,6 +223,30 @@ pub struct MemorySearchResult {
     pub memory: AgentMemory,
     pub similarity_score: Option<f32>,
     pub relevance_rank: usize,
+    /// Set by hybrid search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fusion_scores: Option<FusionScores>,
+}
+
+/// Read access one agent has granted another over a subset of its memories.
+/// Empty `tags` or `memory_types` leave that dimension unrestricted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemoryAccessGrant {
+    pub id: String,
+    pub owner_agent: String,
+    pub grantee_agent: String,
+    pub tags: Vec<String>,
+    pub memory_types: Vec<MemoryType>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MemoryAccessGrant {
+    /// Whether the grantee may read this memory of the owner's
+    pub fn permits(&self, memory: &AgentMemory) -> bool {
+        memory.agent_id == self.owner_agent
+            && (self.memory_types.is_empty() || self.memory_types.contains(&memory.memory_type))
+            && (self.tags.is_empty() || memory.tags.iter().any(|t| self.tags.iter().any(|g| g.eq_ignore_ascii_case(t))))
+    }
 }
 
 // Agent Interaction Tracking
@@ -194,6 +296,8 @@ impl AgentMemory {
             updated_at: Utc::now(),
             access_count: 0,
             tags: Vec::new(),
+            pinned: false,
+            tier: MemoryTier::Episodic,
         }
     }
 
@@ -246,6 +350,42 @@ impl SharedKnowledge {
             self.version += 1;
         }
     }
+
+    /// How this entry contradicts a closely related one, if it does; entries without an
+    /// embedding can't be classified
+    pub fn contradiction_with(&self, other: &SharedKnowledge, classifier: &ContradictionClassifier) -> Option<ContradictionKind> {
+        classifier.classify(self.embedding.as_deref()?, other.embedding.as_deref()?)
+    }
+
+    /// Lower confidence after a contradicting entry was found
+    pub fn record_contradiction(&mut self) {
+        self.confidence_score = (self.confidence_score * CONTRADICTION_CONFIDENCE_FACTOR).max(MIN_CONFIDENCE_SCORE);
+        self.updated_at = Utc::now();
+        self.version += 1;
+    }
+
+    /// Fold in a contribution that agrees with this one: its agents become sources, tags are
+    /// unioned and a missing embedding is filled in. Returns whether anything changed.
+    pub fn merge_contribution(&mut self, other: &SharedKnowledge) -> bool {
+        let before = (self.source_agents.len(), self.tags.len(), self.embedding.is_some());
+        for agent in &other.source_agents {
+            self.add_source_agent(agent.clone());
+        }
+        for tag in &other.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        if self.embedding.is_none() {
+            self.embedding = other.embedding.clone();
+        }
+
+        let changed = before != (self.source_agents.len(), self.tags.len(), self.embedding.is_some());
+        if changed {
+            self.updated_at = Utc::now();
+        }
+        changed
+    }
 }
 
 impl KnowledgeNode {
@@ -282,6 +422,27 @@ impl KnowledgeEdge {
     }
 }
 
+/// Move pinned results ahead of unpinned ones of the same memory type. Each type keeps the
+/// positions it held, and results keep their order within pinned and unpinned.
+pub fn rank_pinned_first(results: &mut Vec<MemorySearchResult>) {
+    let mut slots: HashMap<MemoryType, Vec<usize>> = HashMap::new();
+    for (i, result) in results.iter().enumerate() {
+        slots.entry(result.memory.memory_type.clone()).or_default().push(i);
+    }
+
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    for positions in slots.values() {
+        let mut ranked = positions.clone();
+        ranked.sort_by_key(|&i| !results[i].memory.pinned);
+        for (&slot, &source) in positions.iter().zip(&ranked) {
+            order[slot] = source;
+        }
+    }
+
+    let mut taken: Vec<Option<MemorySearchResult>> = std::mem::take(results).into_iter().map(Some).collect();
+    *results = order.into_iter().filter_map(|i| taken[i].take()).collect();
+}
+
 // Utility functions
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
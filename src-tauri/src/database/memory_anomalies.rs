@@ -0,0 +1,463 @@
+//! Anomaly detection over each agent's stream of new memories.
+//!
+//! A small autoencoder learns to reconstruct the agent's memory embeddings; a memory it
+//! reconstructs far worse than usual is flagged as content drift. Error memories arriving
+//! much faster than the agent's usual error rate are flagged as an error spike. Flags are
+//! stored in the agent database and emitted as `memory://anomaly` events.
+
+use super::embeddings::SimilarityMetric;
+use super::memory::{AgentMemory, MemoryQuery, MemoryType};
+use super::neural_network::{ActivationFunction, NetworkBuilder, NeuralNetwork};
+use super::parse_db_timestamp;
+use super::simple_commands::MemoryState;
+use super::simple_memory::SimpleMemoryManager;
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, State};
+use tracing::{error, info, warn};
+
+/// Event emitted with each `MemoryAnomaly` as it is detected
+pub const MEMORY_ANOMALY_EVENT: &str = "memory://anomaly";
+
+/// Stored memories replayed through a new detector before it flags anything
+const WARMUP_MEMORIES: usize = 200;
+
+/// Scores observed before drift can be flagged
+const MIN_BASELINE: u64 = 20;
+
+/// Standard deviations above the mean reconstruction error that count as drift
+const DRIFT_Z_THRESHOLD: f32 = 3.5;
+
+/// Width of the autoencoder's bottleneck
+const AUTOENCODER_HIDDEN: usize = 32;
+
+const AUTOENCODER_LEARNING_RATE: f32 = 0.01;
+
+const ERROR_SPIKE_WINDOW_MINUTES: i64 = 10;
+
+/// Errors a window needs before it can be a spike
+const ERROR_SPIKE_MIN_ERRORS: usize = 5;
+
+/// How many times the usual error share a window must reach
+const ERROR_SPIKE_RATIO: f32 = 3.0;
+
+/// Usual error share assumed at least this high, so a clean history doesn't make every error a spike
+const MIN_BASELINE_ERROR_SHARE: f32 = 0.1;
+
+const DEFAULT_LIST_LIMIT: usize = 100;
+const MAX_LIST_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    ErrorSpike,
+    ContentDrift,
+}
+
+impl AnomalyKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::ErrorSpike => "error_spike",
+            AnomalyKind::ContentDrift => "content_drift",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "error_spike" => AnomalyKind::ErrorSpike,
+            _ => AnomalyKind::ContentDrift,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryAnomaly {
+    pub id: String,
+    pub agent_id: String,
+    /// Memory that triggered the flag
+    pub memory_id: String,
+    pub kind: AnomalyKind,
+    /// Z-score of the reconstruction error for drift, multiple of the usual error share for spikes
+    pub score: f32,
+    pub detail: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Running mean and variance (Welford)
+#[derive(Debug, Clone, Default)]
+struct RunningStats {
+    count: u64,
+    mean: f32,
+    m2: f32,
+}
+
+impl RunningStats {
+    fn push(&mut self, value: f32) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    fn std_dev(&self) -> f32 {
+        if self.count < 2 {
+            0.0
+        } else {
+            (self.m2 / (self.count - 1) as f32).sqrt()
+        }
+    }
+}
+
+/// Learned picture of one agent's normal memory stream
+pub struct AnomalyDetector {
+    agent_id: String,
+    /// Built on the first embedding, sized to it
+    autoencoder: Option<NeuralNetwork>,
+    reconstruction: RunningStats,
+    /// Creation time and whether it was an error, for memories inside the spike window
+    recent: VecDeque<(DateTime<Utc>, bool)>,
+    seen: u64,
+    errors: u64,
+    last_spike: Option<DateTime<Utc>>,
+}
+
+impl AnomalyDetector {
+    pub fn new(agent_id: String) -> Self {
+        Self {
+            agent_id,
+            autoencoder: None,
+            reconstruction: RunningStats::default(),
+            recent: VecDeque::new(),
+            seen: 0,
+            errors: 0,
+            last_spike: None,
+        }
+    }
+
+    /// Learn from `memory`, returning what was unusual about it given everything before it
+    pub fn observe(&mut self, memory: &AgentMemory) -> Vec<MemoryAnomaly> {
+        let mut anomalies = Vec::new();
+        if let Some(anomaly) = self.check_error_spike(memory) {
+            anomalies.push(anomaly);
+        }
+        if let Some(anomaly) = self.check_drift(memory) {
+            anomalies.push(anomaly);
+        }
+        anomalies
+    }
+
+    fn check_error_spike(&mut self, memory: &AgentMemory) -> Option<MemoryAnomaly> {
+        let is_error = memory.memory_type == MemoryType::Error;
+        let baseline_share = if self.seen == 0 { 0.0 } else { self.errors as f32 / self.seen as f32 };
+        self.seen += 1;
+        if is_error {
+            self.errors += 1;
+        }
+
+        let window = Duration::minutes(ERROR_SPIKE_WINDOW_MINUTES);
+        self.recent.push_back((memory.created_at, is_error));
+        while self.recent.front().is_some_and(|(at, _)| memory.created_at - *at > window) {
+            self.recent.pop_front();
+        }
+
+        if !is_error || self.last_spike.is_some_and(|at| memory.created_at - at <= window) {
+            return None;
+        }
+        let window_errors = self.recent.iter().filter(|(_, error)| *error).count();
+        if window_errors < ERROR_SPIKE_MIN_ERRORS {
+            return None;
+        }
+        let window_share = window_errors as f32 / self.recent.len() as f32;
+        let ratio = window_share / baseline_share.max(MIN_BASELINE_ERROR_SHARE);
+        if ratio < ERROR_SPIKE_RATIO {
+            return None;
+        }
+
+        self.last_spike = Some(memory.created_at);
+        Some(self.anomaly(memory, AnomalyKind::ErrorSpike, ratio, format!(
+            "{} of the last {} memories within {} minutes were errors ({:.0}% against a usual {:.0}%)",
+            window_errors, self.recent.len(), ERROR_SPIKE_WINDOW_MINUTES,
+            window_share * 100.0, baseline_share * 100.0
+        )))
+    }
+
+    fn check_drift(&mut self, memory: &AgentMemory) -> Option<MemoryAnomaly> {
+        let embedding = memory.embedding.as_ref().filter(|e| !e.is_empty())?;
+        let autoencoder = match &mut self.autoencoder {
+            Some(autoencoder) if autoencoder.num_inputs() == embedding.len() => autoencoder,
+            _ => {
+                // First embedding, or the embedding model changed size: start learning afresh
+                let autoencoder = NetworkBuilder::new()
+                    .input_layer(embedding.len())
+                    .hidden_layer_with_activation(AUTOENCODER_HIDDEN, ActivationFunction::Tanh, 0.0)
+                    .output_layer(embedding.len())
+                    .learning_rate(AUTOENCODER_LEARNING_RATE)
+                    .build()
+                    .ok()?;
+                self.reconstruction = RunningStats::default();
+                self.autoencoder.insert(autoencoder)
+            }
+        };
+
+        // The error before this training step is how well the memory fits what came before
+        let error = autoencoder.train_incremental(embedding, embedding).ok()?;
+        let stats = self.reconstruction.clone();
+        self.reconstruction.push(error);
+
+        let std_dev = stats.std_dev();
+        if stats.count < MIN_BASELINE || std_dev <= f32::EPSILON {
+            return None;
+        }
+        let z = (error - stats.mean) / std_dev;
+        if z < DRIFT_Z_THRESHOLD {
+            return None;
+        }
+        Some(self.anomaly(memory, AnomalyKind::ContentDrift, z, format!(
+            "Reconstruction error {:.4} is {:.1} standard deviations above the usual {:.4}",
+            error, z, stats.mean
+        )))
+    }
+
+    fn anomaly(&self, memory: &AgentMemory, kind: AnomalyKind, score: f32, detail: String) -> MemoryAnomaly {
+        MemoryAnomaly {
+            id: uuid::Uuid::new_v4().to_string(),
+            agent_id: self.agent_id.clone(),
+            memory_id: memory.id.clone(),
+            kind,
+            score,
+            detail,
+            detected_at: Utc::now(),
+        }
+    }
+}
+
+pub fn record_anomaly(conn: &Connection, anomaly: &MemoryAnomaly) -> Result<()> {
+    conn.execute(
+        "INSERT INTO memory_anomalies (id, agent_id, memory_id, kind, score, detail, detected_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            anomaly.id,
+            anomaly.agent_id,
+            anomaly.memory_id,
+            anomaly.kind.as_str(),
+            anomaly.score,
+            anomaly.detail,
+            anomaly.detected_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Most recent first
+pub fn list_anomalies(
+    conn: &Connection,
+    agent_id: &str,
+    since: Option<DateTime<Utc>>,
+    kind: Option<AnomalyKind>,
+    limit: usize,
+) -> Result<Vec<MemoryAnomaly>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_id, memory_id, kind, score, detail, detected_at FROM memory_anomalies
+         WHERE agent_id = ?1 AND (?2 IS NULL OR detected_at >= ?2) AND (?3 IS NULL OR kind = ?3)
+         ORDER BY detected_at DESC LIMIT ?4",
+    )?;
+    let rows = stmt.query_map(
+        params![agent_id, since.map(|at| at.to_rfc3339()), kind.map(|k| k.as_str()), limit],
+        |row| {
+            Ok(MemoryAnomaly {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                memory_id: row.get(2)?,
+                kind: AnomalyKind::parse(&row.get::<_, String>(3)?),
+                score: row.get(4)?,
+                detail: row.get(5)?,
+                detected_at: parse_db_timestamp(&row.get::<_, String>(6)?),
+            })
+        },
+    )?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Per-agent detectors fed by every memory save
+pub struct MemoryAnomalyMonitor {
+    detectors: Mutex<HashMap<String, AnomalyDetector>>,
+    notifier: Mutex<Option<AppHandle>>,
+}
+
+impl MemoryAnomalyMonitor {
+    fn new() -> Self {
+        Self {
+            detectors: Mutex::new(HashMap::new()),
+            notifier: Mutex::new(None),
+        }
+    }
+
+    /// Emit `memory://anomaly` events through this app from now on
+    pub fn attach(&self, app: AppHandle) {
+        *self.notifier.lock().unwrap() = Some(app);
+    }
+
+    /// Check a newly saved memory, storing and emitting anything unusual. Failures are
+    /// logged rather than returned so they never fail the save.
+    pub fn observe(&self, manager: &SimpleMemoryManager, memory: &AgentMemory) -> Vec<MemoryAnomaly> {
+        let anomalies = {
+            let mut detectors = self.detectors.lock().unwrap();
+            let detector = detectors.entry(memory.agent_id.clone())
+                .or_insert_with(|| warmed_up_detector(manager, &memory.agent_id, &memory.id));
+            detector.observe(memory)
+        };
+        if anomalies.is_empty() {
+            return anomalies;
+        }
+
+        match manager.agent_connection() {
+            Ok(conn) => {
+                for anomaly in &anomalies {
+                    if let Err(e) = record_anomaly(&conn, anomaly) {
+                        error!("Failed to store memory anomaly for {}: {}", anomaly.agent_id, e);
+                    }
+                }
+            }
+            Err(e) => error!("Failed to open agent database to store anomalies: {}", e),
+        }
+
+        let notifier = self.notifier.lock().unwrap().clone();
+        for anomaly in &anomalies {
+            info!("Memory anomaly for {}: {:?} ({})", anomaly.agent_id, anomaly.kind, anomaly.detail);
+            if let Some(ref app) = notifier {
+                if let Err(e) = app.emit(MEMORY_ANOMALY_EVENT, anomaly) {
+                    error!("Failed to emit memory anomaly: {}", e);
+                }
+            }
+        }
+        anomalies
+    }
+
+    /// Drop what was learned about an agent, e.g. after its memories are purged
+    pub fn forget(&self, agent_id: &str) {
+        self.detectors.lock().unwrap().remove(agent_id);
+    }
+}
+
+/// A detector that has already seen the agent's recent stored memories, other than `skip_id`
+fn warmed_up_detector(manager: &SimpleMemoryManager, agent_id: &str, skip_id: &str) -> AnomalyDetector {
+    let mut detector = AnomalyDetector::new(agent_id.to_string());
+    let history = manager.search_memories(&MemoryQuery {
+        agent_id: Some(agent_id.to_string()),
+        memory_types: None,
+        content_search: None,
+        tags: None,
+        embedding: None,
+        similarity_threshold: None,
+        similarity_metric: SimilarityMetric::default(),
+        limit: Some(WARMUP_MEMORIES),
+        offset: None,
+        time_range: None,
+        tiers: None,
+        fusion: None,
+    });
+    match history {
+        Ok(results) => {
+            let mut memories: Vec<AgentMemory> = results.into_iter()
+                .map(|result| result.memory)
+                .filter(|memory| memory.id != skip_id)
+                .collect();
+            memories.sort_by_key(|memory| memory.created_at);
+            for memory in &memories {
+                detector.observe(memory);
+            }
+        }
+        Err(e) => warn!("Failed to load memory history for anomaly detection: {}", e),
+    }
+    detector
+}
+
+pub static MEMORY_ANOMALY_MONITOR: Lazy<MemoryAnomalyMonitor> = Lazy::new(MemoryAnomalyMonitor::new);
+
+/// Stored anomalies for an agent's dashboard, most recent first
+#[tauri::command]
+pub async fn list_memory_anomalies(
+    agent_id: String,
+    since: Option<DateTime<Utc>>,
+    kind: Option<AnomalyKind>,
+    limit: Option<usize>,
+    state: State<'_, MemoryState>,
+) -> Result<Vec<MemoryAnomaly>, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let limit = limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT);
+
+    tokio::task::spawn_blocking(move || -> Result<Vec<MemoryAnomaly>> {
+        let conn = manager.agent_connection()?;
+        list_anomalies(&conn, &sanitized_agent_id, since, kind, limit)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to list memory anomalies: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+
+    fn memory(memory_type: MemoryType, minutes: i64, embedding: Option<Vec<f32>>) -> AgentMemory {
+        let mut memory = AgentMemory::new("agent_1".to_string(), memory_type, "event".to_string());
+        memory.created_at = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc)
+            + Duration::minutes(minutes);
+        memory.embedding = embedding;
+        memory
+    }
+
+    #[test]
+    fn test_flags_error_spike_once_per_window() {
+        let mut detector = AnomalyDetector::new("agent_1".to_string());
+        for i in 0..40 {
+            let memory_type = if i % 10 == 0 { MemoryType::Error } else { MemoryType::Task };
+            assert!(detector.observe(&memory(memory_type, i * 30, None)).is_empty());
+        }
+
+        let spikes: Vec<_> = (0..8)
+            .flat_map(|i| detector.observe(&memory(MemoryType::Error, 1200 + i, None)))
+            .collect();
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].kind, AnomalyKind::ErrorSpike);
+        assert!(spikes[0].score >= ERROR_SPIKE_RATIO);
+    }
+
+    #[test]
+    fn test_flags_content_drift_and_stores_it() {
+        let mut detector = AnomalyDetector::new("agent_1".to_string());
+        let usual = |i: usize| (0..16).map(|j| if j < 8 { 0.5 + ((i + j) % 3) as f32 * 0.01 } else { 0.0 }).collect();
+        for i in 0..60 {
+            detector.observe(&memory(MemoryType::Context, i as i64, Some(usual(i))));
+        }
+        let drifted: Vec<f32> = (0..16).map(|j| if j < 8 { 0.0 } else { 0.9 }).collect();
+        let anomalies = detector.observe(&memory(MemoryType::Context, 61, Some(drifted)));
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].kind, AnomalyKind::ContentDrift);
+
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        record_anomaly(&conn, &anomalies[0]).unwrap();
+        let stored = list_anomalies(&conn, "agent_1", None, Some(AnomalyKind::ContentDrift), 10).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].memory_id, anomalies[0].memory_id);
+        assert!(list_anomalies(&conn, "agent_1", None, Some(AnomalyKind::ErrorSpike), 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use super::memory::*;
+use super::simple_commands::MemoryState;
+use crate::validation::MemoryValidator;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tauri::State;
+use tracing::info;
+
+/// Most memories considered for a single browse request
+const MAX_BROWSE_MEMORIES: usize = 5000;
+
+/// Cluster grouping compares every pair, so it only looks at the most relevant memories
+const MAX_CLUSTER_MEMORIES: usize = 1000;
+
+/// Similarity at which two memories are linked into the same cluster
+const CLUSTER_SIMILARITY_THRESHOLD: f32 = 0.5;
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const REPRESENTATIVES_PER_GROUP: usize = 3;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryGroupBy {
+    Type,
+    Tag,
+    Day,
+    Cluster,
+}
+
+/// One group of the contact sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryGroup {
+    pub key: String,
+    pub label: String,
+    pub count: usize,
+    /// Highest-relevance members, returned without embeddings
+    pub representatives: Vec<AgentMemory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryBrowsePage {
+    pub agent_id: String,
+    pub group_by: MemoryGroupBy,
+    pub total_memories: usize,
+    pub total_groups: usize,
+    pub page: usize,
+    pub page_size: usize,
+    pub groups: Vec<MemoryGroup>,
+}
+
+fn representatives(members: &[&AgentMemory]) -> Vec<AgentMemory> {
+    let mut ranked = members.to_vec();
+    ranked.sort_by(|a, b| {
+        b.relevance_score.partial_cmp(&a.relevance_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.access_count.cmp(&a.access_count))
+    });
+
+    ranked.into_iter()
+        .take(REPRESENTATIVES_PER_GROUP)
+        .map(|m| AgentMemory { embedding: None, ..m.clone() })
+        .collect()
+}
+
+fn build_group(key: String, label: String, members: &[&AgentMemory]) -> MemoryGroup {
+    MemoryGroup {
+        key,
+        label,
+        count: members.len(),
+        representatives: representatives(members),
+    }
+}
+
+/// Connected components of the similarity graph between memories
+fn cluster_memories(memories: &[AgentMemory], metric: SimilarityMetric) -> Vec<Vec<usize>> {
+    let keywords: Vec<HashSet<String>> = memories.iter()
+        .map(|m| extract_keywords(&m.content).into_iter().collect())
+        .collect();
+
+    let similar = |a: usize, b: usize| -> bool {
+        if let (Some(ea), Some(eb)) = (&memories[a].embedding, &memories[b].embedding) {
+            return metric.similarity(ea, eb) >= CLUSTER_SIMILARITY_THRESHOLD;
+        }
+        let (ka, kb) = (&keywords[a], &keywords[b]);
+        if ka.is_empty() || kb.is_empty() {
+            return false;
+        }
+        let jaccard = ka.intersection(kb).count() as f32 / ka.union(kb).count() as f32;
+        jaccard >= CLUSTER_SIMILARITY_THRESHOLD
+    };
+
+    let mut parent: Vec<usize> = (0..memories.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for a in 0..memories.len() {
+        for b in (a + 1)..memories.len() {
+            if similar(a, b) {
+                let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+                if ra != rb {
+                    parent[rb] = ra;
+                }
+            }
+        }
+    }
+
+    let mut components: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..memories.len() {
+        let root = find(&mut parent, i);
+        components.entry(root).or_default().push(i);
+    }
+    components.into_values().collect()
+}
+
+/// Label a cluster with its most frequent keywords
+fn cluster_label(members: &[&AgentMemory]) -> String {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for memory in members {
+        for keyword in extract_keywords(&memory.content) {
+            *counts.entry(keyword).or_insert(0) += 1;
+        }
+    }
+
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let label = ranked.into_iter().take(3).map(|(k, _)| k).collect::<Vec<_>>().join(", ");
+    if label.is_empty() { "Miscellaneous".to_string() } else { label }
+}
+
+/// Group memories for browsing; groups are ordered largest first (newest first for days)
+pub fn group_memories(memories: &[AgentMemory], group_by: MemoryGroupBy, metric: SimilarityMetric) -> Vec<MemoryGroup> {
+    let mut groups = match group_by {
+        MemoryGroupBy::Type | MemoryGroupBy::Tag | MemoryGroupBy::Day => {
+            let mut buckets: BTreeMap<String, Vec<&AgentMemory>> = BTreeMap::new();
+            for memory in memories {
+                let keys = match group_by {
+                    MemoryGroupBy::Type => vec![format!("{:?}", memory.memory_type)],
+                    MemoryGroupBy::Day => vec![memory.created_at.format("%Y-%m-%d").to_string()],
+                    _ if memory.tags.is_empty() => vec!["untagged".to_string()],
+                    _ => memory.tags.iter().map(|t| t.to_lowercase()).collect::<HashSet<_>>().into_iter().collect(),
+                };
+                for key in keys {
+                    buckets.entry(key).or_default().push(memory);
+                }
+            }
+            buckets.into_iter()
+                .map(|(key, members)| build_group(key.clone(), key, &members))
+                .collect::<Vec<_>>()
+        }
+        MemoryGroupBy::Cluster => {
+            cluster_memories(memories, metric).into_iter()
+                .enumerate()
+                .map(|(i, indices)| {
+                    let members: Vec<&AgentMemory> = indices.iter().map(|&i| &memories[i]).collect();
+                    build_group(format!("cluster-{}", i), cluster_label(&members), &members)
+                })
+                .collect()
+        }
+    };
+
+    if group_by == MemoryGroupBy::Day {
+        groups.sort_by(|a, b| b.key.cmp(&a.key));
+    } else {
+        groups.sort_by(|a, b| b.count.cmp(&a.count).then(a.key.cmp(&b.key)));
+    }
+    groups
+}
+
+/// Browse an agent's memories grouped by type, tag, day or similarity cluster
+#[tauri::command]
+pub async fn browse_memories(
+    agent_id: String,
+    group_by: MemoryGroupBy,
+    page: Option<usize>,
+    page_size: Option<usize>,
+    state: State<'_, MemoryState>,
+) -> Result<MemoryBrowsePage, String> {
+    info!("Browsing memories for agent {} grouped by {:?}", agent_id, group_by);
+
+    // Phase 1: Input Validation
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    let page = page.unwrap_or(0);
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    MemoryValidator::validate_limit(page_size)
+        .map_err(|e| e.to_string())?;
+
+    // Phase 2: Security Middleware
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "memory_operations",
+        &[agent_id.clone()],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+
+    // Phase 3: Business Logic
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let metric = state.similarity_metric().await;
+    let limit = if group_by == MemoryGroupBy::Cluster { MAX_CLUSTER_MEMORIES } else { MAX_BROWSE_MEMORIES };
+
+    let memories: Vec<AgentMemory> = manager.search_memories(&MemoryQuery {
+        agent_id: Some(sanitized_agent_id.clone()),
+        memory_types: None,
+        content_search: None,
+        tags: None,
+        embedding: None,
+        similarity_threshold: None,
+        similarity_metric: metric,
+        limit: Some(limit),
+        offset: Some(0),
+        time_range: None,
+        tiers: None,
+        fusion: None,
+    }).map_err(|e| format!("Failed to load memories: {}", e))?
+        .into_iter()
+        .map(|result| result.memory)
+        .collect();
+
+    let groups = group_memories(&memories, group_by, metric);
+    let total_groups = groups.len();
+
+    Ok(MemoryBrowsePage {
+        agent_id: sanitized_agent_id,
+        group_by,
+        total_memories: memories.len(),
+        total_groups,
+        page,
+        page_size,
+        groups: groups.into_iter().skip(page * page_size).take(page_size).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(memory_type: MemoryType, content: &str, tags: &[&str], day: u32) -> AgentMemory {
+        let mut memory = AgentMemory::new("agent".to_string(), memory_type, content.to_string())
+            .with_tags(tags.iter().map(|t| t.to_string()).collect());
+        memory.created_at = chrono::TimeZone::with_ymd_and_hms(&chrono::Utc, 2024, 5, day, 12, 0, 0).unwrap();
+        memory
+    }
+
+    fn sample() -> Vec<AgentMemory> {
+        vec![
+            memory(MemoryType::Task, "deploy the rust backend service", &["deploy", "rust"], 1),
+            memory(MemoryType::Task, "deploy the rust backend service again", &["deploy"], 2),
+            memory(MemoryType::Learning, "user prefers dark theme colors", &[], 2),
+        ]
+    }
+
+    #[test]
+    fn test_group_by_type_tag_and_day() {
+        let memories = sample();
+
+        let by_type = group_memories(&memories, MemoryGroupBy::Type, SimilarityMetric::Cosine);
+        assert_eq!(by_type[0].key, "Task");
+        assert_eq!(by_type[0].count, 2);
+        assert!(by_type[0].representatives.iter().all(|m| m.embedding.is_none()));
+
+        let by_tag = group_memories(&memories, MemoryGroupBy::Tag, SimilarityMetric::Cosine);
+        let keys: Vec<&str> = by_tag.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["deploy", "rust", "untagged"]);
+
+        let by_day = group_memories(&memories, MemoryGroupBy::Day, SimilarityMetric::Cosine);
+        assert_eq!(by_day[0].key, "2024-05-02");
+        assert_eq!(by_day[0].count, 2);
+    }
+
+    #[test]
+    fn test_group_by_cluster() {
+        let clusters = group_memories(&sample(), MemoryGroupBy::Cluster, SimilarityMetric::Cosine);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].count, 2);
+        assert!(clusters[0].label.contains("deploy"));
+    }
+}
@@ -0,0 +1,383 @@
+use super::memory::*;
+use super::simple_commands::MemoryState;
+use crate::ai::AIState;
+use crate::validation::MemoryValidator;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::State;
+use tracing::{info, warn};
+
+/// Settings key prefix for per-agent memory injection budgets
+const BUDGET_SETTING_PREFIX: &str = "memory_budget.";
+
+/// Upper bound for a single agent's injection budget
+const MAX_BUDGET_TOKENS: usize = 32_000;
+
+/// Per-agent configuration controlling how much retrieved memory may be injected into prompts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryBudgetConfig {
+    /// Maximum number of (estimated) tokens of memory injected per turn
+    pub max_tokens: usize,
+    /// How strongly to penalise candidates that are similar to already selected memories (0.0 - 1.0)
+    pub diversity_weight: f32,
+    /// Candidates scoring below this importance are never injected
+    pub min_importance: f32,
+}
+
+impl Default for MemoryBudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: 1500,
+            diversity_weight: 0.3,
+            min_importance: 0.05,
+        }
+    }
+}
+
+/// A memory that was selected for injection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InjectedMemory {
+    pub memory_id: String,
+    pub memory_type: MemoryType,
+    pub content: String,
+    pub estimated_tokens: usize,
+    pub importance: f32,
+    pub selection_score: f32,
+}
+
+/// Report describing what was injected on a single turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryInjectionReport {
+    pub agent_id: String,
+    pub budget_tokens: usize,
+    pub used_tokens: usize,
+    pub candidate_count: usize,
+    pub included: Vec<InjectedMemory>,
+    pub excluded_ids: Vec<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Rough token estimate (~4 characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    (text.chars().count() + 3) / 4
+}
+
+/// Importance of a memory, combining stored relevance, usage and query similarity
+pub fn memory_importance(memory: &AgentMemory, similarity: Option<f32>) -> f32 {
+    let relevance = memory.relevance_score.clamp(0.0, 1.0);
+    let usage = (1.0 + memory.access_count.max(0) as f32).ln() / 5.0;
+    let query_match = similarity.unwrap_or(0.5).clamp(0.0, 1.0);
+
+    0.5 * query_match + 0.35 * relevance + 0.15 * usage.min(1.0)
+}
+
+/// Similarity between two memories, using embeddings when both are available
+fn memory_similarity(a: &AgentMemory, b: &AgentMemory, metric: SimilarityMetric) -> f32 {
+    if let (Some(ea), Some(eb)) = (&a.embedding, &b.embedding) {
+        return metric.similarity(ea, eb).max(0.0);
+    }
+
+    let ka: HashSet<String> = extract_keywords(&a.content).into_iter().collect();
+    let kb: HashSet<String> = extract_keywords(&b.content).into_iter().collect();
+    if ka.is_empty() || kb.is_empty() {
+        return 0.0;
+    }
+
+    let intersection = ka.intersection(&kb).count() as f32;
+    let union = ka.union(&kb).count() as f32;
+    intersection / union
+}
+
+/// Greedily select the best subset of candidates that fits within the token budget.
+///
+/// Each round picks the candidate with the highest importance after a diversity
+/// penalty against the memories already chosen, skipping anything that would
+/// overflow the remaining budget. Pinned memories go first, exempt from the
+/// importance cutoff and the diversity penalty.
+pub fn select_within_budget(
+    agent_id: &str,
+    candidates: &[MemorySearchResult],
+    config: &MemoryBudgetConfig,
+    metric: SimilarityMetric,
+) -> MemoryInjectionReport {
+    let mut remaining: Vec<(usize, f32, usize)> = candidates.iter()
+        .enumerate()
+        .map(|(i, c)| (i, memory_importance(&c.memory, c.similarity_score), estimate_tokens(&c.memory.content)))
+        .filter(|(i, importance, _)| candidates[*i].memory.pinned || *importance >= config.min_importance)
+        .collect();
+
+    let mut selected: Vec<usize> = Vec::new();
+    let mut included = Vec::new();
+    let mut used_tokens = 0;
+
+    loop {
+        let budget_left = config.max_tokens.saturating_sub(used_tokens);
+        let mut best: Option<(usize, bool, f32)> = None;
+
+        for (pos, (idx, importance, tokens)) in remaining.iter().enumerate() {
+            if *tokens > budget_left {
+                continue;
+            }
+
+            let pinned = candidates[*idx].memory.pinned;
+            let score = if pinned {
+                *importance
+            } else {
+                let redundancy = selected.iter()
+                    .map(|s| memory_similarity(&candidates[*idx].memory, &candidates[*s].memory, metric))
+                    .fold(0.0f32, f32::max);
+                importance - config.diversity_weight * redundancy
+            };
+
+            if best.map_or(true, |(_, best_pinned, best_score)| (pinned, score) > (best_pinned, best_score)) {
+                best = Some((pos, pinned, score));
+            }
+        }
+
+        let Some((pos, _, score)) = best else { break };
+        let (idx, importance, tokens) = remaining.remove(pos);
+        let memory = &candidates[idx].memory;
+
+        used_tokens += tokens;
+        selected.push(idx);
+        included.push(InjectedMemory {
+            memory_id: memory.id.clone(),
+            memory_type: memory.memory_type.clone(),
+            content: memory.content.clone(),
+            estimated_tokens: tokens,
+            importance,
+            selection_score: score,
+        });
+    }
+
+    let excluded_ids = candidates.iter()
+        .enumerate()
+        .filter(|(i, _)| !selected.contains(i))
+        .map(|(_, c)| c.memory.id.clone())
+        .collect();
+
+    MemoryInjectionReport {
+        agent_id: agent_id.to_string(),
+        budget_tokens: config.max_tokens,
+        used_tokens,
+        candidate_count: candidates.len(),
+        included,
+        excluded_ids,
+        created_at: chrono::Utc::now(),
+    }
+}
+
+/// Load the stored budget for an agent, falling back to defaults
+pub fn load_budget_config(ai_state: &AIState, agent_id: &str) -> MemoryBudgetConfig {
+    match ai_state.storage.get_setting(&format!("{}{}", BUDGET_SETTING_PREFIX, agent_id)) {
+        Ok(Some(value)) => serde_json::from_value(value).unwrap_or_else(|e| {
+            warn!("Ignoring malformed memory budget for {}: {}", agent_id, e);
+            MemoryBudgetConfig::default()
+        }),
+        Ok(None) => MemoryBudgetConfig::default(),
+        Err(e) => {
+            warn!("Failed to load memory budget for {}: {}", agent_id, e);
+            MemoryBudgetConfig::default()
+        }
+    }
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn set_memory_injection_budget(
+    agent_id: String,
+    config: MemoryBudgetConfig,
+    ai_state: State<'_, AIState>,
+) -> Result<(), String> {
+    info!("Setting memory injection budget for agent: {}", agent_id);
+
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    if config.max_tokens == 0 || config.max_tokens > MAX_BUDGET_TOKENS {
+        return Err(format!("Budget must be between 1 and {} tokens", MAX_BUDGET_TOKENS));
+    }
+    if !(0.0..=1.0).contains(&config.diversity_weight) || !(0.0..=1.0).contains(&config.min_importance) {
+        return Err("Diversity weight and minimum importance must be between 0.0 and 1.0".to_string());
+    }
+
+    let value = serde_json::to_value(&config)
+        .map_err(|e| format!("Failed to serialize budget: {}", e))?;
+
+    ai_state.storage
+        .set_setting(&format!("{}{}", BUDGET_SETTING_PREFIX, agent_id), value)
+        .map_err(|e| format!("Failed to store memory budget: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_memory_injection_budget(
+    agent_id: String,
+    ai_state: State<'_, AIState>,
+) -> Result<MemoryBudgetConfig, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    Ok(load_budget_config(&ai_state, &agent_id))
+}
+
+/// Retrieve memories for a prompt turn, trimmed to the agent's injection budget
+#[tauri::command]
+pub async fn retrieve_memories_for_prompt(
+    agent_id: String,
+    query: Option<String>,
+    max_candidates: Option<usize>,
+    state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<MemoryInjectionReport, String> {
+    info!("Retrieving budgeted memories for agent: {}", agent_id);
+
+    // Phase 1: Input Validation
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    if let Some(ref q) = query {
+        MemoryValidator::validate_content(q)
+            .map_err(|e| e.to_string())?;
+    }
+    let candidate_limit = max_candidates.unwrap_or(200);
+    MemoryValidator::validate_limit(candidate_limit)
+        .map_err(|e| e.to_string())?;
+
+    // Phase 2: Security Middleware
+    let security_middleware = state.get_security_middleware();
+    let mut inputs = vec![agent_id.clone()];
+    if let Some(ref q) = query {
+        inputs.push(q.clone());
+    }
+    let validation_result = security_middleware.validate_request(
+        "memory_operations",
+        &inputs,
+        &[]
+    ).await?;
+
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+    let sanitized_query = validation_result.sanitized_inputs.get(1).cloned();
+
+    // Phase 3: Business Logic
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+
+    let metric = state.similarity_metric().await;
+    let query_embedding = match sanitized_query {
+        Some(ref q) => {
+            let service_lock = state.get_neural_embedding_service().await?;
+            let service = service_lock.lock().await;
+            match service.as_ref() {
+                Some(service) => service.embed_text(q, None).await.ok(),
+                None => None,
+            }
+        }
+        None => None,
+    };
+
+    let candidates = manager.search_memories(&MemoryQuery {
+        agent_id: Some(sanitized_agent_id.clone()),
+        memory_types: None,
+        content_search: None,
+        tags: None,
+        embedding: query_embedding,
+        similarity_threshold: None,
+        similarity_metric: metric,
+        limit: Some(candidate_limit),
+        offset: Some(0),
+        time_range: None,
+        tiers: None,
+        fusion: None,
+    }).map_err(|e| format!("Failed to retrieve memories: {}", e))?;
+
+    let config = load_budget_config(&ai_state, &sanitized_agent_id);
+    let report = select_within_budget(&sanitized_agent_id, &candidates, &config, metric);
+
+    info!(
+        "Injected {} of {} memories for {} ({} / {} tokens)",
+        report.included.len(), report.candidate_count, sanitized_agent_id,
+        report.used_tokens, report.budget_tokens
+    );
+
+    state.record_injection_report(report.clone());
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_last_memory_injection_report(
+    agent_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<Option<MemoryInjectionReport>, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    Ok(state.last_injection_report(&agent_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(content: &str, relevance: f32, similarity: Option<f32>) -> MemorySearchResult {
+        let mut memory = AgentMemory::new("agent_1".to_string(), MemoryType::Context, content.to_string());
+        memory.relevance_score = relevance;
+        MemorySearchResult {
+            memory,
+            similarity_score: similarity,
+            relevance_rank: 0,
+            fusion_scores: None,
+        }
+    }
+
+    #[test]
+    fn test_selection_respects_budget() {
+        let candidates = vec![
+            candidate(&"a".repeat(400), 1.0, Some(0.9)),
+            candidate(&"b".repeat(400), 0.9, Some(0.8)),
+            candidate(&"c".repeat(400), 0.8, Some(0.7)),
+        ];
+        let config = MemoryBudgetConfig { max_tokens: 220, ..Default::default() };
+
+        let report = select_within_budget("agent_1", &candidates, &config, SimilarityMetric::Cosine);
+        assert_eq!(report.included.len(), 2);
+        assert!(report.used_tokens <= 220);
+        assert_eq!(report.excluded_ids.len(), 1);
+    }
+
+    #[test]
+    fn test_selection_prefers_diverse_memories() {
+        let candidates = vec![
+            candidate("deploy the backend service with docker compose", 1.0, Some(0.9)),
+            candidate("deploy the backend service with docker compose today", 1.0, Some(0.88)),
+            candidate("user prefers concise answers in british english", 1.0, Some(0.85)),
+        ];
+        let config = MemoryBudgetConfig { max_tokens: 30, diversity_weight: 0.8, min_importance: 0.0 };
+
+        let report = select_within_budget("agent_1", &candidates, &config, SimilarityMetric::Cosine);
+        let ids: Vec<&str> = report.included.iter().map(|m| m.memory_id.as_str()).collect();
+        assert_eq!(ids[0], candidates[0].memory.id);
+        assert_eq!(ids[1], candidates[2].memory.id);
+    }
+
+    #[test]
+    fn test_pinned_memories_selected_first() {
+        let mut candidates = vec![
+            candidate("deploy the backend service with docker compose", 1.0, Some(0.9)),
+            candidate("deploy the backend service with docker compose today", 0.1, Some(0.1)),
+            candidate("user prefers concise answers in british english", 1.0, Some(0.85)),
+        ];
+        candidates[1].memory.pinned = true;
+        let config = MemoryBudgetConfig { max_tokens: 30, diversity_weight: 0.8, min_importance: 0.5 };
+
+        let report = select_within_budget("agent_1", &candidates, &config, SimilarityMetric::Cosine);
+        let ids: Vec<&str> = report.included.iter().map(|m| m.memory_id.as_str()).collect();
+        // Below the cutoff but pinned, and still counted against its near-duplicate
+        assert_eq!(ids, vec![candidates[1].memory.id.as_str(), candidates[2].memory.id.as_str()]);
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+}
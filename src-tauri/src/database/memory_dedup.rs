@@ -0,0 +1,301 @@
+//! Deduplication on write: a new memory repeating a recent one of the same agent and type,
+//! by normalized content or by embedding, is merged into it instead of saved as a new row.
+
+use super::embeddings::SimilarityMetric;
+use super::memory::AgentMemory;
+use super::simple_commands::MemoryState;
+use super::simple_memory::SimpleMemoryManager;
+use crate::ai::AIState;
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::State;
+use tracing::{info, warn};
+
+/// Settings key prefix for per-agent deduplication settings
+const DEDUP_SETTING_PREFIX: &str = "memory_dedup.";
+
+/// Most recent memories a new one may be compared against
+const MAX_DEDUP_WINDOW: usize = 5000;
+
+/// Access log context of merges found by content hash
+pub const HASH_MERGE_CONTEXT: &str = "Duplicate merged (content hash)";
+
+/// Access log context of merges found by embedding similarity
+pub const SIMILARITY_MERGE_CONTEXT: &str = "Duplicate merged (similarity)";
+
+/// Per-agent deduplication settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryDedupConfig {
+    pub enabled: bool,
+    /// Embeddings at least this similar are duplicates (0.0 - 1.0)
+    pub similarity_threshold: f32,
+    /// Recent memories of the same type compared against
+    pub window: usize,
+}
+
+impl Default for MemoryDedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            similarity_threshold: 0.95,
+            window: 200,
+        }
+    }
+}
+
+/// Writes and merges for one agent, counted from its access log, so deleted memories drop out
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupStats {
+    pub agent_id: String,
+    pub saved: usize,
+    pub merged_by_hash: usize,
+    pub merged_by_similarity: usize,
+    /// Share of writes that were merged instead of saved
+    pub duplicate_rate: f32,
+}
+
+/// SHA-256 of the content lowercased, with punctuation dropped and whitespace collapsed
+pub fn content_hash(content: &str) -> String {
+    let normalized: Vec<String> = content.split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>())
+        .filter(|word| !word.is_empty())
+        .collect();
+    hex::encode(Sha256::digest(normalized.join(" ").as_bytes()))
+}
+
+/// The recent memory `memory` duplicates, with the similarity when it matched by embedding
+/// rather than content. Content matches win, then the most similar embedding.
+pub fn find_duplicate(
+    memory: &AgentMemory,
+    recent: &[AgentMemory],
+    config: &MemoryDedupConfig,
+    metric: SimilarityMetric,
+) -> Option<(usize, Option<f32>)> {
+    let hash = content_hash(&memory.content);
+    if let Some(index) = recent.iter().position(|other| content_hash(&other.content) == hash) {
+        return Some((index, None));
+    }
+
+    let embedding = memory.embedding.as_ref()?;
+    recent.iter()
+        .enumerate()
+        .filter_map(|(index, other)| Some((index, metric.similarity(embedding, other.embedding.as_ref()?))))
+        .filter(|(_, similarity)| *similarity >= config.similarity_threshold)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, similarity)| (index, Some(similarity)))
+}
+
+/// Fold `duplicate` into `existing`: new tags and metadata keys are added, and the repeat
+/// counts as an access
+pub fn merge_duplicate(existing: &mut AgentMemory, duplicate: &AgentMemory) {
+    for tag in &duplicate.tags {
+        if !existing.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            existing.tags.push(tag.clone());
+        }
+    }
+    for (key, value) in &duplicate.metadata {
+        existing.metadata.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+    existing.access_count += 1;
+    existing.updated_at = chrono::Utc::now();
+}
+
+/// Merge `memory` into a recent duplicate if it has one, returning the updated record;
+/// `None` means it should be saved
+pub fn merge_into_recent(
+    manager: &SimpleMemoryManager,
+    memory: &AgentMemory,
+    config: &MemoryDedupConfig,
+    metric: SimilarityMetric,
+) -> Result<Option<AgentMemory>> {
+    let mut recent = manager.recent_memories(&memory.memory_type, config.window)?;
+    let Some((index, similarity)) = find_duplicate(memory, &recent, config, metric) else {
+        return Ok(None);
+    };
+
+    let mut existing = recent.swap_remove(index);
+    merge_duplicate(&mut existing, memory);
+    let context = if similarity.is_some() { SIMILARITY_MERGE_CONTEXT } else { HASH_MERGE_CONTEXT };
+    manager.save_merged_duplicate(&existing, context)?;
+    Ok(Some(existing))
+}
+
+pub fn dedup_stats(conn: &Connection, agent_id: &str) -> Result<DedupStats> {
+    let (saved, merged_by_hash, merged_by_similarity): (i64, i64, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(access_type = 'Write'), 0),
+                COALESCE(SUM(access_type = 'Update' AND context = ?2), 0),
+                COALESCE(SUM(access_type = 'Update' AND context = ?3), 0)
+         FROM memory_access_log WHERE agent_id = ?1",
+        params![agent_id, HASH_MERGE_CONTEXT, SIMILARITY_MERGE_CONTEXT],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    let merged = merged_by_hash + merged_by_similarity;
+    let writes = saved + merged;
+
+    Ok(DedupStats {
+        agent_id: agent_id.to_string(),
+        saved: saved as usize,
+        merged_by_hash: merged_by_hash as usize,
+        merged_by_similarity: merged_by_similarity as usize,
+        duplicate_rate: if writes > 0 { merged as f32 / writes as f32 } else { 0.0 },
+    })
+}
+
+/// Load the stored deduplication settings for an agent, falling back to defaults
+pub fn load_dedup_config(ai_state: &AIState, agent_id: &str) -> MemoryDedupConfig {
+    match ai_state.storage.get_setting(&format!("{}{}", DEDUP_SETTING_PREFIX, agent_id)) {
+        Ok(Some(value)) => serde_json::from_value(value).unwrap_or_else(|e| {
+            warn!("Ignoring malformed memory deduplication settings for {}: {}", agent_id, e);
+            MemoryDedupConfig::default()
+        }),
+        Ok(None) => MemoryDedupConfig::default(),
+        Err(e) => {
+            warn!("Failed to load memory deduplication settings for {}: {}", agent_id, e);
+            MemoryDedupConfig::default()
+        }
+    }
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn set_memory_dedup_config(
+    agent_id: String,
+    config: MemoryDedupConfig,
+    ai_state: State<'_, AIState>,
+) -> Result<(), String> {
+    info!("Setting memory deduplication for agent: {}", agent_id);
+
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    if !(0.0..=1.0).contains(&config.similarity_threshold) {
+        return Err("Similarity threshold must be between 0.0 and 1.0".to_string());
+    }
+    if config.window == 0 || config.window > MAX_DEDUP_WINDOW {
+        return Err(format!("Window must be between 1 and {} memories", MAX_DEDUP_WINDOW));
+    }
+
+    let value = serde_json::to_value(&config)
+        .map_err(|e| format!("Failed to serialize deduplication settings: {}", e))?;
+
+    ai_state.storage
+        .set_setting(&format!("{}{}", DEDUP_SETTING_PREFIX, agent_id), value)
+        .map_err(|e| format!("Failed to store deduplication settings: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_memory_dedup_config(
+    agent_id: String,
+    ai_state: State<'_, AIState>,
+) -> Result<MemoryDedupConfig, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    Ok(load_dedup_config(&ai_state, &agent_id))
+}
+
+/// How many of the agent's writes were saved and how many merged into duplicates
+#[tauri::command]
+pub async fn get_dedup_stats(
+    agent_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<DedupStats, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    tokio::task::spawn_blocking(move || {
+        let conn = manager.agent_connection()?;
+        dedup_stats(&conn, &sanitized_agent_id)
+    })
+    .await
+    .map_err(|e| format!("Deduplication stats task failed: {}", e))?
+    .map_err(|e| format!("Failed to read deduplication stats: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory::MemoryType;
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+    use std::collections::HashMap;
+
+    fn memory(content: &str, embedding: Vec<f32>) -> AgentMemory {
+        AgentMemory::new("agent".to_string(), MemoryType::Learning, content.to_string()).with_embedding(embedding)
+    }
+
+    #[test]
+    fn test_finds_duplicates_by_content_then_similarity() {
+        assert_eq!(content_hash("User prefers  TABS."), content_hash("user prefers tabs"));
+        assert_ne!(content_hash("user prefers tabs"), content_hash("user prefers spaces"));
+
+        let config = MemoryDedupConfig::default();
+        let recent = vec![
+            memory("deploys with docker", vec![1.0, 0.0]),
+            memory("User prefers tabs!", vec![0.0, 1.0]),
+        ];
+        let metric = SimilarityMetric::Cosine;
+        assert_eq!(find_duplicate(&memory("user prefers tabs", vec![1.0, 0.0]), &recent, &config, metric), Some((1, None)));
+
+        let (index, similarity) = find_duplicate(&memory("ships via docker", vec![0.99, 0.01]), &recent, &config, metric).unwrap();
+        assert_eq!(index, 0);
+        assert!(similarity.unwrap() >= config.similarity_threshold);
+        assert_eq!(find_duplicate(&memory("something new", vec![0.6, 0.8]), &recent, &config, metric), None);
+    }
+
+    #[test]
+    fn test_merge_adds_tags_and_metadata() {
+        let mut existing = memory("user prefers tabs", vec![1.0])
+            .with_tags(vec!["Style".to_string()])
+            .with_metadata(HashMap::from([("source".to_string(), "chat".to_string())]));
+        let duplicate = memory("User prefers tabs.", vec![1.0])
+            .with_tags(vec!["style".to_string(), "editor".to_string()])
+            .with_metadata(HashMap::from([
+                ("source".to_string(), "import".to_string()),
+                ("project".to_string(), "banshee".to_string()),
+            ]));
+
+        merge_duplicate(&mut existing, &duplicate);
+        assert_eq!(existing.tags, vec!["Style", "editor"]);
+        assert_eq!(existing.metadata["source"], "chat");
+        assert_eq!(existing.metadata["project"], "banshee");
+        assert_eq!(existing.access_count, 1);
+        assert_eq!(existing.content, "user prefers tabs");
+    }
+
+    #[test]
+    fn test_stats_count_writes_and_merges() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        conn.execute("INSERT INTO agent_memories (id, agent_id, memory_type, content) VALUES ('m1', 'agent', 'Task', 'memory')", []).unwrap();
+        for (access_type, context) in [
+            ("Write", "Memory saved"),
+            ("Write", "Memory saved"),
+            ("Write", "Memory saved"),
+            ("Update", HASH_MERGE_CONTEXT),
+            ("Update", "Memory updated"),
+        ] {
+            conn.execute(
+                "INSERT INTO memory_access_log (id, memory_id, agent_id, access_type, context) VALUES (?1, 'm1', 'agent', ?2, ?3)",
+                params![uuid::Uuid::new_v4().to_string(), access_type, context],
+            ).unwrap();
+        }
+
+        let stats = dedup_stats(&conn, "agent").unwrap();
+        assert_eq!((stats.saved, stats.merged_by_hash, stats.merged_by_similarity), (3, 1, 0));
+        assert!((stats.duplicate_rate - 0.25).abs() < 1e-6);
+        assert_eq!(dedup_stats(&conn, "other").unwrap().duplicate_rate, 0.0);
+    }
+}
@@ -0,0 +1,351 @@
+//! Encryption at rest for agent memory databases. Built with the `sqlcipher` feature, SQLite is
+//! SQLCipher: an encrypted database gets a random 256-bit key, sealed under the
+//! `memory_databases` data key from `ai::encryption` and kept beside it as `<agent>.db.key`.
+//! Pooled connections apply the key as they open, so `SimpleMemoryManager` reads and writes an
+//! encrypted database exactly as a plain one.
+
+use super::pool::block_pool;
+use super::simple_commands::MemoryState;
+use super::simple_memory::{AgentDatabase, SimpleMemoryManager};
+use crate::ai::encryption::{EnvelopeEncryption, KeyPurpose};
+use crate::validation::MemoryValidator;
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::State;
+use tracing::{error, info, warn};
+
+/// How long encryption waits for the agent's checked-out connections to be returned
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Decrypted database keys, so a new pooled connection doesn't unwrap its key again
+static DATABASE_KEYS: Lazy<Mutex<HashMap<PathBuf, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionMigrationReport {
+    pub encrypted: Vec<String>,
+    pub already_encrypted: Vec<String>,
+    /// Agent id to the reason its database was left as it was
+    pub failed: HashMap<String, String>,
+}
+
+fn path_with_suffix(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Sealed key file of the database at `db_path`
+pub fn key_path(db_path: &Path) -> PathBuf {
+    path_with_suffix(db_path, ".key")
+}
+
+pub fn is_encrypted(db_path: &Path) -> bool {
+    key_path(db_path).exists()
+}
+
+fn write_key_file(envelope: &EnvelopeEncryption, path: &Path, key: &str) -> Result<()> {
+    let sealed = envelope.encrypt(KeyPurpose::MemoryDatabases, key)?;
+    std::fs::write(path, sealed).context("Failed to write database key")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .context("Failed to set database key permissions")?;
+    }
+    Ok(())
+}
+
+fn read_key_file(envelope: &EnvelopeEncryption, path: &Path) -> Result<String> {
+    let sealed = std::fs::read_to_string(path).context("Failed to read database key")?;
+    envelope.decrypt(KeyPurpose::MemoryDatabases, sealed.trim())
+}
+
+/// Finish or roll back an `encrypt_database` that was interrupted. While the encrypted copy
+/// is still beside the database the swap never happened, so the copy and its pending key are
+/// discarded; once the copy has replaced the database, the pending key is the only one that
+/// opens it and is promoted. Returns true when a pending key was promoted.
+pub fn recover_interrupted_encryption(db_path: &Path) -> Result<bool> {
+    let encrypted_path = path_with_suffix(db_path, ".encrypting");
+    let pending_key = path_with_suffix(db_path, ".key.pending");
+
+    if encrypted_path.exists() {
+        warn!("Discarding unfinished encryption of {}", db_path.display());
+        std::fs::remove_file(&encrypted_path)?;
+        if pending_key.exists() {
+            std::fs::remove_file(&pending_key)?;
+        }
+        return Ok(false);
+    }
+    if !pending_key.exists() || key_path(db_path).exists() {
+        return Ok(false);
+    }
+
+    warn!("Completing interrupted encryption of {}", db_path.display());
+    remove_sidecars(db_path)?;
+    std::fs::rename(&pending_key, key_path(db_path))?;
+    Ok(true)
+}
+
+/// The WAL and shared-memory files of the plain database don't belong to its encrypted copy
+fn remove_sidecars(db_path: &Path) -> Result<()> {
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = path_with_suffix(db_path, suffix);
+        if sidecar.exists() {
+            std::fs::remove_file(sidecar)?;
+        }
+    }
+    Ok(())
+}
+
+/// Hex key of the database at `db_path`, or `None` when it isn't encrypted. An interrupted
+/// encryption is recovered first, so the key is found before anything opens the file.
+pub fn database_key(db_path: &Path) -> Result<Option<String>> {
+    let mut keys = DATABASE_KEYS.lock().map_err(|_| anyhow::anyhow!("Database key cache poisoned"))?;
+    if let Some(key) = keys.get(db_path) {
+        return Ok(Some(key.clone()));
+    }
+    recover_interrupted_encryption(db_path)?;
+    let path = key_path(db_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let key = read_key_file(&EnvelopeEncryption::open_default()?, &path)?;
+    keys.insert(db_path.to_path_buf(), key.clone());
+    Ok(Some(key))
+}
+
+/// Key a fresh connection; must run before anything else touches the database
+pub fn apply_key(conn: &Connection, key: &str) -> rusqlite::Result<()> {
+    if !key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(rusqlite::Error::InvalidParameterName("database key must be hex".to_string()));
+    }
+    conn.execute_batch(&format!("PRAGMA key = \"x'{}'\";", key))
+}
+
+/// Rewrite the plain database at `db_path` encrypted under a new key; false when it already
+/// was. New checkouts are refused and connections already out are waited for, so no write
+/// goes to the replaced file. A crash part way is recovered by `recover_interrupted_encryption`.
+pub fn encrypt_database(db_path: &Path, envelope: &EnvelopeEncryption) -> Result<bool> {
+    if !cfg!(feature = "sqlcipher") {
+        bail!("Memory encryption needs a build with the `sqlcipher` feature");
+    }
+    if is_encrypted(db_path) {
+        return Ok(false);
+    }
+
+    let _block = block_pool(db_path, DRAIN_TIMEOUT)?;
+    let key = hex::encode(rand::random::<[u8; 32]>());
+    let encrypted_path = path_with_suffix(db_path, ".encrypting");
+    if encrypted_path.exists() {
+        std::fs::remove_file(&encrypted_path)?;
+    }
+
+    {
+        let conn = Connection::open(db_path)?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        let user_version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        conn.execute_batch(&format!(
+            "ATTACH DATABASE '{}' AS encrypted KEY \"x'{}'\";",
+            encrypted_path.to_string_lossy().replace('\'', "''"),
+            key
+        ))?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        conn.execute_batch(&format!("PRAGMA encrypted.user_version = {}; DETACH DATABASE encrypted;", user_version))?;
+    }
+
+    // The key is sealed before the swap, so an interrupted migration leaves it recoverable
+    let pending_key = path_with_suffix(db_path, ".key.pending");
+    write_key_file(envelope, &pending_key, &key)?;
+    std::fs::rename(&encrypted_path, db_path).context("Failed to replace database with its encrypted copy")?;
+    remove_sidecars(db_path)?;
+    std::fs::rename(&pending_key, key_path(db_path))?;
+
+    // Cached before the block lifts, so the next pool opens the database with its key
+    if let Ok(mut keys) = DATABASE_KEYS.lock() {
+        keys.insert(db_path.to_path_buf(), key);
+    }
+    Ok(true)
+}
+
+/// Seal every agent's database key, in every workspace, under the active `memory_databases`
+/// data key, as part of a key rotation; returns (resealed, failed)
+pub fn reseal_database_keys(envelope: &EnvelopeEncryption) -> Result<(usize, usize)> {
+    Ok(reseal_key_files(envelope, &SimpleMemoryManager::list_agent_databases()?))
+}
+
+fn reseal_key_files(envelope: &EnvelopeEncryption, databases: &[AgentDatabase]) -> (usize, usize) {
+    let (mut resealed, mut failed) = (0, 0);
+    for database in databases {
+        let path = key_path(&database.path);
+        if !path.exists() {
+            continue;
+        }
+        match read_key_file(envelope, &path).and_then(|key| write_key_file(envelope, &path, &key)) {
+            Ok(()) => resealed += 1,
+            Err(e) => {
+                error!("Failed to reseal memory database key for {}: {}", database.label(), e);
+                failed += 1;
+            }
+        }
+    }
+    (resealed, failed)
+}
+
+// Tauri Commands
+
+/// Encrypt the agent's memory database, or every agent's when no id is given
+#[tauri::command]
+pub async fn migrate_to_encrypted_storage(
+    agent_id: Option<String>,
+    state: State<'_, MemoryState>,
+) -> Result<EncryptionMigrationReport, String> {
+    info!("Migrating memory databases to encrypted storage");
+
+    // Opening each manager first brings its schema up to date
+    let mut databases = Vec::new();
+    match agent_id {
+        Some(agent_id) => {
+            MemoryValidator::validate_agent_id(&agent_id)
+                .map_err(|e| e.to_string())?;
+            let validation_result = state.get_security_middleware().validate_request(
+                "memory_operations",
+                &[agent_id],
+                &[]
+            ).await?;
+            let agent_id = validation_result.sanitized_inputs[0].clone();
+            let manager = state.get_or_create_manager(agent_id.clone())?;
+            databases.push((agent_id, manager.get_agent_db_path().clone()));
+        }
+        None => {
+            let agent_databases = SimpleMemoryManager::list_agent_databases()
+                .map_err(|e| format!("Failed to list agents: {}", e))?;
+            for database in agent_databases {
+                let manager = SimpleMemoryManager::for_workspace(database.agent_id.clone(), &database.workspace)
+                    .and_then(|manager| manager.initialize().map(|_| manager))
+                    .map_err(|e| format!("Failed to open memory database for {}: {}", database.label(), e))?;
+                databases.push((database.label(), manager.get_agent_db_path().clone()));
+            }
+        }
+    }
+
+    tokio::task::spawn_blocking(move || -> Result<EncryptionMigrationReport> {
+        let envelope = EnvelopeEncryption::open_default()?;
+        let mut report = EncryptionMigrationReport::default();
+        for (agent_id, db_path) in databases {
+            match encrypt_database(&db_path, &envelope) {
+                Ok(true) => report.encrypted.push(agent_id),
+                Ok(false) => report.already_encrypted.push(agent_id),
+                Err(e) => {
+                    error!("Failed to encrypt memory database for {}: {}", agent_id, e);
+                    report.failed.insert(agent_id, e.to_string());
+                }
+            }
+        }
+        info!(
+            "Encrypted {} memory databases ({} already encrypted, {} failed)",
+            report.encrypted.len(), report.already_encrypted.len(), report.failed.len()
+        );
+        Ok(report)
+    })
+    .await
+    .map_err(|e| format!("Encryption task failed: {}", e))?
+    .map_err(|e| format!("Failed to encrypt memory databases: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::encryption::MasterKeyStore;
+
+    #[test]
+    fn test_key_file_round_trip_and_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let envelope = EnvelopeEncryption::new(dir.path().to_path_buf(), MasterKeyStore::File(dir.path().to_path_buf()));
+        let db_path = dir.path().join("agent_1.db");
+        assert_eq!(key_path(&db_path), dir.path().join("agent_1.db.key"));
+        assert!(!is_encrypted(&db_path));
+
+        let key = hex::encode([7u8; 32]);
+        write_key_file(&envelope, &key_path(&db_path), &key).unwrap();
+        assert!(is_encrypted(&db_path));
+        let sealed = std::fs::read_to_string(key_path(&db_path)).unwrap();
+        assert!(!sealed.contains(&key));
+        assert_eq!(read_key_file(&envelope, &key_path(&db_path)).unwrap(), key);
+    }
+
+    #[test]
+    fn test_rotation_reseals_keys_in_every_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let envelope = EnvelopeEncryption::new(dir.path().to_path_buf(), MasterKeyStore::File(dir.path().to_path_buf()));
+        let memory_dir = dir.path().join("memory");
+        let default_db = memory_dir.join("agents").join("agent_1.db");
+        let workspace_db = memory_dir.join("workspaces").join("project").join("agents").join("agent_2.db");
+        let mut keys = Vec::new();
+        for (db_path, byte) in [(&default_db, 1u8), (&workspace_db, 2u8)] {
+            std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+            std::fs::write(db_path, b"").unwrap();
+            let key = hex::encode([byte; 32]);
+            write_key_file(&envelope, &key_path(db_path), &key).unwrap();
+            keys.push(key);
+        }
+
+        let databases = crate::database::simple_memory::agent_databases_in(&memory_dir).unwrap();
+        assert_eq!(databases.iter().map(AgentDatabase::label).collect::<Vec<_>>(), vec!["agent_1", "project/agent_2"]);
+
+        envelope.rotate_data_keys().unwrap();
+        assert_eq!(reseal_key_files(&envelope, &databases), (2, 0));
+        envelope.retire_inactive_data_keys().unwrap();
+
+        // Only keys sealed under the new data key survive retiring the old one
+        assert_eq!(read_key_file(&envelope, &key_path(&default_db)).unwrap(), keys[0]);
+        assert_eq!(read_key_file(&envelope, &key_path(&workspace_db)).unwrap(), keys[1]);
+    }
+
+    #[test]
+    fn test_interrupted_encryption_is_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("agent_1.db");
+        let copy = path_with_suffix(&db_path, ".encrypting");
+        let pending = path_with_suffix(&db_path, ".key.pending");
+        std::fs::write(&db_path, b"plain").unwrap();
+
+        // Interrupted before the swap: the plain database stays and the copy is dropped
+        std::fs::write(&copy, b"encrypted").unwrap();
+        std::fs::write(&pending, b"sealed").unwrap();
+        assert!(!recover_interrupted_encryption(&db_path).unwrap());
+        assert!(!copy.exists() && !pending.exists() && !is_encrypted(&db_path));
+
+        // Interrupted after the swap: the pending key is the database's key
+        std::fs::write(&pending, b"sealed").unwrap();
+        std::fs::write(path_with_suffix(&db_path, "-wal"), b"").unwrap();
+        assert!(recover_interrupted_encryption(&db_path).unwrap());
+        assert!(is_encrypted(&db_path) && !pending.exists());
+        assert!(!path_with_suffix(&db_path, "-wal").exists());
+        assert!(!recover_interrupted_encryption(&db_path).unwrap());
+    }
+
+    #[test]
+    fn test_apply_key_rejects_non_hex() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(apply_key(&conn, "abc'; DROP TABLE x; --").is_err());
+        assert!(apply_key(&conn, &hex::encode([1u8; 32])).is_ok());
+    }
+
+    #[cfg(not(feature = "sqlcipher"))]
+    #[test]
+    fn test_encryption_needs_sqlcipher() {
+        let dir = tempfile::tempdir().unwrap();
+        let envelope = EnvelopeEncryption::new(dir.path().to_path_buf(), MasterKeyStore::File(dir.path().to_path_buf()));
+        let db_path = dir.path().join("agent_1.db");
+        Connection::open(&db_path).unwrap().execute_batch("CREATE TABLE t (x INTEGER);").unwrap();
+        assert!(encrypt_database(&db_path, &envelope).is_err());
+        assert!(!is_encrypted(&db_path));
+    }
+}
@@ -476,7 +476,8 @@ mod memory_flow_integration_tests {
 
     // Helper function to create test memory state
     async fn create_test_memory_state() -> Arc<MemoryState> {
-        Arc::new(MemoryState::new())
+        let security_manager = Arc::new(AsyncMutex::new(crate::ai::SecurityManager::new()));
+        Arc::new(MemoryState::new(Arc::new(crate::ai::SecurityMiddleware::new(security_manager))))
     }
 
     // Helper function for cosine similarity calculation
@@ -0,0 +1,267 @@
+//! Temporal pattern reports for an agent's memories: the sequence models' analysis plus
+//! rule-based labels a person can read, such as error→success loops or task bursts.
+
+use super::embeddings::SimilarityMetric;
+use super::memory::{AgentMemory, MemoryQuery, MemoryType};
+use super::memory_sequence_models::MemoryPatternAnalysis;
+use super::simple_commands::MemoryState;
+use crate::validation::MemoryValidator;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tracing::info;
+
+/// Most memories analyzed per report, newest first
+const MAX_PATTERN_MEMORIES: usize = 2000;
+
+/// Tasks within this many minutes of the first count towards one burst
+const TASK_BURST_WINDOW_MINUTES: i64 = 60;
+
+/// Tasks a window needs to count as a burst
+const TASK_BURST_SIZE: usize = 5;
+
+/// Consecutive errors that count as a run of repeated errors
+const REPEATED_ERROR_RUN: usize = 3;
+
+/// Silence between memories longer than this is reported as a gap
+const LONG_GAP_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatternTimeRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternKind {
+    ErrorSuccessLoop,
+    RepeatedErrors,
+    LearningAfterError,
+    TaskBurst,
+    LongGap,
+}
+
+impl PatternKind {
+    fn label(&self) -> &'static str {
+        match self {
+            PatternKind::ErrorSuccessLoop => "error→success loops",
+            PatternKind::RepeatedErrors => "repeated errors",
+            PatternKind::LearningAfterError => "learning after errors",
+            PatternKind::TaskBurst => "task bursts",
+            PatternKind::LongGap => "long gaps",
+        }
+    }
+
+    /// Occurrences needed before the pattern is worth reporting
+    fn min_occurrences(&self) -> usize {
+        match self {
+            PatternKind::ErrorSuccessLoop | PatternKind::LearningAfterError => 2,
+            PatternKind::RepeatedErrors | PatternKind::TaskBurst | PatternKind::LongGap => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatternLabel {
+    pub kind: PatternKind,
+    pub label: String,
+    pub occurrences: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryPatternReport {
+    pub agent_id: String,
+    pub analysis: MemoryPatternAnalysis,
+    /// Most frequent first
+    pub labels: Vec<PatternLabel>,
+    pub memory_count: usize,
+    pub time_range: Option<PatternTimeRange>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Label the recognizable patterns in `memories`, which must be oldest first
+pub fn label_patterns(memories: &[AgentMemory]) -> Vec<PatternLabel> {
+    let followed_by = |first: MemoryType, second: MemoryType| {
+        memories.windows(2)
+            .filter(|pair| pair[0].memory_type == first && pair[1].memory_type == second)
+            .count()
+    };
+
+    let mut error_runs = 0;
+    let mut run = 0;
+    for memory in memories {
+        run = if memory.memory_type == MemoryType::Error { run + 1 } else { 0 };
+        if run == REPEATED_ERROR_RUN {
+            error_runs += 1;
+        }
+    }
+
+    // Non-overlapping windows holding enough tasks
+    let tasks: Vec<DateTime<Utc>> = memories.iter()
+        .filter(|memory| memory.memory_type == MemoryType::Task)
+        .map(|memory| memory.created_at)
+        .collect();
+    let mut task_bursts = 0;
+    let mut start = 0;
+    while start + TASK_BURST_SIZE <= tasks.len() {
+        if tasks[start + TASK_BURST_SIZE - 1] - tasks[start] <= Duration::minutes(TASK_BURST_WINDOW_MINUTES) {
+            task_bursts += 1;
+            start += TASK_BURST_SIZE;
+        } else {
+            start += 1;
+        }
+    }
+
+    let long_gaps = memories.windows(2)
+        .filter(|pair| pair[1].created_at - pair[0].created_at > Duration::hours(LONG_GAP_HOURS))
+        .count();
+
+    let mut labels: Vec<PatternLabel> = [
+        (PatternKind::ErrorSuccessLoop, followed_by(MemoryType::Error, MemoryType::Success)),
+        (PatternKind::RepeatedErrors, error_runs),
+        (PatternKind::LearningAfterError, followed_by(MemoryType::Error, MemoryType::Learning)),
+        (PatternKind::TaskBurst, task_bursts),
+        (PatternKind::LongGap, long_gaps),
+    ]
+    .into_iter()
+    .filter(|(kind, occurrences)| *occurrences >= kind.min_occurrences())
+    .map(|(kind, occurrences)| PatternLabel { kind, label: kind.label().to_string(), occurrences })
+    .collect();
+    labels.sort_by_key(|label| std::cmp::Reverse(label.occurrences));
+    labels
+}
+
+/// Analyze an agent's memories, optionally only those created within `time_range`, and
+/// keep the report for `get_memory_pattern_report`
+#[tauri::command]
+pub async fn analyze_memory_patterns(
+    agent_id: String,
+    time_range: Option<PatternTimeRange>,
+    state: State<'_, MemoryState>,
+) -> Result<MemoryPatternReport, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    if let Some(ref range) = time_range {
+        if range.start > range.end {
+            return Err("Time range start must not be after its end".to_string());
+        }
+    }
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let mut memories: Vec<AgentMemory> = manager.search_memories(&MemoryQuery {
+        agent_id: Some(sanitized_agent_id.clone()),
+        memory_types: None,
+        content_search: None,
+        tags: None,
+        embedding: None,
+        similarity_threshold: None,
+        similarity_metric: SimilarityMetric::default(),
+        limit: Some(MAX_PATTERN_MEMORIES),
+        offset: None,
+        time_range: time_range.as_ref().map(|range| (range.start, range.end)),
+        tiers: None,
+        fusion: None,
+    })
+    .map_err(|e| format!("Failed to retrieve memories: {}", e))?
+    .into_iter()
+    .map(|result| result.memory)
+    .collect();
+    memories.sort_by_key(|memory| memory.created_at);
+
+    let analysis = {
+        let graph_lock = state.get_neural_graph().await?;
+        let graph = graph_lock.lock().await;
+        let graph = graph.as_ref().ok_or("Neural knowledge graph not initialized")?;
+        graph.detect_memory_patterns(&memories)
+            .map_err(|e| format!("Failed to analyze memory patterns: {}", e))?
+    };
+
+    let report = MemoryPatternReport {
+        agent_id: sanitized_agent_id,
+        analysis,
+        labels: label_patterns(&memories),
+        memory_count: memories.len(),
+        time_range,
+        generated_at: Utc::now(),
+    };
+    info!(
+        "Analyzed {} memories for {}: {} labeled patterns",
+        report.memory_count, report.agent_id, report.labels.len()
+    );
+
+    state.record_pattern_report(report.clone());
+    Ok(report)
+}
+
+/// The last report `analyze_memory_patterns` produced for the agent this session
+#[tauri::command]
+pub async fn get_memory_pattern_report(
+    agent_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<Option<MemoryPatternReport>, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    Ok(state.last_pattern_report(&agent_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(steps: &[(MemoryType, i64)]) -> Vec<AgentMemory> {
+        let start = Utc::now();
+        steps.iter()
+            .map(|(memory_type, minutes)| {
+                let mut memory = AgentMemory::new("agent_1".to_string(), memory_type.clone(), "step".to_string());
+                memory.created_at = start + Duration::minutes(*minutes);
+                memory
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_labels_error_loops_and_task_bursts() {
+        let mut steps = vec![
+            (MemoryType::Error, 0),
+            (MemoryType::Success, 5),
+            (MemoryType::Error, 10),
+            (MemoryType::Success, 15),
+        ];
+        steps.extend((0..5).map(|i| (MemoryType::Task, 20 + i * 10)));
+        let labels = label_patterns(&sequence(&steps));
+
+        let kinds: Vec<_> = labels.iter().map(|label| (label.kind, label.occurrences)).collect();
+        assert_eq!(kinds, vec![(PatternKind::ErrorSuccessLoop, 2), (PatternKind::TaskBurst, 1)]);
+        assert_eq!(labels[0].label, "error→success loops");
+    }
+
+    #[test]
+    fn test_labels_error_runs_and_gaps() {
+        let labels = label_patterns(&sequence(&[
+            (MemoryType::Error, 0),
+            (MemoryType::Error, 1),
+            (MemoryType::Error, 2),
+            (MemoryType::Error, 3),
+            (MemoryType::Learning, 60 * 30),
+        ]));
+
+        let kinds: Vec<_> = labels.iter().map(|label| label.kind).collect();
+        assert!(kinds.contains(&PatternKind::RepeatedErrors));
+        assert!(kinds.contains(&PatternKind::LongGap));
+        // A single error→learning transition is below the reporting threshold
+        assert!(!kinds.contains(&PatternKind::LearningAfterError));
+        assert!(label_patterns(&[]).is_empty());
+    }
+}
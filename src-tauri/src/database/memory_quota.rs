@@ -0,0 +1,327 @@
+//! Per-agent memory quotas. Limits live in the agent's own database so `save_memory` can
+//! enforce them; when a save goes over, unpinned memories are evicted by the agent's strategy.
+
+use super::memory::AgentMemory;
+use super::simple_commands::MemoryState;
+use super::simple_memory::row_to_memory;
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tracing::info;
+
+/// Smallest database size limit accepted
+const MIN_DB_BYTES: u64 = 1024 * 1024;
+
+/// Share of the agent's memories evicted per round while over the size limit
+const SIZE_EVICTION_FRACTION: usize = 20;
+
+/// Eviction rounds before giving up on reaching the quota
+pub const MAX_EVICTION_ROUNDS: usize = 20;
+
+/// Which unpinned memories go first when an agent is over quota
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionStrategy {
+    /// Least recently read, falling back to creation time for memories never read
+    LeastRecentlyUsed,
+    LowestRelevance,
+    #[default]
+    OldestUnpinned,
+}
+
+impl EvictionStrategy {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "LeastRecentlyUsed" => EvictionStrategy::LeastRecentlyUsed,
+            "LowestRelevance" => EvictionStrategy::LowestRelevance,
+            _ => EvictionStrategy::OldestUnpinned,
+        }
+    }
+
+    /// Eviction order over `agent_memories am`, first evicted first
+    fn order_by(&self) -> &'static str {
+        match self {
+            EvictionStrategy::LeastRecentlyUsed => {
+                "julianday(COALESCE((SELECT MAX(timestamp) FROM memory_access_log l
+                                     WHERE l.memory_id = am.id AND l.access_type = 'Read'), am.created_at)),
+                 julianday(am.created_at)"
+            }
+            EvictionStrategy::LowestRelevance => "am.relevance_score, julianday(am.created_at)",
+            EvictionStrategy::OldestUnpinned => "julianday(am.created_at)",
+        }
+    }
+}
+
+/// Per-agent limits; `None` leaves that dimension unlimited
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MemoryQuota {
+    pub max_memories: Option<usize>,
+    pub max_db_bytes: Option<u64>,
+    #[serde(default)]
+    pub eviction: EvictionStrategy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryQuotaStatus {
+    pub agent_id: String,
+    pub quota: Option<MemoryQuota>,
+    pub memory_count: usize,
+    pub pinned_count: usize,
+    /// Bytes in use by the agent database, excluding free pages
+    pub db_bytes: u64,
+    /// Share of `max_memories` in use
+    pub memory_usage: Option<f32>,
+    /// Share of `max_db_bytes` in use
+    pub size_usage: Option<f32>,
+}
+
+pub fn load_quota(conn: &Connection, agent_id: &str) -> Result<Option<MemoryQuota>> {
+    Ok(conn.prepare_cached("SELECT max_memories, max_db_bytes, eviction FROM memory_quota WHERE agent_id = ?1")?
+        .query_row(params![agent_id], |row| {
+            Ok(MemoryQuota {
+                max_memories: row.get::<_, Option<i64>>(0)?.map(|n| n as usize),
+                max_db_bytes: row.get::<_, Option<i64>>(1)?.map(|n| n as u64),
+                eviction: EvictionStrategy::from_name(&row.get::<_, String>(2)?),
+            })
+        })
+        .optional()?)
+}
+
+/// Store the agent's quota, or remove it with `None`
+pub fn store_quota(conn: &Connection, agent_id: &str, quota: Option<&MemoryQuota>) -> Result<()> {
+    match quota {
+        Some(quota) => conn.execute(
+            "INSERT INTO memory_quota (agent_id, max_memories, max_db_bytes, eviction, updated_at)
+             VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)
+             ON CONFLICT(agent_id) DO UPDATE SET
+                max_memories = excluded.max_memories, max_db_bytes = excluded.max_db_bytes,
+                eviction = excluded.eviction, updated_at = excluded.updated_at",
+            params![
+                agent_id,
+                quota.max_memories.map(|n| n as i64),
+                quota.max_db_bytes.map(|n| n as i64),
+                format!("{:?}", quota.eviction)
+            ],
+        )?,
+        None => conn.execute("DELETE FROM memory_quota WHERE agent_id = ?1", params![agent_id])?,
+    };
+    Ok(())
+}
+
+/// Bytes in use by the database, excluding free pages
+pub fn used_db_bytes(conn: &Connection) -> Result<u64> {
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let freelist_count: i64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+    Ok(((page_count - freelist_count).max(0) * page_size) as u64)
+}
+
+/// The agent's memory and pinned memory counts
+pub fn memory_counts(conn: &Connection, agent_id: &str) -> Result<(usize, usize)> {
+    let (total, pinned): (i64, i64) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(pinned), 0) FROM agent_memories WHERE agent_id = ?1",
+        params![agent_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok((total as usize, pinned as usize))
+}
+
+/// Up to `limit` unpinned memories in the order `strategy` evicts them, never `keep_id`
+pub fn eviction_candidates(
+    conn: &Connection,
+    agent_id: &str,
+    strategy: EvictionStrategy,
+    keep_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<AgentMemory>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT am.id, am.agent_id, am.memory_type, am.content, am.metadata, am.embedding,
+                am.relevance_score, am.created_at, am.updated_at, am.access_count, am.tags, am.pinned, am.tier
+         FROM agent_memories am
+         WHERE am.agent_id = ?1 AND am.pinned = 0 AND (?2 IS NULL OR am.id != ?2)
+         ORDER BY {} LIMIT ?3",
+        strategy.order_by()
+    ))?;
+    let rows = stmt.query_map(params![agent_id, keep_id, limit as i64], row_to_memory)?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// How many memories to evict next to get back under `quota`, or 0 when within it
+pub fn excess_memories(conn: &Connection, agent_id: &str, quota: &MemoryQuota) -> Result<usize> {
+    let (count, _) = memory_counts(conn, agent_id)?;
+    if let Some(max_memories) = quota.max_memories {
+        if count > max_memories {
+            return Ok(count - max_memories);
+        }
+    }
+    if let Some(max_db_bytes) = quota.max_db_bytes {
+        if used_db_bytes(conn)? > max_db_bytes {
+            return Ok((count / SIZE_EVICTION_FRACTION).max(1));
+        }
+    }
+    Ok(0)
+}
+
+pub fn quota_status(conn: &Connection, agent_id: &str) -> Result<MemoryQuotaStatus> {
+    let quota = load_quota(conn, agent_id)?;
+    let (memory_count, pinned_count) = memory_counts(conn, agent_id)?;
+    let db_bytes = used_db_bytes(conn)?;
+    Ok(MemoryQuotaStatus {
+        agent_id: agent_id.to_string(),
+        memory_usage: quota.as_ref()
+            .and_then(|q| q.max_memories)
+            .map(|max| if max > 0 { memory_count as f32 / max as f32 } else { 1.0 }),
+        size_usage: quota.as_ref()
+            .and_then(|q| q.max_db_bytes)
+            .map(|max| db_bytes as f32 / max as f32),
+        quota,
+        memory_count,
+        pinned_count,
+        db_bytes,
+    })
+}
+
+/// Drop memories evicted by a quota from the embedding cache and neural graph
+pub async fn forget_evicted(state: &MemoryState, agent_id: &str, evicted: &[AgentMemory]) {
+    if evicted.is_empty() {
+        return;
+    }
+    if let Some(ref service) = *state.neural_embedding_handle().lock().await {
+        service.evict_memories(evicted).await;
+    }
+    if let Some(ref mut graph) = *state.neural_graph_handle().lock().await {
+        let ids = evicted.iter().map(|memory| memory.id.clone()).collect();
+        graph.remove_agent_nodes(agent_id, Some(&ids)).await;
+    }
+}
+
+// Tauri Commands
+
+/// Set or clear (`None`) the agent's memory quota. It is enforced right away; returns how
+/// many memories were evicted.
+#[tauri::command]
+pub async fn set_memory_quota(
+    agent_id: String,
+    quota: Option<MemoryQuota>,
+    state: State<'_, MemoryState>,
+) -> Result<usize, String> {
+    info!("Setting memory quota for agent: {}", agent_id);
+
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    if let Some(ref quota) = quota {
+        if quota.max_memories == Some(0) {
+            return Err("Memory limit must be at least 1".to_string());
+        }
+        if quota.max_db_bytes.is_some_and(|bytes| bytes < MIN_DB_BYTES) {
+            return Err(format!("Database size limit must be at least {} bytes", MIN_DB_BYTES));
+        }
+    }
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let evicted = tokio::task::spawn_blocking(move || -> Result<Vec<AgentMemory>> {
+        store_quota(&*manager.agent_connection()?, &manager.agent_id, quota.as_ref())?;
+        manager.enforce_quota(None)
+    })
+    .await
+    .map_err(|e| format!("Memory quota task failed: {}", e))?
+    .map_err(|e| format!("Failed to set memory quota: {}", e))?;
+
+    forget_evicted(&state, &sanitized_agent_id, &evicted).await;
+    Ok(evicted.len())
+}
+
+/// The agent's quota with its current memory count and database size
+#[tauri::command]
+pub async fn get_memory_quota_status(
+    agent_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<MemoryQuotaStatus, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    tokio::task::spawn_blocking(move || {
+        let conn = manager.agent_connection()?;
+        quota_status(&conn, &sanitized_agent_id)
+    })
+    .await
+    .map_err(|e| format!("Memory quota task failed: {}", e))?
+    .map_err(|e| format!("Failed to read memory quota: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+
+    fn insert(conn: &Connection, id: &str, relevance: f32, created_at: &str, pinned: bool) {
+        conn.execute(
+            "INSERT INTO agent_memories (id, agent_id, memory_type, content, relevance_score, created_at, updated_at, pinned)
+             VALUES (?1, 'agent', 'Task', ?1, ?2, ?3, ?3, ?4)",
+            params![id, relevance, created_at, pinned],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_eviction_order_per_strategy_skips_pinned() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        insert(&conn, "old", 0.9, "2026-01-01T00:00:00+00:00", false);
+        insert(&conn, "pinned", 0.0, "2025-06-01T00:00:00+00:00", true);
+        insert(&conn, "weak", 0.1, "2026-03-01T00:00:00+00:00", false);
+        insert(&conn, "new", 0.5, "2026-05-01T00:00:00+00:00", false);
+        conn.execute(
+            "INSERT INTO memory_access_log (id, memory_id, agent_id, access_type, timestamp)
+             VALUES ('l1', 'old', 'agent', 'Read', '2026-06-01 12:00:00')",
+            [],
+        ).unwrap();
+
+        let ids = |strategy, keep_id| -> Vec<String> {
+            eviction_candidates(&conn, "agent", strategy, keep_id, 10).unwrap()
+                .into_iter().map(|memory| memory.id).collect()
+        };
+        assert_eq!(ids(EvictionStrategy::OldestUnpinned, None), vec!["old", "weak", "new"]);
+        assert_eq!(ids(EvictionStrategy::LowestRelevance, None), vec!["weak", "new", "old"]);
+        assert_eq!(ids(EvictionStrategy::LeastRecentlyUsed, Some("new")), vec!["weak", "old"]);
+    }
+
+    #[test]
+    fn test_quota_round_trip_and_excess() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        for (i, created_at) in ["2026-01-01T00:00:00+00:00", "2026-01-02T00:00:00+00:00", "2026-01-03T00:00:00+00:00"].iter().enumerate() {
+            insert(&conn, &format!("m{}", i), 0.5, created_at, i == 0);
+        }
+        assert!(load_quota(&conn, "agent").unwrap().is_none());
+
+        let quota = MemoryQuota { max_memories: Some(2), max_db_bytes: None, eviction: EvictionStrategy::LowestRelevance };
+        store_quota(&conn, "agent", Some(&quota)).unwrap();
+        assert_eq!(load_quota(&conn, "agent").unwrap(), Some(quota.clone()));
+        assert_eq!(excess_memories(&conn, "agent", &quota).unwrap(), 1);
+
+        let status = quota_status(&conn, "agent").unwrap();
+        assert_eq!((status.memory_count, status.pinned_count), (3, 1));
+        assert_eq!(status.memory_usage, Some(1.5));
+        assert!(status.db_bytes > 0);
+
+        store_quota(&conn, "agent", None).unwrap();
+        assert!(load_quota(&conn, "agent").unwrap().is_none());
+    }
+}
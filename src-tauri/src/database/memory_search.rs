@@ -0,0 +1,289 @@
+//! Hybrid retrieval over an agent's memories. Vector similarity, FTS5 BM25, tag matches and
+//! recency each rank candidates, and reciprocal rank fusion blends the rankings by weight.
+
+use super::memory::{AgentMemory, FusionScores, FusionWeights, MemoryQuery, MemorySearchResult, SignalScore};
+use super::shared_knowledge::{fts_query, RRF_K};
+use super::simple_memory::row_to_memory;
+use anyhow::Result;
+use chrono::Utc;
+use rusqlite::{Connection, ToSql};
+use std::collections::HashMap;
+
+/// Fewest and most candidates each signal ranks
+const MIN_SIGNAL_WINDOW: usize = 20;
+const MAX_SIGNAL_WINDOW: usize = 500;
+
+const MEMORY_COLUMNS: &str = "am.id, am.agent_id, am.memory_type, am.content, am.metadata, am.embedding, \
+     am.relevance_score, am.created_at, am.updated_at, am.access_count, am.tags, am.pinned, am.tier";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Signal {
+    Vector,
+    Text,
+    Tags,
+    Recency,
+}
+
+/// One signal's candidates, best first, with the raw score each was ranked by
+struct Ranking {
+    signal: Signal,
+    weight: f32,
+    ranked: Vec<(AgentMemory, f32)>,
+}
+
+/// SQL conditions shared by every signal: agent, memory types, tiers and time range
+fn filter_clause(query: &MemoryQuery) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut sql = String::new();
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+    if let Some(agent_id) = &query.agent_id {
+        sql.push_str(" AND am.agent_id = ?");
+        params.push(Box::new(agent_id.clone()));
+    }
+    if let Some(types) = &query.memory_types {
+        let placeholders = vec!["?"; types.len()].join(",");
+        sql.push_str(&format!(" AND am.memory_type IN ({})", placeholders));
+        for memory_type in types {
+            params.push(Box::new(format!("{:?}", memory_type)));
+        }
+    }
+    if let Some(tiers) = &query.tiers {
+        let placeholders = vec!["?"; tiers.len()].join(",");
+        sql.push_str(&format!(" AND am.tier IN ({})", placeholders));
+        for tier in tiers {
+            params.push(Box::new(format!("{:?}", tier)));
+        }
+    }
+    if let Some((start_time, end_time)) = &query.time_range {
+        sql.push_str(" AND am.created_at BETWEEN ? AND ?");
+        params.push(Box::new(start_time.to_rfc3339()));
+        params.push(Box::new(end_time.to_rfc3339()));
+    }
+    (sql, params)
+}
+
+/// Run `sql`, whose first selected columns are `MEMORY_COLUMNS` followed by a score
+fn scored_rows(conn: &Connection, sql: &str, params: Vec<Box<dyn ToSql>>) -> Result<Vec<(AgentMemory, f32)>> {
+    let mut stmt = conn.prepare(sql)?;
+    let params_refs: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let rows = stmt.query_map(&params_refs[..], |row| Ok((row_to_memory(row)?, row.get::<_, f64>("score")? as f32)))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+fn vector_ranking(conn: &Connection, query: &MemoryQuery, query_embedding: &[f32], window: usize) -> Result<Vec<(AgentMemory, f32)>> {
+    let (filter, params) = filter_clause(query);
+    let sql = format!(
+        "SELECT {}, 0.0 AS score FROM agent_memories am WHERE am.embedding IS NOT NULL{}",
+        MEMORY_COLUMNS, filter
+    );
+    let mut ranked: Vec<(AgentMemory, f32)> = scored_rows(conn, &sql, params)?
+        .into_iter()
+        .filter_map(|(memory, _)| {
+            let similarity = query.similarity_metric.similarity(query_embedding, memory.embedding.as_deref()?);
+            Some((memory, similarity))
+        })
+        .filter(|(_, similarity)| query.similarity_threshold.is_none_or(|threshold| *similarity >= threshold))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.truncate(window);
+    Ok(ranked)
+}
+
+fn text_ranking(conn: &Connection, query: &MemoryQuery, fts: String, window: usize) -> Result<Vec<(AgentMemory, f32)>> {
+    let (filter, filter_params) = filter_clause(query);
+    let sql = format!(
+        "SELECT {}, -bm25(agent_memories_fts) AS score FROM agent_memories_fts
+         JOIN agent_memories am ON am.rowid = agent_memories_fts.rowid
+         WHERE agent_memories_fts MATCH ?{}
+         ORDER BY bm25(agent_memories_fts) LIMIT {}",
+        MEMORY_COLUMNS, filter, window
+    );
+    let mut params: Vec<Box<dyn ToSql>> = vec![Box::new(fts)];
+    params.extend(filter_params);
+    scored_rows(conn, &sql, params)
+}
+
+fn tag_ranking(conn: &Connection, query: &MemoryQuery, tags: &[String], window: usize) -> Result<Vec<(AgentMemory, f32)>> {
+    let (filter, filter_params) = filter_clause(query);
+    let sql = format!(
+        "SELECT {}, (SELECT COUNT(*) FROM json_each(am.tags) WHERE lower(json_each.value) IN ({})) AS score
+         FROM agent_memories am WHERE score > 0{}
+         ORDER BY score DESC, am.created_at DESC LIMIT {}",
+        MEMORY_COLUMNS, vec!["?"; tags.len()].join(","), filter, window
+    );
+    let mut params: Vec<Box<dyn ToSql>> = tags.iter()
+        .map(|tag| Box::new(tag.to_lowercase()) as Box<dyn ToSql>)
+        .collect();
+    params.extend(filter_params);
+    scored_rows(conn, &sql, params)
+}
+
+/// Newest first, scored by age in hours
+fn recency_ranking(mut memories: Vec<AgentMemory>) -> Vec<(AgentMemory, f32)> {
+    let now = Utc::now();
+    memories.sort_by_key(|memory| std::cmp::Reverse(memory.created_at));
+    memories.into_iter()
+        .map(|memory| {
+            let age_hours = (now - memory.created_at).num_minutes() as f32 / 60.0;
+            (memory, age_hours)
+        })
+        .collect()
+}
+
+/// Blend the rankings with weighted reciprocal rank fusion, best first; ties go to newer memories
+fn fuse(rankings: Vec<Ranking>) -> Vec<(AgentMemory, FusionScores)> {
+    let mut fused: HashMap<String, (AgentMemory, FusionScores)> = HashMap::new();
+
+    for Ranking { signal, weight, ranked } in rankings {
+        for (rank, (memory, raw)) in ranked.into_iter().enumerate() {
+            let contribution = weight / (RRF_K + rank as f32 + 1.0);
+            let entry = fused.entry(memory.id.clone()).or_insert_with(|| (memory, FusionScores::default()));
+            let score = Some(SignalScore { rank, raw, contribution });
+            match signal {
+                Signal::Vector => entry.1.vector = score,
+                Signal::Text => entry.1.text = score,
+                Signal::Tags => entry.1.tags = score,
+                Signal::Recency => entry.1.recency = score,
+            }
+            entry.1.fused += contribution;
+        }
+    }
+
+    let mut results: Vec<(AgentMemory, FusionScores)> = fused.into_values().collect();
+    results.sort_by(|a, b| b.1.fused.total_cmp(&a.1.fused).then(b.0.created_at.cmp(&a.0.created_at)));
+    results
+}
+
+/// Search with every signal `query` provides: `embedding` for similarity (cut off at
+/// `similarity_threshold`), `content_search` for BM25 and `tags` for tag matches. Recency
+/// re-ranks what those found, or everything matching the filters when none is given.
+pub fn hybrid_search(conn: &Connection, query: &MemoryQuery, weights: FusionWeights) -> Result<Vec<MemorySearchResult>> {
+    let offset = query.offset.unwrap_or(0);
+    let window = query.limit
+        .map_or(MAX_SIGNAL_WINDOW, |limit| (offset + limit).saturating_mul(4))
+        .clamp(MIN_SIGNAL_WINDOW, MAX_SIGNAL_WINDOW);
+
+    let mut rankings = Vec::new();
+    let mut searched = false;
+    if let Some(query_embedding) = query.embedding.as_deref().filter(|_| weights.vector > 0.0) {
+        searched = true;
+        rankings.push(Ranking { signal: Signal::Vector, weight: weights.vector, ranked: vector_ranking(conn, query, query_embedding, window)? });
+    }
+    if let Some(fts) = query.content_search.as_deref().and_then(fts_query).filter(|_| weights.text > 0.0) {
+        searched = true;
+        rankings.push(Ranking { signal: Signal::Text, weight: weights.text, ranked: text_ranking(conn, query, fts, window)? });
+    }
+    if let Some(tags) = query.tags.as_deref().filter(|tags| !tags.is_empty() && weights.tags > 0.0) {
+        searched = true;
+        rankings.push(Ranking { signal: Signal::Tags, weight: weights.tags, ranked: tag_ranking(conn, query, tags, window)? });
+    }
+
+    if weights.recency > 0.0 {
+        let candidates: Vec<AgentMemory> = if searched {
+            let mut seen = std::collections::HashSet::new();
+            rankings.iter()
+                .flat_map(|ranking| ranking.ranked.iter().map(|(memory, _)| memory))
+                .filter(|memory| seen.insert(memory.id.clone()))
+                .cloned()
+                .collect()
+        } else {
+            let (filter, params) = filter_clause(query);
+            let sql = format!(
+                "SELECT {}, 0.0 AS score FROM agent_memories am WHERE 1=1{} ORDER BY am.created_at DESC LIMIT {}",
+                MEMORY_COLUMNS, filter, window
+            );
+            scored_rows(conn, &sql, params)?.into_iter().map(|(memory, _)| memory).collect()
+        };
+        rankings.push(Ranking { signal: Signal::Recency, weight: weights.recency, ranked: recency_ranking(candidates) });
+    }
+
+    Ok(fuse(rankings).into_iter()
+        .skip(offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .enumerate()
+        .map(|(relevance_rank, (memory, scores))| MemorySearchResult {
+            memory,
+            similarity_score: scores.vector.as_ref().map(|score| score.raw),
+            relevance_rank,
+            fusion_scores: Some(scores),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::embeddings::SimilarityMetric;
+    use crate::database::memory::MemoryType;
+    use crate::database::memory_transfer::{import_memories, MergeStrategy};
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+
+    fn memory(content: &str, tags: &[&str], embedding: Vec<f32>) -> AgentMemory {
+        AgentMemory::new("agent".to_string(), MemoryType::Learning, content.to_string())
+            .with_tags(tags.iter().map(|tag| tag.to_string()).collect())
+            .with_embedding(embedding)
+    }
+
+    fn query(content_search: Option<&str>, tags: &[&str], embedding: Option<Vec<f32>>) -> MemoryQuery {
+        MemoryQuery {
+            agent_id: Some("agent".to_string()),
+            memory_types: None,
+            content_search: content_search.map(str::to_string),
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            embedding,
+            similarity_threshold: None,
+            similarity_metric: SimilarityMetric::Cosine,
+            limit: Some(10),
+            offset: None,
+            time_range: None,
+            tiers: None,
+            fusion: Some(FusionWeights::default()),
+        }
+    }
+
+    fn ids(results: &[MemorySearchResult]) -> Vec<&str> {
+        results.iter().map(|result| result.memory.content.as_str()).collect()
+    }
+
+    #[test]
+    fn test_hybrid_search_fuses_signals() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        let memories = vec![
+            memory("rust borrow checker rules", &["Rust"], vec![1.0, 0.0]),
+            memory("python packaging with poetry", &["python"], vec![0.0, 1.0]),
+            memory("rust async runtimes compared", &[], vec![0.6, 0.8]),
+        ];
+        import_memories(&mut conn, "agent", memories, MergeStrategy::Skip).unwrap();
+
+        let results = hybrid_search(&conn, &query(Some("rust"), &["rust"], None), FusionWeights::default()).unwrap();
+        assert_eq!(ids(&results), vec!["rust borrow checker rules", "rust async runtimes compared"]);
+        let scores = results[0].fusion_scores.as_ref().unwrap();
+        assert!(scores.text.is_some() && scores.tags.is_some() && scores.recency.is_some());
+        assert!(scores.vector.is_none() && results[1].fusion_scores.as_ref().unwrap().tags.is_none());
+
+        // Similarity alone brings in the memory no text matched, and is reported per result
+        let results = hybrid_search(&conn, &query(None, &[], Some(vec![0.0, 1.0])), FusionWeights::default()).unwrap();
+        assert_eq!(ids(&results)[0], "python packaging with poetry");
+        assert!((results[0].similarity_score.unwrap() - 1.0).abs() < 1e-3);
+        let weights = FusionWeights { vector: 0.0, ..Default::default() };
+        assert_eq!(hybrid_search(&conn, &query(None, &[], None), weights).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_text_index_follows_overwrites_and_deletes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        let original = memory("prefers tabs", &[], vec![1.0]);
+        import_memories(&mut conn, "agent", vec![original.clone()], MergeStrategy::Skip).unwrap();
+        let edited = AgentMemory { content: "prefers spaces".to_string(), ..original };
+        import_memories(&mut conn, "agent", vec![edited], MergeStrategy::Overwrite).unwrap();
+
+        let weights = FusionWeights { recency: 0.0, ..Default::default() };
+        assert!(hybrid_search(&conn, &query(Some("tabs"), &[], None), weights).unwrap().is_empty());
+        assert_eq!(hybrid_search(&conn, &query(Some("spaces"), &[], None), weights).unwrap().len(), 1);
+
+        conn.execute("DELETE FROM agent_memories", []).unwrap();
+        assert!(hybrid_search(&conn, &query(Some("spaces"), &[], None), weights).unwrap().is_empty());
+    }
+}
@@ -1,12 +1,17 @@
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
-use super::neural_network::{NeuralNetwork, NetworkBuilder, ActivationFunction};
+use std::path::Path;
+use super::neural_network::{NeuralNetwork, NetworkBuilder, ActivationFunction, read_model_file, write_model_file};
 use super::memory::{AgentMemory, MemoryType};
 use serde::{Serialize, Deserialize};
-use ndarray::{Array1, Array2};
+use ndarray::{s, Array1, Array2, Axis};
+
+/// Sequences at least this long go to the analyzer's transformer, since recurrent models
+/// lose the early part of long sequences
+pub const LONG_SEQUENCE_THRESHOLD: usize = 32;
 
 /// LSTM cell implementation for memory sequence modeling
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LSTMCell {
     /// Input dimension
     input_size: usize,
@@ -79,7 +84,7 @@ impl LSTMCell {
 }
 
 /// GRU cell implementation for memory sequence modeling
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GRUCell {
     /// Input dimension
     input_size: usize,
@@ -140,8 +145,125 @@ impl GRUCell {
     }
 }
 
-/// Memory sequence model that uses LSTM/GRU for temporal understanding
-#[derive(Debug)]
+/// One transformer encoder block: multi-head self-attention and a feed-forward layer, each
+/// followed by a residual connection and layer normalization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfAttentionLayer {
+    num_heads: usize,
+    /// Query, key, value and output projections, each (model_dim, model_dim)
+    query: Array2<f32>,
+    key: Array2<f32>,
+    value: Array2<f32>,
+    output: Array2<f32>,
+    /// Feed-forward expansion to twice the model dimension and back
+    feed_forward_in: (Array2<f32>, Array1<f32>),
+    feed_forward_out: (Array2<f32>, Array1<f32>),
+}
+
+impl SelfAttentionLayer {
+    pub fn new(model_dim: usize, num_heads: usize) -> Self {
+        Self {
+            num_heads,
+            query: xavier(model_dim, model_dim),
+            key: xavier(model_dim, model_dim),
+            value: xavier(model_dim, model_dim),
+            output: xavier(model_dim, model_dim),
+            feed_forward_in: (xavier(model_dim * 2, model_dim), Array1::zeros(model_dim * 2)),
+            feed_forward_out: (xavier(model_dim, model_dim * 2), Array1::zeros(model_dim)),
+        }
+    }
+
+    /// Encode a sequence with one row per timestep
+    pub fn forward(&self, sequence: &Array2<f32>) -> Array2<f32> {
+        let queries = sequence.dot(&self.query.t());
+        let keys = sequence.dot(&self.key.t());
+        let values = sequence.dot(&self.value.t());
+
+        // Each head attends over its own slice of the projections
+        let head_dim = sequence.ncols() / self.num_heads;
+        let scale = (head_dim as f32).sqrt();
+        let mut attended = Array2::zeros(sequence.raw_dim());
+        for head in 0..self.num_heads {
+            let columns = s![.., head * head_dim..(head + 1) * head_dim];
+            let mut scores = queries.slice(columns).dot(&keys.slice(columns).t()) / scale;
+            for mut row in scores.rows_mut() {
+                let max = row.fold(f32::NEG_INFINITY, |max, &x| max.max(x));
+                row.mapv_inplace(|x| (x - max).exp());
+                let sum = row.sum();
+                row.mapv_inplace(|x| x / sum);
+            }
+            attended.slice_mut(columns).assign(&scores.dot(&values.slice(columns)));
+        }
+
+        let attention = layer_norm(sequence + &attended.dot(&self.output.t()));
+        let hidden = (attention.dot(&self.feed_forward_in.0.t()) + &self.feed_forward_in.1)
+            .mapv(|x| ActivationFunction::ReLU.apply(x));
+        let feed_forward = hidden.dot(&self.feed_forward_out.0.t()) + &self.feed_forward_out.1;
+        layer_norm(attention + feed_forward)
+    }
+}
+
+/// Stack of self-attention blocks over memory embeddings, with sinusoidal positional
+/// encodings of each memory's age rather than its index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformerEncoder {
+    /// Maps input embeddings to the model dimension, (model_dim, input_size)
+    input_projection: Array2<f32>,
+    layers: Vec<SelfAttentionLayer>,
+}
+
+impl TransformerEncoder {
+    pub fn new(input_size: usize, model_dim: usize, num_layers: usize) -> Self {
+        // Four heads when the dimension allows it, otherwise as many as divide it evenly
+        let num_heads = [4, 2, 1].into_iter().find(|heads| model_dim % heads == 0).unwrap_or(1);
+        Self {
+            input_projection: xavier(model_dim, input_size),
+            layers: (0..num_layers).map(|_| SelfAttentionLayer::new(model_dim, num_heads)).collect(),
+        }
+    }
+
+    /// Mean of the encoded timesteps. `positions` are in hours since the first memory.
+    pub fn encode(&self, sequence: &[Vec<f32>], positions: &[f32]) -> Array1<f32> {
+        let (model_dim, input_size) = self.input_projection.dim();
+        let inputs = Array2::from_shape_fn((sequence.len(), input_size), |(i, j)| {
+            sequence[i].get(j).copied().unwrap_or(0.0)
+        });
+        let mut encoded = inputs.dot(&self.input_projection.t()) + positional_encoding(positions, model_dim);
+        for layer in &self.layers {
+            encoded = layer.forward(&encoded);
+        }
+        encoded.mean_axis(Axis(0)).unwrap_or_else(|| Array1::zeros(model_dim))
+    }
+}
+
+/// Sinusoidal encoding of fractional positions, one row per position
+fn positional_encoding(positions: &[f32], dim: usize) -> Array2<f32> {
+    Array2::from_shape_fn((positions.len(), dim), |(i, j)| {
+        let frequency = 1.0 / 10000f32.powf((j - j % 2) as f32 / dim as f32);
+        let angle = positions[i] * frequency;
+        if j % 2 == 0 { angle.sin() } else { angle.cos() }
+    })
+}
+
+/// Normalize each row to zero mean and unit variance
+fn layer_norm(mut matrix: Array2<f32>) -> Array2<f32> {
+    for mut row in matrix.rows_mut() {
+        let mean = row.mean().unwrap_or(0.0);
+        let variance = row.mapv(|x| (x - mean).powi(2)).mean().unwrap_or(0.0);
+        let std = (variance + 1e-5).sqrt();
+        row.mapv_inplace(|x| (x - mean) / std);
+    }
+    matrix
+}
+
+/// Xavier-initialized (rows, cols) weights
+fn xavier(rows: usize, cols: usize) -> Array2<f32> {
+    let scale = (2.0 / (rows + cols) as f32).sqrt();
+    Array2::from_shape_fn((rows, cols), |_| (fastrand::f32() - 0.5) * 2.0 * scale)
+}
+
+/// Memory sequence model that uses LSTM/GRU or self-attention for temporal understanding
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MemorySequenceModel {
     /// Type of sequence model (LSTM or GRU)
     model_type: SequenceModelType,
@@ -149,6 +271,8 @@ pub struct MemorySequenceModel {
     lstm_cells: Option<Vec<LSTMCell>>,
     /// GRU cells (if using GRU)
     gru_cells: Option<Vec<GRUCell>>,
+    /// Self-attention encoder (if using a transformer)
+    transformer: Option<TransformerEncoder>,
     /// Input embedding size
     input_size: usize,
     /// Hidden state size
@@ -163,6 +287,7 @@ pub struct MemorySequenceModel {
 pub enum SequenceModelType {
     LSTM,
     GRU,
+    Transformer,
 }
 
 impl MemorySequenceModel {
@@ -174,14 +299,14 @@ impl MemorySequenceModel {
         output_size: usize,
         num_layers: usize,
     ) -> Result<Self> {
-        let (lstm_cells, gru_cells) = match model_type {
+        let (lstm_cells, gru_cells, transformer) = match model_type {
             SequenceModelType::LSTM => {
                 let mut cells = Vec::new();
                 for i in 0..num_layers {
                     let layer_input_size = if i == 0 { input_size } else { hidden_size };
                     cells.push(LSTMCell::new(layer_input_size, hidden_size));
                 }
-                (Some(cells), None)
+                (Some(cells), None, None)
             }
             SequenceModelType::GRU => {
                 let mut cells = Vec::new();
@@ -189,7 +314,10 @@ impl MemorySequenceModel {
                     let layer_input_size = if i == 0 { input_size } else { hidden_size };
                     cells.push(GRUCell::new(layer_input_size, hidden_size));
                 }
-                (None, Some(cells))
+                (None, Some(cells), None)
+            }
+            SequenceModelType::Transformer => {
+                (None, None, Some(TransformerEncoder::new(input_size, hidden_size, num_layers)))
             }
         };
 
@@ -205,6 +333,7 @@ impl MemorySequenceModel {
             model_type,
             lstm_cells,
             gru_cells,
+            transformer,
             input_size,
             hidden_size,
             num_layers,
@@ -212,8 +341,24 @@ impl MemorySequenceModel {
         })
     }
 
+    /// Whether both models have the same cell type, sizes and output network
+    pub fn same_architecture(&self, other: &MemorySequenceModel) -> bool {
+        std::mem::discriminant(&self.model_type) == std::mem::discriminant(&other.model_type)
+            && self.input_size == other.input_size
+            && self.hidden_size == other.hidden_size
+            && self.num_layers == other.num_layers
+            && self.output_network.same_architecture(&other.output_network)
+    }
+
     /// Process a sequence of memory embeddings
     pub fn process_sequence(&self, sequence: &[Vec<f32>]) -> Result<Vec<f32>> {
+        let positions: Vec<f32> = (0..sequence.len()).map(|i| i as f32).collect();
+        self.process_timed_sequence(sequence, &positions)
+    }
+
+    /// Process a sequence whose steps are `positions` hours after the first; only the
+    /// transformer uses the spacing, the recurrent models just the order
+    pub fn process_timed_sequence(&self, sequence: &[Vec<f32>], positions: &[f32]) -> Result<Vec<f32>> {
         if sequence.is_empty() {
             return Ok(vec![0.0; self.hidden_size]);
         }
@@ -221,9 +366,21 @@ impl MemorySequenceModel {
         match self.model_type {
             SequenceModelType::LSTM => self.process_lstm_sequence(sequence),
             SequenceModelType::GRU => self.process_gru_sequence(sequence),
+            SequenceModelType::Transformer => self.process_transformer_sequence(sequence, positions),
         }
     }
 
+    /// Process sequence through the self-attention encoder
+    fn process_transformer_sequence(&self, sequence: &[Vec<f32>], positions: &[f32]) -> Result<Vec<f32>> {
+        let transformer = self.transformer.as_ref().ok_or_else(|| anyhow!("Transformer encoder not initialized"))?;
+        if positions.len() != sequence.len() {
+            return Err(anyhow!("Expected {} positions, got {}", sequence.len(), positions.len()));
+        }
+
+        let pooled = transformer.encode(sequence, positions);
+        Ok(self.output_network.run(&pooled.to_vec()))
+    }
+
     /// Process sequence through LSTM layers
     fn process_lstm_sequence(&self, sequence: &[Vec<f32>]) -> Result<Vec<f32>> {
         let lstm_cells = self.lstm_cells.as_ref().ok_or_else(|| anyhow!("LSTM cells not initialized"))?;
@@ -302,8 +459,13 @@ impl MemorySequenceModel {
             .iter()
             .map(|memory| self.memory_to_embedding(memory))
             .collect();
+        let start = sorted_memories[0].created_at;
+        let positions: Vec<f32> = sorted_memories
+            .iter()
+            .map(|memory| (memory.created_at - start).num_seconds() as f32 / 3600.0)
+            .collect();
 
-        self.process_sequence(&memory_embeddings)
+        self.process_timed_sequence(&memory_embeddings, &positions)
     }
 
     /// Convert memory to embedding (simplified implementation)
@@ -356,11 +518,14 @@ impl MemorySequenceModel {
 }
 
 /// Memory sequence analyzer for different memory types
+#[derive(Serialize, Deserialize)]
 pub struct MemorySequenceAnalyzer {
     /// Specialized sequence models for different memory types
     models: HashMap<MemoryType, MemorySequenceModel>,
     /// General sequence model for mixed memory types
     general_model: MemorySequenceModel,
+    /// Transformer used instead of the others for sequences of `LONG_SEQUENCE_THRESHOLD` or more
+    long_sequence_model: MemorySequenceModel,
 }
 
 impl MemorySequenceAnalyzer {
@@ -398,17 +563,70 @@ impl MemorySequenceAnalyzer {
             2
         )?;
 
+        let long_sequence_model = MemorySequenceModel::new(
+            SequenceModelType::Transformer,
+            input_size,
+            hidden_size,
+            output_size,
+            2
+        )?;
+
         Ok(Self {
             models,
             general_model,
+            long_sequence_model,
         })
     }
 
+    pub fn same_architecture(&self, other: &MemorySequenceAnalyzer) -> bool {
+        self.general_model.same_architecture(&other.general_model)
+            && self.long_sequence_model.same_architecture(&other.long_sequence_model)
+            && self.models.len() == other.models.len()
+            && self.models.iter().all(|(memory_type, model)| {
+                other.models.get(memory_type).is_some_and(|o| model.same_architecture(o))
+            })
+    }
+
+    /// Save every sequence model's weights
+    pub fn save_model(&self, path: &Path) -> Result<()> {
+        write_model_file(path, self)
+    }
+
+    pub fn load_model(path: &Path) -> Result<Self> {
+        read_model_file(path)
+    }
+
+    /// Replace these models with the ones saved at `path` if there are any; see
+    /// `NeuralNetwork::load_saved`
+    pub fn load_saved(&mut self, path: &Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+        let saved = Self::load_model(path)?;
+        if !self.same_architecture(&saved) {
+            return Err(anyhow!("Model {} was saved with a different architecture", path.display()));
+        }
+        *self = saved;
+        Ok(true)
+    }
+
+    /// The transformer for long sequences, otherwise `model`
+    fn model_for_length<'a>(&'a self, model: &'a MemorySequenceModel, length: usize) -> &'a MemorySequenceModel {
+        if length >= LONG_SEQUENCE_THRESHOLD {
+            &self.long_sequence_model
+        } else {
+            model
+        }
+    }
+
     /// Analyze memory sequence with appropriate specialized model
     pub fn analyze_sequence(&self, memories: &[AgentMemory]) -> Result<Vec<f32>> {
         if memories.is_empty() {
             return Ok(vec![0.0; 128]); // Default output size
         }
+        if memories.len() >= LONG_SEQUENCE_THRESHOLD {
+            return self.long_sequence_model.extract_temporal_patterns(memories);
+        }
 
         // Determine dominant memory type in sequence
         let mut type_counts = HashMap::new();
@@ -443,7 +661,8 @@ impl MemorySequenceAnalyzer {
         sorted_memories.sort_by(|a, b| a.created_at.cmp(&b.created_at));
 
         // Analyze sequence with general model
-        let sequence_embedding = self.general_model.extract_temporal_patterns(&sorted_memories)?;
+        let sequence_embedding = self.model_for_length(&self.general_model, sorted_memories.len())
+            .extract_temporal_patterns(&sorted_memories)?;
 
         // Analyze patterns by memory type
         let mut type_patterns = HashMap::new();
@@ -461,7 +680,8 @@ impl MemorySequenceAnalyzer {
             
             if !type_memories.is_empty() {
                 if let Some(model) = self.models.get(&memory_type) {
-                    let pattern = model.extract_temporal_patterns(&type_memories)?;
+                    let pattern = self.model_for_length(model, type_memories.len())
+                        .extract_temporal_patterns(&type_memories)?;
                     type_patterns.insert(memory_type, pattern);
                 }
             }
@@ -480,6 +700,7 @@ impl MemorySequenceAnalyzer {
 
 /// Analysis result for memory patterns
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MemoryPatternAnalysis {
     /// Overall sequence pattern
     pub overall_pattern: Vec<f32>,
@@ -558,6 +779,36 @@ mod tests {
         assert_eq!(result.len(), 30); // Output size
     }
 
+    #[test]
+    fn test_transformer_uses_timestamps() {
+        let model = MemorySequenceModel::new(SequenceModelType::Transformer, 10, 16, 8, 2).unwrap();
+        let sequence = vec![vec![0.1; 10], vec![0.5; 10], vec![0.9; 10]];
+
+        let evenly_spaced = model.process_timed_sequence(&sequence, &[0.0, 1.0, 2.0]).unwrap();
+        assert_eq!(evenly_spaced.len(), 8);
+        assert_eq!(model.process_sequence(&sequence).unwrap(), evenly_spaced);
+        assert_ne!(model.process_timed_sequence(&sequence, &[0.0, 1.0, 48.0]).unwrap(), evenly_spaced);
+        assert!(model.process_timed_sequence(&sequence, &[0.0]).is_err());
+    }
+
+    #[test]
+    fn test_analyzer_uses_transformer_for_long_sequences() {
+        let analyzer = MemorySequenceAnalyzer::new(16, 32, 8).unwrap();
+        let start = Utc::now();
+        let memories: Vec<AgentMemory> = (0..LONG_SEQUENCE_THRESHOLD)
+            .map(|i| {
+                let mut memory = AgentMemory::new("agent1".to_string(), MemoryType::Task, format!("Task {}", i));
+                memory.created_at = start + chrono::Duration::minutes(i as i64 * 30);
+                memory
+            })
+            .collect();
+
+        let expected = analyzer.long_sequence_model.extract_temporal_patterns(&memories).unwrap();
+        assert_eq!(analyzer.analyze_sequence(&memories).unwrap(), expected);
+        assert_eq!(analyzer.detect_patterns(&memories).unwrap().overall_pattern, expected);
+        assert_ne!(analyzer.analyze_sequence(&memories[..4]).unwrap(), expected);
+    }
+
     #[test]
     fn test_memory_sequence_analyzer() {
         let analyzer = MemorySequenceAnalyzer::new(32, 64, 128).unwrap();
@@ -577,5 +828,13 @@ mod tests {
 
         let analysis = analyzer.analyze_sequence(&memories).unwrap();
         assert_eq!(analysis.len(), 128);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sequence.bin");
+        analyzer.save_model(&path).unwrap();
+        let mut reloaded = MemorySequenceAnalyzer::new(32, 64, 128).unwrap();
+        assert!(reloaded.load_saved(&path).unwrap());
+        assert_eq!(reloaded.analyze_sequence(&memories).unwrap(), analysis);
+        assert!(MemorySequenceAnalyzer::new(16, 64, 128).unwrap().load_saved(&path).is_err());
     }
 }
\ No newline at end of file
@@ -0,0 +1,237 @@
+use super::memory::*;
+use super::simple_commands::MemoryState;
+use crate::ai::csrf::{guard_secure_command, session_agent};
+use crate::ai::secure_commands::SecureResponse;
+use crate::validation::MemoryValidator;
+use tauri::{State, Webview};
+use tracing::info;
+
+fn parse_memory_types(types: Option<Vec<String>>) -> Result<Vec<MemoryType>, String> {
+    types.unwrap_or_default().iter().map(|t| match t.as_str() {
+        "Conversation" => Ok(MemoryType::Conversation),
+        "Task" => Ok(MemoryType::Task),
+        "Learning" => Ok(MemoryType::Learning),
+        "Context" => Ok(MemoryType::Context),
+        "Tool" => Ok(MemoryType::Tool),
+        "Error" => Ok(MemoryType::Error),
+        "Success" => Ok(MemoryType::Success),
+        "Pattern" => Ok(MemoryType::Pattern),
+        other => Err(format!("Invalid memory type: {}", other)),
+    }).collect()
+}
+
+/// A session acting as an agent may only change the grants on that agent's own memories
+fn ensure_session_owns(command: &str, session_id: &str, origin: &str, owner_agent: &str) -> Result<(), String> {
+    match session_agent(command, session_id, origin)? {
+        Some(agent) if agent != owner_agent => {
+            Err(format!("Session acting as {} cannot change access to memories of {}", agent, owner_agent))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Let `grantee_agent` read the memories of `owner_agent` that carry one of `tags` and are
+/// one of `memory_types`. At least one scope is required; a new grant replaces the old one.
+#[tauri::command]
+pub async fn grant_memory_access(
+    session_id: String,
+    csrf_token: String,
+    csrf_double_submit: String,
+    owner_agent: String,
+    grantee_agent: String,
+    tags: Option<Vec<String>>,
+    memory_types: Option<Vec<String>>,
+    webview: Webview,
+    state: State<'_, MemoryState>,
+) -> Result<SecureResponse<MemoryAccessGrant>, String> {
+    info!("Granting {} access to memories of {}", grantee_agent, owner_agent);
+
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("grant_memory_access", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
+    ensure_session_owns("grant_memory_access", &session_id, webview.label(), &owner_agent)?;
+
+    MemoryValidator::validate_agent_id(&owner_agent).map_err(|e| e.to_string())?;
+    MemoryValidator::validate_agent_id(&grantee_agent).map_err(|e| e.to_string())?;
+    let tags = tags.unwrap_or_default();
+    MemoryValidator::validate_tags(&tags).map_err(|e| e.to_string())?;
+    let memory_types = parse_memory_types(memory_types)?;
+    if tags.is_empty() && memory_types.is_empty() {
+        return Err("A grant must be scoped to at least one tag or memory type".to_string());
+    }
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "memory_operations",
+        &[owner_agent, grantee_agent],
+        &[]
+    ).await?;
+    let sanitized_owner = &validation_result.sanitized_inputs[0];
+    let sanitized_grantee = &validation_result.sanitized_inputs[1];
+
+    let manager = state.get_or_create_manager(sanitized_owner.clone())?;
+    let grant = manager.grant_memory_access(sanitized_grantee, tags, memory_types)
+        .map_err(|e| format!("Failed to grant memory access: {}", e))?;
+
+    security_middleware.audit_access(
+        "grant_memory_access",
+        sanitized_owner,
+        &format!("memories:{}", sanitized_owner),
+        true,
+        &format!("grantee={} tags={:?} types={:?}", sanitized_grantee, grant.tags, grant.memory_types),
+    );
+    Ok(SecureResponse { data: grant, csrf })
+}
+
+/// Withdraw access granted with `grant_memory_access`. Returns whether a grant existed.
+#[tauri::command]
+pub async fn revoke_memory_access(
+    session_id: String,
+    csrf_token: String,
+    csrf_double_submit: String,
+    owner_agent: String,
+    grantee_agent: String,
+    webview: Webview,
+    state: State<'_, MemoryState>,
+) -> Result<SecureResponse<bool>, String> {
+    info!("Revoking {} access to memories of {}", grantee_agent, owner_agent);
+
+    // Validate session and spend the token pair
+    let csrf = guard_secure_command("revoke_memory_access", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
+    ensure_session_owns("revoke_memory_access", &session_id, webview.label(), &owner_agent)?;
+
+    MemoryValidator::validate_agent_id(&owner_agent).map_err(|e| e.to_string())?;
+    MemoryValidator::validate_agent_id(&grantee_agent).map_err(|e| e.to_string())?;
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "memory_operations",
+        &[owner_agent, grantee_agent],
+        &[]
+    ).await?;
+    let sanitized_owner = &validation_result.sanitized_inputs[0];
+    let sanitized_grantee = &validation_result.sanitized_inputs[1];
+
+    let manager = state.get_or_create_manager(sanitized_owner.clone())?;
+    let revoked = manager.revoke_memory_access(sanitized_grantee)
+        .map_err(|e| format!("Failed to revoke memory access: {}", e))?;
+
+    security_middleware.audit_access(
+        "revoke_memory_access",
+        sanitized_owner,
+        &format!("memories:{}", sanitized_owner),
+        true,
+        &format!("grantee={} existed={}", sanitized_grantee, revoked),
+    );
+    Ok(SecureResponse { data: revoked, csrf })
+}
+
+/// Search another agent's memories, limited to what it granted the agent the session acts as
+#[tauri::command]
+pub async fn query_foreign_memories(
+    session_id: String,
+    owner_agent: String,
+    content_search: Option<String>,
+    memory_types: Option<Vec<String>>,
+    tags: Option<Vec<String>>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    webview: Webview,
+    state: State<'_, MemoryState>,
+) -> Result<Vec<MemorySearchResult>, String> {
+    // The requester is whoever the session was bound to, never a caller-supplied id
+    let requesting_agent = session_agent("query_foreign_memories", &session_id, webview.label())?
+        .ok_or_else(|| "Session is not acting as an agent".to_string())?;
+    info!("Agent {} querying memories of {}", requesting_agent, owner_agent);
+
+    MemoryValidator::validate_agent_id(&owner_agent).map_err(|e| e.to_string())?;
+    if let Some(ref search) = content_search {
+        MemoryValidator::validate_content(search).map_err(|e| e.to_string())?;
+    }
+    if let Some(ref tags) = tags {
+        MemoryValidator::validate_tags(tags).map_err(|e| e.to_string())?;
+    }
+    if let Some(limit) = limit {
+        MemoryValidator::validate_limit(limit).map_err(|e| e.to_string())?;
+    }
+    if let Some(offset) = offset {
+        MemoryValidator::validate_offset(offset).map_err(|e| e.to_string())?;
+    }
+    let memory_types = parse_memory_types(memory_types)?;
+
+    let security_middleware = state.get_security_middleware();
+    let mut inputs = vec![requesting_agent, owner_agent];
+    inputs.extend(content_search);
+    let validation_result = security_middleware.validate_request(
+        "memory_operations",
+        &inputs,
+        &[]
+    ).await?;
+    let sanitized_requester = &validation_result.sanitized_inputs[0];
+    let sanitized_owner = &validation_result.sanitized_inputs[1];
+    let resource = format!("memories:{}", sanitized_owner);
+
+    let manager = state.get_or_create_manager(sanitized_owner.clone())?;
+    let query = MemoryQuery {
+        agent_id: Some(sanitized_owner.clone()),
+        memory_types: (!memory_types.is_empty()).then_some(memory_types),
+        content_search: validation_result.sanitized_inputs.get(2).cloned(),
+        tags,
+        embedding: None,
+        similarity_threshold: None,
+        similarity_metric: state.similarity_metric().await,
+        limit,
+        offset,
+        time_range: None,
+        tiers: None,
+        fusion: None,
+    };
+
+    match manager.query_foreign_memories(sanitized_requester, &query) {
+        Ok(results) => {
+            security_middleware.audit_access(
+                "query_foreign_memories",
+                sanitized_requester,
+                &resource,
+                true,
+                &format!("returned={}", results.len()),
+            );
+            Ok(results)
+        }
+        Err(e) => {
+            security_middleware.audit_access("query_foreign_memories", sanitized_requester, &resource, false, &e.to_string());
+            Err(format!("Failed to query foreign memories: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(agent_id: &str, memory_type: MemoryType, tags: &[&str]) -> AgentMemory {
+        let mut memory = AgentMemory::new(agent_id.to_string(), memory_type, "content".to_string());
+        memory.tags = tags.iter().map(|t| t.to_string()).collect();
+        memory
+    }
+
+    #[test]
+    fn test_grant_scopes_by_owner_tags_and_types() {
+        let grant = MemoryAccessGrant {
+            id: "g1".to_string(),
+            owner_agent: "owner".to_string(),
+            grantee_agent: "reader".to_string(),
+            tags: vec!["Project-X".to_string()],
+            memory_types: parse_memory_types(Some(vec!["Task".to_string(), "Learning".to_string()])).unwrap(),
+            created_at: chrono::Utc::now(),
+        };
+
+        assert!(grant.permits(&memory("owner", MemoryType::Task, &["project-x", "urgent"])));
+        assert!(!grant.permits(&memory("owner", MemoryType::Conversation, &["project-x"])));
+        assert!(!grant.permits(&memory("owner", MemoryType::Task, &["personal"])));
+        assert!(!grant.permits(&memory("someone-else", MemoryType::Task, &["project-x"])));
+
+        let any_tag = MemoryAccessGrant { tags: Vec::new(), ..grant };
+        assert!(any_tag.permits(&memory("owner", MemoryType::Learning, &[])));
+        assert!(parse_memory_types(Some(vec!["Secret".to_string()])).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! Episodic and semantic memory tiers. Episodic memories are the raw record of what happened;
+//! consolidation clusters older ones by embedding similarity and summarizes each cluster into a
+//! semantic memory linked back to its sources. Each tier has its own retention policy.
+
+use super::embeddings::SimilarityMetric;
+use super::memory::{AgentMemory, MemoryTier, MemoryType};
+use super::simple_commands::MemoryState;
+use super::simple_memory::SimpleMemoryManager;
+use crate::ai::summarizer::{summarize_offline, SummaryKind};
+use crate::ai::AIState;
+use crate::operations::start_operation;
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, warn};
+
+/// Settings key prefix for per-agent retention policies
+const RETENTION_SETTING_PREFIX: &str = "memory_retention.";
+
+/// Oldest unconsolidated episodes considered in one consolidation run
+const MAX_CONSOLIDATION_CANDIDATES: usize = 2000;
+
+/// Sentences kept in a semantic memory's summary
+const SUMMARY_SENTENCES: usize = 3;
+
+/// Which episodic memories are consolidated and how alike they must be
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsolidationOptions {
+    /// Only consolidate memories of this type
+    pub memory_type: Option<MemoryType>,
+    /// Embedding similarity to a cluster's first memory needed to join it (0.0 - 1.0)
+    pub similarity_threshold: f32,
+    /// Smallest cluster worth summarizing
+    pub min_cluster_size: usize,
+    /// Episodes younger than this are left alone
+    pub older_than_hours: u32,
+}
+
+impl Default for ConsolidationOptions {
+    fn default() -> Self {
+        Self {
+            memory_type: None,
+            similarity_threshold: 0.8,
+            min_cluster_size: 3,
+            older_than_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsolidationReport {
+    pub agent_id: String,
+    pub episodes_considered: usize,
+    pub episodes_consolidated: usize,
+    pub semantic_memory_ids: Vec<String>,
+}
+
+/// Per-agent retention, in days since creation. Pinned memories are always kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryRetentionPolicy {
+    pub episodic_max_age_days: Option<u32>,
+    /// Keep old episodic memories until they have been consolidated
+    pub keep_unconsolidated: bool,
+    pub semantic_max_age_days: Option<u32>,
+}
+
+impl Default for MemoryRetentionPolicy {
+    fn default() -> Self {
+        Self {
+            episodic_max_age_days: Some(30),
+            keep_unconsolidated: true,
+            semantic_max_age_days: None,
+        }
+    }
+}
+
+impl MemoryRetentionPolicy {
+    /// Memories of `tier` created before this are expired
+    pub fn cutoff(&self, tier: MemoryTier, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let max_age_days = match tier {
+            MemoryTier::Episodic => self.episodic_max_age_days,
+            MemoryTier::Semantic => self.semantic_max_age_days,
+        }?;
+        Some(now - Duration::days(max_age_days as i64))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionReport {
+    pub agent_id: String,
+    pub episodic_deleted: usize,
+    pub semantic_deleted: usize,
+}
+
+/// Greedy clusters of episode indexes: each unassigned episode with an embedding starts a
+/// cluster and takes every later unassigned episode of its type similar enough to it.
+/// Clusters smaller than `min_size` are dropped.
+pub fn cluster_episodes(
+    episodes: &[AgentMemory],
+    threshold: f32,
+    min_size: usize,
+    metric: SimilarityMetric,
+) -> Vec<Vec<usize>> {
+    let mut assigned = vec![false; episodes.len()];
+    let mut clusters = Vec::new();
+
+    for seed in 0..episodes.len() {
+        let Some(seed_embedding) = episodes[seed].embedding.as_ref() else {
+            continue;
+        };
+        if assigned[seed] {
+            continue;
+        }
+        let mut cluster = vec![seed];
+        for other in seed + 1..episodes.len() {
+            if assigned[other] || episodes[other].memory_type != episodes[seed].memory_type {
+                continue;
+            }
+            if let Some(embedding) = episodes[other].embedding.as_ref() {
+                if metric.similarity(seed_embedding, embedding) >= threshold {
+                    cluster.push(other);
+                }
+            }
+        }
+        if cluster.len() >= min_size {
+            for &index in &cluster {
+                assigned[index] = true;
+            }
+            clusters.push(cluster);
+        }
+    }
+    clusters
+}
+
+/// The semantic memory standing for `cluster`: its summary as content, the union of the
+/// episodes' tags, their normalized mean embedding and their highest relevance
+pub fn semantic_memory(cluster: &[&AgentMemory], summary: String) -> AgentMemory {
+    let first = cluster[0];
+    let mut memory = AgentMemory::new(first.agent_id.clone(), first.memory_type.clone(), summary);
+    memory.tier = MemoryTier::Semantic;
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in cluster.iter().flat_map(|episode| &episode.tags) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+    memory.tags = tags;
+
+    let embeddings: Vec<&Vec<f32>> = cluster.iter().filter_map(|episode| episode.embedding.as_ref()).collect();
+    if let Some(dimensions) = embeddings.first().map(|e| e.len()) {
+        let mut centroid = vec![0.0f32; dimensions];
+        for embedding in embeddings.iter().filter(|e| e.len() == dimensions) {
+            for (sum, value) in centroid.iter_mut().zip(embedding.iter()) {
+                *sum += value;
+            }
+        }
+        let norm = centroid.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            centroid.iter_mut().for_each(|v| *v /= norm);
+        }
+        memory.embedding = Some(centroid);
+    }
+
+    memory.relevance_score = cluster.iter().map(|episode| episode.relevance_score).fold(0.0, f32::max);
+    let first_at = cluster.iter().map(|episode| episode.created_at).min().unwrap_or(memory.created_at);
+    let last_at = cluster.iter().map(|episode| episode.created_at).max().unwrap_or(memory.created_at);
+    memory.metadata = HashMap::from([
+        ("consolidated_from".to_string(), cluster.len().to_string()),
+        ("first_episode_at".to_string(), first_at.to_rfc3339()),
+        ("last_episode_at".to_string(), last_at.to_rfc3339()),
+    ]);
+    memory
+}
+
+/// Cluster and summarize the agent's unconsolidated episodes, saving one semantic memory per
+/// cluster. Returns the episodes considered and the new semantic memories.
+pub fn consolidate(
+    manager: &SimpleMemoryManager,
+    options: &ConsolidationOptions,
+    metric: SimilarityMetric,
+) -> Result<(usize, Vec<(AgentMemory, Vec<String>)>)> {
+    let created_before = Utc::now() - Duration::hours(options.older_than_hours as i64);
+    let episodes = manager.unconsolidated_episodes(options.memory_type.as_ref(), created_before, MAX_CONSOLIDATION_CANDIDATES)?;
+
+    let mut consolidated = Vec::new();
+    for cluster in cluster_episodes(&episodes, options.similarity_threshold, options.min_cluster_size, metric) {
+        let cluster: Vec<&AgentMemory> = cluster.iter().map(|&index| &episodes[index]).collect();
+        let text = cluster.iter().map(|episode| episode.content.trim()).collect::<Vec<_>>().join("\n");
+        let summary = summarize_offline(&text, SummaryKind::Summary, SUMMARY_SENTENCES);
+
+        let semantic = semantic_memory(&cluster, summary.text);
+        let episode_ids: Vec<String> = cluster.iter().map(|episode| episode.id.clone()).collect();
+        manager.save_consolidation(&semantic, &episode_ids)?;
+        consolidated.push((semantic, episode_ids));
+    }
+    Ok((episodes.len(), consolidated))
+}
+
+/// Delete the agent's expired memories under `policy`, returning what was deleted
+pub fn apply_retention(manager: &SimpleMemoryManager, policy: &MemoryRetentionPolicy) -> Result<Vec<AgentMemory>> {
+    let now = Utc::now();
+    let mut expired = Vec::new();
+    if let Some(cutoff) = policy.cutoff(MemoryTier::Episodic, now) {
+        expired.extend(manager.expired_memories(MemoryTier::Episodic, cutoff, policy.keep_unconsolidated)?);
+    }
+    if let Some(cutoff) = policy.cutoff(MemoryTier::Semantic, now) {
+        expired.extend(manager.expired_memories(MemoryTier::Semantic, cutoff, false)?);
+    }
+
+    let ids: Vec<String> = expired.iter().map(|memory| memory.id.clone()).collect();
+    manager.delete_memories(&ids)?;
+    Ok(expired)
+}
+
+/// Load the stored retention policy for an agent, falling back to defaults
+pub fn load_retention_policy(ai_state: &AIState, agent_id: &str) -> MemoryRetentionPolicy {
+    match ai_state.storage.get_setting(&format!("{}{}", RETENTION_SETTING_PREFIX, agent_id)) {
+        Ok(Some(value)) => serde_json::from_value(value).unwrap_or_else(|e| {
+            warn!("Ignoring malformed memory retention policy for {}: {}", agent_id, e);
+            MemoryRetentionPolicy::default()
+        }),
+        Ok(None) => MemoryRetentionPolicy::default(),
+        Err(e) => {
+            warn!("Failed to load memory retention policy for {}: {}", agent_id, e);
+            MemoryRetentionPolicy::default()
+        }
+    }
+}
+
+// Tauri Commands
+
+/// Summarize clusters of the agent's older, similar episodic memories into semantic memories
+#[tauri::command]
+pub async fn consolidate_memories(
+    agent_id: String,
+    options: Option<ConsolidationOptions>,
+    state: State<'_, MemoryState>,
+) -> Result<ConsolidationReport, String> {
+    info!("Consolidating memories for agent: {}", agent_id);
+
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+    let options = options.unwrap_or_default();
+    MemoryValidator::validate_similarity_threshold(options.similarity_threshold)
+        .map_err(|e| e.to_string())?;
+    if options.min_cluster_size < 2 {
+        return Err("Clusters need at least 2 memories".to_string());
+    }
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let metric = state.similarity_metric().await;
+    let (episodes_considered, consolidated) = tokio::task::spawn_blocking(move || consolidate(&manager, &options, metric))
+        .await
+        .map_err(|e| format!("Consolidation task failed: {}", e))?
+        .map_err(|e| format!("Failed to consolidate memories: {}", e))?;
+
+    let graph_lock = state.get_neural_graph().await?;
+    let mut neural_graph = graph_lock.lock().await;
+    if let Some(ref mut graph) = *neural_graph {
+        for (semantic, _) in &consolidated {
+            if let Err(e) = graph.add_memory_node(semantic).await {
+                warn!("Failed to add semantic memory {} to neural graph: {}", semantic.id, e);
+            }
+        }
+    }
+
+    let report = ConsolidationReport {
+        agent_id: sanitized_agent_id,
+        episodes_considered,
+        episodes_consolidated: consolidated.iter().map(|(_, episode_ids)| episode_ids.len()).sum(),
+        semantic_memory_ids: consolidated.into_iter().map(|(semantic, _)| semantic.id).collect(),
+    };
+    info!(
+        "Consolidated {} of {} episodes into {} semantic memories for {}",
+        report.episodes_consolidated, report.episodes_considered, report.semantic_memory_ids.len(), report.agent_id
+    );
+    Ok(report)
+}
+
+/// Start `consolidate_memories` as an operation; its result is the `ConsolidationReport`
+#[tauri::command]
+pub async fn start_memory_consolidation(
+    agent_id: String,
+    options: Option<ConsolidationOptions>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+    Ok(start_operation(&app_handle, "memory_consolidation", {
+        let app_handle = app_handle.clone();
+        move |operation| async move {
+            operation.check_cancelled()?;
+            operation.report(0.0, format!("Consolidating memories of {}", agent_id));
+            let report = consolidate_memories(agent_id, options, app_handle.state::<MemoryState>()).await?;
+            serde_json::to_value(report).map_err(|e| e.to_string())
+        }
+    }))
+}
+
+#[tauri::command]
+pub async fn set_memory_retention_policy(
+    agent_id: String,
+    policy: MemoryRetentionPolicy,
+    ai_state: State<'_, AIState>,
+) -> Result<(), String> {
+    info!("Setting memory retention policy for agent: {}", agent_id);
+
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    if policy.episodic_max_age_days == Some(0) || policy.semantic_max_age_days == Some(0) {
+        return Err("Maximum age must be at least 1 day".to_string());
+    }
+
+    let value = serde_json::to_value(&policy)
+        .map_err(|e| format!("Failed to serialize retention policy: {}", e))?;
+
+    ai_state.storage
+        .set_setting(&format!("{}{}", RETENTION_SETTING_PREFIX, agent_id), value)
+        .map_err(|e| format!("Failed to store retention policy: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_memory_retention_policy(
+    agent_id: String,
+    ai_state: State<'_, AIState>,
+) -> Result<MemoryRetentionPolicy, String> {
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    Ok(load_retention_policy(&ai_state, &agent_id))
+}
+
+/// Delete the agent's memories that have outlived their tier's retention policy
+#[tauri::command]
+pub async fn apply_memory_retention(
+    agent_id: String,
+    state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<RetentionReport, String> {
+    info!("Applying memory retention for agent: {}", agent_id);
+
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(|e| e.to_string())?;
+
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+
+    let policy = load_retention_policy(&ai_state, &sanitized_agent_id);
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let deleted = tokio::task::spawn_blocking(move || apply_retention(&manager, &policy))
+        .await
+        .map_err(|e| format!("Retention task failed: {}", e))?
+        .map_err(|e| format!("Failed to apply retention policy: {}", e))?;
+
+    if !deleted.is_empty() {
+        let service_lock = state.get_neural_embedding_service().await?;
+        if let Some(ref service) = *service_lock.lock().await {
+            service.evict_memories(&deleted).await;
+        }
+        let graph_lock = state.get_neural_graph().await?;
+        let mut neural_graph = graph_lock.lock().await;
+        if let Some(ref mut graph) = *neural_graph {
+            let ids: HashSet<String> = deleted.iter().map(|memory| memory.id.clone()).collect();
+            graph.remove_agent_nodes(&sanitized_agent_id, Some(&ids)).await;
+        }
+    }
+
+    let semantic_deleted = deleted.iter().filter(|memory| memory.tier == MemoryTier::Semantic).count();
+    Ok(RetentionReport {
+        agent_id: sanitized_agent_id,
+        episodic_deleted: deleted.len() - semantic_deleted,
+        semantic_deleted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(memory_type: MemoryType, embedding: Vec<f32>, tags: &[&str]) -> AgentMemory {
+        AgentMemory::new("agent".to_string(), memory_type, "user asked about deploys".to_string())
+            .with_embedding(embedding)
+            .with_tags(tags.iter().map(|tag| tag.to_string()).collect())
+    }
+
+    #[test]
+    fn test_clusters_similar_episodes_of_one_type() {
+        let episodes = vec![
+            episode(MemoryType::Task, vec![1.0, 0.0], &[]),
+            episode(MemoryType::Task, vec![0.95, 0.05], &[]),
+            episode(MemoryType::Learning, vec![1.0, 0.0], &[]),
+            episode(MemoryType::Task, vec![0.0, 1.0], &[]),
+            episode(MemoryType::Task, vec![0.9, 0.1], &[]),
+            episode(MemoryType::Task, vec![0.05, 0.95], &[]),
+        ];
+        let clusters = cluster_episodes(&episodes, 0.9, 2, SimilarityMetric::Cosine);
+        assert_eq!(clusters, vec![vec![0, 1, 4], vec![3, 5]]);
+        assert_eq!(cluster_episodes(&episodes, 0.9, 3, SimilarityMetric::Cosine), vec![vec![0, 1, 4]]);
+    }
+
+    #[test]
+    fn test_semantic_memory_merges_cluster() {
+        let mut first = episode(MemoryType::Task, vec![2.0, 0.0], &["Deploy"]);
+        first.relevance_score = 0.4;
+        let mut second = episode(MemoryType::Task, vec![0.0, 2.0], &["deploy", "docker"]);
+        second.relevance_score = 0.9;
+
+        let semantic = semantic_memory(&[&first, &second], "Deploys use docker.".to_string());
+        assert_eq!(semantic.tier, MemoryTier::Semantic);
+        assert_eq!(semantic.memory_type, MemoryType::Task);
+        assert_eq!(semantic.tags, vec!["Deploy", "docker"]);
+        let embedding = semantic.embedding.unwrap();
+        assert!((embedding[0] - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(semantic.relevance_score, 0.9);
+        assert_eq!(semantic.metadata["consolidated_from"], "2");
+    }
+
+    #[test]
+    fn test_retention_cutoff_per_tier() {
+        let now = Utc::now();
+        let policy = MemoryRetentionPolicy::default();
+        assert_eq!(policy.cutoff(MemoryTier::Episodic, now), Some(now - Duration::days(30)));
+        assert_eq!(policy.cutoff(MemoryTier::Semantic, now), None);
+    }
+}
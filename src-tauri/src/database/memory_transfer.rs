@@ -0,0 +1,502 @@
+use super::embedding_quantization::encode_embedding;
+use super::memory::*;
+use super::neural_embeddings::NeuralEmbeddingService;
+use super::simple_commands::MemoryState;
+use super::simple_memory::UPSERT_MEMORY;
+use crate::ai::{enforce_path_policy, AIState, PathAccess};
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+use tracing::{info, warn};
+
+/// Version written to export headers; imports from newer versions are refused.
+/// Version 2 records the embedding space.
+pub const MEMORY_EXPORT_SCHEMA_VERSION: u32 = 2;
+
+/// Largest export file accepted for import
+const MAX_IMPORT_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Validation errors listed back to the user; the rest are counted
+const MAX_REPORTED_ERRORS: usize = 50;
+
+/// First line of every export
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemoryExportHeader {
+    pub schema_version: u32,
+    pub agent_id: String,
+    pub exported_at: DateTime<Utc>,
+    pub memory_count: usize,
+    pub includes_embeddings: bool,
+    /// Embedding model the vectors came from (`EmbeddingProviderConfig::cache_id`)
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    #[serde(default)]
+    pub embedding_dimension: Option<usize>,
+}
+
+/// Embedding space of an export, as returned by `NeuralEmbeddingService::embedding_space`
+pub type EmbeddingSpace = (String, Option<usize>);
+
+/// One line of an export file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum ExportRecord {
+    Header(MemoryExportHeader),
+    Memory(AgentMemory),
+}
+
+/// Which memories to export. Empty lists don't restrict.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryExportFilter {
+    pub memory_types: Vec<String>,
+    /// Memories carrying any of these tags
+    pub tags: Vec<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Embeddings are large and can be regenerated, so they're left out unless asked for
+    pub include_embeddings: bool,
+}
+
+impl MemoryExportFilter {
+    fn matches(&self, memory: &AgentMemory) -> bool {
+        (self.memory_types.is_empty() || self.memory_types.contains(&memory.memory_type.to_string()))
+            && (self.tags.is_empty() || memory.tags.iter().any(|t| self.tags.iter().any(|f| f.eq_ignore_ascii_case(t))))
+            && self.since.is_none_or(|since| memory.created_at >= since)
+            && self.until.is_none_or(|until| memory.created_at <= until)
+    }
+}
+
+/// What to do with an imported memory whose id already exists
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// Keep the existing memory
+    #[default]
+    Skip,
+    /// Replace the existing memory with the imported one
+    Overwrite,
+    /// Import under a fresh id, keeping both
+    Duplicate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryExportSummary {
+    pub path: String,
+    pub memory_count: usize,
+    pub includes_embeddings: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MemoryImportSummary {
+    pub imported: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    /// Memories whose embeddings came from another model and were regenerated or dropped
+    #[serde(default)]
+    pub reembedded: usize,
+    /// Agent the memories were exported from
+    pub source_agent: String,
+}
+
+/// Serialize memories as JSON Lines: a header, then one memory per line
+pub fn to_jsonl(
+    agent_id: &str,
+    memories: Vec<AgentMemory>,
+    include_embeddings: bool,
+    space: Option<EmbeddingSpace>,
+) -> Result<String> {
+    let (embedding_model, configured_dimension) = match (include_embeddings, space) {
+        (true, Some((model, dimension))) => (Some(model), dimension),
+        _ => (None, None),
+    };
+    let embedding_dimension = configured_dimension.or_else(|| {
+        memories.iter().find_map(|m| m.embedding.as_ref().map(Vec::len))
+    }).filter(|_| embedding_model.is_some());
+    let header = MemoryExportHeader {
+        schema_version: MEMORY_EXPORT_SCHEMA_VERSION,
+        agent_id: agent_id.to_string(),
+        exported_at: Utc::now(),
+        memory_count: memories.len(),
+        includes_embeddings: include_embeddings,
+        embedding_model,
+        embedding_dimension,
+    };
+
+    let mut out = serde_json::to_string(&ExportRecord::Header(header))?;
+    out.push('\n');
+    for mut memory in memories {
+        if !include_embeddings {
+            memory.embedding = None;
+        }
+        out.push_str(&serde_json::to_string(&ExportRecord::Memory(memory))?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Parse and validate an export. Every problem is reported with its line number.
+pub fn parse_jsonl(content: &str) -> Result<(MemoryExportHeader, Vec<AgentMemory>), Vec<String>> {
+    let mut lines = content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let header = match lines.next().map(|(_, line)| serde_json::from_str::<ExportRecord>(line)) {
+        Some(Ok(ExportRecord::Header(header))) => header,
+        _ => return Err(vec!["Line 1: expected an export header".to_string()]),
+    };
+    if header.schema_version > MEMORY_EXPORT_SCHEMA_VERSION {
+        return Err(vec![format!(
+            "Export schema version {} is newer than the supported version {}",
+            header.schema_version, MEMORY_EXPORT_SCHEMA_VERSION
+        )]);
+    }
+
+    let mut memories = Vec::new();
+    let mut errors = Vec::new();
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let memory = match serde_json::from_str::<ExportRecord>(line) {
+            Ok(ExportRecord::Memory(memory)) => memory,
+            Ok(ExportRecord::Header(_)) => {
+                errors.push(format!("Line {}: unexpected second header", line_number));
+                continue;
+            }
+            Err(e) => {
+                errors.push(format!("Line {}: {}", line_number, e));
+                continue;
+            }
+        };
+        let validated = MemoryValidator::validate_memory_id(&memory.id)
+            .and_then(|_| MemoryValidator::validate_content(&memory.content))
+            .and_then(|_| MemoryValidator::validate_tags(&memory.tags));
+        match validated {
+            Ok(()) => memories.push(memory),
+            Err(e) => errors.push(format!("Line {}: {}", line_number, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok((header, memories))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Whether an imported embedding can be kept as is: it must come from the target agent's
+/// model and have the dimension both the export and the target expect
+fn embedding_matches(header: &MemoryExportHeader, target: &EmbeddingSpace, embedding: &[f32]) -> bool {
+    let (model, dimension) = target;
+    header.embedding_model.as_deref() == Some(model.as_str())
+        && header.embedding_dimension == Some(embedding.len())
+        && dimension.is_none_or(|d| d == embedding.len())
+}
+
+/// Regenerate embeddings that don't belong to the target's embedding space. Without an
+/// embedding service they are dropped, so they can't be compared with the wrong vectors.
+/// Returns how many memories were affected.
+pub async fn reconcile_embeddings(
+    header: &MemoryExportHeader,
+    target: &EmbeddingSpace,
+    service: Option<&NeuralEmbeddingService>,
+    memories: &mut [AgentMemory],
+) -> usize {
+    let mut reembedded = 0;
+    for memory in memories.iter_mut() {
+        match memory.embedding.as_deref() {
+            Some(embedding) if !embedding_matches(header, target, embedding) => {}
+            _ => continue,
+        }
+        memory.embedding = None;
+        reembedded += 1;
+        if let Some(service) = service {
+            match service.embed_memory(memory).await {
+                Ok(embedding) => memory.embedding = Some(embedding),
+                Err(e) => warn!("Failed to re-embed imported memory {}: {}", memory.id, e),
+            }
+        }
+    }
+    reembedded
+}
+
+fn insert_memory(conn: &Connection, memory: &AgentMemory) -> Result<()> {
+    let embedding_blob = memory.embedding.as_deref().map(encode_embedding);
+    conn.execute(
+        UPSERT_MEMORY,
+        params![
+            memory.id,
+            memory.agent_id,
+            format!("{:?}", memory.memory_type),
+            memory.content,
+            serde_json::to_string(&memory.metadata)?,
+            embedding_blob,
+            memory.relevance_score,
+            memory.created_at.to_rfc3339(),
+            memory.updated_at.to_rfc3339(),
+            memory.access_count,
+            serde_json::to_string(&memory.tags)?,
+            memory.pinned,
+            format!("{:?}", memory.tier)
+        ],
+    )?;
+    Ok(())
+}
+
+/// Write imported memories to `agent_id`'s database in one transaction
+pub fn import_memories(
+    conn: &mut Connection,
+    agent_id: &str,
+    memories: Vec<AgentMemory>,
+    strategy: MergeStrategy,
+) -> Result<MemoryImportSummary> {
+    let tx = conn.transaction()?;
+    let mut summary = MemoryImportSummary::default();
+    for mut memory in memories {
+        memory.agent_id = agent_id.to_string();
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM agent_memories WHERE id = ?1)",
+            params![memory.id],
+            |row| row.get(0),
+        )?;
+        match (exists, strategy) {
+            (true, MergeStrategy::Skip) => {
+                summary.skipped += 1;
+                continue;
+            }
+            (true, MergeStrategy::Overwrite) => summary.overwritten += 1,
+            (true, MergeStrategy::Duplicate) => {
+                memory.id = uuid::Uuid::new_v4().to_string();
+                summary.imported += 1;
+            }
+            (false, _) => summary.imported += 1,
+        }
+        insert_memory(&tx, &memory)?;
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// Write an agent's memories, narrowed by `filter`, to a JSON Lines file at `path`.
+/// An existing file is only replaced when `overwrite` is set.
+#[tauri::command]
+pub async fn export_agent_memories(
+    agent_id: String,
+    path: String,
+    filter: Option<MemoryExportFilter>,
+    overwrite: Option<bool>,
+    state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<MemoryExportSummary, String> {
+    info!("Exporting memories of agent {} to {}", agent_id, path);
+
+    MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+    let filter = filter.unwrap_or_default();
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "backup_operations",
+        &[agent_id],
+        std::slice::from_ref(&path)
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let memories: Vec<AgentMemory> = manager.search_memories(&MemoryQuery {
+        agent_id: Some(sanitized_agent_id.clone()),
+        memory_types: None,
+        content_search: None,
+        tags: None,
+        embedding: None,
+        similarity_threshold: None,
+        similarity_metric: SimilarityMetric::default(),
+        limit: None,
+        offset: None,
+        time_range: None,
+        tiers: None,
+        fusion: None,
+    }).map_err(|e| format!("Failed to load memories: {}", e))?
+        .into_iter()
+        .map(|result| result.memory)
+        .filter(|memory| filter.matches(memory))
+        .collect();
+
+    let space = {
+        let service_lock = state.get_neural_embedding_service().await?;
+        let service = service_lock.lock().await;
+        service.as_ref().map(|s| s.embedding_space(sanitized_agent_id))
+    };
+
+    let memory_count = memories.len();
+    let content = to_jsonl(sanitized_agent_id, memories, filter.include_embeddings, space)
+        .map_err(|e| format!("Failed to serialize memories: {}", e))?;
+
+    let bytes = content.len() as u64;
+    let target = enforce_path_policy(&ai_state.storage, None, &path, PathAccess::Write { size: bytes })?;
+    if target.exists() && !overwrite.unwrap_or(false) {
+        return Err(format!("Export {} already exists; pass overwrite to replace it", path));
+    }
+    let directory = target.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    crate::ai::ensure_disk_space(directory, bytes, "memory export")
+        .map_err(|e| e.to_string())?;
+    std::fs::write(&target, content).map_err(|e| format!("Failed to write export {}: {}", path, e))?;
+
+    info!("Exported {} memories of agent {}", memory_count, sanitized_agent_id);
+    Ok(MemoryExportSummary {
+        path: target.to_string_lossy().to_string(),
+        memory_count,
+        includes_embeddings: filter.include_embeddings,
+    })
+}
+
+/// Load a JSON Lines export into an agent, resolving id collisions with `merge_strategy`.
+/// Nothing is written if any line is invalid. Embeddings from another model are regenerated.
+#[tauri::command]
+pub async fn import_agent_memories(
+    agent_id: String,
+    path: String,
+    merge_strategy: Option<MergeStrategy>,
+    state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<MemoryImportSummary, String> {
+    info!("Importing memories into agent {} from {}", agent_id, path);
+
+    MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "backup_operations",
+        &[agent_id],
+        std::slice::from_ref(&path)
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    let source = enforce_path_policy(&ai_state.storage, None, &path, PathAccess::Chunked)?;
+    let size = std::fs::metadata(&source).map_err(|e| format!("Failed to read import {}: {}", path, e))?.len();
+    if size > MAX_IMPORT_BYTES {
+        return Err(format!("Import exceeds {} bytes", MAX_IMPORT_BYTES));
+    }
+    let content = std::fs::read_to_string(&source).map_err(|e| format!("Failed to read import {}: {}", path, e))?;
+
+    let (header, mut memories) = parse_jsonl(&content).map_err(|errors| {
+        let shown: Vec<&String> = errors.iter().take(MAX_REPORTED_ERRORS).collect();
+        let more = errors.len().saturating_sub(MAX_REPORTED_ERRORS);
+        let mut message = format!("Import rejected with {} error(s):\n{}", errors.len(),
+            shown.iter().map(|e| e.as_str()).collect::<Vec<_>>().join("\n"));
+        if more > 0 {
+            message.push_str(&format!("\n…and {} more", more));
+        }
+        message
+    })?;
+
+    for memory in memories.iter_mut() {
+        memory.agent_id = sanitized_agent_id.clone();
+    }
+    let reembedded = {
+        let service_lock = state.get_neural_embedding_service().await?;
+        let service = service_lock.lock().await;
+        let target = match service.as_ref() {
+            Some(service) => service.embedding_space(sanitized_agent_id),
+            None => (String::new(), None),
+        };
+        reconcile_embeddings(&header, &target, service.as_ref(), &mut memories).await
+    };
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    crate::ai::ensure_disk_space(manager.get_agent_db_path(), size, "memory import")
+        .map_err(|e| e.to_string())?;
+    let mut conn = manager.agent_connection()
+        .map_err(|e| format!("Failed to open memory database: {}", e))?;
+    let summary = import_memories(&mut conn, sanitized_agent_id, memories, merge_strategy.unwrap_or_default())
+        .map_err(|e| format!("Failed to import memories: {}", e))?;
+
+    info!(
+        "Imported {} memories from agent {} into {} ({} overwritten, {} skipped, {} re-embedded)",
+        summary.imported, header.agent_id, sanitized_agent_id, summary.overwritten, summary.skipped, reembedded
+    );
+    Ok(MemoryImportSummary { source_agent: header.agent_id, reembedded, ..summary })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
+
+    fn memory(content: &str, tags: &[&str]) -> AgentMemory {
+        let mut memory = AgentMemory::new("source".to_string(), MemoryType::Learning, content.to_string());
+        memory.tags = tags.iter().map(|t| t.to_string()).collect();
+        memory.embedding = Some(vec![0.5, 0.25]);
+        memory.metadata.insert("origin".to_string(), "chat".to_string());
+        memory
+    }
+
+    #[test]
+    fn test_jsonl_round_trip_and_filtering() {
+        let kept = memory("Prefers tabs", &["style"]);
+        let dropped = memory("Lives in Oslo", &["personal"]);
+        let filter = MemoryExportFilter { tags: vec!["STYLE".to_string()], ..Default::default() };
+        let selected: Vec<AgentMemory> = [kept.clone(), dropped].into_iter().filter(|m| filter.matches(m)).collect();
+
+        let content = to_jsonl("source", selected, false, None).unwrap();
+        let (header, memories) = parse_jsonl(&content).unwrap();
+        assert_eq!(header.schema_version, MEMORY_EXPORT_SCHEMA_VERSION);
+        assert_eq!(header.memory_count, 1);
+        assert_eq!(memories[0].id, kept.id);
+        assert_eq!(memories[0].metadata, kept.metadata);
+        assert!(memories[0].embedding.is_none());
+        assert!(header.embedding_model.is_none());
+        assert!(parse_jsonl(&to_jsonl("source", vec![kept], true, None).unwrap()).unwrap().1[0].embedding.is_some());
+
+        let future = content.replacen("\"schema_version\":2", "\"schema_version\":99", 1);
+        assert!(parse_jsonl(&future).unwrap_err()[0].contains("newer"));
+        let broken = format!("{}{{\"record\":\"memory\"}}\n", content);
+        assert!(parse_jsonl(&broken).unwrap_err()[0].starts_with("Line 3:"));
+        assert!(parse_jsonl("").is_err());
+    }
+
+    #[test]
+    fn test_import_merge_strategies() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap();
+        let original = memory("Prefers tabs", &[]);
+        let summary = import_memories(&mut conn, "target", vec![original.clone()], MergeStrategy::Skip).unwrap();
+        assert_eq!(summary.imported, 1);
+
+        let mut edited = original.clone();
+        edited.content = "Prefers spaces".to_string();
+        let skipped = import_memories(&mut conn, "target", vec![edited.clone()], MergeStrategy::Skip).unwrap();
+        assert_eq!(skipped.skipped, 1);
+        let overwritten = import_memories(&mut conn, "target", vec![edited.clone()], MergeStrategy::Overwrite).unwrap();
+        assert_eq!(overwritten.overwritten, 1);
+        let duplicated = import_memories(&mut conn, "target", vec![edited], MergeStrategy::Duplicate).unwrap();
+        assert_eq!(duplicated.imported, 1);
+
+        let rows: Vec<(String, String)> = conn.prepare("SELECT agent_id, content FROM agent_memories ORDER BY id = ?1 DESC").unwrap()
+            .query_map(params![original.id], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(rows, vec![
+            ("target".to_string(), "Prefers spaces".to_string()),
+            ("target".to_string(), "Prefers spaces".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_from_another_model_are_dropped() {
+        let space = ("openai:text-embedding-3-small".to_string(), None);
+        let content = to_jsonl("source", vec![memory("Prefers tabs", &[])], true, Some(space.clone())).unwrap();
+        let (header, memories) = parse_jsonl(&content).unwrap();
+        assert_eq!(header.embedding_model.as_deref(), Some("openai:text-embedding-3-small"));
+        assert_eq!(header.embedding_dimension, Some(2));
+
+        let mut same = memories.clone();
+        assert_eq!(reconcile_embeddings(&header, &space, None, &mut same).await, 0);
+        assert!(same[0].embedding.is_some());
+
+        let mut other = memories.clone();
+        assert_eq!(reconcile_embeddings(&header, &("neural".to_string(), Some(256)), None, &mut other).await, 1);
+        assert!(other[0].embedding.is_none());
+
+        let mut narrower = memories;
+        assert_eq!(reconcile_embeddings(&header, &(space.0, Some(1)), None, &mut narrower).await, 1);
+    }
+}
@@ -0,0 +1,371 @@
+//! Editing and regenerating conversation messages. Changing a message rewrites the conversation
+//! from that point: the original and every later message are copied to `message_revisions`
+//! before they are changed or removed, and the agent can then reply again from the new history.
+
+use super::agent_sessions::load_agent;
+use super::artifacts::{artifact_store_dir, message_images};
+use super::memory_budget::estimate_tokens;
+use super::{open_conversation_db, parse_db_timestamp, DbMessage};
+use crate::ai::orchestration::configured_provider;
+use crate::ai::{complete_conversation, normalize_turns, select_completion_provider, AIState, ChatTurn, GuardrailScope};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use tracing::info;
+
+/// Why a message version was archived
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RevisionReason {
+    /// The message's content was replaced
+    Edited,
+    /// The message came after an edited or regenerated one
+    Truncated,
+    /// The assistant reply was replaced by a regenerated one
+    Regenerated,
+}
+
+impl RevisionReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Edited => "edited",
+            Self::Truncated => "truncated",
+            Self::Regenerated => "regenerated",
+        }
+    }
+}
+
+/// How a conversation changed after an edit or regeneration
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversationRewrite {
+    pub conversation_id: String,
+    /// The edited message with its new content
+    pub edited: Option<DbMessage>,
+    /// The agent's new reply
+    pub reply: Option<DbMessage>,
+    /// Messages archived and removed from the conversation
+    pub removed_message_ids: Vec<String>,
+    pub token_count: i32,
+}
+
+/// Timestamps in the format the frontend writes
+fn now_timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn message_conversation(conn: &Connection, message_id: &str) -> Result<String> {
+    conn.query_row(
+        "SELECT conversation_id FROM messages WHERE id = ?1",
+        params![message_id],
+        |row| row.get(0),
+    ).optional()?
+        .ok_or_else(|| anyhow!("Message not found: {}", message_id))
+}
+
+/// A conversation's messages in order. Timestamps written by the frontend and by SQLite have
+/// different formats, so they're compared parsed, with insertion order breaking ties.
+pub(super) fn ordered_messages(conn: &Connection, conversation_id: &str) -> Result<Vec<DbMessage>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, conversation_id, role, content, tool_calls, timestamp, tokens
+         FROM messages WHERE conversation_id = ?1 ORDER BY rowid",
+    )?;
+    let mut messages = stmt.query_map(params![conversation_id], |row| {
+        Ok(DbMessage {
+            id: row.get(0)?,
+            conversation_id: row.get(1)?,
+            role: row.get(2)?,
+            content: row.get(3)?,
+            tool_calls: row.get(4)?,
+            timestamp: parse_db_timestamp(&row.get::<_, String>(5)?),
+            tokens: row.get(6)?,
+        })
+    })?.collect::<rusqlite::Result<Vec<_>>>()?;
+    messages.sort_by_key(|message| message.timestamp);
+    Ok(messages)
+}
+
+/// The conversation's messages and the position of `message_id` among them
+fn locate_message(conn: &Connection, message_id: &str) -> Result<(Vec<DbMessage>, usize)> {
+    let conversation_id = message_conversation(conn, message_id)?;
+    let messages = ordered_messages(conn, &conversation_id)?;
+    let position = messages.iter().position(|m| m.id == message_id)
+        .ok_or_else(|| anyhow!("Message not found: {}", message_id))?;
+    Ok((messages, position))
+}
+
+fn archive(tx: &Transaction, message: &DbMessage, reason: RevisionReason) -> Result<()> {
+    tx.execute(
+        "INSERT INTO message_revisions
+         (id, message_id, conversation_id, role, content, tool_calls, tokens, message_timestamp, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            message.id,
+            message.conversation_id,
+            message.role,
+            message.content,
+            message.tool_calls,
+            message.tokens,
+            message.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            reason.as_str(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Archive and delete `messages`, returning their ids
+fn remove(tx: &Transaction, messages: &[DbMessage], reason: RevisionReason) -> Result<Vec<String>> {
+    for message in messages {
+        archive(tx, message, reason)?;
+        tx.execute("DELETE FROM messages WHERE id = ?1", params![message.id])?;
+    }
+    Ok(messages.iter().map(|m| m.id.clone()).collect())
+}
+
+/// Recompute the conversation's token count from its remaining messages
+fn recount_tokens(tx: &Transaction, conversation_id: &str) -> Result<i32> {
+    tx.execute(
+        "UPDATE conversations
+         SET token_count = (SELECT COALESCE(SUM(tokens), 0) FROM messages WHERE conversation_id = ?1),
+             updated_at = ?2
+         WHERE id = ?1",
+        params![conversation_id, now_timestamp()],
+    )?;
+    Ok(tx.query_row(
+        "SELECT token_count FROM conversations WHERE id = ?1",
+        params![conversation_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Replace a message's content and drop everything after it, archiving all of it first
+pub fn edit_message_record(conn: &mut Connection, message_id: &str, new_content: &str) -> Result<ConversationRewrite> {
+    if new_content.trim().is_empty() {
+        bail!("Message content cannot be empty");
+    }
+    let (messages, position) = locate_message(conn, message_id)?;
+    let original = &messages[position];
+    let edited = DbMessage {
+        content: new_content.to_string(),
+        tokens: Some(estimate_tokens(new_content) as i32),
+        ..original.clone()
+    };
+
+    let tx = conn.transaction()?;
+    archive(&tx, original, RevisionReason::Edited)?;
+    tx.execute(
+        "UPDATE messages SET content = ?1, tokens = ?2 WHERE id = ?3",
+        params![edited.content, edited.tokens, message_id],
+    )?;
+    let removed_message_ids = remove(&tx, &messages[position + 1..], RevisionReason::Truncated)?;
+    let token_count = recount_tokens(&tx, &edited.conversation_id)?;
+    tx.commit()?;
+
+    Ok(ConversationRewrite {
+        conversation_id: edited.conversation_id.clone(),
+        edited: Some(edited),
+        reply: None,
+        removed_message_ids,
+        token_count,
+    })
+}
+
+/// Where regeneration from `message_id` starts: the history the agent sees and the messages its
+/// reply replaces. From a user message the reply follows it; from an assistant message the reply
+/// replaces it.
+pub fn regeneration_point(conn: &Connection, message_id: &str) -> Result<(Vec<DbMessage>, Vec<DbMessage>)> {
+    let (mut messages, position) = locate_message(conn, message_id)?;
+    let split = match messages[position].role.as_str() {
+        "user" => position + 1,
+        "assistant" => position,
+        other => bail!("Cannot regenerate from a {} message", other),
+    };
+    let replaced = messages.split_off(split);
+    Ok((messages, replaced))
+}
+
+/// Archive and remove `replaced`, then append the agent's reply
+pub fn store_regenerated_reply(
+    conn: &mut Connection,
+    conversation_id: &str,
+    replaced: &[DbMessage],
+    reply: &str,
+) -> Result<ConversationRewrite> {
+    let tx = conn.transaction()?;
+    let mut removed_message_ids = Vec::new();
+    for message in replaced {
+        // The first replaced assistant message is the reply being regenerated; the rest are cut
+        let reason = if removed_message_ids.is_empty() && message.role == "assistant" {
+            RevisionReason::Regenerated
+        } else {
+            RevisionReason::Truncated
+        };
+        removed_message_ids.extend(remove(&tx, std::slice::from_ref(message), reason)?);
+    }
+
+    let timestamp = now_timestamp();
+    let message = DbMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        conversation_id: conversation_id.to_string(),
+        role: "assistant".to_string(),
+        content: reply.to_string(),
+        tool_calls: None,
+        timestamp: parse_db_timestamp(&timestamp),
+        tokens: Some(estimate_tokens(reply) as i32),
+    };
+    tx.execute(
+        "INSERT INTO messages (id, conversation_id, role, content, tool_calls, tokens, timestamp)
+         VALUES (?1, ?2, ?3, ?4, NULL, ?5, ?6)",
+        params![message.id, message.conversation_id, message.role, message.content, message.tokens, timestamp],
+    )?;
+    let token_count = recount_tokens(&tx, conversation_id)?;
+    tx.commit()?;
+
+    Ok(ConversationRewrite {
+        conversation_id: conversation_id.to_string(),
+        edited: None,
+        reply: Some(message),
+        removed_message_ids,
+        token_count,
+    })
+}
+
+/// Have the conversation's agent reply again from `message_id`
+async fn regenerate(app_handle: &AppHandle, state: &AIState, message_id: &str) -> Result<ConversationRewrite> {
+    let (conversation_id, turns, replaced, agent_id, agent, provider) = {
+        let conn = open_conversation_db(app_handle)?;
+        let (history, replaced) = regeneration_point(&conn, message_id)?;
+        let conversation_id = message_conversation(&conn, message_id)?;
+        let agent_id: String = conn.query_row(
+            "SELECT agent_id FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| row.get(0),
+        ).optional()?
+            .ok_or_else(|| anyhow!("Conversation not found: {}", conversation_id))?;
+        let agent = load_agent(&conn, &agent_id)?;
+        let provider = select_completion_provider(state, configured_provider(&conn, &agent_id)?.as_deref())
+            .with_context(|| format!("No provider for agent {}", agent_id))?;
+        // Images attached to user messages go back to the model with them
+        let store = artifact_store_dir(app_handle)?;
+        let turns = history.iter()
+            .map(|m| {
+                let images = if m.role == "user" { message_images(&conn, &store, &m.id)? } else { Vec::new() };
+                Ok(ChatTurn { images, ..ChatTurn::new(&m.role, &m.content) })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        (conversation_id, turns, replaced, agent_id, agent, provider)
+    };
+
+    let turns = normalize_turns(&turns);
+    if turns.last().is_none_or(|turn| turn.role != "user") {
+        bail!("There is no user message to reply to");
+    }
+
+    let system = agent.system_prompt.as_deref().unwrap_or_default();
+    let mut guard = GuardrailScope::new(app_handle, &state.storage, Some(&agent_id));
+    let reply = complete_conversation(state, &mut guard, &provider, Some(&agent.model), system, &turns).await?;
+
+    store_regenerated_reply(&mut open_conversation_db(app_handle)?, &conversation_id, &replaced, &reply)
+}
+
+// Tauri Commands
+
+/// Replace a message's content, archiving it and removing every later message. With
+/// `regenerate`, the agent then replies to the edited user message.
+#[tauri::command]
+pub async fn edit_message(
+    message_id: String,
+    new_content: String,
+    regenerate: Option<bool>,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<ConversationRewrite, String> {
+    let mut conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    let mut rewrite = edit_message_record(&mut conn, &message_id, &new_content)
+        .map_err(|e| format!("Failed to edit message: {}", e))?;
+    info!("Edited message {}, removing {} later messages", message_id, rewrite.removed_message_ids.len());
+
+    let is_user_message = rewrite.edited.as_ref().is_some_and(|m| m.role == "user");
+    if regenerate.unwrap_or(false) && is_user_message {
+        let regenerated = self::regenerate(&app_handle, &state, &message_id).await
+            .map_err(|e| format!("Edited message but failed to regenerate the reply: {:#}", e))?;
+        rewrite.reply = regenerated.reply;
+        rewrite.token_count = regenerated.token_count;
+    }
+    Ok(rewrite)
+}
+
+/// Have the agent reply again from a message: after a user message, or in place of an
+/// assistant reply. Replaced messages are archived.
+#[tauri::command]
+pub async fn regenerate_from(
+    message_id: String,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<ConversationRewrite, String> {
+    let rewrite = regenerate(&app_handle, &state, &message_id).await
+        .map_err(|e| format!("Failed to regenerate: {:#}", e))?;
+    info!("Regenerated from message {}, replacing {} messages", message_id, rewrite.removed_message_ids.len());
+    Ok(rewrite)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    fn conversation() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        conn.execute_batch(
+            "INSERT INTO conversations (id, agent_id, title, token_count) VALUES ('c1', 'coder', 'Chat', 40);
+             INSERT INTO messages (id, conversation_id, role, content, timestamp, tokens) VALUES
+                ('m1', 'c1', 'user', 'Write a parser', '2024-01-01T10:00:00.000Z', 10),
+                ('m2', 'c1', 'assistant', 'Here is a parser', '2024-01-01T10:00:05.000Z', 10),
+                ('m3', 'c1', 'user', 'Add tests', '2024-01-01 10:01:00', 10),
+                ('m4', 'c1', 'assistant', 'Here are tests', '2024-01-01T10:01:05.000Z', 10);",
+        ).unwrap();
+        conn
+    }
+
+    fn revisions(conn: &Connection) -> Vec<(String, String)> {
+        let mut stmt = conn.prepare("SELECT message_id, reason FROM message_revisions ORDER BY rowid").unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn test_edit_archives_and_truncates() {
+        let mut conn = conversation();
+        let rewrite = edit_message_record(&mut conn, "m1", "Write a JSON parser").unwrap();
+        assert_eq!(rewrite.removed_message_ids, vec!["m2", "m3", "m4"]);
+        assert_eq!(rewrite.token_count, estimate_tokens("Write a JSON parser") as i32);
+        assert_eq!(revisions(&conn)[0], ("m1".to_string(), "edited".to_string()));
+        assert_eq!(revisions(&conn).len(), 4);
+        assert_eq!(ordered_messages(&conn, "c1").unwrap().len(), 1);
+        assert!(edit_message_record(&mut conn, "m1", "  ").is_err());
+        assert!(edit_message_record(&mut conn, "missing", "x").is_err());
+    }
+
+    #[test]
+    fn test_regenerate_replaces_reply_and_later_messages() {
+        let mut conn = conversation();
+        let (history, replaced) = regeneration_point(&conn, "m2").unwrap();
+        assert_eq!(history.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m1"]);
+        assert_eq!(replaced.len(), 3);
+        let (history, replaced) = regeneration_point(&conn, "m3").unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(replaced.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["m4"]);
+
+        let (_, replaced) = regeneration_point(&conn, "m2").unwrap();
+        let rewrite = store_regenerated_reply(&mut conn, "c1", &replaced, "A better parser").unwrap();
+        assert_eq!(rewrite.token_count, 10 + estimate_tokens("A better parser") as i32);
+        let reasons: Vec<String> = revisions(&conn).into_iter().map(|(_, reason)| reason).collect();
+        assert_eq!(reasons, vec!["regenerated", "truncated", "truncated"]);
+        let messages = ordered_messages(&conn, "c1").unwrap();
+        assert_eq!(messages.last().unwrap().content, "A better parser");
+        assert_eq!(messages.len(), 2);
+    }
+}
@@ -0,0 +1,312 @@
+//! Versioned schema migrations for the conversation, agent memory and shared knowledge databases.
+//!
+//! Applied migrations are recorded in `schema_migrations` with a checksum of their SQL, so a
+//! migration must never be edited once released; schema changes are added as a new migration
+//! at the end of the relevant list.
+
+use super::schema::{AGENT_MEMORY_SCHEMA, AGENT_MEMORY_VIEWS, EMBEDDING_EVALUATIONS_SCHEMA, MEMORY_ANOMALIES_SCHEMA, MEMORY_FTS_TRIGGERS_SCHEMA, MEMORY_PINS_SCHEMA, MEMORY_QUOTA_SCHEMA, MEMORY_TIERS_SCHEMA};
+use super::simple_commands::MemoryState;
+use super::{AGENT_SESSIONS_SQL, ARTIFACTS_SQL, AGENT_TEMPLATES_SQL, INIT_SQL, MCP_TOOL_AUDIT_SQL, CONVERSATION_ORGANIZATION_SQL, MESSAGE_REPAIRS_FK_SQL, MESSAGE_REVISIONS_SQL, NOTIFICATIONS_SQL, PROMPT_TEMPLATES_SQL, TASK_QUEUE_SQL, TASK_SCHEDULES_SQL, WORKSPACES_SQL};
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::State;
+use tracing::info;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    /// Executed in order inside one transaction
+    pub statements: &'static [&'static str],
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        let mut hasher = Sha256::new();
+        for statement in self.statements {
+            hasher.update(statement.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Migrations for `banshee.db`, which holds conversations and agent configuration
+pub const CONVERSATION_MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "conversations", statements: &[INIT_SQL] },
+    Migration { version: 2, name: "mcp_tool_audit", statements: &[MCP_TOOL_AUDIT_SQL] },
+    Migration { version: 3, name: "agent_sessions", statements: &[AGENT_SESSIONS_SQL] },
+    Migration { version: 4, name: "agent_templates", statements: &[AGENT_TEMPLATES_SQL] },
+    Migration { version: 5, name: "task_queue", statements: &[TASK_QUEUE_SQL] },
+    Migration { version: 6, name: "workspaces", statements: &[WORKSPACES_SQL] },
+    Migration { version: 7, name: "message_revisions", statements: &[MESSAGE_REVISIONS_SQL] },
+    Migration { version: 8, name: "conversation_organization", statements: &[CONVERSATION_ORGANIZATION_SQL] },
+    Migration { version: 9, name: "artifacts", statements: &[ARTIFACTS_SQL] },
+    Migration { version: 10, name: "prompt_templates", statements: &[PROMPT_TEMPLATES_SQL] },
+    Migration { version: 11, name: "notifications", statements: &[NOTIFICATIONS_SQL] },
+    Migration { version: 12, name: "message_repairs_foreign_key", statements: &[MESSAGE_REPAIRS_FK_SQL] },
+    Migration { version: 13, name: "task_schedules", statements: &[TASK_SCHEDULES_SQL] },
+];
+
+/// Migrations for per-agent memory databases and the shared knowledge database
+pub const AGENT_MEMORY_MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "memory_and_knowledge_graph", statements: &[AGENT_MEMORY_SCHEMA, AGENT_MEMORY_VIEWS] },
+    Migration { version: 2, name: "memory_anomalies", statements: &[MEMORY_ANOMALIES_SCHEMA] },
+    Migration { version: 3, name: "embedding_evaluations", statements: &[EMBEDDING_EVALUATIONS_SCHEMA] },
+    Migration { version: 4, name: "memory_pins", statements: &[MEMORY_PINS_SCHEMA] },
+    Migration { version: 5, name: "memory_fts_triggers", statements: &[MEMORY_FTS_TRIGGERS_SCHEMA] },
+    Migration { version: 6, name: "memory_tiers", statements: &[MEMORY_TIERS_SCHEMA] },
+    Migration { version: 7, name: "memory_quota", statements: &[MEMORY_QUOTA_SCHEMA] },
+];
+
+const MIGRATIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    version INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+"#;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaStatus {
+    pub database: String,
+    pub current_version: i64,
+    pub latest_version: i64,
+    pub applied: Vec<AppliedMigration>,
+}
+
+pub fn latest_version(migrations: &[Migration]) -> i64 {
+    migrations.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Highest applied migration, falling back to `PRAGMA user_version` for databases
+/// created before migrations were tracked
+pub fn get_schema_version(conn: &Connection) -> Result<i64> {
+    let tracked: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations')",
+        [],
+        |row| row.get(0),
+    )?;
+    if tracked {
+        let version: Option<i64> = conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))?;
+        if let Some(version) = version {
+            return Ok(version);
+        }
+    }
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+pub fn applied_migrations(conn: &Connection) -> Result<Vec<AppliedMigration>> {
+    conn.execute_batch(MIGRATIONS_TABLE)?;
+    let mut stmt = conn.prepare("SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(AppliedMigration {
+            version: row.get(0)?,
+            name: row.get(1)?,
+            checksum: row.get(2)?,
+            applied_at: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Bring a database up to the latest migration, returning its resulting version.
+///
+/// Refuses databases written by a newer build and migrations whose SQL changed after
+/// being applied, rather than running against a schema it doesn't understand.
+pub fn migrate(conn: &Connection, migrations: &[Migration]) -> Result<i64> {
+    debug_assert!(migrations.windows(2).all(|w| w[0].version < w[1].version));
+    let latest = latest_version(migrations);
+
+    let user_version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if user_version > latest {
+        bail!("Database schema version {} is newer than this build supports ({}); refusing to downgrade", user_version, latest);
+    }
+
+    let applied = applied_migrations(conn)?;
+    for applied in &applied {
+        let known = migrations.iter().find(|m| m.version == applied.version).ok_or_else(|| anyhow!(
+            "Database has migration {} ({}) which this build does not know; refusing to downgrade",
+            applied.version, applied.name
+        ))?;
+        if known.checksum() != applied.checksum {
+            bail!("Migration {} ({}) was modified after being applied", applied.version, applied.name);
+        }
+    }
+
+    for migration in migrations.iter().filter(|m| !applied.iter().any(|a| a.version == m.version)) {
+        // Immediate so concurrent openers wait here instead of applying the same migration twice
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+        let done: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM schema_migrations WHERE version = ?1)",
+            params![migration.version],
+            |row| row.get(0),
+        )?;
+        if done {
+            continue;
+        }
+        for statement in migration.statements {
+            tx.execute_batch(statement)
+                .map_err(|e| anyhow!("Migration {} ({}) failed: {}", migration.version, migration.name, e))?;
+        }
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, checksum) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, migration.checksum()],
+        )?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        info!("Applied schema migration {} ({})", migration.version, migration.name);
+    }
+
+    get_schema_version(conn)
+}
+
+/// Database files migrated by this process; later opens skip the checks and write transactions
+static MIGRATED: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// `migrate` the database at `path` on its first open in this process. The lock is held while
+/// migrating so concurrent first opens wait for it; a failed migration is retried next open.
+pub fn migrate_once(conn: &Connection, path: &Path, migrations: &[Migration]) -> Result<()> {
+    let mut migrated = MIGRATED.lock().map_err(|_| anyhow!("Migration registry poisoned"))?;
+    if migrated.contains(path) {
+        return Ok(());
+    }
+    migrate(conn, migrations)?;
+    migrated.insert(path.to_path_buf());
+    Ok(())
+}
+
+fn schema_status(database: &str, conn: &Connection, migrations: &[Migration]) -> Result<SchemaStatus> {
+    Ok(SchemaStatus {
+        database: database.to_string(),
+        current_version: get_schema_version(conn)?,
+        latest_version: latest_version(migrations),
+        applied: applied_migrations(conn)?,
+    })
+}
+
+/// Schema versions of the conversation and shared knowledge databases, plus the
+/// agent's memory database when one is given
+#[tauri::command]
+pub async fn get_database_schema_status(
+    agent_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, MemoryState>,
+) -> Result<Vec<SchemaStatus>, String> {
+    let conversations = super::open_conversation_db(&app_handle).map_err(|e| e.to_string())?;
+    let shared = super::shared_knowledge::open_store().map_err(|e| e.to_string())?;
+    let mut statuses = vec![
+        schema_status("conversations", &conversations, CONVERSATION_MIGRATIONS).map_err(|e| e.to_string())?,
+        schema_status("shared_knowledge", &shared, AGENT_MEMORY_MIGRATIONS).map_err(|e| e.to_string())?,
+    ];
+
+    if let Some(agent_id) = agent_id {
+        crate::validation::MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+        let validation_result = state.get_security_middleware().validate_request(
+            "memory_operations",
+            &[agent_id],
+            &[]
+        ).await?;
+        let manager = state.get_or_create_manager(validation_result.sanitized_inputs[0].clone())?;
+        let conn = manager.agent_connection().map_err(|e| e.to_string())?;
+        statuses.push(schema_status("agent_memory", &conn, AGENT_MEMORY_MIGRATIONS).map_err(|e| e.to_string())?);
+    }
+
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::AGENT_MEMORY_SCHEMA_VERSION;
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration { version: 1, name: "notes", statements: &["CREATE TABLE notes (id TEXT PRIMARY KEY);"] },
+        Migration { version: 2, name: "note_body", statements: &["ALTER TABLE notes ADD COLUMN body TEXT;"] },
+    ];
+
+    #[test]
+    fn test_migrations_apply_once_and_refuse_downgrade() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate(&conn, &TEST_MIGRATIONS[..1]).unwrap(), 1);
+        assert_eq!(migrate(&conn, TEST_MIGRATIONS).unwrap(), 2);
+        // Re-running is a no-op rather than a duplicate ALTER TABLE
+        assert_eq!(migrate(&conn, TEST_MIGRATIONS).unwrap(), 2);
+        conn.execute("INSERT INTO notes (id, body) VALUES ('a', 'b')", []).unwrap();
+        assert_eq!(applied_migrations(&conn).unwrap().len(), 2);
+
+        let err = migrate(&conn, &TEST_MIGRATIONS[..1]).unwrap_err().to_string();
+        assert!(err.contains("newer than this build"), "{}", err);
+
+        let edited = [
+            Migration { version: 1, name: "notes", statements: &["CREATE TABLE notes (id INTEGER);"] },
+            Migration { version: 2, name: "note_body", statements: TEST_MIGRATIONS[1].statements },
+        ];
+        let err = migrate(&conn, &edited).unwrap_err().to_string();
+        assert!(err.contains("modified after being applied"), "{}", err);
+    }
+
+    #[test]
+    fn test_real_schemas_migrate_existing_databases() {
+        // Databases created before migrations already have the tables; the baseline must be idempotent
+        assert_eq!(latest_version(AGENT_MEMORY_MIGRATIONS), AGENT_MEMORY_SCHEMA_VERSION);
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(AGENT_MEMORY_SCHEMA).unwrap();
+        assert_eq!(migrate(&conn, AGENT_MEMORY_MIGRATIONS).unwrap(), AGENT_MEMORY_SCHEMA_VERSION);
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(INIT_SQL).unwrap();
+        assert_eq!(migrate(&conn, CONVERSATION_MIGRATIONS).unwrap(), latest_version(CONVERSATION_MIGRATIONS));
+    }
+
+    #[test]
+    fn test_message_repairs_cascade_with_their_message() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, &CONVERSATION_MIGRATIONS[..11]).unwrap();
+        conn.execute_batch(r#"
+            INSERT INTO conversations (id, agent_id, title) VALUES ('c1', 'a', 't');
+            INSERT INTO messages (id, conversation_id, role, content) VALUES ('m1', 'c1', 'assistant', 'x');
+            INSERT INTO message_repairs (id, message_id, attempt, valid, issues) VALUES
+                ('r1', 'm1', 1, 0, '[]'), ('orphan', 'gone', 1, 0, '[]');
+        "#).unwrap();
+
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        let ids: Vec<String> = conn.prepare("SELECT id FROM message_repairs").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(ids, vec!["r1"]);
+
+        conn.execute_batch("PRAGMA foreign_keys = ON; DELETE FROM messages WHERE id = 'm1';").unwrap();
+        let remaining: i64 = conn.query_row("SELECT COUNT(*) FROM message_repairs", [], |row| row.get(0)).unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn test_migrate_once_only_checks_the_first_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.db");
+
+        let conn = Connection::open(&path).unwrap();
+        migrate_once(&conn, &path, TEST_MIGRATIONS).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), 2);
+        conn.execute("UPDATE schema_migrations SET checksum = 'edited' WHERE version = 1", []).unwrap();
+
+        // A later open in the same process is a plain open
+        let conn = Connection::open(&path).unwrap();
+        migrate_once(&conn, &path, TEST_MIGRATIONS).unwrap();
+        assert!(migrate(&conn, TEST_MIGRATIONS).is_err());
+    }
+}
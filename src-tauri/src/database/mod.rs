@@ -6,13 +6,59 @@ use anyhow::Result;
 pub mod memory;
 pub mod schema;
 pub mod simple_memory;
+pub mod pool;
 pub mod embeddings;
 pub mod neural_network;
+pub mod accelerated_inference;
 pub mod neural_embeddings;
+pub mod embedding_cache;
+pub mod embedding_providers;
+pub mod embedding_training;
+pub mod embedding_evaluation;
 pub mod memory_sequence_models;
+pub mod memory_patterns;
+pub mod memory_anomalies;
 pub mod neural_knowledge_graph;
 pub mod simple_commands;
+pub mod memory_budget;
+pub mod memory_search;
+pub mod memory_dedup;
+pub mod memory_encryption;
+pub mod memory_quota;
+pub mod memory_tiers;
+pub mod memory_browse;
+pub mod data_purge;
+pub mod conversation_list;
+pub mod conversation_export;
+pub mod artifacts;
+pub mod conversation_merge;
+pub mod message_revisions;
+pub mod agent_config_history;
+pub mod agent_definitions;
+pub mod agent_templates;
+pub mod prompt_templates;
+pub mod notification_center;
+pub mod agent_sessions;
+pub mod run_traces;
+pub mod task_queue;
+pub mod workspaces;
 pub mod graph_commands;
+pub mod graph_paths;
+pub mod graph_query;
+pub mod graph_optimize;
+pub mod graph_history;
+pub mod graph_export;
+pub mod graph_batch;
+pub mod graph_predictions;
+pub mod shared_knowledge;
+pub mod memory_sharing;
+pub mod memory_transfer;
+pub mod vault_export;
+pub mod backup_restore;
+pub mod migrations;
+pub mod startup_health;
+pub mod embedding_projection;
+pub mod embedding_quantization;
 pub mod embedding_migration;
 
 // #[cfg(test)]
@@ -34,8 +80,8 @@ pub use simple_memory::SimpleMemoryManager;
 // and replaced with NeuralEmbeddingService
 pub use neural_network::{NeuralNetwork, NetworkBuilder, ActivationFunction, TrainingData};
 pub use neural_embeddings::{NeuralEmbeddingService, EmbeddingConfig, EmbeddingStats, NeuralEmbeddingResult, NeuralEmbeddingSearchResult, NeuralEmbeddingRequest, NeuralEmbeddingCandidate};
-pub use memory_sequence_models::{MemorySequenceModel, MemorySequenceAnalyzer, SequenceModelType, MemoryPatternAnalysis, LSTMCell, GRUCell};
-pub use neural_knowledge_graph::{NeuralKnowledgeGraph, NeuralGraphConfig, NeuralGraphStatistics, NeuralRelationshipType};
+pub use memory_sequence_models::{MemorySequenceModel, MemorySequenceAnalyzer, SequenceModelType, MemoryPatternAnalysis, LSTMCell, GRUCell, TransformerEncoder};
+pub use neural_knowledge_graph::{NeuralKnowledgeGraph, NeuralGraphConfig, NeuralGraphStatistics, NeuralRelationshipType, EdgeSuggestion, RelationshipPrediction, RelationshipFeedbackStats};
 pub use embedding_migration::*;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -47,9 +93,15 @@ pub struct DbConversation {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub token_count: i32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DbMessage {
     pub id: String,
     pub conversation_id: String,
@@ -103,6 +155,17 @@ CREATE TABLE IF NOT EXISTS messages (
     FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
 );
 
+-- Response validation and repair attempts per assistant message
+CREATE TABLE IF NOT EXISTS message_repairs (
+    id TEXT PRIMARY KEY,
+    message_id TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    valid INTEGER NOT NULL,
+    issues TEXT NOT NULL,
+    repair_prompt TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
 -- Agent settings table
 CREATE TABLE IF NOT EXISTS agent_settings (
     id TEXT PRIMARY KEY,
@@ -112,6 +175,46 @@ CREATE TABLE IF NOT EXISTS agent_settings (
     updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
 );
 
+-- Every configuration an agent has had, including its system prompt
+CREATE TABLE IF NOT EXISTS agent_config_versions (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    configuration TEXT NOT NULL,
+    author TEXT NOT NULL DEFAULT 'app',
+    note TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(agent_id, version)
+);
+
+-- Agent run traces: everything needed to replay a run
+CREATE TABLE IF NOT EXISTS agent_runs (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    replay_of TEXT,
+    input TEXT NOT NULL,
+    output TEXT,
+    error TEXT,
+    status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed')),
+    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    finished_at DATETIME
+);
+
+CREATE TABLE IF NOT EXISTS agent_run_tool_calls (
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    sequence INTEGER NOT NULL,
+    tool_name TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    result TEXT,
+    error TEXT,
+    duration_ms INTEGER,
+    replayed_from INTEGER,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(run_id, sequence),
+    FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+);
+
 -- MCP sessions table
 CREATE TABLE IF NOT EXISTS mcp_sessions (
     id TEXT PRIMARY KEY,
@@ -127,6 +230,9 @@ CREATE INDEX IF NOT EXISTS idx_messages_timestamp ON messages(timestamp);
 CREATE INDEX IF NOT EXISTS idx_conversations_agent_id ON conversations(agent_id);
 CREATE INDEX IF NOT EXISTS idx_conversations_updated_at ON conversations(updated_at);
 CREATE INDEX IF NOT EXISTS idx_mcp_sessions_server_id ON mcp_sessions(server_id);
+CREATE INDEX IF NOT EXISTS idx_message_repairs_message_id ON message_repairs(message_id);
+CREATE INDEX IF NOT EXISTS idx_agent_config_versions_agent_id ON agent_config_versions(agent_id, version);
+CREATE INDEX IF NOT EXISTS idx_agent_runs_agent_id ON agent_runs(agent_id, started_at);
 
 -- Triggers to update timestamps
 CREATE TRIGGER IF NOT EXISTS update_conversations_timestamp 
@@ -140,8 +246,294 @@ AFTER UPDATE ON agent_settings
 BEGIN
     UPDATE agent_settings SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
 END;
+
+-- Version agent settings written by the frontend; changes already recorded with an author are skipped
+CREATE TRIGGER IF NOT EXISTS version_agent_settings_insert
+AFTER INSERT ON agent_settings
+WHEN NOT EXISTS (
+    SELECT 1 FROM agent_config_versions
+    WHERE agent_id = NEW.agent_id AND configuration = NEW.configuration
+      AND version = (SELECT MAX(version) FROM agent_config_versions WHERE agent_id = NEW.agent_id)
+)
+BEGIN
+    INSERT INTO agent_config_versions (id, agent_id, version, configuration)
+    VALUES (
+        lower(hex(randomblob(16))),
+        NEW.agent_id,
+        COALESCE((SELECT MAX(version) FROM agent_config_versions WHERE agent_id = NEW.agent_id), 0) + 1,
+        NEW.configuration
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS version_agent_settings_update
+AFTER UPDATE OF configuration ON agent_settings
+WHEN NOT EXISTS (
+    SELECT 1 FROM agent_config_versions
+    WHERE agent_id = NEW.agent_id AND configuration = NEW.configuration
+      AND version = (SELECT MAX(version) FROM agent_config_versions WHERE agent_id = NEW.agent_id)
+)
+BEGIN
+    INSERT INTO agent_config_versions (id, agent_id, version, configuration)
+    VALUES (
+        lower(hex(randomblob(16))),
+        NEW.agent_id,
+        COALESCE((SELECT MAX(version) FROM agent_config_versions WHERE agent_id = NEW.agent_id), 0) + 1,
+        NEW.configuration
+    );
+END;
+"#;
+
+pub const MCP_TOOL_AUDIT_SQL: &str = r#"
+-- Every MCP tool call, with sensitive arguments redacted
+CREATE TABLE IF NOT EXISTS mcp_tool_calls (
+    id TEXT PRIMARY KEY,
+    server_id TEXT NOT NULL,
+    tool_name TEXT NOT NULL,
+    run_id TEXT,
+    replay_of TEXT,
+    arguments TEXT NOT NULL,
+    redacted_fields TEXT NOT NULL DEFAULT '[]',
+    arguments_hash TEXT NOT NULL,
+    duration_ms INTEGER,
+    result_size INTEGER,
+    success INTEGER NOT NULL,
+    error TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_mcp_tool_calls_server ON mcp_tool_calls(server_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_mcp_tool_calls_run ON mcp_tool_calls(run_id);
 "#;
 
+pub const AGENT_SESSIONS_SQL: &str = r#"
+-- Agent sessions opened from the dashboard, each linked to the conversation it writes to
+CREATE TABLE IF NOT EXISTS agent_sessions (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    conversation_id TEXT,
+    status TEXT NOT NULL DEFAULT 'active' CHECK(status IN ('active', 'closed')),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    closed_at DATETIME,
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE SET NULL
+);
+
+-- At most one active session per agent
+CREATE UNIQUE INDEX IF NOT EXISTS idx_agent_sessions_active ON agent_sessions(agent_id) WHERE status = 'active';
+CREATE INDEX IF NOT EXISTS idx_agent_sessions_conversation ON agent_sessions(conversation_id);
+"#;
+
+pub const AGENT_TEMPLATES_SQL: &str = r#"
+-- User-defined agent templates; built-in templates live in code
+CREATE TABLE IF NOT EXISTS agent_templates (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    configuration TEXT NOT NULL,
+    permissions TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+"#;
+
+pub const TASK_QUEUE_SQL: &str = r#"
+-- Background tool and LLM jobs; failed jobs are retried with backoff, then dead-lettered
+CREATE TABLE IF NOT EXISTS task_queue (
+    id TEXT PRIMARY KEY,
+    job TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'queued' CHECK(status IN ('queued', 'running', 'completed', 'dead', 'cancelled')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 3,
+    run_after INTEGER NOT NULL,
+    last_error TEXT,
+    result TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_queue_ready ON task_queue(status, priority DESC, run_after);
+"#;
+
+pub const WORKSPACES_SQL: &str = r#"
+-- Project workspaces; the default workspace lives in code
+CREATE TABLE IF NOT EXISTS workspaces (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    root_path TEXT NOT NULL,
+    allowed_tools TEXT NOT NULL DEFAULT '[]',
+    default_agents TEXT NOT NULL DEFAULT '[]',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+"#;
+
+pub const MESSAGE_REVISIONS_SQL: &str = r#"
+-- Earlier versions of edited messages and messages removed by an edit or regeneration
+CREATE TABLE IF NOT EXISTS message_revisions (
+    id TEXT PRIMARY KEY,
+    message_id TEXT NOT NULL,
+    conversation_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    tool_calls TEXT,
+    tokens INTEGER,
+    message_timestamp TEXT NOT NULL,
+    reason TEXT NOT NULL CHECK(reason IN ('edited', 'truncated', 'regenerated')),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_revisions_message ON message_revisions(message_id);
+CREATE INDEX IF NOT EXISTS idx_message_revisions_conversation ON message_revisions(conversation_id);
+"#;
+
+pub const CONVERSATION_ORGANIZATION_SQL: &str = r#"
+-- Pinned conversations list first; archived ones are hidden from the default list
+ALTER TABLE conversations ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE conversations ADD COLUMN archived_at DATETIME;
+
+CREATE TABLE IF NOT EXISTS conversation_tags (
+    conversation_id TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (conversation_id, tag),
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversation_tags_tag ON conversation_tags(tag, conversation_id);
+CREATE INDEX IF NOT EXISTS idx_conversations_listing ON conversations(archived_at, pinned, updated_at);
+CREATE INDEX IF NOT EXISTS idx_conversations_agent_listing ON conversations(agent_id, archived_at, pinned, updated_at);
+"#;
+
+pub const ARTIFACTS_SQL: &str = r#"
+-- Files, images and code attached to conversations; content lives on disk keyed by its SHA-256
+CREATE TABLE IF NOT EXISTS artifacts (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    message_id TEXT,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK(kind IN ('file', 'image', 'code')),
+    mime_type TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_artifacts_conversation ON artifacts(conversation_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_artifacts_hash ON artifacts(hash);
+"#;
+
+pub const PROMPT_TEMPLATES_SQL: &str = r#"
+-- Reusable prompts with {{variable}} placeholders; every body change is a new version
+CREATE TABLE IF NOT EXISTS prompt_templates (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    current_version INTEGER NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS prompt_template_versions (
+    template_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    variables TEXT NOT NULL DEFAULT '[]',
+    note TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (template_id, version),
+    FOREIGN KEY (template_id) REFERENCES prompt_templates(id) ON DELETE CASCADE
+);
+"#;
+
+pub const NOTIFICATIONS_SQL: &str = r#"
+-- Notification center: what was shown, where it came from and whether it has been read
+CREATE TABLE IF NOT EXISTS notifications (
+    id TEXT PRIMARY KEY,
+    severity TEXT NOT NULL,
+    source TEXT NOT NULL,
+    title TEXT NOT NULL,
+    message TEXT NOT NULL,
+    actions TEXT NOT NULL DEFAULT '[]',
+    session TEXT,
+    payload TEXT,
+    read_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_created ON notifications(created_at);
+CREATE INDEX IF NOT EXISTS idx_notifications_unread ON notifications(read_at, created_at);
+"#;
+
+pub const MESSAGE_REPAIRS_FK_SQL: &str = r#"
+-- Repairs go with their message; SQLite cannot add a foreign key in place, so the table is
+-- rebuilt and repairs whose message is already gone are dropped
+CREATE TABLE message_repairs_new (
+    id TEXT PRIMARY KEY,
+    message_id TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    valid INTEGER NOT NULL,
+    issues TEXT NOT NULL,
+    repair_prompt TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE
+);
+
+INSERT INTO message_repairs_new (id, message_id, attempt, valid, issues, repair_prompt, created_at)
+SELECT id, message_id, attempt, valid, issues, repair_prompt, created_at FROM message_repairs
+WHERE message_id IN (SELECT id FROM messages);
+
+DROP TABLE message_repairs;
+ALTER TABLE message_repairs_new RENAME TO message_repairs;
+CREATE INDEX IF NOT EXISTS idx_message_repairs_message_id ON message_repairs(message_id);
+"#;
+
+pub const TASK_SCHEDULES_SQL: &str = r#"
+-- Jobs the task scheduler queues on a fixed interval
+CREATE TABLE IF NOT EXISTS task_schedules (
+    id TEXT PRIMARY KEY,
+    job TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0,
+    interval_secs INTEGER NOT NULL CHECK(interval_secs > 0),
+    next_run INTEGER NOT NULL,
+    last_task_id TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_schedules_next_run ON task_schedules(next_run);
+"#;
+
+/// Open the conversations database shared with the frontend SQL plugin
+pub fn open_conversation_db(app_handle: &tauri::AppHandle) -> Result<rusqlite::Connection> {
+    use tauri::Manager;
+
+    let config_dir = app_handle.path().app_config_dir()
+        .map_err(|e| anyhow::anyhow!("Could not resolve app config directory: {}", e))?;
+    std::fs::create_dir_all(&config_dir)?;
+
+    let path = config_dir.join("banshee.db");
+    let conn = rusqlite::Connection::open(&path)?;
+    migrations::migrate_once(&conn, &path, migrations::CONVERSATION_MIGRATIONS)?;
+    conn.execute("PRAGMA foreign_keys = ON;", [])?;
+    Ok(conn)
+}
+
+/// `open_conversation_db` for commands, with the error as a redacted message
+pub(crate) fn open_db(app_handle: &tauri::AppHandle) -> Result<rusqlite::Connection, String> {
+    use crate::ai::RedactErr;
+
+    open_conversation_db(app_handle).redact_err("Failed to open conversation database")
+}
+
+/// Parse timestamps written either by SQLite defaults or as RFC 3339 by the frontend
+pub fn parse_db_timestamp(value: &str) -> DateTime<Utc> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(value) {
+        return ts.with_timezone(&Utc);
+    }
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|naive| naive.and_utc())
+        .unwrap_or_else(|_| Utc::now())
+}
+
 // Database commands
 #[tauri::command]
 pub async fn init_database() -> Result<(), String> {
@@ -161,10 +553,18 @@ pub async fn save_message(message: DbMessage) -> Result<DbMessage, String> {
     Ok(message)
 }
 
+/// Conversations matching `filter`, pinned first; archived conversations are left out by default
 #[tauri::command]
-pub async fn get_conversations(agent_id: Option<String>, limit: Option<i32>) -> Result<Vec<DbConversation>, String> {
-    // Implementation will use tauri-plugin-sql from frontend
-    Ok(vec![])
+pub async fn get_conversations(
+    agent_id: Option<String>,
+    limit: Option<i32>,
+    filter: Option<conversation_list::ConversationFilter>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<DbConversation>, String> {
+    let conn = open_conversation_db(&app_handle)
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+    conversation_list::list_conversations(&conn, agent_id.as_deref(), limit, &filter.unwrap_or_default())
+        .map_err(|e| format!("Failed to list conversations: {}", e))
 }
 
 #[tauri::command]
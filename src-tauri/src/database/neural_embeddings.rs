@@ -1,23 +1,38 @@
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use sha2::{Sha256, Digest};
 use super::neural_network::{NeuralNetwork, NetworkBuilder, ActivationFunction, TrainingData};
 use super::memory::{MemoryType, AgentMemory};
+use super::embeddings::SimilarityMetric;
+use super::embedding_cache::EmbeddingCache;
+use super::embedding_providers::{build_provider, EmbeddingProvider, EmbeddingProviderConfig};
 use serde::{Serialize, Deserialize};
 
+/// Most texts sent to an external provider in one request; Cohere accepts up to 96
+const PROVIDER_BATCH_SIZE: usize = 96;
+
+/// Settings changed at runtime, saved next to the network weights
+const SETTINGS_FILE: &str = "embedding_settings.json";
+
 /// Neural embedding service that uses FANN-inspired neural networks
 /// to generate meaningful embeddings for different memory types
 pub struct NeuralEmbeddingService {
-    /// Cache for computed embeddings
-    cache: Arc<RwLock<HashMap<String, Vec<f32>>>>,
+    /// Least-recently-used cache for computed embeddings
+    cache: Arc<RwLock<EmbeddingCache>>,
     /// Neural networks specialized for different memory types
     memory_networks: HashMap<MemoryType, NeuralNetwork>,
     /// General purpose embedding network
     general_network: NeuralNetwork,
     /// Configuration for the service
     config: EmbeddingConfig,
+    /// External providers created so far, keyed by `EmbeddingProviderConfig::cache_id`
+    providers: Arc<RwLock<HashMap<String, Arc<dyn EmbeddingProvider>>>>,
+    /// Bumped whenever the networks' weights change, so background training can tell
+    /// whether its snapshot is still current
+    generation: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +42,27 @@ pub struct EmbeddingConfig {
     pub learning_rate: f32,
     pub training_epochs: usize,
     pub cache_size_limit: usize,
+    /// Metric the model's embeddings are meant to be compared with
+    #[serde(default)]
+    pub similarity_metric: SimilarityMetric,
+    /// Provider for agents without an override
+    #[serde(default)]
+    pub provider: EmbeddingProviderConfig,
+    /// Per-agent provider overrides, keyed by agent id
+    #[serde(default)]
+    pub agent_providers: HashMap<String, EmbeddingProviderConfig>,
+    /// Where network weights are saved and loaded, defaulting to the memory directory's `models`
+    #[serde(default)]
+    pub model_dir: Option<PathBuf>,
+}
+
+/// The parts of `EmbeddingConfig` that are changed at runtime and survive a restart
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct EmbeddingSettings {
+    #[serde(default)]
+    similarity_metric: Option<SimilarityMetric>,
+    #[serde(default)]
+    agent_providers: HashMap<String, EmbeddingProviderConfig>,
 }
 
 impl Default for EmbeddingConfig {
@@ -37,15 +73,80 @@ impl Default for EmbeddingConfig {
             learning_rate: 0.001,
             training_epochs: 100,
             cache_size_limit: 10000,
+            similarity_metric: SimilarityMetric::Cosine,
+            provider: EmbeddingProviderConfig::Neural,
+            agent_providers: HashMap::new(),
+            model_dir: None,
         }
     }
 }
 
 impl NeuralEmbeddingService {
-    /// Create a new neural embedding service
+    /// Create a new neural embedding service, loading previously saved weights when they
+    /// match the configured architecture
     pub async fn new(config: Option<EmbeddingConfig>) -> Result<Self> {
+        let configured = config.is_some();
         let config = config.unwrap_or_default();
-        
+        let (general_network, memory_networks) = Self::build_networks(&config)?;
+
+        let mut service = Self {
+            cache: Arc::new(RwLock::new(EmbeddingCache::new(config.cache_size_limit))),
+            memory_networks,
+            general_network,
+            config,
+            providers: Arc::new(RwLock::new(HashMap::new())),
+            generation: 0,
+        };
+        service.load_settings(configured);
+        let loaded = service.load_models();
+        if loaded > 0 {
+            tracing::info!("Loaded {} saved embedding networks", loaded);
+        }
+        Ok(service)
+    }
+
+    /// Take saved per-agent providers for agents the given config doesn't set itself, and
+    /// the saved similarity metric unless the service was created with an explicit config
+    fn load_settings(&mut self, configured: bool) {
+        let path = match self.model_dir() {
+            Ok(dir) => dir.join(SETTINGS_FILE),
+            Err(_) => return,
+        };
+        let settings: EmbeddingSettings = match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    tracing::warn!("Ignoring unreadable embedding settings {}: {}", path.display(), e);
+                    return;
+                }
+            },
+            Err(_) => return,
+        };
+        if let Some(metric) = settings.similarity_metric.filter(|_| !configured) {
+            self.config.similarity_metric = metric;
+        }
+        for (agent_id, provider) in settings.agent_providers {
+            self.config.agent_providers.entry(agent_id).or_insert(provider);
+        }
+    }
+
+    /// Save the runtime settings so they apply again after a restart
+    pub fn save_settings(&self) -> Result<()> {
+        let dir = self.model_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let settings = EmbeddingSettings {
+            similarity_metric: Some(self.config.similarity_metric),
+            agent_providers: self.config.agent_providers.clone(),
+        };
+        let path = dir.join(SETTINGS_FILE);
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_vec_pretty(&settings)?)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    /// Freshly initialized general and per-memory-type networks
+    fn build_networks(config: &EmbeddingConfig) -> Result<(NeuralNetwork, HashMap<MemoryType, NeuralNetwork>)> {
         // Create general purpose embedding network
         let general_network = NetworkBuilder::new()
             .input_layer(config.max_text_length) // Text feature input
@@ -98,12 +199,122 @@ impl NeuralEmbeddingService {
             .build()?;
         memory_networks.insert(MemoryType::Pattern, pattern_network);
 
-        Ok(Self {
-            cache: Arc::new(RwLock::new(HashMap::new())),
-            memory_networks,
-            general_network,
-            config,
-        })
+        Ok((general_network, memory_networks))
+    }
+
+    fn model_dir(&self) -> Result<PathBuf> {
+        match &self.config.model_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => super::simple_memory::SimpleMemoryManager::default_model_directory(),
+        }
+    }
+
+    /// Saved weight files, with the memory type each belongs to (`None` for the general network)
+    fn model_paths(&self) -> Result<Vec<(Option<MemoryType>, PathBuf)>> {
+        let dir = self.model_dir()?;
+        let mut paths = vec![(None, dir.join("embedding_general.bin"))];
+        for memory_type in self.memory_networks.keys() {
+            let file_name = format!("embedding_{}.bin", memory_type).to_lowercase();
+            paths.push((Some(memory_type.clone()), dir.join(file_name)));
+        }
+        Ok(paths)
+    }
+
+    /// Save every network's weights so embeddings stay stable across restarts
+    pub fn save_models(&self) -> Result<()> {
+        for (memory_type, path) in self.model_paths()? {
+            let network = match &memory_type {
+                Some(memory_type) => &self.memory_networks[memory_type],
+                None => &self.general_network,
+            };
+            network.save_model(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Replace networks with their saved weights, returning how many were loaded. Missing
+    /// files are skipped; unreadable or mismatched ones are logged and left untouched.
+    pub fn load_models(&mut self) -> usize {
+        let paths = match self.model_paths() {
+            Ok(paths) => paths,
+            Err(e) => {
+                tracing::warn!("No directory for saved embedding networks: {}", e);
+                return 0;
+            }
+        };
+
+        let mut loaded = 0;
+        for (memory_type, path) in paths {
+            let network = match &memory_type {
+                Some(memory_type) => self.memory_networks.get_mut(memory_type),
+                None => Some(&mut self.general_network),
+            };
+            match network.map(|network| network.load_saved(&path)) {
+                Some(Ok(true)) => loaded += 1,
+                Some(Err(e)) => tracing::warn!("Ignoring saved embedding network: {}", e),
+                _ => {}
+            }
+        }
+        if loaded > 0 {
+            self.generation += 1;
+        }
+        loaded
+    }
+
+    /// Discard trained weights, both in memory and on disk, and start from fresh networks.
+    /// Embeddings stored before the reset no longer match and should be re-embedded.
+    pub async fn reset_models(&mut self) -> Result<()> {
+        for (_, path) in self.model_paths()? {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        let (general_network, memory_networks) = Self::build_networks(&self.config)?;
+        self.general_network = general_network;
+        self.memory_networks = memory_networks;
+        self.generation += 1;
+        self.clear_cache().await;
+        self.save_models()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Copy of the networks to train without holding this service, limited to `epochs`
+    /// per training call. It has its own cache and uses only the built-in networks.
+    pub fn training_snapshot(&self, epochs: usize) -> Self {
+        Self {
+            cache: Arc::new(RwLock::new(EmbeddingCache::new(self.config.cache_size_limit))),
+            memory_networks: self.memory_networks.clone(),
+            general_network: self.general_network.clone(),
+            config: EmbeddingConfig { training_epochs: epochs, ..self.config.clone() },
+            providers: Arc::new(RwLock::new(HashMap::new())),
+            generation: self.generation,
+        }
+    }
+
+    /// Take the networks of a trained snapshot, dropping embeddings made with the old ones
+    pub async fn adopt_networks(&mut self, trained: NeuralEmbeddingService) {
+        self.general_network = trained.general_network;
+        self.memory_networks = trained.memory_networks;
+        self.generation += 1;
+        self.clear_cache().await;
+    }
+
+    /// Drop every cached embedding, e.g. after the networks' weights changed
+    pub async fn clear_cache(&self) {
+        self.cache.write().await.clear();
+    }
+
+    /// Drop every cached embedding and zero the hit, miss and eviction counters; returns
+    /// how many embeddings were cached
+    pub async fn reset_cache(&self) -> usize {
+        let mut cache = self.cache.write().await;
+        let cleared = cache.len();
+        cache.clear();
+        cache.reset_counters();
+        cleared
     }
 
     /// Generate embedding for text using appropriate neural network
@@ -111,11 +322,8 @@ impl NeuralEmbeddingService {
         // Check cache first
         let cache_key = self.generate_cache_key(text, &memory_type);
         
-        {
-            let cache = self.cache.read().await;
-            if let Some(cached_embedding) = cache.get(&cache_key) {
-                return Ok(cached_embedding.clone());
-            }
+        if let Some(cached_embedding) = self.cache.write().await.get(&cache_key) {
+            return Ok(cached_embedding);
         }
 
         // Preprocess text into neural network input
@@ -135,39 +343,172 @@ impl NeuralEmbeddingService {
         // Normalize the embedding
         let normalized_embedding = self.normalize_embedding(&embedding);
 
-        // Cache the result (with size limit)
-        {
-            let mut cache = self.cache.write().await;
-            if cache.len() >= self.config.cache_size_limit {
-                // Remove oldest entries (simple LRU would be better, but this works)
-                cache.clear();
-            }
-            cache.insert(cache_key, normalized_embedding.clone());
-        }
+        self.cache.write().await.insert(cache_key, normalized_embedding.clone());
 
         Ok(normalized_embedding)
     }
 
-    /// Generate embeddings for multiple texts
+    /// Generate embeddings for several texts, running each network once over all of its
+    /// uncached inputs
     pub async fn embed_batch(
         &self, 
         texts: &[(String, Option<MemoryType>)]
     ) -> Result<Vec<Vec<f32>>> {
-        let mut embeddings = Vec::new();
-        
-        for (text, memory_type) in texts {
-            let embedding = self.embed_text(text, memory_type.clone()).await?;
-            embeddings.push(embedding);
+        let cache_keys: Vec<String> = texts.iter()
+            .map(|(text, memory_type)| self.generate_cache_key(text, memory_type))
+            .collect();
+        let mut embeddings: Vec<Option<Vec<f32>>> = {
+            let mut cache = self.cache.write().await;
+            cache_keys.iter().map(|key| cache.get(key)).collect()
+        };
+
+        // Group uncached texts by the network that embeds them
+        let mut pending: HashMap<Option<MemoryType>, Vec<usize>> = HashMap::new();
+        for (index, (_, memory_type)) in texts.iter().enumerate() {
+            if embeddings[index].is_none() {
+                let network_type = memory_type.clone().filter(|t| self.memory_networks.contains_key(t));
+                pending.entry(network_type).or_default().push(index);
+            }
+        }
+
+        let mut computed = Vec::new();
+        for (network_type, indices) in pending {
+            let network = network_type.as_ref()
+                .and_then(|t| self.memory_networks.get(t))
+                .unwrap_or(&self.general_network);
+            let features = indices.iter()
+                .map(|&index| self.text_to_features(&texts[index].0))
+                .collect::<Result<Vec<_>>>()?;
+            for (index, output) in indices.into_iter().zip(network.run_batch(&features)) {
+                let embedding = self.normalize_embedding(&output);
+                computed.push((cache_keys[index].clone(), embedding.clone()));
+                embeddings[index] = Some(embedding);
+            }
+        }
+
+        if !computed.is_empty() {
+            let mut cache = self.cache.write().await;
+            for (key, embedding) in computed {
+                cache.insert(key, embedding);
+            }
         }
 
-        Ok(embeddings)
+        embeddings.into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow!("Batch embedding left an input without an embedding"))
     }
 
     /// Generate embedding specifically for agent memory
     pub async fn embed_memory(&self, memory: &AgentMemory) -> Result<Vec<f32>> {
         // Combine content with metadata for richer embeddings
         let enhanced_text = self.enhance_text_with_metadata(memory);
-        self.embed_text(&enhanced_text, Some(memory.memory_type.clone())).await
+        self.embed_text_for_agent(&memory.agent_id, &enhanced_text, Some(memory.memory_type.clone())).await
+    }
+
+    /// Provider configured for an agent, falling back to the service default
+    pub fn provider_config(&self, agent_id: &str) -> &EmbeddingProviderConfig {
+        self.config.agent_providers.get(agent_id).unwrap_or(&self.config.provider)
+    }
+
+    /// Model id and, where the configuration fixes it, vector dimension of an agent's
+    /// embedding space. Embeddings are only comparable within one space.
+    pub fn embedding_space(&self, agent_id: &str) -> (String, Option<usize>) {
+        let config = self.provider_config(agent_id);
+        let dimension = match config {
+            EmbeddingProviderConfig::Neural => Some(self.config.embedding_dim),
+            EmbeddingProviderConfig::OpenAi { dimensions, .. } => *dimensions,
+            _ => None,
+        };
+        (config.cache_id(), dimension)
+    }
+
+    /// Switch an agent to another provider, checking it can be created first, and save the
+    /// choice. Returns whether the agent's embedding space changed, in which case its stored
+    /// embeddings no longer match and should be re-embedded.
+    pub async fn set_agent_provider(&mut self, agent_id: &str, provider: EmbeddingProviderConfig) -> Result<bool> {
+        self.provider_for(&provider).await?;
+        let previous = self.embedding_space(agent_id);
+        if provider == self.config.provider {
+            self.config.agent_providers.remove(agent_id);
+        } else {
+            self.config.agent_providers.insert(agent_id.to_string(), provider);
+        }
+        self.save_settings()?;
+        Ok(self.embedding_space(agent_id) != previous)
+    }
+
+    async fn provider_for(&self, config: &EmbeddingProviderConfig) -> Result<Option<Arc<dyn EmbeddingProvider>>> {
+        let id = config.cache_id();
+        if let Some(provider) = self.providers.read().await.get(&id) {
+            return Ok(Some(provider.clone()));
+        }
+        let Some(provider) = build_provider(config)? else {
+            return Ok(None);
+        };
+        tracing::info!("Using embedding provider {}", provider.name());
+        self.providers.write().await.insert(id, provider.clone());
+        Ok(Some(provider))
+    }
+
+    /// Embed text in the agent's embedding space: its external provider if one is
+    /// configured, otherwise the built-in networks
+    pub async fn embed_text_for_agent(&self, agent_id: &str, text: &str, memory_type: Option<MemoryType>) -> Result<Vec<f32>> {
+        let config = self.provider_config(agent_id);
+        let Some(provider) = self.provider_for(config).await? else {
+            return self.embed_text(text, memory_type).await;
+        };
+
+        let cache_key = self.provider_cache_key(config, text);
+        if let Some(cached_embedding) = self.cache.write().await.get(&cache_key) {
+            return Ok(cached_embedding);
+        }
+
+        let embedding = provider.embed(&[text.to_string()]).await?
+            .pop()
+            .ok_or_else(|| anyhow!("Embedding provider {} returned nothing", provider.name()))?;
+        let normalized_embedding = self.normalize_embedding(&embedding);
+
+        self.cache.write().await.insert(cache_key, normalized_embedding.clone());
+        Ok(normalized_embedding)
+    }
+
+    /// Embed texts with the given provider rather than an agent's, e.g. to compare providers;
+    /// external providers are called in chunks of `PROVIDER_BATCH_SIZE`
+    pub async fn embed_batch_with(
+        &self,
+        provider_config: &EmbeddingProviderConfig,
+        texts: &[(String, Option<MemoryType>)],
+    ) -> Result<Vec<Vec<f32>>> {
+        let Some(provider) = self.provider_for(provider_config).await? else {
+            return self.embed_batch(texts).await;
+        };
+
+        let cache_keys: Vec<String> = texts.iter()
+            .map(|(text, _)| self.provider_cache_key(provider_config, text))
+            .collect();
+        let mut embeddings: Vec<Option<Vec<f32>>> = {
+            let mut cache = self.cache.write().await;
+            cache_keys.iter().map(|key| cache.get(key)).collect()
+        };
+
+        let pending: Vec<usize> = (0..texts.len()).filter(|&index| embeddings[index].is_none()).collect();
+        for chunk in pending.chunks(PROVIDER_BATCH_SIZE) {
+            let chunk_texts: Vec<String> = chunk.iter().map(|&index| texts[index].0.clone()).collect();
+            let vectors = provider.embed(&chunk_texts).await?;
+            if vectors.len() != chunk.len() {
+                return Err(anyhow!("Embedding provider {} returned {} embeddings for {} texts", provider.name(), vectors.len(), chunk.len()));
+            }
+            let mut cache = self.cache.write().await;
+            for (&index, vector) in chunk.iter().zip(vectors) {
+                let embedding = self.normalize_embedding(&vector);
+                cache.insert(cache_keys[index].clone(), embedding.clone());
+                embeddings[index] = Some(embedding);
+            }
+        }
+
+        embeddings.into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow!("Batch embedding left an input without an embedding"))
     }
 
     /// Train networks on memory data to improve embeddings
@@ -206,19 +547,21 @@ impl NeuralEmbeddingService {
 
         // Train general network on all memories
         Self::train_network_static(&mut self.general_network, &all_training_data, self.config.training_epochs, self.config.learning_rate).await?;
+        self.generation += 1;
 
         // Clear cache after training since embeddings will have changed
-        {
-            let mut cache = self.cache.write().await;
-            cache.clear();
-        }
+        self.clear_cache().await;
 
         Ok(())
     }
 
-    /// Compute similarity between two embeddings
+    /// Compute similarity between two embeddings using the configured metric
     pub fn compute_similarity(&self, embedding1: &[f32], embedding2: &[f32]) -> f32 {
-        cosine_similarity(embedding1, embedding2)
+        self.config.similarity_metric.similarity(embedding1, embedding2)
+    }
+
+    pub fn similarity_metric(&self) -> SimilarityMetric {
+        self.config.similarity_metric
     }
 
     /// Find similar embeddings from a set of candidates
@@ -230,11 +573,11 @@ impl NeuralEmbeddingService {
         threshold: f32,
         top_k: usize,
     ) -> Result<Vec<(String, f32)>> {
-        let query_embedding = self.embed_text(query_text, query_type).await?;
-        
         let mut similarities = Vec::new();
         
         for memory in candidate_memories {
+            // Candidates may come from agents with different providers; the query is cached per provider
+            let query_embedding = self.embed_text_for_agent(&memory.agent_id, query_text, query_type.clone()).await?;
             let memory_embedding = self.embed_memory(memory).await?;
             let similarity = self.compute_similarity(&query_embedding, &memory_embedding);
             
@@ -256,12 +599,38 @@ impl NeuralEmbeddingService {
         let cache = self.cache.read().await;
         EmbeddingStats {
             cache_size: cache.len(),
-            cache_limit: self.config.cache_size_limit,
+            cache_limit: cache.capacity(),
+            cache_hits: cache.hits(),
+            cache_misses: cache.misses(),
+            cache_evictions: cache.evictions(),
+            cache_hit_rate: cache.hit_rate(),
             embedding_dimension: self.config.embedding_dim,
             specialized_networks: self.memory_networks.len(),
         }
     }
 
+    /// Drop cached embeddings derived from these memories; returns how many were cached
+    pub async fn evict_memories(&self, memories: &[AgentMemory]) -> usize {
+        let mut cache = self.cache.write().await;
+        let mut evicted = 0;
+        for memory in memories {
+            let memory_type = Some(memory.memory_type.clone());
+            let enhanced_text = self.enhance_text_with_metadata(memory);
+            let keys = [
+                self.provider_cache_key(self.provider_config(&memory.agent_id), &enhanced_text),
+                self.generate_cache_key(&enhanced_text, &memory_type),
+                self.generate_cache_key(&memory.content, &memory_type),
+                self.generate_cache_key(&memory.content, &None),
+            ];
+            for key in keys {
+                if cache.remove(&key) {
+                    evicted += 1;
+                }
+            }
+        }
+        evicted
+    }
+
     /// Get the configuration
     pub fn config(&self) -> &EmbeddingConfig {
         &self.config
@@ -347,6 +716,10 @@ impl NeuralEmbeddingService {
         format!("{:x}", hasher.finalize())
     }
 
+    fn provider_cache_key(&self, config: &EmbeddingProviderConfig, text: &str) -> String {
+        self.generate_cache_key(&format!("{}\n{}", config.cache_id(), text), &None)
+    }
+
     /// Normalize embedding vector
     fn normalize_embedding(&self, embedding: &[f32]) -> Vec<f32> {
         let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
@@ -441,9 +814,15 @@ impl NeuralEmbeddingService {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EmbeddingStats {
     pub cache_size: usize,
     pub cache_limit: usize,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    /// Entries dropped to make room; invalidations after training or deletes are not counted
+    pub cache_evictions: u64,
+    pub cache_hit_rate: f32,
     pub embedding_dimension: usize,
     pub specialized_networks: usize,
 }
@@ -545,7 +924,7 @@ mod tests {
     fn test_text_to_features() {
         let config = EmbeddingConfig::default();
         let service = NeuralEmbeddingService {
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            cache: Arc::new(RwLock::new(EmbeddingCache::new(config.cache_size_limit))),
             memory_networks: HashMap::new(),
             general_network: NetworkBuilder::new()
                 .input_layer(10)
@@ -553,10 +932,101 @@ mod tests {
                 .build()
                 .unwrap(),
             config,
+            providers: Arc::new(RwLock::new(HashMap::new())),
+            generation: 0,
         };
         
         let features = service.text_to_features("Hello").unwrap();
         assert_eq!(features.len(), 512); // max_text_length
         assert!(features[0] > 0.0); // Should have some content from 'H'
     }
+
+    #[tokio::test]
+    async fn test_cache_keeps_recent_embeddings_and_counts_lookups() {
+        let config = EmbeddingConfig { embedding_dim: 16, cache_size_limit: 2, ..Default::default() };
+        let service = NeuralEmbeddingService::new(Some(config)).await.unwrap();
+        for text in ["first", "second", "first", "third"] {
+            service.embed_text(text, None).await.unwrap();
+        }
+
+        let stats = service.get_stats().await;
+        assert_eq!((stats.cache_size, stats.cache_hits, stats.cache_misses, stats.cache_evictions), (2, 1, 3, 1));
+        assert_eq!(service.reset_cache().await, 2);
+        assert_eq!(service.get_stats().await.cache_hits, 0);
+    }
+
+    #[tokio::test]
+    async fn test_saved_models_reload_and_reset() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EmbeddingConfig {
+            embedding_dim: 16,
+            model_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let mut service = NeuralEmbeddingService::new(Some(config.clone())).await.unwrap();
+        let embedding = service.embed_text("persisted weights", None).await.unwrap();
+        service.save_models().unwrap();
+
+        let reloaded = NeuralEmbeddingService::new(Some(config.clone())).await.unwrap();
+        assert_eq!(reloaded.embed_text("persisted weights", None).await.unwrap(), embedding);
+
+        // Networks sized for a different embedding dimension keep their fresh weights
+        let other = EmbeddingConfig { embedding_dim: 8, ..config.clone() };
+        assert_eq!(NeuralEmbeddingService::new(Some(other)).await.unwrap().load_models(), 0);
+
+        service.reset_models().await.unwrap();
+        assert_ne!(service.embed_text("persisted weights", None).await.unwrap(), embedding);
+        assert_eq!(NeuralEmbeddingService::new(Some(config)).await.unwrap().load_models(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_configured_metric_survives_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EmbeddingConfig {
+            embedding_dim: 16,
+            similarity_metric: SimilarityMetric::DotProduct,
+            model_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        NeuralEmbeddingService::new(Some(config)).await.unwrap().save_settings().unwrap();
+
+        // Without a config, the service would otherwise fall back to cosine
+        let mut saved = NeuralEmbeddingService::new(None).await.unwrap();
+        saved.config.model_dir = Some(dir.path().to_path_buf());
+        saved.load_settings(false);
+        assert_eq!(saved.similarity_metric(), SimilarityMetric::DotProduct);
+
+        // An explicit config keeps its own metric
+        let explicit = EmbeddingConfig { model_dir: Some(dir.path().to_path_buf()), ..Default::default() };
+        let explicit = NeuralEmbeddingService::new(Some(explicit)).await.unwrap();
+        assert_eq!(explicit.similarity_metric(), SimilarityMetric::Cosine);
+    }
+
+    #[tokio::test]
+    async fn test_agent_providers_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EmbeddingConfig {
+            embedding_dim: 16,
+            model_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let cohere = EmbeddingProviderConfig::Cohere { model: "embed-english-v3.0".to_string() };
+
+        let mut service = NeuralEmbeddingService::new(Some(config.clone())).await.unwrap();
+        service.config.agent_providers.insert("agent-1".to_string(), cohere.clone());
+        service.save_settings().unwrap();
+
+        let reloaded = NeuralEmbeddingService::new(Some(config.clone())).await.unwrap();
+        assert_eq!(reloaded.provider_config("agent-1"), &cohere);
+        assert_eq!(reloaded.similarity_metric(), SimilarityMetric::Cosine);
+        assert_eq!(reloaded.provider_config("agent-2"), &EmbeddingProviderConfig::Neural);
+
+        // Switching back to the default provider changes the space and forgets the override
+        let mut service = reloaded;
+        assert!(service.set_agent_provider("agent-1", EmbeddingProviderConfig::Neural).await.unwrap());
+        assert!(!service.set_agent_provider("agent-1", EmbeddingProviderConfig::Neural).await.unwrap());
+        let reloaded = NeuralEmbeddingService::new(Some(config)).await.unwrap();
+        assert_eq!(reloaded.provider_config("agent-1"), &EmbeddingProviderConfig::Neural);
+    }
 }
\ No newline at end of file
@@ -1,12 +1,13 @@
 use anyhow::{Result, anyhow};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use super::neural_network::{NeuralNetwork, NetworkBuilder, ActivationFunction};
 use super::neural_embeddings::NeuralEmbeddingService;
-use super::memory_sequence_models::{MemorySequenceAnalyzer, SequenceModelType};
-use super::memory::{AgentMemory, MemoryType};
+use super::memory_sequence_models::{MemorySequenceAnalyzer, MemoryPatternAnalysis, SequenceModelType};
+use super::memory::{AgentMemory, MemoryType, RelationshipType};
 use super::simple_commands::{GraphNode, GraphEdge, KnowledgeGraphView};
 use serde::{Serialize, Deserialize};
 use ndarray::{Array1, Array2};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use chrono::{DateTime, Utc, Duration};
@@ -29,6 +30,12 @@ pub struct NeuralKnowledgeGraph {
     edge_embeddings: Arc<RwLock<HashMap<String, Vec<f32>>>>,
     /// Graph structure cache
     graph_structure: Arc<RwLock<GraphStructure>>,
+    /// Relationships below the auto-accept threshold awaiting user review
+    edge_suggestions: Arc<RwLock<HashMap<String, EdgeSuggestion>>>,
+    /// Confirm/reject decisions the attention network has been trained on
+    relationship_feedback: RelationshipFeedbackStats,
+    /// Decides whether closely related statements contradict each other
+    contradiction_classifier: ContradictionClassifier,
     /// Configuration
     config: NeuralGraphConfig,
 }
@@ -40,11 +47,54 @@ pub struct NeuralGraphConfig {
     pub attention_heads: usize,
     pub max_neighbors: usize,
     pub temporal_window_hours: i64,
+    /// Similarity at or above which discovered relationships become edges automatically
     pub similarity_threshold: f32,
+    /// Similarity at or above which relationships are queued for review
+    #[serde(default = "default_suggestion_threshold")]
+    pub suggestion_threshold: f32,
     pub learning_rate: f32,
     pub cache_size_limit: usize,
+    /// Where network weights are saved and loaded, defaulting to the memory directory's `models`
+    #[serde(default)]
+    pub model_dir: Option<PathBuf>,
 }
 
+fn default_suggestion_threshold() -> f32 {
+    0.5
+}
+
+/// How far a single accept/reject decision moves the thresholds
+const SUGGESTION_FEEDBACK_RATE: f32 = 0.1;
+
+/// Pending suggestions and the thresholds reviews have tuned, saved next to the networks
+const SUGGESTIONS_FILE: &str = "graph_suggestions.json";
+
+/// Contents of `SUGGESTIONS_FILE`
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedSuggestions {
+    similarity_threshold: f32,
+    suggestion_threshold: f32,
+    suggestions: Vec<EdgeSuggestion>,
+}
+
+/// Relationship features in the edge network's input; the one-hot type fills the first 8
+const RELATIONSHIP_FEATURES: usize = 32;
+
+/// Attention score a confirmed relationship is trained towards; rejected ones get its negative
+const FEEDBACK_TARGET: f32 = 2.0;
+
+/// Training steps per confirm or reject
+const FEEDBACK_STEPS: usize = 5;
+
+/// Reviews before learned scores start to influence relationship classification
+const MIN_CLASSIFICATION_FEEDBACK: u64 = 10;
+
+/// Reviews after which learned scores fully replace the heuristic rules
+const FULL_TRUST_FEEDBACK: u64 = 100;
+
+/// Saved weights of the contradiction network
+const CONTRADICTION_MODEL_FILE: &str = "graph_contradiction.bin";
+
 impl Default for NeuralGraphConfig {
     fn default() -> Self {
         Self {
@@ -54,8 +104,10 @@ impl Default for NeuralGraphConfig {
             max_neighbors: 50,
             temporal_window_hours: 24,
             similarity_threshold: 0.7,
+            suggestion_threshold: default_suggestion_threshold(),
             learning_rate: 0.001,
             cache_size_limit: 10000,
+            model_dir: None,
         }
     }
 }
@@ -99,8 +151,57 @@ pub struct NeuralGraphEdge {
     pub properties: HashMap<String, String>,
 }
 
-/// Relationship types discovered by neural analysis
+/// A discovered relationship that fell below the auto-accept threshold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeSuggestion {
+    pub id: String,
+    pub from_node: String,
+    pub to_node: String,
+    pub relationship_type: NeuralRelationshipType,
+    pub confidence: f32,
+    pub agent_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Confirm and reject decisions the edge and attention networks have been trained on
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RelationshipFeedbackStats {
+    pub confirmed: u64,
+    pub rejected: u64,
+    /// Share of relationship classification decided by the learned scores rather than the rules
+    #[serde(default)]
+    pub learned_weight: f32,
+}
+
+impl RelationshipFeedbackStats {
+    fn total(&self) -> u64 {
+        self.confirmed + self.rejected
+    }
+
+    fn update_learned_weight(&mut self) {
+        self.learned_weight = if self.total() < MIN_CLASSIFICATION_FEEDBACK {
+            0.0
+        } else {
+            (self.total() as f32 / FULL_TRUST_FEEDBACK as f32).min(1.0)
+        };
+    }
+}
+
+/// A relationship the graph network predicts between a node and one it isn't connected to
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationshipPrediction {
+    pub from_node: String,
+    pub to_node: String,
+    pub to_node_name: String,
+    pub relationship_type: NeuralRelationshipType,
+    /// Attention network score squashed into (0, 1)
+    pub strength: f32,
+    /// Embedding similarity of the two nodes
+    pub similarity: f32,
+}
+
+/// Relationship types discovered by neural analysis
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NeuralRelationshipType {
     /// Direct semantic similarity
     SemanticSimilarity,
@@ -120,11 +221,109 @@ pub enum NeuralRelationshipType {
     ErrorSolution,
 }
 
+impl NeuralRelationshipType {
+    pub const ALL: [NeuralRelationshipType; 8] = [
+        NeuralRelationshipType::SemanticSimilarity,
+        NeuralRelationshipType::TemporalSequence,
+        NeuralRelationshipType::CausalRelation,
+        NeuralRelationshipType::AgentCollaboration,
+        NeuralRelationshipType::ContextSharing,
+        NeuralRelationshipType::PatternSimilarity,
+        NeuralRelationshipType::ToolUsage,
+        NeuralRelationshipType::ErrorSolution,
+    ];
+
+    /// Inverse of the `Debug` name stored on `NeuralGraphEdge::relationship_type`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| format!("{:?}", t) == name)
+    }
+
+    /// Closest relationship in the persistent knowledge graph
+    pub fn knowledge_relationship(&self) -> RelationshipType {
+        match self {
+            NeuralRelationshipType::SemanticSimilarity
+            | NeuralRelationshipType::ContextSharing
+            | NeuralRelationshipType::PatternSimilarity => RelationshipType::Similar,
+            NeuralRelationshipType::TemporalSequence
+            | NeuralRelationshipType::ErrorSolution => RelationshipType::LeadsTo,
+            NeuralRelationshipType::CausalRelation => RelationshipType::CausedBy,
+            NeuralRelationshipType::AgentCollaboration => RelationshipType::CollaboratesWith,
+            NeuralRelationshipType::ToolUsage => RelationshipType::Uses,
+        }
+    }
+}
+
 impl NeuralKnowledgeGraph {
-    /// Create a new neural knowledge graph
+    /// Create a new neural knowledge graph, loading previously saved weights when they match
+    /// the configured architecture
     pub async fn new(config: Option<NeuralGraphConfig>) -> Result<Self> {
         let config = config.unwrap_or_default();
-        
+        let (node_network, edge_network, attention_network, sequence_analyzer) = Self::build_networks(&config)?;
+
+        // Create neural embedding service
+        let embedding_service = NeuralEmbeddingService::new(None).await?;
+        let contradiction_classifier = ContradictionClassifier::new(embedding_service.config().embedding_dim)?;
+        let embedding_service = Arc::new(RwLock::new(embedding_service));
+
+        let mut graph = Self {
+            node_network,
+            edge_network,
+            attention_network,
+            sequence_analyzer,
+            embedding_service,
+            node_embeddings: Arc::new(RwLock::new(HashMap::new())),
+            edge_embeddings: Arc::new(RwLock::new(HashMap::new())),
+            graph_structure: Arc::new(RwLock::new(GraphStructure {
+                nodes: HashMap::new(),
+                edges: HashMap::new(),
+                adjacency: HashMap::new(),
+                reverse_adjacency: HashMap::new(),
+            })),
+            edge_suggestions: Arc::new(RwLock::new(HashMap::new())),
+            relationship_feedback: RelationshipFeedbackStats::default(),
+            contradiction_classifier,
+            config,
+        };
+        let loaded = graph.load_models();
+        if loaded > 0 {
+            tracing::info!("Loaded {} saved knowledge graph networks", loaded);
+        }
+        graph.load_suggestions();
+        if !graph.model_dir().is_ok_and(|dir| dir.join(CONTRADICTION_MODEL_FILE).exists()) {
+            graph.seed_contradiction_classifier().await?;
+        }
+        Ok(graph)
+    }
+
+    /// Train a fresh contradiction network on `CONTRADICTION_SEED_PAIRS`, so it has a
+    /// starting point before any contradiction is reviewed
+    async fn seed_contradiction_classifier(&mut self) -> Result<()> {
+        let texts: Vec<(String, Option<MemoryType>)> = CONTRADICTION_SEED_PAIRS.iter()
+            .flat_map(|(a, b, _)| [(a.to_string(), None), (b.to_string(), None)])
+            .collect();
+        let embeddings = self.embedding_service.read().await.embed_batch(&texts).await?;
+        for _ in 0..CONTRADICTION_SEED_EPOCHS {
+            for (pair, (_, _, kind)) in embeddings.chunks(2).zip(CONTRADICTION_SEED_PAIRS) {
+                self.contradiction_classifier.train(&pair[0], &pair[1], *kind, 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy of the contradiction network, e.g. to classify shared knowledge off the runtime
+    pub fn contradiction_classifier(&self) -> ContradictionClassifier {
+        self.contradiction_classifier.clone()
+    }
+
+    /// Train the contradiction network on a reviewed pair of statement embeddings, `None`
+    /// meaning the reviewer found they don't contradict
+    pub fn record_contradiction_feedback(&mut self, a: &[f32], b: &[f32], kind: Option<ContradictionKind>) -> Result<()> {
+        self.contradiction_classifier.train(a, b, kind, CONTRADICTION_FEEDBACK_STEPS)?;
+        Ok(())
+    }
+
+    /// Freshly initialized node, edge and attention networks and sequence analyzer
+    fn build_networks(config: &NeuralGraphConfig) -> Result<(NeuralNetwork, NeuralNetwork, NeuralNetwork, MemorySequenceAnalyzer)> {
         // Create node embedding network
         let node_network = NetworkBuilder::new()
             .input_layer(512) // Text features + metadata
@@ -155,27 +354,136 @@ impl NeuralKnowledgeGraph {
         // Create sequence analyzer for temporal patterns
         let sequence_analyzer = MemorySequenceAnalyzer::new(256, 128, config.node_embedding_dim)?;
 
-        // Create neural embedding service
-        let embedding_service = Arc::new(RwLock::new(
-            NeuralEmbeddingService::new(None).await?
-        ));
+        Ok((node_network, edge_network, attention_network, sequence_analyzer))
+    }
 
-        Ok(Self {
-            node_network,
-            edge_network,
-            attention_network,
-            sequence_analyzer,
-            embedding_service,
-            node_embeddings: Arc::new(RwLock::new(HashMap::new())),
-            edge_embeddings: Arc::new(RwLock::new(HashMap::new())),
-            graph_structure: Arc::new(RwLock::new(GraphStructure {
-                nodes: HashMap::new(),
-                edges: HashMap::new(),
-                adjacency: HashMap::new(),
-                reverse_adjacency: HashMap::new(),
-            })),
-            config,
-        })
+    fn model_dir(&self) -> Result<PathBuf> {
+        match &self.config.model_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => super::simple_memory::SimpleMemoryManager::default_model_directory(),
+        }
+    }
+
+    /// Save the graph's own networks and sequence models; the embedding service saves its own
+    pub fn save_models(&self) -> Result<()> {
+        let dir = self.model_dir()?;
+        self.node_network.save_model(&dir.join("graph_node.bin"))?;
+        self.edge_network.save_model(&dir.join("graph_edge.bin"))?;
+        self.attention_network.save_model(&dir.join("graph_attention.bin"))?;
+        self.sequence_analyzer.save_model(&dir.join("graph_sequence.bin"))?;
+        self.contradiction_classifier.save_model(&dir.join(CONTRADICTION_MODEL_FILE))?;
+        std::fs::write(dir.join("graph_feedback.json"), serde_json::to_vec(&self.relationship_feedback)?)?;
+        Ok(())
+    }
+
+    /// Replace networks with their saved weights, returning how many were loaded; see
+    /// `NeuralEmbeddingService::load_models`
+    pub fn load_models(&mut self) -> usize {
+        let dir = match self.model_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                tracing::warn!("No directory for saved knowledge graph networks: {}", e);
+                return 0;
+            }
+        };
+
+        let results = [
+            self.node_network.load_saved(&dir.join("graph_node.bin")),
+            self.edge_network.load_saved(&dir.join("graph_edge.bin")),
+            self.attention_network.load_saved(&dir.join("graph_attention.bin")),
+            self.sequence_analyzer.load_saved(&dir.join("graph_sequence.bin")),
+            self.contradiction_classifier.load_saved(&dir.join(CONTRADICTION_MODEL_FILE)),
+        ];
+        let mut loaded = 0;
+        for result in results {
+            match result {
+                Ok(true) => loaded += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Ignoring saved knowledge graph network: {}", e),
+            }
+        }
+
+        // Feedback counts only mean something for the attention weights they trained
+        if let Ok(bytes) = std::fs::read(dir.join("graph_feedback.json")) {
+            match serde_json::from_slice::<RelationshipFeedbackStats>(&bytes) {
+                Ok(mut feedback) => {
+                    feedback.update_learned_weight();
+                    self.relationship_feedback = feedback;
+                }
+                Err(e) => tracing::warn!("Ignoring saved relationship feedback: {}", e),
+            }
+        }
+        loaded
+    }
+
+    /// Restore the review queue and tuned thresholds, while the graph is being built. Suggestions
+    /// are kept separately from the networks, so `reset_models` leaves them alone.
+    fn load_suggestions(&mut self) {
+        let Ok(dir) = self.model_dir() else { return };
+        let Ok(bytes) = std::fs::read(dir.join(SUGGESTIONS_FILE)) else { return };
+        match serde_json::from_slice::<SavedSuggestions>(&bytes) {
+            Ok(saved) => {
+                self.config.similarity_threshold = saved.similarity_threshold;
+                self.config.suggestion_threshold = saved.suggestion_threshold.min(saved.similarity_threshold);
+                self.edge_suggestions = Arc::new(RwLock::new(
+                    saved.suggestions.into_iter().map(|s| (s.id.clone(), s)).collect(),
+                ));
+            }
+            Err(e) => tracing::warn!("Ignoring saved edge suggestions: {}", e),
+        }
+    }
+
+    /// Save the review queue and thresholds, replacing the previous file in one step
+    pub async fn save_suggestions(&self) -> Result<()> {
+        let saved = SavedSuggestions {
+            similarity_threshold: self.config.similarity_threshold,
+            suggestion_threshold: self.config.suggestion_threshold,
+            suggestions: self.edge_suggestions.read().await.values().cloned().collect(),
+        };
+        let dir = self.model_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(SUGGESTIONS_FILE);
+        let partial = path.with_extension("json.tmp");
+        std::fs::write(&partial, serde_json::to_vec(&saved)?)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    /// `save_suggestions`, logging rather than failing the change that prompted it
+    async fn persist_suggestions(&self) {
+        if let Err(e) = self.save_suggestions().await {
+            tracing::warn!("Failed to save edge suggestions: {}", e);
+        }
+    }
+
+    /// Start the graph's networks from fresh weights and drop embeddings computed with the
+    /// old ones. The embedding service is reloaded from disk, so reset it first.
+    pub async fn reset_models(&mut self) -> Result<()> {
+        let dir = self.model_dir()?;
+        for file_name in ["graph_node.bin", "graph_edge.bin", "graph_attention.bin", "graph_sequence.bin", CONTRADICTION_MODEL_FILE, "graph_feedback.json"] {
+            let path = dir.join(file_name);
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        let (node_network, edge_network, attention_network, sequence_analyzer) = Self::build_networks(&self.config)?;
+        self.node_network = node_network;
+        self.edge_network = edge_network;
+        self.attention_network = attention_network;
+        self.sequence_analyzer = sequence_analyzer;
+        self.relationship_feedback = RelationshipFeedbackStats::default();
+        self.contradiction_classifier = ContradictionClassifier::new(self.contradiction_classifier.embedding_dim)?;
+
+        let mut embedding_service = self.embedding_service.write().await;
+        embedding_service.load_models();
+        embedding_service.clear_cache().await;
+        drop(embedding_service);
+        self.node_embeddings.write().await.clear();
+        self.edge_embeddings.write().await.clear();
+
+        // Seeded with the reloaded embeddings, the ones contributions will be compared with
+        self.seed_contradiction_classifier().await?;
+        self.save_models()
     }
 
     /// Add a memory to the knowledge graph as a node
@@ -244,58 +552,233 @@ impl NeuralKnowledgeGraph {
             .ok_or_else(|| anyhow!("Node has no embedding: {}", node_id))?
             .clone();
 
-        // Find similar nodes within temporal window
+        // Score every node within the temporal window in one pass
+        let metric = self.embedding_service.read().await.similarity_metric();
         let temporal_threshold = Utc::now() - Duration::hours(self.config.temporal_window_hours);
-        let mut candidate_relationships = Vec::new();
-
-        {
+        let similar_nodes: Vec<(NeuralGraphNode, f32)> = {
             let graph = self.graph_structure.read().await;
-            for (other_id, other_node) in &graph.nodes {
-                if other_id == node_id || other_node.created_at < temporal_threshold {
-                    continue;
-                }
+            let candidates: Vec<(&NeuralGraphNode, &[f32])> = graph.nodes.iter()
+                .filter(|(other_id, other_node)| *other_id != node_id && other_node.created_at >= temporal_threshold)
+                .filter_map(|(_, other_node)| other_node.embedding.as_deref().map(|embedding| (other_node, embedding)))
+                .collect();
+            let embeddings: Vec<&[f32]> = candidates.iter().map(|(_, embedding)| *embedding).collect();
+            let similarities = metric.similarities(&current_embedding, &embeddings);
+            candidates.into_iter().zip(similarities)
+                .filter(|(_, similarity)| *similarity >= self.config.suggestion_threshold)
+                .map(|((other_node, _), similarity)| (other_node.clone(), similarity))
+                .collect()
+        };
 
-                if let Some(ref other_embedding) = other_node.embedding {
-                    let similarity = cosine_similarity(&current_embedding, other_embedding);
-                    
-                    if similarity >= self.config.similarity_threshold {
-                        let relationship_type = self.classify_relationship(&current_node, other_node, similarity).await?;
-                        candidate_relationships.push((other_id.clone(), relationship_type, similarity));
-                    }
-                }
+        let other_contents: Vec<&str> = similar_nodes.iter().map(|(node, _)| node.content.as_str()).collect();
+        let context_similarities = self.context_similarities(&current_node.content, &other_contents).await?;
+        let candidate_relationships: Vec<(String, NeuralRelationshipType, f32)> = similar_nodes.iter()
+            .zip(context_similarities)
+            .map(|((other_node, similarity), context_similarity)| {
+                let relationship_type = self.classify_relationship(&current_node, other_node, *similarity, context_similarity);
+                (other_node.id.clone(), relationship_type, *similarity)
+            })
+            .collect();
+
+        // Create edges for confident relationships and queue the rest for review
+        let mut queued = false;
+        for (other_node_id, rel_type, similarity) in candidate_relationships {
+            if similarity >= self.config.similarity_threshold {
+                self.create_neural_edge(node_id, &other_node_id, rel_type, similarity).await?;
+            } else {
+                let suggestion = EdgeSuggestion {
+                    id: format!("suggestion_{}_{}", node_id, other_node_id),
+                    from_node: node_id.to_string(),
+                    to_node: other_node_id,
+                    relationship_type: rel_type,
+                    confidence: similarity,
+                    agent_id: current_node.agent_id.clone(),
+                    created_at: Utc::now(),
+                };
+                self.edge_suggestions.write().await.insert(suggestion.id.clone(), suggestion);
+                queued = true;
             }
         }
-
-        // Create edges for discovered relationships
-        for (other_node_id, rel_type, similarity) in candidate_relationships {
-            self.create_neural_edge(node_id, &other_node_id, rel_type, similarity).await?;
+        if queued {
+            self.persist_suggestions().await;
         }
 
         Ok(())
     }
 
-    /// Classify the type of relationship between two nodes
-    async fn classify_relationship(
+    /// Pending edge suggestions, most confident first
+    pub async fn list_edge_suggestions(&self, agent_id: Option<&str>) -> Vec<EdgeSuggestion> {
+        let suggestions = self.edge_suggestions.read().await;
+        let mut pending: Vec<EdgeSuggestion> = suggestions.values()
+            .filter(|s| agent_id.map_or(true, |id| s.agent_id.as_deref() == Some(id)))
+            .cloned()
+            .collect();
+
+        pending.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
+        pending
+    }
+
+    /// Turn a suggestion into an edge and lower the auto-accept threshold towards its confidence
+    pub async fn accept_edge_suggestion(&mut self, suggestion_id: &str) -> Result<String> {
+        let suggestion = self.edge_suggestions.write().await.remove(suggestion_id)
+            .ok_or_else(|| anyhow!("Edge suggestion not found: {}", suggestion_id))?;
+
+        self.apply_suggestion_feedback(true, suggestion.confidence);
+        let edge_id = self.create_neural_edge(
+            &suggestion.from_node,
+            &suggestion.to_node,
+            suggestion.relationship_type,
+            suggestion.confidence,
+        ).await;
+        self.persist_suggestions().await;
+        edge_id
+    }
+
+    /// Drop a suggestion and raise the suggestion threshold towards its confidence
+    pub async fn reject_edge_suggestion(&mut self, suggestion_id: &str) -> Result<()> {
+        let suggestion = self.edge_suggestions.write().await.remove(suggestion_id)
+            .ok_or_else(|| anyhow!("Edge suggestion not found: {}", suggestion_id))?;
+
+        self.apply_suggestion_feedback(false, suggestion.confidence);
+        self.persist_suggestions().await;
+        Ok(())
+    }
+
+    /// Nudge the thresholds based on a review decision, keeping suggestion <= auto-accept
+    fn apply_suggestion_feedback(&mut self, accepted: bool, confidence: f32) {
+        let config = &mut self.config;
+
+        if accepted {
+            let gap = (config.similarity_threshold - confidence).max(0.0);
+            config.similarity_threshold = (config.similarity_threshold - SUGGESTION_FEEDBACK_RATE * gap)
+                .max(config.suggestion_threshold);
+        } else {
+            let gap = (confidence - config.suggestion_threshold).max(0.0);
+            config.suggestion_threshold = (config.suggestion_threshold + SUGGESTION_FEEDBACK_RATE * gap)
+                .min(config.similarity_threshold);
+        }
+    }
+
+    /// Remove an agent's memory nodes (optionally only the given memory ids) with their edges and suggestions
+    pub async fn remove_agent_nodes(&mut self, agent_id: &str, memory_ids: Option<&HashSet<String>>) -> (usize, usize, usize) {
+        let mut guard = self.graph_structure.write().await;
+        let graph = &mut *guard;
+        let removed: HashSet<String> = graph.nodes.values()
+            .filter(|n| n.agent_id.as_deref() == Some(agent_id))
+            .filter(|n| memory_ids.map_or(true, |ids| {
+                n.id.strip_prefix("memory_").map_or(false, |id| ids.contains(id))
+            }))
+            .map(|n| n.id.clone())
+            .collect();
+
+        let removed_edges: Vec<String> = graph.edges.values()
+            .filter(|e| removed.contains(&e.from_node) || removed.contains(&e.to_node))
+            .map(|e| e.id.clone())
+            .collect();
+
+        for id in &removed {
+            graph.nodes.remove(id);
+            graph.adjacency.remove(id);
+            graph.reverse_adjacency.remove(id);
+        }
+        for neighbours in graph.adjacency.values_mut().chain(graph.reverse_adjacency.values_mut()) {
+            neighbours.retain(|n| !removed.contains(n));
+        }
+        for id in &removed_edges {
+            graph.edges.remove(id);
+        }
+        drop(guard);
+
+        {
+            let mut node_embeddings = self.node_embeddings.write().await;
+            for id in &removed {
+                node_embeddings.remove(id);
+            }
+            let mut edge_embeddings = self.edge_embeddings.write().await;
+            for id in &removed_edges {
+                edge_embeddings.remove(id);
+            }
+        }
+
+        let mut suggestions = self.edge_suggestions.write().await;
+        let before = suggestions.len();
+        suggestions.retain(|_, s| !removed.contains(&s.from_node) && !removed.contains(&s.to_node));
+        let removed_suggestions = before - suggestions.len();
+        drop(suggestions);
+        if removed_suggestions > 0 {
+            self.persist_suggestions().await;
+        }
+
+        (removed.len(), removed_edges.len(), removed_suggestions)
+    }
+
+    /// Drop memory embeddings cached by the graph's embedding service
+    pub async fn evict_memory_embeddings(&self, memories: &[AgentMemory]) -> usize {
+        self.embedding_service.read().await.evict_memories(memories).await
+    }
+
+    pub fn config(&self) -> &NeuralGraphConfig {
+        &self.config
+    }
+
+    /// Sequence-model patterns across `memories`, whatever order they are given in
+    pub fn detect_memory_patterns(&self, memories: &[AgentMemory]) -> Result<MemoryPatternAnalysis> {
+        self.sequence_analyzer.detect_patterns(memories)
+    }
+
+    /// Classify the type of relationship between two nodes, blending the rules with the
+    /// attention network's scores once enough confirmed and rejected edges have trained it
+    fn classify_relationship(
+        &self,
+        node1: &NeuralGraphNode,
+        node2: &NeuralGraphNode,
+        similarity: f32,
+        context_similarity: f32,
+    ) -> NeuralRelationshipType {
+        let heuristic = self.heuristic_relationship(node1, node2, similarity, context_similarity);
+        let weight = self.relationship_feedback.learned_weight;
+        let (Some(from), Some(to)) = (&node1.embedding, &node2.embedding) else {
+            return heuristic;
+        };
+        if weight <= 0.0 {
+            return heuristic;
+        }
+
+        let best = self.relationship_scores(from, to).into_iter()
+            .map(|(relationship_type, score)| {
+                let rule = if relationship_type == heuristic { 1.0 } else { 0.0 };
+                let blended = (1.0 - weight) * rule + weight / (1.0 + (-score).exp());
+                (relationship_type, blended)
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        match best {
+            Some((relationship_type, _)) => relationship_type,
+            None => heuristic,
+        }
+    }
+
+    /// Rule-based relationship type from similarity, agents, timing and memory types
+    fn heuristic_relationship(
         &self,
         node1: &NeuralGraphNode,
         node2: &NeuralGraphNode,
         similarity: f32,
-    ) -> Result<NeuralRelationshipType> {
+        context_similarity: f32,
+    ) -> NeuralRelationshipType {
         // Semantic similarity (high embedding similarity)
         if similarity > 0.9 {
-            return Ok(NeuralRelationshipType::SemanticSimilarity);
+            return NeuralRelationshipType::SemanticSimilarity;
         }
 
         // Same agent collaboration
         if node1.agent_id == node2.agent_id && node1.agent_id.is_some() {
-            return Ok(NeuralRelationshipType::AgentCollaboration);
+            return NeuralRelationshipType::AgentCollaboration;
         }
 
         // Temporal sequence (same agent, close in time)
         if node1.agent_id == node2.agent_id {
             let time_diff = (node2.created_at - node1.created_at).num_minutes().abs();
             if time_diff < 60 { // Within 1 hour
-                return Ok(NeuralRelationshipType::TemporalSequence);
+                return NeuralRelationshipType::TemporalSequence;
             }
         }
 
@@ -303,33 +786,40 @@ impl NeuralKnowledgeGraph {
         if let (Some(type1), Some(type2)) = (&node1.memory_type, &node2.memory_type) {
             match (type1, type2) {
                 (MemoryType::Error, MemoryType::Success) | (MemoryType::Success, MemoryType::Error) => {
-                    return Ok(NeuralRelationshipType::ErrorSolution);
+                    return NeuralRelationshipType::ErrorSolution;
                 }
                 (MemoryType::Tool, _) | (_, MemoryType::Tool) => {
-                    return Ok(NeuralRelationshipType::ToolUsage);
+                    return NeuralRelationshipType::ToolUsage;
                 }
                 (MemoryType::Pattern, _) | (_, MemoryType::Pattern) => {
-                    return Ok(NeuralRelationshipType::PatternSimilarity);
+                    return NeuralRelationshipType::PatternSimilarity;
                 }
                 _ => {}
             }
         }
 
         // Context sharing (similar content themes)
-        if self.analyze_context_similarity(&node1.content, &node2.content).await? > 0.8 {
-            return Ok(NeuralRelationshipType::ContextSharing);
+        if context_similarity > 0.8 {
+            return NeuralRelationshipType::ContextSharing;
         }
 
         // Default to semantic similarity
-        Ok(NeuralRelationshipType::SemanticSimilarity)
+        NeuralRelationshipType::SemanticSimilarity
     }
 
-    /// Analyze context similarity between two text contents
-    async fn analyze_context_similarity(&self, content1: &str, content2: &str) -> Result<f32> {
+    /// Similarity of `content` to each of `others`, embedding them all in one batch
+    async fn context_similarities(&self, content: &str, others: &[&str]) -> Result<Vec<f32>> {
+        if others.is_empty() {
+            return Ok(Vec::new());
+        }
         let embedding_service = self.embedding_service.read().await;
-        let emb1 = embedding_service.embed_text(content1, None).await?;
-        let emb2 = embedding_service.embed_text(content2, None).await?;
-        Ok(cosine_similarity(&emb1, &emb2))
+        let texts: Vec<(String, Option<MemoryType>)> = std::iter::once(content)
+            .chain(others.iter().copied())
+            .map(|text| (text.to_string(), None))
+            .collect();
+        let embeddings = embedding_service.embed_batch(&texts).await?;
+        let other_embeddings: Vec<&[f32]> = embeddings[1..].iter().map(Vec::as_slice).collect();
+        Ok(embedding_service.similarity_metric().similarities(&embeddings[0], &other_embeddings))
     }
 
     /// Create a neural edge between two nodes
@@ -352,16 +842,8 @@ impl NeuralKnowledgeGraph {
             .clone();
         drop(node_embeddings);
 
-        // Create edge features
-        let mut edge_features = Vec::new();
-        edge_features.extend(from_embedding);
-        edge_features.extend(to_embedding);
-        
-        // Add relationship type features
-        let type_features = self.encode_relationship_type(&relationship_type);
-        edge_features.extend(type_features);
-
         // Generate edge embedding
+        let edge_features = self.edge_features(&from_embedding, &to_embedding, Some(&relationship_type));
         let edge_embedding = self.edge_network.run(&edge_features);
 
         // Calculate temporal strength
@@ -421,6 +903,95 @@ impl NeuralKnowledgeGraph {
         features
     }
 
+    /// Edge network input: both node embeddings and the relationship features, neutral when
+    /// no type is given
+    fn edge_features(&self, from: &[f32], to: &[f32], relationship_type: Option<&NeuralRelationshipType>) -> Vec<f32> {
+        let dim = self.config.node_embedding_dim;
+        let type_features = relationship_type
+            .map(|t| self.encode_relationship_type(t))
+            .unwrap_or_default();
+        let mut features = Vec::with_capacity(dim * 2 + RELATIONSHIP_FEATURES);
+        features.extend(fit_dim(from, dim));
+        features.extend(fit_dim(to, dim));
+        features.extend(fit_dim(&type_features, RELATIONSHIP_FEATURES));
+        features
+    }
+
+    /// Attention network input: the source node embedding followed by the edge embedding
+    fn attention_input(&self, from: &[f32], edge_embedding: &[f32]) -> Vec<f32> {
+        let mut input = fit_dim(from, self.config.node_embedding_dim);
+        input.extend(fit_dim(edge_embedding, self.config.edge_embedding_dim));
+        input
+    }
+
+    /// Mean attention score of each relationship type for a pair of node embeddings
+    fn relationship_scores(&self, from: &[f32], to: &[f32]) -> Vec<(NeuralRelationshipType, f32)> {
+        let edge_features: Vec<Vec<f32>> = NeuralRelationshipType::ALL.iter()
+            .map(|relationship_type| self.edge_features(from, to, Some(relationship_type)))
+            .collect();
+        let attention_inputs: Vec<Vec<f32>> = self.edge_network.run_batch(&edge_features).iter()
+            .map(|edge_embedding| self.attention_input(from, edge_embedding))
+            .collect();
+
+        NeuralRelationshipType::ALL.into_iter()
+            .zip(self.attention_network.run_batch(&attention_inputs))
+            .map(|(relationship_type, scores)| {
+                (relationship_type, scores.iter().sum::<f32>() / scores.len().max(1) as f32)
+            })
+            .collect()
+    }
+
+    /// Train the attention network on a reviewed edge, towards a high score for its type when
+    /// confirmed and a low one when rejected. Rejected edges are removed from the graph.
+    pub async fn record_relationship_feedback(&mut self, edge_id: &str, confirmed: bool) -> Result<RelationshipFeedbackStats> {
+        let edge = self.graph_structure.read().await.edges.get(edge_id).cloned()
+            .ok_or_else(|| anyhow!("Edge not found: {}", edge_id))?;
+        let relationship_type = NeuralRelationshipType::from_name(&edge.relationship_type)
+            .ok_or_else(|| anyhow!("Unknown relationship type: {}", edge.relationship_type))?;
+        let (from_embedding, to_embedding) = {
+            let node_embeddings = self.node_embeddings.read().await;
+            let embedding = |id: &str| node_embeddings.get(id).cloned()
+                .ok_or_else(|| anyhow!("Node embedding not found: {}", id));
+            (embedding(&edge.from_node)?, embedding(&edge.to_node)?)
+        };
+
+        let edge_features = self.edge_features(&from_embedding, &to_embedding, Some(&relationship_type));
+        let edge_embedding = self.edge_network.run(&edge_features);
+        let input = self.attention_input(&from_embedding, &edge_embedding);
+        let target_score = if confirmed { FEEDBACK_TARGET } else { -FEEDBACK_TARGET };
+        let target = vec![target_score; self.config.attention_heads];
+        for _ in 0..FEEDBACK_STEPS {
+            self.attention_network.train_incremental(&input, &target)?;
+        }
+
+        if confirmed {
+            let mut graph = self.graph_structure.write().await;
+            if let Some(edge) = graph.edges.get_mut(edge_id) {
+                edge.properties.insert("feedback".to_string(), "confirmed".to_string());
+                edge.last_updated = Utc::now();
+            }
+            self.relationship_feedback.confirmed += 1;
+        } else {
+            let mut graph = self.graph_structure.write().await;
+            graph.edges.remove(edge_id);
+            if let Some(targets) = graph.adjacency.get_mut(&edge.from_node) {
+                targets.retain(|target| *target != edge.to_node);
+            }
+            if let Some(sources) = graph.reverse_adjacency.get_mut(&edge.to_node) {
+                sources.retain(|source| *source != edge.from_node);
+            }
+            drop(graph);
+            self.edge_embeddings.write().await.remove(edge_id);
+            self.relationship_feedback.rejected += 1;
+        }
+        self.relationship_feedback.update_learned_weight();
+
+        if let Err(e) = self.save_models() {
+            tracing::warn!("Failed to save relationship feedback: {}", e);
+        }
+        Ok(self.relationship_feedback.clone())
+    }
+
     /// Calculate temporal strength based on recency
     fn calculate_temporal_strength(&self, created_at: &DateTime<Utc>, now: &DateTime<Utc>) -> f32 {
         let hours_ago = (now.signed_duration_since(*created_at)).num_hours() as f32;
@@ -497,6 +1068,7 @@ impl NeuralKnowledgeGraph {
         // Generate query embedding
         let embedding_service = self.embedding_service.read().await;
         let query_embedding = embedding_service.embed_memory(query_memory).await?;
+        let metric = embedding_service.similarity_metric();
         drop(embedding_service);
 
         let mut similarities = Vec::new();
@@ -504,7 +1076,7 @@ impl NeuralKnowledgeGraph {
         // Compare with all cached node embeddings
         let node_embeddings = self.node_embeddings.read().await;
         for (node_id, node_embedding) in node_embeddings.iter() {
-            let similarity = cosine_similarity(&query_embedding, node_embedding);
+            let similarity = metric.similarity(&query_embedding, node_embedding);
             similarities.push((node_id.clone(), similarity));
         }
         drop(node_embeddings);
@@ -516,36 +1088,36 @@ impl NeuralKnowledgeGraph {
         Ok(similarities)
     }
 
-    /// Perform graph neural network inference for relationship prediction
+    /// Perform graph neural network inference for relationship prediction; strengths are the
+    /// mean attention score squashed into (0, 1)
     pub async fn predict_relationships(&self, from_node_id: &str, candidate_nodes: &[String]) -> Result<Vec<(String, f32)>> {
         let node_embeddings = self.node_embeddings.read().await;
         let from_embedding = node_embeddings.get(from_node_id)
             .ok_or_else(|| anyhow!("From node not found: {}", from_node_id))?;
 
-        let mut predictions = Vec::new();
-        
-        for candidate_id in candidate_nodes {
-            if let Some(to_embedding) = node_embeddings.get(candidate_id) {
-                // Create edge features
-                let mut edge_features = Vec::new();
-                edge_features.extend(from_embedding);
-                edge_features.extend(to_embedding);
-                edge_features.extend(vec![0.0; 8]); // Neutral relationship type
-                
-                // Get edge embedding
-                let edge_embedding = self.edge_network.run(&edge_features);
-                
-                // Use attention network to predict relationship strength
-                let mut attention_input = Vec::new();
-                attention_input.extend(from_embedding);
-                attention_input.extend(&edge_embedding);
-                
-                let attention_scores = self.attention_network.run(&attention_input);
-                let relationship_strength = attention_scores.iter().sum::<f32>() / attention_scores.len() as f32;
-                
-                predictions.push((candidate_id.clone(), relationship_strength));
-            }
-        }
+        let candidates: Vec<(&String, &Vec<f32>)> = candidate_nodes.iter()
+            .filter_map(|candidate_id| node_embeddings.get(candidate_id).map(|embedding| (candidate_id, embedding)))
+            .collect();
+
+        // Edge features: both node embeddings plus a neutral relationship type
+        let edge_features: Vec<Vec<f32>> = candidates.iter()
+            .map(|(_, to_embedding)| self.edge_features(from_embedding, to_embedding, None))
+            .collect();
+        let edge_embeddings = self.edge_network.run_batch(&edge_features);
+
+        // Use attention network to predict relationship strength
+        let attention_inputs: Vec<Vec<f32>> = edge_embeddings.iter()
+            .map(|edge_embedding| self.attention_input(from_embedding, edge_embedding))
+            .collect();
+        let attention_scores = self.attention_network.run_batch(&attention_inputs);
+
+        let mut predictions: Vec<(String, f32)> = candidates.iter()
+            .zip(attention_scores)
+            .map(|((candidate_id, _), scores)| {
+                let score = scores.iter().sum::<f32>() / scores.len().max(1) as f32;
+                ((*candidate_id).clone(), 1.0 / (1.0 + (-score).exp()))
+            })
+            .collect();
 
         // Sort by predicted strength
         predictions.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
@@ -553,6 +1125,108 @@ impl NeuralKnowledgeGraph {
         Ok(predictions)
     }
 
+    /// The `top_k` strongest relationships the network predicts from `node_id` to nodes of the
+    /// same agent it isn't connected to yet
+    pub async fn predict_node_relationships(&self, node_id: &str, top_k: usize) -> Result<Vec<RelationshipPrediction>> {
+        let (current, candidates) = {
+            let graph = self.graph_structure.read().await;
+            let current = graph.nodes.get(node_id)
+                .ok_or_else(|| anyhow!("Node not found: {}", node_id))?
+                .clone();
+            let connected: HashSet<&String> = graph.adjacency.get(node_id).into_iter().flatten()
+                .chain(graph.reverse_adjacency.get(node_id).into_iter().flatten())
+                .collect();
+            let candidates: Vec<NeuralGraphNode> = graph.nodes.values()
+                .filter(|other| other.id != node_id && !connected.contains(&other.id))
+                .filter(|other| current.agent_id.is_none() || other.agent_id == current.agent_id)
+                .cloned()
+                .collect();
+            (current, candidates)
+        };
+        self.score_candidates(&current, candidates, top_k).await
+    }
+
+    /// The network's current prediction for one pair of nodes
+    pub async fn predict_relationship_between(&self, from_node: &str, to_node: &str) -> Result<RelationshipPrediction> {
+        let (current, other) = {
+            let graph = self.graph_structure.read().await;
+            let node = |id: &str| graph.nodes.get(id).cloned().ok_or_else(|| anyhow!("Node not found: {}", id));
+            (node(from_node)?, node(to_node)?)
+        };
+        self.score_candidates(&current, vec![other], 1).await?
+            .pop()
+            .ok_or_else(|| anyhow!("No embedding cached for {} or {}", from_node, to_node))
+    }
+
+    /// Rank `candidates` by attention score and classify the best `top_k`
+    async fn score_candidates(
+        &self,
+        current: &NeuralGraphNode,
+        candidates: Vec<NeuralGraphNode>,
+        top_k: usize,
+    ) -> Result<Vec<RelationshipPrediction>> {
+        let candidate_ids: Vec<String> = candidates.iter().map(|node| node.id.clone()).collect();
+        let mut scores = self.predict_relationships(&current.id, &candidate_ids).await?;
+        scores.truncate(top_k);
+        let by_id: HashMap<&str, &NeuralGraphNode> = candidates.iter().map(|node| (node.id.as_str(), node)).collect();
+        let top: Vec<(&NeuralGraphNode, f32)> = scores.iter()
+            .filter_map(|(id, score)| by_id.get(id.as_str()).map(|node| (*node, *score)))
+            .collect();
+
+        let metric = self.embedding_service.read().await.similarity_metric();
+        let similarities: Vec<f32> = top.iter()
+            .map(|(other, _)| match (&current.embedding, &other.embedding) {
+                (Some(a), Some(b)) => metric.similarities(a, &[b.as_slice()])[0],
+                _ => 0.0,
+            })
+            .collect();
+        let other_contents: Vec<&str> = top.iter().map(|(other, _)| other.content.as_str()).collect();
+        let context_similarities = self.context_similarities(&current.content, &other_contents).await?;
+
+        Ok(top.iter()
+            .zip(similarities.into_iter().zip(context_similarities))
+            .map(|((other, score), (similarity, context_similarity))| RelationshipPrediction {
+                from_node: current.id.clone(),
+                to_node: other.id.clone(),
+                to_node_name: other.name.clone(),
+                relationship_type: self.classify_relationship(current, other, similarity, context_similarity),
+                strength: *score,
+                similarity,
+            })
+            .collect())
+    }
+
+    /// Add the edge for an accepted prediction, dropping any pending suggestion for the pair
+    pub async fn accept_predicted_relationship(&mut self, prediction: &RelationshipPrediction) -> Result<String> {
+        if self.is_connected(&prediction.from_node, &prediction.to_node).await {
+            return Err(anyhow!("Nodes are already connected: {} -> {}", prediction.from_node, prediction.to_node));
+        }
+        let mut suggestions = self.edge_suggestions.write().await;
+        let before = suggestions.len();
+        suggestions.retain(|_, s| !(s.from_node == prediction.from_node && s.to_node == prediction.to_node));
+        let dropped = suggestions.len() < before;
+        drop(suggestions);
+        if dropped {
+            self.persist_suggestions().await;
+        }
+
+        self.create_neural_edge(
+            &prediction.from_node,
+            &prediction.to_node,
+            prediction.relationship_type.clone(),
+            prediction.strength,
+        ).await
+    }
+
+    pub async fn is_connected(&self, from_node: &str, to_node: &str) -> bool {
+        let graph = self.graph_structure.read().await;
+        graph.adjacency.get(from_node).is_some_and(|targets| targets.iter().any(|target| target == to_node))
+    }
+
+    pub async fn get_node(&self, node_id: &str) -> Option<NeuralGraphNode> {
+        self.graph_structure.read().await.nodes.get(node_id).cloned()
+    }
+
     /// Get graph statistics
     pub async fn get_statistics(&self) -> NeuralGraphStatistics {
         let graph = self.graph_structure.read().await;
@@ -591,6 +1265,111 @@ pub struct NeuralGraphStatistics {
     pub relationship_types: HashMap<String, usize>,
 }
 
+/// How two closely related statements disagree
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ContradictionKind {
+    /// One statement negates the other: "tokens expire" / "tokens don't expire"
+    Negation,
+    /// The statements use opposite terms: "enable caching" / "disable caching"
+    Opposite,
+}
+
+impl ContradictionKind {
+    /// Output of the contradiction network scoring this kind; output 0 scores "no contradiction"
+    fn output(self) -> usize {
+        match self {
+            Self::Negation => 1,
+            Self::Opposite => 2,
+        }
+    }
+}
+
+/// Score an output of the contradiction network must reach to count
+const CONTRADICTION_THRESHOLD: f32 = 0.5;
+
+/// Training steps per reviewed contradiction
+const CONTRADICTION_FEEDBACK_STEPS: usize = 20;
+
+/// Passes over the seed pairs when the contradiction network starts from fresh weights
+const CONTRADICTION_SEED_EPOCHS: usize = 200;
+
+/// Labelled statement pairs the contradiction network starts from, before any review
+const CONTRADICTION_SEED_PAIRS: &[(&str, &str, Option<ContradictionKind>)] = &[
+    ("Tokens expire after one hour", "Tokens don't expire after one hour", Some(ContradictionKind::Negation)),
+    ("The API requires authentication", "The API does not require authentication", Some(ContradictionKind::Negation)),
+    ("Retry failed uploads", "Never retry failed uploads", Some(ContradictionKind::Negation)),
+    ("Enable caching for the search API", "Disable caching for the search API", Some(ContradictionKind::Opposite)),
+    ("Requests to the service are allowed", "Requests to the service are denied", Some(ContradictionKind::Opposite)),
+    ("Increase the timeout for slow providers", "Decrease the timeout for slow providers", Some(ContradictionKind::Opposite)),
+    ("Tokens expire after one hour", "Tokens expire after 60 minutes", None),
+    ("Enable caching for the search API", "Turn on caching for the search API", None),
+    ("Retry failed uploads", "Failed uploads should be retried", None),
+];
+
+/// Decides whether two closely related statements contradict each other from their
+/// embeddings. Features are symmetric, so the order of the statements doesn't matter.
+#[derive(Debug, Clone)]
+pub struct ContradictionClassifier {
+    network: NeuralNetwork,
+    /// Embeddings are truncated or zero-padded to this length
+    embedding_dim: usize,
+}
+
+impl ContradictionClassifier {
+    pub fn new(embedding_dim: usize) -> Result<Self> {
+        let network = NetworkBuilder::new()
+            .input_layer(embedding_dim * 2)
+            .hidden_layer_with_activation(64, ActivationFunction::Tanh, 0.0)
+            .output_layer_with_activation(3, ActivationFunction::Sigmoid)
+            .learning_rate(0.1)
+            .build()?;
+        Ok(Self { network, embedding_dim })
+    }
+
+    /// Element-wise distance and agreement of the two embeddings
+    fn features(&self, a: &[f32], b: &[f32]) -> Vec<f32> {
+        let (a, b) = (fit_dim(a, self.embedding_dim), fit_dim(b, self.embedding_dim));
+        let difference = a.iter().zip(&b).map(|(x, y)| (x - y).abs());
+        let agreement = a.iter().zip(&b).map(|(x, y)| x * y);
+        difference.chain(agreement).collect()
+    }
+
+    /// How the statements with these embeddings contradict, if the network says they do
+    pub fn classify(&self, a: &[f32], b: &[f32]) -> Option<ContradictionKind> {
+        let scores = self.network.run(&self.features(a, b));
+        [ContradictionKind::Negation, ContradictionKind::Opposite].into_iter()
+            .filter(|kind| scores[kind.output()] >= CONTRADICTION_THRESHOLD && scores[kind.output()] > scores[0])
+            .max_by(|x, y| scores[x.output()].total_cmp(&scores[y.output()]))
+    }
+
+    /// Train towards `kind` for this pair, `None` meaning they don't contradict
+    pub fn train(&mut self, a: &[f32], b: &[f32], kind: Option<ContradictionKind>, steps: usize) -> Result<f32> {
+        let features = self.features(a, b);
+        let mut target = vec![0.0; 3];
+        target[kind.map_or(0, ContradictionKind::output)] = 1.0;
+        let mut error = 0.0;
+        for _ in 0..steps {
+            error = self.network.train_incremental(&features, &target)?;
+        }
+        Ok(error)
+    }
+
+    fn save_model(&self, path: &std::path::Path) -> Result<()> {
+        self.network.save_model(path)
+    }
+
+    fn load_saved(&mut self, path: &std::path::Path) -> Result<bool> {
+        self.network.load_saved(path)
+    }
+}
+
+/// `values` truncated or zero-padded to `len`, so embeddings of any size fit a network input
+fn fit_dim(values: &[f32], len: usize) -> Vec<f32> {
+    let mut fitted: Vec<f32> = values.iter().copied().take(len).collect();
+    fitted.resize(len, 0.0);
+    fitted
+}
+
 /// Cosine similarity function for embeddings
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
@@ -668,6 +1447,9 @@ mod tests {
                 adjacency: HashMap::new(),
                 reverse_adjacency: HashMap::new(),
             })),
+            edge_suggestions: Arc::new(RwLock::new(HashMap::new())),
+            relationship_feedback: RelationshipFeedbackStats::default(),
+            contradiction_classifier: ContradictionClassifier::new(4).unwrap(),
             config: NeuralGraphConfig::default(),
         };
         
@@ -676,4 +1458,133 @@ mod tests {
         assert_eq!(encoding[0], 1.0);
         assert!(encoding[1..].iter().all(|&x| x == 0.0));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_suggestion_feedback_adjusts_thresholds() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = NeuralGraphConfig { model_dir: Some(dir.path().to_path_buf()), ..Default::default() };
+        let mut graph = NeuralKnowledgeGraph::new(Some(config)).await.unwrap();
+
+        graph.apply_suggestion_feedback(true, 0.6);
+        assert!((graph.config().similarity_threshold - 0.69).abs() < 1e-5);
+
+        graph.apply_suggestion_feedback(false, 0.6);
+        assert!((graph.config().suggestion_threshold - 0.51).abs() < 1e-5);
+        assert!(graph.config().suggestion_threshold <= graph.config().similarity_threshold);
+
+        assert!(graph.list_edge_suggestions(None).await.is_empty());
+        assert!(graph.reject_edge_suggestion("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_suggestions_and_thresholds_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        // Every related pair lands in the review queue
+        let config = NeuralGraphConfig {
+            similarity_threshold: 1.1,
+            suggestion_threshold: -1.0,
+            model_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let mut graph = NeuralKnowledgeGraph::new(Some(config.clone())).await.unwrap();
+        for content in ["Build broke on a stale lockfile", "Regenerated the lockfile", "Release notes drafted"] {
+            let memory = AgentMemory::new("agent_a".to_string(), MemoryType::Task, content.to_string());
+            graph.add_memory_node(&memory).await.unwrap();
+        }
+        let pending = graph.list_edge_suggestions(None).await;
+        assert_eq!(pending.len(), 3);
+
+        graph.reject_edge_suggestion(&pending[0].id).await.unwrap();
+        let tuned = graph.config().suggestion_threshold;
+        assert!(tuned > -1.0);
+
+        let reloaded = NeuralKnowledgeGraph::new(Some(config)).await.unwrap();
+        assert_eq!(reloaded.config().suggestion_threshold, tuned);
+        assert_eq!(reloaded.config().similarity_threshold, 1.1);
+        let ids: HashSet<String> = reloaded.list_edge_suggestions(None).await.into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, pending[1..].iter().map(|s| s.id.clone()).collect());
+    }
+
+    #[tokio::test]
+    async fn test_predictions_skip_connected_and_foreign_nodes() {
+        // Thresholds out of reach, so discovery leaves the nodes unconnected
+        let config = NeuralGraphConfig { similarity_threshold: 1.1, suggestion_threshold: 1.1, ..Default::default() };
+        let mut graph = NeuralKnowledgeGraph::new(Some(config)).await.unwrap();
+        let contents = ["Deploy failed on missing env var", "Set the env var and redeployed", "Weekly planning notes"];
+        let mut node_ids = Vec::new();
+        for (i, content) in contents.iter().enumerate() {
+            let memory_type = if i == 0 { MemoryType::Error } else { MemoryType::Success };
+            let memory = AgentMemory::new("agent_a".to_string(), memory_type, content.to_string());
+            node_ids.push(graph.add_memory_node(&memory).await.unwrap());
+        }
+        let foreign = AgentMemory::new("agent_b".to_string(), MemoryType::Task, "Deploy the service".to_string());
+        let foreign_id = graph.add_memory_node(&foreign).await.unwrap();
+
+        let predictions = graph.predict_node_relationships(&node_ids[0], 10).await.unwrap();
+        for prediction in &predictions {
+            assert_ne!(prediction.to_node, foreign_id);
+            assert!(!graph.is_connected(&node_ids[0], &prediction.to_node).await);
+            assert!(prediction.strength > 0.0 && prediction.strength < 1.0);
+        }
+
+        assert_eq!(predictions.len(), 2);
+        let prediction = &predictions[0];
+        graph.accept_predicted_relationship(prediction).await.unwrap();
+        assert!(graph.is_connected(&prediction.from_node, &prediction.to_node).await);
+        assert!(graph.accept_predicted_relationship(prediction).await.is_err());
+        let remaining = graph.predict_node_relationships(&node_ids[0], 10).await.unwrap();
+        assert!(remaining.iter().all(|p| p.to_node != prediction.to_node));
+    }
+
+    #[tokio::test]
+    async fn test_relationship_feedback_trains_and_removes_rejected_edges() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = NeuralGraphConfig {
+            similarity_threshold: 1.1,
+            suggestion_threshold: 1.1,
+            model_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let mut graph = NeuralKnowledgeGraph::new(Some(config.clone())).await.unwrap();
+        let mut node_ids = Vec::new();
+        for content in ["Build broke on a stale lockfile", "Regenerated the lockfile", "Release notes drafted"] {
+            let memory = AgentMemory::new("agent_a".to_string(), MemoryType::Task, content.to_string());
+            node_ids.push(graph.add_memory_node(&memory).await.unwrap());
+        }
+        let mut edge_ids = Vec::new();
+        for to in &node_ids[1..] {
+            let prediction = graph.predict_relationship_between(&node_ids[0], to).await.unwrap();
+            edge_ids.push(graph.accept_predicted_relationship(&prediction).await.unwrap());
+        }
+
+        let stats = graph.record_relationship_feedback(&edge_ids[0], true).await.unwrap();
+        assert_eq!((stats.confirmed, stats.rejected), (1, 0));
+        assert!(graph.is_connected(&node_ids[0], &node_ids[1]).await);
+
+        let stats = graph.record_relationship_feedback(&edge_ids[1], false).await.unwrap();
+        assert_eq!((stats.confirmed, stats.rejected), (1, 1));
+        assert!(!graph.is_connected(&node_ids[0], &node_ids[2]).await);
+        assert!(graph.record_relationship_feedback(&edge_ids[1], true).await.is_err());
+        // Too few reviews for the learned scores to override the rules yet
+        assert_eq!(stats.learned_weight, 0.0);
+
+        let reloaded = NeuralKnowledgeGraph::new(Some(config)).await.unwrap();
+        assert_eq!(reloaded.relationship_feedback.total(), 2);
+    }
+
+    #[test]
+    fn test_contradiction_classifier_learns_from_review() {
+        let mut classifier = ContradictionClassifier::new(4).unwrap();
+        let (a, b) = (vec![1.0, 0.2, 0.0, 0.5], vec![0.9, 0.3, 0.4, 0.5]);
+        let (c, d) = (vec![0.1, 0.8, 0.6, 0.0], vec![0.1, 0.8, 0.6, 0.1]);
+
+        for _ in 0..50 {
+            classifier.train(&a, &b, Some(ContradictionKind::Opposite), 1).unwrap();
+            classifier.train(&c, &d, None, 1).unwrap();
+        }
+        assert_eq!(classifier.classify(&a, &b), Some(ContradictionKind::Opposite));
+        // The order of the statements doesn't matter
+        assert_eq!(classifier.classify(&b, &a), Some(ContradictionKind::Opposite));
+        assert_eq!(classifier.classify(&c, &d), None);
+    }
+}
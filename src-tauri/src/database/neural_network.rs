@@ -1,11 +1,43 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, Context};
 use fastrand;
-use ndarray::{Array2, Array1};
+use ndarray::{Array2, Array1, ArrayView1};
+use rayon::prelude::*;
 use std::collections::HashMap;
+use std::path::Path;
 use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use super::accelerated_inference;
+
+/// Rows per thread in `run_batch`; smaller batches run as a single matrix product
+const BATCH_CHUNK_ROWS: usize = 64;
+
+/// Bumped whenever a saved model's layout changes, so old files are ignored instead of misread
+const MODEL_FORMAT_VERSION: u32 = 1;
+
+/// Write a model with bincode, replacing any previous file only once the new one is complete
+pub fn write_model_file<T: Serialize>(path: &Path, model: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, bincode::serialize(&(MODEL_FORMAT_VERSION, model))?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Read a model written by `write_model_file`
+pub fn read_model_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read model {}", path.display()))?;
+    let (version, model): (u32, T) = bincode::deserialize(&bytes)
+        .with_context(|| format!("Model {} is corrupt", path.display()))?;
+    if version != MODEL_FORMAT_VERSION {
+        return Err(anyhow!("Model {} has format version {}, expected {}", path.display(), version, MODEL_FORMAT_VERSION));
+    }
+    Ok(model)
+}
 
 /// Activation functions for neural networks
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ActivationFunction {
     Linear,
     Sigmoid,
@@ -182,7 +214,7 @@ impl NetworkBuilder {
 }
 
 /// FANN-inspired Neural Network
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NeuralNetwork {
     layers: Vec<LayerConfig>,
     weights: Vec<Array2<f32>>,
@@ -233,7 +265,65 @@ impl NeuralNetwork {
         activations.to_vec()
     }
 
-    /// Get network statistics 
+    /// Run a forward pass for many inputs at once, as one matrix product per layer, on the
+    /// GPU when one is available and the batch is large, otherwise splitting large batches
+    /// across threads. Inputs of the wrong size give zeros, as with `run`.
+    pub fn run_batch(&self, inputs: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        if inputs.len() >= accelerated_inference::MIN_ACCELERATED_ROWS {
+            if let Some(outputs) = accelerated_inference::forward(&self.input_matrix(inputs), &self.layer_params()) {
+                return self.output_rows(outputs, inputs);
+            }
+        }
+        if inputs.len() <= BATCH_CHUNK_ROWS {
+            return self.run_matrix(inputs);
+        }
+        inputs.par_chunks(BATCH_CHUNK_ROWS)
+            .map(|chunk| self.run_matrix(chunk))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    fn run_matrix(&self, inputs: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        let mut activations = self.input_matrix(inputs);
+
+        // One row per input: outputs = activation(inputs * weights^T + bias)
+        for (weights, biases, activation) in self.layer_params() {
+            activations = (activations.dot(&weights.t()) + biases).mapv(|x| activation.apply(x));
+        }
+
+        self.output_rows(activations, inputs)
+    }
+
+    /// One row per input, with wrong-size inputs left as zeros
+    fn input_matrix(&self, inputs: &[Vec<f32>]) -> Array2<f32> {
+        let input_size = self.num_inputs();
+        let mut matrix = Array2::zeros((inputs.len(), input_size));
+        for (mut row, input) in matrix.rows_mut().into_iter().zip(inputs) {
+            if input.len() == input_size {
+                row.assign(&ArrayView1::from(input.as_slice()));
+            }
+        }
+        matrix
+    }
+
+    fn output_rows(&self, outputs: Array2<f32>, inputs: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        let input_size = self.num_inputs();
+        outputs.rows().into_iter().zip(inputs)
+            .map(|(row, input)| if input.len() == input_size { row.to_vec() } else { vec![0.0; self.num_outputs()] })
+            .collect()
+    }
+
+    /// Weights, biases and activation of each layer after the input layer
+    pub(crate) fn layer_params(&self) -> Vec<(&Array2<f32>, &Array1<f32>, ActivationFunction)> {
+        self.weights.iter()
+            .zip(&self.biases)
+            .zip(&self.layers[1..])
+            .map(|((weights, biases), layer)| (weights, biases, layer.activation))
+            .collect()
+    }
+
     pub fn num_layers(&self) -> usize {
         self.layers.len()
     }
@@ -254,6 +344,46 @@ impl NeuralNetwork {
         self.weights.iter().map(|w| w.len()).sum()
     }
 
+    /// Whether both networks have the same layer sizes and activations, so weights can be swapped
+    pub fn same_architecture(&self, other: &NeuralNetwork) -> bool {
+        self.layers.len() == other.layers.len()
+            && self.layers.iter().zip(&other.layers)
+                .all(|(a, b)| a.size == b.size && a.activation == b.activation)
+    }
+
+    /// Save the layers, weights and learning rate
+    pub fn save_model(&self, path: &Path) -> Result<()> {
+        write_model_file(path, self)
+    }
+
+    /// Load a network saved with `save_model`
+    pub fn load_model(path: &Path) -> Result<Self> {
+        let network: Self = read_model_file(path)?;
+        let consistent = network.layers.len() >= 2
+            && network.weights.len() == network.layers.len() - 1
+            && network.biases.len() == network.weights.len()
+            && network.weights.iter().zip(&network.biases).zip(network.layers.windows(2))
+                .all(|((w, b), l)| w.dim() == (l[1].size, l[0].size) && b.len() == l[1].size);
+        if !consistent {
+            return Err(anyhow!("Model {} has weights that don't match its layers", path.display()));
+        }
+        Ok(network)
+    }
+
+    /// Replace this network with the one saved at `path` if there is one. Fails when the
+    /// saved network has a different architecture, e.g. after the embedding size changed.
+    pub fn load_saved(&mut self, path: &Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+        let saved = Self::load_model(path)?;
+        if !self.same_architecture(&saved) {
+            return Err(anyhow!("Model {} was saved with a different architecture", path.display()));
+        }
+        *self = saved;
+        Ok(true)
+    }
+
     /// Get all weights as a flat vector
     pub fn get_weights(&self) -> Vec<f32> {
         let mut all_weights = Vec::new();
@@ -489,6 +619,50 @@ mod tests {
         assert_eq!(output.len(), 1);
     }
 
+    #[test]
+    fn test_save_and_load_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("network.bin");
+        let network = NetworkBuilder::new()
+            .input_layer(3)
+            .hidden_layer_with_activation(4, ActivationFunction::Tanh, 0.0)
+            .output_layer(2)
+            .build()
+            .unwrap();
+        network.save_model(&path).unwrap();
+
+        let loaded = NeuralNetwork::load_model(&path).unwrap();
+        assert!(loaded.same_architecture(&network));
+        assert_eq!(loaded.get_weights(), network.get_weights());
+        assert_eq!(loaded.run(&[0.1, 0.2, 0.3]), network.run(&[0.1, 0.2, 0.3]));
+
+        let mut other = NeuralNetwork::new(&[3, 5, 2]).unwrap();
+        assert!(other.load_saved(&path).is_err());
+        assert!(!other.load_saved(&dir.path().join("missing.bin")).unwrap());
+    }
+
+    #[test]
+    fn test_run_batch_matches_run() {
+        let network = NetworkBuilder::new()
+            .input_layer(4)
+            .hidden_layer_with_activation(6, ActivationFunction::GELU, 0.0)
+            .output_layer_with_activation(3, ActivationFunction::Tanh)
+            .build()
+            .unwrap();
+
+        // Enough rows to be split across threads, plus one of the wrong size
+        let mut inputs: Vec<Vec<f32>> = (0..150).map(|i| (0..4).map(|j| ((i * 4 + j) as f32).sin()).collect()).collect();
+        inputs.push(vec![1.0, 2.0]);
+
+        let outputs = network.run_batch(&inputs);
+        assert_eq!(outputs.len(), inputs.len());
+        for (input, output) in inputs.iter().zip(&outputs) {
+            let expected = network.run(input);
+            assert!(expected.iter().zip(output).all(|(a, b)| (a - b).abs() < 1e-5));
+        }
+        assert_eq!(outputs.last().unwrap(), &vec![0.0; 3]);
+    }
+
     #[test]
     fn test_xor_training() {
         let mut network = NetworkBuilder::new()
@@ -161,6 +161,8 @@ mod comprehensive_neural_tests {
                 learning_rate: 0.005,
                 training_epochs: 50,
                 cache_size_limit: 5000,
+                similarity_metric: super::super::embeddings::SimilarityMetric::DotProduct,
+                ..Default::default()
             }),
         ];
 
@@ -247,9 +249,13 @@ mod comprehensive_neural_tests {
             ("Learning memory".to_string(), Some(MemoryType::Learning)),
             ("Pattern memory".to_string(), Some(MemoryType::Pattern)),
         ];
+        let single = service.embed_text("Learning memory", Some(MemoryType::Learning)).await.unwrap();
+        service.clear_cache().await;
 
         let embeddings = service.embed_batch(&batch).await.unwrap();
         assert_eq!(embeddings.len(), batch.len());
+        // Batched inference matches one-at-a-time inference
+        assert!(single.iter().zip(&embeddings[1]).all(|(a, b)| (a - b).abs() < 1e-5));
         
         for embedding in &embeddings {
             assert!(!embedding.is_empty());
@@ -350,8 +356,10 @@ mod comprehensive_neural_tests {
                 max_neighbors: 25,
                 temporal_window_hours: 12,
                 similarity_threshold: 0.8,
+                suggestion_threshold: 0.6,
                 learning_rate: 0.005,
                 cache_size_limit: 5000,
+                model_dir: None,
             }),
         ];
 
@@ -0,0 +1,309 @@
+//! Notification center. Every notification is stored with its severity, source, actions and read
+//! state, so agent completions, failures and security alerts outlast the toast that announced
+//! them. Storing one also shows it (natively, or in-app as a fallback) and tells the UI.
+
+use super::{open_conversation_db, open_db, parse_db_timestamp};
+use crate::ai::{dispatch_notification, NotificationAction, NotificationPayload, NotificationSession, NotificationState};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info};
+
+/// Event emitted with each `StoredNotification` as it is created
+pub const NOTIFICATION_STORED_EVENT: &str = "notification_stored";
+
+/// Event emitted with a `NotificationReadEvent` when notifications are marked read
+pub const NOTIFICATIONS_READ_EVENT: &str = "notifications_read";
+
+/// Notifications kept; the oldest are dropped first
+const MAX_STORED_NOTIFICATIONS: i64 = 1000;
+
+const MAX_LIST_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationSeverity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl NotificationSeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Success => "success",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+
+    /// Severity for a notification type from the frontend; unknown types are informational
+    pub fn from_kind(kind: &str) -> Self {
+        match kind {
+            "success" => Self::Success,
+            "warning" => Self::Warning,
+            "error" => Self::Error,
+            _ => Self::Info,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredNotification {
+    pub id: String,
+    pub severity: NotificationSeverity,
+    /// What raised it, e.g. `agent_workflow`, `task_queue` or `security`
+    pub source: String,
+    pub title: String,
+    pub message: String,
+    pub actions: Vec<NotificationAction>,
+    pub session: Option<NotificationSession>,
+    /// Data the UI needs to act on it, such as a run or task id
+    pub payload: Option<Value>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewNotification {
+    pub severity: NotificationSeverity,
+    pub source: String,
+    pub title: String,
+    pub message: String,
+    #[serde(default)]
+    pub actions: Vec<NotificationAction>,
+    pub session: Option<NotificationSession>,
+    pub payload: Option<Value>,
+}
+
+impl NewNotification {
+    pub fn new(severity: NotificationSeverity, source: &str, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            source: source.to_string(),
+            title: title.into(),
+            message: message.into(),
+            actions: Vec::new(),
+            session: None,
+            payload: None,
+        }
+    }
+
+    pub fn with_payload(mut self, payload: Value) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationReadEvent {
+    pub notification_ids: Vec<String>,
+    /// Notifications still unread afterwards
+    pub unread: i64,
+}
+
+const NOTIFICATION_COLUMNS: &str = "id, severity, source, title, message, actions, session, payload, read_at, created_at";
+
+fn notification_from_row(row: &rusqlite::Row) -> rusqlite::Result<StoredNotification> {
+    let severity: String = row.get(1)?;
+    let actions: String = row.get(5)?;
+    let session: Option<String> = row.get(6)?;
+    let payload: Option<String> = row.get(7)?;
+    let read_at: Option<String> = row.get(8)?;
+    let created_at: String = row.get(9)?;
+    Ok(StoredNotification {
+        id: row.get(0)?,
+        severity: NotificationSeverity::from_kind(&severity),
+        source: row.get(2)?,
+        title: row.get(3)?,
+        message: row.get(4)?,
+        actions: serde_json::from_str(&actions).unwrap_or_default(),
+        session: session.and_then(|s| serde_json::from_str(&s).ok()),
+        payload: payload.and_then(|p| serde_json::from_str(&p).ok()),
+        read_at: read_at.as_deref().map(parse_db_timestamp),
+        created_at: parse_db_timestamp(&created_at),
+    })
+}
+
+pub fn find_notification(conn: &Connection, id: &str) -> Result<StoredNotification> {
+    conn.query_row(
+        &format!("SELECT {} FROM notifications WHERE id = ?1", NOTIFICATION_COLUMNS),
+        params![id],
+        notification_from_row,
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => anyhow!("Unknown notification: {}", id),
+        e => e.into(),
+    })
+}
+
+/// Store a notification under `id`, dropping the oldest beyond the limit
+pub fn insert_notification(conn: &Connection, id: &str, notification: &NewNotification) -> Result<StoredNotification> {
+    conn.execute(
+        "INSERT INTO notifications (id, severity, source, title, message, actions, session, payload)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            id,
+            notification.severity.as_str(),
+            notification.source,
+            notification.title,
+            notification.message,
+            serde_json::to_string(&notification.actions)?,
+            notification.session.as_ref().map(serde_json::to_string).transpose()?,
+            notification.payload.as_ref().map(Value::to_string),
+        ],
+    )?;
+    conn.execute(
+        "DELETE FROM notifications WHERE id NOT IN
+            (SELECT id FROM notifications ORDER BY created_at DESC, rowid DESC LIMIT ?1)",
+        params![MAX_STORED_NOTIFICATIONS],
+    )?;
+    find_notification(conn, id)
+}
+
+/// Newest first
+pub fn list_stored_notifications(conn: &Connection, unread_only: bool, source: Option<&str>, limit: usize) -> Result<Vec<StoredNotification>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM notifications
+         WHERE (?1 = 0 OR read_at IS NULL) AND (?2 IS NULL OR source = ?2)
+         ORDER BY created_at DESC, rowid DESC LIMIT ?3",
+        NOTIFICATION_COLUMNS
+    ))?;
+    let notifications = stmt.query_map(params![unread_only, source, limit as i64], notification_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(notifications)
+}
+
+/// Mark the given notifications read, or all of them; returns the ids that were unread
+pub fn mark_read(conn: &Connection, ids: Option<&[String]>) -> Result<Vec<String>> {
+    let unread: Vec<String> = conn.prepare("SELECT id FROM notifications WHERE read_at IS NULL")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?
+        .into_iter()
+        .filter(|id| ids.is_none_or(|ids| ids.contains(id)))
+        .collect();
+    for id in &unread {
+        conn.execute("UPDATE notifications SET read_at = CURRENT_TIMESTAMP WHERE id = ?1", params![id])?;
+    }
+    Ok(unread)
+}
+
+pub fn unread_count(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("SELECT COUNT(*) FROM notifications WHERE read_at IS NULL", [], |row| row.get(0))?)
+}
+
+/// Store a notification, announce it to the UI and show it
+pub fn notify(app: &AppHandle, notification: NewNotification) -> Result<StoredNotification> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let stored = insert_notification(&open_conversation_db(app)?, &id, &notification)?;
+    if let Err(e) = app.emit(NOTIFICATION_STORED_EVENT, &stored) {
+        error!("Failed to emit stored notification: {}", e);
+    }
+    dispatch_notification(app, &app.state::<NotificationState>(), NotificationPayload {
+        notification_id: stored.id.clone(),
+        title: stored.title.clone(),
+        message: stored.message.clone(),
+        kind: stored.severity.as_str().to_string(),
+        actions: stored.actions.clone(),
+        session: stored.session.clone(),
+    });
+    Ok(stored)
+}
+
+/// `notify` for background work, where a notification that cannot be stored is only logged
+pub fn notify_quietly(app: &AppHandle, notification: NewNotification) {
+    let source = notification.source.clone();
+    if let Err(e) = notify(app, notification) {
+        error!("Failed to store {} notification: {:#}", source, e);
+    }
+}
+
+fn emit_read(app_handle: &AppHandle, conn: &Connection, notification_ids: Vec<String>) -> Result<(), String> {
+    let unread = unread_count(conn).map_err(|e| format!("Failed to count notifications: {}", e))?;
+    if let Err(e) = app_handle.emit(NOTIFICATIONS_READ_EVENT, &NotificationReadEvent { notification_ids, unread }) {
+        error!("Failed to emit notification read event: {}", e);
+    }
+    Ok(())
+}
+
+// Tauri Commands
+
+/// Stored notifications, newest first
+#[tauri::command]
+pub async fn list_notifications(
+    unread_only: Option<bool>,
+    source: Option<String>,
+    limit: Option<usize>,
+    app_handle: AppHandle,
+) -> Result<Vec<StoredNotification>, String> {
+    let limit = limit.unwrap_or(100).min(MAX_LIST_LIMIT);
+    list_stored_notifications(&open_db(&app_handle)?, unread_only.unwrap_or(false), source.as_deref(), limit)
+        .map_err(|e| format!("Failed to list notifications: {}", e))
+}
+
+#[tauri::command]
+pub async fn mark_notification_read(notification_id: String, app_handle: AppHandle) -> Result<(), String> {
+    let conn = open_db(&app_handle)?;
+    find_notification(&conn, &notification_id).map_err(|e| e.to_string())?;
+    let marked = mark_read(&conn, Some(&[notification_id]))
+        .map_err(|e| format!("Failed to mark notification read: {}", e))?;
+    if !marked.is_empty() {
+        emit_read(&app_handle, &conn, marked)?;
+    }
+    Ok(())
+}
+
+/// Returns how many notifications were unread
+#[tauri::command]
+pub async fn mark_all_notifications_read(app_handle: AppHandle) -> Result<usize, String> {
+    let conn = open_db(&app_handle)?;
+    let marked = mark_read(&conn, None).map_err(|e| format!("Failed to mark notifications read: {}", e))?;
+    let count = marked.len();
+    if count > 0 {
+        emit_read(&app_handle, &conn, marked)?;
+        info!("Marked {} notifications read", count);
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+    use serde_json::json;
+
+    #[test]
+    fn test_store_list_and_mark_read() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+
+        let done = NewNotification::new(NotificationSeverity::Success, "agent_workflow", "Workflow finished", "release ran")
+            .with_payload(json!({ "run_id": "r1" }));
+        insert_notification(&conn, "n1", &done).unwrap();
+        let mut alert = NewNotification::new(NotificationSeverity::Warning, "security", "Blocked", "guardrail matched");
+        alert.session = Some(NotificationSession { agent_id: "coder".into(), session_id: "s1".into() });
+        let stored = insert_notification(&conn, "n2", &alert).unwrap();
+        assert_eq!(stored.session.unwrap().agent_id, "coder");
+        assert!(stored.read_at.is_none());
+
+        let all = list_stored_notifications(&conn, false, None, 10).unwrap();
+        assert_eq!(all.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), vec!["n2", "n1"]);
+        assert_eq!(all[1].payload, Some(json!({ "run_id": "r1" })));
+        assert_eq!(all[1].severity, NotificationSeverity::Success);
+        assert_eq!(list_stored_notifications(&conn, false, Some("security"), 10).unwrap().len(), 1);
+
+        assert_eq!(mark_read(&conn, Some(&["n1".to_string()])).unwrap(), vec!["n1".to_string()]);
+        assert!(mark_read(&conn, Some(&["n1".to_string()])).unwrap().is_empty());
+        assert!(find_notification(&conn, "n1").unwrap().read_at.is_some());
+        let unread = list_stored_notifications(&conn, true, None, 10).unwrap();
+        assert_eq!(unread.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), vec!["n2"]);
+
+        assert_eq!(mark_read(&conn, None).unwrap(), vec!["n2".to_string()]);
+        assert_eq!(unread_count(&conn).unwrap(), 0);
+        assert!(find_notification(&conn, "missing").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a statement waits on another connection's write lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a caller waits for a free pooled connection
+const CHECKOUT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const MAX_CONNECTIONS_PER_DB: u32 = 8;
+
+/// Prepared statements each connection keeps for `prepare_cached`
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+pub type PooledConnection = r2d2::PooledConnection<SqliteConnectionManager>;
+
+/// Opens memory database connections with the settings every caller relies on
+pub struct SqliteConnectionManager {
+    path: PathBuf,
+    /// SQLCipher key, hex encoded, for an encrypted database
+    key: Option<String>,
+}
+
+impl std::fmt::Debug for SqliteConnectionManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteConnectionManager")
+            .field("path", &self.path)
+            .field("encrypted", &self.key.is_some())
+            .finish()
+    }
+}
+
+impl r2d2::ManageConnection for SqliteConnectionManager {
+    type Connection = Connection;
+    type Error = rusqlite::Error;
+
+    fn connect(&self) -> Result<Connection, rusqlite::Error> {
+        let conn = Connection::open(&self.path)?;
+        if let Some(ref key) = self.key {
+            super::memory_encryption::apply_key(&conn, key)?;
+        }
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA foreign_keys = ON;
+             PRAGMA synchronous = NORMAL;
+             PRAGMA cache_size = -64000;",
+        )?;
+        Ok(conn)
+    }
+
+    fn is_valid(&self, conn: &mut Connection) -> Result<(), rusqlite::Error> {
+        conn.execute_batch("")
+    }
+
+    /// A connection handed back inside a transaction (e.g. one left open by a panic) would
+    /// keep its locks and leak uncommitted state into the next checkout, so it is discarded
+    fn has_broken(&self, conn: &mut Connection) -> bool {
+        !conn.is_autocommit()
+    }
+}
+
+/// One pool per database file, shared by every manager that opens it
+static POOLS: Lazy<Mutex<HashMap<PathBuf, r2d2::Pool<SqliteConnectionManager>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Databases whose file is being replaced; checkouts are refused until the swap finishes.
+/// Always locked after `POOLS`.
+static BLOCKED: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn pool_for(path: &Path) -> Result<r2d2::Pool<SqliteConnectionManager>> {
+    let mut pools = POOLS.lock().map_err(|_| anyhow!("Connection pool registry poisoned"))?;
+    if BLOCKED.lock().map_err(|_| anyhow!("Blocked database registry poisoned"))?.contains(path) {
+        return Err(anyhow!("Database {} is being migrated", path.display()));
+    }
+    if let Some(pool) = pools.get(path) {
+        return Ok(pool.clone());
+    }
+
+    let pool = r2d2::Pool::builder()
+        .max_size(MAX_CONNECTIONS_PER_DB)
+        .min_idle(Some(0))
+        .connection_timeout(CHECKOUT_TIMEOUT)
+        .build(SqliteConnectionManager {
+            path: path.to_path_buf(),
+            key: super::memory_encryption::database_key(path)?,
+        })?;
+    pools.insert(path.to_path_buf(), pool.clone());
+    Ok(pool)
+}
+
+/// Check out a connection to the database at `path`, in WAL mode with foreign keys on
+pub fn connection(path: &Path) -> Result<PooledConnection> {
+    Ok(pool_for(path)?.get()?)
+}
+
+/// Keeps a database's pool closed to new checkouts until dropped
+pub struct PoolBlock {
+    path: PathBuf,
+}
+
+impl Drop for PoolBlock {
+    fn drop(&mut self) {
+        if let Ok(mut blocked) = BLOCKED.lock() {
+            blocked.remove(&self.path);
+        }
+    }
+}
+
+/// Close the pool for `path` and refuse new checkouts, then wait up to `timeout` for the
+/// connections already checked out to come back, so the file can be replaced without a
+/// write landing in the old copy
+pub fn block_pool(path: &Path, timeout: Duration) -> Result<PoolBlock> {
+    let pool = {
+        let mut pools = POOLS.lock().map_err(|_| anyhow!("Connection pool registry poisoned"))?;
+        let mut blocked = BLOCKED.lock().map_err(|_| anyhow!("Blocked database registry poisoned"))?;
+        if !blocked.insert(path.to_path_buf()) {
+            return Err(anyhow!("Database {} is already being migrated", path.display()));
+        }
+        pools.remove(path)
+    };
+    let block = PoolBlock { path: path.to_path_buf() };
+
+    if let Some(pool) = pool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let state = pool.state();
+            if state.idle_connections == state.connections {
+                break;
+            }
+            if Instant::now() >= deadline {
+                let in_use = state.connections - state.idle_connections;
+                // Leave the pool as it was, so the connections still out keep working
+                if let Ok(mut pools) = POOLS.lock() {
+                    pools.entry(path.to_path_buf()).or_insert(pool);
+                }
+                return Err(anyhow!(
+                    "{} connection(s) to {} are still in use",
+                    in_use,
+                    path.display()
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+    Ok(block)
+}
+
+/// Checkpoint the WAL of every pooled database into its main file and close the pools, as on
+/// app exit. Returns the databases that could not be checkpointed.
+pub fn checkpoint_all() -> Vec<(PathBuf, anyhow::Error)> {
+    let pools: Vec<(PathBuf, r2d2::Pool<SqliteConnectionManager>)> = match POOLS.lock() {
+        Ok(mut pools) => pools.drain().collect(),
+        Err(_) => return Vec::new(),
+    };
+    pools.into_iter()
+        .filter_map(|(path, pool)| {
+            let checkpointed = pool.get()
+                .map_err(anyhow::Error::from)
+                .and_then(|conn| Ok(conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?));
+            checkpointed.err().map(|e| (path, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pooled_connections_share_a_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+
+        let first = connection(&path).unwrap();
+        first.execute_batch("CREATE TABLE items (id INTEGER PRIMARY KEY); INSERT INTO items VALUES (1);").unwrap();
+        let mode: String = first.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(mode, "wal");
+
+        // A second checkout while the first is held gets its own connection to the same file
+        let second = connection(&path).unwrap();
+        let foreign_keys: bool = second.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+        assert!(foreign_keys);
+        let count: i64 = second.prepare_cached("SELECT COUNT(*) FROM items").unwrap()
+            .query_row([], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_connection_left_in_a_transaction_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+        connection(&path).unwrap().execute_batch("CREATE TABLE items (id INTEGER PRIMARY KEY);").unwrap();
+
+        let abandoned = connection(&path).unwrap();
+        abandoned.execute_batch("BEGIN IMMEDIATE; INSERT INTO items VALUES (1);").unwrap();
+        drop(abandoned);
+
+        // Had the connection gone back into the pool, its write lock would block this insert
+        let next = connection(&path).unwrap();
+        assert!(next.is_autocommit());
+        next.execute("INSERT INTO items VALUES (2)", []).unwrap();
+        let count: i64 = next.query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_block_waits_for_checked_out_connections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+        let held = connection(&path).unwrap();
+
+        assert!(block_pool(&path, Duration::from_millis(50)).is_err());
+        drop(held);
+
+        let block = block_pool(&path, Duration::from_millis(50)).unwrap();
+        assert!(connection(&path).is_err());
+        drop(block);
+        assert!(connection(&path).is_ok());
+    }
+}
@@ -0,0 +1,437 @@
+//! Prompt template library. Templates are prompts with `{{variable}}` placeholders, versioned on
+//! every body change. Rendering is strict: every placeholder needs a value and every value must be
+//! used. Workflow steps can name a template instead of an inline prompt, and a rendered template
+//! can start an agent turn the same way a resolved MCP prompt does.
+
+use super::{open_db, parse_db_timestamp};
+use crate::mcp::{MCPPromptMessage, MCPPromptRun, PROMPT_TURN_EVENT};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter};
+use tracing::info;
+
+/// `server_id` of prompt runs that come from the local template library
+pub const PROMPT_TEMPLATE_SOURCE: &str = "prompt_templates";
+
+const MAX_TEMPLATE_CHARS: usize = 100_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub body: String,
+    /// Placeholders in `body`, in order of first appearance
+    pub variables: Vec<String>,
+    /// Version of `body`
+    pub version: i64,
+    pub latest_version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptTemplateVersion {
+    pub template_id: String,
+    pub version: i64,
+    pub body: String,
+    pub variables: Vec<String>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Changes to a template; a new body becomes a new version
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptTemplateUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub body: Option<String>,
+    /// Describes the new version
+    pub note: Option<String>,
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+fn parse_template(body: &str) -> Result<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or_else(|| {
+            anyhow!("Unclosed {{{{ at character {}", body.len() - rest.len() + start)
+        })?;
+        let name = after[..end].trim();
+        if !is_variable_name(name) {
+            bail!("Invalid variable name '{}'", name);
+        }
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        segments.push(Segment::Variable(name));
+        rest = &after[end + 2..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    Ok(segments)
+}
+
+/// Placeholders in `body`, in order of first appearance
+pub fn template_variables(body: &str) -> Result<Vec<String>> {
+    let mut variables: Vec<String> = Vec::new();
+    for segment in parse_template(body)? {
+        if let Segment::Variable(name) = segment {
+            if !variables.iter().any(|v| v == name) {
+                variables.push(name.to_string());
+            }
+        }
+    }
+    Ok(variables)
+}
+
+/// Fail unless `provided` names exactly the template's variables
+pub fn check_variables<'a>(variables: &[String], provided: impl IntoIterator<Item = &'a String>) -> Result<()> {
+    let provided: Vec<&String> = provided.into_iter().collect();
+    let missing: Vec<&str> = variables.iter()
+        .filter(|v| !provided.contains(v))
+        .map(String::as_str)
+        .collect();
+    let mut unused: Vec<&str> = provided.iter()
+        .filter(|p| !variables.contains(p))
+        .map(|p| p.as_str())
+        .collect();
+    unused.sort_unstable();
+    match (missing.is_empty(), unused.is_empty()) {
+        (true, true) => Ok(()),
+        (false, true) => bail!("Missing template variables: {}", missing.join(", ")),
+        (true, false) => bail!("Unused template variables: {}", unused.join(", ")),
+        (false, false) => bail!(
+            "Missing template variables: {}; unused template variables: {}",
+            missing.join(", "),
+            unused.join(", ")
+        ),
+    }
+}
+
+/// Fill every placeholder in one pass, so values are never expanded themselves
+pub fn interpolate(body: &str, values: &HashMap<String, String>) -> Result<String> {
+    check_variables(&template_variables(body)?, values.keys())?;
+    let mut rendered = String::with_capacity(body.len());
+    for segment in parse_template(body)? {
+        match segment {
+            Segment::Text(text) => rendered.push_str(text),
+            Segment::Variable(name) => rendered.push_str(&values[name]),
+        }
+    }
+    Ok(rendered)
+}
+
+/// Id for a new template, derived from its name
+fn template_id(name: &str) -> String {
+    name.trim().chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '-' })
+        .take(60)
+        .collect()
+}
+
+fn check_body(body: &str) -> Result<Vec<String>> {
+    if body.trim().is_empty() {
+        bail!("Template body cannot be empty");
+    }
+    if body.chars().count() > MAX_TEMPLATE_CHARS {
+        bail!("Template body exceeds {} characters", MAX_TEMPLATE_CHARS);
+    }
+    template_variables(body)
+}
+
+const TEMPLATE_QUERY: &str =
+    "SELECT t.id, t.name, t.description, v.body, v.variables, v.version, t.current_version, t.created_at, t.updated_at
+     FROM prompt_templates t JOIN prompt_template_versions v ON v.template_id = t.id";
+
+fn template_from_row(row: &rusqlite::Row) -> rusqlite::Result<PromptTemplate> {
+    Ok(PromptTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        body: row.get(3)?,
+        variables: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+        version: row.get(5)?,
+        latest_version: row.get(6)?,
+        created_at: parse_db_timestamp(&row.get::<_, String>(7)?),
+        updated_at: parse_db_timestamp(&row.get::<_, String>(8)?),
+    })
+}
+
+/// A template at `version`, or at its latest version
+pub fn find_template(conn: &Connection, template_id: &str, version: Option<i64>) -> Result<PromptTemplate> {
+    conn.query_row(
+        &format!("{} WHERE t.id = ?1 AND v.version = COALESCE(?2, t.current_version)", TEMPLATE_QUERY),
+        params![template_id, version],
+        template_from_row,
+    ).optional()?
+        .ok_or_else(|| match version {
+            Some(version) => anyhow!("Prompt template {} has no version {}", template_id, version),
+            None => anyhow!("Unknown prompt template: {}", template_id),
+        })
+}
+
+pub fn list_templates(conn: &Connection) -> Result<Vec<PromptTemplate>> {
+    let mut stmt = conn.prepare(&format!("{} WHERE v.version = t.current_version ORDER BY t.name COLLATE NOCASE", TEMPLATE_QUERY))?;
+    let templates = stmt.query_map([], template_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(templates)
+}
+
+pub fn list_versions(conn: &Connection, template_id: &str) -> Result<Vec<PromptTemplateVersion>> {
+    let mut stmt = conn.prepare(
+        "SELECT template_id, version, body, variables, note, created_at FROM prompt_template_versions
+         WHERE template_id = ?1 ORDER BY version"
+    )?;
+    let versions = stmt.query_map(params![template_id], |row| {
+        Ok(PromptTemplateVersion {
+            template_id: row.get(0)?,
+            version: row.get(1)?,
+            body: row.get(2)?,
+            variables: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+            note: row.get(4)?,
+            created_at: parse_db_timestamp(&row.get::<_, String>(5)?),
+        })
+    })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if versions.is_empty() {
+        bail!("Unknown prompt template: {}", template_id);
+    }
+    Ok(versions)
+}
+
+fn insert_version(conn: &Connection, template_id: &str, version: i64, body: &str, variables: &[String], note: Option<&str>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO prompt_template_versions (template_id, version, body, variables, note) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![template_id, version, body, serde_json::to_string(variables)?, note],
+    )?;
+    Ok(())
+}
+
+pub fn create_template(conn: &mut Connection, name: &str, description: &str, body: &str) -> Result<PromptTemplate> {
+    let id = template_id(name);
+    if name.trim().is_empty() || id.is_empty() {
+        bail!("Prompt templates need a name");
+    }
+    let variables = check_body(body)?;
+
+    let tx = conn.transaction()?;
+    let inserted = tx.execute(
+        "INSERT INTO prompt_templates (id, name, description) VALUES (?1, ?2, ?3) ON CONFLICT(id) DO NOTHING",
+        params![id, name.trim(), description.trim()],
+    )?;
+    if inserted == 0 {
+        bail!("Prompt template {} already exists", id);
+    }
+    insert_version(&tx, &id, 1, body, &variables, None)?;
+    tx.commit()?;
+    find_template(conn, &id, None)
+}
+
+pub fn update_template(conn: &mut Connection, template_id: &str, update: &PromptTemplateUpdate) -> Result<PromptTemplate> {
+    let current = find_template(conn, template_id, None)?;
+    if update.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+        bail!("Prompt templates need a name");
+    }
+
+    let tx = conn.transaction()?;
+    let mut version = current.version;
+    if let Some(body) = update.body.as_deref().filter(|body| *body != current.body) {
+        let variables = check_body(body)?;
+        version += 1;
+        insert_version(&tx, template_id, version, body, &variables, update.note.as_deref())?;
+    }
+    tx.execute(
+        "UPDATE prompt_templates SET name = COALESCE(?2, name), description = COALESCE(?3, description),
+         current_version = ?4, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![template_id, update.name.as_deref().map(str::trim), update.description.as_deref().map(str::trim), version],
+    )?;
+    tx.commit()?;
+    find_template(conn, template_id, None)
+}
+
+pub fn delete_template(conn: &Connection, template_id: &str) -> Result<()> {
+    if conn.execute("DELETE FROM prompt_templates WHERE id = ?1", params![template_id])? == 0 {
+        bail!("Unknown prompt template: {}", template_id);
+    }
+    Ok(())
+}
+
+/// Render a stored template with `values`
+pub fn render_template(conn: &Connection, template_id: &str, version: Option<i64>, values: &HashMap<String, String>) -> Result<String> {
+    let template = find_template(conn, template_id, version)?;
+    interpolate(&template.body, values)
+        .map_err(|e| anyhow!("Prompt template {}: {}", template_id, e))
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn create_prompt_template(
+    name: String,
+    body: String,
+    description: Option<String>,
+    app_handle: AppHandle,
+) -> Result<PromptTemplate, String> {
+    let template = create_template(&mut open_db(&app_handle)?, &name, description.as_deref().unwrap_or_default(), &body)
+        .map_err(|e| format!("Failed to create prompt template: {}", e))?;
+    info!("Created prompt template {} with variables {:?}", template.id, template.variables);
+    Ok(template)
+}
+
+/// Rename, describe or rewrite a template; a changed body is stored as a new version
+#[tauri::command]
+pub async fn update_prompt_template(
+    template_id: String,
+    update: PromptTemplateUpdate,
+    app_handle: AppHandle,
+) -> Result<PromptTemplate, String> {
+    let template = update_template(&mut open_db(&app_handle)?, &template_id, &update)
+        .map_err(|e| format!("Failed to update prompt template: {}", e))?;
+    info!("Updated prompt template {} (version {})", template.id, template.version);
+    Ok(template)
+}
+
+#[tauri::command]
+pub async fn delete_prompt_template(template_id: String, app_handle: AppHandle) -> Result<(), String> {
+    delete_template(&open_db(&app_handle)?, &template_id)
+        .map_err(|e| format!("Failed to delete prompt template: {}", e))?;
+    info!("Deleted prompt template {}", template_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_prompt_templates(app_handle: AppHandle) -> Result<Vec<PromptTemplate>, String> {
+    list_templates(&open_db(&app_handle)?).map_err(|e| format!("Failed to list prompt templates: {}", e))
+}
+
+/// A template at `version`, or at its latest version
+#[tauri::command]
+pub async fn get_prompt_template(
+    template_id: String,
+    version: Option<i64>,
+    app_handle: AppHandle,
+) -> Result<PromptTemplate, String> {
+    find_template(&open_db(&app_handle)?, &template_id, version).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_prompt_template_versions(
+    template_id: String,
+    app_handle: AppHandle,
+) -> Result<Vec<PromptTemplateVersion>, String> {
+    list_versions(&open_db(&app_handle)?, &template_id).map_err(|e| e.to_string())
+}
+
+/// Fill a template's variables; fails when a variable has no value or a value has no variable
+#[tauri::command]
+pub async fn render_prompt_template(
+    template_id: String,
+    variables: HashMap<String, String>,
+    version: Option<i64>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    render_template(&open_db(&app_handle)?, &template_id, version, &variables).map_err(|e| e.to_string())
+}
+
+/// Render a template as a prompt run. With an `agent_id`, asks the frontend to run that agent's
+/// turn on it, as for MCP prompts.
+#[tauri::command]
+pub async fn run_prompt_template(
+    template_id: String,
+    variables: HashMap<String, String>,
+    agent_id: Option<String>,
+    app_handle: AppHandle,
+) -> Result<MCPPromptRun, String> {
+    let conn = open_db(&app_handle)?;
+    let template = find_template(&conn, &template_id, None).map_err(|e| e.to_string())?;
+    let text = interpolate(&template.body, &variables)
+        .map_err(|e| format!("Prompt template {}: {}", template_id, e))?;
+
+    let run = MCPPromptRun {
+        server_id: PROMPT_TEMPLATE_SOURCE.to_string(),
+        prompt_name: template.id,
+        description: Some(template.description).filter(|d| !d.is_empty()),
+        messages: vec![MCPPromptMessage { role: "user".to_string(), content: json!({ "type": "text", "text": text }) }],
+        agent_id,
+    };
+    if run.agent_id.is_some() {
+        app_handle.emit(PROMPT_TURN_EVENT, &run)
+            .map_err(|e| format!("Failed to start agent turn: {}", e))?;
+    }
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_strict_interpolation() {
+        let body = "Review this {{ language }} change:\n{{diff}}\nFocus on {{language}}.";
+        assert_eq!(template_variables(body).unwrap(), vec!["language", "diff"]);
+        assert_eq!(
+            interpolate(body, &values(&[("language", "Rust"), ("diff", "{{language}}")])).unwrap(),
+            "Review this Rust change:\n{{language}}\nFocus on Rust."
+        );
+
+        let missing = interpolate(body, &values(&[("language", "Rust"), ("tone", "kind")])).unwrap_err();
+        assert_eq!(missing.to_string(), "Missing template variables: diff; unused template variables: tone");
+        assert!(interpolate("Hi {{name", &HashMap::new()).is_err());
+        assert!(interpolate("Hi {{two words}}", &HashMap::new()).is_err());
+        assert_eq!(interpolate("No variables", &HashMap::new()).unwrap(), "No variables");
+    }
+
+    #[test]
+    fn test_template_versions() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        conn.execute("PRAGMA foreign_keys = ON", []).unwrap();
+
+        let created = create_template(&mut conn, "Code Review", "Reviews a diff", "Review {{diff}}").unwrap();
+        assert_eq!((created.id.as_str(), created.version), ("code-review", 1));
+        assert!(create_template(&mut conn, "code review", "", "Other").is_err());
+        assert!(create_template(&mut conn, "Broken", "", "{{oops").is_err());
+
+        let renamed = update_template(&mut conn, "code-review", &PromptTemplateUpdate { name: Some("Review".to_string()), ..Default::default() }).unwrap();
+        assert_eq!((renamed.name.as_str(), renamed.version), ("Review", 1));
+        let update = PromptTemplateUpdate { body: Some("Review {{diff}} as {{role}}".to_string()), note: Some("Add role".to_string()), ..Default::default() };
+        let updated = update_template(&mut conn, "code-review", &update).unwrap();
+        assert_eq!((updated.version, updated.variables.clone()), (2, vec!["diff".to_string(), "role".to_string()]));
+
+        let versions = list_versions(&conn, "code-review").unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].note.as_deref(), Some("Add role"));
+        assert_eq!(render_template(&conn, "code-review", Some(1), &values(&[("diff", "x")])).unwrap(), "Review x");
+        assert!(render_template(&conn, "code-review", None, &values(&[("diff", "x")])).is_err());
+        assert_eq!(find_template(&conn, "code-review", Some(1)).unwrap().latest_version, 2);
+        assert!(find_template(&conn, "code-review", Some(3)).is_err());
+        assert_eq!(list_templates(&conn).unwrap().len(), 1);
+
+        delete_template(&conn, "code-review").unwrap();
+        assert!(list_versions(&conn, "code-review").is_err());
+        assert!(delete_template(&conn, "code-review").is_err());
+    }
+}
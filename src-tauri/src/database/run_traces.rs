@@ -0,0 +1,524 @@
+use super::{open_db, parse_db_timestamp};
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Model parameters a run was executed with
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RunModelParams {
+    pub model_id: String,
+    pub provider: Option<String>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub seed: Option<u64>,
+}
+
+/// Everything the agent saw before its first model call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunTraceInput {
+    pub agent_id: String,
+    pub conversation_id: Option<String>,
+    pub system_prompt: Option<String>,
+    /// Prompt messages exactly as sent to the model
+    #[serde(default)]
+    pub messages: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub retrieved_memories: Vec<serde_json::Value>,
+    pub model: RunModelParams,
+    /// Replays only: tools executed live instead of answered from the recording
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub live_tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunTraceStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+impl RunTraceStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RunTraceStatus::Running => "running",
+            RunTraceStatus::Completed => "completed",
+            RunTraceStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "completed" => RunTraceStatus::Completed,
+            "failed" => RunTraceStatus::Failed,
+            _ => RunTraceStatus::Running,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunToolCall {
+    pub sequence: i64,
+    pub tool_name: String,
+    pub arguments: serde_json::Value,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub duration_ms: Option<u64>,
+    /// Sequence of the recorded call that answered this one during a replay
+    pub replayed_from: Option<i64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunTrace {
+    pub id: String,
+    pub replay_of: Option<String>,
+    pub input: RunTraceInput,
+    pub tool_calls: Vec<RunToolCall>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub status: RunTraceStatus,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunTraceSummary {
+    pub id: String,
+    pub agent_id: String,
+    pub replay_of: Option<String>,
+    pub status: RunTraceStatus,
+    pub tool_call_count: i64,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Changes applied on top of the recorded input when replaying; unset fields keep the recording
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayOverrides {
+    pub system_prompt: Option<String>,
+    pub messages: Option<Vec<serde_json::Value>>,
+    pub retrieved_memories: Option<Vec<serde_json::Value>>,
+    pub model_id: Option<String>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub live_tools: Vec<String>,
+}
+
+/// A new run primed with the (overridden) recorded input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayPlan {
+    pub run_id: String,
+    pub replay_of: String,
+    pub input: RunTraceInput,
+    pub recorded_tool_calls: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayToolResult {
+    /// False when the caller must execute the tool itself
+    pub stubbed: bool,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub recorded_sequence: Option<i64>,
+    /// False when the replay asked with different arguments than the recording
+    pub arguments_match: bool,
+}
+
+fn apply_overrides(mut input: RunTraceInput, overrides: ReplayOverrides) -> RunTraceInput {
+    if let Some(system_prompt) = overrides.system_prompt {
+        input.system_prompt = Some(system_prompt);
+    }
+    if let Some(messages) = overrides.messages {
+        input.messages = messages;
+    }
+    if let Some(memories) = overrides.retrieved_memories {
+        input.retrieved_memories = memories;
+    }
+    if let Some(model_id) = overrides.model_id {
+        input.model.model_id = model_id;
+    }
+    input.model.temperature = overrides.temperature.or(input.model.temperature);
+    input.model.top_p = overrides.top_p.or(input.model.top_p);
+    input.model.max_tokens = overrides.max_tokens.or(input.model.max_tokens);
+    input.model.seed = overrides.seed.or(input.model.seed);
+    input.live_tools = overrides.live_tools;
+    input
+}
+
+pub fn start_trace(conn: &Connection, run_id: &str, input: &RunTraceInput, replay_of: Option<&str>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO agent_runs (id, agent_id, replay_of, input) VALUES (?1, ?2, ?3, ?4)",
+        params![run_id, input.agent_id, replay_of, serde_json::to_string(input)?],
+    )?;
+    Ok(())
+}
+
+/// A tool call about to be appended to a run
+pub struct NewToolCall<'a> {
+    pub tool_name: &'a str,
+    pub arguments: &'a serde_json::Value,
+    pub result: Option<&'a serde_json::Value>,
+    pub error: Option<&'a str>,
+    pub duration_ms: Option<u64>,
+    pub replayed_from: Option<i64>,
+}
+
+pub fn record_tool_call(conn: &Connection, run_id: &str, call: &NewToolCall) -> Result<i64> {
+    let sequence: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(sequence), 0) + 1 FROM agent_run_tool_calls WHERE run_id = ?1",
+        params![run_id],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO agent_run_tool_calls (id, run_id, sequence, tool_name, arguments, result, error, duration_ms, replayed_from)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            run_id,
+            sequence,
+            call.tool_name,
+            serde_json::to_string(call.arguments)?,
+            call.result.map(serde_json::to_string).transpose()?,
+            call.error,
+            call.duration_ms.map(|d| d as i64),
+            call.replayed_from,
+        ],
+    )?;
+    Ok(sequence)
+}
+
+pub fn finish_trace(conn: &Connection, run_id: &str, output: Option<&str>, error: Option<&str>) -> Result<()> {
+    let status = if error.is_some() { RunTraceStatus::Failed } else { RunTraceStatus::Completed };
+    let updated = conn.execute(
+        "UPDATE agent_runs SET output = ?2, error = ?3, status = ?4, finished_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![run_id, output, error, status.as_str()],
+    )?;
+    if updated == 0 {
+        return Err(anyhow!("Unknown run: {}", run_id));
+    }
+    Ok(())
+}
+
+fn load_tool_calls(conn: &Connection, run_id: &str) -> Result<Vec<RunToolCall>> {
+    let mut stmt = conn.prepare(
+        "SELECT sequence, tool_name, arguments, result, error, duration_ms, replayed_from, created_at
+         FROM agent_run_tool_calls WHERE run_id = ?1 ORDER BY sequence"
+    )?;
+    let calls = stmt.query_map(params![run_id], |row| {
+        let arguments: String = row.get(2)?;
+        let result: Option<String> = row.get(3)?;
+        let duration_ms: Option<i64> = row.get(5)?;
+        let created_at: String = row.get(7)?;
+        Ok(RunToolCall {
+            sequence: row.get(0)?,
+            tool_name: row.get(1)?,
+            arguments: serde_json::from_str(&arguments).unwrap_or(serde_json::Value::Null),
+            result: result.and_then(|r| serde_json::from_str(&r).ok()),
+            error: row.get(4)?,
+            duration_ms: duration_ms.map(|d| d.max(0) as u64),
+            replayed_from: row.get(6)?,
+            created_at: parse_db_timestamp(&created_at),
+        })
+    })?;
+    Ok(calls.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+pub fn load_trace(conn: &Connection, run_id: &str) -> Result<RunTrace> {
+    let row = conn.query_row(
+        "SELECT id, replay_of, input, output, error, status, started_at, finished_at FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+            row.get::<_, Option<String>>(7)?,
+        )),
+    ).optional()?.ok_or_else(|| anyhow!("Unknown run: {}", run_id))?;
+
+    let (id, replay_of, input, output, error, status, started_at, finished_at) = row;
+    Ok(RunTrace {
+        tool_calls: load_tool_calls(conn, &id)?,
+        id,
+        replay_of,
+        input: serde_json::from_str(&input)?,
+        output,
+        error,
+        status: RunTraceStatus::parse(&status),
+        started_at: parse_db_timestamp(&started_at),
+        finished_at: finished_at.as_deref().map(parse_db_timestamp),
+    })
+}
+
+pub fn list_traces(conn: &Connection, agent_id: Option<&str>, limit: usize) -> Result<Vec<RunTraceSummary>> {
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.agent_id, r.replay_of, r.status, r.started_at, r.finished_at,
+                (SELECT COUNT(*) FROM agent_run_tool_calls c WHERE c.run_id = r.id)
+         FROM agent_runs r
+         WHERE ?1 IS NULL OR r.agent_id = ?1
+         ORDER BY r.started_at DESC LIMIT ?2"
+    )?;
+    let rows = stmt.query_map(params![agent_id, limit as i64], |row| {
+        let status: String = row.get(3)?;
+        let started_at: String = row.get(4)?;
+        let finished_at: Option<String> = row.get(5)?;
+        Ok(RunTraceSummary {
+            id: row.get(0)?,
+            agent_id: row.get(1)?,
+            replay_of: row.get(2)?,
+            status: RunTraceStatus::parse(&status),
+            tool_call_count: row.get(6)?,
+            started_at: parse_db_timestamp(&started_at),
+            finished_at: finished_at.as_deref().map(parse_db_timestamp),
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Start a replay run from a recorded one
+pub fn start_replay(conn: &Connection, run_id: &str, overrides: ReplayOverrides) -> Result<ReplayPlan> {
+    let original = load_trace(conn, run_id)?;
+    let input = apply_overrides(original.input, overrides);
+    let replay_id = uuid::Uuid::new_v4().to_string();
+    start_trace(conn, &replay_id, &input, Some(run_id))?;
+
+    Ok(ReplayPlan {
+        run_id: replay_id,
+        replay_of: run_id.to_string(),
+        input,
+        recorded_tool_calls: original.tool_calls.len(),
+    })
+}
+
+/// Answer a replay's tool call from the recording: the first unused recorded call to
+/// the same tool with identical arguments, else the first unused call to that tool
+pub fn stub_tool_call(
+    conn: &Connection,
+    replay_id: &str,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+) -> Result<ReplayToolResult> {
+    let replay = load_trace(conn, replay_id)?;
+    let original_id = replay.replay_of
+        .ok_or_else(|| anyhow!("Run {} is not a replay", replay_id))?;
+
+    let not_stubbed = ReplayToolResult { stubbed: false, result: None, error: None, recorded_sequence: None, arguments_match: false };
+    if replay.input.live_tools.iter().any(|t| t == tool_name) {
+        return Ok(not_stubbed);
+    }
+
+    let used: HashSet<i64> = replay.tool_calls.iter().filter_map(|c| c.replayed_from).collect();
+    let recorded = load_tool_calls(conn, &original_id)?;
+    let mut unused = recorded.iter().filter(|c| c.tool_name == tool_name && !used.contains(&c.sequence));
+    let exact = unused.clone().find(|c| &c.arguments == arguments);
+    let Some((call, arguments_match)) = exact.map(|c| (c, true)).or_else(|| unused.next().map(|c| (c, false))) else {
+        return Ok(not_stubbed);
+    };
+
+    record_tool_call(conn, replay_id, &NewToolCall {
+        tool_name,
+        arguments,
+        result: call.result.as_ref(),
+        error: call.error.as_deref(),
+        duration_ms: call.duration_ms,
+        replayed_from: Some(call.sequence),
+    })?;
+    Ok(ReplayToolResult {
+        stubbed: true,
+        result: call.result.clone(),
+        error: call.error.clone(),
+        recorded_sequence: Some(call.sequence),
+        arguments_match,
+    })
+}
+
+/// Begin recording a run; pass the planner's run id to keep both records joined
+#[tauri::command]
+pub async fn start_run_trace(
+    input: RunTraceInput,
+    run_id: Option<String>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    if input.agent_id.trim().is_empty() {
+        return Err("Agent ID cannot be empty".to_string());
+    }
+
+    let run_id = run_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    start_trace(&open_db(&app_handle)?, &run_id, &input, None)
+        .map_err(|e| format!("Failed to start run trace: {}", e))?;
+    Ok(run_id)
+}
+
+/// Record a live tool call and its result; returns the call's sequence number
+#[tauri::command]
+pub async fn record_run_tool_call(
+    run_id: String,
+    tool_name: String,
+    arguments: serde_json::Value,
+    result: Option<serde_json::Value>,
+    error: Option<String>,
+    duration_ms: Option<u64>,
+    app_handle: AppHandle,
+) -> Result<i64, String> {
+    let call = NewToolCall {
+        tool_name: &tool_name,
+        arguments: &arguments,
+        result: result.as_ref(),
+        error: error.as_deref(),
+        duration_ms,
+        replayed_from: None,
+    };
+    record_tool_call(&open_db(&app_handle)?, &run_id, &call)
+        .map_err(|e| format!("Failed to record tool call: {}", e))
+}
+
+#[tauri::command]
+pub async fn finish_run_trace(
+    run_id: String,
+    output: Option<String>,
+    error: Option<String>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    finish_trace(&open_db(&app_handle)?, &run_id, output.as_deref(), error.as_deref())
+        .map_err(|e| format!("Failed to finish run trace: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_run_trace(run_id: String, app_handle: AppHandle) -> Result<RunTrace, String> {
+    load_trace(&open_db(&app_handle)?, &run_id)
+        .map_err(|e| format!("Failed to load run trace: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_run_traces(
+    agent_id: Option<String>,
+    limit: Option<usize>,
+    app_handle: AppHandle,
+) -> Result<Vec<RunTraceSummary>, String> {
+    list_traces(&open_db(&app_handle)?, agent_id.as_deref(), limit.unwrap_or(50).clamp(1, 500))
+        .map_err(|e| format!("Failed to list run traces: {}", e))
+}
+
+/// Re-run a recorded run with optional overrides. The caller drives the model with the
+/// returned input and routes every tool call through `replay_tool_call`.
+#[tauri::command]
+pub async fn replay_agent_run(
+    run_id: String,
+    overrides: Option<ReplayOverrides>,
+    app_handle: AppHandle,
+) -> Result<ReplayPlan, String> {
+    let plan = start_replay(&open_db(&app_handle)?, &run_id, overrides.unwrap_or_default())
+        .map_err(|e| format!("Failed to replay run: {}", e))?;
+
+    info!("Replaying run {} as {} ({} recorded tool calls)", run_id, plan.run_id, plan.recorded_tool_calls);
+    Ok(plan)
+}
+
+/// Resolve a tool call inside a replay from the recording
+#[tauri::command]
+pub async fn replay_tool_call(
+    run_id: String,
+    tool_name: String,
+    arguments: serde_json::Value,
+    app_handle: AppHandle,
+) -> Result<ReplayToolResult, String> {
+    stub_tool_call(&open_db(&app_handle)?, &run_id, &tool_name, &arguments)
+        .map_err(|e| format!("Failed to resolve replayed tool call: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(super::super::INIT_SQL).unwrap();
+        conn
+    }
+
+    fn recorded_run(conn: &Connection) {
+        let input = RunTraceInput {
+            agent_id: "agent-1".into(),
+            system_prompt: Some("Be terse.".into()),
+            messages: vec![json!({"role": "user", "content": "weather in Oslo and Paris?"})],
+            retrieved_memories: vec![json!({"id": "m1", "content": "User prefers Celsius"})],
+            model: RunModelParams { model_id: "gpt-4o".into(), temperature: Some(0.7), ..Default::default() },
+            ..Default::default()
+        };
+        start_trace(conn, "run-1", &input, None).unwrap();
+        for (city, celsius) in [("Oslo", 4), ("Paris", 12)] {
+            record_tool_call(conn, "run-1", &NewToolCall {
+                tool_name: "weather",
+                arguments: &json!({"city": city}),
+                result: Some(&json!({"c": celsius})),
+                error: None,
+                duration_ms: Some(100),
+                replayed_from: None,
+            }).unwrap();
+        }
+        finish_trace(conn, "run-1", Some("Oslo 4°C, Paris 12°C"), None).unwrap();
+    }
+
+    #[test]
+    fn test_trace_round_trip_and_overrides() {
+        let conn = setup();
+        recorded_run(&conn);
+
+        let trace = load_trace(&conn, "run-1").unwrap();
+        assert_eq!(trace.status, RunTraceStatus::Completed);
+        assert_eq!(trace.tool_calls.len(), 2);
+        assert_eq!(trace.input.retrieved_memories.len(), 1);
+
+        let plan = start_replay(&conn, "run-1", ReplayOverrides {
+            temperature: Some(0.0),
+            seed: Some(7),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(plan.input.model.temperature, Some(0.0));
+        assert_eq!(plan.input.model.seed, Some(7));
+        assert_eq!(plan.input.model.model_id, "gpt-4o");
+        assert_eq!(plan.input.system_prompt.as_deref(), Some("Be terse."));
+        assert_eq!(plan.recorded_tool_calls, 2);
+        assert_eq!(list_traces(&conn, Some("agent-1"), 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_replay_stubs_recorded_tool_results() {
+        let conn = setup();
+        recorded_run(&conn);
+        let plan = start_replay(&conn, "run-1", ReplayOverrides {
+            live_tools: vec!["search".into()],
+            ..Default::default()
+        }).unwrap();
+
+        // Out-of-order calls still match on arguments
+        let paris = stub_tool_call(&conn, &plan.run_id, "weather", &json!({"city": "Paris"})).unwrap();
+        assert!(paris.stubbed && paris.arguments_match);
+        assert_eq!(paris.result, Some(json!({"c": 12})));
+
+        // Diverging arguments fall back to the next unused recording
+        let berlin = stub_tool_call(&conn, &plan.run_id, "weather", &json!({"city": "Berlin"})).unwrap();
+        assert!(berlin.stubbed && !berlin.arguments_match);
+        assert_eq!(berlin.recorded_sequence, Some(1));
+
+        assert!(!stub_tool_call(&conn, &plan.run_id, "weather", &json!({"city": "Rome"})).unwrap().stubbed);
+        assert!(!stub_tool_call(&conn, &plan.run_id, "search", &json!({})).unwrap().stubbed);
+        assert!(stub_tool_call(&conn, "run-1", "weather", &json!({})).is_err());
+
+        let replay = load_trace(&conn, &plan.run_id).unwrap();
+        assert_eq!(replay.replay_of.as_deref(), Some("run-1"));
+        assert_eq!(replay.tool_calls.iter().filter_map(|c| c.replayed_from).collect::<Vec<_>>(), vec![2, 1]);
+    }
+}
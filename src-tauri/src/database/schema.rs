@@ -1,5 +1,9 @@
 // Enhanced database schema for agent memory and knowledge graph system
 
+/// Stored in `PRAGMA user_version` so backups record which schema they were taken from.
+/// Kept equal to the last entry of `migrations::AGENT_MEMORY_MIGRATIONS`.
+pub const AGENT_MEMORY_SCHEMA_VERSION: i64 = 7;
+
 pub const AGENT_MEMORY_SCHEMA: &str = r#"
 -- Agent Memory Tables
 CREATE TABLE IF NOT EXISTS agent_memories (
@@ -78,6 +82,17 @@ CREATE TABLE IF NOT EXISTS memory_access_log (
     FOREIGN KEY (memory_id) REFERENCES agent_memories(id) ON DELETE CASCADE
 );
 
+-- Scoped read access this agent has granted others over its memories
+CREATE TABLE IF NOT EXISTS memory_access_grants (
+    id TEXT PRIMARY KEY,
+    owner_agent TEXT NOT NULL,
+    grantee_agent TEXT NOT NULL,
+    tags TEXT NOT NULL DEFAULT '[]', -- JSON array; empty allows any tag
+    memory_types TEXT NOT NULL DEFAULT '[]', -- JSON array; empty allows any type
+    created_at TEXT NOT NULL,
+    UNIQUE (owner_agent, grantee_agent)
+);
+
 -- Embedding Cache (for performance)
 CREATE TABLE IF NOT EXISTS embedding_cache (
     content_hash TEXT PRIMARY KEY,
@@ -153,22 +168,62 @@ BEGIN
     DELETE FROM agent_memories_fts WHERE id = OLD.id;
 END;
 
-CREATE TRIGGER IF NOT EXISTS shared_knowledge_fts_insert AFTER INSERT ON shared_knowledge
+-- The original shared_knowledge_fts triggers wrote to the external-content index by id, which
+-- FTS5 doesn't support; they are replaced by rowid-keyed ones and the index is rebuilt on open
+DROP TRIGGER IF EXISTS shared_knowledge_fts_insert;
+DROP TRIGGER IF EXISTS shared_knowledge_fts_update;
+DROP TRIGGER IF EXISTS shared_knowledge_fts_delete;
+
+CREATE TRIGGER IF NOT EXISTS shared_knowledge_fts_ai AFTER INSERT ON shared_knowledge
 BEGIN
-    INSERT INTO shared_knowledge_fts(id, title, content, tags) 
-    VALUES (NEW.id, NEW.title, NEW.content, NEW.tags);
+    INSERT INTO shared_knowledge_fts(rowid, id, title, content, tags)
+    VALUES (NEW.rowid, NEW.id, NEW.title, NEW.content, NEW.tags);
 END;
 
-CREATE TRIGGER IF NOT EXISTS shared_knowledge_fts_update AFTER UPDATE ON shared_knowledge
+-- Limited to indexed columns so the updated_at trigger's nested update doesn't re-index stale values
+CREATE TRIGGER IF NOT EXISTS shared_knowledge_fts_au AFTER UPDATE OF title, content, tags ON shared_knowledge
 BEGIN
-    UPDATE shared_knowledge_fts SET title = NEW.title, content = NEW.content, tags = NEW.tags WHERE id = NEW.id;
+    INSERT INTO shared_knowledge_fts(shared_knowledge_fts, rowid, id, title, content, tags)
+    VALUES ('delete', OLD.rowid, OLD.id, OLD.title, OLD.content, OLD.tags);
+    INSERT INTO shared_knowledge_fts(rowid, id, title, content, tags)
+    VALUES (NEW.rowid, NEW.id, NEW.title, NEW.content, NEW.tags);
 END;
 
-CREATE TRIGGER IF NOT EXISTS shared_knowledge_fts_delete AFTER DELETE ON shared_knowledge
+CREATE TRIGGER IF NOT EXISTS shared_knowledge_fts_ad AFTER DELETE ON shared_knowledge
 BEGIN
-    DELETE FROM shared_knowledge_fts WHERE id = OLD.id;
+    INSERT INTO shared_knowledge_fts(shared_knowledge_fts, rowid, id, title, content, tags)
+    VALUES ('delete', OLD.rowid, OLD.id, OLD.title, OLD.content, OLD.tags);
 END;
 
+-- Contributions that contradict existing shared knowledge, kept for review instead of merged
+CREATE TABLE IF NOT EXISTS shared_knowledge_conflicts (
+    id TEXT PRIMARY KEY,
+    knowledge_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (knowledge_id) REFERENCES shared_knowledge(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_shared_knowledge_conflicts_knowledge ON shared_knowledge_conflicts(knowledge_id);
+
+-- Pairs of stored entries that are closely related but contradict each other
+CREATE TABLE IF NOT EXISTS shared_knowledge_contradictions (
+    id TEXT PRIMARY KEY,
+    knowledge_id TEXT NOT NULL,
+    contradicting_id TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK(kind IN ('Negation', 'Opposite')),
+    similarity REAL NOT NULL,
+    detected_at TEXT NOT NULL,
+    UNIQUE (knowledge_id, contradicting_id),
+    FOREIGN KEY (knowledge_id) REFERENCES shared_knowledge(id) ON DELETE CASCADE,
+    FOREIGN KEY (contradicting_id) REFERENCES shared_knowledge(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_shared_knowledge_contradictions_knowledge ON shared_knowledge_contradictions(knowledge_id);
+CREATE INDEX IF NOT EXISTS idx_shared_knowledge_contradictions_contradicting ON shared_knowledge_contradictions(contradicting_id);
+
 -- Triggers for automatic updates
 CREATE TRIGGER IF NOT EXISTS update_agent_memories_timestamp 
 AFTER UPDATE ON agent_memories
@@ -194,6 +249,115 @@ BEGIN
     UPDATE knowledge_edges SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
 END;
 
+-- Knowledge graph change log, one row per node/edge write, for point-in-time views
+CREATE TABLE IF NOT EXISTS knowledge_graph_history (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    entity_kind TEXT NOT NULL CHECK(entity_kind IN ('node', 'edge')),
+    entity_id TEXT NOT NULL,
+    operation TEXT NOT NULL CHECK(operation IN ('upsert', 'delete')),
+    data TEXT NOT NULL, -- JSON row image (the deleted row for deletes)
+    changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_knowledge_graph_history_entity ON knowledge_graph_history(entity_id);
+CREATE INDEX IF NOT EXISTS idx_knowledge_graph_history_changed_at ON knowledge_graph_history(changed_at);
+
+-- Materialized per-agent graphs that time-travel queries replay history from
+CREATE TABLE IF NOT EXISTS knowledge_graph_snapshots (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    history_seq INTEGER NOT NULL, -- Last history row included in the snapshot
+    node_count INTEGER NOT NULL,
+    edge_count INTEGER NOT NULL,
+    data TEXT NOT NULL, -- JSON {nodes, edges}
+    taken_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_knowledge_graph_snapshots_agent ON knowledge_graph_snapshots(agent_id, taken_at);
+
+CREATE TRIGGER IF NOT EXISTS knowledge_nodes_history_insert
+AFTER INSERT ON knowledge_nodes
+BEGIN
+    INSERT INTO knowledge_graph_history (entity_kind, entity_id, operation, data)
+    VALUES ('node', NEW.id, 'upsert', json_object(
+        'id', NEW.id, 'node_type', NEW.node_type, 'name', NEW.name,
+        'properties', json(COALESCE(NEW.properties, '{}')),
+        'created_at', NEW.created_at, 'updated_at', NEW.updated_at));
+END;
+
+CREATE TRIGGER IF NOT EXISTS knowledge_nodes_history_update
+AFTER UPDATE OF node_type, name, properties ON knowledge_nodes
+BEGIN
+    INSERT INTO knowledge_graph_history (entity_kind, entity_id, operation, data)
+    VALUES ('node', NEW.id, 'upsert', json_object(
+        'id', NEW.id, 'node_type', NEW.node_type, 'name', NEW.name,
+        'properties', json(COALESCE(NEW.properties, '{}')),
+        'created_at', NEW.created_at, 'updated_at', NEW.updated_at));
+END;
+
+CREATE TRIGGER IF NOT EXISTS knowledge_nodes_history_delete
+AFTER DELETE ON knowledge_nodes
+BEGIN
+    INSERT INTO knowledge_graph_history (entity_kind, entity_id, operation, data)
+    VALUES ('node', OLD.id, 'delete', json_object(
+        'id', OLD.id, 'node_type', OLD.node_type, 'name', OLD.name,
+        'properties', json(COALESCE(OLD.properties, '{}')),
+        'created_at', OLD.created_at, 'updated_at', OLD.updated_at));
+END;
+
+CREATE TRIGGER IF NOT EXISTS knowledge_edges_history_insert
+AFTER INSERT ON knowledge_edges
+BEGIN
+    INSERT INTO knowledge_graph_history (entity_kind, entity_id, operation, data)
+    VALUES ('edge', NEW.id, 'upsert', json_object(
+        'id', NEW.id, 'from_node', NEW.from_node, 'to_node', NEW.to_node,
+        'relationship_type', NEW.relationship_type, 'weight', NEW.weight,
+        'properties', json(COALESCE(NEW.properties, '{}')),
+        'created_at', NEW.created_at, 'updated_at', NEW.updated_at));
+END;
+
+CREATE TRIGGER IF NOT EXISTS knowledge_edges_history_update
+AFTER UPDATE OF from_node, to_node, relationship_type, weight, properties ON knowledge_edges
+BEGIN
+    INSERT INTO knowledge_graph_history (entity_kind, entity_id, operation, data)
+    VALUES ('edge', NEW.id, 'upsert', json_object(
+        'id', NEW.id, 'from_node', NEW.from_node, 'to_node', NEW.to_node,
+        'relationship_type', NEW.relationship_type, 'weight', NEW.weight,
+        'properties', json(COALESCE(NEW.properties, '{}')),
+        'created_at', NEW.created_at, 'updated_at', NEW.updated_at));
+END;
+
+CREATE TRIGGER IF NOT EXISTS knowledge_edges_history_delete
+AFTER DELETE ON knowledge_edges
+BEGIN
+    INSERT INTO knowledge_graph_history (entity_kind, entity_id, operation, data)
+    VALUES ('edge', OLD.id, 'delete', json_object(
+        'id', OLD.id, 'from_node', OLD.from_node, 'to_node', OLD.to_node,
+        'relationship_type', OLD.relationship_type, 'weight', OLD.weight,
+        'properties', json(COALESCE(OLD.properties, '{}')),
+        'created_at', OLD.created_at, 'updated_at', OLD.updated_at));
+END;
+
+-- Rows written before the change log existed enter it as of their creation
+INSERT INTO knowledge_graph_history (entity_kind, entity_id, operation, data, changed_at)
+SELECT 'node', n.id, 'upsert', json_object(
+        'id', n.id, 'node_type', n.node_type, 'name', n.name,
+        'properties', json(COALESCE(n.properties, '{}')),
+        'created_at', n.created_at, 'updated_at', n.updated_at),
+    COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', n.created_at), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+FROM knowledge_nodes n
+WHERE NOT EXISTS (SELECT 1 FROM knowledge_graph_history h WHERE h.entity_id = n.id);
+
+INSERT INTO knowledge_graph_history (entity_kind, entity_id, operation, data, changed_at)
+SELECT 'edge', e.id, 'upsert', json_object(
+        'id', e.id, 'from_node', e.from_node, 'to_node', e.to_node,
+        'relationship_type', e.relationship_type, 'weight', e.weight,
+        'properties', json(COALESCE(e.properties, '{}')),
+        'created_at', e.created_at, 'updated_at', e.updated_at),
+    COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', e.created_at), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+FROM knowledge_edges e
+WHERE NOT EXISTS (SELECT 1 FROM knowledge_graph_history h WHERE h.entity_id = e.id);
+
 -- Trigger to increment access count
 CREATE TRIGGER IF NOT EXISTS increment_memory_access_count
 AFTER INSERT ON memory_access_log
@@ -262,4 +426,100 @@ SELECT
     MAX(created_at) as last_memory_created
 FROM agent_memories
 GROUP BY agent_id;
-"#;
\ No newline at end of file
+"#;
+
+// Unusual memories flagged by `memory_anomalies`
+pub const MEMORY_ANOMALIES_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS memory_anomalies (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    memory_id TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK(kind IN ('error_spike', 'content_drift')),
+    score REAL NOT NULL,
+    detail TEXT NOT NULL,
+    detected_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_memory_anomalies_agent_detected ON memory_anomalies(agent_id, detected_at);
+CREATE INDEX IF NOT EXISTS idx_memory_anomalies_memory ON memory_anomalies(memory_id);
+"#;
+
+// Provider comparison reports from `embedding_evaluation`
+pub const EMBEDDING_EVALUATIONS_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS embedding_evaluations (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    provider_a TEXT NOT NULL,
+    provider_b TEXT NOT NULL,
+    report TEXT NOT NULL, -- JSON EmbeddingEvaluationReport
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_embedding_evaluations_agent_created ON embedding_evaluations(agent_id, created_at);
+"#;
+
+// Pinned memories are exempt from decay, consolidation and eviction
+pub const MEMORY_PINS_SCHEMA: &str = r#"
+ALTER TABLE agent_memories ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_agent_memories_pinned ON agent_memories(agent_id, pinned);
+"#;
+
+// The original agent_memories_fts triggers keyed the external-content index by id, which FTS5
+// doesn't support; hybrid search ranks by BM25, so they are replaced by rowid-keyed ones and
+// the index is rebuilt
+pub const MEMORY_FTS_TRIGGERS_SCHEMA: &str = r#"
+DROP TRIGGER IF EXISTS agent_memories_fts_insert;
+DROP TRIGGER IF EXISTS agent_memories_fts_update;
+DROP TRIGGER IF EXISTS agent_memories_fts_delete;
+
+CREATE TRIGGER IF NOT EXISTS agent_memories_fts_ai AFTER INSERT ON agent_memories
+BEGIN
+    INSERT INTO agent_memories_fts(rowid, id, agent_id, content, tags)
+    VALUES (NEW.rowid, NEW.id, NEW.agent_id, NEW.content, NEW.tags);
+END;
+
+CREATE TRIGGER IF NOT EXISTS agent_memories_fts_au AFTER UPDATE OF agent_id, content, tags ON agent_memories
+BEGIN
+    INSERT INTO agent_memories_fts(agent_memories_fts, rowid, id, agent_id, content, tags)
+    VALUES ('delete', OLD.rowid, OLD.id, OLD.agent_id, OLD.content, OLD.tags);
+    INSERT INTO agent_memories_fts(rowid, id, agent_id, content, tags)
+    VALUES (NEW.rowid, NEW.id, NEW.agent_id, NEW.content, NEW.tags);
+END;
+
+CREATE TRIGGER IF NOT EXISTS agent_memories_fts_ad AFTER DELETE ON agent_memories
+BEGIN
+    INSERT INTO agent_memories_fts(agent_memories_fts, rowid, id, agent_id, content, tags)
+    VALUES ('delete', OLD.rowid, OLD.id, OLD.agent_id, OLD.content, OLD.tags);
+END;
+
+INSERT INTO agent_memories_fts(agent_memories_fts) VALUES ('rebuild');
+"#;
+
+// Episodic memories are raw records; semantic ones are consolidated from groups of them
+pub const MEMORY_TIERS_SCHEMA: &str = r#"
+ALTER TABLE agent_memories ADD COLUMN tier TEXT NOT NULL DEFAULT 'Episodic';
+
+CREATE INDEX IF NOT EXISTS idx_agent_memories_tier ON agent_memories(agent_id, tier, created_at);
+
+-- Which episodic memories each semantic memory was consolidated from
+CREATE TABLE IF NOT EXISTS memory_consolidations (
+    semantic_id TEXT NOT NULL,
+    episodic_id TEXT NOT NULL,
+    consolidated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (semantic_id, episodic_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_memory_consolidations_episodic ON memory_consolidations(episodic_id);
+"#;
+
+// Per-agent limits enforced when memories are saved
+pub const MEMORY_QUOTA_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS memory_quota (
+    agent_id TEXT PRIMARY KEY,
+    max_memories INTEGER,
+    max_db_bytes INTEGER,
+    eviction TEXT NOT NULL DEFAULT 'OldestUnpinned',
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+"#;
@@ -0,0 +1,673 @@
+use super::embedding_quantization::{decode_embedding, encode_embedding};
+use super::embeddings::SimilarityMetric;
+use super::memory::{KnowledgeType, SharedKnowledge};
+use super::neural_knowledge_graph::{ContradictionClassifier, ContradictionKind};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+use tracing::{info, warn};
+
+/// Contributions whose embeddings are at least this close to an entry are the same knowledge
+pub const DUPLICATE_SIMILARITY: f32 = 0.92;
+
+/// Entries at least this close are related enough to check whether they contradict
+pub const CONTRADICTION_SIMILARITY: f32 = 0.8;
+
+/// Reciprocal rank fusion constant used to blend search rankings
+pub(super) const RRF_K: f32 = 60.0;
+
+/// Search terms passed to FTS
+const MAX_QUERY_TERMS: usize = 32;
+
+const KNOWLEDGE_COLUMNS: &str = "shared_knowledge.id, shared_knowledge.knowledge_type, shared_knowledge.title, \
+     shared_knowledge.content, shared_knowledge.source_agents, shared_knowledge.embedding, \
+     shared_knowledge.confidence_score, shared_knowledge.created_at, shared_knowledge.updated_at, \
+     shared_knowledge.version, shared_knowledge.tags";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ContributionOutcome {
+    Created { knowledge_id: String },
+    /// Another agent contributed the same knowledge; sources and confidence grew
+    Merged { knowledge_id: String },
+    /// The entry's only contributors revised its content
+    Revised { knowledge_id: String },
+    /// Contradicts what other agents contributed; kept for review instead of merged
+    Conflict { knowledge_id: String, conflict_id: String },
+    /// Stored as its own entry but flagged, along with the entry it contradicts
+    Contradiction { knowledge_id: String, contradicts: String, kind: ContradictionKind },
+}
+
+impl ContributionOutcome {
+    pub fn knowledge_id(&self) -> &str {
+        match self {
+            Self::Created { knowledge_id }
+            | Self::Merged { knowledge_id }
+            | Self::Revised { knowledge_id }
+            | Self::Conflict { knowledge_id, .. }
+            | Self::Contradiction { knowledge_id, .. } => knowledge_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeConflict {
+    pub id: String,
+    pub knowledge_id: String,
+    pub agent_id: String,
+    pub title: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A flagged pair of entries, with both sides loaded for review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeContradiction {
+    pub id: String,
+    pub kind: ContradictionKind,
+    pub similarity: f32,
+    pub detected_at: DateTime<Utc>,
+    pub knowledge: SharedKnowledge,
+    pub contradicting: SharedKnowledge,
+}
+
+pub fn parse_knowledge_type(value: &str) -> Option<KnowledgeType> {
+    match value {
+        "Fact" => Some(KnowledgeType::Fact),
+        "Procedure" => Some(KnowledgeType::Procedure),
+        "Pattern" => Some(KnowledgeType::Pattern),
+        "Rule" => Some(KnowledgeType::Rule),
+        "Concept" => Some(KnowledgeType::Concept),
+        "Relationship" => Some(KnowledgeType::Relationship),
+        _ => None,
+    }
+}
+
+/// Case- and whitespace-insensitive form used to spot the same title or content
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+fn row_to_knowledge(row: &rusqlite::Row) -> rusqlite::Result<SharedKnowledge> {
+    let knowledge_type: String = row.get(1)?;
+    let source_agents: String = row.get(4)?;
+    let embedding: Option<Vec<u8>> = row.get(5)?;
+    let created_at: String = row.get(7)?;
+    let updated_at: String = row.get(8)?;
+    let tags: String = row.get(10)?;
+
+    Ok(SharedKnowledge {
+        id: row.get(0)?,
+        knowledge_type: parse_knowledge_type(&knowledge_type).unwrap_or(KnowledgeType::Fact),
+        title: row.get(2)?,
+        content: row.get(3)?,
+        source_agents: serde_json::from_str(&source_agents).unwrap_or_default(),
+        embedding: embedding.and_then(|blob| decode_embedding(&blob)),
+        confidence_score: row.get(6)?,
+        created_at: super::parse_db_timestamp(&created_at),
+        updated_at: super::parse_db_timestamp(&updated_at),
+        version: row.get(9)?,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+    })
+}
+
+/// Insert or update an entry in place. Unlike `INSERT OR REPLACE`, this fires the update
+/// triggers that keep the FTS index in sync.
+pub fn upsert_knowledge(conn: &Connection, knowledge: &SharedKnowledge) -> Result<()> {
+    let embedding_blob = knowledge.embedding.as_deref().map(encode_embedding);
+    conn.execute(
+        r#"
+        INSERT INTO shared_knowledge
+        (id, knowledge_type, title, content, source_agents, embedding,
+         confidence_score, created_at, updated_at, version, tags)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        ON CONFLICT(id) DO UPDATE SET
+            knowledge_type = excluded.knowledge_type, title = excluded.title, content = excluded.content,
+            source_agents = excluded.source_agents, embedding = excluded.embedding,
+            confidence_score = excluded.confidence_score, updated_at = excluded.updated_at,
+            version = excluded.version, tags = excluded.tags
+        "#,
+        params![
+            knowledge.id,
+            format!("{:?}", knowledge.knowledge_type),
+            knowledge.title,
+            knowledge.content,
+            serde_json::to_string(&knowledge.source_agents)?,
+            embedding_blob,
+            knowledge.confidence_score,
+            knowledge.created_at.to_rfc3339(),
+            knowledge.updated_at.to_rfc3339(),
+            knowledge.version,
+            serde_json::to_string(&knowledge.tags)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// Rebuild the full-text index if it has drifted from the table, as it did under the old triggers
+pub fn repair_fts_index(conn: &Connection) -> Result<()> {
+    let consistent = conn.execute(
+        "INSERT INTO shared_knowledge_fts(shared_knowledge_fts, rank) VALUES ('integrity-check', 1)",
+        [],
+    ).is_ok();
+    if !consistent {
+        warn!("Shared knowledge search index out of sync, rebuilding");
+        conn.execute("INSERT INTO shared_knowledge_fts(shared_knowledge_fts) VALUES ('rebuild')", [])?;
+    }
+    Ok(())
+}
+
+fn load_knowledge(conn: &Connection, knowledge_type: Option<&str>) -> Result<Vec<SharedKnowledge>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM shared_knowledge WHERE ?1 IS NULL OR knowledge_type = ?1",
+        KNOWLEDGE_COLUMNS
+    ))?;
+    let rows = stmt.query_map(params![knowledge_type], row_to_knowledge)?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Add an agent's contribution to the shared store, merging it into an existing entry when
+/// it is the same knowledge and recording a conflict when it contradicts one.
+///
+/// Entries match on knowledge type plus either a near-identical embedding or the same title;
+/// an embedding match whose content contradicts the contribution is never merged into.
+/// A title match with different content revises the entry if the contributor is its only
+/// source; otherwise it's a conflict, so one agent can't overwrite what others agreed on.
+/// A closely related entry from other agents whose content negates or opposes the
+/// contribution is a contradiction: both are kept, flagged and lose confidence.
+/// Embeddings are compared with `metric`, the one the embedding model is configured for,
+/// and `classifier` decides from them whether two entries contradict.
+pub fn contribute(
+    conn: &mut Connection,
+    contribution: SharedKnowledge,
+    metric: SimilarityMetric,
+    classifier: &ContradictionClassifier,
+) -> Result<ContributionOutcome> {
+    let tx = conn.transaction()?;
+    let existing = load_knowledge(&tx, Some(&format!("{:?}", contribution.knowledge_type)))?;
+
+    if let Some(outcome) = flag_contradiction(&tx, &existing, contribution.clone(), metric, classifier)? {
+        tx.commit()?;
+        return Ok(outcome);
+    }
+
+    let similar = contribution.embedding.as_ref().and_then(|embedding| {
+        existing.iter()
+            .filter_map(|k| k.embedding.as_ref().map(|e| (k, metric.similarity(embedding, e))))
+            .filter(|(_, similarity)| *similarity >= DUPLICATE_SIMILARITY)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(k, _)| k)
+    });
+    let title = normalize(&contribution.title);
+    let same_title = existing.iter().find(|k| normalize(&k.title) == title);
+
+    // A near-identical embedding only means the same knowledge if the content doesn't contradict
+    let matched = similar.map(|entry| (entry, entry.contradiction_with(&contribution, classifier).is_none()))
+        .or_else(|| same_title.map(|entry| (entry, false)));
+    let outcome = match matched {
+        Some((entry, agrees)) if agrees || normalize(&entry.content) == normalize(&contribution.content) => {
+            merge(&tx, entry, &contribution)?
+        }
+        Some((entry, _)) if entry.source_agents.iter().all(|a| contribution.source_agents.contains(a)) => {
+            let mut revised = entry.clone();
+            revised.title = contribution.title.clone();
+            revised.content = contribution.content.clone();
+            revised.embedding = contribution.embedding.clone();
+            revised.merge_contribution(&contribution);
+            revised.version += 1;
+            revised.updated_at = Utc::now();
+            upsert_knowledge(&tx, &revised)?;
+            ContributionOutcome::Revised { knowledge_id: revised.id }
+        }
+        Some((entry, _)) => record_conflict(&tx, entry, &contribution)?,
+        None => {
+            upsert_knowledge(&tx, &contribution)?;
+            ContributionOutcome::Created { knowledge_id: contribution.id.clone() }
+        }
+    };
+
+    tx.commit()?;
+    Ok(outcome)
+}
+
+/// Store the contribution alongside the first related entry it contradicts, if any
+fn flag_contradiction(
+    conn: &Connection,
+    existing: &[SharedKnowledge],
+    mut contribution: SharedKnowledge,
+    metric: SimilarityMetric,
+    classifier: &ContradictionClassifier,
+) -> Result<Option<ContributionOutcome>> {
+    let title = normalize(&contribution.title);
+    let related = existing.iter()
+        // An agent correcting only itself is a revision, not a contradiction
+        .filter(|k| k.source_agents.iter().any(|a| !contribution.source_agents.contains(a)))
+        .filter_map(|k| {
+            let similarity = match (&contribution.embedding, &k.embedding) {
+                (Some(a), Some(b)) => metric.similarity(a, b),
+                _ if normalize(&k.title) == title => 1.0,
+                _ => return None,
+            };
+            (similarity >= CONTRADICTION_SIMILARITY).then_some((k, similarity))
+        });
+    let Some((entry, similarity, kind)) = related
+        .filter_map(|(k, similarity)| k.contradiction_with(&contribution, classifier).map(|kind| (k, similarity, kind)))
+        .next()
+    else {
+        return Ok(None);
+    };
+
+    let mut entry = entry.clone();
+    entry.record_contradiction();
+    contribution.record_contradiction();
+    upsert_knowledge(conn, &entry)?;
+    upsert_knowledge(conn, &contribution)?;
+    conn.execute(
+        "INSERT INTO shared_knowledge_contradictions (id, knowledge_id, contradicting_id, kind, similarity, detected_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            contribution.id,
+            entry.id,
+            format!("{:?}", kind),
+            similarity,
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(Some(ContributionOutcome::Contradiction { knowledge_id: contribution.id, contradicts: entry.id, kind }))
+}
+
+fn merge(conn: &Connection, entry: &SharedKnowledge, contribution: &SharedKnowledge) -> Result<ContributionOutcome> {
+    let mut merged = entry.clone();
+    if merged.merge_contribution(contribution) {
+        upsert_knowledge(conn, &merged)?;
+    }
+    Ok(ContributionOutcome::Merged { knowledge_id: merged.id })
+}
+
+fn record_conflict(conn: &Connection, entry: &SharedKnowledge, contribution: &SharedKnowledge) -> Result<ContributionOutcome> {
+    let agent_id = contribution.source_agents.first().cloned().unwrap_or_default();
+    // The same agent repeating the same claim doesn't need a second review entry
+    let existing: Option<String> = conn.query_row(
+        "SELECT id FROM shared_knowledge_conflicts WHERE knowledge_id = ?1 AND agent_id = ?2 AND content = ?3",
+        params![entry.id, agent_id, contribution.content],
+        |row| row.get(0),
+    ).optional()?;
+
+    let conflict_id = match existing {
+        Some(id) => id,
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO shared_knowledge_conflicts (id, knowledge_id, agent_id, title, content, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![id, entry.id, agent_id, contribution.title, contribution.content, Utc::now().to_rfc3339()],
+            )?;
+            id
+        }
+    };
+    Ok(ContributionOutcome::Conflict { knowledge_id: entry.id.clone(), conflict_id })
+}
+
+/// FTS5 query matching any of the words in `query`, each quoted so user text can't inject syntax
+pub(super) fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .take(MAX_QUERY_TERMS)
+        .map(|term| format!("\"{}\"", term))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+/// Search the shared store by full text and, given a query embedding, by similarity.
+/// The two rankings are blended with reciprocal rank fusion; ties go to higher confidence.
+pub fn search_knowledge(
+    conn: &Connection,
+    query: &str,
+    knowledge_type: Option<&str>,
+    query_embedding: Option<&[f32]>,
+    metric: SimilarityMetric,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<SharedKnowledge>> {
+    let window = (offset + limit).saturating_mul(4).clamp(20, 500);
+    let mut scores: HashMap<String, f32> = HashMap::new();
+    let mut entries: HashMap<String, SharedKnowledge> = HashMap::new();
+    let mut add_ranking = |ranked: Vec<SharedKnowledge>| {
+        for (rank, knowledge) in ranked.into_iter().enumerate() {
+            *scores.entry(knowledge.id.clone()).or_insert(0.0) += 1.0 / (RRF_K + rank as f32 + 1.0);
+            entries.entry(knowledge.id.clone()).or_insert(knowledge);
+        }
+    };
+
+    if let Some(fts) = fts_query(query) {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM shared_knowledge_fts
+             JOIN shared_knowledge ON shared_knowledge.rowid = shared_knowledge_fts.rowid
+             WHERE shared_knowledge_fts MATCH ?1 AND (?2 IS NULL OR shared_knowledge.knowledge_type = ?2)
+             ORDER BY bm25(shared_knowledge_fts) LIMIT ?3",
+            KNOWLEDGE_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![fts, knowledge_type, window as i64], row_to_knowledge)?;
+        add_ranking(rows.collect::<rusqlite::Result<Vec<_>>>()?);
+    }
+
+    if let Some(query_embedding) = query_embedding {
+        let mut ranked: Vec<(f32, SharedKnowledge)> = load_knowledge(conn, knowledge_type)?
+            .into_iter()
+            .filter_map(|k| k.embedding.as_ref().map(|e| metric.similarity(query_embedding, e)).map(|s| (s, k)))
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranked.truncate(window);
+        add_ranking(ranked.into_iter().map(|(_, k)| k).collect());
+    }
+
+    let mut results: Vec<(f32, SharedKnowledge)> = entries.into_iter()
+        .map(|(id, knowledge)| (scores[&id], knowledge))
+        .collect();
+    results.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.confidence_score.total_cmp(&a.1.confidence_score)));
+    Ok(results.into_iter().skip(offset).take(limit).map(|(_, k)| k).collect())
+}
+
+/// Open the global store, creating its schema if no agent has initialized it yet
+pub fn open_store() -> Result<super::pool::PooledConnection> {
+    let path = super::simple_memory::SimpleMemoryManager::default_shared_db_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let conn = super::pool::connection(&path)?;
+    super::migrations::migrate(&conn, super::migrations::AGENT_MEMORY_MIGRATIONS)?;
+    conn.execute("PRAGMA foreign_keys = ON;", [])?;
+    Ok(conn)
+}
+
+pub fn list_conflicts(conn: &Connection, knowledge_id: &str) -> Result<Vec<KnowledgeConflict>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, knowledge_id, agent_id, title, content, created_at FROM shared_knowledge_conflicts
+         WHERE knowledge_id = ?1 ORDER BY created_at"
+    )?;
+    let rows = stmt.query_map(params![knowledge_id], |row| {
+        let created_at: String = row.get(5)?;
+        Ok(KnowledgeConflict {
+            id: row.get(0)?,
+            knowledge_id: row.get(1)?,
+            agent_id: row.get(2)?,
+            title: row.get(3)?,
+            content: row.get(4)?,
+            created_at: super::parse_db_timestamp(&created_at),
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Flagged contradictions, newest first, optionally only those involving one entry
+pub fn list_contradictions(conn: &Connection, knowledge_id: Option<&str>) -> Result<Vec<KnowledgeContradiction>> {
+    query_contradictions(conn, "?1 IS NULL OR knowledge_id = ?1 OR contradicting_id = ?1", knowledge_id)
+}
+
+/// One flagged contradiction, with both entries
+pub fn get_contradiction(conn: &Connection, contradiction_id: &str) -> Result<Option<KnowledgeContradiction>> {
+    Ok(query_contradictions(conn, "id = ?1", Some(contradiction_id))?.pop())
+}
+
+fn query_contradictions(conn: &Connection, filter: &str, value: Option<&str>) -> Result<Vec<KnowledgeContradiction>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, knowledge_id, contradicting_id, kind, similarity, detected_at FROM shared_knowledge_contradictions
+         WHERE {} ORDER BY detected_at DESC",
+        filter
+    ))?;
+    let rows = stmt.query_map(params![value], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, f64>(4)?,
+            row.get::<_, String>(5)?,
+        ))
+    })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut entry_stmt = conn.prepare(&format!("SELECT {} FROM shared_knowledge WHERE id = ?1", KNOWLEDGE_COLUMNS))?;
+    let mut contradictions = Vec::with_capacity(rows.len());
+    for (id, knowledge_id, contradicting_id, kind, similarity, detected_at) in rows {
+        let knowledge = entry_stmt.query_row(params![knowledge_id], row_to_knowledge)?;
+        let contradicting = entry_stmt.query_row(params![contradicting_id], row_to_knowledge)?;
+        contradictions.push(KnowledgeContradiction {
+            id,
+            kind: if kind == "Opposite" { ContradictionKind::Opposite } else { ContradictionKind::Negation },
+            similarity: similarity as f32,
+            detected_at: super::parse_db_timestamp(&detected_at),
+            knowledge,
+            contradicting,
+        });
+    }
+    Ok(contradictions)
+}
+
+/// Contributions that contradicted a shared knowledge entry and await review
+#[tauri::command]
+pub async fn get_shared_knowledge_conflicts(
+    knowledge_id: String,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<Vec<KnowledgeConflict>, String> {
+    info!("Listing conflicts for shared knowledge: {}", knowledge_id);
+
+    crate::validation::MemoryValidator::validate_memory_id(&knowledge_id)
+        .map_err(|e| e.to_string())?;
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "search_shared_knowledge",
+        &[knowledge_id.clone()],
+        &[]
+    ).await?;
+    let sanitized_knowledge_id = &validation_result.sanitized_inputs[0];
+
+    let conn = open_store().map_err(|e| format!("Failed to open shared knowledge store: {}", e))?;
+    list_conflicts(&conn, sanitized_knowledge_id)
+        .map_err(|e| format!("Failed to list shared knowledge conflicts: {}", e))
+}
+
+/// Pairs of shared knowledge entries flagged as contradicting each other, for review
+#[tauri::command]
+pub async fn list_contradicting_knowledge(
+    knowledge_id: Option<String>,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<Vec<KnowledgeContradiction>, String> {
+    info!("Listing contradicting shared knowledge: {:?}", knowledge_id);
+
+    if let Some(knowledge_id) = &knowledge_id {
+        crate::validation::MemoryValidator::validate_memory_id(knowledge_id)
+            .map_err(|e| e.to_string())?;
+    }
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "search_shared_knowledge",
+        &knowledge_id.iter().cloned().collect::<Vec<_>>(),
+        &[]
+    ).await?;
+    let sanitized_knowledge_id = validation_result.sanitized_inputs.first().map(String::as_str);
+
+    let conn = open_store().map_err(|e| format!("Failed to open shared knowledge store: {}", e))?;
+    list_contradictions(&conn, sanitized_knowledge_id)
+        .map_err(|e| format!("Failed to list contradicting shared knowledge: {}", e))
+}
+
+/// Record a reviewer's verdict on a flagged contradiction and train the contradiction network
+/// on it. A dismissed contradiction is removed from review; a confirmed one stays flagged.
+#[tauri::command]
+pub async fn review_knowledge_contradiction(
+    contradiction_id: String,
+    confirmed: bool,
+    state: State<'_, super::simple_commands::MemoryState>,
+) -> Result<(), String> {
+    info!("Reviewing shared knowledge contradiction {}: confirmed={}", contradiction_id, confirmed);
+
+    crate::validation::MemoryValidator::validate_memory_id(&contradiction_id)
+        .map_err(|e| e.to_string())?;
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "knowledge_operations",
+        &[contradiction_id],
+        &[]
+    ).await?;
+    let sanitized_contradiction_id = &validation_result.sanitized_inputs[0];
+
+    let conn = open_store().map_err(|e| format!("Failed to open shared knowledge store: {}", e))?;
+    let contradiction = get_contradiction(&conn, sanitized_contradiction_id)
+        .map_err(|e| format!("Failed to load contradiction: {}", e))?
+        .ok_or_else(|| format!("Contradiction not found: {}", sanitized_contradiction_id))?;
+
+    if let (Some(a), Some(b)) = (&contradiction.knowledge.embedding, &contradiction.contradicting.embedding) {
+        let graph_lock = state.get_neural_graph().await?;
+        let mut graph = graph_lock.lock().await;
+        let graph = graph.as_mut().ok_or("Neural knowledge graph not initialized")?;
+        graph.record_contradiction_feedback(a, b, confirmed.then_some(contradiction.kind))
+            .map_err(|e| format!("Failed to train contradiction network: {}", e))?;
+        if let Err(e) = graph.save_models() {
+            warn!("Failed to save knowledge graph networks: {}", e);
+        }
+    }
+
+    if !confirmed {
+        conn.execute("DELETE FROM shared_knowledge_contradictions WHERE id = ?1", params![contradiction.id])
+            .map_err(|e| format!("Failed to dismiss contradiction: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::AGENT_MEMORY_SCHEMA;
+
+    fn store() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(AGENT_MEMORY_SCHEMA).unwrap();
+        conn
+    }
+
+    /// Contradiction network trained on just these embedding pairs
+    fn classifier(pairs: &[(&[f32], &[f32], Option<ContradictionKind>)]) -> ContradictionClassifier {
+        let mut classifier = ContradictionClassifier::new(2).unwrap();
+        for _ in 0..300 {
+            for (a, b, kind) in pairs {
+                classifier.train(a, b, *kind, 1).unwrap();
+            }
+        }
+        classifier
+    }
+
+    fn knowledge(title: &str, content: &str, agent: &str, embedding: Option<Vec<f32>>) -> SharedKnowledge {
+        let mut k = SharedKnowledge::new(KnowledgeType::Fact, title.to_string(), content.to_string(), agent.to_string());
+        k.embedding = embedding;
+        k
+    }
+
+    #[test]
+    fn test_contributions_merge_revise_and_conflict() {
+        let mut conn = store();
+        let classifier = classifier(&[(&[1.0, 0.0], &[1.0, 0.0], None), (&[1.0, 0.0], &[0.99, 0.05], None)]);
+        let first = contribute(&mut conn, knowledge("Token expiry", "Tokens expire after one hour", "a1", None), SimilarityMetric::Cosine, &classifier).unwrap();
+        let id = first.knowledge_id().to_string();
+        assert_eq!(first, ContributionOutcome::Created { knowledge_id: id.clone() });
+
+        // Sole contributor rewording its own entry
+        let revised = contribute(&mut conn, knowledge("token  EXPIRY", "Tokens expire after 60 minutes", "a1", Some(vec![1.0, 0.0])), SimilarityMetric::Cosine, &classifier).unwrap();
+        assert_eq!(revised, ContributionOutcome::Revised { knowledge_id: id.clone() });
+
+        // Another agent agreeing, found through its embedding despite a different title
+        let mut agreeing = knowledge("Access token lifetime", "Tokens expire after 60 minutes", "a2", Some(vec![1.0, 0.0]));
+        agreeing.tags = vec!["auth".to_string()];
+        assert_eq!(contribute(&mut conn, agreeing, SimilarityMetric::Cosine, &classifier).unwrap(), ContributionOutcome::Merged { knowledge_id: id.clone() });
+        let similar = knowledge("Lifetime of tokens", "Tokens last an hour", "a3", Some(vec![0.99, 0.05]));
+        assert_eq!(contribute(&mut conn, similar, SimilarityMetric::Cosine, &classifier).unwrap(), ContributionOutcome::Merged { knowledge_id: id.clone() });
+
+        // A disagreeing agent can't overwrite what others contributed
+        let contradiction = knowledge("Token expiry", "Tokens never expire", "a4", None);
+        let outcome = contribute(&mut conn, contradiction.clone(), SimilarityMetric::Cosine, &classifier).unwrap();
+        assert!(matches!(outcome, ContributionOutcome::Conflict { ref knowledge_id, .. } if *knowledge_id == id));
+        assert_eq!(contribute(&mut conn, contradiction, SimilarityMetric::Cosine, &classifier).unwrap(), outcome);
+
+        let entry = &load_knowledge(&conn, None).unwrap()[0];
+        assert_eq!(entry.content, "Tokens expire after 60 minutes");
+        assert_eq!(entry.source_agents, vec!["a1", "a2", "a3"]);
+        assert_eq!(entry.tags, vec!["auth"]);
+        assert_eq!(entry.version, 4);
+        assert_eq!(list_conflicts(&conn, &id).unwrap()[0].agent_id, "a4");
+    }
+
+    #[test]
+    fn test_contradicting_contribution_flags_both_entries() {
+        let mut conn = store();
+        let classifier = classifier(&[(&[1.0, 0.0], &[0.9, 0.2], Some(ContradictionKind::Opposite)), (&[1.0, 0.0], &[1.0, 0.0], None)]);
+        let original = contribute(&mut conn, knowledge("Caching", "Enable caching for the search API", "a1", Some(vec![1.0, 0.0])), SimilarityMetric::Cosine, &classifier).unwrap();
+        let original_id = original.knowledge_id().to_string();
+
+        // Related by embedding, opposite in content
+        let opposing = knowledge("Search cache", "Disable caching for the search API", "a2", Some(vec![0.9, 0.2]));
+        let outcome = contribute(&mut conn, opposing.clone(), SimilarityMetric::Cosine, &classifier).unwrap();
+        assert_eq!(outcome, ContributionOutcome::Contradiction {
+            knowledge_id: opposing.id.clone(),
+            contradicts: original_id.clone(),
+            kind: ContradictionKind::Opposite,
+        });
+
+        let contradictions = list_contradictions(&conn, Some(&original_id)).unwrap();
+        assert_eq!(contradictions.len(), 1);
+        assert_eq!(contradictions[0].knowledge.id, opposing.id);
+        assert!(contradictions[0].knowledge.confidence_score < opposing.confidence_score);
+        assert!(contradictions[0].contradicting.confidence_score < opposing.confidence_score);
+        assert!(list_contradictions(&conn, Some("unrelated")).unwrap().is_empty());
+
+        // The author rewording its own claim isn't contradicting anyone
+        let own = contribute(&mut conn, knowledge("Search cache", "Don't disable caching for the search API", "a2", None), SimilarityMetric::Cosine, &classifier).unwrap();
+        assert!(!matches!(own, ContributionOutcome::Contradiction { .. }));
+    }
+
+    #[test]
+    fn test_contradicting_contribution_is_never_merged() {
+        let mut conn = store();
+        let classifier = classifier(&[(&[1.0, 0.0], &[0.97, 0.2], Some(ContradictionKind::Negation)), (&[1.0, 0.0], &[1.0, 0.0], None)]);
+        let original = contribute(&mut conn, knowledge("Retries", "Retry failed uploads", "a1", Some(vec![1.0, 0.0])), SimilarityMetric::Cosine, &classifier).unwrap();
+        let id = original.knowledge_id().to_string();
+
+        // Close enough to be a duplicate, but the author now says the opposite: a revision
+        let reversed = knowledge("Upload retries", "Don't retry failed uploads", "a1", Some(vec![0.97, 0.2]));
+        assert_eq!(contribute(&mut conn, reversed, SimilarityMetric::Cosine, &classifier).unwrap(), ContributionOutcome::Revised { knowledge_id: id });
+        let entry = &load_knowledge(&conn, None).unwrap()[0];
+        assert_eq!(entry.content, "Don't retry failed uploads");
+    }
+
+    #[test]
+    fn test_search_blends_text_and_embeddings() {
+        let mut conn = store();
+        let classifier = classifier(&[]);
+        for (title, content, embedding) in [
+            ("Retry policy", "Retry failed requests with exponential backoff", vec![0.0, 1.0]),
+            ("Rate limits", "Providers throttle bursts of requests", vec![1.0, 0.0]),
+            ("Logging", "Write structured logs", vec![0.7, 0.7]),
+        ] {
+            contribute(&mut conn, knowledge(title, content, "a1", Some(embedding)), SimilarityMetric::Cosine, &classifier).unwrap();
+        }
+        // Edits keep the index in sync
+        let mut logging = load_knowledge(&conn, None).unwrap().into_iter().find(|k| k.title == "Logging").unwrap();
+        logging.content = "Write structured logs, never secrets".to_string();
+        upsert_knowledge(&conn, &logging).unwrap();
+        repair_fts_index(&conn).unwrap();
+
+        let titles = |results: Vec<SharedKnowledge>| results.into_iter().map(|k| k.title).collect::<Vec<_>>();
+        let metric = SimilarityMetric::Cosine;
+        assert_eq!(titles(search_knowledge(&conn, "backoff", None, None, metric, 10, 0).unwrap()), vec!["Retry policy"]);
+        assert_eq!(titles(search_knowledge(&conn, "secrets", None, None, metric, 10, 0).unwrap()), vec!["Logging"]);
+
+        // Text and embedding both point at rate limits, so it outranks the text-only retry match
+        let blended = search_knowledge(&conn, "requests", None, Some(&[1.0, 0.1]), metric, 2, 0).unwrap();
+        assert_eq!(titles(blended), vec!["Rate limits", "Retry policy"]);
+        assert!(search_knowledge(&conn, "backoff", Some("Rule"), None, metric, 10, 0).unwrap().is_empty());
+    }
+}
@@ -1,14 +1,25 @@
 use super::memory::*;
 use super::simple_memory::SimpleMemoryManager;
 use super::neural_embeddings::NeuralEmbeddingService;
-use crate::ai::{SecurityManager, SecurityMiddleware};
+use super::neural_knowledge_graph::{EdgeSuggestion, NeuralKnowledgeGraph, RelationshipFeedbackStats};
+use super::memory_budget::MemoryInjectionReport;
+use super::memory_dedup::{load_dedup_config, merge_into_recent};
+use super::memory_quota::forget_evicted;
+use super::memory_patterns::MemoryPatternReport;
+use super::memory_anomalies::MEMORY_ANOMALY_MONITOR;
+use super::shared_knowledge::{
+    open_store as open_shared_knowledge_store, parse_knowledge_type, search_knowledge, ContributionOutcome,
+};
+use crate::ai::{enforce_path_policy, ensure_disk_space, AIState, PathAccess, SecurityMiddleware};
+use crate::ai::csrf::session_agent;
 use crate::validation::{MemoryValidator, ValidationError};
+use crate::operations::start_operation;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tokio::sync::Mutex as AsyncMutex;
-use tauri::State;
+use tauri::{AppHandle, Manager, State, Webview};
 use tracing::{info, warn, error};
 
 // Additional types for knowledge graph endpoints
@@ -38,22 +49,92 @@ pub struct KnowledgeGraphView {
     pub metadata: HashMap<String, String>,
 }
 
+impl From<&KnowledgeNode> for GraphNode {
+    fn from(node: &KnowledgeNode) -> Self {
+        Self {
+            id: node.id.clone(),
+            node_type: format!("{:?}", node.node_type),
+            name: node.name.clone(),
+            properties: Some(node.properties.clone()),
+            position: None,
+        }
+    }
+}
+
+impl From<&KnowledgeEdge> for GraphEdge {
+    fn from(edge: &KnowledgeEdge) -> Self {
+        Self {
+            id: edge.id.clone(),
+            from_node: edge.from_node.clone(),
+            to_node: edge.to_node.clone(),
+            relationship_type: format!("{:?}", edge.relationship_type),
+            weight: Some(edge.weight),
+            properties: Some(edge.properties.clone()),
+        }
+    }
+}
+
+/// View of the weighted neighbourhood of `seed` in the agent's stored graph
+pub(crate) fn subgraph_view(
+    agent_id: &str,
+    nodes: &[KnowledgeNode],
+    edges: &[KnowledgeEdge],
+    seed: &str,
+    depth: usize,
+    max_nodes: usize,
+) -> KnowledgeGraphView {
+    let (node_indices, edge_indices) = super::graph_paths::bounded_subgraph(nodes, edges, seed, depth, max_nodes);
+    let nodes: Vec<GraphNode> = node_indices.into_iter().map(|i| GraphNode::from(&nodes[i])).collect();
+    let edges: Vec<GraphEdge> = edge_indices.into_iter().map(|i| GraphEdge::from(&edges[i])).collect();
+
+    let metadata = HashMap::from([
+        ("agent_id".to_string(), agent_id.to_string()),
+        ("seed_node".to_string(), seed.to_string()),
+        ("node_count".to_string(), nodes.len().to_string()),
+        ("edge_count".to_string(), edges.len().to_string()),
+        ("depth".to_string(), depth.to_string()),
+        ("max_nodes".to_string(), max_nodes.to_string()),
+    ]);
+    KnowledgeGraphView { nodes, edges, metadata }
+}
+
+/// Memories at least this large are checked against free disk space before saving
+const LARGE_MEMORY_BYTES: usize = 64 * 1024;
+
 // Global state for memory managers, neural embedding service, and security
 pub struct MemoryState {
+    /// Managers of the active workspace, keyed by agent id
     managers: Arc<Mutex<HashMap<String, SimpleMemoryManager>>>,
+    workspace: Arc<Mutex<String>>,
     neural_embedding_service: Arc<AsyncMutex<Option<NeuralEmbeddingService>>>,
+    neural_graph: Arc<AsyncMutex<Option<NeuralKnowledgeGraph>>>,
     security_middleware: Arc<SecurityMiddleware>,
+    injection_reports: Arc<Mutex<HashMap<String, MemoryInjectionReport>>>,
+    pattern_reports: Arc<Mutex<HashMap<String, MemoryPatternReport>>>,
+}
+
+/// Run SQLite work for `manager` on the blocking thread pool, so a statement waiting on the
+/// database's write lock stalls a blocking thread rather than an async worker
+pub(crate) async fn on_blocking_pool<T, F>(manager: &SimpleMemoryManager, work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&SimpleMemoryManager) -> Result<T> + Send + 'static,
+{
+    let manager = manager.clone();
+    tokio::task::spawn_blocking(move || work(&manager)).await?
 }
 
 impl MemoryState {
-    pub fn new() -> Self {
-        let security_manager = Arc::new(AsyncMutex::new(SecurityManager::new()));
-        let security_middleware = Arc::new(SecurityMiddleware::new(security_manager));
-        
+    /// Takes the app-wide middleware so memory commands share its rate limit buckets
+    pub fn new(security_middleware: Arc<SecurityMiddleware>) -> Self {
         Self {
             managers: Arc::new(Mutex::new(HashMap::new())),
+            workspace: Arc::new(Mutex::new(crate::ai::DEFAULT_WORKSPACE.to_string())),
             neural_embedding_service: Arc::new(AsyncMutex::new(None)),
+            neural_graph: Arc::new(AsyncMutex::new(None)),
             security_middleware,
+            injection_reports: Arc::new(Mutex::new(HashMap::new())),
+            pattern_reports: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -61,11 +142,22 @@ impl MemoryState {
         self.security_middleware.clone()
     }
 
+    /// Scope memory managers to `workspace`; managers of the previous workspace are dropped
+    pub fn set_workspace(&self, workspace: &str) {
+        let mut current = self.workspace.lock().unwrap();
+        if *current != workspace {
+            *current = workspace.to_string();
+            self.managers.lock().unwrap().clear();
+        }
+    }
+
     pub fn get_or_create_manager(&self, agent_id: String) -> Result<SimpleMemoryManager, String> {
+        // Held until the manager is cached so a concurrent switch can't cache it for the wrong workspace
+        let workspace = self.workspace.lock().unwrap();
         let mut managers = self.managers.lock().unwrap();
         
         if !managers.contains_key(&agent_id) {
-            let manager = SimpleMemoryManager::new(agent_id.clone())
+            let manager = SimpleMemoryManager::for_workspace(agent_id.clone(), &workspace)
                 .map_err(|e| format!("Failed to create memory manager: {}", e))?;
             
             manager.initialize()
@@ -79,21 +171,91 @@ impl MemoryState {
             .cloned()
     }
 
+    /// Drop the cached manager of `agent_id`, as when its database is removed
+    pub fn forget_manager(&self, agent_id: &str) -> bool {
+        self.managers.lock().unwrap().remove(agent_id).is_some()
+    }
+
     pub async fn initialize_neural_embedding_service(&self) -> Result<(), String> {
         let mut service_lock = self.neural_embedding_service.lock().await;
         if service_lock.is_none() {
             let service = NeuralEmbeddingService::new(None).await
                 .map_err(|e| format!("Failed to create neural embedding service: {}", e))?;
+            // Persist freshly initialized weights too, so embeddings stay comparable after a restart
+            if let Err(e) = service.save_models() {
+                warn!("Failed to save neural embedding networks: {}", e);
+            }
             
             *service_lock = Some(service);
         }
         Ok(())
     }
 
+    /// Metric of the active embedding model, or cosine before the service is initialized
+    pub async fn similarity_metric(&self) -> SimilarityMetric {
+        self.neural_embedding_service.lock().await.as_ref()
+            .map(|service| service.similarity_metric())
+            .unwrap_or_default()
+    }
+
     pub async fn get_neural_embedding_service(&self) -> Result<Arc<AsyncMutex<Option<NeuralEmbeddingService>>>, String> {
         self.initialize_neural_embedding_service().await?;
         Ok(self.neural_embedding_service.clone())
     }
+
+    pub async fn initialize_neural_graph(&self) -> Result<(), String> {
+        let mut graph_lock = self.neural_graph.lock().await;
+        if graph_lock.is_none() {
+            let graph = NeuralKnowledgeGraph::new(None).await
+                .map_err(|e| format!("Failed to create neural knowledge graph: {}", e))?;
+            if let Err(e) = graph.save_models() {
+                warn!("Failed to save knowledge graph networks: {}", e);
+            }
+
+            *graph_lock = Some(graph);
+        }
+        Ok(())
+    }
+
+    pub async fn get_neural_graph(&self) -> Result<Arc<AsyncMutex<Option<NeuralKnowledgeGraph>>>, String> {
+        self.initialize_neural_graph().await?;
+        Ok(self.neural_graph.clone())
+    }
+
+    /// Neural embedding service handle, without starting the service
+    pub fn neural_embedding_handle(&self) -> Arc<AsyncMutex<Option<NeuralEmbeddingService>>> {
+        self.neural_embedding_service.clone()
+    }
+
+    /// Neural knowledge graph handle, without building the graph
+    pub fn neural_graph_handle(&self) -> Arc<AsyncMutex<Option<NeuralKnowledgeGraph>>> {
+        self.neural_graph.clone()
+    }
+
+    pub fn record_injection_report(&self, report: MemoryInjectionReport) {
+        let mut reports = self.injection_reports.lock().unwrap();
+        reports.insert(report.agent_id.clone(), report);
+    }
+
+    pub fn last_injection_report(&self, agent_id: &str) -> Option<MemoryInjectionReport> {
+        let reports = self.injection_reports.lock().unwrap();
+        reports.get(agent_id).cloned()
+    }
+
+    pub fn clear_injection_report(&self, agent_id: &str) -> bool {
+        let mut reports = self.injection_reports.lock().unwrap();
+        reports.remove(agent_id).is_some()
+    }
+
+    pub fn record_pattern_report(&self, report: MemoryPatternReport) {
+        let mut reports = self.pattern_reports.lock().unwrap();
+        reports.insert(report.agent_id.clone(), report);
+    }
+
+    pub fn last_pattern_report(&self, agent_id: &str) -> Option<MemoryPatternReport> {
+        let reports = self.pattern_reports.lock().unwrap();
+        reports.get(agent_id).cloned()
+    }
 }
 
 // Helper function to convert ValidationError to String
@@ -101,6 +263,18 @@ fn validation_error_to_string(err: ValidationError) -> String {
     err.to_string()
 }
 
+/// Where memory backups are written, created on first use
+pub(crate) fn backup_directory() -> Result<std::path::PathBuf, String> {
+    let backup_dir = dirs::home_dir()
+        .ok_or_else(|| "Could not find home directory".to_string())?
+        .join(".agent-memory")
+        .join("backups");
+
+    std::fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    Ok(backup_dir)
+}
+
 // Tauri Commands
 
 #[tauri::command]
@@ -127,6 +301,7 @@ pub async fn init_agent_memory(
     // Phase 3: Business Logic
     let _manager = state.get_or_create_manager(agent_id)?;
     state.initialize_neural_embedding_service().await?;
+    state.initialize_neural_graph().await?;
     Ok(())
 }
 
@@ -138,6 +313,7 @@ pub async fn save_agent_memory(
     tags: Option<Vec<String>>,
     metadata: Option<HashMap<String, String>>,
     state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
 ) -> Result<String, String> {
     info!("Saving agent memory for: {}", agent_id);
     
@@ -203,6 +379,19 @@ pub async fn save_agent_memory(
         memory = memory.with_metadata(metadata);
     }
 
+    store_memory(&state, &ai_state, &manager, memory).await
+}
+
+/// Embed, deduplicate and save a validated memory, then feed it to the anomaly monitor and
+/// the neural graph. Every path that stores memories goes through here so they are all
+/// searchable by embedding. Returns the id of the stored memory, or of the recent memory a
+/// duplicate was merged into.
+pub(crate) async fn store_memory(
+    state: &MemoryState,
+    ai_state: &AIState,
+    manager: &SimpleMemoryManager,
+    mut memory: AgentMemory,
+) -> Result<String, String> {
     // Generate neural embedding if service is available
     let neural_embedding_service_lock = state.get_neural_embedding_service().await?;
     let mut neural_embedding_service = neural_embedding_service_lock.lock().await;
@@ -219,9 +408,40 @@ pub async fn save_agent_memory(
         }
     }
 
+    drop(neural_embedding_service);
+
+    // A repeat of a recent memory is folded into it rather than stored again
+    let dedup_config = load_dedup_config(ai_state, &memory.agent_id);
+    if dedup_config.enabled {
+        let metric = state.similarity_metric().await;
+        if let Some(existing) = merge_into_recent(manager, &memory, &dedup_config, metric)
+            .map_err(|e| format!("Failed to deduplicate memory: {}", e))? {
+            info!("Merged duplicate memory into {}", existing.id);
+            return Ok(existing.id);
+        }
+    }
+
+    // Embeddings and indexes take several times the raw content, so large saves are checked first
+    if memory.content.len() >= LARGE_MEMORY_BYTES {
+        let estimate = (memory.content.len() * 4) as u64;
+        ensure_disk_space(manager.get_agent_db_path(), estimate, "memory save")
+            .map_err(|e| e.to_string())?;
+    }
+
     let memory_id = memory.id.clone();
-    manager.save_memory(&memory)
+    let saved = memory.clone();
+    let evicted = on_blocking_pool(manager, move |m| m.save_memory(&saved)).await
         .map_err(|e| format!("Failed to save memory: {}", e))?;
+    MEMORY_ANOMALY_MONITOR.observe(manager, &memory);
+    forget_evicted(state, &memory.agent_id, &evicted).await;
+
+    // Feed the neural graph so relationship discovery can queue edge suggestions
+    let mut neural_graph = state.neural_graph.lock().await;
+    if let Some(ref mut graph) = *neural_graph {
+        if let Err(e) = graph.add_memory_node(&memory).await {
+            warn!("Failed to add memory {} to neural graph: {}", memory_id, e);
+        }
+    }
 
     Ok(memory_id)
 }
@@ -256,8 +476,9 @@ pub async fn get_agent_memory(
     let sanitized_memory_id = &validation_result.sanitized_inputs[1];
     
     let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let memory_id = sanitized_memory_id.clone();
     
-    manager.get_memory(sanitized_memory_id)
+    on_blocking_pool(&manager, move |m| m.get_memory(&memory_id)).await
         .map_err(|e| format!("Failed to get memory: {}", e))
 }
 
@@ -270,9 +491,19 @@ pub async fn search_agent_memories(
     limit: Option<usize>,
     offset: Option<usize>,
     similarity_threshold: Option<f32>,
+    fusion_weights: Option<FusionWeights>,
+    tiers: Option<Vec<MemoryTier>>,
+    session_id: Option<String>,
+    webview: Webview,
     state: State<'_, MemoryState>,
 ) -> Result<Vec<MemorySearchResult>, String> {
     info!("Searching agent memories for: {}", agent_id);
+
+    // A session acting as an agent only sees another agent's memories through its grants
+    let requesting_agent = match session_id {
+        Some(ref session_id) => session_agent("search_agent_memories", session_id, webview.label())?,
+        None => None,
+    };
     
     // Phase 1: Input Validation (Highest Priority)
     MemoryValidator::validate_agent_id(&agent_id)
@@ -302,6 +533,12 @@ pub async fn search_agent_memories(
         MemoryValidator::validate_similarity_threshold(threshold)
             .map_err(validation_error_to_string)?;
     }
+
+    let fusion_weights = fusion_weights.unwrap_or_default();
+    let weights = [fusion_weights.vector, fusion_weights.text, fusion_weights.tags, fusion_weights.recency];
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return Err("Fusion weights must be finite and non-negative".to_string());
+    }
     
     // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
     let security_middleware = state.get_security_middleware();
@@ -345,20 +582,315 @@ pub async fn search_agent_memories(
         }).collect()
     });
 
+    // The search text doubles as the query for the vector signal
+    let query_embedding = match sanitized_content_search {
+        Some(ref q) if fusion_weights.vector > 0.0 => {
+            let service_lock = state.get_neural_embedding_service().await?;
+            let service = service_lock.lock().await;
+            match service.as_ref() {
+                Some(service) => service.embed_text(q, None).await.ok(),
+                None => None,
+            }
+        }
+        _ => None,
+    };
+
     let query = MemoryQuery {
         agent_id: Some(sanitized_agent_id.clone()),
         memory_types: memory_type_enums,
         content_search: sanitized_content_search,
         tags,
-        embedding: None, // Skip embeddings for now
+        embedding: query_embedding,
         similarity_threshold,
+        similarity_metric: state.similarity_metric().await,
+        limit,
+        offset,
+        time_range: None,
+        tiers,
+        fusion: Some(fusion_weights),
+    };
+
+    let requesting_agent = match requesting_agent {
+        Some(agent) if agent != *sanitized_agent_id => agent,
+        _ => {
+            return on_blocking_pool(&manager, move |m| m.search_memories(&query)).await
+                .map_err(|e| format!("Failed to search memories: {}", e));
+        }
+    };
+
+    let resource = format!("memories:{}", sanitized_agent_id);
+    let requester = requesting_agent.clone();
+    match on_blocking_pool(&manager, move |m| m.query_foreign_memories(&requester, &query)).await {
+        Ok(results) => {
+            security_middleware.audit_access(
+                "search_agent_memories",
+                &requesting_agent,
+                &resource,
+                true,
+                &format!("returned={}", results.len()),
+            );
+            Ok(results)
+        }
+        Err(e) => {
+            security_middleware.audit_access("search_agent_memories", &requesting_agent, &resource, false, &e.to_string());
+            Err(format!("Failed to search memories: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn list_agent_memories(
+    agent_id: String,
+    memory_type: Option<MemoryType>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    state: State<'_, MemoryState>,
+) -> Result<Vec<AgentMemory>, String> {
+    info!("Listing agent memories for: {}", agent_id);
+
+    // Phase 1: Input Validation (Highest Priority)
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(validation_error_to_string)?;
+    if let Some(limit_val) = limit {
+        MemoryValidator::validate_limit(limit_val)
+            .map_err(validation_error_to_string)?;
+    }
+    if let Some(offset_val) = offset {
+        MemoryValidator::validate_offset(offset_val)
+            .map_err(validation_error_to_string)?;
+    }
+
+    // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let query = MemoryQuery {
+        agent_id: Some(sanitized_agent_id.clone()),
+        memory_types: memory_type.map(|memory_type| vec![memory_type]),
+        content_search: None,
+        tags: None,
+        embedding: None,
+        similarity_threshold: None,
+        similarity_metric: state.similarity_metric().await,
         limit,
         offset,
         time_range: None,
+        tiers: None,
+        fusion: None,
     };
 
-    manager.search_memories(&query)
-        .map_err(|e| format!("Failed to search memories: {}", e))
+    on_blocking_pool(&manager, move |m| m.search_memories(&query)).await
+        .map(|results| results.into_iter().map(|result| result.memory).collect())
+        .map_err(|e| format!("Failed to list memories: {}", e))
+}
+
+/// Change a memory's content, tags or metadata; new content is embedded again and replaces
+/// the memory's node in the neural graph
+#[tauri::command]
+pub async fn update_agent_memory(
+    agent_id: String,
+    memory_id: String,
+    content: Option<String>,
+    tags: Option<Vec<String>>,
+    metadata: Option<HashMap<String, String>>,
+    state: State<'_, MemoryState>,
+) -> Result<AgentMemory, String> {
+    info!("Updating agent memory: {} for agent: {}", memory_id, agent_id);
+
+    // Phase 1: Input Validation (Highest Priority)
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(validation_error_to_string)?;
+    MemoryValidator::validate_memory_id(&memory_id)
+        .map_err(validation_error_to_string)?;
+    if let Some(ref new_content) = content {
+        MemoryValidator::validate_content(new_content)
+            .map_err(validation_error_to_string)?;
+    }
+    if let Some(ref tags_vec) = tags {
+        MemoryValidator::validate_tags(tags_vec)
+            .map_err(validation_error_to_string)?;
+    }
+    if let Some(ref metadata_map) = metadata {
+        MemoryValidator::validate_metadata(metadata_map)
+            .map_err(validation_error_to_string)?;
+    }
+
+    // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
+    let security_middleware = state.get_security_middleware();
+    let mut inputs = vec![agent_id, memory_id];
+    if let Some(ref new_content) = content {
+        inputs.push(new_content.clone());
+    }
+    let validation_result = security_middleware.validate_request(
+        "memory_operations",
+        &inputs,
+        &[]
+    ).await?;
+
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+    let sanitized_memory_id = &validation_result.sanitized_inputs[1];
+    let sanitized_content = validation_result.sanitized_inputs.get(2).cloned();
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let memory_id = sanitized_memory_id.clone();
+    let mut memory = on_blocking_pool(&manager, move |m| m.get_memory(&memory_id)).await
+        .map_err(|e| format!("Failed to get memory: {}", e))?
+        .ok_or_else(|| format!("Memory not found: {}", sanitized_memory_id))?;
+    let previous = memory.clone();
+
+    let content_changed = sanitized_content.as_ref().is_some_and(|new_content| *new_content != memory.content);
+    if let Some(new_content) = sanitized_content {
+        memory.content = new_content;
+    }
+    if let Some(tags) = tags {
+        let mut sanitized_tags = Vec::with_capacity(tags.len());
+        for tag in &tags {
+            sanitized_tags.push(security_middleware.sanitize_input(tag).await);
+        }
+        memory.tags = sanitized_tags;
+    }
+    if let Some(metadata) = metadata {
+        memory.metadata = metadata;
+    }
+    memory.updated_at = chrono::Utc::now();
+
+    if content_changed {
+        let neural_embedding_service_lock = state.get_neural_embedding_service().await?;
+        let neural_embedding_service = neural_embedding_service_lock.lock().await;
+        if let Some(ref service) = *neural_embedding_service {
+            service.evict_memories(std::slice::from_ref(&previous)).await;
+            match service.embed_memory(&memory).await {
+                Ok(embedding) => memory.embedding = Some(embedding),
+                Err(e) => {
+                    // The old embedding no longer describes the content
+                    memory.embedding = None;
+                    warn!("Failed to re-embed memory {}: {}", memory.id, e);
+                }
+            }
+        }
+    }
+
+    let updated = memory.clone();
+    if !on_blocking_pool(&manager, move |m| m.update_memory(&updated)).await
+        .map_err(|e| format!("Failed to update memory: {}", e))?
+    {
+        return Err(format!("Memory not found: {}", memory.id));
+    }
+
+    if content_changed {
+        let mut neural_graph = state.neural_graph.lock().await;
+        if let Some(ref mut graph) = *neural_graph {
+            let ids = std::collections::HashSet::from([memory.id.clone()]);
+            graph.remove_agent_nodes(&memory.agent_id, Some(&ids)).await;
+            if let Err(e) = graph.add_memory_node(&memory).await {
+                warn!("Failed to re-add memory {} to neural graph: {}", memory.id, e);
+            }
+        }
+    }
+
+    Ok(memory)
+}
+
+/// Delete a memory with its access log, cached embeddings and neural graph node. Returns
+/// false when there was nothing to delete.
+#[tauri::command]
+pub async fn delete_agent_memory(
+    agent_id: String,
+    memory_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<bool, String> {
+    info!("Deleting agent memory: {} for agent: {}", memory_id, agent_id);
+
+    // Phase 1: Input Validation (Highest Priority)
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(validation_error_to_string)?;
+    MemoryValidator::validate_memory_id(&memory_id)
+        .map_err(validation_error_to_string)?;
+
+    // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id, memory_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+    let sanitized_memory_id = &validation_result.sanitized_inputs[1];
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let memory_id = sanitized_memory_id.clone();
+    let Some(memory) = on_blocking_pool(&manager, move |m| m.get_memory(&memory_id)).await
+        .map_err(|e| format!("Failed to get memory: {}", e))? else {
+        return Ok(false);
+    };
+    let memory_id = memory.id.clone();
+    let deleted = on_blocking_pool(&manager, move |m| m.delete_memory(&memory_id)).await
+        .map_err(|e| format!("Failed to delete memory: {}", e))?;
+
+    if let Some(ref service) = *state.neural_embedding_service.lock().await {
+        service.evict_memories(std::slice::from_ref(&memory)).await;
+    }
+    let mut neural_graph = state.neural_graph.lock().await;
+    if let Some(ref mut graph) = *neural_graph {
+        let ids = std::collections::HashSet::from([memory.id.clone()]);
+        graph.remove_agent_nodes(&memory.agent_id, Some(&ids)).await;
+    }
+
+    Ok(deleted)
+}
+
+async fn set_memory_pinned(
+    agent_id: String,
+    memory_id: String,
+    pinned: bool,
+    state: State<'_, MemoryState>,
+) -> Result<bool, String> {
+    // Phase 1: Input Validation (Highest Priority)
+    MemoryValidator::validate_agent_id(&agent_id)
+        .map_err(validation_error_to_string)?;
+    MemoryValidator::validate_memory_id(&memory_id)
+        .map_err(validation_error_to_string)?;
+
+    // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id, memory_id],
+        &[]
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+    let sanitized_memory_id = &validation_result.sanitized_inputs[1];
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let memory_id = sanitized_memory_id.clone();
+    on_blocking_pool(&manager, move |m| m.set_memory_pinned(&memory_id, pinned)).await
+        .map_err(|e| format!("Failed to update memory pin: {}", e))
+}
+
+/// Pin a memory so it is exempt from decay, consolidation and eviction and ranked first
+/// among its type in retrieval. Returns false when the memory doesn't exist.
+#[tauri::command]
+pub async fn pin_memory(
+    agent_id: String,
+    memory_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<bool, String> {
+    info!("Pinning memory: {} for agent: {}", memory_id, agent_id);
+    set_memory_pinned(agent_id, memory_id, true, state).await
+}
+
+/// Unpin a memory. Returns false when the memory doesn't exist.
+#[tauri::command]
+pub async fn unpin_memory(
+    agent_id: String,
+    memory_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<bool, String> {
+    info!("Unpinning memory: {} for agent: {}", memory_id, agent_id);
+    set_memory_pinned(agent_id, memory_id, false, state).await
 }
 
 #[tauri::command]
@@ -425,11 +957,104 @@ pub async fn save_shared_knowledge(
         knowledge.tags = sanitized_tags;
     }
 
-    let knowledge_id = knowledge.id.clone();
-    manager.save_shared_knowledge(&knowledge)
+    contribute_knowledge(&state, &manager, knowledge).await
+}
+
+/// Embed and contribute shared knowledge, returning the id of the entry it was stored as
+async fn contribute_knowledge(
+    state: &MemoryState,
+    manager: &SimpleMemoryManager,
+    mut knowledge: SharedKnowledge,
+) -> Result<String, String> {
+    // Embeddings let other agents' wording of the same knowledge merge into one entry
+    let neural_embedding_service_lock = state.get_neural_embedding_service().await?;
+    let mut metric = SimilarityMetric::default();
+    if let Some(ref service) = *neural_embedding_service_lock.lock().await {
+        metric = service.similarity_metric();
+        match service.embed_text(&format!("{}\n\n{}", knowledge.title, knowledge.content), None).await {
+            Ok(embedding) => knowledge.embedding = Some(embedding),
+            Err(e) => warn!("Failed to generate embedding for shared knowledge {}: {}", knowledge.id, e),
+        }
+    }
+
+    let classifier = {
+        let graph_lock = state.get_neural_graph().await?;
+        let graph = graph_lock.lock().await;
+        graph.as_ref().ok_or("Neural knowledge graph not initialized")?.contradiction_classifier()
+    };
+
+    let sources = knowledge.source_agents.join(", ");
+    let outcome = on_blocking_pool(manager, move |m| m.contribute_shared_knowledge(knowledge, metric, &classifier)).await
         .map_err(|e| format!("Failed to save shared knowledge: {}", e))?;
+    match outcome {
+        ContributionOutcome::Conflict { ref knowledge_id, .. } => {
+            warn!("Shared knowledge from {} conflicts with {}; recorded for review", sources, knowledge_id);
+        }
+        ContributionOutcome::Contradiction { ref contradicts, kind, .. } => {
+            warn!("Shared knowledge from {} contradicts {} ({:?}); both flagged for review", sources, contradicts, kind);
+        }
+        _ => {}
+    }
 
-    Ok(knowledge_id)
+    Ok(outcome.knowledge_id().to_string())
+}
+
+/// Share knowledge contributed by several agents at once. It is stored as a `Fact`; use
+/// `save_shared_knowledge` to pick another type.
+#[tauri::command]
+pub async fn create_shared_knowledge(
+    title: String,
+    content: String,
+    source_agents: Vec<String>,
+    tags: Vec<String>,
+    state: State<'_, MemoryState>,
+) -> Result<String, String> {
+    info!("Creating shared knowledge from agents: {:?}", source_agents);
+
+    // Phase 1: Input Validation (Highest Priority)
+    if source_agents.is_empty() {
+        return Err("At least one source agent is required".to_string());
+    }
+    for agent_id in &source_agents {
+        MemoryValidator::validate_agent_id(agent_id)
+            .map_err(validation_error_to_string)?;
+    }
+    MemoryValidator::validate_title(&title)
+        .map_err(validation_error_to_string)?;
+    MemoryValidator::validate_content(&content)
+        .map_err(validation_error_to_string)?;
+    MemoryValidator::validate_tags(&tags)
+        .map_err(validation_error_to_string)?;
+
+    // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
+    let security_middleware = state.get_security_middleware();
+    let mut inputs = vec![title, content];
+    inputs.extend(source_agents);
+    let validation_result = security_middleware.validate_request(
+        "knowledge_operations",
+        &inputs,
+        &[]
+    ).await?;
+
+    let sanitized_title = &validation_result.sanitized_inputs[0];
+    let sanitized_content = &validation_result.sanitized_inputs[1];
+    let mut sanitized_agents: Vec<String> = Vec::new();
+    for agent_id in &validation_result.sanitized_inputs[2..] {
+        if !sanitized_agents.contains(agent_id) {
+            sanitized_agents.push(agent_id.clone());
+        }
+    }
+
+    let manager = state.get_or_create_manager(sanitized_agents[0].clone())?;
+    let mut knowledge = SharedKnowledge::new(KnowledgeType::Fact, sanitized_title.clone(), sanitized_content.clone(), sanitized_agents[0].clone());
+    knowledge.source_agents = sanitized_agents;
+    let mut sanitized_tags = Vec::with_capacity(tags.len());
+    for tag in &tags {
+        sanitized_tags.push(security_middleware.sanitize_input(tag).await);
+    }
+    knowledge.tags = sanitized_tags;
+
+    contribute_knowledge(&state, &manager, knowledge).await
 }
 
 #[tauri::command]
@@ -496,9 +1121,11 @@ pub async fn add_knowledge_graph_node(
             .collect();
         node.properties = sanitized_props;
     }
+    // Record ownership so the agent's graph entries can be found again for deletion
+    node.properties.entry("agent_id".to_string()).or_insert_with(|| sanitized_agent_id.clone());
 
     let node_id = node.id.clone();
-    manager.add_knowledge_node(&node)
+    on_blocking_pool(&manager, move |m| m.add_knowledge_node(&node)).await
         .map_err(|e| format!("Failed to add knowledge node: {}", e))?;
 
     Ok(node_id)
@@ -586,7 +1213,7 @@ pub async fn add_knowledge_graph_edge(
     }
 
     let edge_id = edge.id.clone();
-    manager.add_knowledge_edge(&edge)
+    on_blocking_pool(&manager, move |m| m.add_knowledge_edge(&edge)).await
         .map_err(|e| format!("Failed to add knowledge edge: {}", e))?;
 
     Ok(edge_id)
@@ -640,22 +1267,39 @@ pub async fn backup_agent_memories(
         format!("agent_{}_backup_{}.db", sanitized_agent_id, chrono::Utc::now().format("%Y%m%d_%H%M%S"))
     });
     
-    let backup_dir = dirs::home_dir()
-        .ok_or_else(|| "Could not find home directory".to_string())?
-        .join(".agent-memory")
-        .join("backups");
-    
-    std::fs::create_dir_all(&backup_dir)
-        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    let backup_dir = backup_directory()?;
     
     let backup_path = backup_dir.join(&backup_filename);
+    let db_size = std::fs::metadata(manager.get_agent_db_path()).map(|m| m.len()).unwrap_or(0);
+    ensure_disk_space(&backup_dir, db_size, "memory backup")
+        .map_err(|e| e.to_string())?;
     
-    manager.backup_agent_memory(&backup_path)
+    let target = backup_path.clone();
+    on_blocking_pool(&manager, move |m| m.backup_agent_memory(&target)).await
         .map_err(|e| format!("Failed to backup memories: {}", e))?;
 
     Ok(backup_path.to_string_lossy().to_string())
 }
 
+/// Start `backup_agent_memories` as an operation; its result is the backup path
+#[tauri::command]
+pub async fn start_memory_backup(
+    agent_id: String,
+    backup_name: Option<String>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    MemoryValidator::validate_agent_id(&agent_id).map_err(validation_error_to_string)?;
+    Ok(start_operation(&app_handle, "memory_backup", {
+        let app_handle = app_handle.clone();
+        move |operation| async move {
+            operation.check_cancelled()?;
+            operation.report(0.0, format!("Backing up memories of {}", agent_id));
+            let path = backup_agent_memories(agent_id, backup_name, app_handle.state::<MemoryState>()).await?;
+            Ok(serde_json::Value::String(path))
+        }
+    }))
+}
+
 #[tauri::command]
 pub async fn search_shared_knowledge(
     query: String,
@@ -684,13 +1328,41 @@ pub async fn search_shared_knowledge(
     };
     
     let sanitized_query = &validation_result.sanitized_inputs[0];
-    let final_limit = limit.unwrap_or(50).min(100); // Cap at 100
+    let final_limit = limit.unwrap_or(50).clamp(1, 100); // Cap at 100
     let final_offset = offset.unwrap_or(0).max(0);
-    
-    // For now, return empty results until we implement a shared knowledge store
-    // In a full implementation, this would search across a global knowledge database
-    info!("Shared knowledge search not yet implemented - returning empty results");
-    Ok(vec![])
+
+    if let Some(ref t) = knowledge_type {
+        if parse_knowledge_type(t).is_none() {
+            return Err("Invalid knowledge type".to_string());
+        }
+    }
+
+    // Rank by meaning as well as wording when the embedding service is available
+    let neural_embedding_service_lock = state.get_neural_embedding_service().await?;
+    let (query_embedding, metric) = match *neural_embedding_service_lock.lock().await {
+        Some(ref service) => (
+            service.embed_text(sanitized_query, None).await
+                .map_err(|e| warn!("Failed to embed shared knowledge query: {}", e))
+                .ok(),
+            service.similarity_metric(),
+        ),
+        None => (None, SimilarityMetric::default()),
+    };
+
+    let conn = open_shared_knowledge_store()
+        .map_err(|e| format!("Failed to open shared knowledge store: {}", e))?;
+    let results = search_knowledge(
+        &conn,
+        sanitized_query,
+        knowledge_type.as_deref(),
+        query_embedding.as_deref(),
+        metric,
+        final_limit as usize,
+        final_offset as usize,
+    ).map_err(|e| format!("Failed to search shared knowledge: {}", e))?;
+
+    info!("Shared knowledge search returned {} result(s)", results.len());
+    Ok(results)
 }
 
 #[tauri::command]
@@ -723,81 +1395,36 @@ pub async fn get_knowledge_graph(
     let final_limit = limit.unwrap_or(100).min(500); // Cap at 500 nodes
     
     let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
-    
-    // Get all knowledge nodes and edges for the agent
-    let memories = manager.search_memories(&MemoryQuery {
-        agent_id: Some(sanitized_agent_id.clone()),
-        memory_types: None,
-        content_search: None,
-        tags: None,
-        embedding: None,
-        similarity_threshold: None,
-        limit: Some(final_limit as usize),
-        offset: Some(0),
-        time_range: None,
-    }).map_err(|e| format!("Failed to get memories: {}", e))?;
-    
-    // For now, create a basic graph view from memories
-    // In a full implementation, this would query dedicated graph storage
-    let mut nodes = Vec::new();
-    let mut edges = Vec::new();
-    
-    for memory_result in memories.iter().take(final_limit as usize) {
-        let memory = &memory_result.memory;
-        
-        // Create a node for each memory
-        let node = GraphNode {
-            id: memory.id.clone(),
-            node_type: memory.memory_type.to_string(),
-            name: memory.content.chars().take(50).collect::<String>(),
-            properties: Some(memory.metadata.clone()),
-            position: None, // Will be calculated by frontend
-        };
-        nodes.push(node);
-        
-        // Create edges based on tags (simple relationship inference)
-        for tag in &memory.tags {
-            // Find other memories with the same tag to create edges
-            for other_result in memories.iter() {
-                let other_memory = &other_result.memory;
-                if other_memory.id != memory.id {
-                    if other_memory.tags.contains(tag) && edges.len() < 200 {
-                        let edge = GraphEdge {
-                            id: format!("{}_{}", memory.id, other_memory.id),
-                            from_node: memory.id.clone(),
-                            to_node: other_memory.id.clone(),
-                            relationship_type: format!("shared_tag_{}", tag),
-                            weight: Some(0.5),
-                            properties: Some(HashMap::from([
-                                ("tag".to_string(), tag.clone()),
-                                ("type".to_string(), "semantic".to_string()),
-                            ])),
-                        };
-                        edges.push(edge);
-                    }
-                }
-            }
-        }
+    let (nodes, edges) = on_blocking_pool(&manager, |m| m.load_knowledge_graph()).await
+        .map_err(|e| format!("Failed to load knowledge graph: {}", e))?;
+
+    // Centre the overview on the node with the strongest connections
+    let mut strength: HashMap<&str, f32> = HashMap::new();
+    for edge in &edges {
+        *strength.entry(edge.from_node.as_str()).or_insert(0.0) += edge.weight;
+        *strength.entry(edge.to_node.as_str()).or_insert(0.0) += edge.weight;
     }
-    
-    // Remove duplicate edges
-    edges.sort_by(|a, b| a.id.cmp(&b.id));
-    edges.dedup_by(|a, b| a.id == b.id);
-    
-    // Get counts before moving
-    let node_count = nodes.len();
-    let edge_count = edges.len();
-    
-    Ok(KnowledgeGraphView {
-        nodes,
-        edges,
-        metadata: HashMap::from([
-            ("agent_id".to_string(), sanitized_agent_id.clone()),
-            ("node_count".to_string(), node_count.to_string()),
-            ("edge_count".to_string(), edge_count.to_string()),
-            ("depth".to_string(), final_depth.to_string()),
-        ]),
-    })
+    let seed = nodes.iter()
+        .max_by(|a, b| {
+            let weight = |n: &KnowledgeNode| strength.get(n.id.as_str()).copied().unwrap_or(0.0);
+            weight(a).total_cmp(&weight(b)).then_with(|| b.id.cmp(&a.id))
+        })
+        .map(|n| n.id.clone());
+
+    let Some(seed) = seed else {
+        return Ok(KnowledgeGraphView {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            metadata: HashMap::from([
+                ("agent_id".to_string(), sanitized_agent_id.clone()),
+                ("node_count".to_string(), "0".to_string()),
+                ("edge_count".to_string(), "0".to_string()),
+                ("depth".to_string(), final_depth.to_string()),
+            ]),
+        });
+    };
+
+    Ok(subgraph_view(sanitized_agent_id, &nodes, &edges, &seed, final_depth, final_limit.max(1) as usize))
 }
 
 /// Initialize neural embedding service
@@ -810,8 +1437,17 @@ pub async fn init_neural_embedding_service(
     
     let mut service_lock = state.neural_embedding_service.lock().await;
     if service_lock.is_none() {
+        let configured = config.is_some();
         let service = NeuralEmbeddingService::new(config).await
             .map_err(|e| format!("Failed to create neural embedding service: {}", e))?;
+        if let Err(e) = service.save_models() {
+            warn!("Failed to save neural embedding networks: {}", e);
+        }
+        // Keep the chosen metric for services created without a config after a restart
+        if configured {
+            service.save_settings()
+                .map_err(|e| format!("Failed to save embedding settings: {}", e))?;
+        }
         
         *service_lock = Some(service);
         info!("Neural embedding service initialized successfully");
@@ -820,6 +1456,108 @@ pub async fn init_neural_embedding_service(
     Ok(())
 }
 
+/// Choose where an agent's memory embeddings come from. The choice is saved; when it moves
+/// the agent to another embedding space, its memories are re-embedded in the background and
+/// the id of that operation is returned.
+#[tauri::command]
+pub async fn set_agent_embedding_provider(
+    agent_id: String,
+    provider: super::embedding_providers::EmbeddingProviderConfig,
+    app_handle: AppHandle,
+    state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<Option<String>, String> {
+    use super::embedding_providers::EmbeddingProviderConfig;
+
+    info!("Setting embedding provider for agent {} to {}", agent_id, provider.cache_id());
+
+    MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+    let model_path = match &provider {
+        EmbeddingProviderConfig::LocalGguf { model_path } => vec![model_path.to_string_lossy().to_string()],
+        _ => Vec::new(),
+    };
+    let validation_result = state.get_security_middleware().validate_request(
+        "memory_operations",
+        &[agent_id],
+        &model_path
+    ).await?;
+    let sanitized_agent_id = validation_result.sanitized_inputs[0].clone();
+
+    let provider = match provider {
+        EmbeddingProviderConfig::LocalGguf { model_path } => EmbeddingProviderConfig::LocalGguf {
+            model_path: enforce_path_policy(&ai_state.storage, None, &model_path.to_string_lossy(), PathAccess::Read)?,
+        },
+        provider => provider,
+    };
+
+    let service_lock = state.get_neural_embedding_service().await?;
+    let space_changed = {
+        let mut service = service_lock.lock().await;
+        let service = service.as_mut()
+            .ok_or("Neural embedding service not initialized")?;
+        service.set_agent_provider(&sanitized_agent_id, provider).await
+            .map_err(|e| format!("Failed to set embedding provider: {}", e))?
+    };
+    if !space_changed {
+        return Ok(None);
+    }
+
+    Ok(Some(start_operation(&app_handle, "memory_reembedding", {
+        let app_handle = app_handle.clone();
+        move |operation| async move {
+            let state = app_handle.state::<MemoryState>();
+            let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+            let query = MemoryQuery {
+                agent_id: Some(sanitized_agent_id.clone()),
+                memory_types: None,
+                content_search: None,
+                tags: None,
+                embedding: None,
+                similarity_threshold: None,
+                similarity_metric: SimilarityMetric::default(),
+                limit: None,
+                offset: None,
+                time_range: None,
+                tiers: None,
+                fusion: None,
+            };
+            let memories: Vec<AgentMemory> = on_blocking_pool(&manager, move |m| m.search_memories(&query)).await
+                .map_err(|e| format!("Failed to load memories: {}", e))?
+                .into_iter()
+                .map(|result| result.memory)
+                .collect();
+
+            let total = memories.len();
+            let mut reembedded = 0;
+            for (index, mut memory) in memories.into_iter().enumerate() {
+                operation.check_cancelled()?;
+                operation.report(index as f32 / total.max(1) as f32, format!("Re-embedding memories of {}", sanitized_agent_id));
+                let embedding = {
+                    let service = service_lock.lock().await;
+                    let service = service.as_ref().ok_or("Neural embedding service not initialized")?;
+                    service.embed_memory(&memory).await
+                };
+                // A stale embedding would be compared against vectors from the new space
+                memory.embedding = match embedding {
+                    Ok(embedding) => {
+                        reembedded += 1;
+                        Some(embedding)
+                    }
+                    Err(e) => {
+                        warn!("Failed to re-embed memory {}: {}", memory.id, e);
+                        None
+                    }
+                };
+                on_blocking_pool(&manager, move |m| m.update_memory(&memory)).await
+                    .map_err(|e| format!("Failed to update memory: {}", e))?;
+            }
+
+            info!("Re-embedded {} of {} memories of agent {}", reembedded, total, sanitized_agent_id);
+            Ok(serde_json::json!({ "total": total, "reembedded": reembedded }))
+        }
+    })))
+}
+
 /// Generate neural embedding for text
 #[tauri::command]
 pub async fn generate_neural_embedding(
@@ -970,17 +1708,22 @@ pub async fn find_similar_memories(
     
     // Get agent memories
     let manager = state.get_or_create_manager(agent_id.clone())?;
-    let memories = manager.search_memories(&MemoryQuery {
+    let query = MemoryQuery {
         agent_id: Some(agent_id.clone()),
         memory_types: memory_type_enum.as_ref().map(|mt| vec![mt.clone()]),
         content_search: None,
         tags: None,
         embedding: None,
         similarity_threshold: None,
+        similarity_metric: SimilarityMetric::default(),
         limit: Some(1000), // Get more memories for better search
         offset: Some(0),
         time_range: None,
-    }).map_err(|e| format!("Failed to get memories: {}", e))?;
+        tiers: None,
+        fusion: None,
+    };
+    let memories = on_blocking_pool(&manager, move |m| m.search_memories(&query)).await
+        .map_err(|e| format!("Failed to get memories: {}", e))?;
     
     let candidate_memories: Vec<AgentMemory> = memories
         .into_iter()
@@ -1025,17 +1768,22 @@ pub async fn train_neural_networks(
     
     // Get agent memories for training
     let manager = state.get_or_create_manager(agent_id.clone())?;
-    let memories = manager.search_memories(&MemoryQuery {
+    let query = MemoryQuery {
         agent_id: Some(agent_id.clone()),
         memory_types: None,
         content_search: None,
         tags: None,
         embedding: None,
         similarity_threshold: None,
+        similarity_metric: SimilarityMetric::default(),
         limit: Some(10000), // Get all memories for training
         offset: Some(0),
         time_range: None,
-    }).map_err(|e| format!("Failed to get memories for training: {}", e))?;
+        tiers: None,
+        fusion: None,
+    };
+    let memories = on_blocking_pool(&manager, move |m| m.search_memories(&query)).await
+        .map_err(|e| format!("Failed to get memories for training: {}", e))?;
     
     let training_memories: Vec<AgentMemory> = memories
         .into_iter()
@@ -1044,11 +1792,40 @@ pub async fn train_neural_networks(
     
     service.train_on_memories(&training_memories).await
         .map_err(|e| format!("Failed to train neural networks: {}", e))?;
+    service.save_models()
+        .map_err(|e| format!("Failed to save trained neural networks: {}", e))?;
     
     info!("Neural networks trained successfully for agent: {}", agent_id);
     Ok(())
 }
 
+/// Discard trained weights for the embedding, sequence and graph networks and continue with
+/// fresh ones, without restarting. Stored embeddings should be regenerated afterwards.
+#[tauri::command]
+pub async fn reset_neural_models(
+    state: State<'_, MemoryState>,
+) -> Result<(), String> {
+    info!("Resetting neural network models");
+
+    let service = state.get_neural_embedding_service().await?;
+    let mut service_lock = service.lock().await;
+    service_lock.as_mut()
+        .ok_or("Neural embedding service not initialized")?
+        .reset_models().await
+        .map_err(|e| format!("Failed to reset neural embedding networks: {}", e))?;
+    drop(service_lock);
+
+    let graph = state.get_neural_graph().await?;
+    let mut graph_lock = graph.lock().await;
+    graph_lock.as_mut()
+        .ok_or("Neural knowledge graph not initialized")?
+        .reset_models().await
+        .map_err(|e| format!("Failed to reset knowledge graph networks: {}", e))?;
+
+    info!("Neural network models reset");
+    Ok(())
+}
+
 /// Get neural embedding service statistics
 #[tauri::command]
 pub async fn get_neural_embedding_stats(
@@ -1062,19 +1839,134 @@ pub async fn get_neural_embedding_stats(
     Ok(stats)
 }
 
-/// Clear neural embedding cache
+/// Clear the neural embedding cache and its hit/miss statistics; returns how many
+/// embeddings were dropped
 #[tauri::command]
-pub async fn clear_neural_embedding_cache(
+pub async fn clear_embedding_cache(
     state: State<'_, MemoryState>,
-) -> Result<(), String> {
+) -> Result<usize, String> {
     info!("Clearing neural embedding cache");
     
     let service_lock = state.neural_embedding_service.lock().await;
     let service = service_lock.as_ref()
         .ok_or("Neural embedding service not initialized")?;
     
-    // Since clear_cache doesn't exist, we'll just log that it's not implemented
-    info!("Neural embedding cache clear not implemented - cache is managed automatically");
+    Ok(service.reset_cache().await)
+}
+
+
+/// Suggestion ids are `suggestion_<from node>_<to node>`, built from memory ids
+fn validate_suggestion_id(suggestion_id: &str) -> Result<(), String> {
+    if !suggestion_id.starts_with("suggestion_") || suggestion_id.len() > 512 {
+        return Err("Invalid edge suggestion id".to_string());
+    }
     Ok(())
 }
 
+/// List relationship suggestions awaiting review
+#[tauri::command]
+pub async fn list_edge_suggestions(
+    agent_id: Option<String>,
+    state: State<'_, MemoryState>,
+) -> Result<Vec<EdgeSuggestion>, String> {
+    // Phase 1: Input Validation
+    if let Some(ref id) = agent_id {
+        MemoryValidator::validate_agent_id(id)
+            .map_err(validation_error_to_string)?;
+    }
+
+    // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
+    let inputs: Vec<String> = agent_id.iter().cloned().collect();
+    state.get_security_middleware().validate_request("memory_operations", &inputs, &[]).await?;
+
+    // Phase 3: Business Logic
+    let graph_lock = state.get_neural_graph().await?;
+    let graph = graph_lock.lock().await;
+    let graph = graph.as_ref().ok_or("Neural knowledge graph not initialized")?;
+
+    Ok(graph.list_edge_suggestions(agent_id.as_deref()).await)
+}
+
+/// Accept a suggested relationship, creating the edge
+#[tauri::command]
+pub async fn accept_edge_suggestion(
+    suggestion_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<String, String> {
+    info!("Accepting edge suggestion: {}", suggestion_id);
+
+    // Phase 1: Input Validation
+    validate_suggestion_id(&suggestion_id)?;
+
+    // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
+    state.get_security_middleware().validate_request("memory_operations", std::slice::from_ref(&suggestion_id), &[]).await?;
+
+    // Phase 3: Business Logic
+    let graph_lock = state.get_neural_graph().await?;
+    let mut graph = graph_lock.lock().await;
+    let graph = graph.as_mut().ok_or("Neural knowledge graph not initialized")?;
+
+    graph.accept_edge_suggestion(&suggestion_id).await
+        .map_err(|e| format!("Failed to accept edge suggestion: {}", e))
+}
+
+/// Reject a suggested relationship
+#[tauri::command]
+pub async fn reject_edge_suggestion(
+    suggestion_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<(), String> {
+    info!("Rejecting edge suggestion: {}", suggestion_id);
+
+    // Phase 1: Input Validation
+    validate_suggestion_id(&suggestion_id)?;
+
+    // Phase 2: Security Middleware (Rate limiting, sanitization, etc.)
+    state.get_security_middleware().validate_request("memory_operations", std::slice::from_ref(&suggestion_id), &[]).await?;
+
+    // Phase 3: Business Logic
+    let graph_lock = state.get_neural_graph().await?;
+    let mut graph = graph_lock.lock().await;
+    let graph = graph.as_mut().ok_or("Neural knowledge graph not initialized")?;
+
+    graph.reject_edge_suggestion(&suggestion_id).await
+        .map_err(|e| format!("Failed to reject edge suggestion: {}", e))
+}
+
+/// Confirm a graph edge's relationship type, training the graph's relationship scoring on it
+#[tauri::command]
+pub async fn confirm_relationship(
+    edge_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<RelationshipFeedbackStats, String> {
+    info!("Confirming relationship: {}", edge_id);
+    if edge_id.trim().is_empty() {
+        return Err("Edge id cannot be empty".to_string());
+    }
+
+    let graph_lock = state.get_neural_graph().await?;
+    let mut graph = graph_lock.lock().await;
+    let graph = graph.as_mut().ok_or("Neural knowledge graph not initialized")?;
+
+    graph.record_relationship_feedback(&edge_id, true).await
+        .map_err(|e| format!("Failed to confirm relationship: {}", e))
+}
+
+/// Reject a graph edge, removing it and training the graph's relationship scoring against it
+#[tauri::command]
+pub async fn reject_relationship(
+    edge_id: String,
+    state: State<'_, MemoryState>,
+) -> Result<RelationshipFeedbackStats, String> {
+    info!("Rejecting relationship: {}", edge_id);
+    if edge_id.trim().is_empty() {
+        return Err("Edge id cannot be empty".to_string());
+    }
+
+    let graph_lock = state.get_neural_graph().await?;
+    let mut graph = graph_lock.lock().await;
+    let graph = graph.as_mut().ok_or("Neural knowledge graph not initialized")?;
+
+    graph.record_relationship_feedback(&edge_id, false).await
+        .map_err(|e| format!("Failed to reject relationship: {}", e))
+}
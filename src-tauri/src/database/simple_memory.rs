@@ -1,10 +1,75 @@
 use super::memory::*;
-use super::schema::{AGENT_MEMORY_SCHEMA, AGENT_MEMORY_VIEWS};
+use super::embedding_quantization::{decode_embedding, encode_embedding};
+use super::pool::PooledConnection;
+use super::migrations::{migrate, AGENT_MEMORY_MIGRATIONS};
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use dirs;
 use serde_json;
+use tracing::{info, warn};
+
+/// Insert a memory or overwrite it in place. Unlike `INSERT OR REPLACE` the row keeps its
+/// rowid, which the full-text index is keyed by.
+pub(super) const UPSERT_MEMORY: &str = r#"
+    INSERT INTO agent_memories
+    (id, agent_id, memory_type, content, metadata, embedding, relevance_score,
+     created_at, updated_at, access_count, tags, pinned, tier)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+    ON CONFLICT(id) DO UPDATE SET
+        agent_id = excluded.agent_id, memory_type = excluded.memory_type, content = excluded.content,
+        metadata = excluded.metadata, embedding = excluded.embedding, relevance_score = excluded.relevance_score,
+        created_at = excluded.created_at, updated_at = excluded.updated_at,
+        access_count = excluded.access_count, tags = excluded.tags, pinned = excluded.pinned, tier = excluded.tier
+"#;
+
+/// An agent's memory database in one workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentDatabase {
+    pub workspace: String,
+    pub agent_id: String,
+    pub path: PathBuf,
+}
+
+impl AgentDatabase {
+    /// The agent id, prefixed with the workspace outside the default one
+    pub fn label(&self) -> String {
+        if self.workspace == crate::ai::DEFAULT_WORKSPACE {
+            self.agent_id.clone()
+        } else {
+            format!("{}/{}", self.workspace, self.agent_id)
+        }
+    }
+}
+
+/// Agent databases under `memory_dir`: `agents/` for the default workspace and
+/// `workspaces/<id>/agents/` for the others, sorted by agent id
+pub(crate) fn agent_databases_in(memory_dir: &Path) -> Result<Vec<AgentDatabase>> {
+    let mut agent_dirs = vec![(crate::ai::DEFAULT_WORKSPACE.to_string(), memory_dir.join("agents"))];
+    let workspaces_dir = memory_dir.join("workspaces");
+    if workspaces_dir.is_dir() {
+        for entry in std::fs::read_dir(&workspaces_dir)? {
+            let path = entry?.path();
+            if let Some(workspace) = path.file_name().and_then(|name| name.to_str()) {
+                agent_dirs.push((workspace.to_string(), path.join("agents")));
+            }
+        }
+    }
+
+    let mut databases = Vec::new();
+    for (workspace, agents_dir) in agent_dirs.into_iter().filter(|(_, dir)| dir.is_dir()) {
+        for entry in std::fs::read_dir(&agents_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "db") {
+                if let Some(agent_id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    databases.push(AgentDatabase { workspace: workspace.clone(), agent_id: agent_id.to_string(), path: path.clone() });
+                }
+            }
+        }
+    }
+    databases.sort_by(|a, b| (&a.agent_id, &a.workspace).cmp(&(&b.agent_id, &b.workspace)));
+    Ok(databases)
+}
 
 // Simplified memory manager that doesn't store connections
 #[derive(Clone)]
@@ -16,10 +81,15 @@ pub struct SimpleMemoryManager {
 
 impl SimpleMemoryManager {
     pub fn new(agent_id: String) -> Result<Self> {
-        let memory_dir = Self::get_memory_directory()?;
-        
-        let agent_db_path = memory_dir.join("agents").join(format!("{}.db", agent_id));
-        let shared_db_path = memory_dir.join("shared").join("knowledge.db");
+        Self::for_workspace(agent_id, crate::ai::DEFAULT_WORKSPACE)
+    }
+
+    /// Manager for the agent's memories in `workspace`. The default workspace keeps the
+    /// original layout; others get their own agent databases under `workspaces/<id>`, while
+    /// the shared knowledge database spans every workspace.
+    pub fn for_workspace(agent_id: String, workspace: &str) -> Result<Self> {
+        let agent_db_path = Self::agents_directory(workspace)?.join(format!("{}.db", agent_id));
+        let shared_db_path = Self::default_shared_db_path()?;
 
         // Ensure directories exist
         if let Some(parent) = agent_db_path.parent() {
@@ -36,12 +106,57 @@ impl SimpleMemoryManager {
         })
     }
 
-    fn get_memory_directory() -> Result<PathBuf> {
+    /// Knowledge database shared by every agent
+    pub fn default_shared_db_path() -> Result<PathBuf> {
+        Ok(Self::get_memory_directory()?.join("shared").join("knowledge.db"))
+    }
+
+    /// Directory holding the agent databases of `workspace`
+    pub fn agents_directory(workspace: &str) -> Result<PathBuf> {
+        let memory_dir = Self::get_memory_directory()?;
+        if workspace == crate::ai::DEFAULT_WORKSPACE {
+            Ok(memory_dir.join("agents"))
+        } else {
+            Ok(memory_dir.join("workspaces").join(workspace).join("agents"))
+        }
+    }
+
+    /// Agents that have a memory database in any workspace
+    pub fn list_agent_ids() -> Result<Vec<String>> {
+        let mut agent_ids: Vec<String> = Self::list_agent_databases()?
+            .into_iter()
+            .map(|database| database.agent_id)
+            .collect();
+        agent_ids.dedup();
+        Ok(agent_ids)
+    }
+
+    /// Every agent memory database, across all workspaces
+    pub fn list_agent_databases() -> Result<Vec<AgentDatabase>> {
+        agent_databases_in(&Self::get_memory_directory()?)
+    }
+
+    /// Saved neural network weights, shared by every agent
+    pub fn default_model_directory() -> Result<PathBuf> {
+        Ok(Self::get_memory_directory()?.join("models"))
+    }
+
+    pub(crate) fn get_memory_directory() -> Result<PathBuf> {
         let home_dir = dirs::home_dir()
             .ok_or_else(|| anyhow!("Could not find home directory"))?;
         Ok(home_dir.join(".agent-memory"))
     }
 
+    /// Pooled connection to this agent's database
+    pub fn agent_connection(&self) -> Result<PooledConnection> {
+        super::pool::connection(&self.agent_db_path)
+    }
+
+    /// Pooled connection to the knowledge database shared by every agent
+    pub fn shared_connection(&self) -> Result<PooledConnection> {
+        super::pool::connection(&self.shared_db_path)
+    }
+
     pub fn initialize(&self) -> Result<()> {
         self.initialize_agent_db()?;
         self.initialize_shared_db()?;
@@ -49,53 +164,32 @@ impl SimpleMemoryManager {
     }
 
     fn initialize_agent_db(&self) -> Result<()> {
-        use rusqlite::Connection;
-        
-        let conn = Connection::open(&self.agent_db_path)?;
-        conn.execute_batch(AGENT_MEMORY_SCHEMA)?;
-        conn.execute_batch(AGENT_MEMORY_VIEWS)?;
-        
-        // Enable foreign keys and optimizations
-        conn.execute("PRAGMA foreign_keys = ON;", [])?;
-        conn.execute("PRAGMA journal_mode = WAL;", [])?;
-        conn.execute("PRAGMA synchronous = NORMAL;", [])?;
-        conn.execute("PRAGMA cache_size = -64000;", [])?;
+        let conn = self.agent_connection()?;
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS)?;
 
         Ok(())
     }
 
     fn initialize_shared_db(&self) -> Result<()> {
-        use rusqlite::Connection;
-        
-        let conn = Connection::open(&self.shared_db_path)?;
-        conn.execute_batch(AGENT_MEMORY_SCHEMA)?;
-        conn.execute_batch(AGENT_MEMORY_VIEWS)?;
-        
-        // Enable foreign keys and optimizations
-        conn.execute("PRAGMA foreign_keys = ON;", [])?;
-        conn.execute("PRAGMA journal_mode = WAL;", [])?;
-        conn.execute("PRAGMA synchronous = NORMAL;", [])?;
-        conn.execute("PRAGMA cache_size = -64000;", [])?;
+        let conn = self.shared_connection()?;
+        migrate(&conn, AGENT_MEMORY_MIGRATIONS)?;
+        super::shared_knowledge::repair_fts_index(&conn)?;
 
         Ok(())
     }
 
-    pub fn save_memory(&self, memory: &AgentMemory) -> Result<()> {
-        use rusqlite::{Connection, params};
+    /// Save the memory, then evict others if it put the agent over its quota; returns the
+    /// evicted memories
+    pub fn save_memory(&self, memory: &AgentMemory) -> Result<Vec<AgentMemory>> {
+        use rusqlite::params;
         
-        let conn = Connection::open(&self.agent_db_path)?;
+        let conn = self.agent_connection()?;
         
         let metadata_json = serde_json::to_string(&memory.metadata)?;
         let tags_json = serde_json::to_string(&memory.tags)?;
-        let embedding_blob = memory.embedding.as_ref().map(|e| bincode::serialize(e)).transpose()?;
+        let embedding_blob = memory.embedding.as_deref().map(encode_embedding);
 
-        conn.execute(
-            r#"
-            INSERT OR REPLACE INTO agent_memories 
-            (id, agent_id, memory_type, content, metadata, embedding, relevance_score, 
-             created_at, updated_at, access_count, tags)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
-            "#,
+        conn.prepare_cached(UPSERT_MEMORY)?.execute(
             params![
                 memory.id,
                 memory.agent_id,
@@ -107,34 +201,68 @@ impl SimpleMemoryManager {
                 memory.created_at.to_rfc3339(),
                 memory.updated_at.to_rfc3339(),
                 memory.access_count,
-                tags_json
+                tags_json,
+                memory.pinned,
+                format!("{:?}", memory.tier)
             ],
         )?;
 
-        self.log_memory_access(&memory.id, "Write", Some("Memory saved"))?;
-        Ok(())
+        self.log_memory_access(&conn, &memory.id, "Write", Some("Memory saved"))?;
+        drop(conn);
+        self.enforce_quota(Some(&memory.id))
+    }
+
+    /// Evict unpinned memories, never `keep_id`, until the agent is within its quota; returns
+    /// what was evicted
+    pub fn enforce_quota(&self, keep_id: Option<&str>) -> Result<Vec<AgentMemory>> {
+        use super::memory_quota::{eviction_candidates, excess_memories, load_quota, MAX_EVICTION_ROUNDS};
+
+        let conn = self.agent_connection()?;
+        let Some(quota) = load_quota(&conn, &self.agent_id)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut evicted = Vec::new();
+        for _ in 0..MAX_EVICTION_ROUNDS {
+            let excess = excess_memories(&conn, &self.agent_id, &quota)?;
+            if excess == 0 {
+                break;
+            }
+            let candidates = eviction_candidates(&conn, &self.agent_id, quota.eviction, keep_id, excess)?;
+            if candidates.is_empty() {
+                warn!("Agent {} is over its memory quota with nothing left to evict", self.agent_id);
+                break;
+            }
+            let ids: Vec<String> = candidates.iter().map(|memory| memory.id.clone()).collect();
+            self.delete_memories(&ids)?;
+            evicted.extend(candidates);
+        }
+        if !evicted.is_empty() {
+            info!("Evicted {} memories for agent {} ({:?})", evicted.len(), self.agent_id, quota.eviction);
+        }
+        Ok(evicted)
     }
 
     pub fn get_memory(&self, memory_id: &str) -> Result<Option<AgentMemory>> {
-        use rusqlite::{Connection, params};
+        use rusqlite::params;
         
-        let conn = Connection::open(&self.agent_db_path)?;
+        let conn = self.agent_connection()?;
 
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(
             r#"
             SELECT id, agent_id, memory_type, content, metadata, embedding, 
-                   relevance_score, created_at, updated_at, access_count, tags
+                   relevance_score, created_at, updated_at, access_count, tags, pinned, tier
             FROM agent_memories WHERE id = ?1
             "#,
         )?;
 
         let memory_row = stmt.query_row(params![memory_id], |row| {
-            self.row_to_memory(row)
+            row_to_memory(row)
         });
 
         match memory_row {
             Ok(memory) => {
-                self.log_memory_access(memory_id, "Read", Some("Memory retrieved"))?;
+                self.log_memory_access(&conn, memory_id, "Read", Some("Memory retrieved"))?;
                 Ok(Some(memory))
             }
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -142,16 +270,230 @@ impl SimpleMemoryManager {
         }
     }
 
+    /// Write a memory's content, tags, metadata, embedding and update time over the stored row;
+    /// false when it doesn't exist
+    pub fn update_memory(&self, memory: &AgentMemory) -> Result<bool> {
+        use rusqlite::params;
+
+        let conn = self.agent_connection()?;
+        let updated = conn.prepare_cached(
+            r#"
+            UPDATE agent_memories
+            SET content = ?3, metadata = ?4, embedding = ?5, tags = ?6, updated_at = ?7
+            WHERE id = ?1 AND agent_id = ?2
+            "#,
+        )?.execute(
+            params![
+                memory.id,
+                memory.agent_id,
+                memory.content,
+                serde_json::to_string(&memory.metadata)?,
+                memory.embedding.as_deref().map(encode_embedding),
+                serde_json::to_string(&memory.tags)?,
+                memory.updated_at.to_rfc3339(),
+            ],
+        )?;
+
+        if updated > 0 {
+            self.log_memory_access(&conn, &memory.id, "Update", Some("Memory updated"))?;
+        }
+        Ok(updated > 0)
+    }
+
+    /// This agent's newest memories of one type
+    pub fn recent_memories(&self, memory_type: &MemoryType, limit: usize) -> Result<Vec<AgentMemory>> {
+        use rusqlite::params;
+
+        let conn = self.agent_connection()?;
+        let mut stmt = conn.prepare_cached(
+            r#"
+            SELECT id, agent_id, memory_type, content, metadata, embedding,
+                   relevance_score, created_at, updated_at, access_count, tags, pinned, tier
+            FROM agent_memories WHERE agent_id = ?1 AND memory_type = ?2
+            ORDER BY created_at DESC LIMIT ?3
+            "#,
+        )?;
+        let rows = stmt.query_map(
+            params![&self.agent_id, format!("{:?}", memory_type), limit as i64],
+            row_to_memory,
+        )?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Write the tags, metadata and access count of a memory a duplicate was merged into
+    pub fn save_merged_duplicate(&self, memory: &AgentMemory, context: &str) -> Result<()> {
+        use rusqlite::params;
+
+        let conn = self.agent_connection()?;
+        conn.execute(
+            "UPDATE agent_memories SET tags = ?2, metadata = ?3, access_count = ?4 WHERE id = ?1",
+            params![
+                memory.id,
+                serde_json::to_string(&memory.tags)?,
+                serde_json::to_string(&memory.metadata)?,
+                memory.access_count,
+            ],
+        )?;
+        self.log_memory_access(&conn, &memory.id, "Update", Some(context))?;
+        Ok(())
+    }
+
+    /// Delete a memory with its access log, anomaly flags and consolidation links; false when
+    /// it doesn't exist
+    pub fn delete_memory(&self, memory_id: &str) -> Result<bool> {
+        Ok(self.delete_memories(&[memory_id.to_string()])? > 0)
+    }
+
+    /// Delete memories as `delete_memory` does, in one transaction; returns how many existed
+    pub fn delete_memories(&self, memory_ids: &[String]) -> Result<usize> {
+        use rusqlite::params;
+
+        let mut conn = self.agent_connection()?;
+        let tx = conn.transaction()?;
+        let mut deleted = 0;
+        for memory_id in memory_ids {
+            tx.execute("DELETE FROM memory_access_log WHERE memory_id = ?1", params![memory_id])?;
+            tx.execute("DELETE FROM memory_anomalies WHERE memory_id = ?1", params![memory_id])?;
+            tx.execute(
+                "DELETE FROM memory_consolidations WHERE semantic_id = ?1 OR episodic_id = ?1",
+                params![memory_id],
+            )?;
+            deleted += tx.execute(
+                "DELETE FROM agent_memories WHERE id = ?1 AND agent_id = ?2",
+                params![memory_id, &self.agent_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Save a semantic memory and link it to the episodic memories it was consolidated from
+    pub fn save_consolidation(&self, semantic: &AgentMemory, episodic_ids: &[String]) -> Result<()> {
+        use rusqlite::params;
+
+        let mut conn = self.agent_connection()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            UPSERT_MEMORY,
+            params![
+                semantic.id,
+                semantic.agent_id,
+                format!("{:?}", semantic.memory_type),
+                semantic.content,
+                serde_json::to_string(&semantic.metadata)?,
+                semantic.embedding.as_deref().map(encode_embedding),
+                semantic.relevance_score,
+                semantic.created_at.to_rfc3339(),
+                semantic.updated_at.to_rfc3339(),
+                semantic.access_count,
+                serde_json::to_string(&semantic.tags)?,
+                semantic.pinned,
+                format!("{:?}", semantic.tier)
+            ],
+        )?;
+        for episodic_id in episodic_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO memory_consolidations (semantic_id, episodic_id) VALUES (?1, ?2)",
+                params![semantic.id, episodic_id],
+            )?;
+        }
+        self.log_memory_access(&tx, &semantic.id, "Write", Some("Memory consolidated"))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Unpinned episodic memories created before `created_before` that no semantic memory was
+    /// consolidated from yet, oldest first
+    pub fn unconsolidated_episodes(
+        &self,
+        memory_type: Option<&MemoryType>,
+        created_before: chrono::DateTime<chrono::Utc>,
+        limit: usize,
+    ) -> Result<Vec<AgentMemory>> {
+        use rusqlite::params;
+
+        let conn = self.agent_connection()?;
+        let mut stmt = conn.prepare_cached(
+            r#"
+            SELECT id, agent_id, memory_type, content, metadata, embedding,
+                   relevance_score, created_at, updated_at, access_count, tags, pinned, tier
+            FROM agent_memories am
+            WHERE agent_id = ?1 AND tier = 'Episodic' AND pinned = 0 AND created_at < ?2
+              AND (?3 IS NULL OR memory_type = ?3)
+              AND NOT EXISTS (SELECT 1 FROM memory_consolidations mc WHERE mc.episodic_id = am.id)
+            ORDER BY created_at LIMIT ?4
+            "#,
+        )?;
+        let rows = stmt.query_map(
+            params![
+                &self.agent_id,
+                created_before.to_rfc3339(),
+                memory_type.map(|t| format!("{:?}", t)),
+                limit as i64
+            ],
+            row_to_memory,
+        )?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Unpinned memories of `tier` created before `cutoff`; with `consolidated_only`, just those
+    /// a semantic memory was consolidated from
+    pub fn expired_memories(
+        &self,
+        tier: MemoryTier,
+        cutoff: chrono::DateTime<chrono::Utc>,
+        consolidated_only: bool,
+    ) -> Result<Vec<AgentMemory>> {
+        use rusqlite::params;
+
+        let conn = self.agent_connection()?;
+        let mut stmt = conn.prepare_cached(
+            r#"
+            SELECT id, agent_id, memory_type, content, metadata, embedding,
+                   relevance_score, created_at, updated_at, access_count, tags, pinned, tier
+            FROM agent_memories am
+            WHERE agent_id = ?1 AND tier = ?2 AND pinned = 0 AND created_at < ?3
+              AND (?4 = 0 OR EXISTS (SELECT 1 FROM memory_consolidations mc WHERE mc.episodic_id = am.id))
+            "#,
+        )?;
+        let rows = stmt.query_map(
+            params![&self.agent_id, format!("{:?}", tier), cutoff.to_rfc3339(), consolidated_only],
+            row_to_memory,
+        )?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Pin or unpin a memory; false when it doesn't exist
+    pub fn set_memory_pinned(&self, memory_id: &str, pinned: bool) -> Result<bool> {
+        use rusqlite::params;
+
+        let conn = self.agent_connection()?;
+        let updated = conn.execute(
+            "UPDATE agent_memories SET pinned = ?3 WHERE id = ?1 AND agent_id = ?2",
+            params![memory_id, &self.agent_id, pinned],
+        )?;
+
+        if updated > 0 {
+            let action = if pinned { "Memory pinned" } else { "Memory unpinned" };
+            self.log_memory_access(&conn, memory_id, "Update", Some(action))?;
+        }
+        Ok(updated > 0)
+    }
+
     pub fn search_memories(&self, query: &MemoryQuery) -> Result<Vec<MemorySearchResult>> {
-        use rusqlite::{Connection, params};
-        
-        let conn = Connection::open(&self.agent_db_path)?;
+        let conn = self.agent_connection()?;
+
+        if let Some(weights) = query.fusion {
+            let mut results = super::memory_search::hybrid_search(&conn, query, weights)?;
+            rank_pinned_first(&mut results);
+            return Ok(results);
+        }
 
         let mut sql = String::from(
             r#"
             SELECT am.id, am.agent_id, am.memory_type, am.content, am.metadata, 
                    am.embedding, am.relevance_score, am.created_at, am.updated_at, 
-                   am.access_count, am.tags
+                   am.access_count, am.tags, am.pinned, am.tier
             FROM agent_memories am
             WHERE 1=1
             "#,
@@ -177,6 +519,14 @@ impl SimpleMemoryManager {
             }
         }
 
+        if let Some(tiers) = &query.tiers {
+            let tier_placeholders = vec!["?"; tiers.len()].join(",");
+            sql.push_str(&format!(" AND am.tier IN ({})", tier_placeholders));
+            for tier in tiers {
+                params_vec.push(Box::new(format!("{:?}", tier)));
+            }
+        }
+
         if let Some(content_search) = &query.content_search {
             sql.push_str(" AND am.content LIKE ?");
             params_vec.push(Box::new(format!("%{}%", content_search)));
@@ -204,7 +554,7 @@ impl SimpleMemoryManager {
             .collect();
 
         let memory_rows = stmt.query_map(&params_refs[..], |row| {
-            self.row_to_memory(row)
+            row_to_memory(row)
         })?;
 
         let mut results = Vec::new();
@@ -214,7 +564,7 @@ impl SimpleMemoryManager {
             // Calculate similarity if embedding provided
             let similarity_score = if let (Some(query_embedding), Some(memory_embedding)) = 
                 (&query.embedding, &memory.embedding) {
-                Some(cosine_similarity(query_embedding, memory_embedding))
+                Some(query.similarity_metric.similarity(query_embedding, memory_embedding))
             } else {
                 None
             };
@@ -223,6 +573,7 @@ impl SimpleMemoryManager {
                 memory,
                 similarity_score,
                 relevance_rank: index,
+                fusion_scores: None,
             });
         }
 
@@ -239,51 +590,143 @@ impl SimpleMemoryManager {
                 b.similarity_score.partial_cmp(&a.similarity_score).unwrap_or(std::cmp::Ordering::Equal)
             });
         }
+        rank_pinned_first(&mut results);
 
         Ok(results)
     }
 
-    pub fn save_shared_knowledge(&self, knowledge: &SharedKnowledge) -> Result<()> {
-        use rusqlite::{Connection, params};
-        
-        let conn = Connection::open(&self.shared_db_path)?;
-
-        let source_agents_json = serde_json::to_string(&knowledge.source_agents)?;
-        let tags_json = serde_json::to_string(&knowledge.tags)?;
-        let embedding_blob = knowledge.embedding.as_ref().map(|e| bincode::serialize(e)).transpose()?;
+    /// Let another agent read the memories of this one matching `tags` and `memory_types`,
+    /// replacing any earlier grant to it
+    pub fn grant_memory_access(&self, grantee_agent: &str, tags: Vec<String>, memory_types: Vec<MemoryType>) -> Result<MemoryAccessGrant> {
+        use rusqlite::params;
 
+        if grantee_agent == self.agent_id {
+            return Err(anyhow!("An agent cannot grant access to itself"));
+        }
+        let conn = self.agent_connection()?;
+        let grant = MemoryAccessGrant {
+            id: uuid::Uuid::new_v4().to_string(),
+            owner_agent: self.agent_id.clone(),
+            grantee_agent: grantee_agent.to_string(),
+            tags,
+            memory_types,
+            created_at: chrono::Utc::now(),
+        };
         conn.execute(
             r#"
-            INSERT OR REPLACE INTO shared_knowledge 
-            (id, knowledge_type, title, content, source_agents, embedding, 
-             confidence_score, created_at, updated_at, version, tags)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+            INSERT INTO memory_access_grants (id, owner_agent, grantee_agent, tags, memory_types, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(owner_agent, grantee_agent) DO UPDATE SET
+                id = excluded.id, tags = excluded.tags,
+                memory_types = excluded.memory_types, created_at = excluded.created_at
             "#,
             params![
-                knowledge.id,
-                format!("{:?}", knowledge.knowledge_type),
-                knowledge.title,
-                knowledge.content,
-                source_agents_json,
-                embedding_blob,
-                knowledge.confidence_score,
-                knowledge.created_at.to_rfc3339(),
-                knowledge.updated_at.to_rfc3339(),
-                knowledge.version,
-                tags_json
+                grant.id,
+                grant.owner_agent,
+                grant.grantee_agent,
+                serde_json::to_string(&grant.tags)?,
+                serde_json::to_string(&grant.memory_types)?,
+                grant.created_at.to_rfc3339()
             ],
         )?;
+        Ok(grant)
+    }
 
-        Ok(())
+    /// Withdraw a grant. Returns whether one existed.
+    pub fn revoke_memory_access(&self, grantee_agent: &str) -> Result<bool> {
+        use rusqlite::params;
+
+        let conn = self.agent_connection()?;
+        let removed = conn.execute(
+            "DELETE FROM memory_access_grants WHERE owner_agent = ?1 AND grantee_agent = ?2",
+            params![&self.agent_id, grantee_agent],
+        )?;
+        Ok(removed > 0)
+    }
+
+    pub fn get_memory_access_grant(&self, grantee_agent: &str) -> Result<Option<MemoryAccessGrant>> {
+        use rusqlite::{OptionalExtension, params};
+
+        let conn = self.agent_connection()?;
+        let row = conn.query_row(
+            "SELECT id, tags, memory_types, created_at FROM memory_access_grants WHERE owner_agent = ?1 AND grantee_agent = ?2",
+            params![&self.agent_id, grantee_agent],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?)),
+        ).optional()?;
+
+        row.map(|(id, tags, memory_types, created_at)| Ok(MemoryAccessGrant {
+            id,
+            owner_agent: self.agent_id.clone(),
+            grantee_agent: grantee_agent.to_string(),
+            tags: serde_json::from_str(&tags)?,
+            memory_types: serde_json::from_str(&memory_types)?,
+            created_at: super::parse_db_timestamp(&created_at),
+        })).transpose()
+    }
+
+    /// Search this agent's memories on behalf of `grantee_agent`, limited to what it was
+    /// granted. Fails if there is no grant; each memory returned is logged as read.
+    pub fn query_foreign_memories(&self, grantee_agent: &str, query: &MemoryQuery) -> Result<Vec<MemorySearchResult>> {
+        let grant = self.get_memory_access_grant(grantee_agent)?
+            .ok_or_else(|| anyhow!("Agent {} has no access to memories of {}", grantee_agent, self.agent_id))?;
+
+        let mut scoped = query.clone();
+        scoped.agent_id = Some(self.agent_id.clone());
+        scoped.limit = None;
+        scoped.offset = None;
+        if !grant.memory_types.is_empty() {
+            let types: Vec<MemoryType> = match &query.memory_types {
+                Some(requested) => requested.iter().filter(|t| grant.memory_types.contains(t)).cloned().collect(),
+                None => grant.memory_types.clone(),
+            };
+            if types.is_empty() {
+                return Ok(Vec::new());
+            }
+            scoped.memory_types = Some(types);
+        }
+
+        let requested_tags = query.tags.as_deref().unwrap_or_default();
+        let results: Vec<MemorySearchResult> = self.search_memories(&scoped)?
+            .into_iter()
+            .filter(|r| grant.permits(&r.memory))
+            .filter(|r| requested_tags.is_empty() || r.memory.tags.iter().any(|t| requested_tags.iter().any(|q| q.eq_ignore_ascii_case(t))))
+            .skip(query.offset.unwrap_or(0))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .enumerate()
+            .map(|(relevance_rank, r)| MemorySearchResult { relevance_rank, ..r })
+            .collect();
+
+        let conn = self.agent_connection()?;
+        let context = format!("Read by {}", grantee_agent);
+        for result in &results {
+            self.log_memory_access(&conn, &result.memory.id, "Read", Some(&context))?;
+        }
+        Ok(results)
+    }
+
+    pub fn save_shared_knowledge(&self, knowledge: &SharedKnowledge) -> Result<()> {
+        let conn = self.shared_connection()?;
+        super::shared_knowledge::upsert_knowledge(&conn, knowledge)
+    }
+
+    /// Add knowledge to the shared store, merging with what other agents already contributed
+    pub fn contribute_shared_knowledge(
+        &self,
+        knowledge: SharedKnowledge,
+        metric: SimilarityMetric,
+        classifier: &super::neural_knowledge_graph::ContradictionClassifier,
+    ) -> Result<super::shared_knowledge::ContributionOutcome> {
+        let mut conn = self.shared_connection()?;
+        super::shared_knowledge::contribute(&mut conn, knowledge, metric, classifier)
     }
 
     pub fn add_knowledge_node(&self, node: &KnowledgeNode) -> Result<()> {
-        use rusqlite::{Connection, params};
+        use rusqlite::params;
         
-        let conn = Connection::open(&self.shared_db_path)?;
+        let conn = self.shared_connection()?;
 
         let properties_json = serde_json::to_string(&node.properties)?;
-        let embedding_blob = node.embedding.as_ref().map(|e| bincode::serialize(e)).transpose()?;
+        let embedding_blob = node.embedding.as_deref().map(encode_embedding);
 
         conn.execute(
             r#"
@@ -306,9 +749,9 @@ impl SimpleMemoryManager {
     }
 
     pub fn add_knowledge_edge(&self, edge: &KnowledgeEdge) -> Result<()> {
-        use rusqlite::{Connection, params};
+        use rusqlite::params;
         
-        let conn = Connection::open(&self.shared_db_path)?;
+        let conn = self.shared_connection()?;
 
         let properties_json = serde_json::to_string(&edge.properties)?;
 
@@ -333,16 +776,171 @@ impl SimpleMemoryManager {
         Ok(())
     }
 
-    fn log_memory_access(&self, memory_id: &str, access_type: &str, context: Option<&str>) -> Result<()> {
-        use rusqlite::{Connection, params};
-        
-        let conn = Connection::open(&self.agent_db_path)?;
+    /// Write many nodes and edges to the shared graph in a single transaction
+    pub fn add_knowledge_batch(&self, nodes: &[KnowledgeNode], edges: &[KnowledgeEdge]) -> Result<()> {
+        use rusqlite::params;
 
-        conn.execute(
+        let mut conn = self.shared_connection()?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert_node = tx.prepare(
+                r#"
+                INSERT OR REPLACE INTO knowledge_nodes
+                (id, node_type, name, properties, embedding, created_at, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+            )?;
+            for node in nodes {
+                let embedding_blob = node.embedding.as_deref().map(encode_embedding);
+                insert_node.execute(params![
+                    node.id,
+                    format!("{:?}", node.node_type),
+                    node.name,
+                    serde_json::to_string(&node.properties)?,
+                    embedding_blob,
+                    node.created_at.to_rfc3339(),
+                    node.updated_at.to_rfc3339()
+                ])?;
+            }
+
+            let mut insert_edge = tx.prepare(
+                r#"
+                INSERT OR REPLACE INTO knowledge_edges
+                (id, from_node, to_node, relationship_type, weight, properties, created_at, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+            )?;
+            for edge in edges {
+                insert_edge.execute(params![
+                    edge.id,
+                    edge.from_node,
+                    edge.to_node,
+                    format!("{:?}", edge.relationship_type),
+                    edge.weight,
+                    serde_json::to_string(&edge.properties)?,
+                    edge.created_at.to_rfc3339(),
+                    edge.updated_at.to_rfc3339()
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Which of `ids` exist as nodes anywhere in the shared graph
+    pub fn existing_knowledge_node_ids(&self, ids: &[&str]) -> Result<std::collections::HashSet<String>> {
+        let conn = self.shared_connection()?;
+        let mut found = std::collections::HashSet::new();
+        // Stay well under SQLite's bound parameter limit
+        for chunk in ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = conn.prepare(&format!("SELECT id FROM knowledge_nodes WHERE id IN ({})", placeholders))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(chunk.iter()), |row| row.get::<_, String>(0))?;
+            for id in rows {
+                found.insert(id?);
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Load the agent's slice of the shared knowledge graph: its nodes and the edges leaving them
+    pub fn load_knowledge_graph(&self) -> Result<(Vec<KnowledgeNode>, Vec<KnowledgeEdge>)> {
+        use rusqlite::params;
+
+        let conn = self.shared_connection()?;
+
+        let nodes = {
+            let mut stmt = conn.prepare(
+                r#"
+                SELECT id, node_type, name, properties, embedding, created_at, updated_at
+                FROM knowledge_nodes
+                WHERE json_extract(properties, '$.agent_id') = ?1
+                   OR (node_type = 'Agent' AND (name = ?1 OR id = ?1))
+                "#
+            )?;
+            let rows = stmt.query_map(params![&self.agent_id], |row| self.row_to_knowledge_node(row))?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+
+        let edges = {
+            let mut stmt = conn.prepare(
+                r#"
+                SELECT id, from_node, to_node, relationship_type, weight, properties, created_at, updated_at
+                FROM knowledge_edges
+                WHERE json_extract(properties, '$.agent_id') = ?1
+                   OR from_node IN (SELECT id FROM knowledge_nodes WHERE json_extract(properties, '$.agent_id') = ?1)
+                "#
+            )?;
+            let rows = stmt.query_map(params![&self.agent_id], |row| self.row_to_knowledge_edge(row))?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+
+        Ok((nodes, edges))
+    }
+
+    fn row_to_knowledge_node(&self, row: &rusqlite::Row) -> rusqlite::Result<KnowledgeNode> {
+        let properties_json: Option<String> = row.get("properties")?;
+        let embedding_blob: Option<Vec<u8>> = row.get("embedding")?;
+
+        let node_type = match row.get::<_, String>("node_type")?.as_str() {
+            "Agent" => NodeType::Agent,
+            "Memory" => NodeType::Memory,
+            "Task" => NodeType::Task,
+            "Tool" => NodeType::Tool,
+            "Context" => NodeType::Context,
+            "Pattern" => NodeType::Pattern,
+            _ => NodeType::Concept, // Default fallback
+        };
+
+        Ok(KnowledgeNode {
+            id: row.get("id")?,
+            node_type,
+            name: row.get("name")?,
+            properties: properties_json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default(),
+            embedding: embedding_blob.and_then(|blob| decode_embedding(&blob)),
+            created_at: super::parse_db_timestamp(&row.get::<_, String>("created_at")?),
+            updated_at: super::parse_db_timestamp(&row.get::<_, String>("updated_at")?),
+        })
+    }
+
+    fn row_to_knowledge_edge(&self, row: &rusqlite::Row) -> rusqlite::Result<KnowledgeEdge> {
+        let properties_json: Option<String> = row.get("properties")?;
+
+        let relationship_type = match row.get::<_, String>("relationship_type")?.as_str() {
+            "Knows" => RelationshipType::Knows,
+            "Uses" => RelationshipType::Uses,
+            "LearnedFrom" => RelationshipType::LearnedFrom,
+            "CollaboratesWith" => RelationshipType::CollaboratesWith,
+            "DependsOn" => RelationshipType::DependsOn,
+            "Opposite" => RelationshipType::Opposite,
+            "CausedBy" => RelationshipType::CausedBy,
+            "LeadsTo" => RelationshipType::LeadsTo,
+            _ => RelationshipType::Similar, // Default fallback
+        };
+
+        Ok(KnowledgeEdge {
+            id: row.get("id")?,
+            from_node: row.get("from_node")?,
+            to_node: row.get("to_node")?,
+            relationship_type,
+            weight: row.get::<_, Option<f32>>("weight")?.unwrap_or(1.0),
+            properties: properties_json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default(),
+            created_at: super::parse_db_timestamp(&row.get::<_, String>("created_at")?),
+            updated_at: super::parse_db_timestamp(&row.get::<_, String>("updated_at")?),
+        })
+    }
+
+    fn log_memory_access(&self, conn: &rusqlite::Connection, memory_id: &str, access_type: &str, context: Option<&str>) -> Result<()> {
+        use rusqlite::params;
+
+        conn.prepare_cached(
             r#"
             INSERT INTO memory_access_log (id, memory_id, agent_id, access_type, context, timestamp)
             VALUES (?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP)
             "#,
+        )?.execute(
             params![
                 uuid::Uuid::new_v4().to_string(),
                 memory_id,
@@ -355,53 +953,39 @@ impl SimpleMemoryManager {
         Ok(())
     }
 
-    fn row_to_memory(&self, row: &rusqlite::Row) -> rusqlite::Result<AgentMemory> {
-        let metadata_json: String = row.get("metadata")?;
-        let tags_json: String = row.get("tags")?;
-        let embedding_blob: Option<Vec<u8>> = row.get("embedding")?;
+    /// Repoint memories tagged with any of `from_conversations` to `to_conversation`
+    pub fn reassign_conversation_memories(&self, from_conversations: &[String], to_conversation: &str) -> Result<usize> {
+        use rusqlite::params;
 
-        let metadata: HashMap<String, String> = serde_json::from_str(&metadata_json)
-            .unwrap_or_default();
-        let tags: Vec<String> = serde_json::from_str(&tags_json)
-            .unwrap_or_default();
-        let embedding: Option<Vec<f32>> = embedding_blob
-            .and_then(|blob| bincode::deserialize(&blob).ok());
-
-        let memory_type_str: String = row.get("memory_type")?;
-        let memory_type = match memory_type_str.as_str() {
-            "Conversation" => MemoryType::Conversation,
-            "Task" => MemoryType::Task,
-            "Learning" => MemoryType::Learning,
-            "Context" => MemoryType::Context,
-            "Tool" => MemoryType::Tool,
-            "Error" => MemoryType::Error,
-            "Success" => MemoryType::Success,
-            "Pattern" => MemoryType::Pattern,
-            _ => MemoryType::Context, // Default fallback
-        };
+        let conn = self.agent_connection()?;
+        let mut updated = 0;
 
-        Ok(AgentMemory {
-            id: row.get("id")?,
-            agent_id: row.get("agent_id")?,
-            memory_type,
-            content: row.get("content")?,
-            metadata,
-            embedding,
-            relevance_score: row.get("relevance_score")?,
-            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>("created_at")?)
-                .map_err(|_| rusqlite::Error::InvalidColumnType(0, "created_at".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&chrono::Utc),
-            updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>("updated_at")?)
-                .map_err(|_| rusqlite::Error::InvalidColumnType(0, "updated_at".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&chrono::Utc),
-            access_count: row.get("access_count")?,
-            tags,
-        })
+        for from in from_conversations {
+            updated += conn.execute(
+                r#"
+                UPDATE agent_memories
+                SET metadata = json_set(metadata, '$.conversation_id', ?1),
+                    updated_at = ?2
+                WHERE json_extract(metadata, '$.conversation_id') = ?3
+                "#,
+                params![to_conversation, chrono::Utc::now().to_rfc3339(), from],
+            )?;
+        }
+
+        Ok(updated)
     }
 
+    /// Write a consistent snapshot of the agent database. Unlike copying the file, this
+    /// includes transactions still held in the WAL.
     pub fn backup_agent_memory(&self, backup_path: &Path) -> Result<()> {
-        // Simple file copy backup (in production, use SQLite backup API)
-        std::fs::copy(&self.agent_db_path, backup_path)?;
+        use rusqlite::params;
+
+        // VACUUM INTO refuses to overwrite, while a named backup has always replaced its namesake
+        if backup_path.exists() {
+            std::fs::remove_file(backup_path)?;
+        }
+        let conn = self.agent_connection()?;
+        conn.execute("VACUUM INTO ?1", params![backup_path.to_string_lossy()])?;
         Ok(())
     }
 
@@ -412,4 +996,47 @@ impl SimpleMemoryManager {
     pub fn get_shared_db_path(&self) -> &PathBuf {
         &self.shared_db_path
     }
-}
\ No newline at end of file
+}
+
+pub(super) fn row_to_memory(row: &rusqlite::Row) -> rusqlite::Result<AgentMemory> {
+    let metadata_json: String = row.get("metadata")?;
+    let tags_json: String = row.get("tags")?;
+    let embedding_blob: Option<Vec<u8>> = row.get("embedding")?;
+
+    let metadata: HashMap<String, String> = serde_json::from_str(&metadata_json)
+        .unwrap_or_default();
+    let tags: Vec<String> = serde_json::from_str(&tags_json)
+        .unwrap_or_default();
+    let embedding: Option<Vec<f32>> = embedding_blob
+        .and_then(|blob| decode_embedding(&blob));
+
+    let memory_type_str: String = row.get("memory_type")?;
+    let memory_type = match memory_type_str.as_str() {
+        "Conversation" => MemoryType::Conversation,
+        "Task" => MemoryType::Task,
+        "Learning" => MemoryType::Learning,
+        "Context" => MemoryType::Context,
+        "Tool" => MemoryType::Tool,
+        "Error" => MemoryType::Error,
+        "Success" => MemoryType::Success,
+        "Pattern" => MemoryType::Pattern,
+        _ => MemoryType::Context, // Default fallback
+    };
+
+    Ok(AgentMemory {
+        id: row.get("id")?,
+        agent_id: row.get("agent_id")?,
+        memory_type,
+        content: row.get("content")?,
+        metadata,
+        embedding,
+        relevance_score: row.get("relevance_score")?,
+        created_at: super::parse_db_timestamp(&row.get::<_, String>("created_at")?),
+        // The update trigger rewrites this with SQLite's CURRENT_TIMESTAMP format
+        updated_at: super::parse_db_timestamp(&row.get::<_, String>("updated_at")?),
+        access_count: row.get("access_count")?,
+        tags,
+        pinned: row.get("pinned")?,
+        tier: MemoryTier::from_name(&row.get::<_, String>("tier")?),
+    })
+}
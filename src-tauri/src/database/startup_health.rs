@@ -0,0 +1,297 @@
+//! Startup integrity check. A sentinel file marks a running app and is removed on a clean exit,
+//! so finding one at startup means the last run crashed or was killed. Every memory database is
+//! then checked, fully after an unclean exit and quickly otherwise, and a corrupted one is moved
+//! to a recovery folder so the agent starts over with a fresh database.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::State;
+use tracing::{error, info, warn};
+
+use super::memory_encryption::{apply_key, database_key, key_path};
+use super::simple_memory::{agent_databases_in, SimpleMemoryManager};
+
+/// Present while the app runs; removed by a clean shutdown
+const RUN_SENTINEL_FILE: &str = "app.running";
+
+/// Folder under the memory directory that receives quarantined databases
+const RECOVERY_DIR: &str = "recovery";
+
+/// Integrity problems reported per database, beyond which SQLite's list is cut short
+const MAX_REPORTED_ISSUES: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunSentinel {
+    pid: u32,
+    started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseCheck {
+    pub path: String,
+    pub healthy: bool,
+    /// Whether the full `integrity_check` ran rather than `quick_check`
+    pub full_check: bool,
+    pub issues: Vec<String>,
+    /// Size of a write-ahead log left behind, which a clean exit checkpoints away
+    pub wal_bytes: u64,
+    /// Where the database was moved if it was corrupted
+    pub quarantined_to: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupHealthReport {
+    pub unclean_shutdown: bool,
+    /// Start time of the run that didn't shut down cleanly
+    pub previous_run_started_at: Option<DateTime<Utc>>,
+    pub checked_at: DateTime<Utc>,
+    pub databases: Vec<DatabaseCheck>,
+}
+
+impl StartupHealthReport {
+    pub fn quarantined(&self) -> impl Iterator<Item = &DatabaseCheck> {
+        self.databases.iter().filter(|db| db.quarantined_to.is_some())
+    }
+}
+
+fn file_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Every memory database: the shared knowledge database and one per agent and workspace
+fn memory_databases(memory_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut databases = Vec::new();
+    let shared = memory_dir.join("shared").join("knowledge.db");
+    if shared.is_file() {
+        databases.push(shared);
+    }
+    databases.extend(agent_databases_in(memory_dir)?.into_iter().map(|database| database.path));
+    databases.sort();
+    Ok(databases)
+}
+
+/// Outcome of checking one database
+#[derive(Debug, PartialEq)]
+enum Integrity {
+    Sound,
+    /// SQLite reported the file corrupt or not a database
+    Corrupted(Vec<String>),
+    /// The check couldn't run, e.g. the key is unavailable or the file is locked; the
+    /// database may be fine, so it is left in place
+    Unchecked(String),
+}
+
+fn is_corruption(e: &rusqlite::Error) -> bool {
+    matches!(e.sqlite_error_code(), Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase))
+}
+
+/// Run SQLite's integrity check; `quick_check` skips the index-content comparison. An
+/// encrypted database reads as "not a database" without its key, so it only counts as
+/// corrupted once the key has been applied.
+fn check_integrity(path: &Path, full: bool) -> Integrity {
+    let key = match database_key(path) {
+        Ok(key) => key,
+        Err(e) => return Integrity::Unchecked(format!("Could not load database key: {}", e)),
+    };
+    let pragma = if full { "PRAGMA integrity_check" } else { "PRAGMA quick_check" };
+    let rows = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE).and_then(|conn| {
+        if let Some(ref key) = key {
+            apply_key(&conn, key)?;
+        }
+        let mut stmt = conn.prepare(pragma)?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    });
+    match rows {
+        Ok(rows) => {
+            let issues: Vec<String> = rows.into_iter()
+                .filter(|row| row != "ok")
+                .take(MAX_REPORTED_ISSUES)
+                .collect();
+            if issues.is_empty() { Integrity::Sound } else { Integrity::Corrupted(issues) }
+        }
+        Err(e) if is_corruption(&e) => Integrity::Corrupted(vec![format!("Could not read database: {}", e)]),
+        Err(e) => Integrity::Unchecked(format!("Could not read database: {}", e)),
+    }
+}
+
+/// Move a database with its WAL, shared-memory and key files into `recovery_dir`
+fn quarantine(path: &Path, recovery_dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(recovery_dir)
+        .with_context(|| format!("Failed to create {}", recovery_dir.display()))?;
+    let file_name = path.file_name().context("Database path has no file name")?;
+    let destination = recovery_dir.join(file_name);
+    for (from, to) in [
+        (path.to_path_buf(), destination.clone()),
+        (file_with_suffix(path, "-wal"), file_with_suffix(&destination, "-wal")),
+        (file_with_suffix(path, "-shm"), file_with_suffix(&destination, "-shm")),
+        (key_path(path), key_path(&destination)),
+    ] {
+        if from.exists() {
+            std::fs::rename(&from, &to)
+                .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+        }
+    }
+    Ok(destination)
+}
+
+fn check_database(path: &Path, full: bool, recovery_dir: &Path) -> DatabaseCheck {
+    let wal_bytes = std::fs::metadata(file_with_suffix(path, "-wal")).map(|m| m.len()).unwrap_or(0);
+    let (issues, quarantined_to) = match check_integrity(path, full) {
+        Integrity::Sound => (Vec::new(), None),
+        Integrity::Unchecked(reason) => {
+            warn!("Could not check memory database {}: {}", path.display(), reason);
+            (vec![reason], None)
+        }
+        Integrity::Corrupted(issues) => {
+            warn!("Memory database {} failed its integrity check: {}", path.display(), issues.join("; "));
+            let quarantined_to = match quarantine(path, recovery_dir) {
+                Ok(destination) => Some(destination.to_string_lossy().to_string()),
+                Err(e) => {
+                    error!("Failed to quarantine {}: {:#}", path.display(), e);
+                    None
+                }
+            };
+            (issues, quarantined_to)
+        }
+    };
+    DatabaseCheck {
+        path: path.to_string_lossy().to_string(),
+        healthy: issues.is_empty(),
+        full_check: full,
+        issues,
+        wal_bytes,
+        quarantined_to,
+    }
+}
+
+/// Check for an unclean exit and the health of every database under `memory_dir`, then mark
+/// this run as started
+pub fn run_startup_checks_in(memory_dir: &Path) -> Result<StartupHealthReport> {
+    std::fs::create_dir_all(memory_dir)?;
+    let sentinel_path = memory_dir.join(RUN_SENTINEL_FILE);
+    let leftover = std::fs::read_to_string(&sentinel_path).ok();
+    let unclean_shutdown = leftover.is_some();
+    let previous: Option<RunSentinel> = leftover.and_then(|contents| serde_json::from_str(&contents).ok());
+    if unclean_shutdown {
+        warn!("The previous run did not shut down cleanly: {:?}", previous);
+    }
+
+    let checked_at = Utc::now();
+    let recovery_dir = memory_dir.join(RECOVERY_DIR).join(checked_at.format("%Y%m%d_%H%M%S").to_string());
+    let databases = memory_databases(memory_dir)?
+        .iter()
+        .map(|path| {
+            // Keep the layout below the memory directory so same-named agents of different
+            // workspaces don't collide
+            let relative_dir = path.strip_prefix(memory_dir).ok().and_then(Path::parent).unwrap_or(Path::new(""));
+            check_database(path, unclean_shutdown, &recovery_dir.join(relative_dir))
+        })
+        .collect();
+
+    let sentinel = RunSentinel { pid: std::process::id(), started_at: checked_at };
+    std::fs::write(&sentinel_path, serde_json::to_string(&sentinel)?)
+        .context("Failed to write run sentinel")?;
+
+    Ok(StartupHealthReport {
+        unclean_shutdown,
+        previous_run_started_at: previous.map(|p| p.started_at),
+        checked_at,
+        databases,
+    })
+}
+
+pub fn run_startup_checks() -> Result<StartupHealthReport> {
+    let report = run_startup_checks_in(&SimpleMemoryManager::get_memory_directory()?)?;
+    info!(
+        "Startup check: {} databases, {} quarantined{}",
+        report.databases.len(),
+        report.quarantined().count(),
+        if report.unclean_shutdown { " after an unclean shutdown" } else { "" },
+    );
+    Ok(report)
+}
+
+/// Mark the run as cleanly finished; the last step of shutdown
+pub fn clear_run_sentinel() -> Result<()> {
+    let path = SimpleMemoryManager::get_memory_directory()?.join(RUN_SENTINEL_FILE);
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The report from this run's startup check
+pub struct StartupHealthState {
+    report: Mutex<Result<StartupHealthReport, String>>,
+}
+
+impl StartupHealthState {
+    pub fn new(report: Result<StartupHealthReport>) -> Self {
+        Self { report: Mutex::new(report.map_err(|e| format!("{:#}", e))) }
+    }
+}
+
+#[tauri::command]
+pub async fn get_startup_health_report(
+    state: State<'_, StartupHealthState>,
+) -> Result<StartupHealthReport, String> {
+    state.report.lock().unwrap().clone()
+        .map_err(|e| format!("Startup health check failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_unclean_shutdown_and_quarantines_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let agents = dir.path().join("agents");
+        std::fs::create_dir_all(&agents).unwrap();
+        let healthy = agents.join("coder.db");
+        rusqlite::Connection::open(&healthy).unwrap()
+            .execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);").unwrap();
+        let corrupted = agents.join("broken.db");
+        std::fs::write(&corrupted, b"this is not a sqlite database, just some bytes").unwrap();
+
+        let first = run_startup_checks_in(dir.path()).unwrap();
+        assert!(!first.unclean_shutdown);
+        assert_eq!(first.databases.len(), 2);
+        let broken = first.quarantined().next().unwrap();
+        assert!(broken.path.ends_with("broken.db"));
+        assert!(!corrupted.exists());
+        assert!(Path::new(broken.quarantined_to.as_ref().unwrap()).exists());
+        assert!(first.databases.iter().any(|db| db.healthy && !db.full_check));
+
+        // The sentinel from the first run was never cleared, as after a crash
+        let second = run_startup_checks_in(dir.path()).unwrap();
+        assert!(second.unclean_shutdown);
+        assert_eq!(second.previous_run_started_at, Some(first.checked_at));
+        assert!(second.databases.iter().all(|db| db.healthy && db.full_check));
+    }
+
+    #[test]
+    fn test_unreadable_database_is_reported_but_left_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let agents = dir.path().join("agents");
+        std::fs::create_dir_all(&agents).unwrap();
+        let locked = agents.join("locked.db");
+        let conn = rusqlite::Connection::open(&locked).unwrap();
+        conn.execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY); BEGIN EXCLUSIVE; INSERT INTO notes VALUES (1);").unwrap();
+
+        let report = run_startup_checks_in(dir.path()).unwrap();
+        let check = &report.databases[0];
+        assert!(!check.healthy);
+        assert!(check.quarantined_to.is_none());
+        assert!(locked.exists());
+        drop(conn);
+    }
+}
@@ -0,0 +1,795 @@
+//! Persistent queue of background tool and LLM jobs. Jobs run highest priority first, failures
+//! are retried with exponential backoff, and a job out of attempts is moved to the dead-letter
+//! list, where it stays until it is retried by hand. Tool calls are only retried when the tool
+//! declares itself idempotent, since a failed call may still have taken effect.
+//!
+//! Jobs come from the frontend, from workflow steps, which wait for their job's outcome, and from
+//! schedules that queue a job on a fixed interval. A job made for an agent runs under that agent's
+//! permissions and guardrails; one made for no agent gets the default manifest.
+
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tokio::sync::{oneshot, Semaphore};
+use tracing::{error, info, warn};
+
+use super::notification_center::{notify_quietly, NewNotification, NotificationSeverity};
+use super::{open_conversation_db, parse_db_timestamp};
+use crate::ai::{
+    complete_text, load_agent_permissions, load_default_permissions, select_completion_provider, AIState,
+    GuardrailScope, GuardrailViolation,
+};
+use crate::mcp::{call_routed_tool, resolve_route, MCPToolCatalog};
+
+/// Event emitted with the `QueuedTask` whenever a task changes status
+pub const TASK_UPDATED_EVENT: &str = "task_queue_updated";
+
+/// Time between checks for ready tasks
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Tasks the worker runs at once
+pub const MAX_CONCURRENT_TASKS: usize = 4;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubled for each further attempt
+const BASE_RETRY_DELAY_MS: i64 = 2_000;
+
+const MAX_RETRY_DELAY_MS: i64 = 5 * 60 * 1000;
+
+/// Shortest interval a schedule may repeat at
+const MIN_SCHEDULE_INTERVAL_SECS: u64 = 60;
+
+/// Error recorded on a task that can't be retried and was running when the app stopped
+const INTERRUPTED_ERROR: &str = "Interrupted while running; not retried because the tool is not idempotent";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskJob {
+    /// Call an MCP tool by its routed name
+    Tool {
+        tool: String,
+        arguments: Value,
+        /// Agent the call is made for
+        #[serde(default)]
+        agent_id: Option<String>,
+        /// Set from the tool's annotations when the job is queued
+        #[serde(default)]
+        idempotent: bool,
+    },
+    /// Ask a model for a plain-text completion
+    Llm {
+        /// Agent the completion is made for
+        #[serde(default)]
+        agent_id: Option<String>,
+        provider: Option<String>,
+        model: Option<String>,
+        #[serde(default)]
+        system: String,
+        prompt: String,
+    },
+}
+
+impl TaskJob {
+    /// Whether a failed attempt may be repeated
+    pub fn retryable(&self) -> bool {
+        match self {
+            TaskJob::Tool { idempotent, .. } => *idempotent,
+            TaskJob::Llm { .. } => true,
+        }
+    }
+}
+
+/// Result of an LLM job: the reply, and what guardrails found on the prompt and reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmJobOutput {
+    pub text: String,
+    #[serde(default)]
+    pub guardrail_violations: Vec<GuardrailViolation>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    Queued,
+    Running,
+    Completed,
+    /// Out of attempts; kept on the dead-letter list
+    Dead,
+    Cancelled,
+}
+
+impl TaskStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            TaskStatus::Queued => "queued",
+            TaskStatus::Running => "running",
+            TaskStatus::Completed => "completed",
+            TaskStatus::Dead => "dead",
+            TaskStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [Self::Queued, Self::Running, Self::Completed, Self::Dead, Self::Cancelled]
+            .into_iter()
+            .find(|status| status.as_str() == value)
+    }
+
+    /// The task will not run again unless retried by hand
+    pub fn is_final(self) -> bool {
+        matches!(self, TaskStatus::Completed | TaskStatus::Dead | TaskStatus::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedTask {
+    pub id: String,
+    pub job: TaskJob,
+    pub priority: i64,
+    pub status: TaskStatus,
+    pub attempts: u32,
+    pub max_attempts: u32,
+    /// Earliest time the task may run, in Unix milliseconds
+    pub run_after: i64,
+    pub last_error: Option<String>,
+    pub result: Option<Value>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+const TASK_COLUMNS: &str =
+    "id, job, priority, status, attempts, max_attempts, run_after, last_error, result, created_at, updated_at";
+
+/// A `task_queue` row with its JSON and status columns still raw
+struct TaskRow {
+    id: String,
+    job: String,
+    priority: i64,
+    status: String,
+    attempts: u32,
+    max_attempts: u32,
+    run_after: i64,
+    last_error: Option<String>,
+    result: Option<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+fn task_from_row(row: &Row) -> rusqlite::Result<TaskRow> {
+    Ok(TaskRow {
+        id: row.get(0)?,
+        job: row.get(1)?,
+        priority: row.get(2)?,
+        status: row.get(3)?,
+        attempts: row.get(4)?,
+        max_attempts: row.get(5)?,
+        run_after: row.get(6)?,
+        last_error: row.get(7)?,
+        result: row.get(8)?,
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+    })
+}
+
+fn parse_task(row: TaskRow) -> Result<QueuedTask> {
+    Ok(QueuedTask {
+        job: serde_json::from_str(&row.job).with_context(|| format!("Task {} has a malformed job", row.id))?,
+        status: TaskStatus::parse(&row.status)
+            .ok_or_else(|| anyhow!("Task {} has unknown status {}", row.id, row.status))?,
+        result: row.result.map(|r| serde_json::from_str(&r)).transpose()?,
+        priority: row.priority,
+        attempts: row.attempts,
+        max_attempts: row.max_attempts,
+        run_after: row.run_after,
+        last_error: row.last_error,
+        created_at: parse_db_timestamp(&row.created_at),
+        updated_at: parse_db_timestamp(&row.updated_at),
+        id: row.id,
+    })
+}
+
+pub fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Delay before the retry that follows the given number of attempts
+pub fn retry_delay_ms(attempts: u32) -> i64 {
+    let doublings = attempts.saturating_sub(1).min(20);
+    (BASE_RETRY_DELAY_MS << doublings).min(MAX_RETRY_DELAY_MS)
+}
+
+pub fn get_task(conn: &Connection, task_id: &str) -> Result<Option<QueuedTask>> {
+    let row = conn.query_row(
+        &format!("SELECT {} FROM task_queue WHERE id = ?1", TASK_COLUMNS),
+        params![task_id],
+        task_from_row,
+    ).optional()?;
+    row.map(parse_task).transpose()
+}
+
+fn require_task(conn: &Connection, task_id: &str) -> Result<QueuedTask> {
+    get_task(conn, task_id)?.ok_or_else(|| anyhow!("Unknown task: {}", task_id))
+}
+
+pub fn enqueue(conn: &Connection, job: &TaskJob, priority: i64, max_attempts: u32, now: i64) -> Result<QueuedTask> {
+    if max_attempts == 0 {
+        bail!("max_attempts must be at least 1");
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO task_queue (id, job, priority, max_attempts, run_after) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![id, serde_json::to_string(job)?, priority, max_attempts, now],
+    )?;
+    require_task(conn, &id)
+}
+
+/// Mark the next ready task running and count the attempt
+pub fn claim_next(conn: &Connection, now: i64) -> Result<Option<QueuedTask>> {
+    let id: Option<String> = conn.query_row(
+        "UPDATE task_queue SET status = 'running', attempts = attempts + 1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = (
+             SELECT id FROM task_queue WHERE status = 'queued' AND run_after <= ?1
+             ORDER BY priority DESC, run_after, created_at LIMIT 1
+         )
+         RETURNING id",
+        params![now],
+        |row| row.get(0),
+    ).optional()?;
+    id.map(|id| require_task(conn, &id)).transpose()
+}
+
+/// Record a running task's result; None if it was cancelled meanwhile
+pub fn complete_task(conn: &Connection, task_id: &str, result: &Value) -> Result<Option<QueuedTask>> {
+    let updated = conn.execute(
+        "UPDATE task_queue SET status = 'completed', result = ?2, last_error = NULL, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND status = 'running'",
+        params![task_id, result.to_string()],
+    )?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    get_task(conn, task_id)
+}
+
+/// Requeue a failed running task after its backoff, or dead-letter it once out of attempts or
+/// when its job can't be retried
+pub fn fail_task(conn: &Connection, task_id: &str, error: &str, now: i64) -> Result<Option<QueuedTask>> {
+    let Some(task) = get_task(conn, task_id)?.filter(|t| t.status == TaskStatus::Running) else {
+        return Ok(None);
+    };
+    let (status, run_after) = if task.attempts >= task.max_attempts || !task.job.retryable() {
+        (TaskStatus::Dead, task.run_after)
+    } else {
+        (TaskStatus::Queued, now + retry_delay_ms(task.attempts))
+    };
+    conn.execute(
+        "UPDATE task_queue SET status = ?2, run_after = ?3, last_error = ?4, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND status = 'running'",
+        params![task_id, status.as_str(), run_after, error],
+    )?;
+    get_task(conn, task_id)
+}
+
+/// Cancel a queued or running task
+pub fn cancel(conn: &Connection, task_id: &str) -> Result<QueuedTask> {
+    let updated = conn.execute(
+        "UPDATE task_queue SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND status IN ('queued', 'running')",
+        params![task_id],
+    )?;
+    let task = require_task(conn, task_id)?;
+    if updated == 0 {
+        bail!("Task {} is already {}", task_id, task.status.as_str());
+    }
+    Ok(task)
+}
+
+/// Move a dead-lettered task back to the queue with a fresh set of attempts
+pub fn retry_dead(conn: &Connection, task_id: &str, now: i64) -> Result<QueuedTask> {
+    let updated = conn.execute(
+        "UPDATE task_queue SET status = 'queued', attempts = 0, run_after = ?2, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND status = 'dead'",
+        params![task_id, now],
+    )?;
+    let task = require_task(conn, task_id)?;
+    if updated == 0 {
+        bail!("Task {} is {}, not failed", task_id, task.status.as_str());
+    }
+    Ok(task)
+}
+
+/// Tasks in the given status, or all tasks, next to run first
+pub fn list_tasks(conn: &Connection, status: Option<TaskStatus>) -> Result<Vec<QueuedTask>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM task_queue WHERE ?1 IS NULL OR status = ?1
+         ORDER BY priority DESC, run_after, created_at",
+        TASK_COLUMNS,
+    ))?;
+    let rows = stmt.query_map(params![status.map(TaskStatus::as_str)], task_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    rows.into_iter().map(parse_task).collect()
+}
+
+/// Requeue tasks left running by a previous instance of the app. A task that can't be retried
+/// may already have taken effect, so it is dead-lettered instead. Returns the number requeued.
+pub fn requeue_interrupted(conn: &Connection) -> Result<usize> {
+    let mut requeued = 0;
+    for task in list_tasks(conn, Some(TaskStatus::Running))? {
+        if task.job.retryable() {
+            conn.execute(
+                "UPDATE task_queue SET status = 'queued', updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                params![task.id],
+            )?;
+            requeued += 1;
+        } else {
+            conn.execute(
+                "UPDATE task_queue SET status = 'dead', last_error = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                params![task.id, INTERRUPTED_ERROR],
+            )?;
+        }
+    }
+    Ok(requeued)
+}
+
+/// A job queued every `interval_secs` by the scheduler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSchedule {
+    pub id: String,
+    pub job: TaskJob,
+    pub priority: i64,
+    pub interval_secs: u64,
+    /// Next time the job is queued, in Unix milliseconds
+    pub next_run: i64,
+    /// Task queued by the last run
+    pub last_task_id: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+const SCHEDULE_COLUMNS: &str = "id, job, priority, interval_secs, next_run, last_task_id, created_at";
+
+/// A `task_schedules` row with its job still raw
+type ScheduleRow = (String, String, i64, u64, i64, Option<String>, String);
+
+fn schedule_from_row(row: &Row) -> rusqlite::Result<ScheduleRow> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?))
+}
+
+fn parse_schedule((id, job, priority, interval_secs, next_run, last_task_id, created_at): ScheduleRow) -> Result<TaskSchedule> {
+    Ok(TaskSchedule {
+        job: serde_json::from_str(&job).with_context(|| format!("Schedule {} has a malformed job", id))?,
+        id,
+        priority,
+        interval_secs,
+        next_run,
+        last_task_id,
+        created_at: parse_db_timestamp(&created_at),
+    })
+}
+
+/// First run after `now` of a schedule due at `next_run`; runs missed while the app was closed
+/// are skipped rather than caught up
+pub fn next_run_after(next_run: i64, interval_secs: u64, now: i64) -> i64 {
+    if next_run > now {
+        return next_run;
+    }
+    let interval_ms = interval_secs as i64 * 1000;
+    next_run + ((now - next_run) / interval_ms + 1) * interval_ms
+}
+
+pub fn add_schedule(conn: &Connection, job: &TaskJob, priority: i64, interval_secs: u64, first_run: i64) -> Result<TaskSchedule> {
+    if interval_secs < MIN_SCHEDULE_INTERVAL_SECS {
+        bail!("Schedules repeat at most every {} seconds", MIN_SCHEDULE_INTERVAL_SECS);
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO task_schedules (id, job, priority, interval_secs, next_run) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![id, serde_json::to_string(job)?, priority, interval_secs, first_run],
+    )?;
+    list_schedules(conn)?.into_iter()
+        .find(|schedule| schedule.id == id)
+        .ok_or_else(|| anyhow!("Schedule {} was not stored", id))
+}
+
+pub fn list_schedules(conn: &Connection) -> Result<Vec<TaskSchedule>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM task_schedules ORDER BY next_run, created_at", SCHEDULE_COLUMNS))?;
+    let rows = stmt.query_map([], schedule_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    rows.into_iter().map(parse_schedule).collect()
+}
+
+pub fn delete_schedule(conn: &Connection, schedule_id: &str) -> Result<()> {
+    if conn.execute("DELETE FROM task_schedules WHERE id = ?1", params![schedule_id])? == 0 {
+        bail!("Unknown schedule: {}", schedule_id);
+    }
+    Ok(())
+}
+
+/// Schedules due at `now`, moved on to their next run. A schedule whose last task is still
+/// queued or running is moved on without being returned, so slow jobs don't pile up.
+pub fn take_due_schedules(conn: &Connection, now: i64) -> Result<Vec<TaskSchedule>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM task_schedules WHERE next_run <= ?1", SCHEDULE_COLUMNS))?;
+    let rows = stmt.query_map(params![now], schedule_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut due = Vec::new();
+    for schedule in rows.into_iter().map(parse_schedule) {
+        let schedule = schedule?;
+        conn.execute(
+            "UPDATE task_schedules SET next_run = ?2 WHERE id = ?1",
+            params![schedule.id, next_run_after(schedule.next_run, schedule.interval_secs, now)],
+        )?;
+        let previous = schedule.last_task_id.as_deref().map(|id| get_task(conn, id)).transpose()?.flatten();
+        if previous.is_some_and(|task| !task.status.is_final()) {
+            continue;
+        }
+        due.push(schedule);
+    }
+    Ok(due)
+}
+
+fn record_scheduled_task(conn: &Connection, schedule_id: &str, task_id: &str) -> Result<()> {
+    conn.execute("UPDATE task_schedules SET last_task_id = ?2 WHERE id = ?1", params![schedule_id, task_id])?;
+    Ok(())
+}
+
+/// Running tasks, so cancelling one can stop it, and callers waiting on a task's outcome
+#[derive(Default)]
+pub struct TaskQueueState {
+    running: Mutex<HashMap<String, JoinHandle<()>>>,
+    waiters: Mutex<HashMap<String, oneshot::Sender<QueuedTask>>>,
+}
+
+impl TaskQueueState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Announce a status change, and hand a finished task to whoever is waiting on it
+fn emit_update(app: &AppHandle, task: &QueuedTask) {
+    if let Err(e) = app.emit(TASK_UPDATED_EVENT, task) {
+        error!("Failed to emit task update: {}", e);
+    }
+    if task.status.is_final() {
+        if let Some(waiter) = app.state::<TaskQueueState>().waiters.lock().unwrap().remove(&task.id) {
+            let _ = waiter.send(task.clone());
+        }
+    }
+}
+
+/// Queue a job. Tool jobs are marked idempotent from the tool's annotations, whatever the
+/// producer claimed; a tool not connected yet is taken as not idempotent.
+pub fn submit(app: &AppHandle, mut job: TaskJob, priority: i64, max_attempts: u32) -> Result<QueuedTask> {
+    if let TaskJob::Tool { tool, idempotent, .. } = &mut job {
+        *idempotent = resolve_route(&app.state::<MCPToolCatalog>(), tool).is_ok_and(|route| route.idempotent);
+    }
+    let conn = open_conversation_db(app)?;
+    let task = enqueue(&conn, &job, priority, max_attempts, now_ms())?;
+    emit_update(app, &task);
+    Ok(task)
+}
+
+/// Queue a job and wait until it completes, is dead-lettered or is cancelled
+pub async fn submit_and_wait(app: &AppHandle, job: TaskJob, priority: i64) -> Result<Value> {
+    let task = submit(app, job, priority, DEFAULT_MAX_ATTEMPTS)?;
+    let (sender, receiver) = oneshot::channel();
+    let waiters = &app.state::<TaskQueueState>().waiters;
+    waiters.lock().unwrap().insert(task.id.clone(), sender);
+
+    // The worker may have finished the task before the waiter was registered
+    let current = require_task(&open_conversation_db(app)?, &task.id)?;
+    let finished = if current.status.is_final() {
+        waiters.lock().unwrap().remove(&task.id);
+        current
+    } else {
+        receiver.await.map_err(|_| anyhow!("Task {} was dropped before it finished", task.id))?
+    };
+    match finished.status {
+        TaskStatus::Completed => Ok(finished.result.unwrap_or(Value::Null)),
+        TaskStatus::Cancelled => bail!("Task {} was cancelled", finished.id),
+        _ => bail!(
+            "Task {} failed after {} attempts: {}",
+            finished.id, finished.attempts, finished.last_error.unwrap_or_default()
+        ),
+    }
+}
+
+async fn execute_job(app: &AppHandle, job: &TaskJob) -> Result<Value> {
+    let state = app.state::<AIState>();
+    match job {
+        TaskJob::Tool { tool, arguments, agent_id, .. } => {
+            let catalog = app.state::<MCPToolCatalog>();
+            let route = resolve_route(&catalog, tool)?;
+            let permissions = match agent_id {
+                Some(agent_id) => load_agent_permissions(&state.storage, agent_id)?,
+                None => load_default_permissions(&state.storage)?,
+            };
+            if !permissions.allows_mcp_server(&route.server_id) {
+                bail!("MCP server {} is not permitted for agent {}", route.server_id, permissions.agent_id);
+            }
+            call_routed_tool(app, &state, &catalog, tool, arguments, agent_id.as_deref()).await
+        }
+        TaskJob::Llm { agent_id, provider, model, system, prompt } => {
+            let agent_id = agent_id.as_deref();
+            let provider = select_completion_provider(&state, provider.as_deref())?;
+            let mut guard = GuardrailScope::new(app, &state.storage, agent_id);
+            let text = complete_text(&state, &mut guard, &provider, model.as_deref(), system, prompt).await?;
+            Ok(serde_json::to_value(LlmJobOutput { text, guardrail_violations: guard.into_violations() })?)
+        }
+    }
+}
+
+/// Queue the jobs of due schedules
+fn run_due_schedules(app: &AppHandle) -> Result<()> {
+    let conn = open_conversation_db(app)?;
+    for schedule in take_due_schedules(&conn, now_ms())? {
+        match submit(app, schedule.job, schedule.priority, DEFAULT_MAX_ATTEMPTS) {
+            Ok(task) => record_scheduled_task(&conn, &schedule.id, &task.id)?,
+            Err(e) => warn!("Failed to queue the job of schedule {}: {}", schedule.id, e),
+        }
+    }
+    Ok(())
+}
+
+async fn run_task(app: &AppHandle, task: QueuedTask) {
+    let outcome = execute_job(app, &task.job).await;
+    let recorded = open_conversation_db(app).and_then(|conn| match &outcome {
+        Ok(result) => complete_task(&conn, &task.id, result),
+        Err(e) => fail_task(&conn, &task.id, &format!("{:#}", e), now_ms()),
+    });
+    match recorded {
+        Ok(Some(updated)) => {
+            if updated.status == TaskStatus::Dead {
+                warn!("Task {} failed {} times and was dead-lettered", updated.id, updated.attempts);
+                let message = format!(
+                    "Task {} gave up after {} attempts: {}",
+                    updated.id, updated.attempts, updated.last_error.as_deref().unwrap_or_default()
+                );
+                notify_quietly(app, NewNotification::new(NotificationSeverity::Error, "task_queue", "Background task failed", message)
+                    .with_payload(serde_json::json!({ "task_id": updated.id })));
+            }
+            emit_update(app, &updated);
+        }
+        Ok(None) => info!("Discarded the outcome of cancelled task {}", task.id),
+        Err(e) => error!("Failed to record the outcome of task {}: {}", task.id, e),
+    }
+}
+
+/// Run ready tasks in the background, up to `MAX_CONCURRENT_TASKS` at once, queueing the jobs
+/// of due schedules as it goes
+pub fn spawn_task_worker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        match open_conversation_db(&app).and_then(|conn| requeue_interrupted(&conn)) {
+            Ok(0) => {}
+            Ok(count) => info!("Requeued {} interrupted tasks", count),
+            Err(e) => error!("Failed to requeue interrupted tasks: {}", e),
+        }
+
+        let slots = Arc::new(Semaphore::new(MAX_CONCURRENT_TASKS));
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_due_schedules(&app) {
+                error!("Failed to run due schedules: {}", e);
+            }
+            while let Ok(permit) = slots.clone().try_acquire_owned() {
+                let task = match open_conversation_db(&app).and_then(|conn| claim_next(&conn, now_ms())) {
+                    Ok(Some(task)) => task,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Failed to claim a task: {}", e);
+                        break;
+                    }
+                };
+                emit_update(&app, &task);
+
+                // Hold the lock until the handle is stored, so the task can't remove itself first
+                let queue = app.state::<TaskQueueState>();
+                let mut running = queue.running.lock().unwrap();
+                let task_id = task.id.clone();
+                let handle = tauri::async_runtime::spawn({
+                    let app = app.clone();
+                    async move {
+                        let _permit = permit;
+                        let task_id = task.id.clone();
+                        run_task(&app, task).await;
+                        app.state::<TaskQueueState>().running.lock().unwrap().remove(&task_id);
+                    }
+                });
+                running.insert(task_id, handle);
+            }
+        }
+    });
+}
+
+// Tauri Commands
+
+#[command]
+pub async fn enqueue_task(
+    job: TaskJob,
+    priority: Option<i64>,
+    max_attempts: Option<u32>,
+    app_handle: AppHandle,
+) -> Result<QueuedTask, String> {
+    submit(&app_handle, job, priority.unwrap_or(0), max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS))
+        .map_err(|e| format!("Failed to enqueue task: {}", e))
+}
+
+#[command]
+pub async fn list_queued_tasks(
+    status: Option<TaskStatus>,
+    app_handle: AppHandle,
+) -> Result<Vec<QueuedTask>, String> {
+    let conn = open_conversation_db(&app_handle).map_err(|e| e.to_string())?;
+    list_tasks(&conn, status).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn cancel_task(
+    task_id: String,
+    app_handle: AppHandle,
+    queue: State<'_, TaskQueueState>,
+) -> Result<QueuedTask, String> {
+    let conn = open_conversation_db(&app_handle).map_err(|e| e.to_string())?;
+    let task = cancel(&conn, &task_id).map_err(|e| e.to_string())?;
+    if let Some(handle) = queue.running.lock().unwrap().remove(&task_id) {
+        handle.abort();
+    }
+    emit_update(&app_handle, &task);
+    Ok(task)
+}
+
+/// The dead-letter list: tasks that failed on every attempt
+#[command]
+pub async fn list_failed_tasks(app_handle: AppHandle) -> Result<Vec<QueuedTask>, String> {
+    let conn = open_conversation_db(&app_handle).map_err(|e| e.to_string())?;
+    list_tasks(&conn, Some(TaskStatus::Dead)).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn retry_failed_task(task_id: String, app_handle: AppHandle) -> Result<QueuedTask, String> {
+    let conn = open_conversation_db(&app_handle).map_err(|e| e.to_string())?;
+    let task = retry_dead(&conn, &task_id, now_ms()).map_err(|e| e.to_string())?;
+    emit_update(&app_handle, &task);
+    Ok(task)
+}
+
+/// Queue `job` every `interval_secs`, first at `first_run` (Unix milliseconds) or right away
+#[command]
+pub async fn schedule_task(
+    job: TaskJob,
+    interval_secs: u64,
+    priority: Option<i64>,
+    first_run: Option<i64>,
+    app_handle: AppHandle,
+) -> Result<TaskSchedule, String> {
+    let conn = open_conversation_db(&app_handle).map_err(|e| e.to_string())?;
+    add_schedule(&conn, &job, priority.unwrap_or(0), interval_secs, first_run.unwrap_or_else(now_ms))
+        .map_err(|e| format!("Failed to schedule task: {}", e))
+}
+
+#[command]
+pub async fn list_task_schedules(app_handle: AppHandle) -> Result<Vec<TaskSchedule>, String> {
+    let conn = open_conversation_db(&app_handle).map_err(|e| e.to_string())?;
+    list_schedules(&conn).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn delete_task_schedule(schedule_id: String, app_handle: AppHandle) -> Result<(), String> {
+    let conn = open_conversation_db(&app_handle).map_err(|e| e.to_string())?;
+    delete_schedule(&conn, &schedule_id).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+    use serde_json::json;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        conn
+    }
+
+    fn llm(prompt: &str) -> TaskJob {
+        TaskJob::Llm { agent_id: None, provider: None, model: None, system: String::new(), prompt: prompt.to_string() }
+    }
+
+    fn tool(name: &str, idempotent: bool) -> TaskJob {
+        TaskJob::Tool { tool: name.to_string(), arguments: json!({}), agent_id: None, idempotent }
+    }
+
+    #[test]
+    fn test_claims_by_priority_and_backs_off_to_dead_letter() {
+        let conn = setup();
+        let low = enqueue(&conn, &llm("low"), 0, 2, 1_000).unwrap();
+        let high = enqueue(&conn, &tool("fs__read", true), 5, 2, 1_000).unwrap();
+
+        let claimed = claim_next(&conn, 1_000).unwrap().unwrap();
+        assert_eq!(claimed.id, high.id);
+        assert_eq!((claimed.status, claimed.attempts), (TaskStatus::Running, 1));
+
+        let retried = fail_task(&conn, &high.id, "timeout", 1_000).unwrap().unwrap();
+        assert_eq!(retried.status, TaskStatus::Queued);
+        assert_eq!(retried.run_after, 1_000 + retry_delay_ms(1));
+
+        // The failed task waits out its backoff, so the lower priority one goes next
+        assert_eq!(claim_next(&conn, 1_500).unwrap().unwrap().id, low.id);
+        assert!(claim_next(&conn, 1_500).unwrap().is_none());
+        complete_task(&conn, &low.id, &json!("done")).unwrap().unwrap();
+
+        claim_next(&conn, 10_000).unwrap().unwrap();
+        let dead = fail_task(&conn, &high.id, "timeout again", 10_000).unwrap().unwrap();
+        assert_eq!(dead.status, TaskStatus::Dead);
+        assert_eq!(list_tasks(&conn, Some(TaskStatus::Dead)).unwrap().len(), 1);
+
+        let requeued = retry_dead(&conn, &high.id, 20_000).unwrap();
+        assert_eq!((requeued.status, requeued.attempts), (TaskStatus::Queued, 0));
+        assert!(retry_dead(&conn, &low.id, 20_000).is_err());
+    }
+
+    #[test]
+    fn test_cancelled_task_discards_outcome() {
+        let conn = setup();
+        let task = enqueue(&conn, &llm("hi"), 0, 3, 0).unwrap();
+        claim_next(&conn, 0).unwrap().unwrap();
+
+        assert_eq!(cancel(&conn, &task.id).unwrap().status, TaskStatus::Cancelled);
+        assert!(complete_task(&conn, &task.id, &json!("late")).unwrap().is_none());
+        assert!(fail_task(&conn, &task.id, "late", 0).unwrap().is_none());
+        assert!(cancel(&conn, &task.id).is_err());
+        assert_eq!(retry_delay_ms(30), MAX_RETRY_DELAY_MS);
+    }
+
+    #[test]
+    fn test_non_idempotent_tool_is_never_retried() {
+        let conn = setup();
+        let write = enqueue(&conn, &tool("fs__write", false), 0, 3, 0).unwrap();
+        claim_next(&conn, 0).unwrap().unwrap();
+        let dead = fail_task(&conn, &write.id, "connection reset", 0).unwrap().unwrap();
+        assert_eq!((dead.status, dead.attempts), (TaskStatus::Dead, 1));
+
+        // Left running by a previous instance: the read is requeued, the write dead-lettered
+        let read = enqueue(&conn, &tool("fs__read", true), 0, 3, 0).unwrap();
+        let write = enqueue(&conn, &tool("fs__write", false), 0, 3, 0).unwrap();
+        claim_next(&conn, 0).unwrap().unwrap();
+        claim_next(&conn, 0).unwrap().unwrap();
+        assert_eq!(requeue_interrupted(&conn).unwrap(), 1);
+        assert_eq!(get_task(&conn, &read.id).unwrap().unwrap().status, TaskStatus::Queued);
+        let interrupted = get_task(&conn, &write.id).unwrap().unwrap();
+        assert_eq!(interrupted.status, TaskStatus::Dead);
+        assert_eq!(interrupted.last_error.as_deref(), Some(INTERRUPTED_ERROR));
+    }
+
+    #[test]
+    fn test_schedules_skip_missed_runs_and_wait_for_their_last_task() {
+        let conn = setup();
+        assert!(add_schedule(&conn, &llm("too often"), 0, 1, 0).is_err());
+        let schedule = add_schedule(&conn, &llm("digest"), 0, 60, 1_000).unwrap();
+        assert!(take_due_schedules(&conn, 999).unwrap().is_empty());
+
+        let due = take_due_schedules(&conn, 1_000).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].job, llm("digest"));
+        let task = enqueue(&conn, &due[0].job, 0, 3, 1_000).unwrap();
+        record_scheduled_task(&conn, &schedule.id, &task.id).unwrap();
+        assert_eq!(list_schedules(&conn).unwrap()[0].next_run, 61_000);
+
+        // Three runs were missed; the schedule moves past them, but its last task is still queued
+        assert!(take_due_schedules(&conn, 200_000).unwrap().is_empty());
+        assert_eq!(list_schedules(&conn).unwrap()[0].next_run, 241_000);
+
+        claim_next(&conn, 241_000).unwrap().unwrap();
+        complete_task(&conn, &task.id, &json!("sent")).unwrap().unwrap();
+        assert_eq!(take_due_schedules(&conn, 241_000).unwrap().len(), 1);
+
+        delete_schedule(&conn, &schedule.id).unwrap();
+        assert!(list_schedules(&conn).unwrap().is_empty());
+        assert!(delete_schedule(&conn, &schedule.id).is_err());
+    }
+}
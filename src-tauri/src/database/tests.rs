@@ -323,9 +323,12 @@ mod memory_tests {
             tags: Some(vec!["urgent".to_string(), "work".to_string()]),
             embedding: Some(vec![0.1, 0.2, 0.3]),
             similarity_threshold: Some(0.8),
+            similarity_metric: SimilarityMetric::Cosine,
             limit: Some(50),
             offset: Some(0),
             time_range: None,
+            tiers: None,
+            fusion: None,
         };
         
         assert_eq!(query.agent_id, Some("test_agent".to_string()));
@@ -345,6 +348,7 @@ mod memory_tests {
             memory,
             similarity_score: Some(0.92),
             relevance_rank: 1,
+            fusion_scores: None,
         };
         
         assert_eq!(search_result.similarity_score, Some(0.92));
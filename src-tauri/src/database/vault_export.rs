@@ -0,0 +1,392 @@
+use super::memory::*;
+use super::simple_commands::MemoryState;
+use crate::ai::{enforce_path_policy, resolve_in_workspace, AIState, PathAccess};
+use crate::validation::MemoryValidator;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use tauri::State;
+use tracing::{info, warn};
+
+/// Records what the last sync wrote, relative to the agent's folder in the vault
+const MANIFEST_FILE: &str = ".banshee-sync.json";
+
+const MEMORIES_DIR: &str = "Memories";
+const KNOWLEDGE_DIR: &str = "Knowledge";
+
+/// Longest title kept in a note's file name, before the id suffix
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Words of a memory's content used as its note title
+const MEMORY_TITLE_WORDS: usize = 8;
+
+/// A rendered note, addressed relative to the agent's folder
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultNote {
+    pub relative_path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SyncManifest {
+    /// Relative path to the hash of the content last written there
+    files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct VaultSyncSummary {
+    pub vault_path: String,
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    /// Notes edited in the vault since the last sync, left as the user wrote them
+    pub skipped_modified: Vec<String>,
+}
+
+fn content_hash(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// File name stem Obsidian can store and link to: characters it rejects are replaced and
+/// the id prefix keeps notes with the same title apart
+fn note_stem(title: &str, id: &str) -> String {
+    let cleaned: String = title.chars()
+        .map(|c| if "\\/:*?\"<>|#^[]".contains(c) || c.is_control() { '-' } else { c })
+        .take(MAX_TITLE_CHARS)
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let short_id: String = id.chars().take(8).collect();
+    if cleaned.is_empty() {
+        short_id
+    } else {
+        format!("{} ({})", cleaned, short_id)
+    }
+}
+
+fn wikilink(stem: &str, label: &str) -> String {
+    let label: String = label.chars().map(|c| if "|[]".contains(c) { '-' } else { c }).collect();
+    format!("[[{}|{}]]", stem, label)
+}
+
+/// JSON strings and arrays are valid YAML scalars, so values need no YAML escaping of their own
+fn yaml_value<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".to_string())
+}
+
+fn front_matter(fields: &[(&str, String)]) -> String {
+    let mut out = String::from("---\n");
+    for (key, value) in fields {
+        out.push_str(&format!("{}: {}\n", key, value));
+    }
+    out.push_str("---\n\n");
+    out
+}
+
+fn memory_stem(memory: &AgentMemory) -> String {
+    let title = memory.content.split_whitespace().take(MEMORY_TITLE_WORDS).collect::<Vec<_>>().join(" ");
+    note_stem(&title, &memory.id)
+}
+
+/// Id of the memory a graph node may have been created from, using the same links as purging
+fn node_memory_id(node: &KnowledgeNode) -> &str {
+    node.properties.get("memory_id").map(String::as_str)
+        .or_else(|| node.id.strip_prefix("memory_"))
+        .unwrap_or(&node.id)
+}
+
+/// Render memories and graph nodes as interlinked notes. Output is deterministic so
+/// unchanged items produce identical notes and can be skipped by the sync.
+pub fn render_vault(agent_id: &str, memories: &[AgentMemory], nodes: &[KnowledgeNode], edges: &[KnowledgeEdge]) -> Vec<VaultNote> {
+    let memory_stems: HashMap<&str, String> = memories.iter().map(|m| (m.id.as_str(), memory_stem(m))).collect();
+    let node_stems: HashMap<&str, String> = nodes.iter().map(|n| (n.id.as_str(), note_stem(&n.name, &n.id))).collect();
+    let node_names: HashMap<&str, &str> = nodes.iter().map(|n| (n.id.as_str(), n.name.as_str())).collect();
+
+    let mut notes = Vec::with_capacity(memories.len() + nodes.len());
+    for memory in memories {
+        let metadata: BTreeMap<&String, &String> = memory.metadata.iter().collect();
+        let mut content = front_matter(&[
+            ("id", yaml_value(&memory.id)),
+            ("agent_id", yaml_value(agent_id)),
+            ("memory_type", yaml_value(&memory.memory_type.to_string())),
+            ("tags", yaml_value(&memory.tags)),
+            ("relevance_score", memory.relevance_score.to_string()),
+            ("created_at", yaml_value(&memory.created_at.to_rfc3339())),
+            ("updated_at", yaml_value(&memory.updated_at.to_rfc3339())),
+            ("metadata", yaml_value(&metadata)),
+        ]);
+        content.push_str(&memory.content);
+        content.push('\n');
+        notes.push(VaultNote {
+            relative_path: format!("{}/{}.md", MEMORIES_DIR, memory_stems[memory.id.as_str()]),
+            content,
+        });
+    }
+
+    let mut outgoing: HashMap<&str, Vec<&KnowledgeEdge>> = HashMap::new();
+    for edge in edges {
+        outgoing.entry(edge.from_node.as_str()).or_default().push(edge);
+    }
+
+    for node in nodes {
+        let properties: BTreeMap<&String, &String> = node.properties.iter().collect();
+        let mut content = front_matter(&[
+            ("id", yaml_value(&node.id)),
+            ("node_type", yaml_value(&format!("{:?}", node.node_type))),
+            ("created_at", yaml_value(&node.created_at.to_rfc3339())),
+            ("updated_at", yaml_value(&node.updated_at.to_rfc3339())),
+            ("properties", yaml_value(&properties)),
+        ]);
+        content.push_str(&format!("# {}\n", node.name));
+
+        if let Some(stem) = memory_stems.get(node_memory_id(node)) {
+            content.push_str(&format!("\nSource memory: [[{}]]\n", stem));
+        }
+
+        let mut links: Vec<String> = outgoing.get(node.id.as_str()).into_iter().flatten()
+            .map(|edge| {
+                let target = match node_stems.get(edge.to_node.as_str()) {
+                    Some(stem) => wikilink(stem, node_names[edge.to_node.as_str()]),
+                    // Nodes owned by other agents aren't in this vault
+                    None => format!("`{}`", edge.to_node),
+                };
+                format!("- {:?} → {} (weight {:.2})", edge.relationship_type, target, edge.weight)
+            })
+            .collect();
+        if !links.is_empty() {
+            links.sort();
+            content.push_str("\n## Relationships\n\n");
+            content.push_str(&links.join("\n"));
+            content.push('\n');
+        }
+
+        notes.push(VaultNote {
+            relative_path: format!("{}/{}.md", KNOWLEDGE_DIR, node_stems[node.id.as_str()]),
+            content,
+        });
+    }
+    notes
+}
+
+/// Resolve a manifest or note path under the canonical vault root. The manifest is a file
+/// in the user's vault, so entries that are absolute, contain `..` or lead out of the root
+/// through a symlink are refused rather than read or deleted.
+fn vault_file(root: &Path, relative: &str) -> Option<PathBuf> {
+    let plain = Path::new(relative).components().all(|c| matches!(c, Component::Normal(_)));
+    if !plain || relative.is_empty() {
+        warn!("Ignoring vault path outside the agent folder: {}", relative);
+        return None;
+    }
+    match resolve_in_workspace(root, relative) {
+        Ok(path) => Some(path),
+        Err(e) => {
+            warn!("Ignoring vault path {}: {}", relative, e);
+            None
+        }
+    }
+}
+
+/// Bring the notes under `root` in line with `notes`, touching only files whose content
+/// changed. Files the previous sync wrote are removed once their item is gone; any that
+/// were edited in the vault since are left alone and reported instead.
+pub fn sync_vault(root: &Path, notes: &[VaultNote]) -> Result<VaultSyncSummary> {
+    std::fs::create_dir_all(root)?;
+    let root = &root.canonicalize()?;
+    let manifest_path = root.join(MANIFEST_FILE);
+    let previous: SyncManifest = match std::fs::read_to_string(&manifest_path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            warn!("Ignoring unreadable vault sync manifest {}: {}", manifest_path.display(), e);
+            SyncManifest::default()
+        }),
+        Err(_) => SyncManifest::default(),
+    };
+    let disk_hash = |path: &Path| std::fs::read(path).ok().map(|bytes| content_hash(&bytes));
+
+    let mut summary = VaultSyncSummary { vault_path: root.to_string_lossy().to_string(), ..Default::default() };
+    let mut manifest = SyncManifest::default();
+    for note in notes {
+        let Some(path) = vault_file(root, &note.relative_path) else {
+            continue;
+        };
+        let hash = content_hash(note.content.as_bytes());
+        let on_disk = disk_hash(&path);
+        let last_written = previous.files.get(&note.relative_path);
+
+        if on_disk.is_some() && on_disk.as_ref() != last_written {
+            summary.skipped_modified.push(note.relative_path.clone());
+            if let Some(last_written) = last_written {
+                manifest.files.insert(note.relative_path.clone(), last_written.clone());
+            }
+            continue;
+        }
+        if on_disk.as_ref() == Some(&hash) {
+            summary.unchanged += 1;
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, &note.content)?;
+            summary.written += 1;
+        }
+        manifest.files.insert(note.relative_path.clone(), hash);
+    }
+
+    let current: HashSet<&str> = notes.iter().map(|n| n.relative_path.as_str()).collect();
+    for (relative, last_written) in &previous.files {
+        if current.contains(relative.as_str()) {
+            continue;
+        }
+        let Some(path) = vault_file(root, relative) else {
+            continue;
+        };
+        match disk_hash(&path) {
+            Some(hash) if hash == *last_written => {
+                std::fs::remove_file(&path)?;
+                summary.removed += 1;
+            }
+            Some(_) => summary.skipped_modified.push(relative.clone()),
+            None => {}
+        }
+    }
+
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    Ok(summary)
+}
+
+/// Write an agent's memories and knowledge graph into an Obsidian vault as Markdown notes,
+/// linked by `[[wikilinks]]`. Re-running only rewrites notes whose items changed.
+#[tauri::command]
+pub async fn export_memory_vault(
+    agent_id: String,
+    vault_path: String,
+    state: State<'_, MemoryState>,
+    ai_state: State<'_, AIState>,
+) -> Result<VaultSyncSummary, String> {
+    info!("Syncing memory vault for agent {} into {}", agent_id, vault_path);
+
+    MemoryValidator::validate_agent_id(&agent_id).map_err(|e| e.to_string())?;
+
+    let security_middleware = state.get_security_middleware();
+    let validation_result = security_middleware.validate_request(
+        "backup_operations",
+        &[agent_id],
+        std::slice::from_ref(&vault_path)
+    ).await?;
+    let sanitized_agent_id = &validation_result.sanitized_inputs[0];
+
+    let manager = state.get_or_create_manager(sanitized_agent_id.clone())?;
+    let memories: Vec<AgentMemory> = manager.search_memories(&MemoryQuery {
+        agent_id: Some(sanitized_agent_id.clone()),
+        memory_types: None,
+        content_search: None,
+        tags: None,
+        embedding: None,
+        similarity_threshold: None,
+        similarity_metric: SimilarityMetric::default(),
+        limit: None,
+        offset: None,
+        time_range: None,
+        tiers: None,
+        fusion: None,
+    }).map_err(|e| format!("Failed to load memories: {}", e))?
+        .into_iter()
+        .map(|result| result.memory)
+        .collect();
+    let (nodes, edges) = manager.load_knowledge_graph()
+        .map_err(|e| format!("Failed to load knowledge graph: {}", e))?;
+
+    let notes = render_vault(sanitized_agent_id, &memories, &nodes, &edges);
+    let largest_note = notes.iter().map(|n| n.content.len() as u64).max().unwrap_or(0);
+    let vault = enforce_path_policy(&ai_state.storage, None, &vault_path, PathAccess::Write { size: largest_note })?;
+    let root = vault.join(sanitized_agent_id);
+    let total_bytes: u64 = notes.iter().map(|n| n.content.len() as u64).sum();
+    std::fs::create_dir_all(&root).map_err(|e| format!("Failed to create vault folder: {}", e))?;
+    crate::ai::ensure_disk_space(&root, total_bytes, "memory vault export")
+        .map_err(|e| e.to_string())?;
+
+    let summary = sync_vault(&root, &notes).map_err(|e| format!("Failed to sync memory vault: {}", e))?;
+    info!(
+        "Vault sync for {}: {} written, {} unchanged, {} removed, {} edited in vault",
+        sanitized_agent_id, summary.written, summary.unchanged, summary.removed, summary.skipped_modified.len()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (Vec<AgentMemory>, Vec<KnowledgeNode>, Vec<KnowledgeEdge>) {
+        let mut memory = AgentMemory::new("agent".to_string(), MemoryType::Learning, "Cargo builds Rust crates: fast".to_string());
+        memory.tags = vec!["rust".to_string()];
+        let mut rust = KnowledgeNode::new(NodeType::Concept, "Rust [lang]".to_string());
+        rust.properties.insert("memory_id".to_string(), memory.id.clone());
+        let cargo = KnowledgeNode::new(NodeType::Tool, "Cargo".to_string());
+        let edge = KnowledgeEdge::new(cargo.id.clone(), rust.id.clone(), RelationshipType::DependsOn);
+        (vec![memory], vec![rust, cargo], vec![edge])
+    }
+
+    #[test]
+    fn test_render_links_notes() {
+        let (memories, nodes, edges) = fixture();
+        let notes = render_vault("agent", &memories, &nodes, &edges);
+        assert_eq!(notes.len(), 3);
+
+        let memory_note = &notes[0];
+        assert!(memory_note.relative_path.starts_with("Memories/Cargo builds Rust crates- fast ("));
+        assert!(memory_note.content.starts_with("---\nid: "));
+        assert!(memory_note.content.contains("tags: [\"rust\"]\n"));
+
+        let rust_stem = note_stem("Rust [lang]", &nodes[0].id);
+        assert!(notes[1].content.contains(&format!("Source memory: [[{}]]", memory_note.relative_path[9..].trim_end_matches(".md"))));
+        assert!(notes[2].content.contains(&format!("- DependsOn → [[{}|Rust -lang-]]", rust_stem)));
+        assert_eq!(render_vault("agent", &memories, &nodes, &edges), notes);
+    }
+
+    #[test]
+    fn test_sync_is_incremental_and_keeps_user_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let (memories, mut nodes, edges) = fixture();
+        let notes = render_vault("agent", &memories, &nodes, &edges);
+        assert_eq!(sync_vault(dir.path(), &notes).unwrap().written, 3);
+
+        let again = sync_vault(dir.path(), &notes).unwrap();
+        assert_eq!((again.written, again.unchanged), (0, 3));
+
+        // The user annotates the memory note, the Cargo node is renamed and Rust is deleted
+        std::fs::write(dir.path().join(&notes[0].relative_path), "my notes").unwrap();
+        nodes[1].name = "Cargo build tool".to_string();
+        nodes.remove(0);
+        let summary = sync_vault(dir.path(), &render_vault("agent", &memories, &nodes, &[])).unwrap();
+        assert_eq!(summary.written, 1);
+        assert_eq!(summary.removed, 2);
+        assert_eq!(summary.skipped_modified, vec![notes[0].relative_path.clone()]);
+        assert_eq!(std::fs::read_to_string(dir.path().join(&notes[0].relative_path)).unwrap(), "my notes");
+        assert!(!dir.path().join(&notes[1].relative_path).exists());
+    }
+
+    #[test]
+    fn test_sync_never_touches_paths_outside_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("agent");
+        let outside = dir.path().join("outside.md");
+        std::fs::write(&outside, "keep").unwrap();
+        let hash = content_hash(b"keep");
+
+        std::fs::create_dir_all(&root).unwrap();
+        let manifest = SyncManifest {
+            files: [("../outside.md", &hash), (outside.to_str().unwrap(), &hash)]
+                .into_iter()
+                .map(|(path, hash)| (path.to_string(), hash.clone()))
+                .collect(),
+        };
+        std::fs::write(root.join(MANIFEST_FILE), serde_json::to_string(&manifest).unwrap()).unwrap();
+        let escaping = VaultNote { relative_path: "../escaped.md".to_string(), content: "x".to_string() };
+
+        let summary = sync_vault(&root, &[escaping]).unwrap();
+        assert_eq!((summary.written, summary.removed), (0, 0));
+        assert!(outside.exists());
+        assert!(!dir.path().join("escaped.md").exists());
+    }
+}
@@ -0,0 +1,262 @@
+//! Project workspaces. A workspace names a root directory, the tools agents may call in it and
+//! the agents it opens with. The active one scopes file tools through its path policy, gives MCP
+//! servers started without a workspace their roots, and keeps agent memories apart.
+
+use super::{open_conversation_db, open_db};
+use super::simple_commands::MemoryState;
+use crate::ai::{active_workspace, save_path_policy, set_active_workspace, AIState, PathPolicy, StorageManager, DEFAULT_WORKSPACE};
+use anyhow::{anyhow, bail, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use tracing::{error, info, warn};
+
+/// Event emitted with the new active `Workspace` after a switch
+pub const WORKSPACE_SWITCHED_EVENT: &str = "workspace_switched";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub root_path: String,
+    /// Tools agents may call while the workspace is active; empty allows every tool
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Agents opened when the workspace becomes active
+    #[serde(default)]
+    pub default_agents: Vec<String>,
+    #[serde(default)]
+    pub active: bool,
+}
+
+impl Workspace {
+    pub fn allows_tool(&self, name: &str) -> bool {
+        self.allowed_tools.is_empty() || self.allowed_tools.iter().any(|tool| tool == name)
+    }
+
+    /// Default path policy confined to the workspace root
+    pub fn path_policy(&self) -> PathPolicy {
+        PathPolicy {
+            allowed_roots: vec![self.root_path.clone()],
+            ..PathPolicy::default_for(&self.id)
+        }
+    }
+}
+
+/// The workspace in use before any other is created, covering the home directory
+pub fn default_workspace() -> Workspace {
+    Workspace {
+        id: DEFAULT_WORKSPACE.to_string(),
+        name: "Default".to_string(),
+        root_path: dirs::home_dir().map(|home| home.to_string_lossy().to_string()).unwrap_or_default(),
+        allowed_tools: Vec::new(),
+        default_agents: Vec::new(),
+        active: false,
+    }
+}
+
+/// Id for a new workspace, safe to use as a directory name
+fn workspace_id(name: &str) -> String {
+    name.trim().chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '-' })
+        .take(40)
+        .collect()
+}
+
+fn row_to_workspace(row: &rusqlite::Row) -> rusqlite::Result<(String, String, String, String, String)> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+}
+
+fn parse_workspace(
+    (id, name, root_path, allowed_tools, default_agents): (String, String, String, String, String),
+) -> Result<Workspace> {
+    Ok(Workspace {
+        allowed_tools: serde_json::from_str(&allowed_tools)?,
+        default_agents: serde_json::from_str(&default_agents)?,
+        id,
+        name,
+        root_path,
+        active: false,
+    })
+}
+
+/// The default workspace followed by stored ones
+pub fn load_workspaces(conn: &Connection) -> Result<Vec<Workspace>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, root_path, allowed_tools, default_agents FROM workspaces ORDER BY name"
+    )?;
+    let stored = stmt.query_map([], row_to_workspace)?
+        .map(|row| parse_workspace(row?))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(std::iter::once(default_workspace()).chain(stored).collect())
+}
+
+pub fn find_workspace(conn: &Connection, workspace_id: &str) -> Result<Workspace> {
+    if workspace_id == DEFAULT_WORKSPACE {
+        return Ok(default_workspace());
+    }
+    let row = conn.query_row(
+        "SELECT id, name, root_path, allowed_tools, default_agents FROM workspaces WHERE id = ?1",
+        params![workspace_id],
+        row_to_workspace,
+    ).optional()?
+        .ok_or_else(|| anyhow!("Unknown workspace: {}", workspace_id))?;
+    parse_workspace(row)
+}
+
+/// Store a new workspace; its root must be an existing directory and is stored canonicalized
+pub fn insert_workspace(conn: &Connection, workspace: &Workspace) -> Result<Workspace> {
+    if workspace.name.trim().is_empty() || workspace.id.is_empty() {
+        bail!("Workspaces need a name");
+    }
+    if workspace.id == DEFAULT_WORKSPACE {
+        bail!("'{}' is the built-in workspace", DEFAULT_WORKSPACE);
+    }
+    let root = std::path::Path::new(&workspace.root_path).canonicalize()
+        .map_err(|e| anyhow!("Workspace root {} is not accessible: {}", workspace.root_path, e))?;
+    if !root.is_dir() {
+        bail!("Workspace root {} is not a directory", workspace.root_path);
+    }
+    let workspace = Workspace { root_path: root.to_string_lossy().to_string(), ..workspace.clone() };
+
+    let inserted = conn.execute(
+        "INSERT INTO workspaces (id, name, root_path, allowed_tools, default_agents) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(id) DO NOTHING",
+        params![
+            workspace.id,
+            workspace.name.trim(),
+            workspace.root_path,
+            serde_json::to_string(&workspace.allowed_tools)?,
+            serde_json::to_string(&workspace.default_agents)?,
+        ],
+    )?;
+    if inserted == 0 {
+        bail!("Workspace {} already exists", workspace.id);
+    }
+    Ok(workspace)
+}
+
+/// Refuse a tool call the active workspace doesn't allow; `names` are the names the tool goes by
+pub fn ensure_tool_allowed(app_handle: &AppHandle, storage: &StorageManager, names: &[&str]) -> Result<()> {
+    let workspace_id = active_workspace(storage);
+    if workspace_id == DEFAULT_WORKSPACE {
+        return Ok(());
+    }
+    let workspace = find_workspace(&open_conversation_db(app_handle)?, &workspace_id)?;
+    if !names.iter().any(|name| workspace.allows_tool(name)) {
+        bail!("Tool {} is not allowed in workspace {}", names.first().unwrap_or(&""), workspace.name);
+    }
+    Ok(())
+}
+
+// Tauri Commands
+
+/// Create a workspace rooted at `root_path`; file tools in it are confined to that directory
+#[tauri::command]
+pub async fn create_workspace(
+    name: String,
+    root_path: String,
+    allowed_tools: Option<Vec<String>>,
+    default_agents: Option<Vec<String>>,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<Workspace, String> {
+    let workspace = Workspace {
+        id: workspace_id(&name),
+        name,
+        root_path,
+        allowed_tools: allowed_tools.unwrap_or_default(),
+        default_agents: default_agents.unwrap_or_default(),
+        active: false,
+    };
+    let workspace = insert_workspace(&open_db(&app_handle)?, &workspace)
+        .map_err(|e| format!("Failed to create workspace: {}", e))?;
+
+    save_path_policy(&state.storage, &workspace.path_policy())
+        .map_err(|e| format!("Created workspace {} but failed to save its path policy: {}", workspace.id, e))?;
+
+    info!("Created workspace {} at {}", workspace.id, workspace.root_path);
+    Ok(workspace)
+}
+
+/// Make a workspace active, scoping file tools, new MCP servers and agent memory to it
+#[tauri::command]
+pub async fn switch_workspace(
+    workspace_id: String,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+    memory_state: State<'_, MemoryState>,
+) -> Result<Workspace, String> {
+    let mut workspace = find_workspace(&open_db(&app_handle)?, &workspace_id).map_err(|e| e.to_string())?;
+    set_active_workspace(&state.storage, &workspace.id)
+        .map_err(|e| format!("Failed to switch workspace: {}", e))?;
+    memory_state.set_workspace(&workspace.id);
+    workspace.active = true;
+
+    for agent_id in &workspace.default_agents {
+        if let Err(e) = memory_state.get_or_create_manager(agent_id.clone()) {
+            warn!("Failed to open memory for default agent {}: {}", agent_id, e);
+        }
+    }
+
+    if let Err(e) = app_handle.emit(WORKSPACE_SWITCHED_EVENT, &workspace) {
+        error!("Failed to emit workspace switch: {}", e);
+    }
+    info!("Switched to workspace {}", workspace.id);
+    Ok(workspace)
+}
+
+#[tauri::command]
+pub async fn list_workspaces(
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<Vec<Workspace>, String> {
+    let active = active_workspace(&state.storage);
+    let mut workspaces = load_workspaces(&open_db(&app_handle)?)
+        .map_err(|e| format!("Failed to list workspaces: {}", e))?;
+    for workspace in &mut workspaces {
+        workspace.active = workspace.id == active;
+    }
+    Ok(workspaces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    #[test]
+    fn test_create_and_list_workspaces() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let workspace = Workspace {
+            id: workspace_id("My Project!"),
+            name: "My Project!".to_string(),
+            root_path: dir.path().join(".").to_string_lossy().to_string(),
+            allowed_tools: vec!["read_file".to_string()],
+            default_agents: vec!["coder".to_string()],
+            active: false,
+        };
+        assert_eq!(workspace.id, "my-project-");
+        let stored = insert_workspace(&conn, &workspace).unwrap();
+        assert_eq!(stored.root_path, dir.path().canonicalize().unwrap().to_string_lossy());
+        assert!(insert_workspace(&conn, &workspace).is_err());
+        assert!(insert_workspace(&conn, &Workspace { id: DEFAULT_WORKSPACE.to_string(), ..workspace.clone() }).is_err());
+        assert!(insert_workspace(&conn, &Workspace { id: "missing".to_string(), root_path: "/no/such/dir".to_string(), ..workspace.clone() }).is_err());
+
+        let workspaces = load_workspaces(&conn).unwrap();
+        assert_eq!(workspaces.len(), 2);
+        assert_eq!(workspaces[0].id, DEFAULT_WORKSPACE);
+        assert_eq!(find_workspace(&conn, &workspace.id).unwrap(), stored);
+        assert!(find_workspace(&conn, "unknown").is_err());
+
+        assert!(stored.allows_tool("read_file"));
+        assert!(!stored.allows_tool("run_command"));
+        assert!(default_workspace().allows_tool("run_command"));
+        let policy = stored.path_policy();
+        assert!(policy.check(&dir.path().join("notes.txt").to_string_lossy(), crate::ai::PathAccess::Read).is_ok());
+        assert!(policy.check(&std::env::temp_dir().to_string_lossy(), crate::ai::PathAccess::List).is_err());
+    }
+}
@@ -1,5 +1,4 @@
-use tracing::{info, error, Level};
-use tracing_subscriber;
+use tracing::{info, error};
 
 mod ai;
 mod mcp;
@@ -7,31 +6,86 @@ mod commands;
 mod database;
 mod validation;
 mod app_state;
+mod logging;
+mod operations;
+mod shutdown;
 
 use app_state::AppState;
+use logging::{setup_logging, set_log_level, get_log_levels, get_log_file_settings, set_log_file_settings, get_recent_logs};
+use operations::{OperationRegistry, get_operation_status, list_operations, cancel_operation};
 
 use ai::{
-    AIState,
+    AIState, active_workspace,
     store_api_key_command, get_api_key_command, remove_api_key_command, list_providers_command,
-    read_file_command, write_file_command, list_files_command,
-    execute_command, http_request_command, show_notification_command,
-    set_setting_command, get_setting_command, get_rate_limit_stats,
+    read_file_command, write_file_command, list_files_command, get_path_policy, set_path_policy,
+    execute_command, execute_command_sandboxed, http_request_command, show_notification_command,
+    get_http_cache_stats, list_http_cache_entries, clear_http_cache,
+    set_setting_command, get_setting_command, get_rate_limit_stats, get_rate_limit_config,
+    set_rate_limit_config, acquire_rate_limit, rotate_encryption_keys, get_encryption_key_status,
+    get_http_client_config, set_http_client_config,
     // Secure commands
-    create_session, generate_csrf_token, execute_command_secure,
+    create_session, generate_csrf_token, set_session_workspace, set_session_agent, execute_command_secure,
     read_file_tool_secure, write_file_tool_secure, list_files_tool_secure,
     execute_agent_tool_secure, store_api_key_secure, get_api_key_secure,
     init_secure_session, init_security_managers, SecureSession,
+    set_agent_permissions, get_agent_permissions,
+    add_whitelisted_command, remove_whitelisted_command, list_whitelisted_commands,
+    // Offline summarization
+    get_local_summarizer_status, download_summarization_model, summarize_locally,
+    // File watching
+    FileWatcherState, watch_path_command, unwatch_path_command,
+    // Workspace search
+    search_workspace_command,
+    // Diff and patch tools
+    compute_diff_command, apply_patch_command,
+    // Environment context
+    get_environment_context,
+    // Chunked file streaming
+    FileStreamState, read_file_chunk, write_file_stream_start, write_file_stream_append,
+    write_file_stream_finish, write_file_stream_abort,
+    // Budget-aware run planning
+    RunPlannerState, plan_agent_run, record_run_step, halt_agent_run,
+    // WebSocket client
+    WebSocketState, websocket_connect_command, websocket_send_command, websocket_close_command,
+    // Notification actions
+    NotificationState, get_notification_capabilities, notification_reply_command,
+    // Response validation
+    validate_assistant_response, get_message_repairs,
+    // Multi-agent workflows
+    list_agent_workflows, register_agent_workflow, delete_agent_workflow, run_agent_workflow,
+    // Structured output
+    generate_structured,
+    // Guardrails
+    list_guardrail_rules, save_guardrail_rule, delete_guardrail_rule, set_agent_guardrail, check_guardrails,
+    // Secret redaction
+    set_secret_patterns, get_secret_patterns,
 };
 
 use mcp::{
-    start_mcp_process, stop_mcp_process, send_mcp_message,
+    start_mcp_process, stop_mcp_process, send_mcp_message, list_mcp_processes,
     connect_local_mcp, disconnect_local_mcp, send_local_mcp_message,
     get_agent_configs, get_conversation_history, get_system_status, list_workspace_files,
     execute_agent_tool, read_file_tool, write_file_tool, list_files_tool, execute_command_tool,
-    MCPProcessInfo,
+    MCPProcessMap,
     // OAuth commands
     store_mcp_oauth_token, get_mcp_oauth_tokens, delete_mcp_oauth_token,
     clear_all_mcp_oauth_tokens, encrypt_data, decrypt_data, open_oauth_browser,
+    OAuthFlowState, start_mcp_oauth_flow, refresh_mcp_oauth_token, resume_oauth_refresh,
+    start_device_code_flow, cancel_device_code_flow,
+    // MCP registry
+    add_mcp_server, update_mcp_server, remove_mcp_server, export_mcp_registry, import_mcp_registry,
+    import_mcp_servers_from_config,
+    // MCP prompts
+    list_mcp_prompts, run_mcp_prompt,
+    // MCP tool call audit
+    record_mcp_tool_call, list_mcp_tool_calls, replay_mcp_tool_call,
+    // MCP process resource limits
+    spawn_resource_monitor, get_mcp_resource_limits, set_mcp_resource_limits,
+    // MCP tool routing
+    get_routed_mcp_tools, call_routed_mcp_tool, fan_out_mcp_tool_call,
+    // MCP tool catalog
+    MCPToolCatalog, refresh_mcp_tool_catalog, handle_mcp_notification, register_plugin_tools,
+    get_all_available_tools,
 };
 
 use commands::{
@@ -44,26 +98,111 @@ use commands::{
 use database::{
     init_database, save_conversation, save_message, get_conversations,
     get_messages, search_conversations, delete_conversation,
+    conversation_list::{set_conversation_tags, archive_conversation, pin_conversation},
+    conversation_merge::{find_duplicate_conversations, merge_conversations},
+    message_revisions::{edit_message, regenerate_from},
+    conversation_export::export_conversation,
+    artifacts::{save_artifact, get_artifact, list_conversation_artifacts, collect_artifact_garbage},
+    agent_sessions::AgentSessionManager,
     // Agent memory system
     simple_commands::{
         MemoryState, init_agent_memory, save_agent_memory, get_agent_memory,
-        search_agent_memories, save_shared_knowledge, add_knowledge_graph_node, 
-        add_knowledge_graph_edge, backup_agent_memories, search_shared_knowledge,
+        search_agent_memories, list_agent_memories, update_agent_memory, delete_agent_memory, pin_memory, unpin_memory,
+        save_shared_knowledge, create_shared_knowledge, add_knowledge_graph_node, 
+        add_knowledge_graph_edge, backup_agent_memories, start_memory_backup, search_shared_knowledge,
         get_knowledge_graph,
         // Neural embedding commands
-        init_neural_embedding_service, generate_neural_embedding, generate_neural_embeddings_batch,
-        search_neural_similar, find_similar_memories, train_neural_networks,
-        get_neural_embedding_stats, clear_neural_embedding_cache,
+        init_neural_embedding_service, set_agent_embedding_provider, generate_neural_embedding, generate_neural_embeddings_batch,
+        search_neural_similar, find_similar_memories, train_neural_networks, reset_neural_models,
+        get_neural_embedding_stats, clear_embedding_cache,
+        // Edge suggestion review
+        list_edge_suggestions, accept_edge_suggestion, reject_edge_suggestion,
+        // Relationship feedback
+        confirm_relationship, reject_relationship,
     },
     // Knowledge graph system
     graph_commands::{
         create_graph_node, get_graph_node, update_graph_node, delete_graph_node,
         create_graph_edge, get_graph_edge, update_graph_edge, delete_graph_edge,
-        get_graph_view, find_graph_path, get_graph_neighbors, get_graph_stats,
+        get_graph_view, find_graph_path, get_graph_neighbors, get_subgraph, get_graph_stats,
         find_graph_clusters, optimize_graph,
     },
+    graph_query::ask_knowledge_graph,
+    graph_history::{list_graph_snapshots, spawn_graph_snapshot_scheduler},
+    graph_export::{export_knowledge_graph, import_knowledge_graph},
+    graph_batch::{create_graph_nodes_batch, create_graph_edges_batch},
+    graph_predictions::{predict_graph_relationships, accept_predicted_relationship},
+    shared_knowledge::{get_shared_knowledge_conflicts, list_contradicting_knowledge, review_knowledge_contradiction},
+    memory_sharing::{grant_memory_access, revoke_memory_access, query_foreign_memories},
+    memory_transfer::{export_agent_memories, import_agent_memories},
+    vault_export::export_memory_vault,
+    backup_restore::{verify_backup, restore_agent_memories},
+    migrations::get_database_schema_status,
+    startup_health::{run_startup_checks, StartupHealthState, get_startup_health_report},
+    // Embedding model migration
+    embedding_migration::{
+        start_embedding_migration, get_migration_status, validate_migration_results,
+        rollback_migration, get_migration_stats,
+    },
+    // Background neural training
+    embedding_training::{configure_embedding_training, get_training_status, spawn_training_scheduler},
+    accelerated_inference::{get_inference_accelerator, set_force_cpu_inference},
+    embedding_quantization::quantize_stored_embeddings,
+    embedding_evaluation::{evaluate_embeddings, list_embedding_evaluations},
+    // Memory injection budget
+    memory_budget::{
+        set_memory_injection_budget, get_memory_injection_budget,
+        retrieve_memories_for_prompt, get_last_memory_injection_report,
+    },
+    // Memory deduplication
+    memory_dedup::{set_memory_dedup_config, get_memory_dedup_config, get_dedup_stats},
+    // Memory encryption at rest
+    memory_encryption::migrate_to_encrypted_storage,
+    // Memory quotas
+    memory_quota::{set_memory_quota, get_memory_quota_status},
+    // Memory tiers
+    memory_tiers::{
+        consolidate_memories, start_memory_consolidation, set_memory_retention_policy, get_memory_retention_policy,
+        apply_memory_retention,
+    },
+    // Memory pattern analysis
+    memory_patterns::{analyze_memory_patterns, get_memory_pattern_report},
+    // Memory anomaly detection
+    memory_anomalies::{list_memory_anomalies, MEMORY_ANOMALY_MONITOR},
+    // Grouped memory browsing
+    memory_browse::browse_memories,
+    // Data deletion
+    data_purge::purge_agent_data,
+    // Agent config history
+    agent_config_history::{save_agent_config, get_agent_config_history, diff_agent_config_versions},
+    // Agent definitions
+    agent_definitions::{
+        get_agent_config_schema, list_agents, create_agent, update_agent, delete_agent, export_agents, import_agents,
+    },
+    // Agent templates and cloning
+    agent_templates::{
+        list_agent_templates, save_agent_template, delete_agent_template, create_agent_from_template, clone_agent,
+    },
+    // Prompt templates
+    prompt_templates::{
+        create_prompt_template, update_prompt_template, delete_prompt_template, list_prompt_templates,
+        get_prompt_template, list_prompt_template_versions, render_prompt_template, run_prompt_template,
+    },
+    // Notification center
+    notification_center::{list_notifications, mark_notification_read, mark_all_notifications_read},
+    // Run traces and replay
+    run_traces::{
+        start_run_trace, record_run_tool_call, finish_run_trace, get_run_trace, list_run_traces,
+        replay_agent_run, replay_tool_call,
+    },
+    // Background task queue
+    task_queue::{
+        TaskQueueState, spawn_task_worker, enqueue_task, list_queued_tasks, cancel_task,
+        list_failed_tasks, retry_failed_task, schedule_task, list_task_schedules, delete_task_schedule,
+    },
+    // Workspaces
+    workspaces::{create_workspace, switch_workspace, list_workspaces},
 };
-use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -81,23 +220,14 @@ fn greet(name: &str) -> String {
     response
 }
 
-fn setup_logging() {
-    tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
-        .with_target(false)
-        .with_thread_ids(true)
-        .with_file(true)
-        .with_line_number(true)
-        .init();
-    
-    info!("Logging system initialized");
-}
-
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     setup_logging();
     info!("Starting Tauri application with AI capabilities");
     
+    // Check for an unclean exit and quarantine corrupted databases before anything opens them
+    let startup_health = StartupHealthState::new(run_startup_checks());
+    
     // Initialize AI state
     let ai_state = match AIState::new() {
         Ok(state) => state,
@@ -115,10 +245,42 @@ pub fn run() {
     info!("Security managers initialized");
     
     // Initialize MCP process map
-    let mcp_processes: Arc<Mutex<HashMap<u32, MCPProcessInfo>>> = Arc::new(Mutex::new(HashMap::new()));
+    let mcp_processes = MCPProcessMap::default();
+    
+    // Initialize MCP tool catalog
+    let mcp_tool_catalog = MCPToolCatalog::new();
+    
+    // Initialize dashboard agent session registry
+    let agent_session_manager = AgentSessionManager::new();
+    
+    // Initialize background task registry
+    let task_queue_state = TaskQueueState::new();
+    
+    // Initialize long-running operation registry
+    let operation_registry = OperationRegistry::new();
     
     // Initialize Agent Memory state
-    let memory_state = MemoryState::new();
+    let memory_state = MemoryState::new(ai_state.get_security_middleware());
+    memory_state.set_workspace(&active_workspace(&ai_state.storage));
+    let neural_embedding_handle = memory_state.neural_embedding_handle();
+    
+    // Initialize file watcher registry
+    let file_watcher_state = FileWatcherState::new();
+    
+    // Initialize chunked write stream registry
+    let file_stream_state = FileStreamState::new();
+    
+    // Initialize run planner registry
+    let run_planner_state = RunPlannerState::new();
+    
+    // Initialize WebSocket connection registry
+    let websocket_state = WebSocketState::new();
+    
+    // Initialize pending notification registry
+    let notification_state = NotificationState::new();
+    
+    // Initialize OAuth authorization flow and refresh registry
+    let oauth_flow_state = OAuthFlowState::new();
     
     // Initialize App State with OAuth storage
     let app_data_dir = dirs::data_dir()
@@ -138,10 +300,36 @@ pub fn run() {
         .plugin(tauri_plugin_oauth::init())
         .manage(ai_state)
         .manage(mcp_processes)
+        .manage(mcp_tool_catalog)
+        .manage(agent_session_manager)
+        .manage(task_queue_state)
+        .manage(operation_registry)
+        .manage(startup_health)
         .manage(secure_session)
         .manage(memory_state)
         .manage(app_state)
-        .setup(|_app| {
+        .manage(oauth_flow_state)
+        .manage(file_watcher_state)
+        .manage(file_stream_state)
+        .manage(run_planner_state)
+        .manage(websocket_state)
+        .manage(notification_state)
+        .setup(move |app| {
+            // Keep tokens from earlier sessions fresh
+            resume_oauth_refresh(app.handle().clone());
+            // Let the disk space guard raise low-disk notifications
+            ai::DISK_SPACE_GUARD.attach(app.handle().clone());
+            // Let memory saves raise anomaly events
+            MEMORY_ANOMALY_MONITOR.attach(app.handle().clone());
+            // Daily knowledge graph snapshots for time-travel views
+            spawn_graph_snapshot_scheduler();
+            // Background neural training, if an earlier session enabled it
+            spawn_training_scheduler(neural_embedding_handle);
+            // Sample spawned MCP servers and enforce their resource limits
+            spawn_resource_monitor(app.handle().clone());
+            // Run queued tool and LLM jobs
+            spawn_task_worker(app.handle().clone());
+
             // Start cleanup tasks within Tauri's async runtime
             tauri::async_runtime::spawn(async {
                 use std::time::Duration;
@@ -165,21 +353,87 @@ pub fn run() {
             read_file_command,
             write_file_command,
             list_files_command,
+            get_path_policy,
+            set_path_policy,
+            read_file_chunk,
+            write_file_stream_start,
+            write_file_stream_append,
+            write_file_stream_finish,
+            write_file_stream_abort,
+            watch_path_command,
+            unwatch_path_command,
+            search_workspace_command,
+            compute_diff_command,
+            apply_patch_command,
+            // Logging
+            set_log_level,
+            get_log_levels,
+            get_log_file_settings,
+            set_log_file_settings,
+            get_recent_logs,
+            set_secret_patterns,
+            get_secret_patterns,
+            // Long-running operations
+            get_operation_status,
+            list_operations,
+            cancel_operation,
             // System
+            get_environment_context,
             execute_command,
+            execute_command_sandboxed,
             // HTTP
             http_request_command,
+            get_http_cache_stats,
+            list_http_cache_entries,
+            clear_http_cache,
+            // WebSocket
+            websocket_connect_command,
+            websocket_send_command,
+            websocket_close_command,
+            // Run planning
+            plan_agent_run,
+            record_run_step,
+            halt_agent_run,
+            // Response validation
+            validate_assistant_response,
+            get_message_repairs,
+            // Multi-agent workflows
+            list_agent_workflows,
+            register_agent_workflow,
+            delete_agent_workflow,
+            run_agent_workflow,
+            // Structured output
+            generate_structured,
+            // Guardrails
+            list_guardrail_rules,
+            save_guardrail_rule,
+            delete_guardrail_rule,
+            set_agent_guardrail,
+            check_guardrails,
             // UI
             show_notification_command,
+            get_notification_capabilities,
+            notification_reply_command,
+            list_notifications,
+            mark_notification_read,
+            mark_all_notifications_read,
             // Settings
             set_setting_command,
             get_setting_command,
             // Security
             get_rate_limit_stats,
+            get_rate_limit_config,
+            set_rate_limit_config,
+            get_http_client_config,
+            set_http_client_config,
+            acquire_rate_limit,
+            rotate_encryption_keys,
+            get_encryption_key_status,
             // MCP Process Management
             start_mcp_process,
             stop_mcp_process,
             send_mcp_message,
+            list_mcp_processes,
             connect_local_mcp,
             disconnect_local_mcp,
             send_local_mcp_message,
@@ -214,26 +468,156 @@ pub fn run() {
             get_messages,
             search_conversations,
             delete_conversation,
+            set_conversation_tags,
+            archive_conversation,
+            pin_conversation,
+            find_duplicate_conversations,
+            merge_conversations,
+            edit_message,
+            regenerate_from,
+            export_conversation,
+            save_artifact,
+            get_artifact,
+            list_conversation_artifacts,
+            collect_artifact_garbage,
+            save_agent_config,
+            get_agent_config_history,
+            diff_agent_config_versions,
+            // Agent definitions
+            get_agent_config_schema,
+            list_agents,
+            create_agent,
+            update_agent,
+            delete_agent,
+            export_agents,
+            import_agents,
+            // Agent templates and cloning
+            list_agent_templates,
+            save_agent_template,
+            delete_agent_template,
+            create_agent_from_template,
+            clone_agent,
+            // Prompt templates
+            create_prompt_template,
+            update_prompt_template,
+            delete_prompt_template,
+            list_prompt_templates,
+            get_prompt_template,
+            list_prompt_template_versions,
+            render_prompt_template,
+            run_prompt_template,
+            // Run traces and replay
+            start_run_trace,
+            record_run_tool_call,
+            finish_run_trace,
+            get_run_trace,
+            list_run_traces,
+            replay_agent_run,
+            replay_tool_call,
+            // Background task queue
+            enqueue_task,
+            list_queued_tasks,
+            cancel_task,
+            list_failed_tasks,
+            retry_failed_task,
+            schedule_task,
+            list_task_schedules,
+            delete_task_schedule,
+            // Workspaces
+            create_workspace,
+            switch_workspace,
+            list_workspaces,
             // Agent Memory System commands
             init_agent_memory,
             save_agent_memory,
             get_agent_memory,
             search_agent_memories,
+            list_agent_memories,
+            update_agent_memory,
+            delete_agent_memory,
+            pin_memory,
+            unpin_memory,
             save_shared_knowledge,
+            create_shared_knowledge,
             add_knowledge_graph_node,
             add_knowledge_graph_edge,
             backup_agent_memories,
+            start_memory_backup,
             search_shared_knowledge,
+            get_shared_knowledge_conflicts,
+            list_contradicting_knowledge,
+            review_knowledge_contradiction,
+            grant_memory_access,
+            revoke_memory_access,
+            query_foreign_memories,
+            export_agent_memories,
+            import_agent_memories,
+            export_memory_vault,
+            verify_backup,
+            restore_agent_memories,
+            get_database_schema_status,
+            get_startup_health_report,
             get_knowledge_graph,
             // Neural Embedding System commands
             init_neural_embedding_service,
+            set_agent_embedding_provider,
             generate_neural_embedding,
             generate_neural_embeddings_batch,
             search_neural_similar,
             find_similar_memories,
             train_neural_networks,
+            reset_neural_models,
+            configure_embedding_training,
+            get_training_status,
+            get_inference_accelerator,
+            set_force_cpu_inference,
+            quantize_stored_embeddings,
+            evaluate_embeddings,
+            list_embedding_evaluations,
             get_neural_embedding_stats,
-            clear_neural_embedding_cache,
+            clear_embedding_cache,
+            // Embedding model migration commands
+            start_embedding_migration,
+            get_migration_status,
+            validate_migration_results,
+            rollback_migration,
+            get_migration_stats,
+            // Edge suggestion review commands
+            list_edge_suggestions,
+            accept_edge_suggestion,
+            reject_edge_suggestion,
+            // Relationship feedback commands
+            confirm_relationship,
+            reject_relationship,
+            // Memory injection budget commands
+            set_memory_injection_budget,
+            get_memory_injection_budget,
+            retrieve_memories_for_prompt,
+            get_last_memory_injection_report,
+            // Memory deduplication
+            set_memory_dedup_config,
+            get_memory_dedup_config,
+            get_dedup_stats,
+            // Memory encryption at rest
+            migrate_to_encrypted_storage,
+            // Memory quotas
+            set_memory_quota,
+            get_memory_quota_status,
+            // Memory tiers
+            consolidate_memories,
+            start_memory_consolidation,
+            set_memory_retention_policy,
+            get_memory_retention_policy,
+            apply_memory_retention,
+            // Memory pattern analysis
+            analyze_memory_patterns,
+            get_memory_pattern_report,
+            // Memory anomaly detection
+            list_memory_anomalies,
+            // Memory browsing
+            browse_memories,
+            // Data deletion
+            purge_agent_data,
             // Enhanced knowledge graph commands
             create_graph_node,
             get_graph_node,
@@ -246,12 +630,23 @@ pub fn run() {
             get_graph_view,
             find_graph_path,
             get_graph_neighbors,
+            get_subgraph,
             get_graph_stats,
             find_graph_clusters,
             optimize_graph,
+            ask_knowledge_graph,
+            list_graph_snapshots,
+            export_knowledge_graph,
+            import_knowledge_graph,
+            create_graph_nodes_batch,
+            create_graph_edges_batch,
+            predict_graph_relationships,
+            accept_predicted_relationship,
             // Secure commands
             create_session,
             generate_csrf_token,
+            set_session_workspace,
+            set_session_agent,
             execute_command_secure,
             read_file_tool_secure,
             write_file_tool_secure,
@@ -259,6 +654,17 @@ pub fn run() {
             execute_agent_tool_secure,
             store_api_key_secure,
             get_api_key_secure,
+            // Agent permission manifests
+            set_agent_permissions,
+            get_agent_permissions,
+            // Command whitelist management
+            add_whitelisted_command,
+            remove_whitelisted_command,
+            list_whitelisted_commands,
+            // Offline summarization
+            get_local_summarizer_status,
+            download_summarization_model,
+            summarize_locally,
             // OAuth token management
             store_mcp_oauth_token,
             get_mcp_oauth_tokens,
@@ -267,7 +673,38 @@ pub fn run() {
             encrypt_data,
             decrypt_data,
             open_oauth_browser,
+            start_mcp_oauth_flow,
+            refresh_mcp_oauth_token,
+            start_device_code_flow,
+            cancel_device_code_flow,
+            // MCP registry
+            add_mcp_server,
+            update_mcp_server,
+            remove_mcp_server,
+            export_mcp_registry,
+            import_mcp_registry,
+            import_mcp_servers_from_config,
+            // MCP prompts
+            list_mcp_prompts,
+            run_mcp_prompt,
+            // MCP tool call audit
+            record_mcp_tool_call,
+            list_mcp_tool_calls,
+            replay_mcp_tool_call,
+            // MCP process resource limits
+            get_mcp_resource_limits,
+            set_mcp_resource_limits,
+            // MCP tool routing
+            get_routed_mcp_tools,
+            call_routed_mcp_tool,
+            fan_out_mcp_tool_call,
+            // MCP tool catalog
+            refresh_mcp_tool_catalog,
+            handle_mcp_notification,
+            register_plugin_tools,
+            get_all_available_tools,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| shutdown::handle_run_event(app, &event));
 }
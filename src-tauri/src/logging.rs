@@ -0,0 +1,561 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use tauri::State;
+use tracing::field::{Field, Visit};
+use tracing::{info, warn, error, Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context as LayerContext, Layer};
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
+
+use crate::ai::{redact_secrets, reload_secret_patterns, AIState, StorageManager};
+
+/// Settings key holding per-module level overrides
+const LOG_LEVELS_SETTING: &str = "logging.levels";
+
+/// Settings key holding the log file configuration
+const LOG_FILE_SETTING: &str = "logging.file";
+
+const LOG_FILE_NAME: &str = "banshee.log";
+
+/// Records kept in memory for the in-app console
+const RECENT_LOG_CAPACITY: usize = 2000;
+
+/// Module name used for the global default level
+const DEFAULT_MODULE: &str = "default";
+
+/// Crate prefix added to short module names such as `mcp`
+const CRATE_TARGET: &str = "banshee_lib";
+
+const VALID_LEVELS: [&str; 6] = ["trace", "debug", "info", "warn", "error", "off"];
+
+static FILTER_HANDLE: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+
+/// The open log file; `None` while file logging is off
+static LOG_FILE: Lazy<Mutex<Option<RotatingLogFile>>> = Lazy::new(|| Mutex::new(None));
+
+static RECENT_LOGS: Lazy<Mutex<VecDeque<LogRecord>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// When the log file starts over regardless of its size
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl LogRotation {
+    /// Label of the period a time falls in; a new label means a new file
+    fn period(self, time: DateTime<Local>) -> String {
+        match self {
+            Self::Never => String::new(),
+            Self::Hourly => time.format("%Y-%m-%d %H").to_string(),
+            Self::Daily => time.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogFileSettings {
+    pub enabled: bool,
+    /// Size at which the file is rotated
+    pub max_file_bytes: u64,
+    pub rotation: LogRotation,
+    /// Rotated files kept besides the current one
+    pub max_files: usize,
+}
+
+impl Default for LogFileSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_file_bytes: 10 * 1024 * 1024,
+            rotation: LogRotation::Daily,
+            max_files: 5,
+        }
+    }
+}
+
+impl LogFileSettings {
+    fn validate(&self) -> Result<()> {
+        if self.max_file_bytes < 1024 {
+            bail!("Log files must be allowed at least 1024 bytes");
+        }
+        if !(1..=100).contains(&self.max_files) {
+            bail!("Between 1 and 100 rotated log files can be kept");
+        }
+        Ok(())
+    }
+}
+
+/// A log file that moves aside as `banshee.log.1`, `.2`, ... when it grows too large or its
+/// period ends, dropping the oldest beyond `max_files`
+struct RotatingLogFile {
+    dir: PathBuf,
+    settings: LogFileSettings,
+    file: File,
+    size: u64,
+    period: String,
+}
+
+impl RotatingLogFile {
+    fn open(dir: &Path, settings: LogFileSettings) -> Result<Self> {
+        fs::create_dir_all(dir).context("Failed to create log directory")?;
+        let path = dir.join(LOG_FILE_NAME);
+        let file = OpenOptions::new().create(true).append(true).open(&path)
+            .with_context(|| format!("Failed to open log file {}", path.display()))?;
+        let metadata = file.metadata()?;
+        // A file left from an earlier period is rotated on the first write
+        let modified: DateTime<Local> = metadata.modified().map(DateTime::from).unwrap_or_else(|_| Local::now());
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            period: settings.rotation.period(modified),
+            settings,
+            file,
+            size: metadata.len(),
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("{}.{}", LOG_FILE_NAME, index))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let oldest = self.rotated_path(self.settings.max_files);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for index in (1..self.settings.max_files).rev() {
+            let path = self.rotated_path(index);
+            if path.exists() {
+                fs::rename(&path, self.rotated_path(index + 1))?;
+            }
+        }
+        let current = self.dir.join(LOG_FILE_NAME);
+        fs::rename(&current, self.rotated_path(1))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&current)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &[u8], now: DateTime<Local>) -> io::Result<()> {
+        let period = self.settings.rotation.period(now);
+        let full = self.size > 0 && self.size + record.len() as u64 > self.settings.max_file_bytes;
+        if full || period != self.period {
+            self.rotate()?;
+            self.period = period;
+        }
+        self.file.write_all(record)?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+}
+
+/// Directory holding the log files, beside the rest of the app's configuration
+pub fn log_dir() -> Result<PathBuf> {
+    Ok(dirs::config_dir().context("Failed to get config directory")?.join("banshee").join("logs"))
+}
+
+fn load_file_settings(storage: &StorageManager) -> Result<LogFileSettings> {
+    match storage.get_setting(LOG_FILE_SETTING)? {
+        Some(value) => serde_json::from_value(value).context("Stored log file settings are malformed"),
+        None => Ok(LogFileSettings::default()),
+    }
+}
+
+/// Open, reopen or close the log file to match `settings`
+fn apply_file_settings(settings: &LogFileSettings) -> Result<()> {
+    let file = if settings.enabled {
+        Some(RotatingLogFile::open(&log_dir()?, settings.clone())?)
+    } else {
+        None
+    };
+    *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = file;
+    Ok(())
+}
+
+/// Writes formatted records to the log file, if one is open
+struct LogFileWriter;
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(file) = file.as_mut() {
+            let (redacted, _) = redact_secrets(&String::from_utf8_lossy(buf));
+            file.write_record(redacted.as_bytes(), Local::now())?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Some(file) => file.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A log record as shown in the in-app console
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    /// The message followed by the record's other fields, with secrets redacted
+    pub message: String,
+}
+
+/// Which recent records to return; every field narrows the result
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogFilter {
+    /// Least severe level to include
+    pub level: Option<String>,
+    /// Module or target prefix, e.g. `mcp`
+    pub target: Option<String>,
+    /// Case-insensitive text in the message
+    pub contains: Option<String>,
+}
+
+impl LogFilter {
+    fn matcher(&self) -> Result<impl Fn(&LogRecord) -> bool + '_> {
+        let level = self.level.as_deref()
+            .map(|level| Level::from_str(level).map_err(|_| anyhow::anyhow!("Invalid log level: {}", level)))
+            .transpose()?;
+        let target = self.target.as_deref().map(module_target);
+        let contains = self.contains.as_deref().map(str::to_lowercase);
+        Ok(move |record: &LogRecord| {
+            level.is_none_or(|min| Level::from_str(&record.level).is_ok_and(|l| l <= min))
+                && target.as_deref().is_none_or(|t| record.target.starts_with(t))
+                && contains.as_deref().is_none_or(|c| record.message.to_lowercase().contains(c))
+        })
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push_str(&format!(" {}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// Keeps the latest records in memory for `get_recent_logs`
+struct RecentLogLayer;
+
+impl<S: Subscriber> Layer<S> for RecentLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let (message, _) = redact_secrets(&format!("{}{}", visitor.message, visitor.fields));
+        let record = LogRecord {
+            timestamp: Utc::now(),
+            level: event.metadata().level().to_string(),
+            target: event.metadata().target().to_string(),
+            message,
+        };
+        let mut recent = RECENT_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == RECENT_LOG_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(record);
+    }
+}
+
+/// The last `limit` records matching `filter`, oldest first
+fn recent_logs(records: &VecDeque<LogRecord>, limit: usize, filter: &LogFilter) -> Result<Vec<LogRecord>> {
+    let matches = filter.matcher()?;
+    let mut found: Vec<LogRecord> = records.iter().rev().filter(|r| matches(r)).take(limit).cloned().collect();
+    found.reverse();
+    Ok(found)
+}
+
+/// Expand a short module name into a tracing target
+fn module_target(module: &str) -> String {
+    if module.contains("::") || module.starts_with(CRATE_TARGET) {
+        module.to_string()
+    } else {
+        format!("{}::{}", CRATE_TARGET, module)
+    }
+}
+
+/// Build filter directives from the level overrides, e.g. `info,banshee_lib::mcp=debug`
+fn build_directives(levels: &BTreeMap<String, String>) -> String {
+    let default = levels.get(DEFAULT_MODULE).map(String::as_str).unwrap_or("info");
+
+    std::iter::once(default.to_string())
+        .chain(levels.iter()
+            .filter(|(module, _)| module.as_str() != DEFAULT_MODULE)
+            .map(|(module, level)| format!("{}={}", module_target(module), level)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn load_levels(storage: &StorageManager) -> Result<BTreeMap<String, String>> {
+    match storage.get_setting(LOG_LEVELS_SETTING)? {
+        Some(value) => serde_json::from_value(value).context("Stored log levels are malformed"),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+fn validate_module(module: &str) -> Result<(), String> {
+    let valid = !module.is_empty()
+        && module.len() <= 200
+        && module.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid module name: {}", module))
+    }
+}
+
+/// Log output with secrets replaced before it reaches the terminal. The formatter writes each
+/// record in one call, so a secret is never split across writes.
+struct RedactingWriter;
+
+impl Write for RedactingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (redacted, _) = redact_secrets(&String::from_utf8_lossy(buf));
+        io::stdout().write_all(redacted.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+fn apply_levels(levels: &BTreeMap<String, String>) -> Result<(), String> {
+    let handle = FILTER_HANDLE.get()
+        .ok_or_else(|| "Logging has not been initialized".to_string())?;
+    let filter = EnvFilter::try_new(build_directives(levels))
+        .map_err(|e| format!("Invalid log filter: {}", e))?;
+
+    handle.reload(filter).map_err(|e| format!("Failed to update log filter: {}", e))
+}
+
+/// Install the global subscriber with a reloadable filter seeded from settings. Records go to
+/// stdout, the rotating log file and the in-memory console buffer.
+pub fn setup_logging() {
+    let storage = StorageManager::new();
+    let levels = storage.as_ref().ok()
+        .and_then(|storage| load_levels(storage).ok())
+        .unwrap_or_default();
+    let file_settings = storage.as_ref().ok()
+        .and_then(|storage| load_file_settings(storage).ok())
+        .unwrap_or_default();
+    let file_result = apply_file_settings(&file_settings);
+
+    let filter = EnvFilter::try_new(build_directives(&levels))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter_layer, handle) = reload::Layer::new(filter);
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(fmt::layer()
+            .with_target(false)
+            .with_thread_ids(true)
+            .with_file(true)
+            .with_line_number(true)
+            .with_writer(|| RedactingWriter))
+        .with(fmt::layer()
+            .with_ansi(false)
+            .with_thread_ids(true)
+            .with_writer(|| LogFileWriter))
+        .with(RecentLogLayer)
+        .init();
+
+    let _ = FILTER_HANDLE.set(handle);
+    info!("Logging system initialized");
+    if let Err(e) = file_result {
+        warn!("Logging to file is unavailable: {:#}", e);
+    }
+    if let Ok(storage) = &storage {
+        if let Err(e) = reload_secret_patterns(storage) {
+            warn!("Custom secret patterns not loaded: {:#}", e);
+        }
+    }
+}
+
+/// Change the log level for a module (e.g. `mcp`) or for `default`; `inherit` removes an override
+#[tauri::command]
+pub async fn set_log_level(
+    module: String,
+    level: String,
+    state: State<'_, AIState>,
+) -> Result<(), String> {
+    let module = module.trim().to_string();
+    let level = level.trim().to_lowercase();
+    validate_module(&module)?;
+
+    if level != "inherit" && !VALID_LEVELS.contains(&level.as_str()) {
+        warn!("Rejected log level '{}' for {}", level, module);
+        return Err(format!("Invalid log level: {}", level));
+    }
+
+    let mut levels = load_levels(&state.storage).map_err(|e| {
+        error!("Failed to load log levels: {}", e);
+        format!("Failed to load log levels: {}", e)
+    })?;
+
+    if level == "inherit" {
+        levels.remove(&module);
+    } else {
+        levels.insert(module.clone(), level.clone());
+    }
+
+    apply_levels(&levels)?;
+
+    let value = serde_json::to_value(&levels).map_err(|e| e.to_string())?;
+    state.storage.set_setting(LOG_LEVELS_SETTING, value).map_err(|e| {
+        error!("Failed to persist log levels: {}", e);
+        format!("Failed to persist log levels: {}", e)
+    })?;
+
+    info!("Log level for {} set to {}", module, level);
+    Ok(())
+}
+
+/// Current per-module level overrides
+#[tauri::command]
+pub async fn get_log_levels(
+    state: State<'_, AIState>,
+) -> Result<BTreeMap<String, String>, String> {
+    load_levels(&state.storage).map_err(|e| format!("Failed to load log levels: {}", e))
+}
+
+/// Where log files go and when they rotate
+#[tauri::command]
+pub async fn get_log_file_settings(
+    state: State<'_, AIState>,
+) -> Result<LogFileSettings, String> {
+    load_file_settings(&state.storage).map_err(|e| format!("Failed to load log file settings: {}", e))
+}
+
+/// Change file logging; takes effect immediately
+#[tauri::command]
+pub async fn set_log_file_settings(
+    settings: LogFileSettings,
+    state: State<'_, AIState>,
+) -> Result<(), String> {
+    settings.validate().map_err(|e| e.to_string())?;
+    apply_file_settings(&settings).map_err(|e| {
+        error!("Failed to apply log file settings: {:#}", e);
+        format!("Failed to apply log file settings: {:#}", e)
+    })?;
+
+    let value = serde_json::to_value(&settings).map_err(|e| e.to_string())?;
+    state.storage.set_setting(LOG_FILE_SETTING, value)
+        .map_err(|e| format!("Failed to persist log file settings: {}", e))?;
+    info!("Log file settings updated: {:?}", settings);
+    Ok(())
+}
+
+/// Up to `limit` of the most recent log records matching `filter`, oldest first
+#[tauri::command]
+pub async fn get_recent_logs(
+    limit: Option<usize>,
+    filter: Option<LogFilter>,
+) -> Result<Vec<LogRecord>, String> {
+    let limit = limit.unwrap_or(200).min(RECENT_LOG_CAPACITY);
+    let records = RECENT_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+    recent_logs(&records, limit, &filter.unwrap_or_default()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_build_directives() {
+        assert_eq!(build_directives(&BTreeMap::new()), "info");
+
+        let mut levels = BTreeMap::new();
+        levels.insert("default".to_string(), "warn".to_string());
+        levels.insert("mcp".to_string(), "debug".to_string());
+        levels.insert("hyper::client".to_string(), "error".to_string());
+
+        let directives = build_directives(&levels);
+        assert_eq!(directives, "warn,hyper::client=error,banshee_lib::mcp=debug");
+        assert!(EnvFilter::try_new(directives).is_ok());
+        assert!(validate_module("database::simple_commands").is_ok());
+        assert!(validate_module("mcp=trace,evil").is_err());
+    }
+
+    #[test]
+    fn test_log_file_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = LogFileSettings { enabled: true, max_file_bytes: 1024, rotation: LogRotation::Daily, max_files: 2 };
+        let mut file = RotatingLogFile::open(dir.path(), settings).unwrap();
+        let day = |d: u32| Local.with_ymd_and_hms(2024, 3, d, 12, 0, 0).unwrap();
+        file.period = LogRotation::Daily.period(day(1));
+
+        let record = [b'a'; 600];
+        file.write_record(&record, day(1)).unwrap();
+        file.write_record(&record, day(1)).unwrap();
+        assert_eq!(fs::metadata(dir.path().join("banshee.log.1")).unwrap().len(), 600);
+
+        file.write_record(b"next day\n", day(2)).unwrap();
+        file.write_record(b"later\n", day(2)).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("banshee.log")).unwrap(), "next day\nlater\n");
+        assert_eq!(fs::metadata(dir.path().join("banshee.log.2")).unwrap().len(), 600);
+
+        file.write_record(b"third day\n", day(3)).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("banshee.log.1")).unwrap(), "next day\nlater\n");
+        assert!(!dir.path().join("banshee.log.3").exists());
+        assert!(LogFileSettings { max_files: 0, ..LogFileSettings::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_recent_logs_filter() {
+        let record = |level: &str, target: &str, message: &str| LogRecord {
+            timestamp: Utc::now(),
+            level: level.to_string(),
+            target: target.to_string(),
+            message: message.to_string(),
+        };
+        let records: VecDeque<LogRecord> = vec![
+            record("INFO", "banshee_lib::mcp::router", "Fanned search out"),
+            record("WARN", "banshee_lib::mcp::audit", "Replay failed"),
+            record("DEBUG", "banshee_lib::ai", "Cache miss"),
+            record("ERROR", "banshee_lib::mcp::registry", "Server crashed"),
+        ].into();
+
+        let all = recent_logs(&records, 10, &LogFilter::default()).unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(recent_logs(&records, 2, &LogFilter::default()).unwrap(), all[2..].to_vec());
+
+        let filter = LogFilter { level: Some("warn".to_string()), target: Some("mcp".to_string()), contains: None };
+        let messages: Vec<String> = recent_logs(&records, 10, &filter).unwrap().into_iter().map(|r| r.message).collect();
+        assert_eq!(messages, vec!["Replay failed", "Server crashed"]);
+
+        let filter = LogFilter { contains: Some("CACHE".to_string()), ..LogFilter::default() };
+        assert_eq!(recent_logs(&records, 10, &filter).unwrap().len(), 1);
+        assert!(recent_logs(&records, 10, &LogFilter { level: Some("loud".to_string()), ..LogFilter::default() }).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+//! Audit trail of MCP tool calls. Arguments are stored with secret-looking fields redacted and
+//! a hash of the originals, so a failed call can be replayed and checked against what ran. Calls
+//! are recorded where they leave the app: the tool router, `tools/call` requests sent to stdio
+//! servers, and replays.
+
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::Instant;
+use tauri::{command, AppHandle, State, Webview};
+use tracing::{info, warn};
+
+use super::registry::{find_server, is_secret_name};
+use super::stdio::MCPStdioError;
+use super::tool_catalog::{call_server_tool, rpc_result};
+use crate::ai::csrf::guard_secure_command;
+use crate::ai::secure_commands::SecureResponse;
+use crate::ai::{redact_error, redact_secrets, AIState, GuardrailScope, RedactErr};
+use crate::database::workspaces::ensure_tool_allowed;
+use crate::database::{open_conversation_db, open_db, parse_db_timestamp};
+
+/// Stored in place of a redacted argument value
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A tool call as reported by whoever executed it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewMCPToolCall {
+    pub server_id: String,
+    pub tool_name: String,
+    pub run_id: Option<String>,
+    pub arguments: Value,
+    pub duration_ms: Option<u64>,
+    pub result_size: Option<u64>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPToolCallRecord {
+    pub id: String,
+    pub server_id: String,
+    pub tool_name: String,
+    pub run_id: Option<String>,
+    pub replay_of: Option<String>,
+    /// Arguments with sensitive fields replaced by `[REDACTED]`
+    pub arguments: Value,
+    /// JSON paths of the redacted fields, e.g. `auth.token`
+    pub redacted_fields: Vec<String>,
+    /// SHA-256 of the arguments as called, before redaction
+    pub arguments_hash: String,
+    pub duration_ms: Option<u64>,
+    pub result_size: Option<u64>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPToolReplay {
+    pub call: MCPToolCallRecord,
+    pub result: Option<Value>,
+    /// False when the replay ran with different arguments than the original call
+    pub arguments_match: bool,
+}
+
+pub fn hash_arguments(arguments: &Value) -> String {
+    hex::encode(Sha256::digest(arguments.to_string().as_bytes()))
+}
+
+/// Copy of `arguments` with secret-named fields and secret-looking text redacted, plus the paths
+/// that were
+pub fn redact_arguments(arguments: &Value) -> (Value, Vec<String>) {
+    fn walk(value: &mut Value, path: &str, redacted: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, field) in map.iter_mut() {
+                    let field_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    if is_secret_name(key) && !field.is_null() {
+                        *field = Value::String(REDACTED.to_string());
+                        redacted.push(field_path);
+                    } else {
+                        walk(field, &field_path, redacted);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    walk(item, &format!("{}[{}]", path, i), redacted);
+                }
+            }
+            Value::String(text) => {
+                let (replaced, count) = redact_secrets(text);
+                if count > 0 {
+                    *text = replaced;
+                    redacted.push(path.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut copy = arguments.clone();
+    let mut redacted = Vec::new();
+    walk(&mut copy, "", &mut redacted);
+    (copy, redacted)
+}
+
+pub fn record_call(conn: &Connection, call: &NewMCPToolCall, replay_of: Option<&str>) -> Result<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let (arguments, redacted_fields) = redact_arguments(&call.arguments);
+    conn.execute(
+        "INSERT INTO mcp_tool_calls
+            (id, server_id, tool_name, run_id, replay_of, arguments, redacted_fields, arguments_hash,
+             duration_ms, result_size, success, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            id,
+            call.server_id,
+            call.tool_name,
+            call.run_id,
+            replay_of,
+            arguments.to_string(),
+            serde_json::to_string(&redacted_fields)?,
+            hash_arguments(&call.arguments),
+            call.duration_ms.map(|d| d as i64),
+            call.result_size.map(|s| s as i64),
+            call.success,
+            call.error.as_deref().map(redact_error),
+        ],
+    )?;
+    Ok(id)
+}
+
+const CALL_COLUMNS: &str = "id, server_id, tool_name, run_id, replay_of, arguments, redacted_fields, arguments_hash,
+    duration_ms, result_size, success, error, created_at";
+
+fn call_from_row(row: &rusqlite::Row) -> rusqlite::Result<MCPToolCallRecord> {
+    let arguments: String = row.get(5)?;
+    let redacted_fields: String = row.get(6)?;
+    let duration_ms: Option<i64> = row.get(8)?;
+    let result_size: Option<i64> = row.get(9)?;
+    let created_at: String = row.get(12)?;
+    Ok(MCPToolCallRecord {
+        id: row.get(0)?,
+        server_id: row.get(1)?,
+        tool_name: row.get(2)?,
+        run_id: row.get(3)?,
+        replay_of: row.get(4)?,
+        arguments: serde_json::from_str(&arguments).unwrap_or(Value::Null),
+        redacted_fields: serde_json::from_str(&redacted_fields).unwrap_or_default(),
+        arguments_hash: row.get(7)?,
+        duration_ms: duration_ms.map(|d| d.max(0) as u64),
+        result_size: result_size.map(|s| s.max(0) as u64),
+        success: row.get(10)?,
+        error: row.get(11)?,
+        created_at: parse_db_timestamp(&created_at),
+    })
+}
+
+pub fn load_call(conn: &Connection, call_id: &str) -> Result<MCPToolCallRecord> {
+    conn.query_row(
+        &format!("SELECT {} FROM mcp_tool_calls WHERE id = ?1", CALL_COLUMNS),
+        params![call_id],
+        call_from_row,
+    ).optional()?.ok_or_else(|| anyhow!("Unknown MCP tool call: {}", call_id))
+}
+
+pub fn list_calls(
+    conn: &Connection,
+    server_id: Option<&str>,
+    run_id: Option<&str>,
+    failed_only: bool,
+    limit: usize,
+) -> Result<Vec<MCPToolCallRecord>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM mcp_tool_calls
+         WHERE (?1 IS NULL OR server_id = ?1) AND (?2 IS NULL OR run_id = ?2) AND (?3 = 0 OR success = 0)
+         ORDER BY created_at DESC, rowid DESC LIMIT ?4",
+        CALL_COLUMNS
+    ))?;
+    let calls = stmt.query_map(params![server_id, run_id, failed_only, limit as i64], call_from_row)?;
+    Ok(calls.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Arguments to replay `call` with: the caller's, or the stored ones when nothing was redacted
+pub fn replay_arguments(call: &MCPToolCallRecord, arguments: Option<Value>) -> Result<Value> {
+    match arguments {
+        Some(arguments) => Ok(arguments),
+        None if call.redacted_fields.is_empty() => Ok(call.arguments.clone()),
+        None => Err(anyhow!(
+            "Call {} had redacted arguments ({}); pass the full arguments to replay it",
+            call.id, call.redacted_fields.join(", ")
+        )),
+    }
+}
+
+/// Split a `tools/call` outcome into its result and error. Tools report their own failures in
+/// the result with `isError` rather than as JSON-RPC errors.
+pub fn call_outcome(outcome: Result<Value>) -> (Option<Value>, Option<String>) {
+    match outcome {
+        Ok(result) if result.get("isError").and_then(Value::as_bool) == Some(true) => {
+            (Some(result), Some("Tool reported an error".to_string()))
+        }
+        Ok(result) => (Some(result), None),
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+/// Tool name and arguments of a JSON-RPC `tools/call` request
+pub fn tool_call_request(message: &Value) -> Option<(String, Value)> {
+    if message.get("method").and_then(Value::as_str) != Some("tools/call") {
+        return None;
+    }
+    let params = message.get("params")?;
+    let name = params.get("name")?.as_str()?.to_string();
+    Some((name, params.get("arguments").cloned().unwrap_or(Value::Null)))
+}
+
+/// Outcome of a `tools/call` request sent to a stdio server
+pub fn stdio_call_outcome(response: &Result<Option<Value>, MCPStdioError>) -> (Option<Value>, Option<String>) {
+    call_outcome(match response {
+        Ok(Some(response)) => rpc_result(response.clone()),
+        Ok(None) => Err(anyhow!("No response")),
+        Err(e) => Err(anyhow!("{}", e)),
+    })
+}
+
+/// Record a call made by the app itself; failures to record are logged, not returned
+pub fn audit_call(app_handle: &AppHandle, call: &NewMCPToolCall) {
+    let recorded = open_conversation_db(app_handle).and_then(|conn| record_call(&conn, call, None));
+    if let Err(e) = recorded {
+        warn!("Failed to record MCP tool call {}/{}: {}", call.server_id, call.tool_name, e);
+    }
+}
+
+// Tauri Commands
+
+/// Record a tool call made through an MCP connection the backend doesn't carry (HTTP and local
+/// transports); returns the audit id
+#[command]
+pub async fn record_mcp_tool_call(
+    call: NewMCPToolCall,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    if call.server_id.trim().is_empty() || call.tool_name.trim().is_empty() {
+        return Err("Server and tool name are required".to_string());
+    }
+    record_call(&open_db(&app_handle)?, &call, None)
+        .redact_err("Failed to record MCP tool call")
+}
+
+#[command]
+pub async fn list_mcp_tool_calls(
+    server_id: Option<String>,
+    run_id: Option<String>,
+    failed_only: Option<bool>,
+    limit: Option<usize>,
+    app_handle: AppHandle,
+) -> Result<Vec<MCPToolCallRecord>, String> {
+    list_calls(
+        &open_db(&app_handle)?,
+        server_id.as_deref(),
+        run_id.as_deref(),
+        failed_only.unwrap_or(false),
+        limit.unwrap_or(100).min(1000),
+    ).redact_err("Failed to list MCP tool calls")
+}
+
+/// Run a recorded call again against its server and audit the replay. Calls with redacted
+/// arguments need the full `arguments` passed back in. Replaying runs a tool, so it takes a
+/// session and CSRF token and the active workspace must allow the tool.
+#[allow(clippy::too_many_arguments)]
+#[command]
+pub async fn replay_mcp_tool_call(
+    session_id: String,
+    csrf_token: String,
+    csrf_double_submit: String,
+    call_id: String,
+    arguments: Option<Value>,
+    app_handle: AppHandle,
+    webview: Webview,
+    state: State<'_, AIState>,
+) -> Result<SecureResponse<MCPToolReplay>, String> {
+    let csrf = guard_secure_command("replay_mcp_tool_call", &session_id, &csrf_token, &csrf_double_submit, webview.label())?;
+
+    let original = load_call(&open_db(&app_handle)?, &call_id).redacted()?;
+    ensure_tool_allowed(&app_handle, &state.storage, &[&original.tool_name]).redacted()?;
+    let arguments = replay_arguments(&original, arguments).redacted()?;
+    let server = find_server(&state.storage, &original.server_id).redacted()?;
+
+    let started = Instant::now();
+    let mut guard = GuardrailScope::new(&app_handle, &state.storage, None);
+    let (result, error) = call_outcome(call_server_tool(&app_handle, &server, &original.tool_name, &arguments, &mut guard).await);
+
+    let replay = NewMCPToolCall {
+        server_id: original.server_id.clone(),
+        tool_name: original.tool_name.clone(),
+        run_id: original.run_id.clone(),
+        arguments,
+        duration_ms: Some(started.elapsed().as_millis() as u64),
+        result_size: result.as_ref().map(|r| r.to_string().len() as u64),
+        success: error.is_none(),
+        error,
+    };
+    let conn = open_db(&app_handle)?;
+    let replay_id = record_call(&conn, &replay, Some(&call_id))
+        .redact_err("Failed to record replayed call")?;
+    let call = load_call(&conn, &replay_id).redacted()?;
+
+    if let Some(error) = &call.error {
+        warn!("Replay of MCP tool call {} failed: {}", call_id, error);
+    }
+    info!("Replayed MCP tool call {} as {}", call_id, replay_id);
+    let replay = MCPToolReplay {
+        arguments_match: call.arguments_hash == original.arguments_hash,
+        call,
+        result,
+    };
+    Ok(SecureResponse { data: replay, csrf })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::database::migrations::{migrate, CONVERSATION_MIGRATIONS};
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, CONVERSATION_MIGRATIONS).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_redact_arguments() {
+        let arguments = json!({
+            "query": "rust",
+            "api_key": "sk-123",
+            "auth_header": { "scheme": "basic" },
+            "account": { "token": "abc", "user": "me" },
+            "headers": [{ "Authorization": "Bearer x" }],
+            "password": null
+        });
+        let (redacted, fields) = redact_arguments(&arguments);
+
+        assert_eq!(redacted["query"], "rust");
+        assert_eq!(redacted["api_key"], REDACTED);
+        assert_eq!(redacted["auth_header"], REDACTED);
+        assert_eq!(redacted["account"]["user"], "me");
+        assert!(fields.contains(&"account.token".to_string()));
+        assert!(fields.contains(&"headers[0].Authorization".to_string()));
+        assert!(!redacted.to_string().contains("sk-123"));
+        assert_eq!(redacted["password"], Value::Null);
+    }
+
+    #[test]
+    fn test_record_and_replay_arguments() {
+        let conn = setup();
+        let mut call = NewMCPToolCall {
+            server_id: "github".to_string(),
+            tool_name: "search_issues".to_string(),
+            run_id: Some("run-1".to_string()),
+            arguments: json!({ "q": "bug" }),
+            duration_ms: Some(120),
+            result_size: Some(2048),
+            success: false,
+            error: Some("rate limited".to_string()),
+        };
+        let plain_id = record_call(&conn, &call, None).unwrap();
+        call.arguments = json!({ "q": "bug", "token": "ghp_secret" });
+        call.success = true;
+        call.error = None;
+        let secret_id = record_call(&conn, &call, None).unwrap();
+
+        let plain = load_call(&conn, &plain_id).unwrap();
+        assert_eq!(replay_arguments(&plain, None).unwrap(), json!({ "q": "bug" }));
+        assert_eq!(plain.arguments_hash, hash_arguments(&json!({ "q": "bug" })));
+
+        let secret = load_call(&conn, &secret_id).unwrap();
+        assert_eq!(secret.redacted_fields, vec!["token".to_string()]);
+        assert!(replay_arguments(&secret, None).is_err());
+        assert_eq!(hash_arguments(&call.arguments), secret.arguments_hash);
+
+        let failed = list_calls(&conn, Some("github"), None, true, 10).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].id, plain_id);
+        assert_eq!(list_calls(&conn, None, Some("run-1"), false, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_stdio_tool_calls_are_recognized() {
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": "tools/call", "params": { "name": "read", "arguments": { "path": "a" } } });
+        assert_eq!(tool_call_request(&request), Some(("read".to_string(), json!({ "path": "a" }))));
+        assert_eq!(tool_call_request(&json!({ "id": 8, "method": "tools/list" })), None);
+
+        let (result, error) = stdio_call_outcome(&Ok(Some(json!({ "id": 7, "result": { "content": [] } }))));
+        assert_eq!((result, error), (Some(json!({ "content": [] })), None));
+        let (_, error) = stdio_call_outcome(&Ok(Some(json!({ "id": 7, "error": { "message": "no such tool" } }))));
+        assert!(error.unwrap().contains("no such tool"));
+        let (_, error) = stdio_call_outcome(&Err(MCPStdioError::ProcessExited(42)));
+        assert!(error.unwrap().contains("exited"));
+    }
+}
@@ -1,8 +1,16 @@
-use tauri::{command, AppHandle, Manager, Emitter};
+use tauri::{command, AppHandle, Manager, Emitter, State};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex};
+use std::process::Command;
+use std::time::Instant;
+
+use super::audit::{audit_call, stdio_call_outcome, tool_call_request, NewMCPToolCall};
+use super::health::{spawn_health_checks, ConnectionHealth};
+use super::monitor::MCPProcessUsage;
+use super::stdio::{send_message, spawn_process, MCPProcessMap, MCPStdioError};
+use crate::ai::{active_workspace, enforce_path_policy, session_scoped_path, AIState, GuardrailScope, PathAccess, RedactErr};
+use crate::database::agent_definitions::list_definitions;
+use crate::database::open_conversation_db;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MCPServer {
@@ -17,111 +25,181 @@ pub struct MCPServer {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MCPProcessInfo {
     pub pid: u32,
+    /// Registry id of the server, when the frontend gave one
+    #[serde(default)]
+    pub server_id: Option<String>,
     pub command: String,
     pub args: Vec<String>,
+    #[serde(default)]
+    pub health: ConnectionHealth,
+    /// Latest resource sample, once the monitor has taken one
+    #[serde(default)]
+    pub usage: Option<MCPProcessUsage>,
+    /// Times the resource monitor has restarted this server
+    #[serde(default)]
+    pub restarts: u32,
 }
 
-type ProcessMap = Arc<Mutex<HashMap<u32, MCPProcessInfo>>>;
-
+/// Start an MCP server process speaking newline-delimited JSON-RPC over stdio. Its roots are
+/// the allowed roots of `workspace`'s path policy, the active workspace's when not given;
+/// `server_id` names the server in the audit trail.
 #[command]
 pub async fn start_mcp_process(
     app: AppHandle,
     command: String,
     args: Vec<String>,
     env: HashMap<String, String>,
-) -> Result<serde_json::Value, String> {
-    let mut cmd = Command::new(&command);
-    cmd.args(&args)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
-
-    for (key, value) in env {
-        cmd.env(key, value);
-    }
-
-    let mut child = cmd.spawn().map_err(|e| format!("Failed to start process: {}", e))?;
-    
-    let pid = child.id();
-    
-    // Store process info
-    let processes = app.state::<ProcessMap>();
-    {
-        let mut procs = processes.lock().unwrap();
-        procs.insert(pid, MCPProcessInfo {
-            pid,
-            command: command.clone(),
-            args: args.clone(),
-        });
-    }
-
-    // Spawn task to handle process I/O - simplified for now
-    let app_handle = app.clone();
-    tokio::spawn(async move {
-        // Wait for process to exit
-        let _ = child.wait();
-        let _ = app_handle.emit(&format!("mcp_close_{}", pid), ());
-        
-        // Clean up process info
-        let processes = app_handle.state::<ProcessMap>();
-        let mut procs = processes.lock().unwrap();
-        procs.remove(&pid);
-    });
-
+    workspace: Option<String>,
+    server_id: Option<String>,
+) -> Result<serde_json::Value, MCPStdioError> {
+    let workspace = workspace.unwrap_or_else(|| active_workspace(&app.state::<AIState>().storage));
+    let pid = spawn_process(&app, server_id, command, args, env, workspace)?;
+    spawn_health_checks(app, pid);
     Ok(serde_json::json!({ "pid": pid }))
 }
 
+/// Running MCP processes with their health
+#[command]
+pub async fn list_mcp_processes(
+    processes: State<'_, MCPProcessMap>,
+) -> Result<Vec<MCPProcessInfo>, String> {
+    let mut infos: Vec<MCPProcessInfo> = processes.lock().unwrap()
+        .values()
+        .map(|p| p.info.clone())
+        .collect();
+    infos.sort_by_key(|info| info.pid);
+    Ok(infos)
+}
+
 #[command]
 pub async fn stop_mcp_process(
     app: AppHandle,
     pid: u32,
 ) -> Result<(), String> {
-    let processes = app.state::<ProcessMap>();
+    let processes = app.state::<MCPProcessMap>();
     
-    // Remove from our tracking
-    {
-        let mut procs = processes.lock().unwrap();
-        procs.remove(&pid);
+    // Remove from our tracking; dropping its writer closes the process's stdin
+    if let Some(process) = processes.lock().unwrap().remove(&pid) {
+        tracing::info!("Stopping MCP process {} ({})", pid, process.info.command);
     }
 
-    // Try to terminate the process gracefully
+    // Give it a moment to exit gracefully before force killing it
+    terminate_process(pid, std::time::Duration::from_millis(100)).await;
+
+    Ok(())
+}
+
+/// Ask a server and the processes it started to exit with SIGTERM, killing them if any are
+/// still running after `grace`. Spawned servers lead their own process group.
+pub async fn terminate_process(pid: u32, grace: std::time::Duration) {
     #[cfg(unix)]
     {
         use nix::sys::signal::{self, Signal};
         use nix::unistd::Pid;
-        
-        let process_pid = Pid::from_raw(pid as i32);
-        let _ = signal::kill(process_pid, Signal::SIGTERM);
-        
-        // Give it a moment to exit gracefully
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        
-        // Force kill if still running
-        let _ = signal::kill(process_pid, Signal::SIGKILL);
+
+        let group = Pid::from_raw(pid as i32);
+        if signal::killpg(group, Signal::SIGTERM).is_err() {
+            return;
+        }
+        let deadline = tokio::time::Instant::now() + grace;
+        while tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            // Signal 0 only checks that some process in the group still exists
+            if signal::killpg(group, None).is_err() {
+                return;
+            }
+        }
+        let _ = signal::killpg(group, Signal::SIGKILL);
     }
 
     #[cfg(windows)]
     {
         let _ = Command::new("taskkill")
-            .args(&["/PID", &pid.to_string(), "/F"])
+            .args(["/PID", &pid.to_string(), "/T"])
+            .output();
+        tokio::time::sleep(grace).await;
+        let _ = Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
             .output();
     }
+}
 
-    Ok(())
+/// Kill a server's process group and every process in `tree`, which also catches children
+/// that moved to a group of their own
+pub fn kill_process_tree(tree: &[u32]) {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{self, Signal};
+        use nix::unistd::Pid;
+
+        if let Some(&leader) = tree.first() {
+            let _ = signal::killpg(Pid::from_raw(leader as i32), Signal::SIGKILL);
+        }
+        for &pid in tree {
+            let _ = signal::kill(Pid::from_raw(pid as i32), Signal::SIGKILL);
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        for pid in tree {
+            let _ = Command::new("taskkill")
+                .args(["/PID", &pid.to_string(), "/T", "/F"])
+                .output();
+        }
+    }
 }
 
+/// Pass the result of a `tools/call` response through the tool-output guardrails
+fn guard_tool_response(
+    mut guard: GuardrailScope<'_>,
+    mut response: Option<serde_json::Value>,
+) -> Result<Option<serde_json::Value>, MCPStdioError> {
+    if let Some(result) = response.as_mut().and_then(|r| r.get_mut("result")) {
+        guard.check_tool_result(result)
+            .map_err(|e| MCPStdioError::GuardrailBlocked(e.to_string()))?;
+    }
+    Ok(response)
+}
+
+/// Send a JSON-RPC message to an MCP process. A request resolves with its response, or fails
+/// after `timeout_ms`; notifications and responses resolve with null once queued. `tools/call`
+/// requests are audited here rather than left to the frontend to report, and their results
+/// pass the tool-output guardrails of `agent_id`, or the defaults without one.
 #[command]
 pub async fn send_mcp_message(
-    app: AppHandle,
     pid: u32,
     message: String,
-) -> Result<(), String> {
-    // In a real implementation, we would send this to the process stdin
-    // For now, just emit it as an event for demonstration
-    app.emit(&format!("mcp_send_{}", pid), &message)
-        .map_err(|e| format!("Failed to send message: {}", e))?;
-    
-    Ok(())
+    timeout_ms: Option<u64>,
+    agent_id: Option<String>,
+    app: AppHandle,
+    processes: State<'_, MCPProcessMap>,
+    state: State<'_, AIState>,
+) -> Result<Option<serde_json::Value>, MCPStdioError> {
+    let tool_call = serde_json::from_str(&message).ok().as_ref().and_then(tool_call_request);
+    let server_id = processes.lock().unwrap().get(&pid)
+        .map(|process| process.info.server_id.clone().unwrap_or_else(|| process.info.command.clone()));
+
+    let started = Instant::now();
+    let mut response = send_message(&processes, pid, &message, timeout_ms).await;
+    if tool_call.is_some() {
+        let guard = GuardrailScope::new(&app, &state.storage, agent_id.as_deref());
+        response = response.and_then(|response| guard_tool_response(guard, response));
+    }
+    if let (Some((tool_name, arguments)), Some(server_id)) = (tool_call, server_id) {
+        let (result, error) = stdio_call_outcome(&response);
+        audit_call(&app, &NewMCPToolCall {
+            server_id,
+            tool_name,
+            run_id: None,
+            arguments,
+            duration_ms: Some(started.elapsed().as_millis() as u64),
+            result_size: result.as_ref().map(|r| r.to_string().len() as u64),
+            success: error.is_none(),
+            error,
+        });
+    }
+    response
 }
 
 #[command]
@@ -135,7 +213,7 @@ pub async fn connect_local_mcp(
     
     // Simulate connection success
     app.emit(&format!("local_mcp_connected_{}", socket_path), ())
-        .map_err(|e| format!("Failed to emit connection event: {}", e))?;
+        .redact_err("Failed to emit connection event")?;
     
     Ok(())
 }
@@ -148,7 +226,7 @@ pub async fn disconnect_local_mcp(
     tracing::info!("Disconnecting from local MCP: {}", socket_path);
     
     app.emit(&format!("local_mcp_close_{}", socket_path), ())
-        .map_err(|e| format!("Failed to emit disconnect event: {}", e))?;
+        .redact_err("Failed to emit disconnect event")?;
     
     Ok(())
 }
@@ -164,48 +242,34 @@ pub async fn send_local_mcp_message(
     
     // Simulate response
     app.emit(&format!("local_mcp_message_{}", socket_path), &message)
-        .map_err(|e| format!("Failed to send local message: {}", e))?;
+        .redact_err("Failed to send local message")?;
     
     Ok(())
 }
 
 // MCP Server resource and tool commands
+
+/// Agents stored in `agent_settings`, summarized for the `banshee://agents/configs` resource
 #[command]
-pub async fn get_agent_configs() -> Result<String, String> {
-    let configs = serde_json::json!({
-        "agents": [
-            {
-                "id": "assistant",
-                "name": "AI Assistant",
-                "description": "General purpose conversational agent",
-                "capabilities": ["chat", "analysis", "writing"],
-                "model": "gpt-4o"
-            },
-            {
-                "id": "fileManager",
-                "name": "File Manager",
-                "description": "Manages files and directories",
-                "capabilities": ["file_read", "file_write", "directory_list"],
-                "model": "gpt-4o-mini"
-            },
-            {
-                "id": "webAgent",
-                "name": "Web Agent",
-                "description": "Fetches and processes web content",
-                "capabilities": ["web_fetch", "html_parse", "content_extract"],
-                "model": "gpt-4o"
-            },
-            {
-                "id": "developer",
-                "name": "Developer Agent",
-                "description": "Code analysis and development tasks",
-                "capabilities": ["code_review", "test_generation", "refactoring"],
-                "model": "gpt-4o"
-            }
-        ]
-    });
-    
-    Ok(configs.to_string())
+pub async fn get_agent_configs(app_handle: AppHandle) -> Result<String, String> {
+    let conn = open_conversation_db(&app_handle)
+        .redact_err("Failed to open conversation database")?;
+    let agents: Vec<serde_json::Value> = list_definitions(&conn)
+        .redact_err("Failed to list agents")?
+        .into_iter()
+        .map(|agent| {
+            let config = &agent.configuration;
+            serde_json::json!({
+                "id": agent.id,
+                "name": config.get("name"),
+                "description": config.get("description"),
+                "capabilities": config.get("tools").cloned().unwrap_or_else(|| serde_json::json!([])),
+                "model": config.get("model")
+            })
+        })
+        .collect();
+
+    Ok(serde_json::json!({ "agents": agents }).to_string())
 }
 
 #[command]
@@ -232,8 +296,17 @@ pub async fn get_conversation_history() -> Result<String, String> {
     Ok(history.to_string())
 }
 
+/// System summary; `mcp_processes` carries the latest resource sample of each spawned server
 #[command]
-pub async fn get_system_status() -> Result<String, String> {
+pub async fn get_system_status(
+    processes: State<'_, MCPProcessMap>,
+) -> Result<String, String> {
+    let mut mcp_processes: Vec<MCPProcessInfo> = processes.lock().unwrap()
+        .values()
+        .map(|p| p.info.clone())
+        .collect();
+    mcp_processes.sort_by_key(|info| info.pid);
+
     let status = serde_json::json!({
         "system": {
             "uptime": "2h 15m",
@@ -241,7 +314,7 @@ pub async fn get_system_status() -> Result<String, String> {
             "memory_usage": 45.2,
             "disk_usage": 67.8,
             "active_agents": 3,
-            "mcp_connections": 2,
+            "mcp_connections": mcp_processes.len(),
             "health": "healthy"
         },
         "services": {
@@ -249,7 +322,8 @@ pub async fn get_system_status() -> Result<String, String> {
             "mcp_server": "running",
             "file_monitor": "running",
             "security_manager": "running"
-        }
+        },
+        "mcp_processes": mcp_processes
     });
     
     Ok(status.to_string())
@@ -304,33 +378,57 @@ pub async fn execute_agent_tool(
 }
 
 #[command]
-pub async fn read_file_tool(path: String) -> Result<String, String> {
+pub async fn read_file_tool(
+    path: String,
+    workspace: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<String, String> {
     use std::fs;
     
-    fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read file {}: {}", path, e))
+    let scoped = session_scoped_path(session_id.as_deref(), &path)?;
+    let resolved = enforce_path_policy(&state.storage, workspace.as_deref(), &scoped, PathAccess::Read)?;
+
+    fs::read_to_string(&resolved)
+        .redact_err(&format!("Failed to read file {}", path))
 }
 
 #[command]
-pub async fn write_file_tool(path: String, contents: String) -> Result<(), String> {
+pub async fn write_file_tool(
+    path: String,
+    contents: String,
+    workspace: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<(), String> {
     use std::fs;
     
-    fs::write(&path, contents)
-        .map_err(|e| format!("Failed to write file {}: {}", path, e))
+    let access = PathAccess::Write { size: contents.len() as u64 };
+    let scoped = session_scoped_path(session_id.as_deref(), &path)?;
+    let resolved = enforce_path_policy(&state.storage, workspace.as_deref(), &scoped, access)?;
+
+    fs::write(&resolved, contents)
+        .redact_err(&format!("Failed to write file {}", path))
 }
 
 #[command]
-pub async fn list_files_tool(path: String, recursive: bool) -> Result<Vec<String>, String> {
+pub async fn list_files_tool(
+    path: String,
+    recursive: bool,
+    workspace: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AIState>,
+) -> Result<Vec<String>, String> {
     fn list_files_sync(path: &str, recursive: bool) -> Result<Vec<String>, String> {
         use std::fs;
         
         let entries = fs::read_dir(path)
-            .map_err(|e| format!("Failed to read directory {}: {}", path, e))?;
+            .redact_err(&format!("Failed to read directory {}", path))?;
         
         let mut files = Vec::new();
         
         for entry in entries {
-            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+            let entry = entry.redact_err("Failed to read entry")?;
             let entry_path = entry.path();
             
             if entry_path.is_file() {
@@ -344,7 +442,10 @@ pub async fn list_files_tool(path: String, recursive: bool) -> Result<Vec<String
         Ok(files)
     }
     
-    list_files_sync(&path, recursive)
+    let scoped = session_scoped_path(session_id.as_deref(), &path)?;
+    let resolved = enforce_path_policy(&state.storage, workspace.as_deref(), &scoped, PathAccess::List)?;
+
+    list_files_sync(&resolved.to_string_lossy(), recursive)
 }
 
 #[command]
@@ -362,7 +463,7 @@ pub async fn execute_command_tool(command: String, args: Vec<String>) -> Result<
     let output = Command::new(&command)
         .args(&args)
         .output()
-        .map_err(|e| format!("Failed to execute command: {}", e))?;
+        .redact_err("Failed to execute command")?;
     
     if output.status.success() {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
@@ -0,0 +1,212 @@
+//! Liveness of MCP servers: spawned processes are pinged on an interval and their rolling
+//! latency and failures recorded, and registered servers can be probed on demand.
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{info, warn};
+
+use super::registry::MCPServerDefinition;
+use super::stdio::{send_message, MCPProcessMap, MCPStdioError};
+use super::tool_catalog::{list_tools, open_session, DEFAULT_FETCH_TIMEOUT_MS};
+
+/// Time between pings of a spawned server
+pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A ping slower than this counts as a failure
+pub const PING_TIMEOUT_MS: u64 = 5_000;
+
+/// Pings averaged into the rolling latency
+const LATENCY_WINDOW: usize = 10;
+
+/// Rolling latency above which a server is degraded
+const DEGRADED_LATENCY_MS: u64 = 2_000;
+
+/// Consecutive failed pings before a server is unhealthy
+const UNHEALTHY_AFTER_FAILURES: u32 = 3;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// Not yet checked
+    #[default]
+    Unknown,
+    Healthy,
+    /// Slow, or failing intermittently
+    Degraded,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConnectionHealth {
+    pub status: HealthStatus,
+    pub avg_latency_ms: Option<u64>,
+    pub last_latency_ms: Option<u64>,
+    pub consecutive_failures: u32,
+    pub total_checks: u64,
+    pub total_failures: u64,
+    pub last_error: Option<String>,
+    pub last_checked: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip)]
+    samples: VecDeque<u64>,
+}
+
+impl ConnectionHealth {
+    pub fn record_success(&mut self, latency_ms: u64) {
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency_ms);
+        self.avg_latency_ms = Some(self.samples.iter().sum::<u64>() / self.samples.len() as u64);
+        self.last_latency_ms = Some(latency_ms);
+        self.consecutive_failures = 0;
+        self.checked();
+    }
+
+    pub fn record_failure(&mut self, error: String) {
+        self.consecutive_failures += 1;
+        self.total_failures += 1;
+        self.last_error = Some(error);
+        self.checked();
+    }
+
+    fn checked(&mut self) {
+        self.total_checks += 1;
+        self.last_checked = Some(chrono::Utc::now());
+        self.status = if self.consecutive_failures >= UNHEALTHY_AFTER_FAILURES {
+            HealthStatus::Unhealthy
+        } else if self.consecutive_failures > 0 || self.avg_latency_ms.is_some_and(|ms| ms > DEGRADED_LATENCY_MS) {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+    }
+}
+
+/// Result of probing a registered server with a fresh handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPConnectionDiagnostics {
+    pub server_id: String,
+    pub reachable: bool,
+    pub handshake_ms: Option<u64>,
+    pub tool_count: Option<usize>,
+    pub server_version: Option<String>,
+    pub total_ms: u64,
+    pub last_error: Option<String>,
+}
+
+/// Open a session with a server and list its tools, timing each step. A stdio server already
+/// connected is probed over that connection.
+pub async fn diagnose_server(app: &AppHandle, server: &MCPServerDefinition) -> MCPConnectionDiagnostics {
+    let started = Instant::now();
+    let mut diagnostics = MCPConnectionDiagnostics {
+        server_id: server.id.clone(),
+        reachable: false,
+        handshake_ms: None,
+        tool_count: None,
+        server_version: None,
+        total_ms: 0,
+        last_error: None,
+    };
+
+    let probe = async {
+        let (mut session, server_version) = open_session(app, server).await?;
+        diagnostics.handshake_ms = Some(started.elapsed().as_millis() as u64);
+        diagnostics.server_version = server_version;
+        diagnostics.tool_count = Some(list_tools(&mut session).await?.len());
+        anyhow::Ok(())
+    };
+    let timeout = Duration::from_millis(server.timeout_ms.unwrap_or(DEFAULT_FETCH_TIMEOUT_MS));
+    let outcome = tokio::time::timeout(timeout, probe).await;
+    match outcome {
+        Ok(Ok(())) => diagnostics.reachable = true,
+        Ok(Err(e)) => diagnostics.last_error = Some(e.to_string()),
+        Err(_) => diagnostics.last_error = Some(format!("Timed out after {} ms", timeout.as_millis())),
+    }
+    diagnostics.total_ms = started.elapsed().as_millis() as u64;
+    diagnostics
+}
+
+/// Ping a spawned process every `PING_INTERVAL` until it exits, emitting `mcp_health_changed`
+/// whenever its status moves
+pub fn spawn_health_checks(app: AppHandle, pid: u32) {
+    tokio::spawn(async move {
+        let processes = app.state::<MCPProcessMap>().inner().clone();
+        for ping in 1u64.. {
+            tokio::time::sleep(PING_INTERVAL).await;
+
+            let message = json!({ "jsonrpc": "2.0", "id": format!("banshee-ping-{}", ping), "method": "ping" });
+            let started = Instant::now();
+            let outcome = send_message(&processes, pid, &message.to_string(), Some(PING_TIMEOUT_MS)).await;
+            if matches!(outcome, Err(MCPStdioError::UnknownProcess(_) | MCPStdioError::ProcessExited(_))) {
+                break;
+            }
+
+            let changed = {
+                let mut processes = processes.lock().unwrap();
+                let Some(process) = processes.get_mut(&pid) else { break };
+                let health = &mut process.info.health;
+                let before = health.status;
+                // Any response, even a method-not-found error, shows the server is alive
+                match outcome {
+                    Ok(_) => health.record_success(started.elapsed().as_millis() as u64),
+                    Err(e) => health.record_failure(e.to_string()),
+                }
+                (health.status != before).then(|| process.info.clone())
+            };
+
+            if let Some(info) = changed {
+                match info.health.status {
+                    HealthStatus::Healthy => info!("MCP process {} is healthy", pid),
+                    status => warn!("MCP process {} is {:?}: {:?}", pid, status, info.health.last_error),
+                }
+                let _ = app.emit("mcp_health_changed", &info);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_transitions() {
+        let mut health = ConnectionHealth::default();
+        assert_eq!(health.status, HealthStatus::Unknown);
+
+        health.record_success(40);
+        health.record_success(60);
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.avg_latency_ms, Some(50));
+
+        for _ in 0..UNHEALTHY_AFTER_FAILURES - 1 {
+            health.record_failure("timeout".to_string());
+            assert_eq!(health.status, HealthStatus::Degraded);
+        }
+        health.record_failure("timeout".to_string());
+        assert_eq!(health.status, HealthStatus::Unhealthy);
+        assert_eq!(health.total_failures, UNHEALTHY_AFTER_FAILURES as u64);
+
+        health.record_success(50);
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.total_checks, 3 + UNHEALTHY_AFTER_FAILURES as u64);
+    }
+
+    #[test]
+    fn test_slow_latency_degrades_over_window() {
+        let mut health = ConnectionHealth::default();
+        for _ in 0..LATENCY_WINDOW {
+            health.record_success(DEGRADED_LATENCY_MS * 2);
+        }
+        assert_eq!(health.status, HealthStatus::Degraded);
+
+        for _ in 0..LATENCY_WINDOW {
+            health.record_success(10);
+        }
+        assert_eq!(health.avg_latency_ms, Some(10));
+        assert_eq!(health.status, HealthStatus::Healthy);
+    }
+}
@@ -1,5 +1,25 @@
+pub mod audit;
 pub mod commands;
+pub mod health;
+pub mod monitor;
+pub mod oauth_flow;
 pub mod oauth_storage;
+pub mod prompts;
+pub mod registry;
+pub mod roots;
+pub mod router;
+pub mod stdio;
+pub mod tool_catalog;
 
+pub use audit::*;
 pub use commands::*;
-pub use oauth_storage::*;
\ No newline at end of file
+pub use health::*;
+pub use monitor::*;
+pub use oauth_flow::*;
+pub use oauth_storage::*;
+pub use prompts::*;
+pub use registry::*;
+pub use roots::*;
+pub use router::*;
+pub use stdio::*;
+pub use tool_catalog::*;
\ No newline at end of file
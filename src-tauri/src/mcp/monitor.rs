@@ -0,0 +1,363 @@
+//! Resource usage of spawned MCP servers. Every server is sampled for CPU, resident memory and
+//! uptime, counting the processes it started, and one that stays over the configured limits is
+//! terminated or restarted along with them.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, System};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tracing::{error, info, warn};
+
+use super::commands::kill_process_tree;
+use super::health::spawn_health_checks;
+use super::stdio::{reinitialize, spawn_process, MCPProcess, MCPProcessMap};
+use crate::ai::{dispatch_notification, AIState, NotificationPayload, NotificationState, RedactErr, StorageManager};
+
+/// Time between resource samples
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Restarts allowed for one server before it is terminated instead
+pub const MAX_RESTARTS: u32 = 3;
+
+/// Event emitted with an `MCPLimitAction` whenever a server is terminated or restarted
+pub const MCP_LIMIT_EXCEEDED_EVENT: &str = "mcp_resource_limit_exceeded";
+
+/// Prefix of the event, suffixed with the old pid, that carries a restarted server's new pid
+pub const MCP_RESTARTED_EVENT_PREFIX: &str = "mcp_restarted_";
+
+const RESOURCE_LIMITS_SETTING: &str = "mcp_resource_limits";
+
+/// Usage of a server together with the processes it started
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MCPProcessUsage {
+    /// Share of one core; above 100 when several cores are busy
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+    pub uptime_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitAction {
+    #[default]
+    Terminate,
+    Restart,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MCPResourceLimits {
+    pub max_memory_bytes: Option<u64>,
+    pub max_cpu_percent: Option<f32>,
+    /// Consecutive samples over a limit before acting, so short spikes are tolerated
+    pub grace_samples: u32,
+    pub action: LimitAction,
+}
+
+impl Default for MCPResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: None,
+            max_cpu_percent: None,
+            grace_samples: 3,
+            action: LimitAction::Terminate,
+        }
+    }
+}
+
+impl MCPResourceLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.grace_samples == 0 {
+            return Err("grace_samples must be at least 1".to_string());
+        }
+        if self.max_memory_bytes == Some(0) {
+            return Err("max_memory_bytes must be positive".to_string());
+        }
+        if self.max_cpu_percent.is_some_and(|cpu| cpu.is_nan() || cpu <= 0.0) {
+            return Err("max_cpu_percent must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Which limit `usage` is over, if any
+    pub fn exceeded(&self, usage: &MCPProcessUsage) -> Option<String> {
+        if let Some(max) = self.max_memory_bytes.filter(|max| usage.memory_bytes > *max) {
+            return Some(format!("memory {} MB over the {} MB limit", usage.memory_bytes / 1_048_576, max / 1_048_576));
+        }
+        if let Some(max) = self.max_cpu_percent.filter(|max| usage.cpu_percent > *max) {
+            return Some(format!("CPU {:.0}% over the {:.0}% limit", usage.cpu_percent, max));
+        }
+        None
+    }
+}
+
+pub fn load_resource_limits(storage: &StorageManager) -> Result<MCPResourceLimits> {
+    match storage.get_setting(RESOURCE_LIMITS_SETTING)? {
+        Some(value) => serde_json::from_value(value).context("Stored MCP resource limits are malformed"),
+        None => Ok(MCPResourceLimits::default()),
+    }
+}
+
+/// What the monitor did to a server over its limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPLimitAction {
+    pub pid: u32,
+    pub command: String,
+    pub reason: String,
+    pub action: LimitAction,
+    /// Pid of the replacement process when restarted
+    pub new_pid: Option<u32>,
+}
+
+/// Counts consecutive over-limit samples per process
+#[derive(Debug, Default)]
+pub struct LimitTracker {
+    breaches: HashMap<u32, u32>,
+}
+
+impl LimitTracker {
+    /// Record a sample; returns the reason once a process has been over a limit for the grace period
+    pub fn observe(&mut self, pid: u32, usage: &MCPProcessUsage, limits: &MCPResourceLimits) -> Option<String> {
+        let Some(reason) = limits.exceeded(usage) else {
+            self.breaches.remove(&pid);
+            return None;
+        };
+        let count = self.breaches.entry(pid).or_default();
+        *count += 1;
+        if *count < limits.grace_samples {
+            return None;
+        }
+        self.breaches.remove(&pid);
+        Some(reason)
+    }
+
+    /// Forget processes that are no longer running
+    pub fn retain(&mut self, running: &[u32]) {
+        self.breaches.retain(|pid, _| running.contains(pid));
+    }
+}
+
+/// `root` and every process descended from it, given each process's parent
+pub fn process_tree(root: u32, parents: impl IntoIterator<Item = (u32, Option<u32>)>) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (pid, parent) in parents {
+        if let Some(parent) = parent.filter(|parent| *parent != pid) {
+            children.entry(parent).or_default().push(pid);
+        }
+    }
+    let mut tree = vec![root];
+    let mut next = 0;
+    while let Some(&pid) = tree.get(next) {
+        for &child in children.get(&pid).into_iter().flatten() {
+            if !tree.contains(&child) {
+                tree.push(child);
+            }
+        }
+        next += 1;
+    }
+    tree
+}
+
+/// Processes of the server at `pid`, or None once it has exited
+fn server_tree(system: &System, pid: u32) -> Option<Vec<u32>> {
+    system.process(Pid::from_u32(pid))?;
+    let parents = system.processes().iter().map(|(pid, process)| (pid.as_u32(), process.parent().map(|p| p.as_u32())));
+    Some(process_tree(pid, parents))
+}
+
+/// Usage summed over a server's tree; uptime is the server's own
+fn sample(system: &System, tree: &[u32]) -> Option<MCPProcessUsage> {
+    let root = system.process(Pid::from_u32(*tree.first()?))?;
+    let mut usage = MCPProcessUsage { cpu_percent: 0.0, memory_bytes: 0, uptime_secs: root.run_time() };
+    for process in tree.iter().filter_map(|pid| system.process(Pid::from_u32(*pid))) {
+        usage.cpu_percent += process.cpu_usage();
+        usage.memory_bytes += process.memory();
+    }
+    Some(usage)
+}
+
+/// Start a replacement for `process` and repeat the client's handshake with it. The frontend is
+/// told the new pid before the old server goes, so it never sees the server close.
+async fn restart(app: &AppHandle, pid: u32, process: &MCPProcess) -> Result<u32> {
+    let info = process.info.clone();
+    let new_pid = spawn_process(app, info.server_id, info.command, info.args, process.env.clone(), process.workspace.clone())?;
+    if let Some(replacement) = app.state::<MCPProcessMap>().lock().unwrap().get_mut(&new_pid) {
+        replacement.info.restarts = process.info.restarts + 1;
+    }
+    if let Some(params) = process.initialize.clone() {
+        let processes = app.state::<MCPProcessMap>().inner().clone();
+        // Sending the request also keeps its params for the next restart
+        if let Err(e) = reinitialize(&processes, new_pid, params).await {
+            if processes.lock().unwrap().remove(&new_pid).is_some() {
+                kill_process_tree(&[new_pid]);
+            }
+            return Err(e.into());
+        }
+    }
+    spawn_health_checks(app.clone(), new_pid);
+    if let Err(e) = app.emit(&format!("{}{}", MCP_RESTARTED_EVENT_PREFIX, pid), new_pid) {
+        error!("Failed to emit MCP restart of {}: {}", pid, e);
+    }
+    Ok(new_pid)
+}
+
+/// Stop tracking a server and kill its process tree, after starting its replacement when the
+/// action and restart budget allow
+async fn enforce(app: &AppHandle, tree: Vec<u32>, reason: String, limits: &MCPResourceLimits) -> Option<MCPLimitAction> {
+    let pid = *tree.first()?;
+    let process = app.state::<MCPProcessMap>().lock().unwrap().remove(&pid)?;
+
+    let new_pid = if limits.action == LimitAction::Restart && process.info.restarts < MAX_RESTARTS {
+        restart(app, pid, &process).await
+            .map_err(|e| error!("Failed to restart MCP process {}: {}", pid, e))
+            .ok()
+    } else {
+        None
+    };
+    kill_process_tree(&tree);
+
+    Some(MCPLimitAction {
+        pid,
+        command: process.info.command.clone(),
+        reason,
+        action: if new_pid.is_some() { LimitAction::Restart } else { LimitAction::Terminate },
+        new_pid,
+    })
+}
+
+fn notify(app: &AppHandle, action: &MCPLimitAction) {
+    let (title, message) = match action.new_pid {
+        Some(new_pid) => (
+            "MCP server restarted",
+            format!("{} (pid {}) was restarted as pid {}: {}", action.command, action.pid, new_pid, action.reason),
+        ),
+        None => (
+            "MCP server stopped",
+            format!("{} (pid {}) was terminated: {}", action.command, action.pid, action.reason),
+        ),
+    };
+    warn!("{}", message);
+    if let Err(e) = app.emit(MCP_LIMIT_EXCEEDED_EVENT, action) {
+        error!("Failed to emit MCP limit event: {}", e);
+    }
+    if let Some(notification_state) = app.try_state::<NotificationState>() {
+        dispatch_notification(app, &notification_state, NotificationPayload {
+            notification_id: uuid::Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            message,
+            kind: "warning".to_string(),
+            actions: Vec::new(),
+            session: None,
+        });
+    }
+}
+
+/// Sample every spawned MCP process each `SAMPLE_INTERVAL`, recording usage on its info and
+/// enforcing the stored limits
+pub fn spawn_resource_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut system = System::new();
+        let mut tracker = LimitTracker::default();
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let pids: Vec<u32> = app.state::<MCPProcessMap>().lock().unwrap().keys().copied().collect();
+            tracker.retain(&pids);
+            if pids.is_empty() {
+                continue;
+            }
+            system.refresh_processes_specifics(ProcessRefreshKind::new().with_cpu().with_memory());
+            let limits = load_resource_limits(&app.state::<AIState>().storage).unwrap_or_else(|e| {
+                warn!("Using default MCP resource limits: {}", e);
+                MCPResourceLimits::default()
+            });
+
+            for pid in pids {
+                let Some(tree) = server_tree(&system, pid) else { continue };
+                let Some(usage) = sample(&system, &tree) else { continue };
+                {
+                    let processes = app.state::<MCPProcessMap>();
+                    let mut processes = processes.lock().unwrap();
+                    let Some(process) = processes.get_mut(&pid) else { continue };
+                    process.info.usage = Some(usage.clone());
+                }
+                if let Some(reason) = tracker.observe(pid, &usage, &limits) {
+                    if let Some(action) = enforce(&app, tree, reason, &limits).await {
+                        notify(&app, &action);
+                    }
+                }
+            }
+        }
+    });
+}
+
+// Tauri Commands
+
+#[command]
+pub async fn get_mcp_resource_limits(state: State<'_, AIState>) -> Result<MCPResourceLimits, String> {
+    load_resource_limits(&state.storage).redacted()
+}
+
+/// Store the limits applied to every spawned MCP server; they take effect on the next sample
+#[command]
+pub async fn set_mcp_resource_limits(
+    limits: MCPResourceLimits,
+    state: State<'_, AIState>,
+) -> Result<(), String> {
+    limits.validate()?;
+    let value = serde_json::to_value(&limits).redacted()?;
+    state.storage.set_setting(RESOURCE_LIMITS_SETTING, value)
+        .redact_err("Failed to store MCP resource limits")?;
+    info!("Updated MCP resource limits: {:?}", limits);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(cpu_percent: f32, memory_bytes: u64) -> MCPProcessUsage {
+        MCPProcessUsage { cpu_percent, memory_bytes, uptime_secs: 60 }
+    }
+
+    #[test]
+    fn test_tracker_waits_for_grace_samples() {
+        let limits = MCPResourceLimits {
+            max_memory_bytes: Some(100 * 1_048_576),
+            max_cpu_percent: Some(90.0),
+            grace_samples: 2,
+            action: LimitAction::Restart,
+        };
+        let mut tracker = LimitTracker::default();
+
+        assert_eq!(tracker.observe(1, &usage(95.0, 0), &limits), None);
+        // A sample back under the limits resets the count
+        assert_eq!(tracker.observe(1, &usage(10.0, 0), &limits), None);
+        assert_eq!(tracker.observe(1, &usage(95.0, 0), &limits), None);
+        assert!(tracker.observe(1, &usage(95.0, 0), &limits).unwrap().contains("CPU"));
+
+        assert_eq!(tracker.observe(2, &usage(0.0, 200 * 1_048_576), &limits), None);
+        tracker.retain(&[1]);
+        assert_eq!(tracker.observe(2, &usage(0.0, 200 * 1_048_576), &limits), None);
+        assert!(tracker.observe(2, &usage(0.0, 200 * 1_048_576), &limits).unwrap().contains("memory"));
+    }
+
+    #[test]
+    fn test_process_tree_follows_descendants() {
+        let parents = [(10, Some(1)), (11, Some(10)), (12, Some(11)), (20, Some(1)), (13, Some(10)), (14, Some(14))];
+        assert_eq!(process_tree(10, parents), vec![10, 11, 13, 12]);
+        assert_eq!(process_tree(99, parents), vec![99]);
+        assert_eq!(process_tree(14, parents), vec![14]);
+    }
+
+    #[test]
+    fn test_limits_validate() {
+        assert!(MCPResourceLimits::default().validate().is_ok());
+        assert_eq!(MCPResourceLimits::default().exceeded(&usage(1000.0, u64::MAX)), None);
+        assert!(MCPResourceLimits { grace_samples: 0, ..Default::default() }.validate().is_err());
+        assert!(MCPResourceLimits { max_cpu_percent: Some(f32::NAN), ..Default::default() }.validate().is_err());
+    }
+}
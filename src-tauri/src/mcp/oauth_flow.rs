@@ -0,0 +1,816 @@
+use anyhow::{anyhow, Context, Result};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tracing::{error, info, warn};
+
+use crate::AppState;
+use crate::ai::{redact_error, RedactErr};
+
+/// Tokens are refreshed this long before they expire
+const REFRESH_MARGIN_SECONDS: i64 = 120;
+
+/// Authorizations not completed within this window are discarded
+const PENDING_TTL_MINUTES: i64 = 10;
+
+/// Attempts per scheduled refresh before giving up until the next manual refresh
+const MAX_REFRESH_ATTEMPTS: u32 = 3;
+
+const REFRESH_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+const TOKEN_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Added to the polling interval each time the server answers `slow_down` (RFC 8628 §3.5)
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+
+const CALLBACK_RESPONSE: &str = "<html><body><h3>Authorization complete</h3><p>You can close this window and return to Banshee.</p></body></html>";
+
+/// Authorization server details for an MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthClientConfig {
+    /// Not needed for the device-code flow
+    #[serde(default)]
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    /// RFC 8628 endpoint, required for the device-code flow
+    pub device_authorization_endpoint: Option<String>,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// RFC 8707 resource indicator, usually the MCP server URL
+    pub resource: Option<String>,
+}
+
+/// Token set persisted in the encrypted OAuth store, with what is needed to refresh it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredOAuthToken {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub token_type: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scope: Option<String>,
+    pub client: OAuthClientConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default = "default_token_type")]
+    token_type: String,
+    expires_in: Option<i64>,
+    refresh_token: Option<String>,
+    scope: Option<String>,
+}
+
+fn default_token_type() -> String {
+    "Bearer".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthAuthorizationStart {
+    pub server_id: String,
+    pub authorization_url: String,
+    pub redirect_uri: String,
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceAuthorizationResponse {
+    device_code: String,
+    user_code: String,
+    #[serde(alias = "verification_url")]
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: i64,
+    #[serde(default = "default_poll_interval")]
+    interval: u64,
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+/// What the user needs to authorize a device-code flow from another machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCodeStart {
+    pub server_id: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub interval_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthTokenStatus {
+    pub server_id: String,
+    pub token_type: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scope: Option<String>,
+    pub refreshable: bool,
+}
+
+impl OAuthTokenStatus {
+    fn new(server_id: &str, token: &StoredOAuthToken) -> Self {
+        Self {
+            server_id: server_id.to_string(),
+            token_type: token.token_type.clone(),
+            expires_at: token.expires_at,
+            scope: token.scope.clone(),
+            refreshable: token.refresh_token.is_some(),
+        }
+    }
+}
+
+/// Payload of the `mcp_oauth_*` events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthEvent {
+    pub server_id: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    /// For refresh failures: whether another attempt is scheduled
+    pub will_retry: bool,
+}
+
+/// RFC 7636 code verifier and its S256 challenge
+#[derive(Debug, Clone)]
+pub struct PkceChallenge {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl PkceChallenge {
+    pub fn new() -> Self {
+        Self::from_verifier(random_token(32))
+    }
+
+    pub fn from_verifier(verifier: String) -> Self {
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Self { verifier, challenge }
+    }
+}
+
+fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+struct PendingAuthorization {
+    server_id: String,
+    config: OAuthClientConfig,
+    verifier: String,
+    redirect_uri: String,
+    port: u16,
+    created_at: DateTime<Utc>,
+}
+
+/// In-flight authorizations keyed by `state`, device-code pollers and the refresh task of each server
+pub struct OAuthFlowState {
+    pending: Arc<Mutex<HashMap<String, PendingAuthorization>>>,
+    device_polls: Arc<Mutex<HashMap<String, tauri::async_runtime::JoinHandle<()>>>>,
+    refresh_tasks: Arc<Mutex<HashMap<String, tauri::async_runtime::JoinHandle<()>>>>,
+    /// Serializes refreshes so a rotated refresh token is never used twice
+    refresh_lock: tokio::sync::Mutex<()>,
+}
+
+impl OAuthFlowState {
+    pub fn new() -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            device_polls: Arc::new(Mutex::new(HashMap::new())),
+            refresh_tasks: Arc::new(Mutex::new(HashMap::new())),
+            refresh_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Stop the automatic refresh of a server's token
+    pub fn cancel_refresh(&self, server_id: &str) {
+        if let Some(task) = self.refresh_tasks.lock().unwrap().remove(server_id) {
+            task.abort();
+        }
+    }
+
+    pub fn cancel_all_refreshes(&self) {
+        for (_, task) in self.refresh_tasks.lock().unwrap().drain() {
+            task.abort();
+        }
+    }
+
+    /// Stop polling for a device-code authorization; returns whether one was running
+    pub fn cancel_device_poll(&self, server_id: &str) -> bool {
+        match self.device_polls.lock().unwrap().remove(server_id) {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn take_pending(&self, state: &str) -> Option<PendingAuthorization> {
+        self.pending.lock().unwrap().remove(state)
+    }
+}
+
+pub fn build_authorization_url(
+    config: &OAuthClientConfig,
+    redirect_uri: &str,
+    state: &str,
+    pkce: &PkceChallenge,
+) -> Result<String> {
+    let mut url = url::Url::parse(&config.authorization_endpoint)
+        .context("Invalid authorization endpoint")?;
+    {
+        let mut query = url.query_pairs_mut();
+        query.append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("state", state)
+            .append_pair("code_challenge", &pkce.challenge)
+            .append_pair("code_challenge_method", "S256");
+        if !config.scopes.is_empty() {
+            query.append_pair("scope", &config.scopes.join(" "));
+        }
+        if let Some(resource) = &config.resource {
+            query.append_pair("resource", resource);
+        }
+    }
+    Ok(url.to_string())
+}
+
+/// Extract `(code, state)` from the redirect, surfacing an authorization error if present
+pub fn parse_callback(callback_url: &str) -> Result<(String, String)> {
+    let url = url::Url::parse(callback_url).context("Invalid OAuth callback URL")?;
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    if let Some(error) = params.get("error") {
+        let description = params.get("error_description").map(|d| format!(": {}", d)).unwrap_or_default();
+        return Err(anyhow!("Authorization denied ({}){}", error, description));
+    }
+    match (params.get("code"), params.get("state")) {
+        (Some(code), Some(state)) => Ok((code.clone(), state.clone())),
+        _ => Err(anyhow!("OAuth callback is missing the code or state parameter")),
+    }
+}
+
+/// How long until the token should be refreshed; None when it cannot or need not be
+pub fn refresh_due_in(token: &StoredOAuthToken, now: DateTime<Utc>) -> Option<Duration> {
+    token.refresh_token.as_ref()?;
+    let due = token.expires_at? - chrono::Duration::seconds(REFRESH_MARGIN_SECONDS);
+    Some((due - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// POST a form to an authorization server endpoint; OAuth error responses are returned as `Ok(Err(_))`
+async fn post_form(
+    config: &OAuthClientConfig,
+    endpoint: &str,
+    mut form: Vec<(&str, String)>,
+) -> Result<std::result::Result<String, TokenErrorResponse>> {
+    form.push(("client_id", config.client_id.clone()));
+    if let Some(secret) = &config.client_secret {
+        form.push(("client_secret", secret.clone()));
+    }
+    if let Some(resource) = &config.resource {
+        form.push(("resource", resource.clone()));
+    }
+
+    let response = reqwest::Client::builder()
+        .timeout(TOKEN_REQUEST_TIMEOUT)
+        .build()?
+        .post(endpoint)
+        .header("Accept", "application/json")
+        .form(&form)
+        .send()
+        .await
+        .context("Token request failed")?;
+
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return match serde_json::from_str::<TokenErrorResponse>(&body) {
+            Ok(e) => Ok(Err(e)),
+            Err(_) => Err(anyhow!("{} returned HTTP {}", endpoint, status)),
+        };
+    }
+    Ok(Ok(body))
+}
+
+impl TokenErrorResponse {
+    fn into_error(self) -> anyhow::Error {
+        anyhow!("Token endpoint returned {}: {}", self.error, self.error_description.unwrap_or_default())
+    }
+}
+
+async fn request_token(config: &OAuthClientConfig, form: Vec<(&str, String)>) -> Result<TokenResponse> {
+    let body = post_form(config, &config.token_endpoint, form).await?
+        .map_err(TokenErrorResponse::into_error)?;
+    serde_json::from_str(&body).context("Malformed token response")
+}
+
+fn to_stored(response: TokenResponse, client: OAuthClientConfig, previous_refresh: Option<String>) -> StoredOAuthToken {
+    StoredOAuthToken {
+        access_token: response.access_token,
+        // Servers that don't rotate refresh tokens omit it from refresh responses
+        refresh_token: response.refresh_token.or(previous_refresh),
+        token_type: response.token_type,
+        expires_at: response.expires_in.map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+        scope: response.scope,
+        client,
+    }
+}
+
+pub async fn exchange_code(
+    config: &OAuthClientConfig,
+    code: &str,
+    verifier: &str,
+    redirect_uri: &str,
+) -> Result<StoredOAuthToken> {
+    let response = request_token(config, vec![
+        ("grant_type", "authorization_code".to_string()),
+        ("code", code.to_string()),
+        ("redirect_uri", redirect_uri.to_string()),
+        ("code_verifier", verifier.to_string()),
+    ]).await?;
+    Ok(to_stored(response, config.clone(), None))
+}
+
+pub async fn refresh_access_token(token: &StoredOAuthToken) -> Result<StoredOAuthToken> {
+    let refresh_token = token.refresh_token.clone()
+        .ok_or_else(|| anyhow!("Token has no refresh token"))?;
+    let mut form = vec![
+        ("grant_type", "refresh_token".to_string()),
+        ("refresh_token", refresh_token.clone()),
+    ];
+    if !token.client.scopes.is_empty() {
+        form.push(("scope", token.client.scopes.join(" ")));
+    }
+    let response = request_token(&token.client, form).await?;
+    Ok(to_stored(response, token.client.clone(), Some(refresh_token)))
+}
+
+/// Next polling interval while the user has not finished, or None once the grant has failed
+fn device_poll_interval(error: &str, interval: Duration) -> Option<Duration> {
+    match error {
+        "authorization_pending" => Some(interval),
+        "slow_down" => Some(interval + SLOW_DOWN_STEP),
+        _ => None,
+    }
+}
+
+async fn load_stored_token(app: &AppHandle, server_id: &str) -> Result<StoredOAuthToken> {
+    let raw = app.state::<AppState>().oauth_storage.get_token(server_id).await?
+        .ok_or_else(|| anyhow!("No OAuth token stored for {}", server_id))?;
+    serde_json::from_str(&raw)
+        .map_err(|_| anyhow!("Token for {} was not issued by the authorization flow and cannot be refreshed", server_id))
+}
+
+async fn save_stored_token(app: &AppHandle, server_id: &str, token: &StoredOAuthToken) -> Result<()> {
+    app.state::<AppState>().oauth_storage
+        .store_token(server_id.to_string(), serde_json::to_string(token)?)
+        .await
+}
+
+/// Refresh and persist a server's token
+async fn refresh_stored_token(app: &AppHandle, server_id: &str) -> Result<StoredOAuthToken> {
+    let flows = app.state::<OAuthFlowState>();
+    let _guard = flows.refresh_lock.lock().await;
+    let current = load_stored_token(app, server_id).await?;
+    let refreshed = refresh_access_token(&current).await?;
+    save_stored_token(app, server_id, &refreshed).await?;
+    Ok(refreshed)
+}
+
+fn emit_event(app: &AppHandle, event: &str, payload: OAuthEvent) {
+    if let Err(e) = app.emit(event, payload) {
+        error!("Failed to emit {}: {}", event, e);
+    }
+}
+
+/// (Re)start the background task that refreshes a token shortly before it expires
+pub fn schedule_refresh(app: &AppHandle, server_id: &str, token: &StoredOAuthToken) {
+    let flows = app.state::<OAuthFlowState>();
+    flows.cancel_refresh(server_id);
+    let Some(mut delay) = refresh_due_in(token, Utc::now()) else {
+        return;
+    };
+
+    let app = app.clone();
+    let task_server_id = server_id.to_string();
+    let task = tauri::async_runtime::spawn(async move {
+        let server_id = task_server_id;
+        let mut attempt = 0;
+        loop {
+            tokio::time::sleep(delay).await;
+            match refresh_stored_token(&app, &server_id).await {
+                Ok(token) => {
+                    info!("Refreshed OAuth token for {}", server_id);
+                    emit_event(&app, "mcp_oauth_refreshed", OAuthEvent {
+                        server_id: server_id.clone(),
+                        expires_at: token.expires_at,
+                        error: None,
+                        will_retry: false,
+                    });
+                    attempt = 0;
+                    match refresh_due_in(&token, Utc::now()) {
+                        Some(next) => delay = next,
+                        None => break,
+                    }
+                }
+                Err(e) => {
+                    attempt += 1;
+                    let will_retry = attempt < MAX_REFRESH_ATTEMPTS;
+                    warn!("OAuth refresh for {} failed (attempt {}): {}", server_id, attempt, e);
+                    emit_event(&app, "mcp_oauth_refresh_failed", OAuthEvent {
+                        server_id: server_id.clone(),
+                        expires_at: None,
+                        error: Some(redact_error(&e)),
+                        will_retry,
+                    });
+                    if !will_retry {
+                        break;
+                    }
+                    delay = REFRESH_RETRY_DELAY * attempt;
+                }
+            }
+        }
+    });
+    flows.refresh_tasks.lock().unwrap().insert(server_id.to_string(), task);
+}
+
+/// Schedule refreshes for tokens persisted by earlier sessions
+pub fn resume_oauth_refresh(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let server_ids: Vec<String> = match app.state::<AppState>().oauth_storage.get_all_tokens().await {
+            Ok(tokens) => tokens.into_keys().collect(),
+            Err(e) => {
+                error!("Failed to load OAuth tokens for refresh: {}", e);
+                return;
+            }
+        };
+        for server_id in server_ids {
+            if let Ok(token) = load_stored_token(&app, &server_id).await {
+                schedule_refresh(&app, &server_id, &token);
+            }
+        }
+    });
+}
+
+async fn complete_authorization(app: AppHandle, callback_url: String) {
+    let (code, state) = match parse_callback(&callback_url) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            // Without a state we can't tell which flow failed, so fail every flow on this port
+            warn!("OAuth callback rejected: {}", e);
+            let port = url::Url::parse(&callback_url).ok().and_then(|u| u.port());
+            let flows = app.state::<OAuthFlowState>();
+            let failed: Vec<PendingAuthorization> = {
+                let mut pending = flows.pending.lock().unwrap();
+                let states: Vec<String> = pending.iter()
+                    .filter(|(_, p)| Some(p.port) == port)
+                    .map(|(s, _)| s.clone())
+                    .collect();
+                states.iter().filter_map(|s| pending.remove(s)).collect()
+            };
+            for pending in failed {
+                let _ = tauri_plugin_oauth::cancel(pending.port);
+                emit_event(&app, "mcp_oauth_failed", OAuthEvent {
+                    server_id: pending.server_id,
+                    expires_at: None,
+                    error: Some(redact_error(&e)),
+                    will_retry: false,
+                });
+            }
+            return;
+        }
+    };
+
+    let Some(pending) = app.state::<OAuthFlowState>().take_pending(&state) else {
+        warn!("Ignoring OAuth callback with unknown state");
+        return;
+    };
+    let _ = tauri_plugin_oauth::cancel(pending.port);
+
+    let result = async {
+        let token = exchange_code(&pending.config, &code, &pending.verifier, &pending.redirect_uri).await?;
+        save_stored_token(&app, &pending.server_id, &token).await?;
+        Ok::<_, anyhow::Error>(token)
+    }.await;
+
+    match result {
+        Ok(token) => {
+            info!("OAuth authorization completed for {}", pending.server_id);
+            schedule_refresh(&app, &pending.server_id, &token);
+            emit_event(&app, "mcp_oauth_authorized", OAuthEvent {
+                server_id: pending.server_id,
+                expires_at: token.expires_at,
+                error: None,
+                will_retry: false,
+            });
+        }
+        Err(e) => {
+            error!("OAuth token exchange for {} failed: {}", pending.server_id, e);
+            emit_event(&app, "mcp_oauth_failed", OAuthEvent {
+                server_id: pending.server_id,
+                expires_at: None,
+                error: Some(redact_error(&e)),
+                will_retry: false,
+            });
+        }
+    }
+}
+
+async fn poll_device_token(
+    app: &AppHandle,
+    server_id: &str,
+    config: &OAuthClientConfig,
+    device_code: &str,
+    mut interval: Duration,
+    expires_at: DateTime<Utc>,
+) -> Result<StoredOAuthToken> {
+    loop {
+        tokio::time::sleep(interval).await;
+        if Utc::now() >= expires_at {
+            return Err(anyhow!("Device code expired before authorization completed"));
+        }
+
+        let form = vec![
+            ("grant_type", DEVICE_CODE_GRANT.to_string()),
+            ("device_code", device_code.to_string()),
+        ];
+        match post_form(config, &config.token_endpoint, form).await {
+            Ok(Ok(body)) => {
+                let response: TokenResponse = serde_json::from_str(&body).context("Malformed token response")?;
+                let token = to_stored(response, config.clone(), None);
+                save_stored_token(app, server_id, &token).await?;
+                return Ok(token);
+            }
+            Ok(Err(e)) => match device_poll_interval(&e.error, interval) {
+                Some(next) => interval = next,
+                None => return Err(e.into_error()),
+            },
+            // Headless machines often have flaky links; keep polling until the code expires
+            Err(e) => warn!("Device token poll for {} failed: {}", server_id, e),
+        }
+    }
+}
+
+/// Begin a PKCE authorization-code flow; the result arrives as `mcp_oauth_authorized` or `mcp_oauth_failed`
+#[command]
+pub async fn start_mcp_oauth_flow(
+    server_id: String,
+    config: OAuthClientConfig,
+    redirect_ports: Option<Vec<u16>>,
+    open_browser: Option<bool>,
+    app: AppHandle,
+    flows: State<'_, OAuthFlowState>,
+) -> Result<OAuthAuthorizationStart, String> {
+    if server_id.trim().is_empty() {
+        return Err("Server ID cannot be empty".to_string());
+    }
+    url::Url::parse(&config.token_endpoint).redact_err("Invalid token endpoint")?;
+
+    // Drop abandoned flows, and any earlier attempt for the same server
+    let stale: Vec<PendingAuthorization> = {
+        let cutoff = Utc::now() - chrono::Duration::minutes(PENDING_TTL_MINUTES);
+        let mut pending = flows.pending.lock().unwrap();
+        let states: Vec<String> = pending.iter()
+            .filter(|(_, p)| p.created_at < cutoff || p.server_id == server_id)
+            .map(|(s, _)| s.clone())
+            .collect();
+        states.iter().filter_map(|s| pending.remove(s)).collect()
+    };
+    for pending in stale {
+        let _ = tauri_plugin_oauth::cancel(pending.port);
+    }
+
+    let callback_app = app.clone();
+    let oauth_config = tauri_plugin_oauth::OauthConfig {
+        ports: redirect_ports,
+        response: Some(CALLBACK_RESPONSE.into()),
+    };
+    let port = tauri_plugin_oauth::start_with_config(oauth_config, move |url| {
+        tauri::async_runtime::spawn(complete_authorization(callback_app.clone(), url));
+    }).redact_err("Failed to start OAuth callback server")?;
+
+    let redirect_uri = format!("http://localhost:{}/callback", port);
+    let state = random_token(24);
+    let pkce = PkceChallenge::new();
+    let authorization_url = build_authorization_url(&config, &redirect_uri, &state, &pkce)
+        .map_err(|e| {
+            let _ = tauri_plugin_oauth::cancel(port);
+            e.to_string()
+        })?;
+
+    flows.pending.lock().unwrap().insert(state.clone(), PendingAuthorization {
+        server_id: server_id.clone(),
+        config,
+        verifier: pkce.verifier,
+        redirect_uri: redirect_uri.clone(),
+        port,
+        created_at: Utc::now(),
+    });
+
+    if open_browser.unwrap_or(true) {
+        if let Err(e) = webbrowser::open(&authorization_url) {
+            warn!("Failed to open browser for OAuth: {}", e);
+        }
+    }
+
+    info!("Started OAuth authorization for {} on port {}", server_id, port);
+    Ok(OAuthAuthorizationStart { server_id, authorization_url, redirect_uri, state })
+}
+
+/// Begin an RFC 8628 device authorization; the token is stored in the background once the user approves
+#[command]
+pub async fn start_device_code_flow(
+    server_id: String,
+    config: OAuthClientConfig,
+    app: AppHandle,
+    flows: State<'_, OAuthFlowState>,
+) -> Result<DeviceCodeStart, String> {
+    if server_id.trim().is_empty() {
+        return Err("Server ID cannot be empty".to_string());
+    }
+    let endpoint = config.device_authorization_endpoint.clone()
+        .ok_or("Server has no device authorization endpoint")?;
+
+    let mut form = Vec::new();
+    if !config.scopes.is_empty() {
+        form.push(("scope", config.scopes.join(" ")));
+    }
+    let body = post_form(&config, &endpoint, form).await
+        .redact_err("Device authorization failed")?
+        .map_err(|e| e.into_error()).redact_err("Device authorization failed")?;
+    let authorization: DeviceAuthorizationResponse = serde_json::from_str(&body)
+        .redact_err("Malformed device authorization response")?;
+
+    let expires_at = Utc::now() + chrono::Duration::seconds(authorization.expires_in);
+    let interval = Duration::from_secs(authorization.interval.max(1));
+
+    flows.cancel_device_poll(&server_id);
+    let task_app = app.clone();
+    let task_server_id = server_id.clone();
+    let device_code = authorization.device_code;
+    let task = tauri::async_runtime::spawn(async move {
+        let (app, server_id) = (task_app, task_server_id);
+        let result = poll_device_token(&app, &server_id, &config, &device_code, interval, expires_at).await;
+        app.state::<OAuthFlowState>().device_polls.lock().unwrap().remove(&server_id);
+
+        match result {
+            Ok(token) => {
+                info!("Device authorization completed for {}", server_id);
+                schedule_refresh(&app, &server_id, &token);
+                emit_event(&app, "mcp_oauth_authorized", OAuthEvent {
+                    server_id,
+                    expires_at: token.expires_at,
+                    error: None,
+                    will_retry: false,
+                });
+            }
+            Err(e) => {
+                warn!("Device authorization for {} failed: {}", server_id, e);
+                emit_event(&app, "mcp_oauth_failed", OAuthEvent {
+                    server_id,
+                    expires_at: None,
+                    error: Some(redact_error(&e)),
+                    will_retry: false,
+                });
+            }
+        }
+    });
+    flows.device_polls.lock().unwrap().insert(server_id.clone(), task);
+
+    info!("Started device authorization for {}", server_id);
+    Ok(DeviceCodeStart {
+        server_id,
+        user_code: authorization.user_code,
+        verification_uri: authorization.verification_uri,
+        verification_uri_complete: authorization.verification_uri_complete,
+        expires_at,
+        interval_seconds: interval.as_secs(),
+    })
+}
+
+#[command]
+pub async fn cancel_device_code_flow(
+    server_id: String,
+    flows: State<'_, OAuthFlowState>,
+) -> Result<bool, String> {
+    Ok(flows.cancel_device_poll(&server_id))
+}
+
+/// Refresh a server's token now and reschedule its automatic refresh
+#[command]
+pub async fn refresh_mcp_oauth_token(
+    server_id: String,
+    app: AppHandle,
+) -> Result<OAuthTokenStatus, String> {
+    match refresh_stored_token(&app, &server_id).await {
+        Ok(token) => {
+            schedule_refresh(&app, &server_id, &token);
+            emit_event(&app, "mcp_oauth_refreshed", OAuthEvent {
+                server_id: server_id.clone(),
+                expires_at: token.expires_at,
+                error: None,
+                will_retry: false,
+            });
+            Ok(OAuthTokenStatus::new(&server_id, &token))
+        }
+        Err(e) => {
+            emit_event(&app, "mcp_oauth_refresh_failed", OAuthEvent {
+                server_id: server_id.clone(),
+                expires_at: None,
+                error: Some(redact_error(&e)),
+                will_retry: false,
+            });
+            Err(format!("Failed to refresh token: {}", redact_error(e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> OAuthClientConfig {
+        OAuthClientConfig {
+            authorization_endpoint: "https://auth.example.com/authorize?audience=mcp".into(),
+            token_endpoint: "https://auth.example.com/token".into(),
+            device_authorization_endpoint: None,
+            client_id: "banshee".into(),
+            client_secret: None,
+            scopes: vec!["tools:read".into(), "tools:call".into()],
+            resource: Some("https://mcp.example.com".into()),
+        }
+    }
+
+    #[test]
+    fn test_pkce_and_authorization_url() {
+        // RFC 7636 appendix B
+        let pkce = PkceChallenge::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".into());
+        assert_eq!(pkce.challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+
+        let url = build_authorization_url(&config(), "http://localhost:4000/callback", "xyz", &pkce).unwrap();
+        let params: HashMap<String, String> = url::Url::parse(&url).unwrap().query_pairs().into_owned().collect();
+        assert_eq!(params["audience"], "mcp");
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["scope"], "tools:read tools:call");
+        assert_eq!(params["redirect_uri"], "http://localhost:4000/callback");
+
+        assert_eq!(parse_callback("http://localhost:4000/callback?code=abc&state=xyz").unwrap(), ("abc".into(), "xyz".into()));
+        let denied = parse_callback("http://localhost:4000/callback?error=access_denied&state=xyz").unwrap_err();
+        assert!(denied.to_string().contains("access_denied"));
+    }
+
+    #[test]
+    fn test_refresh_schedule() {
+        let now = Utc::now();
+        let mut token = StoredOAuthToken {
+            access_token: "a".into(),
+            refresh_token: Some("r".into()),
+            token_type: "Bearer".into(),
+            expires_at: Some(now + chrono::Duration::seconds(3600)),
+            scope: None,
+            client: config(),
+        };
+        let due = refresh_due_in(&token, now).unwrap();
+        assert_eq!(due.as_secs(), (3600 - REFRESH_MARGIN_SECONDS) as u64);
+
+        token.expires_at = Some(now + chrono::Duration::seconds(30));
+        assert_eq!(refresh_due_in(&token, now), Some(Duration::ZERO));
+
+        token.refresh_token = None;
+        assert!(refresh_due_in(&token, now).is_none());
+    }
+
+    #[test]
+    fn test_device_authorization_polling() {
+        let response: DeviceAuthorizationResponse = serde_json::from_str(
+            r#"{"device_code":"d","user_code":"WDJB-MJHT","verification_url":"https://example.com/device","expires_in":900}"#,
+        ).unwrap();
+        assert_eq!(response.verification_uri, "https://example.com/device");
+        assert_eq!(response.interval, 5);
+
+        let interval = Duration::from_secs(5);
+        assert_eq!(device_poll_interval("authorization_pending", interval), Some(interval));
+        assert_eq!(device_poll_interval("slow_down", interval), Some(Duration::from_secs(10)));
+        assert_eq!(device_poll_interval("access_denied", interval), None);
+        assert_eq!(device_poll_interval("expired_token", interval), None);
+    }
+}
@@ -11,6 +11,10 @@ use aes_gcm::{
 type Key = GenericArray<u8, aes_gcm::aes::cipher::consts::U32>;
 use aes_gcm::aead::rand_core::RngCore;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use tracing::error;
+
+use crate::ai::encryption::{EnvelopeEncryption, KeyPurpose};
+use crate::ai::RedactErr;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct TokenStorage {
@@ -29,7 +33,9 @@ impl Default for TokenStorage {
 
 pub struct OAuthTokenStorage {
     storage_path: PathBuf,
-    cipher: Aes256Gcm,
+    /// Key file of the pre-envelope scheme, kept until its tokens are re-encrypted
+    legacy_key_path: PathBuf,
+    envelope: EnvelopeEncryption,
 }
 
 impl OAuthTokenStorage {
@@ -40,35 +46,72 @@ impl OAuthTokenStorage {
         
         let storage_path = storage_dir.join("tokens.enc");
         
-        // Load or generate encryption key
-        let key = Self::load_or_generate_key(&storage_dir)?;
-        let cipher = Aes256Gcm::new(&key);
-        
         Ok(Self {
             storage_path,
-            cipher,
+            legacy_key_path: storage_dir.join("key.enc"),
+            envelope: EnvelopeEncryption::open_default()?,
         })
     }
     
-    fn load_or_generate_key(storage_dir: &PathBuf) -> Result<Key> {
-        let key_path = storage_dir.join("key.enc");
+    fn legacy_cipher(&self) -> Result<Aes256Gcm> {
+        let key_data = std::fs::read(&self.legacy_key_path)?;
+        if key_data.len() != 32 {
+            return Err(anyhow::anyhow!("Legacy OAuth key is malformed"));
+        }
+        let key: Key = *GenericArray::from_slice(&key_data);
+        Ok(Aes256Gcm::new(&key))
+    }
+
+    fn decrypt_token(&self, encoded: &str) -> Result<String> {
+        if EnvelopeEncryption::is_envelope(encoded) {
+            return self.envelope.decrypt(KeyPurpose::OauthTokens, encoded);
+        }
+
+        // Base64 decode
+        let encrypted = BASE64.decode(encoded)?;
         
-        if key_path.exists() {
-            // Load existing key
-            let key_data = std::fs::read(&key_path)?;
-            let key = *GenericArray::from_slice(&key_data);
-            Ok(key)
-        } else {
-            // Generate new key
-            let mut key_bytes = [0u8; 32];
-            OsRng.fill_bytes(&mut key_bytes);
-            let key = *GenericArray::from_slice(&key_bytes);
-            
-            // Save key (in production, use OS keychain)
-            std::fs::write(&key_path, &key_bytes)?;
-            
-            Ok(key)
+        if encrypted.len() < 12 {
+            return Err(anyhow::anyhow!("Invalid encrypted data"));
+        }
+        
+        // Extract nonce and ciphertext
+        let (nonce_bytes, ciphertext) = encrypted.split_at(12);
+        let nonce = Nonce::from_slice(nonce_bytes);
+        
+        // Decrypt
+        let plaintext = self.legacy_cipher()?
+            .decrypt(nonce, ciphertext)
+            .map_err(|e| anyhow::anyhow!("Decryption failed: {:?}", e))?;
+        
+        Ok(String::from_utf8(plaintext)?)
+    }
+
+    /// Re-encrypt every token with the active data key; returns (re-encrypted, failed).
+    /// The legacy key file is deleted once no token depends on it.
+    pub async fn reencrypt_tokens(&self) -> Result<(usize, usize)> {
+        let mut storage = self.load_storage().await?;
+        let (mut reencrypted, mut failed) = (0, 0);
+
+        for (server_id, encoded) in storage.tokens.iter_mut() {
+            match self.decrypt_token(encoded)
+                .and_then(|token| self.envelope.encrypt(KeyPurpose::OauthTokens, &token))
+            {
+                Ok(updated) => {
+                    *encoded = updated;
+                    reencrypted += 1;
+                }
+                Err(e) => {
+                    error!("Failed to re-encrypt OAuth token for {}: {}", server_id, e);
+                    failed += 1;
+                }
+            }
+        }
+
+        self.save_storage(&storage).await?;
+        if failed == 0 && self.legacy_key_path.exists() {
+            fs::remove_file(&self.legacy_key_path).await?;
         }
+        Ok((reencrypted, failed))
     }
     
     async fn load_storage(&self) -> Result<TokenStorage> {
@@ -88,21 +131,8 @@ impl OAuthTokenStorage {
     }
     
     pub async fn store_token(&self, server_id: String, token_data: String) -> Result<()> {
-        // Encrypt token data
-        let mut nonce_bytes = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
-        
-        let ciphertext = self.cipher
-            .encrypt(nonce, token_data.as_bytes())
-            .map_err(|e| anyhow::anyhow!("Encryption failed: {:?}", e))?;
-        
-        // Combine nonce and ciphertext
-        let mut encrypted = nonce_bytes.to_vec();
-        encrypted.extend_from_slice(&ciphertext);
-        
-        // Base64 encode
-        let encoded = BASE64.encode(&encrypted);
+        // Encrypt token data with the active OAuth data key
+        let encoded = self.envelope.encrypt(KeyPurpose::OauthTokens, &token_data)?;
         
         // Update storage
         let mut storage = self.load_storage().await?;
@@ -116,24 +146,7 @@ impl OAuthTokenStorage {
         let storage = self.load_storage().await?;
         
         if let Some(encoded) = storage.tokens.get(server_id) {
-            // Base64 decode
-            let encrypted = BASE64.decode(encoded)?;
-            
-            if encrypted.len() < 12 {
-                return Err(anyhow::anyhow!("Invalid encrypted data"));
-            }
-            
-            // Extract nonce and ciphertext
-            let (nonce_bytes, ciphertext) = encrypted.split_at(12);
-            let nonce = Nonce::from_slice(nonce_bytes);
-            
-            // Decrypt
-            let plaintext = self.cipher
-                .decrypt(nonce, ciphertext)
-                .map_err(|e| anyhow::anyhow!("Decryption failed: {:?}", e))?;
-            
-            let token_data = String::from_utf8(plaintext)?;
-            Ok(Some(token_data))
+            Ok(Some(self.decrypt_token(encoded)?))
         } else {
             Ok(None)
         }
@@ -168,7 +181,7 @@ pub async fn store_mcp_oauth_token(
     state.oauth_storage
         .store_token(server_id, encrypted_data)
         .await
-        .map_err(|e| format!("Failed to store token: {}", e))
+        .redact_err("Failed to store token")
 }
 
 #[tauri::command]
@@ -178,28 +191,32 @@ pub async fn get_mcp_oauth_tokens(
     state.oauth_storage
         .get_all_tokens()
         .await
-        .map_err(|e| format!("Failed to get tokens: {}", e))
+        .redact_err("Failed to get tokens")
 }
 
 #[tauri::command]
 pub async fn delete_mcp_oauth_token(
     server_id: String,
     state: tauri::State<'_, crate::AppState>,
+    flows: tauri::State<'_, super::OAuthFlowState>,
 ) -> Result<(), String> {
+    flows.cancel_refresh(&server_id);
     state.oauth_storage
         .delete_token(&server_id)
         .await
-        .map_err(|e| format!("Failed to delete token: {}", e))
+        .redact_err("Failed to delete token")
 }
 
 #[tauri::command]
 pub async fn clear_all_mcp_oauth_tokens(
     state: tauri::State<'_, crate::AppState>,
+    flows: tauri::State<'_, super::OAuthFlowState>,
 ) -> Result<(), String> {
+    flows.cancel_all_refreshes();
     state.oauth_storage
         .clear_all_tokens()
         .await
-        .map_err(|e| format!("Failed to clear tokens: {}", e))
+        .redact_err("Failed to clear tokens")
 }
 
 #[tauri::command]
@@ -230,7 +247,7 @@ pub async fn encrypt_data(data: String) -> Result<String, String> {
 #[tauri::command]
 pub async fn decrypt_data(data: String) -> Result<String, String> {
     let encrypted = BASE64.decode(&data)
-        .map_err(|e| format!("Base64 decode failed: {}", e))?;
+        .redact_err("Base64 decode failed")?;
     
     if encrypted.len() < 44 { // 32 (key) + 12 (nonce) + min ciphertext
         return Err("Invalid encrypted data".to_string());
@@ -249,11 +266,11 @@ pub async fn decrypt_data(data: String) -> Result<String, String> {
         .map_err(|e| format!("Decryption failed: {:?}", e))?;
     
     String::from_utf8(plaintext)
-        .map_err(|e| format!("UTF-8 decode failed: {}", e))
+        .redact_err("UTF-8 decode failed")
 }
 
 #[tauri::command]
 pub async fn open_oauth_browser(url: String) -> Result<(), String> {
     webbrowser::open(&url)
-        .map_err(|e| format!("Failed to open browser: {}", e))
+        .redact_err("Failed to open browser")
 }
\ No newline at end of file
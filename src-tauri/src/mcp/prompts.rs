@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, State};
+use tracing::info;
+
+use super::registry::{find_server, MCPServerDefinition};
+use super::tool_catalog::{open_session, RpcSession, DEFAULT_FETCH_TIMEOUT_MS};
+use crate::ai::{AIState, GuardrailScope, GuardrailStage, RedactErr};
+
+/// Guards against servers that keep returning cursors
+const MAX_PROMPT_PAGES: usize = 50;
+
+/// Event asking the frontend to run an agent turn with a resolved prompt
+pub const PROMPT_TURN_EVENT: &str = "mcp_prompt_turn";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MCPPromptArgument {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A prompt as advertised by an MCP server's `prompts/list`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MCPPrompt {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<MCPPromptArgument>,
+}
+
+/// One message of a resolved prompt; `content` is an MCP content block (text, image or resource)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MCPPromptMessage {
+    pub role: String,
+    pub content: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPPromptRun {
+    pub server_id: String,
+    pub prompt_name: String,
+    pub description: Option<String>,
+    pub messages: Vec<MCPPromptMessage>,
+    /// Set when an agent turn was requested with these messages
+    pub agent_id: Option<String>,
+}
+
+/// Check `args` against the prompt's declared arguments
+pub fn validate_arguments(prompt: &MCPPrompt, args: &HashMap<String, String>) -> Result<()> {
+    if let Some(unknown) = args.keys().find(|name| !prompt.arguments.iter().any(|a| &a.name == *name)) {
+        return Err(anyhow!("Prompt '{}' has no argument '{}'", prompt.name, unknown));
+    }
+    let missing: Vec<&str> = prompt.arguments.iter()
+        .filter(|a| a.required && args.get(&a.name).is_none_or(|v| v.trim().is_empty()))
+        .map(|a| a.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!("Prompt '{}' is missing required arguments: {}", prompt.name, missing.join(", ")));
+    }
+    Ok(())
+}
+
+/// Replace `{{name}}` placeholders the server left in text content with argument values
+pub fn substitute_arguments(messages: &mut [MCPPromptMessage], args: &HashMap<String, String>) {
+    for message in messages {
+        if message.content.get("type").and_then(Value::as_str) != Some("text") {
+            continue;
+        }
+        if let Some(Value::String(text)) = message.content.get_mut("text") {
+            for (name, value) in args {
+                *text = text.replace(&format!("{{{{{}}}}}", name), value);
+            }
+        }
+    }
+}
+
+async fn list_prompts(session: &mut RpcSession) -> Result<Vec<MCPPrompt>> {
+    let mut prompts = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_PROMPT_PAGES {
+        let params = match cursor.take() {
+            Some(c) => json!({ "cursor": c }),
+            None => json!({}),
+        };
+        let result = session.request("prompts/list", params).await?;
+        let page: Vec<MCPPrompt> = serde_json::from_value(result.get("prompts").cloned().unwrap_or_default())
+            .context("Malformed prompt definitions")?;
+        prompts.extend(page);
+        match result.get("nextCursor").and_then(Value::as_str) {
+            Some(next) => cursor = Some(next.to_string()),
+            None => return Ok(prompts),
+        }
+    }
+    Err(anyhow!("MCP server returned more than {} prompt pages", MAX_PROMPT_PAGES))
+}
+
+/// Resolve a prompt into messages: validate the arguments, `prompts/get` it, then fill any
+/// placeholders the server returned unexpanded
+async fn get_prompt(
+    session: &mut RpcSession,
+    prompt_name: &str,
+    args: &HashMap<String, String>,
+) -> Result<(Option<String>, Vec<MCPPromptMessage>)> {
+    let prompt = list_prompts(session).await?
+        .into_iter()
+        .find(|p| p.name == prompt_name)
+        .ok_or_else(|| anyhow!("Unknown prompt: {}", prompt_name))?;
+    validate_arguments(&prompt, args)?;
+
+    let result = session.request("prompts/get", json!({ "name": prompt_name, "arguments": args })).await?;
+    let mut messages: Vec<MCPPromptMessage> = serde_json::from_value(result.get("messages").cloned().unwrap_or_default())
+        .context("Malformed prompt messages")?;
+    substitute_arguments(&mut messages, args);
+
+    let description = result.get("description")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or(prompt.description);
+    Ok((description, messages))
+}
+
+fn fetch_timeout(server: &MCPServerDefinition) -> Duration {
+    Duration::from_millis(server.timeout_ms.unwrap_or(DEFAULT_FETCH_TIMEOUT_MS))
+}
+
+fn enabled_server(state: &AIState, server_id: &str) -> Result<MCPServerDefinition> {
+    let server = find_server(&state.storage, server_id)?;
+    if !server.enabled {
+        return Err(anyhow!("MCP server '{}' is disabled", server_id));
+    }
+    Ok(server)
+}
+
+// Tauri Commands
+
+/// List the prompts a registered server offers
+#[command]
+pub async fn list_mcp_prompts(
+    server_id: String,
+    app: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<Vec<MCPPrompt>, String> {
+    let server = enabled_server(&state, &server_id).redacted()?;
+
+    let fetch = async {
+        let (mut session, _) = open_session(&app, &server).await?;
+        list_prompts(&mut session).await
+    };
+    tokio::time::timeout(fetch_timeout(&server), fetch)
+        .await
+        .map_err(|_| format!("Timed out listing prompts from '{}'", server_id))?
+        .redact_err("Failed to list prompts")
+}
+
+/// Resolve a server's prompt into messages. With an `agent_id`, also asks the frontend to run
+/// that agent's turn on them.
+#[command]
+pub async fn run_mcp_prompt(
+    server_id: String,
+    prompt_name: String,
+    args: Option<HashMap<String, String>>,
+    agent_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AIState>,
+) -> Result<MCPPromptRun, String> {
+    let server = enabled_server(&state, &server_id).redacted()?;
+    let args = args.unwrap_or_default();
+
+    let fetch = async {
+        let (mut session, _) = open_session(&app, &server).await?;
+        get_prompt(&mut session, &prompt_name, &args).await
+    };
+    let (description, mut messages) = tokio::time::timeout(fetch_timeout(&server), fetch)
+        .await
+        .map_err(|_| format!("Timed out getting prompt '{}' from '{}'", prompt_name, server_id))?
+        .redact_err("Failed to get prompt")?;
+
+    // The server wrote these messages, so they pass the tool-output guardrails before an agent sees them
+    let mut guard = GuardrailScope::new(&app, &state.storage, agent_id.as_deref());
+    for message in &mut messages {
+        if message.content.get("type").and_then(Value::as_str) != Some("text") {
+            continue;
+        }
+        if let Some(Value::String(text)) = message.content.get_mut("text") {
+            *text = guard.check(GuardrailStage::ToolOutput, text).redacted()?;
+        }
+    }
+
+    let run = MCPPromptRun { server_id, prompt_name, description, messages, agent_id };
+    if run.agent_id.is_some() {
+        app.emit(PROMPT_TURN_EVENT, &run)
+            .redact_err("Failed to start agent turn")?;
+    }
+
+    info!("Resolved MCP prompt {} from {} into {} messages", run.prompt_name, run.server_id, run.messages.len());
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review_prompt() -> MCPPrompt {
+        serde_json::from_value(json!({
+            "name": "code_review",
+            "description": "Review a change",
+            "arguments": [
+                { "name": "language", "required": true },
+                { "name": "focus" }
+            ]
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_validate_arguments() {
+        let prompt = review_prompt();
+        let args = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        assert!(validate_arguments(&prompt, &args(&[("language", "rust")])).is_ok());
+        assert!(validate_arguments(&prompt, &args(&[("focus", "safety")])).is_err());
+        assert!(validate_arguments(&prompt, &args(&[("language", " ")])).is_err());
+        assert!(validate_arguments(&prompt, &args(&[("language", "rust"), ("tone", "kind")])).is_err());
+    }
+
+    #[test]
+    fn test_substitute_arguments_in_text_only() {
+        let mut messages: Vec<MCPPromptMessage> = serde_json::from_value(json!([
+            { "role": "user", "content": { "type": "text", "text": "Review this {{language}} code for {{focus}}." } },
+            { "role": "user", "content": { "type": "image", "data": "{{language}}", "mimeType": "image/png" } }
+        ])).unwrap();
+        let args = HashMap::from([
+            ("language".to_string(), "Rust".to_string()),
+            ("focus".to_string(), "safety".to_string()),
+        ]);
+
+        substitute_arguments(&mut messages, &args);
+        assert_eq!(messages[0].content["text"], "Review this Rust code for safety.");
+        assert_eq!(messages[1].content["data"], "{{language}}");
+    }
+}
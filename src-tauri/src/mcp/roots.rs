@@ -0,0 +1,100 @@
+//! MCP `roots` capability: stdio servers are told which directories they may work in, taken
+//! from the allowed roots of their workspace's `PathPolicy`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::stdio::MCPProcessMap;
+use crate::ai::{load_path_policy, PathPolicy, StorageManager};
+
+pub const ROOTS_LIST_CHANGED: &str = "notifications/roots/list_changed";
+
+/// A directory offered to an MCP server, as a `file://` URI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MCPRoot {
+    pub uri: String,
+    pub name: Option<String>,
+}
+
+/// The policy's allowed roots that exist on disk, as MCP roots
+pub fn roots_for_policy(policy: &PathPolicy) -> Vec<MCPRoot> {
+    let mut roots: Vec<MCPRoot> = Vec::new();
+    for path in policy.resolved_roots() {
+        let Ok(uri) = url::Url::from_directory_path(&path) else { continue };
+        let uri = uri.to_string();
+        if roots.iter().any(|r| r.uri == uri) {
+            continue;
+        }
+        roots.push(MCPRoot {
+            uri,
+            name: path.file_name().map(|n| n.to_string_lossy().to_string()),
+        });
+    }
+    roots
+}
+
+pub fn is_roots_list_request(message: &Value) -> bool {
+    message.get("method").and_then(Value::as_str) == Some("roots/list")
+        && message.get("id").is_some_and(|id| !id.is_null())
+}
+
+/// Reply to a server's `roots/list` request with the roots of `workspace`
+pub fn roots_list_response(storage: &StorageManager, workspace: &str, request: &Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    match load_path_policy(storage, workspace) {
+        Ok(policy) => json!({ "jsonrpc": "2.0", "id": id, "result": { "roots": roots_for_policy(&policy) } }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32603, "message": format!("Failed to load path policy: {}", e) }
+        }),
+    }
+}
+
+/// Tell every process scoped to `workspace` that its roots changed; returns how many were told
+pub fn notify_roots_changed(processes: &MCPProcessMap, workspace: &str) -> usize {
+    let notified = processes.lock().unwrap()
+        .values()
+        .filter(|p| p.workspace == workspace)
+        .filter(|p| p.notify(ROOTS_LIST_CHANGED))
+        .count();
+    if notified > 0 {
+        info!("Notified {} MCP servers that the roots of {} changed", notified, workspace);
+    }
+    notified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roots_for_policy_skips_missing_and_duplicate_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let policy = PathPolicy {
+            workspace: "default".to_string(),
+            allowed_roots: vec![
+                root.to_string_lossy().to_string(),
+                root.join(".").to_string_lossy().to_string(),
+                root.join("missing").to_string_lossy().to_string(),
+            ],
+            denied_patterns: vec![],
+            max_file_size: 1024,
+        };
+
+        let roots = roots_for_policy(&policy);
+        assert_eq!(roots.len(), 1);
+        assert!(roots[0].uri.starts_with("file://"));
+        assert!(roots[0].uri.ends_with('/'));
+        assert_eq!(roots[0].name, root.file_name().map(|n| n.to_string_lossy().to_string()));
+    }
+
+    #[test]
+    fn test_is_roots_list_request() {
+        assert!(is_roots_list_request(&json!({ "jsonrpc": "2.0", "id": 4, "method": "roots/list" })));
+        assert!(!is_roots_list_request(&json!({ "jsonrpc": "2.0", "method": "roots/list" })));
+        assert!(!is_roots_list_request(&json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/list" })));
+    }
+}
@@ -0,0 +1,346 @@
+//! One tool namespace over every connected MCP server. Each tool gets a server-prefixed name
+//! that model APIs accept, and a tool several servers offer (search, typically) can be called
+//! on all of them at once with the results merged.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::time::Instant;
+use tauri::{command, AppHandle, State};
+use tracing::info;
+
+use super::audit::{audit_call, call_outcome, NewMCPToolCall};
+use super::registry::find_server;
+use super::tool_catalog::{call_server_tool, MCPToolCatalog, MCPToolSchema};
+use crate::ai::{AIState, GuardrailScope, RedactErr};
+use crate::database::workspaces::ensure_tool_allowed;
+
+/// Longest tool name model providers accept
+pub const MAX_ROUTED_NAME_LEN: usize = 64;
+
+/// Separates the server prefix from the tool name
+const ROUTE_SEPARATOR: &str = "__";
+
+/// A tool exposed through the router
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoutedTool {
+    /// Unique name to call the tool by
+    pub name: String,
+    pub server_id: String,
+    pub tool_name: String,
+    pub description: Option<String>,
+    pub parameters: Value,
+    /// The server marks the tool read-only or idempotent, so a failed call may be repeated
+    #[serde(default)]
+    pub idempotent: bool,
+}
+
+/// How one server answered a fanned-out call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanOutSource {
+    pub server_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Content blocks this server contributed after deduplication
+    pub contributed: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanOutResult {
+    pub tool_name: String,
+    /// Merged `tools/call` result: deduplicated content, `isError` only when every server failed
+    pub result: Value,
+    pub sources: Vec<FanOutSource>,
+}
+
+#[derive(Debug, Default)]
+pub struct McpToolRouter {
+    routes: BTreeMap<String, RoutedTool>,
+}
+
+fn sanitize(part: &str) -> String {
+    part.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// `<server>__<tool>` made safe for model APIs, truncated to fit
+fn routed_name(server_id: &str, tool_name: &str) -> String {
+    let mut name = format!("{}{}{}", sanitize(server_id), ROUTE_SEPARATOR, sanitize(tool_name));
+    name.truncate(MAX_ROUTED_NAME_LEN);
+    name
+}
+
+impl McpToolRouter {
+    /// Route `tools` of each server; servers are taken in id order so names are stable
+    pub fn new(servers: &[(String, Vec<MCPToolSchema>)]) -> Self {
+        let mut sorted: Vec<&(String, Vec<MCPToolSchema>)> = servers.iter().collect();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut router = Self::default();
+        for (server_id, tools) in sorted {
+            for tool in tools {
+                let name = router.unique_name(routed_name(server_id, &tool.name));
+                router.routes.insert(name.clone(), RoutedTool {
+                    name,
+                    server_id: server_id.clone(),
+                    tool_name: tool.name.clone(),
+                    description: tool.description.clone(),
+                    parameters: tool.input_schema.clone(),
+                    idempotent: tool.annotations.is_idempotent(),
+                });
+            }
+        }
+        router
+    }
+
+    /// Router over the connected servers in the catalog
+    pub fn from_catalog(catalog: &MCPToolCatalog) -> Self {
+        Self::new(&catalog.connected_servers()
+            .into_iter()
+            .map(|cache| (cache.server_id, cache.tools))
+            .collect::<Vec<_>>())
+    }
+
+    /// Names that sanitize or truncate to the same string get `_2`, `_3`, ... suffixes
+    fn unique_name(&self, base: String) -> String {
+        if !self.routes.contains_key(&base) {
+            return base;
+        }
+        (2..).map(|n| {
+            let suffix = format!("_{}", n);
+            let mut name = base.clone();
+            name.truncate(MAX_ROUTED_NAME_LEN - suffix.len());
+            name + &suffix
+        })
+        .find(|name| !self.routes.contains_key(name))
+        .expect("unbounded suffixes")
+    }
+
+    pub fn tools(&self) -> Vec<RoutedTool> {
+        self.routes.values().cloned().collect()
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<&RoutedTool> {
+        self.routes.get(name)
+    }
+
+    /// Every route to a tool called `tool_name`, optionally limited to some servers
+    pub fn routes_for(&self, tool_name: &str, server_ids: Option<&[String]>) -> Vec<&RoutedTool> {
+        self.routes.values()
+            .filter(|r| r.tool_name == tool_name)
+            .filter(|r| server_ids.is_none_or(|ids| ids.contains(&r.server_id)))
+            .collect()
+    }
+}
+
+/// Key under which two content blocks count as duplicates: text compares trimmed and
+/// case-insensitively, resource links by URI, anything else by its JSON
+fn dedup_key(block: &Value) -> String {
+    match block.get("type").and_then(Value::as_str) {
+        Some("text") => format!("text:{}", block.get("text").and_then(Value::as_str).unwrap_or_default().trim().to_lowercase()),
+        Some("resource_link") => format!("link:{}", block.get("uri").and_then(Value::as_str).unwrap_or_default()),
+        Some("resource") => format!("resource:{}", block.pointer("/resource/uri").and_then(Value::as_str).unwrap_or_default()),
+        _ => block.to_string(),
+    }
+}
+
+/// Merge per-server `tools/call` outcomes into one result, keeping the first of duplicate blocks
+pub fn merge_results(tool_name: &str, outcomes: Vec<(String, u64, Option<Value>, Option<String>)>) -> FanOutResult {
+    let mut seen = HashSet::new();
+    let mut content = Vec::new();
+    let mut sources = Vec::new();
+
+    for (server_id, duration_ms, result, error) in outcomes {
+        let mut contributed = 0;
+        if error.is_none() {
+            let blocks = result.as_ref()
+                .and_then(|r| r.get("content"))
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            for block in blocks {
+                if seen.insert(dedup_key(&block)) {
+                    content.push(block);
+                    contributed += 1;
+                }
+            }
+        }
+        sources.push(FanOutSource { server_id, success: error.is_none(), error, duration_ms, contributed });
+    }
+
+    let all_failed = sources.iter().all(|s| !s.success);
+    FanOutResult {
+        tool_name: tool_name.to_string(),
+        result: json!({ "content": content, "isError": all_failed }),
+        sources,
+    }
+}
+
+/// Call a route for `agent_id`, or for no agent, whose tool-output guardrails the result passes
+async fn call_route(
+    app_handle: &AppHandle,
+    state: &AIState,
+    route: &RoutedTool,
+    arguments: &Value,
+    agent_id: Option<&str>,
+) -> (u64, Option<Value>, Option<String>) {
+    let started = Instant::now();
+    let server = ensure_tool_allowed(app_handle, &state.storage, &[&route.name, &route.tool_name])
+        .and_then(|_| find_server(&state.storage, &route.server_id));
+    let mut guard = GuardrailScope::new(app_handle, &state.storage, agent_id);
+    let outcome = match server {
+        Ok(server) => call_server_tool(app_handle, &server, &route.tool_name, arguments, &mut guard).await,
+        Err(e) => Err(e),
+    };
+    let (result, error) = call_outcome(outcome);
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    audit_call(app_handle, &NewMCPToolCall {
+        server_id: route.server_id.clone(),
+        tool_name: route.tool_name.clone(),
+        run_id: None,
+        arguments: arguments.clone(),
+        duration_ms: Some(duration_ms),
+        result_size: result.as_ref().map(|r| r.to_string().len() as u64),
+        success: error.is_none(),
+        error: error.clone(),
+    });
+    (duration_ms, result, error)
+}
+
+pub fn resolve_route(catalog: &MCPToolCatalog, name: &str) -> Result<RoutedTool> {
+    McpToolRouter::from_catalog(catalog)
+        .resolve(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("Unknown routed tool: {}", name))
+}
+
+/// Call a tool by its routed name for `agent_id`, auditing the call
+pub async fn call_routed_tool(
+    app_handle: &AppHandle,
+    state: &AIState,
+    catalog: &MCPToolCatalog,
+    name: &str,
+    arguments: &Value,
+    agent_id: Option<&str>,
+) -> Result<Value> {
+    let route = resolve_route(catalog, name)?;
+    let (_, result, error) = call_route(app_handle, state, &route, arguments, agent_id).await;
+    result.ok_or_else(|| anyhow!("Failed to call {}: {}", name, error.unwrap_or_default()))
+}
+
+// Tauri Commands
+
+/// Tools of every connected MCP server under their routed names
+#[command]
+pub async fn get_routed_mcp_tools(
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<Vec<RoutedTool>, String> {
+    Ok(McpToolRouter::from_catalog(&catalog).tools())
+}
+
+/// Call a tool by its routed name; returns the server's `tools/call` result after the
+/// tool-output guardrails of `agent_id`, or the defaults without one
+#[command]
+pub async fn call_routed_mcp_tool(
+    name: String,
+    arguments: Value,
+    agent_id: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<Value, String> {
+    call_routed_tool(&app_handle, &state, &catalog, &name, &arguments, agent_id.as_deref()).await.redacted()
+}
+
+/// Call `tool_name` on every connected server offering it (or just `server_ids`) concurrently,
+/// merging the results
+#[command]
+pub async fn fan_out_mcp_tool_call(
+    tool_name: String,
+    arguments: Value,
+    server_ids: Option<Vec<String>>,
+    agent_id: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, AIState>,
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<FanOutResult, String> {
+    let router = McpToolRouter::from_catalog(&catalog);
+    let routes: Vec<RoutedTool> = router.routes_for(&tool_name, server_ids.as_deref())
+        .into_iter()
+        .cloned()
+        .collect();
+    if routes.is_empty() {
+        return Err(format!("No connected MCP server offers '{}'", tool_name));
+    }
+
+    let outcomes = futures::future::join_all(routes.iter().map(|route| async {
+        let (duration_ms, result, error) = call_route(&app_handle, &state, route, &arguments, agent_id.as_deref()).await;
+        (route.server_id.clone(), duration_ms, result, error)
+    })).await;
+
+    let merged = merge_results(&tool_name, outcomes);
+    info!(
+        "Fanned {} out to {} MCP servers ({} failed, {} content blocks)",
+        tool_name,
+        merged.sources.len(),
+        merged.sources.iter().filter(|s| !s.success).count(),
+        merged.sources.iter().map(|s| s.contributed).sum::<usize>()
+    );
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str) -> MCPToolSchema {
+        MCPToolSchema { name: name.to_string(), description: None, input_schema: json!({ "type": "object" }), annotations: Default::default() }
+    }
+
+    #[test]
+    fn test_router_prefixes_and_resolves_collisions() {
+        let router = McpToolRouter::new(&[
+            ("web".to_string(), vec![tool("search")]),
+            ("docs".to_string(), vec![tool("search"), tool("fetch.page")]),
+            ("docs.v2".to_string(), vec![tool("fetch.page")]),
+            ("a".repeat(70), vec![tool("one"), tool("two")]),
+        ]);
+
+        let names: Vec<String> = router.tools().into_iter().map(|t| t.name).collect();
+        assert!(names.contains(&"web__search".to_string()));
+        assert!(names.contains(&"docs__search".to_string()));
+        assert!(names.contains(&"docs__fetch_page".to_string()));
+        assert!(names.contains(&"docs_v2__fetch_page".to_string()));
+        assert!(names.iter().all(|n| n.len() <= MAX_ROUTED_NAME_LEN));
+
+        let long: Vec<&String> = names.iter().filter(|n| n.starts_with("aaaa")).collect();
+        assert_eq!(long.len(), 2);
+        assert!(long.iter().any(|n| n.ends_with("_2")));
+
+        assert_eq!(router.resolve("docs_v2__fetch_page").unwrap().server_id, "docs.v2");
+        assert_eq!(router.routes_for("search", None).len(), 2);
+        assert_eq!(router.routes_for("search", Some(&["web".to_string()])).len(), 1);
+    }
+
+    #[test]
+    fn test_merge_results_deduplicates_content() {
+        let text = |t: &str| json!({ "type": "text", "text": t });
+        let merged = merge_results("search", vec![
+            ("docs".to_string(), 10, Some(json!({ "content": [text("Rust book"), text("Tokio guide")] })), None),
+            ("web".to_string(), 20, Some(json!({ "content": [text(" rust BOOK "), text("Serde docs")] })), None),
+            ("down".to_string(), 30, None, Some("connection refused".to_string())),
+        ]);
+
+        assert_eq!(merged.result["content"].as_array().unwrap().len(), 3);
+        assert_eq!(merged.result["isError"], false);
+        assert_eq!(merged.sources[1].contributed, 1);
+        assert!(!merged.sources[2].success);
+
+        let failed = merge_results("search", vec![("down".to_string(), 5, None, Some("timeout".to_string()))]);
+        assert_eq!(failed.result["isError"], true);
+    }
+}
@@ -0,0 +1,403 @@
+//! Stdio transport for MCP server processes. Messages are newline-delimited JSON-RPC: writes go
+//! through one queue per process so frames never interleave, stdout is reassembled into whole
+//! frames however it is chunked, and responses are matched to their requests by id.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+use super::commands::MCPProcessInfo;
+use super::roots::{is_roots_list_request, roots_list_response};
+use crate::ai::AIState;
+
+/// Response wait applied when a request gives no timeout
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 30_000;
+
+/// Largest frame accepted from a server before its output is treated as corrupt
+pub const MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
+/// Structured failures returned to the frontend
+#[derive(Error, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "detail")]
+pub enum MCPStdioError {
+    #[error("Failed to start MCP process: {0}")]
+    SpawnFailed(String),
+
+    #[error("No MCP process with pid {0}")]
+    UnknownProcess(u32),
+
+    #[error("Invalid JSON-RPC message: {0}")]
+    InvalidMessage(String),
+
+    #[error("Request id {0} is already awaiting a response")]
+    DuplicateRequestId(String),
+
+    #[error("Request {id} got no response within {timeout_ms} ms")]
+    Timeout { id: String, timeout_ms: u64 },
+
+    #[error("MCP process {0} exited")]
+    ProcessExited(u32),
+
+    #[error("MCP handshake failed: {0}")]
+    HandshakeFailed(String),
+
+    #[error("{0}")]
+    GuardrailBlocked(String),
+}
+
+/// Waiters for responses, keyed by the request id's JSON text so `1` and `"1"` stay distinct
+type PendingRequests = Arc<Mutex<HashMap<String, oneshot::Sender<Value>>>>;
+
+/// A running MCP server process
+pub struct MCPProcess {
+    pub info: MCPProcessInfo,
+    /// Workspace whose path policy supplies the server's roots
+    pub workspace: String,
+    /// Environment it was started with, for restarts
+    pub(crate) env: HashMap<String, String>,
+    /// Params of the client's `initialize` request, repeated to a restarted server
+    pub(crate) initialize: Option<Value>,
+    /// Whether a client's `initialize` has been answered, so the server takes other requests
+    pub(crate) initialized: bool,
+    /// `serverInfo.version` from the server's `initialize` response
+    pub(crate) server_version: Option<String>,
+    writer: mpsc::UnboundedSender<String>,
+    pending: PendingRequests,
+}
+
+impl MCPProcess {
+    /// Queue a parameterless notification; false once the process has exited
+    pub fn notify(&self, method: &str) -> bool {
+        self.writer.send(json!({ "jsonrpc": "2.0", "method": method }).to_string()).is_ok()
+    }
+}
+
+pub type MCPProcessMap = Arc<Mutex<HashMap<u32, MCPProcess>>>;
+
+/// One decoded line of server output
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    Message(Value),
+    /// A line that isn't JSON, such as a server writing logs to stdout
+    Text(String),
+}
+
+/// Reassembles newline-delimited frames from arbitrarily split reads
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    discarding: bool,
+}
+
+impl FrameDecoder {
+    /// Feed a chunk of output, returning the frames it completes
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        for &byte in chunk {
+            if byte != b'\n' {
+                if !self.discarding {
+                    self.buffer.push(byte);
+                    if self.buffer.len() > MAX_FRAME_BYTES {
+                        warn!("Dropping MCP frame larger than {} bytes", MAX_FRAME_BYTES);
+                        self.buffer.clear();
+                        self.discarding = true;
+                    }
+                }
+                continue;
+            }
+
+            let line = std::mem::take(&mut self.buffer);
+            if std::mem::take(&mut self.discarding) {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            frames.push(match serde_json::from_str(text) {
+                Ok(value) => Frame::Message(value),
+                Err(_) => Frame::Text(text.to_string()),
+            });
+        }
+        frames
+    }
+}
+
+/// Id of a JSON-RPC response, as its pending-request key
+fn response_key(message: &Value) -> Option<String> {
+    let object = message.as_object()?;
+    if object.contains_key("method") {
+        return None;
+    }
+    object.get("id").filter(|id| !id.is_null()).map(Value::to_string)
+}
+
+/// Where a process's incoming messages are routed
+struct Router {
+    app: AppHandle,
+    pid: u32,
+    workspace: String,
+    pending: PendingRequests,
+    /// Weak so that removing the process from the map still closes its stdin
+    writer: mpsc::WeakUnboundedSender<String>,
+}
+
+impl Router {
+    /// Hand a response to its waiting request and answer `roots/list` here; anything else goes
+    /// to the frontend
+    fn route(&self, message: Value) {
+        if let Value::Array(batch) = message {
+            batch.into_iter().for_each(|m| self.route(m));
+            return;
+        }
+
+        if is_roots_list_request(&message) {
+            if let Some(writer) = self.writer.upgrade() {
+                let storage = &self.app.state::<AIState>().storage;
+                let _ = writer.send(roots_list_response(storage, &self.workspace, &message).to_string());
+            }
+            return;
+        }
+
+        if let Some(key) = response_key(&message) {
+            let waiter = self.pending.lock().ok().and_then(|mut p| p.remove(&key));
+            if let Some(waiter) = waiter {
+                let _ = waiter.send(message);
+                return;
+            }
+        }
+        let _ = self.app.emit(&format!("mcp_message_{}", self.pid), message.to_string());
+    }
+}
+
+/// Spawn an MCP server and start its writer, reader and exit tasks
+pub fn spawn_process(
+    app: &AppHandle,
+    server_id: Option<String>,
+    command: String,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    workspace: String,
+) -> Result<u32, MCPStdioError> {
+    let mut process = tokio::process::Command::new(&command);
+    process.args(&args)
+        .envs(&env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Its own process group, so the server and whatever it starts can be signalled together
+    #[cfg(unix)]
+    process.process_group(0);
+    let mut child = process.spawn().map_err(|e| MCPStdioError::SpawnFailed(e.to_string()))?;
+
+    let pid = child.id().ok_or_else(|| MCPStdioError::SpawnFailed("process exited immediately".to_string()))?;
+    let (Some(mut stdin), Some(mut stdout), Some(stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take()) else {
+        return Err(MCPStdioError::SpawnFailed("process pipes unavailable".to_string()));
+    };
+
+    let (writer, mut queue) = mpsc::unbounded_channel::<String>();
+    let pending = PendingRequests::default();
+    let router = Router {
+        app: app.clone(),
+        pid,
+        workspace: workspace.clone(),
+        pending: pending.clone(),
+        writer: writer.downgrade(),
+    };
+    app.state::<MCPProcessMap>().lock().unwrap().insert(pid, MCPProcess {
+        info: MCPProcessInfo { pid, server_id, command, args, health: Default::default(), usage: None, restarts: 0 },
+        workspace,
+        env,
+        initialize: None,
+        initialized: false,
+        server_version: None,
+        writer,
+        pending: pending.clone(),
+    });
+
+    // Single writer keeps frames whole and in the order they were sent
+    tokio::spawn(async move {
+        while let Some(frame) = queue.recv().await {
+            let written = async {
+                stdin.write_all(frame.as_bytes()).await?;
+                stdin.write_all(b"\n").await?;
+                stdin.flush().await
+            };
+            if let Err(e) = written.await {
+                warn!("Failed to write to MCP process {}: {}", pid, e);
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            debug!("MCP process {} stderr: {}", pid, line);
+        }
+    });
+
+    let app_handle = app.clone();
+    tokio::spawn(async move {
+        let mut decoder = FrameDecoder::default();
+        let mut chunk = vec![0u8; 8192];
+        loop {
+            match stdout.read(&mut chunk).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    for frame in decoder.push(&chunk[..n]) {
+                        match frame {
+                            Frame::Message(message) => router.route(message),
+                            Frame::Text(text) => debug!("MCP process {} output: {}", pid, text),
+                        }
+                    }
+                }
+            }
+        }
+
+        let _ = child.wait().await;
+        // Dropping the waiters fails their requests with ProcessExited
+        pending.lock().unwrap().clear();
+        app_handle.state::<MCPProcessMap>().lock().unwrap().remove(&pid);
+        let _ = app_handle.emit(&format!("mcp_close_{}", pid), ());
+    });
+
+    Ok(pid)
+}
+
+/// Queue a message for the process. Requests wait for their response, up to `timeout_ms`;
+/// notifications and responses return `None` once queued.
+pub async fn send_message(
+    processes: &MCPProcessMap,
+    pid: u32,
+    message: &str,
+    timeout_ms: Option<u64>,
+) -> Result<Option<Value>, MCPStdioError> {
+    let message: Value = serde_json::from_str(message)
+        .map_err(|e| MCPStdioError::InvalidMessage(e.to_string()))?;
+    if !message.is_object() {
+        return Err(MCPStdioError::InvalidMessage("expected a JSON-RPC object".to_string()));
+    }
+    let request_key = message.get("method")
+        .and(message.get("id"))
+        .filter(|id| !id.is_null())
+        .map(Value::to_string);
+    let is_initialize = message.get("method").and_then(Value::as_str) == Some("initialize");
+
+    let (writer, pending) = {
+        let mut processes = processes.lock().unwrap();
+        let process = processes.get_mut(&pid).ok_or(MCPStdioError::UnknownProcess(pid))?;
+        if is_initialize {
+            process.initialize = message.get("params").cloned();
+        }
+        (process.writer.clone(), process.pending.clone())
+    };
+
+    // Serialized JSON has no raw newlines, so it is always a single frame
+    let frame = message.to_string();
+    let Some(key) = request_key else {
+        writer.send(frame).map_err(|_| MCPStdioError::ProcessExited(pid))?;
+        return Ok(None);
+    };
+
+    let (tx, rx) = oneshot::channel();
+    {
+        let mut pending = pending.lock().unwrap();
+        if pending.contains_key(&key) {
+            return Err(MCPStdioError::DuplicateRequestId(key));
+        }
+        pending.insert(key.clone(), tx);
+    }
+    if writer.send(frame).is_err() {
+        pending.lock().unwrap().remove(&key);
+        return Err(MCPStdioError::ProcessExited(pid));
+    }
+
+    let timeout_ms = timeout_ms.unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS);
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), rx).await {
+        Ok(Ok(response)) => {
+            if is_initialize && response.get("result").is_some() {
+                if let Some(process) = processes.lock().unwrap().get_mut(&pid) {
+                    process.initialized = true;
+                    process.server_version = response.pointer("/result/serverInfo/version")
+                        .and_then(Value::as_str)
+                        .map(str::to_string);
+                }
+            }
+            Ok(Some(response))
+        }
+        Ok(Err(_)) => Err(MCPStdioError::ProcessExited(pid)),
+        Err(_) => {
+            pending.lock().unwrap().remove(&key);
+            Err(MCPStdioError::Timeout { id: key, timeout_ms })
+        }
+    }
+}
+
+/// A running process of registry server `server_id` that has completed its handshake, with the
+/// version it reported
+pub fn find_server_process(processes: &MCPProcessMap, server_id: &str) -> Option<(u32, Option<String>)> {
+    processes.lock().unwrap()
+        .values()
+        .filter(|p| p.initialized && p.info.server_id.as_deref() == Some(server_id))
+        .min_by_key(|p| p.info.pid)
+        .map(|p| (p.info.pid, p.server_version.clone()))
+}
+
+/// Repeat the client's `initialize` handshake with a restarted server, so it is ready for
+/// requests from a client that already considers it initialized
+pub async fn reinitialize(processes: &MCPProcessMap, pid: u32, params: Value) -> Result<(), MCPStdioError> {
+    let request = json!({ "jsonrpc": "2.0", "id": format!("reinitialize-{}", pid), "method": "initialize", "params": params });
+    let response = send_message(processes, pid, &request.to_string(), None).await?;
+    if let Some(error) = response.as_ref().and_then(|r| r.get("error")) {
+        return Err(MCPStdioError::HandshakeFailed(error.to_string()));
+    }
+    let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    send_message(processes, pid, &initialized.to_string(), None).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_decoder_reassembles_split_and_interleaved_frames() {
+        let mut decoder = FrameDecoder::default();
+        assert!(decoder.push(br#"{"jsonrpc":"2.0","id":1,"res"#).is_empty());
+
+        let frames = decoder.push(b"ult\":{}}\r\nstarting up\n\n{\"jsonrpc\":\"2.0\",\"method\":\"ping\"}\n{\"id\"");
+        assert_eq!(frames, vec![
+            Frame::Message(json!({ "jsonrpc": "2.0", "id": 1, "result": {} })),
+            Frame::Text("starting up".to_string()),
+            Frame::Message(json!({ "jsonrpc": "2.0", "method": "ping" })),
+        ]);
+        assert_eq!(decoder.push(b":2}\n"), vec![Frame::Message(json!({ "id": 2 }))]);
+    }
+
+    #[test]
+    fn test_decoder_drops_oversized_frames() {
+        let mut decoder = FrameDecoder::default();
+        assert!(decoder.push(&vec![b'x'; MAX_FRAME_BYTES + 1]).is_empty());
+        assert_eq!(decoder.push(b"xx\n{\"id\":3}\n"), vec![Frame::Message(json!({ "id": 3 }))]);
+    }
+
+    #[test]
+    fn test_response_keys() {
+        assert_eq!(response_key(&json!({ "id": 1, "result": {} })), Some("1".to_string()));
+        assert_eq!(response_key(&json!({ "id": "1", "error": {} })), Some("\"1\"".to_string()));
+        assert_eq!(response_key(&json!({ "id": 1, "method": "roots/list" })), None);
+        assert_eq!(response_key(&json!({ "id": null, "error": {} })), None);
+    }
+}
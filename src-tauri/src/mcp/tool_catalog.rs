@@ -0,0 +1,631 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use super::commands::terminate_process;
+use super::health::spawn_health_checks;
+use super::registry::{find_server, load_registry, MCPServerDefinition, MCPTransport};
+use super::stdio::{find_server_process, send_message, spawn_process, MCPProcessMap};
+use crate::ai::{active_workspace, AIState, GuardrailScope, RedactErr};
+
+/// MCP protocol revision spoken when fetching tool lists (matches the frontend client)
+pub const MCP_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Fallback timeout for a whole catalog fetch when the server sets none
+pub(crate) const DEFAULT_FETCH_TIMEOUT_MS: u64 = 15_000;
+
+/// Guards against servers that keep returning cursors
+const MAX_TOOL_PAGES: usize = 50;
+
+const TOOLS_LIST_CHANGED: &str = "notifications/tools/list_changed";
+
+/// A tool as advertised by an MCP server's `tools/list`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MCPToolSchema {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "inputSchema", alias = "input_schema", default = "empty_object_schema")]
+    pub input_schema: Value,
+    #[serde(default)]
+    pub annotations: MCPToolAnnotations,
+}
+
+/// Behaviour hints from a tool's `annotations`; absent hints are taken as false
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MCPToolAnnotations {
+    #[serde(default)]
+    pub read_only_hint: bool,
+    #[serde(default)]
+    pub idempotent_hint: bool,
+}
+
+impl MCPToolAnnotations {
+    /// Whether calling the tool again with the same arguments has no further effect
+    pub fn is_idempotent(&self) -> bool {
+        self.read_only_hint || self.idempotent_hint
+    }
+}
+
+fn empty_object_schema() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerToolCache {
+    pub server_id: String,
+    /// `serverInfo.version` from the server's initialize response
+    #[serde(default)]
+    pub server_version: Option<String>,
+    pub tools: Vec<MCPToolSchema>,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    /// Cached schemas are kept across disconnects but only offered while connected
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ToolSource {
+    Builtin,
+    Mcp { server_id: String },
+    Plugin { plugin_id: String },
+}
+
+/// A tool the agent runtime can call, with its parameter schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailableTool {
+    pub name: String,
+    /// Unique across sources: `mcp__<server>__<tool>`, `plugin__<plugin>__<tool>` or the builtin name
+    pub qualified_name: String,
+    pub description: Option<String>,
+    pub parameters: Value,
+    pub source: ToolSource,
+}
+
+/// Cached tool lists for MCP servers and tools registered by plugins
+pub struct MCPToolCatalog {
+    servers: Mutex<HashMap<String, ServerToolCache>>,
+    plugins: Mutex<HashMap<String, Vec<MCPToolSchema>>>,
+}
+
+impl MCPToolCatalog {
+    pub fn new() -> Self {
+        Self {
+            servers: Mutex::new(HashMap::new()),
+            plugins: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn store(&self, server_id: &str, server_version: Option<String>, tools: Vec<MCPToolSchema>) {
+        let mut servers = self.servers.lock().unwrap();
+        servers.insert(server_id.to_string(), ServerToolCache {
+            server_id: server_id.to_string(),
+            server_version,
+            tools,
+            fetched_at: chrono::Utc::now(),
+            connected: true,
+        });
+    }
+
+    pub fn mark_disconnected(&self, server_id: &str) {
+        let mut servers = self.servers.lock().unwrap();
+        if let Some(cache) = servers.get_mut(server_id) {
+            cache.connected = false;
+        }
+    }
+
+    /// Drop a server's cached tools; false when none were cached
+    pub fn remove(&self, server_id: &str) -> bool {
+        self.servers.lock().unwrap().remove(server_id).is_some()
+    }
+
+    pub fn server(&self, server_id: &str) -> Option<ServerToolCache> {
+        self.servers.lock().unwrap().get(server_id).cloned()
+    }
+
+    /// Cached tool lists of servers that are still connected
+    pub fn connected_servers(&self) -> Vec<ServerToolCache> {
+        self.servers.lock().unwrap().values().filter(|c| c.connected).cloned().collect()
+    }
+
+    pub fn set_plugin_tools(&self, plugin_id: &str, tools: Vec<MCPToolSchema>) {
+        let mut plugins = self.plugins.lock().unwrap();
+        if tools.is_empty() {
+            plugins.remove(plugin_id);
+        } else {
+            plugins.insert(plugin_id.to_string(), tools);
+        }
+    }
+
+    /// Builtin, connected MCP and plugin tools, in that order
+    pub fn all_tools(&self) -> Vec<AvailableTool> {
+        let mut tools = builtin_tools();
+
+        let servers = self.servers.lock().unwrap();
+        let mut connected: Vec<&ServerToolCache> = servers.values().filter(|c| c.connected).collect();
+        connected.sort_by(|a, b| a.server_id.cmp(&b.server_id));
+        for cache in connected {
+            tools.extend(cache.tools.iter().map(|tool| AvailableTool {
+                name: tool.name.clone(),
+                qualified_name: format!("mcp__{}__{}", cache.server_id, tool.name),
+                description: tool.description.clone(),
+                parameters: tool.input_schema.clone(),
+                source: ToolSource::Mcp { server_id: cache.server_id.clone() },
+            }));
+        }
+        drop(servers);
+
+        let plugins = self.plugins.lock().unwrap();
+        let mut plugin_ids: Vec<&String> = plugins.keys().collect();
+        plugin_ids.sort();
+        for plugin_id in plugin_ids {
+            tools.extend(plugins[plugin_id].iter().map(|tool| AvailableTool {
+                name: tool.name.clone(),
+                qualified_name: format!("plugin__{}__{}", plugin_id, tool.name),
+                description: tool.description.clone(),
+                parameters: tool.input_schema.clone(),
+                source: ToolSource::Plugin { plugin_id: plugin_id.clone() },
+            }));
+        }
+
+        tools
+    }
+}
+
+fn builtin(name: &str, description: &str, parameters: Value) -> AvailableTool {
+    AvailableTool {
+        name: name.to_string(),
+        qualified_name: name.to_string(),
+        description: Some(description.to_string()),
+        parameters,
+        source: ToolSource::Builtin,
+    }
+}
+
+/// Tools implemented by the backend itself
+pub fn builtin_tools() -> Vec<AvailableTool> {
+    let workspace = json!({ "type": "string", "description": "Workspace whose path policy applies" });
+    vec![
+        builtin("read_file", "Read a text file", json!({
+            "type": "object",
+            "properties": { "path": { "type": "string" }, "workspace": workspace },
+            "required": ["path"]
+        })),
+        builtin("write_file", "Write a text file, replacing its contents", json!({
+            "type": "object",
+            "properties": { "path": { "type": "string" }, "contents": { "type": "string" }, "workspace": workspace },
+            "required": ["path", "contents"]
+        })),
+        builtin("list_files", "List files in a directory", json!({
+            "type": "object",
+            "properties": { "path": { "type": "string" }, "recursive": { "type": "boolean" }, "workspace": workspace },
+            "required": ["path", "recursive"]
+        })),
+        builtin("search_workspace", "Search file contents, honouring .gitignore", json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "path": { "type": "string" },
+                "options": { "type": "object" }
+            },
+            "required": ["query"]
+        })),
+        builtin("apply_patch", "Apply a unified diff, optionally as a dry run", json!({
+            "type": "object",
+            "properties": {
+                "patch": { "type": "string" },
+                "root": { "type": "string" },
+                "dry_run": { "type": "boolean" },
+                "workspace": workspace
+            },
+            "required": ["patch"]
+        })),
+        builtin("execute_command", "Run a whitelisted command", json!({
+            "type": "object",
+            "properties": { "command": { "type": "string" }, "args": { "type": "array", "items": { "type": "string" } } },
+            "required": ["command", "args"]
+        })),
+        builtin("http_request", "Make an HTTP request", json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "method": { "type": "string" },
+                "headers": { "type": "object", "additionalProperties": { "type": "string" } },
+                "body": { "type": "string" }
+            },
+            "required": ["url", "method"]
+        })),
+        builtin("get_environment_context", "Describe the OS, shell, runtimes and workspace roots", json!({
+            "type": "object",
+            "properties": { "workspace": workspace }
+        })),
+    ]
+}
+
+/// Parse one `tools/list` result page into tools and the next cursor
+pub fn parse_tools_page(result: &Value) -> Result<(Vec<MCPToolSchema>, Option<String>)> {
+    let tools = result.get("tools")
+        .cloned()
+        .ok_or_else(|| anyhow!("tools/list result has no tools array"))?;
+    let tools: Vec<MCPToolSchema> = serde_json::from_value(tools).context("Malformed tool definitions")?;
+    let cursor = result.get("nextCursor").and_then(|c| c.as_str()).map(|c| c.to_string());
+    Ok((tools, cursor))
+}
+
+fn is_response_to(message: &Value, id: u64) -> bool {
+    message.get("id").and_then(|v| v.as_u64()) == Some(id)
+}
+
+/// Result of a JSON-RPC response, or its error
+pub(crate) fn rpc_result(message: Value) -> Result<Value> {
+    if let Some(error) = message.get("error") {
+        return Err(anyhow!("MCP error: {}", error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown")));
+    }
+    message.get("result").cloned().ok_or_else(|| anyhow!("MCP response has no result"))
+}
+
+/// Find the response with `id` in a Streamable HTTP body (plain JSON or an SSE stream)
+pub fn extract_http_response(body: &str, id: u64) -> Option<Value> {
+    if let Ok(message) = serde_json::from_str::<Value>(body) {
+        return match message {
+            Value::Array(batch) => batch.into_iter().find(|m| is_response_to(m, id)),
+            message if is_response_to(&message, id) => Some(message),
+            _ => None,
+        };
+    }
+
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .find(|m| is_response_to(m, id))
+}
+
+pub fn is_tools_list_changed(message: &Value) -> bool {
+    message.get("method").and_then(|m| m.as_str()) == Some(TOOLS_LIST_CHANGED)
+}
+
+/// JSON-RPC session for reading a server's tools and prompts and calling its tools. Stdio servers
+/// are reached over the shared stdio transport, through a process already connected for the
+/// server or one started on it for later calls to reuse.
+pub(crate) enum RpcSession {
+    Stdio {
+        processes: MCPProcessMap,
+        pid: u32,
+        timeout_ms: Option<u64>,
+    },
+    Http {
+        client: reqwest::Client,
+        url: String,
+        headers: HashMap<String, String>,
+        session_id: Option<String>,
+        next_id: u64,
+    },
+}
+
+impl RpcSession {
+    fn http(server: &MCPServerDefinition) -> Self {
+        let mut headers = server.headers.clone();
+        if let Some(token) = server.auth.as_ref().and_then(|a| a.token.as_ref()) {
+            headers.entry("Authorization".to_string()).or_insert_with(|| format!("Bearer {}", token));
+        }
+        RpcSession::Http {
+            client: reqwest::Client::new(),
+            url: server.url.clone().unwrap_or_default(),
+            headers,
+            session_id: None,
+            next_id: 0,
+        }
+    }
+
+    async fn send(&mut self, message: Value, expect_id: Option<u64>) -> Result<Option<Value>> {
+        match self {
+            RpcSession::Stdio { processes, pid, timeout_ms } => {
+                Ok(send_message(processes, *pid, &message.to_string(), *timeout_ms).await?)
+            }
+            RpcSession::Http { client, url, headers, session_id, .. } => {
+                let mut request = client.post(url.as_str())
+                    .header("Accept", "application/json, text/event-stream")
+                    .header("MCP-Protocol-Version", MCP_PROTOCOL_VERSION)
+                    .json(&message);
+                for (key, value) in headers.iter() {
+                    request = request.header(key, value);
+                }
+                if let Some(id) = session_id.as_ref() {
+                    request = request.header("Mcp-Session-Id", id);
+                }
+
+                let response = request.send().await.context("MCP request failed")?;
+                if !response.status().is_success() {
+                    return Err(anyhow!("MCP server returned HTTP {}", response.status()));
+                }
+                if let Some(id) = response.headers().get("mcp-session-id").and_then(|v| v.to_str().ok()) {
+                    *session_id = Some(id.to_string());
+                }
+                let body = response.text().await.context("Failed to read MCP response")?;
+
+                match expect_id {
+                    Some(id) => extract_http_response(&body, id)
+                        .map(Some)
+                        .ok_or_else(|| anyhow!("No response for MCP request {}", id)),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let (id, expect_id) = match self {
+            // Shares the process with the frontend's client, so ids must not collide with its own
+            RpcSession::Stdio { .. } => (json!(format!("banshee-rpc-{}", uuid::Uuid::new_v4())), None),
+            RpcSession::Http { next_id, .. } => {
+                *next_id += 1;
+                (json!(*next_id), Some(*next_id))
+            }
+        };
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let reply = self.send(message, expect_id).await?
+            .ok_or_else(|| anyhow!("No response for {}", method))?;
+        rpc_result(reply)
+    }
+
+    async fn notify(&mut self, method: &str) -> Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method }), None).await?;
+        Ok(())
+    }
+
+    /// Handshake, returning the server's reported version
+    async fn initialize(&mut self) -> Result<Option<String>> {
+        let initialized = self.request("initialize", json!({
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "banshee", "version": env!("CARGO_PKG_VERSION") }
+        })).await?;
+        self.notify("notifications/initialized").await?;
+        Ok(initialized.pointer("/serverInfo/version")
+            .and_then(Value::as_str)
+            .map(str::to_string))
+    }
+}
+
+/// Open an initialized session, returning it with the server's reported version. A stdio server
+/// without a connected process gets one, registered like any other and kept for later sessions.
+pub(crate) async fn open_session(app: &AppHandle, server: &MCPServerDefinition) -> Result<(RpcSession, Option<String>)> {
+    match server.transport {
+        MCPTransport::Stdio => {
+            let processes = app.state::<MCPProcessMap>().inner().clone();
+            if let Some((pid, server_version)) = find_server_process(&processes, &server.id) {
+                return Ok((RpcSession::Stdio { processes, pid, timeout_ms: server.timeout_ms }, server_version));
+            }
+
+            let workspace = active_workspace(&app.state::<AIState>().storage);
+            let pid = spawn_process(
+                app,
+                Some(server.id.clone()),
+                server.command.clone().unwrap_or_default(),
+                server.args.clone(),
+                server.env.clone(),
+                workspace,
+            )?;
+            let mut session = RpcSession::Stdio { processes: processes.clone(), pid, timeout_ms: server.timeout_ms };
+            match session.initialize().await {
+                Ok(server_version) => {
+                    spawn_health_checks(app.clone(), pid);
+                    info!("Connected to MCP server {} (pid {})", server.id, pid);
+                    Ok((session, server_version))
+                }
+                Err(e) => {
+                    processes.lock().unwrap().remove(&pid);
+                    terminate_process(pid, Duration::from_millis(100)).await;
+                    Err(e)
+                }
+            }
+        }
+        MCPTransport::Http => {
+            let mut session = RpcSession::http(server);
+            let server_version = session.initialize().await?;
+            Ok((session, server_version))
+        }
+        MCPTransport::Local => Err(anyhow!("Tool discovery is not supported for local socket servers")),
+    }
+}
+
+/// Every tool of an open session, following `tools/list` cursors
+pub(crate) async fn list_tools(session: &mut RpcSession) -> Result<Vec<MCPToolSchema>> {
+    let mut tools = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_TOOL_PAGES {
+        let params = match cursor.take() {
+            Some(c) => json!({ "cursor": c }),
+            None => json!({}),
+        };
+        let (page, next) = parse_tools_page(&session.request("tools/list", params).await?)?;
+        tools.extend(page);
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(tools),
+        }
+    }
+    Err(anyhow!("MCP server returned more than {} tool pages", MAX_TOOL_PAGES))
+}
+
+/// Call one tool on a server, within the server's timeout. Returns the `tools/call` result,
+/// which may itself report `isError`, after it passes `guard`.
+pub async fn call_server_tool(
+    app: &AppHandle,
+    server: &MCPServerDefinition,
+    tool_name: &str,
+    arguments: &Value,
+    guard: &mut GuardrailScope<'_>,
+) -> Result<Value> {
+    let call = async {
+        let (mut session, _) = open_session(app, server).await?;
+        session.request("tools/call", json!({ "name": tool_name, "arguments": arguments })).await
+    };
+    let timeout = Duration::from_millis(server.timeout_ms.unwrap_or(DEFAULT_FETCH_TIMEOUT_MS));
+    let mut result = tokio::time::timeout(timeout, call)
+        .await
+        .map_err(|_| anyhow!("Timed out after {} ms calling '{}' on '{}'", timeout.as_millis(), tool_name, server.id))??;
+    guard.check_tool_result(&mut result)?;
+    Ok(result)
+}
+
+/// The server's reported version and its tools
+async fn list_server_tools(app: &AppHandle, server: &MCPServerDefinition) -> Result<(Option<String>, Vec<MCPToolSchema>)> {
+    let (mut session, server_version) = open_session(app, server).await?;
+    Ok((server_version, list_tools(&mut session).await?))
+}
+
+/// Fetch a server's version and tools within its configured timeout
+pub async fn fetch_server_tools(app: &AppHandle, server: &MCPServerDefinition) -> Result<(Option<String>, Vec<MCPToolSchema>)> {
+    let timeout = Duration::from_millis(server.timeout_ms.unwrap_or(DEFAULT_FETCH_TIMEOUT_MS));
+    tokio::time::timeout(timeout, list_server_tools(app, server))
+        .await
+        .map_err(|_| anyhow!("Timed out fetching tools from '{}'", server.id))?
+}
+
+/// Re-read a registered server's tool list into the catalog and announce the change
+pub async fn refresh_server_catalog(
+    app: &AppHandle,
+    storage: &crate::ai::StorageManager,
+    catalog: &MCPToolCatalog,
+    server_id: &str,
+) -> Result<Vec<MCPToolSchema>> {
+    let server = find_server(storage, server_id)?;
+    if !server.enabled {
+        return Err(anyhow!("MCP server '{}' is disabled", server_id));
+    }
+
+    let (server_version, tools) = fetch_server_tools(app, &server).await?;
+    info!("Cached {} tools for MCP server {}", tools.len(), server_id);
+    catalog.store(server_id, server_version, tools.clone());
+    let _ = app.emit("mcp_tools_changed", server_id);
+    Ok(tools)
+}
+
+// Tauri Commands
+
+/// Fetch and cache the tool list of one server, or of every enabled server
+#[command]
+pub async fn refresh_mcp_tool_catalog(
+    server_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AIState>,
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<Vec<ServerToolCache>, String> {
+    let ids: Vec<String> = match server_id {
+        Some(id) => vec![id],
+        None => load_registry(&state.storage)
+            .redact_err("Failed to load MCP registry")?
+            .into_iter()
+            .filter(|s| s.enabled)
+            .map(|s| s.id)
+            .collect(),
+    };
+
+    let mut refreshed = Vec::new();
+    for id in ids {
+        match refresh_server_catalog(&app, &state.storage, &catalog, &id).await {
+            Ok(_) => refreshed.extend(catalog.server(&id)),
+            Err(e) => warn!("Failed to refresh tools for {}: {}", id, e),
+        }
+    }
+    Ok(refreshed)
+}
+
+/// Handle a JSON-RPC notification received from a server; refreshes on `tools/list_changed`
+#[command]
+pub async fn handle_mcp_notification(
+    server_id: String,
+    message: Value,
+    app: AppHandle,
+    state: State<'_, AIState>,
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<bool, String> {
+    if !is_tools_list_changed(&message) {
+        return Ok(false);
+    }
+
+    info!("Tool list changed for MCP server {}", server_id);
+    refresh_server_catalog(&app, &state.storage, &catalog, &server_id).await
+        .redact_err(&format!("Failed to refresh tools for {}", server_id))?;
+    Ok(true)
+}
+
+/// Register (or with an empty list, remove) the tools a plugin provides
+#[command]
+pub async fn register_plugin_tools(
+    plugin_id: String,
+    tools: Vec<MCPToolSchema>,
+    app: AppHandle,
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<(), String> {
+    if plugin_id.trim().is_empty() {
+        return Err("Plugin id cannot be empty".to_string());
+    }
+    if tools.iter().any(|t| t.name.trim().is_empty()) {
+        return Err("Plugin tools must have a name".to_string());
+    }
+
+    catalog.set_plugin_tools(&plugin_id, tools);
+    let _ = app.emit("mcp_tools_changed", &plugin_id);
+    Ok(())
+}
+
+/// Every tool the agent runtime can call right now, with parameter schemas
+#[command]
+pub async fn get_all_available_tools(
+    catalog: State<'_, MCPToolCatalog>,
+) -> Result<Vec<AvailableTool>, String> {
+    Ok(catalog.all_tools())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tools_and_sse_responses() {
+        let result = json!({
+            "tools": [
+                { "name": "search", "description": "Search issues", "inputSchema": { "type": "object", "properties": { "q": { "type": "string" } } } },
+                { "name": "ping" }
+            ],
+            "nextCursor": "page-2"
+        });
+        let (tools, cursor) = parse_tools_page(&result).unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[1].input_schema, empty_object_schema());
+        assert_eq!(cursor.as_deref(), Some("page-2"));
+        assert!(parse_tools_page(&json!({})).is_err());
+
+        let sse = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\"}\n\ndata: {\"jsonrpc\":\"2.0\",\"id\":3,\"result\":{\"tools\":[]}}\n\n";
+        assert_eq!(extract_http_response(sse, 3).unwrap()["result"], json!({ "tools": [] }));
+        assert!(extract_http_response("{\"jsonrpc\":\"2.0\",\"id\":4,\"result\":{}}", 3).is_none());
+
+        assert!(is_tools_list_changed(&json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" })));
+        assert!(rpc_result(json!({ "id": 1, "error": { "code": -32601, "message": "nope" } })).is_err());
+    }
+
+    #[test]
+    fn test_catalog_aggregates_sources() {
+        let catalog = MCPToolCatalog::new();
+        let tool = |name: &str| MCPToolSchema { name: name.to_string(), description: None, input_schema: empty_object_schema(), annotations: Default::default() };
+        catalog.store("github", Some("1.2.0".to_string()), vec![tool("create_issue")]);
+        catalog.set_plugin_tools("calendar", vec![tool("list_events")]);
+
+        let builtin_count = builtin_tools().len();
+        let all = catalog.all_tools();
+        assert_eq!(all.len(), builtin_count + 2);
+        assert_eq!(all[builtin_count].qualified_name, "mcp__github__create_issue");
+        assert_eq!(all[builtin_count + 1].source, ToolSource::Plugin { plugin_id: "calendar".to_string() });
+
+        catalog.mark_disconnected("github");
+        assert_eq!(catalog.all_tools().len(), builtin_count + 1);
+        assert_eq!(catalog.server("github").unwrap().tools.len(), 1);
+    }
+}
@@ -0,0 +1,304 @@
+//! Progress reporting for long-running commands. A command starts an operation and returns its id
+//! at once; the work reports progress through its `OperationHandle`, and the UI follows it through
+//! `operation_progress` events or `get_operation_status` and can stop it with `cancel_operation`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// Event emitted with the `OperationStatus` whenever an operation starts, progresses or ends
+pub const OPERATION_PROGRESS_EVENT: &str = "operation_progress";
+
+/// Finished operations kept for status queries; older ones are dropped first
+const MAX_FINISHED_OPERATIONS: usize = 100;
+
+/// Error returned by work that stopped because it was cancelled
+pub const OPERATION_CANCELLED: &str = "Operation cancelled";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationStatus {
+    pub id: String,
+    /// What the operation does, e.g. `memory_backup`
+    pub kind: String,
+    pub state: OperationState,
+    /// Fraction done, from 0 to 1
+    pub progress: f32,
+    pub message: Option<String>,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    /// Set once cancellation is requested; the work stops at its next check
+    pub cancel_requested: bool,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+struct TrackedOperation {
+    status: OperationStatus,
+    cancel: watch::Sender<bool>,
+}
+
+/// Every running operation and the most recent finished ones
+#[derive(Clone, Default)]
+pub struct OperationRegistry {
+    operations: Arc<Mutex<HashMap<String, TrackedOperation>>>,
+}
+
+impl OperationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a new running operation
+    pub fn register(&self, kind: &str, app: Option<AppHandle>) -> OperationHandle {
+        let (cancel, cancelled) = watch::channel(false);
+        let status = OperationStatus {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            state: OperationState::Running,
+            progress: 0.0,
+            message: None,
+            result: None,
+            error: None,
+            cancel_requested: false,
+            started_at: chrono::Utc::now(),
+            finished_at: None,
+        };
+        let handle = OperationHandle { id: status.id.clone(), registry: self.clone(), app, cancelled };
+        handle.emit(&status);
+        self.operations.lock().unwrap().insert(status.id.clone(), TrackedOperation { status, cancel });
+        handle
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut OperationStatus)) -> Option<OperationStatus> {
+        let mut operations = self.operations.lock().unwrap();
+        let operation = operations.get_mut(id)?;
+        f(&mut operation.status);
+        Some(operation.status.clone())
+    }
+
+    pub fn status(&self, id: &str) -> Option<OperationStatus> {
+        self.operations.lock().unwrap().get(id).map(|op| op.status.clone())
+    }
+
+    /// Running operations first, then the rest, newest first
+    pub fn list(&self) -> Vec<OperationStatus> {
+        let mut statuses: Vec<OperationStatus> = self.operations.lock().unwrap()
+            .values()
+            .map(|op| op.status.clone())
+            .collect();
+        statuses.sort_by(|a, b| {
+            (b.state == OperationState::Running).cmp(&(a.state == OperationState::Running))
+                .then(b.started_at.cmp(&a.started_at))
+        });
+        statuses
+    }
+
+    /// Ask a running operation to stop
+    pub fn cancel(&self, id: &str) -> Result<OperationStatus, String> {
+        let mut operations = self.operations.lock().unwrap();
+        let operation = operations.get_mut(id).ok_or_else(|| format!("Unknown operation: {}", id))?;
+        if operation.status.state != OperationState::Running {
+            return Err(format!("Operation {} has already finished", id));
+        }
+        operation.status.cancel_requested = true;
+        operation.cancel.send_replace(true);
+        Ok(operation.status.clone())
+    }
+
+    fn finish(&self, id: &str, outcome: Result<Value, String>) -> Option<OperationStatus> {
+        let status = self.update(id, |status| {
+            status.finished_at = Some(chrono::Utc::now());
+            match outcome {
+                Ok(result) => {
+                    status.state = OperationState::Completed;
+                    status.progress = 1.0;
+                    status.result = Some(result);
+                }
+                Err(_) if status.cancel_requested => status.state = OperationState::Cancelled,
+                Err(e) => {
+                    status.state = OperationState::Failed;
+                    status.error = Some(e);
+                }
+            }
+        });
+        self.prune();
+        status
+    }
+
+    fn prune(&self) {
+        let mut operations = self.operations.lock().unwrap();
+        let mut finished: Vec<(chrono::DateTime<chrono::Utc>, String)> = operations.values()
+            .filter_map(|op| op.status.finished_at.map(|at| (at, op.status.id.clone())))
+            .collect();
+        if finished.len() <= MAX_FINISHED_OPERATIONS {
+            return;
+        }
+        finished.sort();
+        for (_, id) in finished.iter().take(finished.len() - MAX_FINISHED_OPERATIONS) {
+            operations.remove(id);
+        }
+    }
+}
+
+/// Given to an operation's work to report progress and notice cancellation
+#[derive(Clone)]
+pub struct OperationHandle {
+    id: String,
+    registry: OperationRegistry,
+    app: Option<AppHandle>,
+    cancelled: watch::Receiver<bool>,
+}
+
+impl OperationHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn emit(&self, status: &OperationStatus) {
+        if let Some(app) = &self.app {
+            if let Err(e) = app.emit(OPERATION_PROGRESS_EVENT, status) {
+                error!("Failed to emit operation progress: {}", e);
+            }
+        }
+    }
+
+    /// Record progress as a fraction from 0 to 1 with a message for the UI
+    pub fn report(&self, progress: f32, message: impl Into<String>) {
+        let message = message.into();
+        if let Some(status) = self.registry.update(&self.id, |status| {
+            status.progress = progress.clamp(0.0, 1.0);
+            status.message = Some(message);
+        }) {
+            self.emit(&status);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    /// `Err(OPERATION_CANCELLED)` once cancellation is requested, for `?` between steps
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err(OPERATION_CANCELLED.to_string());
+        }
+        Ok(())
+    }
+
+    /// Resolves when cancellation is requested; for racing work that can be dropped mid-way
+    pub async fn cancelled(&self) {
+        let mut cancelled = self.cancelled.clone();
+        if cancelled.wait_for(|cancelled| *cancelled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    fn finish(&self, outcome: Result<Value, String>) {
+        if let Some(status) = self.registry.finish(&self.id, outcome) {
+            match status.state {
+                OperationState::Failed => warn!("Operation {} ({}) failed: {}", status.id, status.kind, status.error.as_deref().unwrap_or_default()),
+                state => info!("Operation {} ({}) finished: {:?}", status.id, status.kind, state),
+            }
+            self.emit(&status);
+        }
+    }
+}
+
+/// Run `work` in the background as a tracked operation and return its id
+pub fn start_operation<F, Fut>(app: &AppHandle, kind: &str, work: F) -> String
+where
+    F: FnOnce(OperationHandle) -> Fut,
+    Fut: Future<Output = Result<Value, String>> + Send + 'static,
+{
+    let handle = app.state::<OperationRegistry>().register(kind, Some(app.clone()));
+    let id = handle.id.clone();
+    let work = work(handle.clone());
+    tauri::async_runtime::spawn(async move {
+        let outcome = work.await;
+        handle.finish(outcome);
+    });
+    id
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub async fn get_operation_status(
+    operation_id: String,
+    registry: State<'_, OperationRegistry>,
+) -> Result<OperationStatus, String> {
+    registry.status(&operation_id).ok_or_else(|| format!("Unknown operation: {}", operation_id))
+}
+
+#[tauri::command]
+pub async fn list_operations(registry: State<'_, OperationRegistry>) -> Result<Vec<OperationStatus>, String> {
+    Ok(registry.list())
+}
+
+#[tauri::command]
+pub async fn cancel_operation(
+    operation_id: String,
+    app_handle: AppHandle,
+    registry: State<'_, OperationRegistry>,
+) -> Result<OperationStatus, String> {
+    let status = registry.cancel(&operation_id)?;
+    info!("Cancellation requested for operation {} ({})", status.id, status.kind);
+    if let Err(e) = app_handle.emit(OPERATION_PROGRESS_EVENT, &status) {
+        error!("Failed to emit operation progress: {}", e);
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_operation_lifecycle() {
+        let registry = OperationRegistry::new();
+        let done = registry.register("memory_backup", None);
+        done.report(1.5, "Copying");
+        assert_eq!(registry.status(done.id()).unwrap().progress, 1.0);
+        done.finish(Ok(json!("/tmp/backup.db")));
+        let status = registry.status(done.id()).unwrap();
+        assert_eq!(status.state, OperationState::Completed);
+        assert!(registry.cancel(done.id()).is_err());
+
+        let cancelled = registry.register("workflow_run", None);
+        assert!(cancelled.check_cancelled().is_ok());
+        registry.cancel(cancelled.id()).unwrap();
+        assert!(cancelled.is_cancelled());
+        cancelled.finish(cancelled.check_cancelled().map(|_| Value::Null));
+        assert_eq!(registry.status(cancelled.id()).unwrap().state, OperationState::Cancelled);
+
+        let failed = registry.register("workflow_run", None);
+        failed.finish(Err("boom".to_string()));
+        assert_eq!(registry.status(failed.id()).unwrap().error.as_deref(), Some("boom"));
+        assert!(registry.cancel("missing").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_resolves_on_cancel() {
+        let registry = OperationRegistry::new();
+        let handle = registry.register("embedding_migration", None);
+        let waiter = handle.clone();
+        let wait = tokio::spawn(async move { waiter.cancelled().await });
+        registry.cancel(handle.id()).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(1), wait).await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+//! Orderly shutdown. Exit is held back until sessions are closed, MCP servers have stopped,
+//! neural weights are saved and every database's WAL is checkpointed into its main file, so
+//! quitting never leaves an agent database mid-write.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, RunEvent};
+use tracing::{error, info, warn};
+
+use crate::database::agent_sessions::AgentSessionManager;
+use crate::database::open_conversation_db;
+use crate::database::pool::checkpoint_all;
+use crate::database::simple_commands::MemoryState;
+use crate::database::startup_health::clear_run_sentinel;
+use crate::mcp::{terminate_process, MCPProcessMap};
+
+/// Longest the app waits for shutdown work before exiting anyway
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time MCP servers get to exit after SIGTERM before they are killed
+pub const MCP_TERMINATE_GRACE: Duration = Duration::from_secs(2);
+
+static SHUTDOWN_STARTED: AtomicBool = AtomicBool::new(false);
+static SHUTDOWN_FINISHED: AtomicBool = AtomicBool::new(false);
+
+fn close_sessions(app: &AppHandle) {
+    let closed = open_conversation_db(app)
+        .and_then(|conn| app.state::<AgentSessionManager>().close_all(&conn));
+    match closed {
+        Ok(count) => info!("Closed {} agent sessions", count),
+        Err(e) => error!("Failed to close agent sessions: {}", e),
+    }
+}
+
+async fn stop_mcp_processes(app: &AppHandle) {
+    // Dropping each process's writer closes its stdin before the signal arrives
+    let pids: Vec<u32> = app.state::<MCPProcessMap>().lock().unwrap().drain().map(|(pid, _)| pid).collect();
+    if pids.is_empty() {
+        return;
+    }
+    info!("Stopping {} MCP processes", pids.len());
+    futures::future::join_all(pids.into_iter().map(|pid| terminate_process(pid, MCP_TERMINATE_GRACE))).await;
+}
+
+/// Save the embedding networks and the knowledge graph's node, edge, attention and
+/// sequence networks; either may not have been built this run
+async fn save_neural_models(app: &AppHandle) {
+    let state = app.state::<MemoryState>();
+    let service = state.neural_embedding_handle();
+    let service = service.lock().await;
+    if let Some(service) = service.as_ref() {
+        match service.save_models() {
+            Ok(()) => info!("Saved neural embedding weights"),
+            Err(e) => error!("Failed to save neural embedding weights: {}", e),
+        }
+    }
+
+    let graph = state.neural_graph_handle();
+    let graph = graph.lock().await;
+    if let Some(graph) = graph.as_ref() {
+        match graph.save_models() {
+            Ok(()) => info!("Saved knowledge graph and sequence network weights"),
+            Err(e) => error!("Failed to save knowledge graph network weights: {}", e),
+        }
+    }
+}
+
+fn checkpoint_databases(app: &AppHandle) {
+    let conversations = open_conversation_db(app)
+        .and_then(|conn| Ok(conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?));
+    if let Err(e) = conversations {
+        error!("Failed to checkpoint the conversation database: {}", e);
+    }
+    for (path, e) in checkpoint_all() {
+        error!("Failed to checkpoint {}: {}", path.display(), e);
+    }
+}
+
+/// Flush state in dependency order: sessions and MCP servers stop writing before the
+/// databases are checkpointed
+pub async fn shutdown(app: &AppHandle) {
+    let work = async {
+        close_sessions(app);
+        stop_mcp_processes(app).await;
+        save_neural_models(app).await;
+        checkpoint_databases(app);
+    };
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, work).await.is_err() {
+        warn!("Shutdown did not finish within {:?}; exiting anyway", SHUTDOWN_TIMEOUT);
+        return;
+    }
+    // Only a completed flush counts as a clean exit for the next startup check
+    if let Err(e) = clear_run_sentinel() {
+        error!("Failed to clear the run sentinel: {}", e);
+    }
+    info!("Shutdown complete");
+}
+
+/// Hold back the first exit request until `shutdown` has run, then exit with its code
+pub fn handle_run_event(app: &AppHandle, event: &RunEvent) {
+    match event {
+        RunEvent::ExitRequested { code, api, .. } => {
+            if SHUTDOWN_FINISHED.load(Ordering::SeqCst) {
+                return;
+            }
+            api.prevent_exit();
+            if SHUTDOWN_STARTED.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let (app, code) = (app.clone(), code.unwrap_or(0));
+            tauri::async_runtime::spawn(async move {
+                shutdown(&app).await;
+                SHUTDOWN_FINISHED.store(true, Ordering::SeqCst);
+                app.exit(code);
+            });
+        }
+        // Exiting without an exit request first still gets a best-effort flush
+        RunEvent::Exit if !SHUTDOWN_STARTED.swap(true, Ordering::SeqCst) => {
+            tauri::async_runtime::block_on(shutdown(app));
+        }
+        _ => {}
+    }
+}
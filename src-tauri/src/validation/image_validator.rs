@@ -0,0 +1,110 @@
+use thiserror::Error;
+
+/// Largest image accepted; the tightest limit among supported providers
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Most images a single message may carry
+pub const MAX_IMAGES_PER_MESSAGE: usize = 20;
+
+/// Formats every supported provider accepts
+pub const SUPPORTED_IMAGE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ImageValidationError {
+    #[error("Image is empty")]
+    Empty,
+
+    #[error("Image is {0} bytes; the limit is {MAX_IMAGE_BYTES}")]
+    TooLarge(usize),
+
+    #[error("Unsupported image format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Image is {detected} but was declared as {declared}")]
+    FormatMismatch { declared: String, detected: String },
+
+    #[error("A message can carry at most {MAX_IMAGES_PER_MESSAGE} images, got {0}")]
+    TooManyImages(usize),
+}
+
+pub struct ImageValidator;
+
+impl ImageValidator {
+    /// Media type from the file signature, if it is a supported format
+    pub fn detect_media_type(bytes: &[u8]) -> Option<&'static str> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some("image/png")
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some("image/jpeg")
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some("image/gif")
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some("image/webp")
+        } else {
+            None
+        }
+    }
+
+    /// Check size and format, returning the media type the content actually has. A declared type
+    /// must agree with the content.
+    pub fn validate_image(bytes: &[u8], declared: Option<&str>) -> Result<&'static str, ImageValidationError> {
+        if bytes.is_empty() {
+            return Err(ImageValidationError::Empty);
+        }
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(ImageValidationError::TooLarge(bytes.len()));
+        }
+
+        let declared = declared.map(|d| d.trim().to_lowercase()).filter(|d| !d.is_empty());
+        let declared = declared.map(|d| if d == "image/jpg" { "image/jpeg".to_string() } else { d });
+        if let Some(declared) = &declared {
+            if !SUPPORTED_IMAGE_TYPES.contains(&declared.as_str()) {
+                return Err(ImageValidationError::UnsupportedFormat(declared.clone()));
+            }
+        }
+
+        let detected = Self::detect_media_type(bytes)
+            .ok_or_else(|| ImageValidationError::UnsupportedFormat("unrecognized image data".to_string()))?;
+        match declared {
+            Some(declared) if declared != detected => Err(ImageValidationError::FormatMismatch {
+                declared,
+                detected: detected.to_string(),
+            }),
+            _ => Ok(detected),
+        }
+    }
+
+    pub fn validate_image_count(count: usize) -> Result<(), ImageValidationError> {
+        if count > MAX_IMAGES_PER_MESSAGE {
+            return Err(ImageValidationError::TooManyImages(count));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_validate_image() {
+        // Valid cases
+        assert_eq!(ImageValidator::validate_image(PNG, None), Ok("image/png"));
+        assert_eq!(ImageValidator::validate_image(PNG, Some("IMAGE/PNG")), Ok("image/png"));
+        assert_eq!(ImageValidator::validate_image(&[0xFF, 0xD8, 0xFF, 0xE0], Some("image/jpg")), Ok("image/jpeg"));
+        assert_eq!(ImageValidator::validate_image(b"RIFF\0\0\0\0WEBPVP8 ", None), Ok("image/webp"));
+
+        // Invalid cases
+        assert_eq!(ImageValidator::validate_image(&[], None), Err(ImageValidationError::Empty));
+        assert!(matches!(ImageValidator::validate_image(PNG, Some("image/gif")), Err(ImageValidationError::FormatMismatch { .. })));
+        assert!(matches!(ImageValidator::validate_image(PNG, Some("image/tiff")), Err(ImageValidationError::UnsupportedFormat(_))));
+        assert!(matches!(ImageValidator::validate_image(b"<svg></svg>", None), Err(ImageValidationError::UnsupportedFormat(_))));
+        let oversized = [PNG, &vec![0u8; MAX_IMAGE_BYTES]].concat();
+        assert_eq!(ImageValidator::validate_image(&oversized, None), Err(ImageValidationError::TooLarge(oversized.len())));
+
+        assert!(ImageValidator::validate_image_count(MAX_IMAGES_PER_MESSAGE).is_ok());
+        assert!(ImageValidator::validate_image_count(MAX_IMAGES_PER_MESSAGE + 1).is_err());
+    }
+}
@@ -0,0 +1,317 @@
+//! JSON Schema validation shared by everything that checks a value against a schema: agent
+//! configurations, structured model output and the like. Covers the keywords models and
+//! configurations use: types, enums and consts, required and additional properties, items,
+//! length, count and range bounds, patterns, combinators and local `$ref`s.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Nesting beyond this is treated as a `$ref` cycle
+const MAX_SCHEMA_DEPTH: usize = 64;
+
+const TYPES: [&str; 7] = ["null", "boolean", "object", "array", "string", "number", "integer"];
+
+/// Where a value failed its schema; `path` is a JSON pointer, empty for the root
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", if self.path.is_empty() { "/" } else { &self.path }, self.message)
+    }
+}
+
+fn pointer_child(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        other => type_name(value) == other,
+    }
+}
+
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    reference.strip_prefix('#').and_then(|pointer| root.pointer(pointer))
+}
+
+/// Every way `value` fails `schema`; empty when it conforms
+pub fn validate_json_schema(value: &Value, schema: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    check_value(value, schema, schema, "", 0, &mut violations);
+    violations
+}
+
+fn check_value(value: &Value, schema: &Value, root: &Value, path: &str, depth: usize, out: &mut Vec<SchemaViolation>) {
+    let mut violation = |message: String| out.push(SchemaViolation { path: path.to_string(), message });
+    let schema = match schema {
+        Value::Bool(false) => return violation("no value is allowed here".to_string()),
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+    if depth > MAX_SCHEMA_DEPTH {
+        return violation("schema nests too deeply".to_string());
+    }
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match resolve_ref(root, reference) {
+            Some(target) => check_value(value, target, root, path, depth + 1, out),
+            None => out.push(SchemaViolation { path: path.to_string(), message: format!("unresolvable $ref {}", reference) }),
+        }
+    }
+    let mut violation = |message: String| out.push(SchemaViolation { path: path.to_string(), message });
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(value, t)) {
+            // Other keywords only add noise once the type is wrong
+            return violation(format!("expected {}, got {}", allowed.join(" or "), type_name(value)));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            violation(format!("must be one of {}", Value::Array(options.clone())));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            violation(format!("must be {}", constant));
+        }
+    }
+
+    match value {
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64).filter(|min| length < *min) {
+                violation(format!("must be at least {} characters", min));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64).filter(|max| length > *max) {
+                violation(format!("must be at most {} characters", max));
+            }
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                if Regex::new(pattern).is_ok_and(|regex| !regex.is_match(text)) {
+                    violation(format!("must match pattern {}", pattern));
+                }
+            }
+        }
+        Value::Number(number) => {
+            let n = number.as_f64().unwrap_or_default();
+            let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+            if let Some(min) = bound("minimum").filter(|min| n < *min) {
+                violation(format!("must be at least {}", min));
+            }
+            if let Some(max) = bound("maximum").filter(|max| n > *max) {
+                violation(format!("must be at most {}", max));
+            }
+            if let Some(min) = bound("exclusiveMinimum").filter(|min| n <= *min) {
+                violation(format!("must be greater than {}", min));
+            }
+            if let Some(max) = bound("exclusiveMaximum").filter(|max| n >= *max) {
+                violation(format!("must be less than {}", max));
+            }
+        }
+        Value::Array(items) => {
+            let count = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64).filter(|min| count < *min) {
+                violation(format!("must have at least {} items", min));
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64).filter(|max| count > *max) {
+                violation(format!("must have at most {} items", max));
+            }
+            if schema.get("uniqueItems") == Some(&Value::Bool(true))
+                && items.iter().enumerate().any(|(i, item)| items[..i].contains(item))
+            {
+                violation("items must be unique".to_string());
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_value(item, item_schema, root, &format!("{}/{}", path, i), depth + 1, out);
+                }
+            }
+        }
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for key in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    out.push(SchemaViolation { path: pointer_child(path, key), message: "is required".to_string() });
+                }
+            }
+            for (key, item) in object {
+                let child = pointer_child(path, key);
+                match (properties.and_then(|p| p.get(key)), schema.get("additionalProperties")) {
+                    (Some(property), _) => check_value(item, property, root, &child, depth + 1, out),
+                    (None, Some(Value::Bool(false))) => {
+                        out.push(SchemaViolation { path: child, message: "is not an allowed property".to_string() });
+                    }
+                    (None, Some(additional)) => check_value(item, additional, root, &child, depth + 1, out),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let branch_matches = |branch: &Value| validate_branch(value, branch, root, path, depth);
+    if let Some(branches) = schema.get("allOf").and_then(Value::as_array) {
+        for branch in branches {
+            check_value(value, branch, root, path, depth + 1, out);
+        }
+    }
+    if let Some(branches) = schema.get("anyOf").and_then(Value::as_array) {
+        if !branches.iter().any(branch_matches) {
+            out.push(SchemaViolation { path: path.to_string(), message: "does not match any of the allowed schemas".to_string() });
+        }
+    }
+    if let Some(branches) = schema.get("oneOf").and_then(Value::as_array) {
+        let matching = branches.iter().filter(|branch| branch_matches(branch)).count();
+        if matching != 1 {
+            out.push(SchemaViolation { path: path.to_string(), message: format!("must match exactly one schema, matched {}", matching) });
+        }
+    }
+}
+
+fn validate_branch(value: &Value, branch: &Value, root: &Value, path: &str, depth: usize) -> bool {
+    let mut violations = Vec::new();
+    check_value(value, branch, root, path, depth + 1, &mut violations);
+    violations.is_empty()
+}
+
+/// Reject schemas the validator can't apply faithfully: unknown types, bad patterns and
+/// unresolvable references
+pub fn check_schema(schema: &Value) -> Result<(), SchemaViolation> {
+    check_schema_node(schema, schema, "", 0)
+}
+
+fn check_schema_node(node: &Value, root: &Value, path: &str, depth: usize) -> Result<(), SchemaViolation> {
+    let invalid = |message: String| Err(SchemaViolation { path: path.to_string(), message });
+    let node = match node {
+        Value::Bool(_) => return Ok(()),
+        Value::Object(node) => node,
+        other => return invalid(format!("expected a schema object, got {}", type_name(other))),
+    };
+    if depth > MAX_SCHEMA_DEPTH {
+        return invalid("schema nests too deeply".to_string());
+    }
+
+    match node.get("type") {
+        None => {}
+        Some(Value::String(t)) if TYPES.contains(&t.as_str()) => {}
+        Some(Value::Array(types)) if !types.is_empty() && types.iter().all(|t| t.as_str().is_some_and(|t| TYPES.contains(&t))) => {}
+        Some(other) => return invalid(format!("unsupported type {}", other)),
+    }
+    if let Some(pattern) = node.get("pattern") {
+        let pattern = pattern.as_str().unwrap_or_default();
+        if let Err(e) = Regex::new(pattern) {
+            return invalid(format!("invalid pattern {}: {}", pattern, e));
+        }
+    }
+    if let Some(reference) = node.get("$ref") {
+        if reference.as_str().and_then(|r| resolve_ref(root, r)).is_none() {
+            return invalid(format!("unresolvable $ref {}", reference));
+        }
+    }
+    if node.get("required").is_some_and(|required| !required.as_array().is_some_and(|keys| keys.iter().all(Value::is_string))) {
+        return invalid("required must be an array of property names".to_string());
+    }
+
+    for keyword in ["properties", "$defs", "definitions"] {
+        if let Some(children) = node.get(keyword) {
+            let children = match children.as_object() {
+                Some(children) => children,
+                None => return invalid(format!("{} must be an object", keyword)),
+            };
+            for (key, child) in children {
+                check_schema_node(child, root, &pointer_child(&pointer_child(path, keyword), key), depth + 1)?;
+            }
+        }
+    }
+    for keyword in ["items", "additionalProperties"] {
+        if let Some(child) = node.get(keyword) {
+            check_schema_node(child, root, &pointer_child(path, keyword), depth + 1)?;
+        }
+    }
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        if let Some(branches) = node.get(keyword) {
+            let branches = match branches.as_array() {
+                Some(branches) if !branches.is_empty() => branches,
+                _ => return invalid(format!("{} must be a non-empty array", keyword)),
+            };
+            for (i, branch) in branches.iter().enumerate() {
+                check_schema_node(branch, root, &format!("{}/{}", pointer_child(path, keyword), i), depth + 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(violations: &[SchemaViolation]) -> Vec<&str> {
+        violations.iter().map(|v| v.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_violations_carry_their_path() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 2 },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" }, "maxItems": 3 },
+                "score": { "type": "integer", "minimum": 0, "maximum": 10 },
+                "a/b": { "enum": ["x", "y"] },
+                "owner": { "anyOf": [{ "type": "null" }, { "type": "object", "required": ["id"] }] },
+            },
+            "required": ["name", "score"],
+            "additionalProperties": false,
+            "$defs": { "tag": { "type": "string", "pattern": "^[a-z]+$" } },
+        });
+        assert!(check_schema(&schema).is_ok());
+
+        let valid = json!({ "name": "Ada", "tags": ["math"], "score": 7, "owner": null });
+        assert!(validate_json_schema(&valid, &schema).is_empty());
+        assert!(validate_json_schema(&json!({ "name": "Ada", "score": 7.0, "owner": { "id": 1 } }), &schema).is_empty());
+
+        let invalid = json!({ "name": "A", "tags": ["ok", "Not OK", 3], "score": 11, "a/b": "z", "owner": {}, "extra": true });
+        let violations = validate_json_schema(&invalid, &schema);
+        assert_eq!(paths(&violations), vec!["/a~1b", "/extra", "/name", "/owner", "/score", "/tags/1", "/tags/2"]);
+        assert_eq!(violations[5].to_string(), "/tags/1: must match pattern ^[a-z]+$");
+        assert_eq!(violations[6].message, "expected string, got integer");
+
+        let missing = validate_json_schema(&json!({}), &schema);
+        assert_eq!(paths(&missing), vec!["/name", "/score"]);
+        assert_eq!(validate_json_schema(&json!([]), &schema)[0].to_string(), "/: expected object, got array");
+    }
+
+    #[test]
+    fn test_invalid_schemas_are_rejected() {
+        assert!(check_schema(&json!({ "type": "decimal" })).is_err());
+        assert_eq!(check_schema(&json!({ "properties": { "id": { "pattern": "(" } } })).unwrap_err().path, "/properties/id");
+        assert!(check_schema(&json!({ "items": { "$ref": "#/$defs/missing" } })).is_err());
+        assert_eq!(check_schema(&json!("object")).unwrap_err().to_string(), "/: expected a schema object, got string");
+    }
+}
@@ -4,9 +4,13 @@ use uuid::Uuid;
 use anyhow::{Result, anyhow};
 use once_cell::sync::Lazy;
 
-// Re-export graph validator
+// Re-export graph and image validators
 pub mod graph_validator;
 pub use graph_validator::{GraphValidator, GraphValidationError};
+pub mod image_validator;
+pub use image_validator::ImageValidator;
+pub mod json_schema;
+pub use json_schema::{check_schema, validate_json_schema, SchemaViolation};
 
 /// Memory validation module that mirrors frontend MemoryValidation class
 /// Provides comprehensive input validation for all memory-related operations
@@ -4,10 +4,12 @@ import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query';
 import { invoke } from '@tauri-apps/api/core';
 
 export interface AgentSessionData {
+  sessionId: string;
   agentId: string;
   agent: Agent;
   conversationId?: string;
   conversation?: Conversation;
+  createdAt: string;
 }
 
 async function fetchActiveSessions(): Promise<AgentSessionData[]> {
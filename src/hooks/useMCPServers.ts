@@ -1,4 +1,4 @@
-import type { MCPServer } from '@/lib/mcp/types';
+import type { MCPConnectionDiagnostics, MCPServer } from '@/lib/mcp/types';
 import { toast } from '@/store/uiStore';
 import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query';
 import { invoke } from '@tauri-apps/api/core';
@@ -20,7 +20,7 @@ async function disconnectMCPServer(serverId: string): Promise<void> {
   await invoke('disconnect_mcp_server_command', { serverId });
 }
 
-async function testMCPConnection(serverId: string): Promise<boolean> {
+async function testMCPConnection(serverId: string): Promise<MCPConnectionDiagnostics> {
   return await invoke('test_mcp_connection_command', { serverId });
 }
 
@@ -67,11 +67,14 @@ export function useDisconnectMCPServer() {
 export function useTestMCPConnection() {
   return useMutation({
     mutationFn: testMCPConnection,
-    onSuccess: (isConnected) => {
-      if (isConnected) {
-        toast.success('Connection test passed', 'MCP server is reachable');
+    onSuccess: (diagnostics) => {
+      if (diagnostics.reachable) {
+        toast.success(
+          'Connection test passed',
+          `Handshake in ${diagnostics.handshake_ms} ms, ${diagnostics.tool_count} tools available`
+        );
       } else {
-        toast.warning('Connection test failed', 'MCP server is not responding');
+        toast.warning('Connection test failed', diagnostics.last_error ?? 'MCP server is not responding');
       }
     },
     onError: (error) => {
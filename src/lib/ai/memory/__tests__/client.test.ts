@@ -200,6 +200,9 @@ describe('MemoryClient', () => {
         limit: searchRequest.limit,
         offset: searchRequest.offset,
         similarityThreshold: null,
+        fusionWeights: null,
+        tiers: null,
+        sessionId: null,
       });
     });
 
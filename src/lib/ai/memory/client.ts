@@ -1,21 +1,40 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import type {
   AgentMemory,
+  AnomalyKind,
+  ConsolidationOptions,
+  ConsolidationReport,
   CreateEdgeRequest,
   CreateKnowledgeRequest,
   CreateMemoryRequest,
   CreateNodeRequest,
+  DedupStats,
+  EmbeddingEvaluationReport,
+  EmbeddingProviderConfig,
+  EncryptionMigrationReport,
   KnowledgeEdge,
   KnowledgeNode,
   KnowledgeType,
+  MemoryAnomaly,
+  MemoryDedupConfig,
+  MemoryPatternReport,
+  MemoryQuota,
+  MemoryQuotaStatus,
+  MemoryRetentionPolicy,
   MemorySearchResult,
+  PatternTimeRange,
   NodeType,
   RelationshipType,
+  RetentionReport,
   SearchMemoriesRequest,
   SharedKnowledge,
 } from './types';
 import { MemoryType } from './types';
 
+/** Emitted by the backend with a `MemoryAnomaly` whenever a saved memory looks unusual */
+export const MEMORY_ANOMALY_EVENT = 'memory://anomaly';
+
 /**
  * Client for interacting with the Agent Memory System
  */
@@ -80,6 +99,9 @@ export class MemoryClient {
         limit: request.limit || null,
         offset: request.offset || null,
         similarityThreshold: request.similarity_threshold || null,
+        fusionWeights: request.fusion_weights || null,
+        tiers: request.tiers || null,
+        sessionId: request.session_id || null,
       });
       return results;
     } catch (error) {
@@ -88,6 +110,84 @@ export class MemoryClient {
     }
   }
 
+  /**
+   * List an agent's memories, optionally of one type
+   */
+  static async listMemories(
+    agentId: string,
+    options: { memoryType?: MemoryType; limit?: number; offset?: number } = {}
+  ): Promise<AgentMemory[]> {
+    try {
+      return await invoke<AgentMemory[]>('list_agent_memories', {
+        agentId,
+        memoryType: options.memoryType ?? null,
+        limit: options.limit ?? null,
+        offset: options.offset ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to list memories:', error);
+      throw new Error(`Failed to list memories: ${error}`);
+    }
+  }
+
+  /**
+   * Update a memory's content, tags or metadata
+   */
+  static async updateMemory(
+    agentId: string,
+    memoryId: string,
+    changes: { content?: string; tags?: string[]; metadata?: Record<string, string> }
+  ): Promise<AgentMemory> {
+    try {
+      return await invoke<AgentMemory>('update_agent_memory', {
+        agentId,
+        memoryId,
+        content: changes.content ?? null,
+        tags: changes.tags ?? null,
+        metadata: changes.metadata ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to update memory:', error);
+      throw new Error(`Failed to update memory: ${error}`);
+    }
+  }
+
+  /**
+   * Delete a memory; resolves to false when it didn't exist
+   */
+  static async deleteMemory(agentId: string, memoryId: string): Promise<boolean> {
+    try {
+      return await invoke<boolean>('delete_agent_memory', { agentId, memoryId });
+    } catch (error) {
+      console.error('Failed to delete memory:', error);
+      throw new Error(`Failed to delete memory: ${error}`);
+    }
+  }
+
+  /**
+   * Pin a memory; resolves to false when it doesn't exist
+   */
+  static async pinMemory(agentId: string, memoryId: string): Promise<boolean> {
+    try {
+      return await invoke<boolean>('pin_memory', { agentId, memoryId });
+    } catch (error) {
+      console.error('Failed to pin memory:', error);
+      throw new Error(`Failed to pin memory: ${error}`);
+    }
+  }
+
+  /**
+   * Unpin a memory; resolves to false when it doesn't exist
+   */
+  static async unpinMemory(agentId: string, memoryId: string): Promise<boolean> {
+    try {
+      return await invoke<boolean>('unpin_memory', { agentId, memoryId });
+    } catch (error) {
+      console.error('Failed to unpin memory:', error);
+      throw new Error(`Failed to unpin memory: ${error}`);
+    }
+  }
+
   /**
    * Save shared knowledge
    */
@@ -107,6 +207,23 @@ export class MemoryClient {
     }
   }
 
+  /**
+   * Share a fact contributed by several agents at once
+   */
+  static async createSharedKnowledge(
+    title: string,
+    content: string,
+    sourceAgents: string[],
+    tags: string[] = []
+  ): Promise<string> {
+    try {
+      return await invoke<string>('create_shared_knowledge', { title, content, sourceAgents, tags });
+    } catch (error) {
+      console.error('Failed to create shared knowledge:', error);
+      throw new Error(`Failed to create shared knowledge: ${error}`);
+    }
+  }
+
   /**
    * Add a node to the knowledge graph
    */
@@ -160,6 +277,231 @@ export class MemoryClient {
       throw new Error(`Failed to backup memories: ${error}`);
     }
   }
+
+  /**
+   * Analyze temporal patterns in an agent's memories, optionally within a time range
+   */
+  static async analyzeMemoryPatterns(
+    agentId: string,
+    timeRange?: PatternTimeRange
+  ): Promise<MemoryPatternReport> {
+    try {
+      return await invoke<MemoryPatternReport>('analyze_memory_patterns', {
+        agentId,
+        timeRange: timeRange || null,
+      });
+    } catch (error) {
+      console.error('Failed to analyze memory patterns:', error);
+      throw new Error(`Failed to analyze memory patterns: ${error}`);
+    }
+  }
+
+  /**
+   * Get the last pattern report produced for an agent this session
+   */
+  static async getMemoryPatternReport(agentId: string): Promise<MemoryPatternReport | null> {
+    try {
+      return await invoke<MemoryPatternReport | null>('get_memory_pattern_report', { agentId });
+    } catch (error) {
+      console.error('Failed to get memory pattern report:', error);
+      throw new Error(`Failed to get memory pattern report: ${error}`);
+    }
+  }
+
+  /**
+   * Set how an agent's new memories are deduplicated against recent ones
+   */
+  static async setMemoryDedupConfig(agentId: string, config: MemoryDedupConfig): Promise<void> {
+    try {
+      await invoke('set_memory_dedup_config', { agentId, config });
+    } catch (error) {
+      console.error('Failed to set memory deduplication:', error);
+      throw new Error(`Failed to set memory deduplication: ${error}`);
+    }
+  }
+
+  /**
+   * Get an agent's memory deduplication settings
+   */
+  static async getMemoryDedupConfig(agentId: string): Promise<MemoryDedupConfig> {
+    try {
+      return await invoke<MemoryDedupConfig>('get_memory_dedup_config', { agentId });
+    } catch (error) {
+      console.error('Failed to get memory deduplication:', error);
+      throw new Error(`Failed to get memory deduplication: ${error}`);
+    }
+  }
+
+  /**
+   * How many of an agent's writes were saved and how many merged into duplicates
+   */
+  static async getDedupStats(agentId: string): Promise<DedupStats> {
+    try {
+      return await invoke<DedupStats>('get_dedup_stats', { agentId });
+    } catch (error) {
+      console.error('Failed to get deduplication stats:', error);
+      throw new Error(`Failed to get deduplication stats: ${error}`);
+    }
+  }
+
+  /**
+   * Encrypt an agent's memory database at rest, or every agent's when no id is given
+   */
+  static async migrateToEncryptedStorage(agentId?: string): Promise<EncryptionMigrationReport> {
+    try {
+      return await invoke<EncryptionMigrationReport>('migrate_to_encrypted_storage', {
+        agentId: agentId || null,
+      });
+    } catch (error) {
+      console.error('Failed to migrate to encrypted storage:', error);
+      throw new Error(`Failed to migrate to encrypted storage: ${error}`);
+    }
+  }
+
+  /**
+   * Set or clear (null) an agent's memory quota; returns how many memories it evicted
+   */
+  static async setMemoryQuota(agentId: string, quota: MemoryQuota | null): Promise<number> {
+    try {
+      return await invoke<number>('set_memory_quota', { agentId, quota });
+    } catch (error) {
+      console.error('Failed to set memory quota:', error);
+      throw new Error(`Failed to set memory quota: ${error}`);
+    }
+  }
+
+  /**
+   * Get an agent's memory quota with its current memory count and database size
+   */
+  static async getMemoryQuotaStatus(agentId: string): Promise<MemoryQuotaStatus> {
+    try {
+      return await invoke<MemoryQuotaStatus>('get_memory_quota_status', { agentId });
+    } catch (error) {
+      console.error('Failed to get memory quota status:', error);
+      throw new Error(`Failed to get memory quota status: ${error}`);
+    }
+  }
+
+  /**
+   * Summarize clusters of an agent's older, similar episodic memories into semantic memories
+   */
+  static async consolidateMemories(
+    agentId: string,
+    options?: ConsolidationOptions
+  ): Promise<ConsolidationReport> {
+    try {
+      return await invoke<ConsolidationReport>('consolidate_memories', {
+        agentId,
+        options: options || null,
+      });
+    } catch (error) {
+      console.error('Failed to consolidate memories:', error);
+      throw new Error(`Failed to consolidate memories: ${error}`);
+    }
+  }
+
+  /**
+   * Set how long an agent's episodic and semantic memories are kept
+   */
+  static async setMemoryRetentionPolicy(agentId: string, policy: MemoryRetentionPolicy): Promise<void> {
+    try {
+      await invoke('set_memory_retention_policy', { agentId, policy });
+    } catch (error) {
+      console.error('Failed to set memory retention policy:', error);
+      throw new Error(`Failed to set memory retention policy: ${error}`);
+    }
+  }
+
+  /**
+   * Get an agent's memory retention policy
+   */
+  static async getMemoryRetentionPolicy(agentId: string): Promise<MemoryRetentionPolicy> {
+    try {
+      return await invoke<MemoryRetentionPolicy>('get_memory_retention_policy', { agentId });
+    } catch (error) {
+      console.error('Failed to get memory retention policy:', error);
+      throw new Error(`Failed to get memory retention policy: ${error}`);
+    }
+  }
+
+  /**
+   * Delete an agent's memories that have outlived their tier's retention policy
+   */
+  static async applyMemoryRetention(agentId: string): Promise<RetentionReport> {
+    try {
+      return await invoke<RetentionReport>('apply_memory_retention', { agentId });
+    } catch (error) {
+      console.error('Failed to apply memory retention:', error);
+      throw new Error(`Failed to apply memory retention: ${error}`);
+    }
+  }
+
+  /**
+   * List stored memory anomalies for an agent, most recent first
+   */
+  static async listMemoryAnomalies(
+    agentId: string,
+    options: { since?: string; kind?: AnomalyKind; limit?: number } = {}
+  ): Promise<MemoryAnomaly[]> {
+    try {
+      return await invoke<MemoryAnomaly[]>('list_memory_anomalies', {
+        agentId,
+        since: options.since ?? null,
+        kind: options.kind ?? null,
+        limit: options.limit ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to list memory anomalies:', error);
+      throw new Error(`Failed to list memory anomalies: ${error}`);
+    }
+  }
+
+  /**
+   * Compare two embedding providers on a sample of an agent's tagged memories
+   */
+  static async evaluateEmbeddings(
+    agentId: string,
+    providerA: EmbeddingProviderConfig,
+    providerB: EmbeddingProviderConfig,
+    sampleSize?: number
+  ): Promise<EmbeddingEvaluationReport> {
+    try {
+      return await invoke<EmbeddingEvaluationReport>('evaluate_embeddings', {
+        agentId,
+        providerA,
+        providerB,
+        sampleSize: sampleSize ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to evaluate embeddings:', error);
+      throw new Error(`Failed to evaluate embeddings: ${error}`);
+    }
+  }
+
+  /**
+   * List stored embedding provider comparisons for an agent, most recent first
+   */
+  static async listEmbeddingEvaluations(
+    agentId: string,
+    limit?: number
+  ): Promise<EmbeddingEvaluationReport[]> {
+    try {
+      return await invoke<EmbeddingEvaluationReport[]>('list_embedding_evaluations', {
+        agentId,
+        limit: limit ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to list embedding evaluations:', error);
+      throw new Error(`Failed to list embedding evaluations: ${error}`);
+    }
+  }
+
+  /**
+   * Subscribe to anomalies as memories are saved
+   */
+  static async onMemoryAnomaly(handler: (anomaly: MemoryAnomaly) => void): Promise<UnlistenFn> {
+    return listen<MemoryAnomaly>(MEMORY_ANOMALY_EVENT, (event) => handler(event.payload));
+  }
 }
 
 /**
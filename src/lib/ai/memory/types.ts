@@ -42,6 +42,9 @@ export enum RelationshipType {
   LeadsTo = 'LeadsTo',
 }
 
+/** Episodic memories are recorded as things happen; semantic ones are consolidated from them */
+export type MemoryTier = 'Episodic' | 'Semantic';
+
 export interface AgentMemory {
   id: string;
   agent_id: string;
@@ -54,6 +57,9 @@ export interface AgentMemory {
   updated_at: string;
   access_count: number;
   tags: string[];
+  /** Exempt from decay, consolidation and eviction; ranked first within its type */
+  pinned?: boolean;
+  tier?: MemoryTier;
 }
 
 export interface SharedKnowledge {
@@ -102,10 +108,35 @@ export interface MemoryQuery {
   time_range?: [string, string];
 }
 
+/** Weight of each signal in hybrid search's reciprocal rank fusion; 0 leaves a signal out */
+export interface FusionWeights {
+  vector?: number;
+  text?: number;
+  tags?: number;
+  recency?: number;
+}
+
+export interface SignalScore {
+  rank: number;
+  /** Similarity, negated BM25, matching tag count or age in hours */
+  raw: number;
+  contribution: number;
+}
+
+export interface FusionScores {
+  vector?: SignalScore | null;
+  text?: SignalScore | null;
+  tags?: SignalScore | null;
+  recency?: SignalScore | null;
+  fused: number;
+}
+
 export interface MemorySearchResult {
   memory: AgentMemory;
   similarity_score?: number;
   relevance_rank: number;
+  /** Per-signal contributions from hybrid search */
+  fusion_scores?: FusionScores;
 }
 
 export interface MemoryStats {
@@ -118,6 +149,170 @@ export interface MemoryStats {
   knowledge_graph_size: number;
 }
 
+// Memory pattern analysis
+export interface PatternTimeRange {
+  start: string;
+  end: string;
+}
+
+export type PatternKind =
+  | 'error_success_loop'
+  | 'repeated_errors'
+  | 'learning_after_error'
+  | 'task_burst'
+  | 'long_gap';
+
+export interface PatternLabel {
+  kind: PatternKind;
+  label: string;
+  occurrences: number;
+}
+
+export interface MemoryPatternAnalysis {
+  overallPattern: number[];
+  typePatterns: Partial<Record<MemoryType, number[]>>;
+  sequenceLength: number;
+  /** Seconds between the first and last memory */
+  timeSpan: number;
+}
+
+export interface MemoryPatternReport {
+  agentId: string;
+  analysis: MemoryPatternAnalysis;
+  /** Most frequent first */
+  labels: PatternLabel[];
+  memoryCount: number;
+  timeRange: PatternTimeRange | null;
+  generatedAt: string;
+}
+
+// Memory deduplication
+export interface MemoryDedupConfig {
+  enabled: boolean;
+  /** Embeddings at least this similar are duplicates (0.0 - 1.0) */
+  similarity_threshold: number;
+  /** Recent memories of the same type compared against */
+  window: number;
+}
+
+export interface DedupStats {
+  agentId: string;
+  saved: number;
+  mergedByHash: number;
+  mergedBySimilarity: number;
+  /** Share of writes that were merged instead of saved */
+  duplicateRate: number;
+}
+
+// Memory encryption at rest
+export interface EncryptionMigrationReport {
+  encrypted: string[];
+  alreadyEncrypted: string[];
+  /** Agent id to the reason its database was left as it was */
+  failed: Record<string, string>;
+}
+
+// Memory quotas
+/** Which unpinned memories go first when an agent is over quota */
+export type EvictionStrategy = 'LeastRecentlyUsed' | 'LowestRelevance' | 'OldestUnpinned';
+
+/** Per-agent limits; null leaves that dimension unlimited */
+export interface MemoryQuota {
+  max_memories: number | null;
+  max_db_bytes: number | null;
+  eviction?: EvictionStrategy;
+}
+
+export interface MemoryQuotaStatus {
+  agentId: string;
+  quota: MemoryQuota | null;
+  memoryCount: number;
+  pinnedCount: number;
+  /** Bytes in use by the agent database, excluding free pages */
+  dbBytes: number;
+  /** Share of max_memories in use */
+  memoryUsage: number | null;
+  /** Share of max_db_bytes in use */
+  sizeUsage: number | null;
+}
+
+// Memory tiers
+export interface ConsolidationOptions {
+  /** Only consolidate memories of this type */
+  memory_type?: MemoryType;
+  /** Embedding similarity to a cluster's first memory needed to join it (0.0 - 1.0) */
+  similarity_threshold?: number;
+  /** Smallest cluster worth summarizing */
+  min_cluster_size?: number;
+  /** Episodes younger than this are left alone */
+  older_than_hours?: number;
+}
+
+export interface ConsolidationReport {
+  agentId: string;
+  episodesConsidered: number;
+  episodesConsolidated: number;
+  semanticMemoryIds: string[];
+}
+
+/** Per-agent retention, in days since creation; pinned memories are always kept */
+export interface MemoryRetentionPolicy {
+  episodic_max_age_days: number | null;
+  /** Keep old episodic memories until they have been consolidated */
+  keep_unconsolidated: boolean;
+  semantic_max_age_days: number | null;
+}
+
+export interface RetentionReport {
+  agentId: string;
+  episodicDeleted: number;
+  semanticDeleted: number;
+}
+
+// Memory anomaly detection
+export type AnomalyKind = 'error_spike' | 'content_drift';
+
+export interface MemoryAnomaly {
+  id: string;
+  agentId: string;
+  memoryId: string;
+  kind: AnomalyKind;
+  /** Z-score of the reconstruction error for drift, multiple of the usual error share for spikes */
+  score: number;
+  detail: string;
+  detectedAt: string;
+}
+
+/** Where an agent's memory embeddings come from */
+export type EmbeddingProviderConfig =
+  | { kind: 'neural' }
+  | { kind: 'openai'; model?: string; dimensions?: number | null }
+  | { kind: 'cohere'; model?: string }
+  | { kind: 'local_gguf'; model_path: string };
+
+export interface ProviderEvaluation {
+  provider: EmbeddingProviderConfig;
+  providerId: string;
+  /** Mean share of a memory's tag-sharing memories found in its top k, out of at most k */
+  recallAtK: number;
+  meanReciprocalRank: number;
+  dimensions: number;
+  durationMs: number;
+}
+
+export interface EmbeddingEvaluationReport {
+  id: string;
+  agentId: string;
+  k: number;
+  sampleSize: number;
+  queryCount: number;
+  providerA: ProviderEvaluation;
+  providerB: ProviderEvaluation;
+  /** Provider id with the better recall, null when they are too close to call */
+  recommended: string | null;
+  createdAt: string;
+}
+
 // API Response types
 export interface ApiResponse<T> {
   success: boolean;
@@ -143,6 +338,11 @@ export interface SearchMemoriesRequest {
   limit?: number;
   offset?: number;
   similarity_threshold?: number;
+  fusion_weights?: FusionWeights;
+  /** Only search these tiers; both when omitted */
+  tiers?: MemoryTier[];
+  /** Secure session the search runs in; a session bound to another agent only sees granted memories */
+  session_id?: string;
 }
 
 // Knowledge creation request
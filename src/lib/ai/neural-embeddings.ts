@@ -25,10 +25,51 @@ export interface NeuralEmbeddingConfig {
 export interface NeuralEmbeddingStats {
   cacheSize: number;
   cacheLimit: number;
+  cacheHits: number;
+  cacheMisses: number;
+  /** Entries dropped to make room; invalidations after training or deletes are not counted */
+  cacheEvictions: number;
+  cacheHitRate: number;
   embeddingDimension: number;
   specializedNetworks: number;
 }
 
+export interface TrainingScheduleConfig {
+  enabled: boolean;
+  intervalMinutes?: number;
+  /** Most memories trained on per run, newest first */
+  batchSize?: number;
+  /** Epochs per network per run */
+  epochs?: number;
+  /** How far back the first run looks */
+  initialLookbackHours?: number;
+}
+
+export interface TrainingStatus {
+  config: Required<TrainingScheduleConfig>;
+  training: boolean;
+  completedRuns: number;
+  lastRunAt?: string;
+  /** Memories created before this have been trained on */
+  trainedThrough?: string;
+  lastMemoryCount: number;
+  lastDurationMs: number;
+  nextRunAt?: string;
+  lastError?: string;
+}
+
+export type ComputeBackend = 'cpu' | 'metal' | 'cuda';
+
+export interface AcceleratorStatus {
+  /** GPU backends this build was compiled with */
+  compiledBackends: ComputeBackend[];
+  /** GPU found at runtime, if any */
+  detectedBackend?: ComputeBackend;
+  /** Where large batches run now */
+  activeBackend: ComputeBackend;
+  forceCpu: boolean;
+}
+
 /**
  * Neural embedding service that uses the Rust backend's neural networks
  * for generating meaningful embeddings for different memory types
@@ -211,6 +252,64 @@ export class NeuralEmbeddingService {
     }
   }
 
+  /**
+   * Enable, disable or change background training on recent memories
+   */
+  async configureTraining(config: TrainingScheduleConfig): Promise<TrainingStatus> {
+    try {
+      return await invoke<TrainingStatus>('configure_embedding_training', { config });
+    } catch (error) {
+      throw new Error(`Failed to configure neural training: ${error}`);
+    }
+  }
+
+  /**
+   * Get the background training schedule and its latest run
+   */
+  async getTrainingStatus(): Promise<TrainingStatus> {
+    try {
+      return await invoke<TrainingStatus>('get_training_status');
+    } catch (error) {
+      throw new Error(`Failed to get neural training status: ${error}`);
+    }
+  }
+
+  /**
+   * Get the GPU backends available for neural inference and which one is in use
+   */
+  async getAccelerator(): Promise<AcceleratorStatus> {
+    try {
+      return await invoke<AcceleratorStatus>('get_inference_accelerator');
+    } catch (error) {
+      throw new Error(`Failed to get inference accelerator: ${error}`);
+    }
+  }
+
+  /**
+   * Keep neural inference on the CPU, or let it use a detected GPU again
+   */
+  async setForceCpu(forceCpu: boolean): Promise<AcceleratorStatus> {
+    try {
+      return await invoke<AcceleratorStatus>('set_force_cpu_inference', { forceCpu });
+    } catch (error) {
+      throw new Error(`Failed to change inference accelerator: ${error}`);
+    }
+  }
+
+  /**
+   * Discard trained weights and start every network from fresh ones.
+   * Stored embeddings should be regenerated afterwards.
+   */
+  async resetModels(): Promise<void> {
+    await this.ensureInitialized();
+
+    try {
+      await invoke('reset_neural_models');
+    } catch (error) {
+      throw new Error(`Failed to reset neural models: ${error}`);
+    }
+  }
+
   /**
    * Get embedding service statistics
    */
@@ -226,13 +325,14 @@ export class NeuralEmbeddingService {
   }
 
   /**
-   * Clear the embedding cache
+   * Clear the embedding cache and its hit/miss statistics; resolves to how many
+   * embeddings were dropped
    */
-  async clearCache(): Promise<void> {
+  async clearCache(): Promise<number> {
     await this.ensureInitialized();
 
     try {
-      await invoke('clear_neural_embedding_cache');
+      return await invoke<number>('clear_embedding_cache');
     } catch (error) {
       throw new Error(`Failed to clear neural embedding cache: ${error}`);
     }
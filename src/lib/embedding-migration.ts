@@ -1,4 +1,14 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+/** Emitted by the backend after each migrated batch and phase change */
+export const MIGRATION_PROGRESS_EVENT = 'embedding_migration_progress';
+
+/**
+ * How stored embeddings reach the target dimension: re-embedding each item's text,
+ * mapping the old vectors through a learned linear projection, or truncating them with PCA
+ */
+export type DimensionStrategy = 'reEmbed' | 'linearProjection' | 'pcaTruncation';
 
 export interface EmbeddingMigrationConfig {
   sourceModel: string;
@@ -11,6 +21,10 @@ export interface EmbeddingMigrationConfig {
   similarityThreshold: number;
   maxRetries: number;
   useNeuralEmbeddings: boolean;
+  dimensionStrategy?: DimensionStrategy;
+  /** Stored embeddings used to fit a projection */
+  projectionSamples?: number;
+  projectionEpochs?: number;
 }
 
 export interface MigrationStatus {
@@ -24,6 +38,10 @@ export interface MigrationStatus {
   estimatedCompletion?: string;
   statusMessage: string;
   errors: string[];
+  running: boolean;
+  /** Mean `1 - cosine` between old and migrated embeddings, when measurable */
+  averageCosineDrift?: number;
+  driftSamples: number;
 }
 
 export interface MigrationValidationResult {
@@ -31,6 +49,7 @@ export interface MigrationValidationResult {
   validEmbeddings: number;
   invalidEmbeddings: number;
   errors: string[];
+  averageCosineDrift?: number;
 }
 
 export interface MigrationStats {
@@ -40,6 +59,15 @@ export interface MigrationStats {
   tableBreakdown: Record<string, number>;
 }
 
+export interface QuantizationReport {
+  rowsQuantized: number;
+  alreadyQuantized: number;
+  /** Blobs that are neither format; left untouched */
+  unreadable: number;
+  bytesBefore: number;
+  bytesAfter: number;
+}
+
 /**
  * Embedding Migration Service
  * Provides a high-level interface for migrating embeddings between different models
@@ -47,7 +75,7 @@ export interface MigrationStats {
 export class EmbeddingMigrationService {
   private static instance: EmbeddingMigrationService;
   private migrationStatus: MigrationStatus | null = null;
-  private statusPollingInterval: NodeJS.Timeout | null = null;
+  private unlistenProgress: UnlistenFn | null = null;
 
   private constructor() {}
 
@@ -59,9 +87,10 @@ export class EmbeddingMigrationService {
   }
 
   /**
-   * Start embedding migration with custom configuration
+   * Start embedding migration with custom configuration. Migrates the shared knowledge
+   * database, or the agent's memory database when `agentId` is given.
    */
-  async startMigration(config: Partial<EmbeddingMigrationConfig> = {}): Promise<string> {
+  async startMigration(config: Partial<EmbeddingMigrationConfig> = {}, agentId?: string): Promise<string> {
     const defaultConfig: EmbeddingMigrationConfig = {
       sourceModel: 'text-embedding-ada-002',
       targetModel: 'text-embedding-3-small',
@@ -77,13 +106,14 @@ export class EmbeddingMigrationService {
     };
 
     try {
+      // Subscribe before starting so the first progress event isn't missed
+      await this.listenForProgress();
+
       const result = await invoke<string>('start_embedding_migration', {
         config: defaultConfig,
+        agentId,
       });
 
-      // Start polling for status updates
-      this.startStatusPolling();
-
       return result;
     } catch (error) {
       throw new Error(`Failed to start migration: ${error}`);
@@ -120,7 +150,7 @@ export class EmbeddingMigrationService {
   async rollbackMigration(): Promise<string> {
     try {
       const result = await invoke<string>('rollback_migration');
-      this.stopStatusPolling();
+      await this.getMigrationStatus();
       return result;
     } catch (error) {
       throw new Error(`Failed to rollback migration: ${error}`);
@@ -139,37 +169,35 @@ export class EmbeddingMigrationService {
   }
 
   /**
-   * Start polling for status updates
+   * Rewrite stored f32 embeddings as int8 for an agent, or the shared database when no agent is given
    */
-  private startStatusPolling(): void {
-    if (this.statusPollingInterval) {
-      clearInterval(this.statusPollingInterval);
+  async quantizeStoredEmbeddings(agentId?: string): Promise<QuantizationReport> {
+    try {
+      return await invoke<QuantizationReport>('quantize_stored_embeddings', { agentId });
+    } catch (error) {
+      throw new Error(`Failed to quantize embeddings: ${error}`);
     }
-
-    this.statusPollingInterval = setInterval(async () => {
-      try {
-        const status = await this.getMigrationStatus();
-        
-        // Emit status update event
-        this.emitStatusUpdate(status);
-
-        // Stop polling if migration is complete
-        if (status.processedItems >= status.totalItems && status.totalItems > 0) {
-          this.stopStatusPolling();
-        }
-      } catch (error) {
-        console.error('Failed to poll migration status:', error);
-      }
-    }, 2000); // Poll every 2 seconds
   }
 
   /**
-   * Stop status polling
+   * Follow backend progress events until the migration finishes
    */
-  private stopStatusPolling(): void {
-    if (this.statusPollingInterval) {
-      clearInterval(this.statusPollingInterval);
-      this.statusPollingInterval = null;
+  private async listenForProgress(): Promise<void> {
+    this.stopListening();
+    this.unlistenProgress = await listen<MigrationStatus>(MIGRATION_PROGRESS_EVENT, (event) => {
+      this.migrationStatus = event.payload;
+      this.emitStatusUpdate(event.payload);
+
+      if (!event.payload.running) {
+        this.stopListening();
+      }
+    });
+  }
+
+  private stopListening(): void {
+    if (this.unlistenProgress) {
+      this.unlistenProgress();
+      this.unlistenProgress = null;
     }
   }
 
@@ -195,7 +223,7 @@ export class EmbeddingMigrationService {
    * Check if migration is in progress
    */
   isMigrationInProgress(): boolean {
-    return this.statusPollingInterval !== null;
+    return this.migrationStatus?.running ?? false;
   }
 
   /**
@@ -248,8 +276,8 @@ export class EmbeddingMigrationService {
 export function useEmbeddingMigration() {
   const service = EmbeddingMigrationService.getInstance();
 
-  const startMigration = async (config?: Partial<EmbeddingMigrationConfig>) => {
-    return await service.startMigration(config);
+  const startMigration = async (config?: Partial<EmbeddingMigrationConfig>, agentId?: string) => {
+    return await service.startMigration(config, agentId);
   };
 
   const getStatus = async () => {
@@ -413,6 +441,10 @@ export class MigrationUtils {
       errors.push('Max retries must be between 1 and 10');
     }
 
+    if (config.dimensionStrategy === 'pcaTruncation' && config.targetDimensions >= config.sourceDimensions) {
+      errors.push('PCA truncation needs fewer target dimensions than source dimensions');
+    }
+
     return errors;
   }
 
@@ -1,3 +1,4 @@
+import { invoke } from '@tauri-apps/api/core';
 import { HTTPTransport } from './transport/http';
 import { LocalTransport } from './transport/local';
 import { StdioTransport } from './transport/stdio';
@@ -14,6 +15,28 @@ import type {
   ToolCallResult,
 } from './types';
 
+/** Audit a tool call in the backend; the backend redacts sensitive arguments before storing */
+function recordToolCall(
+  serverId: string,
+  toolName: string,
+  args: unknown,
+  startedAt: number,
+  outcome: { resultSize?: number; error?: string }
+): void {
+  invoke('record_mcp_tool_call', {
+    call: {
+      server_id: serverId,
+      tool_name: toolName,
+      run_id: null,
+      arguments: args ?? null,
+      duration_ms: Math.round(performance.now() - startedAt),
+      result_size: outcome.resultSize ?? null,
+      success: !outcome.error,
+      error: outcome.error ?? null,
+    },
+  }).catch((error) => console.warn('Failed to record MCP tool call:', error));
+}
+
 export class MCPClient {
   private servers = new Map<string, MCPServerConnection>();
 
@@ -96,7 +119,23 @@ export class MCPClient {
       throw new Error(`Server ${serverId} not connected`);
     }
 
-    return connection.callTool(toolName, args);
+    // The backend audits stdio calls itself as they are sent
+    if (connection.getServer().type === 'stdio') {
+      return connection.callTool(toolName, args);
+    }
+
+    const startedAt = performance.now();
+    try {
+      const result = await connection.callTool(toolName, args);
+      recordToolCall(serverId, toolName, args, startedAt, {
+        resultSize: JSON.stringify(result).length,
+        error: result.isError ? 'Tool reported an error' : undefined,
+      });
+      return result;
+    } catch (error) {
+      recordToolCall(serverId, toolName, args, startedAt, { error: String(error) });
+      throw error;
+    }
   }
 
   async listPrompts(serverId: string): Promise<MCPPrompt[]> {
@@ -138,7 +177,7 @@ export class MCPClient {
         return new HTTPTransport(server.config.url, server.config);
 
       case 'stdio':
-        return new StdioTransport(server.config);
+        return new StdioTransport(server.config, server.id);
 
       case 'local':
         return new LocalTransport(server.config);
@@ -187,6 +226,8 @@ class MCPServerConnection {
         prompts: {},
         // New in 2025-06-18 specification
         elicitation: { supported: true },
+        // The stdio bridge answers roots/list from the workspace path policy
+        ...(this.server.type === 'stdio' ? { roots: { listChanged: true } } : {}),
       },
       clientInfo: {
         name: 'Banshee',
@@ -1,15 +1,19 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import type { MCPMessage, MCPServerConfig, MCPTransport } from '../types';
 
 export class StdioTransport implements MCPTransport {
   private config: MCPServerConfig;
+  private serverId?: string;
   private processId?: number;
+  private unlisteners: UnlistenFn[] = [];
   private messageCallbacks: ((message: MCPMessage) => void)[] = [];
   private closeCallbacks: (() => void)[] = [];
   private errorCallbacks: ((error: Error) => void)[] = [];
 
-  constructor(config: MCPServerConfig) {
+  constructor(config: MCPServerConfig, serverId?: string) {
     this.config = config;
+    this.serverId = serverId;
   }
 
   async connect(): Promise<void> {
@@ -23,14 +27,26 @@ export class StdioTransport implements MCPTransport {
         command: this.config.command,
         args: this.config.args || [],
         env: this.config.env || {},
+        serverId: this.serverId,
       });
 
-      this.processId = result.pid;
+      await this.attach(result.pid);
 
-      // Set up message listeners via Tauri events
-      const { listen } = await import('@tauri-apps/api/event');
+      console.log(`MCP stdio transport connected with PID: ${this.processId}`);
+    } catch (error) {
+      throw new Error(`Failed to start MCP process: ${error}`);
+    }
+  }
 
-      await listen<string>(`mcp_message_${this.processId}`, (event) => {
+  /** Route the events of process `pid` to this transport, replacing those of any earlier process */
+  private async attach(pid: number): Promise<void> {
+    for (const unlisten of this.unlisteners.splice(0)) {
+      unlisten();
+    }
+    this.processId = pid;
+
+    this.unlisteners.push(
+      await listen<string>(`mcp_message_${pid}`, (event) => {
         try {
           const message: MCPMessage = JSON.parse(event.payload);
           for (const callback of this.messageCallbacks) {
@@ -41,27 +57,32 @@ export class StdioTransport implements MCPTransport {
             callback(new Error(`Failed to parse message: ${error}`));
           }
         }
-      });
-
-      await listen<string>(`mcp_error_${this.processId}`, (event) => {
+      }),
+      await listen<string>(`mcp_error_${pid}`, (event) => {
         for (const callback of this.errorCallbacks) {
           callback(new Error(event.payload));
         }
-      });
-
-      await listen<void>(`mcp_close_${this.processId}`, () => {
+      }),
+      await listen<void>(`mcp_close_${pid}`, () => {
+        if (pid !== this.processId) {
+          return;
+        }
         for (const callback of this.closeCallbacks) {
           callback();
         }
-      });
-
-      console.log(`MCP stdio transport connected with PID: ${this.processId}`);
-    } catch (error) {
-      throw new Error(`Failed to start MCP process: ${error}`);
-    }
+      }),
+      // The resource monitor restarted the server; the backend has already repeated the handshake
+      await listen<number>(`mcp_restarted_${pid}`, (event) => {
+        console.log(`MCP process ${pid} restarted as ${event.payload}`);
+        void this.attach(event.payload);
+      })
+    );
   }
 
   async disconnect(): Promise<void> {
+    for (const unlisten of this.unlisteners.splice(0)) {
+      unlisten();
+    }
     if (this.processId) {
       try {
         await invoke('stop_mcp_process', { pid: this.processId });
@@ -80,13 +101,23 @@ export class StdioTransport implements MCPTransport {
       throw new Error('Transport not connected');
     }
 
+    let response: MCPMessage | null;
     try {
-      await invoke('send_mcp_message', {
+      // Requests resolve with their correlated response; other messages with null
+      response = await invoke<MCPMessage | null>('send_mcp_message', {
         pid: this.processId,
         message: JSON.stringify(message),
+        timeoutMs: this.config.timeout,
       });
     } catch (error) {
-      throw new Error(`Failed to send message: ${error}`);
+      const detail = typeof error === 'object' && error && 'kind' in error ? JSON.stringify(error) : error;
+      throw new Error(`Failed to send message: ${detail}`);
+    }
+
+    if (response) {
+      for (const callback of this.messageCallbacks) {
+        callback(response);
+      }
     }
   }
 
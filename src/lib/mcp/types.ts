@@ -10,6 +10,17 @@ export interface MCPServer {
   error?: string;
 }
 
+// Result of test_mcp_connection_command
+export interface MCPConnectionDiagnostics {
+  server_id: string;
+  reachable: boolean;
+  handshake_ms: number | null;
+  tool_count: number | null;
+  server_version: string | null;
+  total_ms: number;
+  last_error: string | null;
+}
+
 export interface MCPServerConfig {
   // HTTP transport
   url?: string;
@@ -62,6 +62,34 @@ export interface PathResult {
   weight: number;
 }
 
+export type NeuralRelationshipType =
+  | 'SemanticSimilarity'
+  | 'TemporalSequence'
+  | 'CausalRelation'
+  | 'AgentCollaboration'
+  | 'ContextSharing'
+  | 'PatternSimilarity'
+  | 'ToolUsage'
+  | 'ErrorSolution';
+
+export interface RelationshipPrediction {
+  from_node: string;
+  to_node: string;
+  to_node_name: string;
+  relationship_type: NeuralRelationshipType;
+  /** Attention network score squashed into (0, 1) */
+  strength: number;
+  /** Embedding similarity of the two nodes */
+  similarity: number;
+}
+
+export interface RelationshipFeedbackStats {
+  confirmed: number;
+  rejected: number;
+  /** Share of relationship classification decided by the learned scores rather than the rules */
+  learned_weight: number;
+}
+
 export interface GraphStats {
   node_count: number;
   edge_count: number;
@@ -16,6 +16,8 @@ import type {
   GraphStats,
   GraphView,
   PathResult,
+  RelationshipFeedbackStats,
+  RelationshipPrediction,
   UpdateEdgeRequest,
   UpdateNodeRequest,
 } from './graph-api-types';
@@ -294,6 +296,73 @@ export class TauriGraphService implements GraphApiService {
     }
   }
 
+  /**
+   * Relationships the graph network predicts from one of the agent's memory nodes
+   */
+  async predictRelationships(
+    agentId: string,
+    nodeId: string,
+    topK?: number
+  ): Promise<RelationshipPrediction[]> {
+    try {
+      if (!GraphApiValidator.validateAgentId(agentId)) {
+        throw new GraphApiError('Invalid agent ID format', GRAPH_ERROR_CODES.VALIDATION_ERROR);
+      }
+
+      return await invoke<RelationshipPrediction[]>('predict_graph_relationships', {
+        agentId,
+        nodeId,
+        topK: topK ?? null,
+      });
+    } catch (error) {
+      throw this.handleError(error, 'Failed to predict relationships');
+    }
+  }
+
+  /**
+   * Accept a predicted relationship, creating the edge with its provenance recorded
+   */
+  async acceptPredictedRelationship(
+    agentId: string,
+    prediction: Pick<RelationshipPrediction, 'from_node' | 'to_node'>
+  ): Promise<string> {
+    try {
+      if (!GraphApiValidator.validateAgentId(agentId)) {
+        throw new GraphApiError('Invalid agent ID format', GRAPH_ERROR_CODES.VALIDATION_ERROR);
+      }
+
+      return await invoke<string>('accept_predicted_relationship', {
+        agentId,
+        fromNode: prediction.from_node,
+        toNode: prediction.to_node,
+      });
+    } catch (error) {
+      throw this.handleError(error, 'Failed to accept predicted relationship');
+    }
+  }
+
+  /**
+   * Confirm a neural graph edge's relationship type, training the relationship scoring on it
+   */
+  async confirmRelationship(edgeId: string): Promise<RelationshipFeedbackStats> {
+    try {
+      return await invoke<RelationshipFeedbackStats>('confirm_relationship', { edgeId });
+    } catch (error) {
+      throw this.handleError(error, 'Failed to confirm relationship');
+    }
+  }
+
+  /**
+   * Reject a neural graph edge, removing it and training the relationship scoring against it
+   */
+  async rejectRelationship(edgeId: string): Promise<RelationshipFeedbackStats> {
+    try {
+      return await invoke<RelationshipFeedbackStats>('reject_relationship', { edgeId });
+    } catch (error) {
+      throw this.handleError(error, 'Failed to reject relationship');
+    }
+  }
+
   // Private validation methods
   private validateCreateNodeRequest(request: CreateNodeRequest): void {
     if (!GraphApiValidator.validateAgentId(request.agent_id)) {